@@ -0,0 +1,96 @@
+// Debug mode for the macro expansions, driven by the `R55_MACRO_DEBUG` env var:
+// * `R55_MACRO_DEBUG=1` pretty-prints the generated code to stderr
+// * `R55_MACRO_DEBUG=file` writes it to `target/r55-expand/<contract>.rs` instead, which is
+//   handy to diff the expansions between versions
+//
+// The output is segmented by section (e.g. dispatcher, interface, deployable glue), and the dumped
+// file is valid Rust on its own.
+
+use proc_macro2::TokenStream;
+use std::{env, fs, path::PathBuf};
+
+const DEBUG_ENV: &str = "R55_MACRO_DEBUG";
+
+// Dumps the expansion of `name`, if the debug mode is enabled
+pub fn dump(name: &str, sections: &[(&str, &TokenStream)]) {
+    let Ok(mode) = env::var(DEBUG_ENV) else {
+        return;
+    };
+
+    match mode.as_str() {
+        "1" | "stderr" => eprintln!("// ===== r55 expansion: {} =====\n{}", name, render(sections)),
+        "file" => {
+            let dir = expand_dir();
+            let path = dir.join(format!("{}.rs", name));
+            let result = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, render(sections)));
+            match result {
+                Ok(()) => eprintln!("r55: expansion of `{}` written to {}", name, path.display()),
+                Err(err) => eprintln!("r55: unable to write {}: {}", path.display(), err),
+            }
+        }
+        _ => {}
+    }
+}
+
+// Pretty-prints each section under a header comment. Sections that don't parse as a file are
+// printed as raw tokens, so that the dump is still useful when debugging broken expansions.
+pub fn render(sections: &[(&str, &TokenStream)]) -> String {
+    let mut output = String::new();
+    for (name, tokens) in sections {
+        output.push_str(&format!("// ===== {} =====\n\n", name));
+        match syn::parse2::<syn::File>((*tokens).clone()) {
+            Ok(file) => output.push_str(&prettyplease::unparse(&file)),
+            Err(_) => output.push_str(&format!("{}\n", tokens)),
+        }
+        output.push('\n');
+    }
+
+    output
+}
+
+// `r55-expand` dir under the target dir of the crate being compiled
+fn expand_dir() -> PathBuf {
+    let target_dir = env::var("CARGO_TARGET_DIR").map(PathBuf::from).unwrap_or_else(|_| {
+        let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".into());
+        PathBuf::from(manifest_dir).join("target")
+    });
+
+    target_dir.join("r55-expand")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::{Item, ItemImpl};
+
+    // The #[contract] impl of the erc20 example, without the attribute
+    fn erc20_contract() -> ItemImpl {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../examples/erc20/src/lib.rs");
+        let source = fs::read_to_string(path).expect("Unable to read the erc20 example");
+        let file = syn::parse_file(&source).expect("Unable to parse the erc20 example");
+
+        file.items
+            .into_iter()
+            .find_map(|item| match item {
+                Item::Impl(mut item) if item.attrs.iter().any(|a| a.path.is_ident("contract")) => {
+                    item.attrs.retain(|a| !a.path.is_ident("contract"));
+                    Some(item)
+                }
+                _ => None,
+            })
+            .expect("No #[contract] impl in the erc20 example")
+    }
+
+    #[test]
+    fn test_render_erc20_expansion() {
+        let expansion = crate::expand_contract(TokenStream::new(), &erc20_contract()).unwrap();
+        let rendered = render(&[("expansion", &expansion)]);
+
+        // The dump is valid Rust on its own, and the dispatcher is readable
+        syn::parse_file(&rendered).expect("The rendered expansion doesn't parse");
+        assert!(rendered.starts_with("// ===== expansion =====\n"));
+        assert!(rendered.contains("fn call_with_data(&mut self, calldata: &[u8]) {\n"));
+        assert!(rendered.contains("pub mod interface {\n"));
+        assert!(rendered.contains("pub mod deploy {\n"));
+    }
+}
@@ -1,10 +1,11 @@
-use alloy_core::primitives::keccak256;
+use alloy_core::primitives::{keccak256, U256};
 use alloy_dyn_abi::DynSolType;
-use proc_macro2::TokenStream;
-use quote::{format_ident, quote};
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
     parse::{Parse, ParseStream},
-    FnArg, Ident, ImplItemMethod, LitStr, PathArguments, ReturnType, TraitItemMethod, Type,
+    punctuated::Punctuated,
+    FnArg, Ident, ImplItemMethod, LitStr, PathArguments, ReturnType, Token, TraitItemMethod, Type,
 };
 
 // Unified method info from `ImplItemMethod` and `TraitItemMethod`
@@ -13,6 +14,10 @@ pub struct MethodInfo<'a> {
     name: &'a Ident,
     args: Vec<syn::FnArg>,
     return_type: &'a ReturnType,
+    // `None` when unknown, as interface traits don't need to tag their payable methods
+    payable: Option<bool>,
+    // Span of the method name, which errors about the method as a whole point at
+    span: Span,
 }
 
 impl<'a> From<&'a ImplItemMethod> for MethodInfo<'a> {
@@ -21,6 +26,8 @@ impl<'a> From<&'a ImplItemMethod> for MethodInfo<'a> {
             name: &method.sig.ident,
             args: method.sig.inputs.iter().cloned().collect(),
             return_type: &method.sig.output,
+            payable: Some(has_payable_attr(&method.attrs)),
+            span: method.sig.ident.span(),
         }
     }
 }
@@ -31,47 +38,410 @@ impl<'a> From<&'a TraitItemMethod> for MethodInfo<'a> {
             name: &method.sig.ident,
             args: method.sig.inputs.iter().cloned().collect(),
             return_type: &method.sig.output,
+            payable: has_payable_attr(&method.attrs).then_some(true),
+            span: method.sig.ident.span(),
         }
     }
 }
 
+// Check if a method is tagged with the payable attribute
+pub fn has_payable_attr(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        if let Ok(syn::Meta::Path(path)) = attr.parse_meta() {
+            // Also matches the qualified `#[contract_derive::payable]`
+            if let Some(segment) = path.segments.last() {
+                return segment.ident == "payable";
+            }
+        }
+        false
+    })
+}
+
+// Rejects `#[payable]` attributes with arguments, which would otherwise be silently ignored
+pub fn check_payable_attr(attrs: &[syn::Attribute]) -> syn::Result<()> {
+    for attr in attrs {
+        let is_payable = attr
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "payable");
+        if is_payable && !attr.tokens.is_empty() {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "`#[payable]` doesn't take arguments",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Check if a method argument is tagged with `#[calldata]`, i.e. read on demand from the calldata
+pub fn has_calldata_attr(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path.is_ident("calldata"))
+}
+
+fn is_calldata_reader(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path
+        .path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == "CalldataReader"))
+}
+
+// Whether each argument of a method (other than `self`) is tagged with `#[calldata]`, which must
+// be the case of its `CalldataReader` arguments, and only them
+pub fn get_calldata_args(method: &ImplItemMethod) -> syn::Result<Vec<bool>> {
+    method
+        .sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat_type) => Some(pat_type),
+            FnArg::Receiver(_) => None,
+        })
+        .map(|pat_type| {
+            let is_tagged = has_calldata_attr(&pat_type.attrs);
+            match (is_tagged, is_calldata_reader(&pat_type.ty)) {
+                (true, false) => Err(syn::Error::new_spanned(
+                    &pat_type.ty,
+                    "`#[calldata]` arguments must be `CalldataReader`s",
+                )),
+                (false, true) => Err(syn::Error::new_spanned(
+                    pat_type,
+                    "`CalldataReader` arguments must be tagged with `#[calldata]`",
+                )),
+                _ => Ok(is_tagged),
+            }
+        })
+        .collect()
+}
+
+// Removes the `#[calldata]` tags from the arguments of a method, as they aren't actual attributes
+pub fn strip_calldata_attrs(method: &ImplItemMethod) -> ImplItemMethod {
+    let mut method = method.clone();
+    for arg in method.sig.inputs.iter_mut() {
+        if let FnArg::Typed(pat_type) = arg {
+            pat_type.attrs.retain(|attr| !attr.path.is_ident("calldata"));
+        }
+    }
+    method
+}
+
+// Default value of each constructor argument, from its `#[default(value)]` tag. Deployments can
+// leave out trailing args with a default, so they can't be followed by args without one
+pub fn get_arg_defaults(method: &ImplItemMethod) -> syn::Result<Vec<Option<syn::Expr>>> {
+    let mut defaults = Vec::new();
+    for arg in method.sig.inputs.iter() {
+        let FnArg::Typed(pat_type) = arg else {
+            continue;
+        };
+        let default = match pat_type.attrs.iter().find(|attr| attr.path.is_ident("default")) {
+            Some(attr) => Some(attr.parse_args::<syn::Expr>().map_err(|_| {
+                syn::Error::new_spanned(
+                    attr,
+                    "expected the default value of the argument, e.g. `#[default(U256::from(18))]`",
+                )
+            })?),
+            None if defaults.iter().any(Option::is_some) => {
+                return Err(syn::Error::new_spanned(
+                    pat_type,
+                    "arguments without a `#[default]` can't follow the ones with a default",
+                ))
+            }
+            None => None,
+        };
+        defaults.push(default);
+    }
+
+    Ok(defaults)
+}
+
+// Removes the `#[default]` tags from the arguments of the constructor
+pub fn strip_default_attrs(method: &ImplItemMethod) -> ImplItemMethod {
+    let mut method = method.clone();
+    for arg in method.sig.inputs.iter_mut() {
+        if let FnArg::Typed(pat_type) = arg {
+            pat_type.attrs.retain(|attr| !attr.path.is_ident("default"));
+        }
+    }
+    method
+}
+
+// Slot that a storage field is pinned to with `#[slot(..)]`, which takes an integer literal
+pub fn get_pinned_slot(attrs: &[syn::Attribute]) -> syn::Result<Option<U256>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("slot")) else {
+        return Ok(None);
+    };
+    let slot: syn::LitInt = attr.parse_args().map_err(|_| {
+        syn::Error::new_spanned(
+            attr,
+            "expected an integer literal as the slot, e.g. `#[slot(0x10)]`",
+        )
+    })?;
+
+    U256::from_str_radix(slot.base10_digits(), 10)
+        .map(Some)
+        .map_err(|_| syn::Error::new_spanned(&slot, "slot exceeds 256 bits"))
+}
+
+// Number of slots reserved by a `#[gap(..)]` field, which takes a non-zero integer literal
+pub fn get_gap_size(attrs: &[syn::Attribute]) -> syn::Result<Option<u64>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("gap")) else {
+        return Ok(None);
+    };
+    let size = attr
+        .parse_args::<syn::LitInt>()
+        .and_then(|size| size.base10_parse::<u64>())
+        .ok()
+        .filter(|size| *size > 0)
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                attr,
+                "expected the number of reserved slots, e.g. `#[gap(10)]`",
+            )
+        })?;
+
+    Ok(Some(size))
+}
+
+// Value written to a storage field at deployment, set with `#[init(..)]`
+pub fn get_init(attrs: &[syn::Attribute]) -> syn::Result<Option<syn::Expr>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("init")) else {
+        return Ok(None);
+    };
+    let value = attr.parse_args::<syn::Expr>().map_err(|_| {
+        syn::Error::new_spanned(
+            attr,
+            "expected the initial value of the field, e.g. `#[init(U256::from(1_000_000))]`",
+        )
+    })?;
+
+    Ok(Some(value))
+}
+
+// Name of the hidden method that flags a field of a `#[storage]` struct with an `#[init]` default.
+// Being a method, it's also found on the structs that inherit the storage
+pub fn init_const(field: &Ident) -> Ident {
+    format_ident!("__init_{}", field)
+}
+
+// Fields of the storage that the constructor writes to: `x.field.write(..)` calls, where `x` was
+// created with `Self::default()` (or `Struct::default()`)
+fn written_fields(struct_name: &Ident, method: &ImplItemMethod) -> Vec<Ident> {
+    let is_default = |expr: &syn::Expr| {
+        let syn::Expr::Call(call) = expr else {
+            return false;
+        };
+        let syn::Expr::Path(path) = &*call.func else {
+            return false;
+        };
+        let segments: Vec<_> = path.path.segments.iter().map(|s| &s.ident).collect();
+        matches!(segments.as_slice(), [ty, f] if (*ty == "Self" || *ty == struct_name) && *f == "default")
+    };
+
+    let mut storages = Vec::new();
+    let mut fields = Vec::new();
+    for stmt in &method.block.stmts {
+        match stmt {
+            syn::Stmt::Local(local) => {
+                let Some((_, init)) = &local.init else { continue };
+                let pat = match &local.pat {
+                    syn::Pat::Type(pat_type) => &*pat_type.pat,
+                    pat => pat,
+                };
+                if let (syn::Pat::Ident(pat), true) = (pat, is_default(init)) {
+                    storages.push(pat.ident.clone());
+                }
+            }
+            syn::Stmt::Semi(syn::Expr::MethodCall(call), _) | syn::Stmt::Expr(syn::Expr::MethodCall(call)) => {
+                let syn::Expr::Field(field) = &*call.receiver else { continue };
+                let syn::Expr::Path(base) = &*field.base else { continue };
+                let (syn::Member::Named(name), Some(base)) = (&field.member, base.path.get_ident()) else {
+                    continue;
+                };
+                if call.method == "write" && storages.contains(base) && !fields.contains(name) {
+                    fields.push(name.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fields
+}
+
+// Version of a `#[storage]` struct, set with `#[storage_version(..)]`
+// Storage struct embedded by a `#[storage]` struct with `#[inherit(..)]`, whose fields are laid out
+// first and whose `#[contract_base]` methods are dispatched after the ones of the contract
+pub fn get_inherit(attrs: &[syn::Attribute]) -> syn::Result<Option<syn::Path>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("inherit")) else {
+        return Ok(None);
+    };
+    let base = attr.parse_args::<syn::Path>().map_err(|_| {
+        syn::Error::new_spanned(
+            attr,
+            "expected the storage struct to inherit from, e.g. `#[inherit(ERC20Base)]`",
+        )
+    })?;
+
+    Ok(Some(base))
+}
+
+pub fn get_storage_version(attrs: &[syn::Attribute]) -> syn::Result<Option<u64>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path.is_ident("storage_version")) else {
+        return Ok(None);
+    };
+    let version = attr
+        .parse_args::<syn::LitInt>()
+        .and_then(|version| version.base10_parse::<u64>())
+        .map_err(|_| {
+            syn::Error::new_spanned(
+                attr,
+                "expected the version of the storage layout, e.g. `#[storage_version(2)]`",
+            )
+        })?;
+
+    Ok(Some(version))
+}
+
+// Gas budget of a method, set with `#[max_gas(..)]`, which takes an integer literal. Also matches
+// the qualified `#[contract_derive::max_gas]`
+pub fn get_max_gas(attrs: &[syn::Attribute]) -> syn::Result<Option<u64>> {
+    let Some(attr) = attrs
+        .iter()
+        .find(|attr| attr.path.segments.last().is_some_and(|segment| segment.ident == "max_gas"))
+    else {
+        return Ok(None);
+    };
+    let max_gas: syn::LitInt = attr.parse_args().map_err(|_| {
+        syn::Error::new_spanned(
+            attr,
+            "expected the gas budget of the method, e.g. `#[max_gas(60_000)]`",
+        )
+    })?;
+
+    max_gas
+        .base10_parse::<u64>()
+        .map(Some)
+        .map_err(|_| syn::Error::new_spanned(&max_gas, "gas budget exceeds 64 bits"))
+}
+
+// Static holding the `#[max_gas]` budgets of some methods, as `(selector, max_gas, name)`, in the
+// section of the binary that `r55-compile` moves to the metadata trailer. The selectors are
+// expressions, as the ones of interface implementations are only known once expanded.
+//
+// NOTE: must be kept in sync with `GasBudget::parse_section` in `eth-riscv-syscalls`
+pub fn generate_max_gas_section(
+    budgets: &[(TokenStream, u64, &Ident)],
+) -> syn::Result<TokenStream> {
+    if budgets.is_empty() {
+        return Ok(quote! {});
+    }
+
+    let mut size = 0;
+    let mut entries = Vec::new();
+    for (selector, max_gas, name) in budgets {
+        let name_str = name.to_string();
+        if name_str.len() > u8::MAX as usize {
+            return Err(syn::Error::new_spanned(
+                name,
+                "methods with a `#[max_gas]` budget can't have names longer than 255 bytes",
+            ));
+        }
+        size += 13 + name_str.len();
+        let name_bytes = syn::LitByteStr::new(name_str.as_bytes(), name.span());
+        entries.push(quote! { (#selector, #max_gas, #name_bytes) });
+    }
+    let count = entries.len();
+
+    Ok(quote! {
+        #[used]
+        #[link_section = ".r55.max_gas"]
+        static __R55_MAX_GAS: [u8; #size] = {
+            let entries: [(u32, u64, &[u8]); #count] = [#(#entries),*];
+            let mut section = [0u8; #size];
+            let (mut i, mut offset) = (0, 0);
+            while i < entries.len() {
+                let (selector, max_gas, name) = entries[i];
+                let (selector, max_gas) = (selector.to_be_bytes(), max_gas.to_be_bytes());
+                let mut j = 0;
+                while j < 4 {
+                    section[offset + j] = selector[j];
+                    j += 1;
+                }
+                j = 0;
+                while j < 8 {
+                    section[offset + 4 + j] = max_gas[j];
+                    j += 1;
+                }
+                section[offset + 12] = name.len() as u8;
+                j = 0;
+                while j < name.len() {
+                    section[offset + 13 + j] = name[j];
+                    j += 1;
+                }
+                offset += 13 + name.len();
+                i += 1;
+            }
+            section
+        };
+    })
+}
+
 impl<'a> MethodInfo<'a> {
-    pub fn is_mutable(&self) -> bool {
+    pub fn is_mutable(&self) -> syn::Result<bool> {
         match self.args.first() {
-            Some(FnArg::Receiver(receiver)) => receiver.mutability.is_some(),
-            Some(FnArg::Typed(_)) => panic!("First argument must be self"),
-            None => panic!("Expected `self` as the first arg"),
+            Some(FnArg::Receiver(receiver)) => Ok(receiver.mutability.is_some()),
+            _ => Err(self.missing_self()),
         }
     }
+
+    fn missing_self(&self) -> syn::Error {
+        syn::Error::new(
+            self.span,
+            format!(
+                "`{}` must take `&self` or `&mut self` as its first argument",
+                self.name
+            ),
+        )
+    }
 }
 
 // Helper function to get the parameter names + types of a method
 fn get_arg_props<'a>(
     skip_first_arg: bool,
     method: &'a MethodInfo<'a>,
-) -> (Vec<Ident>, Vec<&'a syn::Type>) {
+) -> syn::Result<(Vec<Ident>, Vec<&'a syn::Type>)> {
+    if skip_first_arg && !matches!(method.args.first(), Some(FnArg::Receiver(_))) {
+        return Err(method.missing_self());
+    }
+
     method
         .args
         .iter()
         .skip(if skip_first_arg { 1 } else { 0 })
         .enumerate()
-        .map(|(i, arg)| {
-            if let FnArg::Typed(pat_type) = arg {
-                (format_ident!("arg{}", i), &*pat_type.ty)
-            } else {
-                panic!("Expected typed arguments");
-            }
+        .map(|(i, arg)| match arg {
+            FnArg::Typed(pat_type) => Ok((format_ident!("arg{}", i), &*pat_type.ty)),
+            FnArg::Receiver(receiver) => Err(syn::Error::new_spanned(
+                receiver,
+                "unexpected `self` argument",
+            )),
         })
-        .unzip()
+        .collect::<syn::Result<Vec<_>>>()
+        .map(|args| args.into_iter().unzip())
 }
 
 pub fn get_arg_props_skip_first<'a>(
     method: &'a MethodInfo<'a>,
-) -> (Vec<Ident>, Vec<&'a syn::Type>) {
+) -> syn::Result<(Vec<Ident>, Vec<&'a syn::Type>)> {
     get_arg_props(true, method)
 }
 
-pub fn get_arg_props_all<'a>(method: &'a MethodInfo<'a>) -> (Vec<Ident>, Vec<&'a syn::Type>) {
+pub fn get_arg_props_all<'a>(
+    method: &'a MethodInfo<'a>,
+) -> syn::Result<(Vec<Ident>, Vec<&'a syn::Type>)> {
     get_arg_props(false, method)
 }
 
@@ -87,6 +457,7 @@ pub struct InterfaceArgs {
 impl Parse for InterfaceArgs {
     fn parse(input: ParseStream) -> Result<Self, syn::Error> {
         let rename_style = if !input.is_empty() {
+            let span = input.span();
             let value = if input.peek(LitStr) {
                 input.parse::<LitStr>()?.value()
             } else {
@@ -97,7 +468,7 @@ impl Parse for InterfaceArgs {
                 "camelCase" => Some(InterfaceNamingStyle::CamelCase),
                 invalid => {
                     return Err(syn::Error::new(
-                        input.span(),
+                        span,
                         format!(
                             "unsupported style: {}. Only 'camelCase' is supported",
                             invalid
@@ -115,24 +486,76 @@ impl Parse for InterfaceArgs {
     }
 }
 
+// Interfaces implemented by a contract, through `#[contract_impl]` blocks: `#[contract(IERC20, IOwned)]`
+pub struct ContractArgs {
+    pub interfaces: Vec<syn::Path>,
+}
+
+impl Parse for ContractArgs {
+    fn parse(input: ParseStream) -> Result<Self, syn::Error> {
+        let interfaces = Punctuated::<syn::Path, Token![,]>::parse_terminated(input).map_err(|err| {
+            syn::Error::new(
+                err.span(),
+                "`#[contract]` only takes the interfaces implemented by the contract, e.g. `#[contract(IERC20)]`",
+            )
+        })?;
+        Ok(ContractArgs {
+            interfaces: interfaces.into_iter().collect(),
+        })
+    }
+}
+
+impl ContractArgs {
+    // Names of the interfaces, which their dispatch methods are named after
+    pub fn names(&self) -> syn::Result<Vec<&Ident>> {
+        self.interfaces
+            .iter()
+            .map(|path| {
+                path.segments
+                    .last()
+                    .map(|segment| &segment.ident)
+                    .ok_or_else(|| syn::Error::new_spanned(path, "expected the name of an interface"))
+            })
+            .collect()
+    }
+}
+
+// Name of the method dispatching the calls to the methods of an interface implemented by a contract
+pub fn dispatch_fn(interface: &Ident) -> Ident {
+    format_ident!("__dispatch_{}", interface)
+}
+
 // Helper function to generate interface impl from user-defined methods
 pub fn generate_interface<T>(
     methods: &[&T],
     interface_name: &Ident,
     interface_style: Option<InterfaceNamingStyle>,
-) -> quote::__private::TokenStream
+) -> syn::Result<quote::__private::TokenStream>
 where
     for<'a> MethodInfo<'a>: From<&'a T>,
 {
-    let methods: Vec<MethodInfo> = methods.iter().map(|&m| MethodInfo::from(m)).collect();
-    let (mut_methods, immut_methods): (Vec<MethodInfo>, Vec<MethodInfo>) =
-        methods.into_iter().partition(|m| m.is_mutable());
+    let mut mut_methods = Vec::new();
+    let mut immut_methods = Vec::new();
+    let mut selectors = Vec::new();
+    let mut selector_consts = Vec::new();
+    for method in methods.iter().map(|&m| MethodInfo::from(m)) {
+        let selector = u32::from_be_bytes(generate_fn_selector(&method, interface_style)?);
+        let const_name = selector_const(method.name);
+        selectors.push(selector);
+        selector_consts.push(quote! { pub const #const_name: u32 = #selector; });
+
+        if method.is_mutable()? {
+            mut_methods.push(method);
+        } else {
+            immut_methods.push(method);
+        }
+    }
 
    // Generate implementations and documentation for mutable methods
     let mut mut_method_impls = Vec::new();
     let mut mut_method_docs = Vec::new();
     for method in &mut_methods {
-        let (impl_code, doc_code) = generate_method_impl(method, interface_style, true);
+        let (impl_code, doc_code) = generate_method_impl(method, interface_style, true)?;
         mut_method_impls.push(impl_code);
         mut_method_docs.push(doc_code);
     }
@@ -141,12 +564,12 @@ where
     let mut immut_method_impls = Vec::new();
     let mut immut_method_docs = Vec::new();
     for method in &immut_methods {
-        let (impl_code, doc_code) = generate_method_impl(method, interface_style, false);
+        let (impl_code, doc_code) = generate_method_impl(method, interface_style, false)?;
         immut_method_impls.push(impl_code);
         immut_method_docs.push(doc_code);
     }
 
-    quote! {
+    Ok(quote! {
         /// `Interface` is a wrapper type for `Address`, which allows to easily interact with the contract's bytecode. Automatically derives the `CallCtx`, but needs to be initialized using a builder pattern.
         ///   ```
         ///   // Can perform a staticcall to an ERC20
@@ -164,6 +587,30 @@ where
         ///
         /// Implementation methods, always available:
         /// * fn `address`() -> `Address`; Returns the address of the underlying contract
+        /// * fn `with_value`(amount: `U256`) -> `Self`; Only available on `ValueCtx`. Sends `amount`
+        ///   with the next mutable call made through this interface, after which it is reset to zero
+        ///   (e.g. `weth.with_value(amount).deposit()`). Calling a method that isn't `#[payable]`
+        ///   with a value panics, when the target's methods are known (i.e. not for `#[interface]`
+        ///   traits). Staticcalls can't send value, so it is left for the next mutable call
+        /// * fn `with_gas`(gas: `u64`) -> `Self`; Caps the gas forwarded to every subsequent call
+        ///   made through this interface (e.g. `token.with_gas(50_000).transfer(to, amount)`). The
+        ///   cap is persistent: it isn't reset after a call, and a cap of zero lifts it. A callee
+        ///   running out of gas under the cap fails the call (`None`, or `CallError::Unknown` with
+        ///   no data), without reverting the caller
+        /// * fn `send`(amount: `U256`) -> `Result<(), CallError>`; Only available on `ValueCtx`.
+        ///   Sends `amount` with empty calldata, which a contract handles with its `fallback` (e.g.
+        ///   `IVault::new(addr).with_ctx(self).send(amount)`). Reverts are returned along with their
+        ///   data, and the gas cap of `with_gas` applies. Doesn't consume the `with_value` amount
+        /// * fn `delegate`() -> `Self<Delegate>`; Only available on `ValueCtx`. Makes every call
+        ///   made through the returned interface a delegatecall, which runs the code of the target
+        ///   with the storage, balance, caller and value of the current contract (e.g.
+        ///   `IERC20::new(implementation).with_ctx(self).delegate().transfer(to, amount)`).
+        ///   Delegatecalls can't send value, so `with_value` and `send` aren't available on it, and
+        ///   a pending `with_value` amount panics
+        ///
+        /// Every method also has a `try_` variant, with the same context requirements, which returns
+        /// the undecoded `RawCallResult` of the call, and an `encode_` variant, which only returns
+        /// its calldata (e.g. to batch static calls with a `Multicall`).
         ///
         /// Immutable methods, available on `StaticCtx` and `MutableCtx`:
         #(#immut_method_docs)*
@@ -172,6 +619,8 @@ where
         #(#mut_method_docs)*
         pub struct #interface_name<C: CallCtx> {
             address: Address,
+            value: alloy_core::primitives::U256,
+            gas_limit: Option<u64>,
             _ctx: core::marker::PhantomData<C>
         }
 
@@ -189,6 +638,8 @@ where
             fn into_interface(self) -> #interface_name<C> {
                 #interface_name {
                     address: self.address,
+                    value: self.value,
+                    gas_limit: self.gas_limit,
                     _ctx: core::marker::PhantomData
                 }
             }
@@ -200,39 +651,113 @@ where
             fn from_builder(builder: InterfaceBuilder<Self>) -> Self {
                 Self {
                     address: builder.address,
+                    value: alloy_core::primitives::U256::ZERO,
+                    gas_limit: None,
                     _ctx: core::marker::PhantomData
                 }
             }
         }
 
+        // Selectors of the methods, which the dispatchers of `#[contract_impl]` blocks match on
+        #[allow(non_upper_case_globals)]
+        impl <C: CallCtx> #interface_name<C> {
+            /// Selectors of every method of the interface.
+            pub const SELECTORS: &'static [u32] = &[#(#selectors),*];
+
+            #(#[doc(hidden)] #selector_consts)*
+        }
+
         impl <C: CallCtx> #interface_name<C> {
             pub fn address(&self) -> Address {
                 self.address
             }
+
+            pub fn with_gas(mut self, gas: u64) -> Self {
+                self.gas_limit = Some(gas);
+                self
+            }
+
+            // Performs the call of a method, as a delegatecall if the interface delegates (which
+            // never has a value), or as a staticcall if the method is a view one
+            #[doc(hidden)]
+            pub fn __try_call(
+                &self,
+                value: alloy_core::primitives::U256,
+                calldata: &[u8],
+                is_static: bool,
+            ) -> Result<alloy_core::primitives::Bytes, alloy_core::primitives::Bytes> {
+                if C::DELEGATE {
+                    eth_riscv_runtime::try_delegatecall_contract(self.address, calldata, self.gas_limit)
+                } else if is_static {
+                    eth_riscv_runtime::try_staticcall_contract(self.address, value, calldata, self.gas_limit)
+                } else {
+                    eth_riscv_runtime::try_call_contract(self.address, value, calldata, self.gas_limit)
+                }
+            }
         }
 
+        // Method names mirror the ABI ones, which aren't always snake case (e.g. `onERC721Received`)
+        #[allow(non_snake_case)]
         impl<C: StaticCtx> #interface_name<C> {
             #(#immut_method_impls)*
         }
 
+        impl<C: eth_riscv_runtime::ValueCtx> #interface_name<C> {
+            pub fn delegate(self) -> #interface_name<eth_riscv_runtime::Delegate> {
+                if !self.value.is_zero() {
+                    panic!("Delegatecalls can't send value");
+                }
+                #interface_name {
+                    address: self.address,
+                    value: alloy_core::primitives::U256::ZERO,
+                    gas_limit: self.gas_limit,
+                    _ctx: core::marker::PhantomData
+                }
+            }
+
+            pub fn with_value(mut self, amount: alloy_core::primitives::U256) -> Self {
+                self.value = amount;
+                self
+            }
+
+            pub fn send(
+                &mut self,
+                amount: alloy_core::primitives::U256,
+            ) -> Result<(), eth_riscv_runtime::CallError> {
+                eth_riscv_runtime::try_call_contract(self.address, amount, &[], self.gas_limit)
+                    .map(|_| ())
+                    .map_err(|revert_data| eth_riscv_runtime::CallError::decode(&revert_data))
+            }
+        }
+
+        #[allow(non_snake_case)]
         impl<C: MutableCtx> #interface_name<C> {
             #(#mut_method_impls)*
         }
-    }
+    })
 }
 
 fn generate_method_impl(
     method: &MethodInfo,
     interface_style: Option<InterfaceNamingStyle>,
     is_mutable: bool,
-) -> (TokenStream, TokenStream) {
+) -> syn::Result<(TokenStream, TokenStream)> {
     let name = method.name;
     let return_type = method.return_type;
-    let method_selector = u32::from_be_bytes(
-        generate_fn_selector(method, interface_style).expect("Unable to generate fn selector"),
-    );
+    let method_selector = u32::from_be_bytes(generate_fn_selector(method, interface_style)?);
 
-    let (arg_names, arg_types) = get_arg_props_skip_first(method);
+    let (arg_names, arg_types) = get_arg_props_skip_first(method)?;
+    // Callers pass the `bytes` streamed by a `CalldataReader` as `Bytes`
+    let arg_types = arg_types
+        .iter()
+        .map(|ty| {
+            if is_calldata_reader(ty) {
+                quote! { alloy_core::primitives::Bytes }
+            } else {
+                quote! { #ty }
+            }
+        })
+        .collect::<Vec<_>>();
 
     let calldata = if arg_names.is_empty() {
         quote! {
@@ -258,37 +783,53 @@ fn generate_method_impl(
         }
     };
 
-    let (call_fn, self_param) = if is_mutable {
-        (
-            quote! { eth_riscv_runtime::call_contract },
-            quote! { &mut self },
-        )
+    let self_param = if is_mutable {
+        quote! { &mut self }
     } else {
-        (
-            quote! { eth_riscv_runtime::staticcall_contract },
-            quote! { &self},
-        )
+        quote! { &self }
+    };
+    let is_static = !is_mutable;
+
+    // Only mutable calls can send value, which is consumed by the call
+    let value = if !is_mutable {
+        quote! { let value = alloy_core::primitives::U256::ZERO; }
+    } else if method.payable == Some(false) {
+        quote! {
+            let value = core::mem::take(&mut self.value);
+            if !value.is_zero() {
+                panic!("Non-payable function");
+            }
+        }
+    } else {
+        quote! { let value = core::mem::take(&mut self.value); }
+    };
+    // Methods without a return value can only fail with the errors that any call can revert with
+    let wrapper_type = match return_type {
+        ReturnType::Default => {
+            WrapperType::Result(quote!(()), quote!(core::convert::Infallible))
+        }
+        ReturnType::Type(..) => extract_wrapper_types(return_type),
+    };
+    let return_type_tokens = match return_type {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
     };
-    let wrapper_type = extract_wrapper_types(return_type);
 
     // Generate documentation
-    let (arg_names_for_docs, arg_types_for_docs) = get_arg_props_skip_first(method);
-    let args_docs = if arg_names_for_docs.is_empty() {
+    let args_docs = if arg_names.is_empty() {
         String::new()
     } else {
-        let args_with_types = arg_names_for_docs.iter().zip(arg_types_for_docs.iter())
+        let args_with_types = arg_names.iter().zip(arg_types.iter())
             .map(|(name, ty)| format!("{}: `{}`", name, quote!(#ty).to_string().replace(" ", "").replace(",", ", ")))
             .collect::<Vec<_>>()
             .join(", ");
         args_with_types
     };
-    let doc_return_type = match &wrapper_type {
-        WrapperType::Result(ok_type, err_type) => quote!(Result<#ok_type, #err_type>),
-        WrapperType::Option(inner_type) => quote!(Option<#inner_type>),
-        WrapperType::None => match return_type {
-            ReturnType::Default => quote!{Option<()>},
-            ReturnType::Type(_, ty) => quote!(Option<#ty>),
-        },
+    let doc_return_type = match (&wrapper_type, return_type) {
+        (WrapperType::Result(..), ReturnType::Default) => quote!(Result<(), CallError>),
+        (WrapperType::Result(ok_type, err_type), _) => quote!(Result<#ok_type, CallError<#err_type>>),
+        (WrapperType::Option(inner_type), _) => quote!(Option<#inner_type>),
+        (WrapperType::None, _) => quote!(Option<#return_type_tokens>),
     };
     
     let doc_line = format!(
@@ -302,41 +843,52 @@ fn generate_method_impl(
     // Generate different implementations based on return type
     let impl_stream = match wrapper_type {
         // If `Result<T, E>` handle each individual type
-        WrapperType::Result(ok_type, err_type) => quote! {
-            pub fn #name(#self_param, #(#arg_names: #arg_types),*) -> Result<#ok_type, #err_type>  {
+        WrapperType::Result(ok_type, err_type) => {
+            // Unit results ignore the success data, like Solidity does for functions without return
+            // values, while the rest reject empty data
+            let decode_success = if is_unit(&ok_type) {
+                quote! { Ok(_) => Ok(()), }
+            } else {
+                let empty_output =
+                    empty_output_check(&ok_type, quote! { Err(CallError::Unknown(output)) });
+                quote! {
+                    #empty_output
+                    Ok(output) => <#ok_type>::abi_decode(&output, true)
+                        .map_err(|_| CallError::Unknown(output)),
+                }
+            };
+            quote! {
+            pub fn #name(#self_param, #(#arg_names: #arg_types),*) -> Result<#ok_type, eth_riscv_runtime::CallError<#err_type>>  {
                 use alloy_sol_types::SolValue;
                 use alloc::vec::Vec;
+                use eth_riscv_runtime::CallError;
 
                 #calldata
+                #value
 
-                let result = #call_fn(
-                    self.address,
-                    0_u64,
-                    &complete_calldata,
-                    None
-                );
-
-                match <#ok_type>::abi_decode(&result, true) {
-                    Ok(decoded) => Ok(decoded),
-                    Err(_) => Err(<#err_type>::abi_decode(&result, true))
+                match self.__try_call(value, &complete_calldata, #is_static) {
+                    #decode_success
+                    Err(revert_data) => Err(CallError::decode(&revert_data)),
                 }
             }
-        },
+        }},
         // If `Option<T>` unwrap the type to decode, and wrap it back
         WrapperType::Option(return_ty) => {
+            let empty_output = empty_output_check(&return_ty, quote! { return None });
             quote! {
                 pub fn #name(#self_param, #(#arg_names: #arg_types),*) -> Option<#return_ty> {
                     use alloy_sol_types::SolValue;
                     use alloc::vec::Vec;
 
                     #calldata
+                    #value
 
-                    let result = #call_fn(
-                        self.address,
-                        0_u64,
-                        &complete_calldata,
-                        None
-                    );
+                    // A failed call is `None`, even if its revert data happens to decode
+                    let result = match self.__try_call(value, &complete_calldata, #is_static) {
+                        #empty_output
+                        Ok(output) => output,
+                        Err(_) => return None,
+                    };
 
                     match <#return_ty>::abi_decode(&result, true) {
                         Ok(decoded) => Some(decoded),
@@ -347,23 +899,22 @@ fn generate_method_impl(
         }
         // Otherwise, simply decode the value + wrap it in an `Option` to force error-handling
         WrapperType::None => {
-            let return_ty = match return_type {
-                ReturnType::Default => quote! { () },
-                ReturnType::Type(_, ty) => quote! { #ty },
-            };
+            let return_ty = return_type_tokens;
+            let empty_output = empty_output_check(&return_ty, quote! { return None });
             quote! {
                 pub fn #name(#self_param, #(#arg_names: #arg_types),*) -> Option<#return_ty> {
                     use alloy_sol_types::SolValue;
                     use alloc::vec::Vec;
 
                     #calldata
+                    #value
 
-                    let result = #call_fn(
-                        self.address,
-                        0_u64,
-                        &complete_calldata,
-                        None
-                    );
+                    // A failed call is `None`, even if its revert data happens to decode
+                    let result = match self.__try_call(value, &complete_calldata, #is_static) {
+                        #empty_output
+                        Ok(output) => output,
+                        Err(_) => return None,
+                    };
 
                     match <#return_ty>::abi_decode(&result, true) {
                         Ok(decoded) => Some(decoded),
@@ -374,7 +925,50 @@ fn generate_method_impl(
         }
     };
 
-    (impl_stream, doc_stream)
+    // Raw variant, which leaves the outcome of the call undecoded, and calldata encoder (e.g. to
+    // batch calls with a `Multicall`)
+    let try_name = format_ident!("try_{}", name);
+    let encode_name = format_ident!("encode_{}", name);
+    let impl_stream = quote! {
+        #impl_stream
+
+        pub fn #encode_name(&self, #(#arg_names: #arg_types),*) -> alloy_core::primitives::Bytes {
+            use alloy_sol_types::SolValue;
+            use alloc::vec::Vec;
+
+            #calldata
+
+            complete_calldata.into()
+        }
+
+        pub fn #try_name(#self_param, #(#arg_names: #arg_types),*) -> eth_riscv_runtime::RawCallResult {
+            use alloy_sol_types::SolValue;
+            use alloc::vec::Vec;
+
+            #calldata
+            #value
+
+            self.__try_call(value, &complete_calldata, #is_static).into()
+        }
+    };
+
+    Ok((impl_stream, doc_stream))
+}
+
+fn is_unit(ty: &TokenStream) -> bool {
+    ty.to_string() == quote!(()).to_string()
+}
+
+// Helper function to generate the match arm that rejects empty output for non-void methods, as calls
+// to addresses without code succeed with no data
+fn empty_output_check(return_ty: &TokenStream, on_empty: TokenStream) -> TokenStream {
+    if is_unit(return_ty) {
+        return quote! {};
+    }
+
+    quote! {
+        Ok(output) if output.is_empty() => #on_empty,
+    }
 }
 
 pub enum WrapperType {
@@ -444,11 +1038,16 @@ pub fn extract_wrapper_types(return_type: &ReturnType) -> WrapperType {
     }
 }
 
+// Name of the (hidden) const holding the selector of an interface method
+pub fn selector_const(method: &Ident) -> Ident {
+    format_ident!("__SELECTOR_{}", method)
+}
+
 // Helper function to generate fn selector
 pub fn generate_fn_selector(
     method: &MethodInfo,
     style: Option<InterfaceNamingStyle>,
-) -> Option<[u8; 4]> {
+) -> syn::Result<[u8; 4]> {
     let name = match style {
         None => method.name.to_string(),
         Some(style) => match style {
@@ -456,21 +1055,67 @@ pub fn generate_fn_selector(
         },
     };
 
-    let (_, arg_types) = get_arg_props_skip_first(method);
+    let (_, arg_types) = get_arg_props_skip_first(method)?;
     let args = arg_types
         .iter()
-        .map(|ty| rust_type_to_sol_type(ty))
-        .collect::<Result<Vec<_>, _>>()
-        .ok()?;
+        .map(|ty| sol_type(ty))
+        .collect::<syn::Result<Vec<_>>>()?;
     let args_str = args
         .iter()
         .map(|ty| ty.sol_type_name().into_owned())
         .collect::<Vec<_>>()
         .join(",");
 
-    let selector = format!("{}({})", name, args_str);
-    let selector_bytes = keccak256(selector.as_bytes())[..4].try_into().ok()?;
-    Some(selector_bytes)
+    let selector = keccak256(format!("{}({})", name, args_str).as_bytes());
+    Ok([selector[0], selector[1], selector[2], selector[3]])
+}
+
+// Same as `rust_type_to_sol_type`, with the error pointing at the offending type
+pub fn sol_type(ty: &Type) -> syn::Result<DynSolType> {
+    rust_type_to_sol_type(ty).map_err(|err| {
+        let ty_str = quote!(#ty).to_string().replace(' ', "");
+        syn::Error::new_spanned(
+            ty,
+            format!("`{}` can't be mapped to a Solidity type: {}", ty_str, err),
+        )
+    })
+}
+
+// Whether the values of a Solidity type have a fixed-size encoding
+pub fn is_fixed_size(ty: &DynSolType) -> bool {
+    match ty {
+        DynSolType::Bytes | DynSolType::String | DynSolType::Array(_) => false,
+        DynSolType::FixedArray(inner, _) => is_fixed_size(inner),
+        DynSolType::Tuple(types) => types.iter().all(is_fixed_size),
+        _ => true,
+    }
+}
+
+// Whether `ty` implements the runtime's `StaticAbi`, so that its values can be encoded on the stack.
+// Aliases aren't resolved, and fall back to `abi_encode`
+pub fn is_static_abi(ty: &Type) -> bool {
+    match ty {
+        Type::Paren(paren) => is_static_abi(&paren.elem),
+        Type::Tuple(tuple) => tuple.elems.len() <= 8 && tuple.elems.iter().all(is_static_abi),
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let Some(segment) = type_path.path.segments.last() else {
+                return false;
+            };
+            if !segment.arguments.is_empty() {
+                return false;
+            }
+
+            match segment.ident.to_string().as_str() {
+                "bool" | "Address" | "U256" | "I256" => true,
+                "u16" | "u32" | "u64" | "u128" | "i8" | "i16" | "i32" | "i64" | "i128" => true,
+                name => {
+                    name.starts_with('B')
+                        && matches!(rust_type_to_sol_type(ty), Ok(DynSolType::FixedBytes(_)))
+                }
+            }
+        }
+        _ => false,
+    }
 }
 
 // Helper function to convert rust types to their solidity equivalent
@@ -489,7 +1134,7 @@ pub fn rust_type_to_sol_type(ty: &Type) -> Result<DynSolType, &'static str> {
                 "Function" => Ok(DynSolType::Function),
                 "bool" | "Bool" => Ok(DynSolType::Bool),
                 "String" | "str" => Ok(DynSolType::String),
-                "Bytes" => Ok(DynSolType::Bytes),
+                "Bytes" | "CalldataReader" => Ok(DynSolType::Bytes),
                 // Fixed-size bytes
                 b if b.starts_with('B') => {
                     let size: usize = b
@@ -535,6 +1180,10 @@ pub fn rust_type_to_sol_type(ty: &Type) -> Result<DynSolType, &'static str> {
                             "Vec" => {
                                 let inner = args.args.first().ok_or("Empty Vec type argument")?;
                                 if let syn::GenericArgument::Type(inner_ty) = inner {
+                                    // `Vec<u8>` is abi-encoded as `bytes` by alloy, not as `uint8[]`
+                                    if matches!(inner_ty, Type::Path(p) if p.path.is_ident("u8")) {
+                                        return Ok(DynSolType::Bytes);
+                                    }
                                     let inner_sol_type = rust_type_to_sol_type(inner_ty)?;
                                     Ok(DynSolType::Array(Box::new(inner_sol_type)))
                                 } else {
@@ -557,6 +1206,11 @@ pub fn rust_type_to_sol_type(ty: &Type) -> Result<DynSolType, &'static str> {
                         .base10_digits()
                         .parse()
                         .map_err(|_| "Invalid array size")?;
+                    // `[u8; N]` is abi-encoded as `bytesN` by alloy, not as `uint8[N]`
+                    let is_byte = matches!(&*array.elem, Type::Path(p) if p.path.is_ident("u8"));
+                    if is_byte && size > 0 && size <= 32 {
+                        return Ok(DynSolType::FixedBytes(size));
+                    }
                     Ok(DynSolType::FixedArray(Box::new(inner_sol_type), size))
                 } else {
                     Err("Invalid array size literal")
@@ -602,30 +1256,119 @@ fn to_camel_case(s: String) -> String {
 }
 
 // Helper function to generate the deployment code
+// Helper function to generate the alias of the constructor args, which `Deployable` impls use to
+// type-check deployments: the arg type itself for a single arg, a tuple otherwise
+pub fn generate_constructor_args(
+    struct_name: &Ident,
+    constructor: Option<&ImplItemMethod>,
+) -> syn::Result<TokenStream> {
+    let alias = format_ident!("{}ConstructorArgs", struct_name);
+    let required_alias = format_ident!("{}RequiredArgs", struct_name);
+    let interface_name = format_ident!("I{}", struct_name);
+    let (arg_types, defaults): (Vec<Type>, _) = match constructor {
+        Some(method) => {
+            let method_info = MethodInfo::from(method);
+            let (_, arg_types) = get_arg_props_all(&method_info)?;
+            (arg_types.into_iter().cloned().collect(), get_arg_defaults(method)?)
+        }
+        None => (Vec::new(), Vec::new()),
+    };
+
+    // Deployments accept any prefix of the args that covers the ones without a default
+    let required = defaults.iter().take_while(|default| default.is_none()).count();
+    let required_types = &arg_types[..required];
+    let accepted_args = (required..=arg_types.len()).map(|len| {
+        let types = &arg_types[..len];
+        quote! {
+            impl eth_riscv_runtime::create::DeployArgs<#interface_name<eth_riscv_runtime::ReadOnly>> for (#(#types),*) {}
+        }
+    });
+
+    Ok(quote! {
+        /// Constructor args of the contract, as expected by its `Deployable` implementation
+        pub type #alias = (#(#arg_types),*);
+
+        /// Constructor args of the contract without the trailing ones with a `#[default]`, the
+        /// shortest accepted by its `Deployable` implementation
+        pub type #required_alias = (#(#required_types),*);
+
+        #(#accepted_args)*
+    })
+}
+
 pub fn generate_deployment_code(
     struct_name: &Ident,
     constructor: Option<&ImplItemMethod>,
-) -> quote::__private::TokenStream {
+) -> syn::Result<quote::__private::TokenStream> {
     // Decode constructor args + trigger constructor logic
     let constructor_code = match constructor {
         Some(method) => {
             let method_info = MethodInfo::from(method);
-            let (arg_names, arg_types) = get_arg_props_all(&method_info);
+            let (arg_names, arg_types) = get_arg_props_all(&method_info)?;
+            let defaults = get_arg_defaults(method)?;
+            let overridden = written_fields(struct_name, method);
+            let method = strip_default_attrs(method);
+
+            let decode_args = if defaults.iter().all(Option::is_none) {
+                quote! {
+                    let (#(#arg_names),*) = match <(#(#arg_types),*)>::abi_decode(&calldata, true) {
+                        Ok(args) => args,
+                        Err(_) => eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::CALLDATA_DECODE_ERROR),
+                    };
+                }
+            } else {
+                // Trailing args with a default can be left out of the encoding. The longest
+                // encoding that decodes (and re-encodes) exactly to the calldata wins, and the
+                // missing args are filled with their defaults
+                let required = defaults.iter().take_while(|default| default.is_none()).count();
+                let attempts = (required..=arg_names.len()).rev().map(|len| {
+                    let (names, types) = (&arg_names[..len], &arg_types[..len]);
+                    let values = names
+                        .iter()
+                        .map(|name| quote! { #name })
+                        .chain(defaults[len..].iter().flatten().map(|default| quote! { #default }));
+                    if len == 0 {
+                        quote! {
+                            if calldata.is_empty() { (#(#values),*) }
+                        }
+                    } else {
+                        quote! {
+                            if let Ok((#(#names),*)) = <(#(#types),*)>::abi_decode(&calldata, true) {
+                                (#(#values),*)
+                            }
+                        }
+                    }
+                });
+                quote! {
+                    let (#(#arg_names),*) = #(#attempts else)* {
+                        eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::CALLDATA_DECODE_ERROR)
+                    };
+                }
+            };
+
+            // Fields with an `#[init]` default that the constructor writes anyway, which warn
+            let overrides = overridden.iter().map(|field| {
+                let init_const = init_const(field);
+                quote_spanned! {field.span()=>
+                    let _ = |storage: &#struct_name| storage.#init_const();
+                }
+            });
 
             if arg_types.is_empty() {
                 quote! {
                     impl #struct_name { #method }
+                    #(#overrides)*
                     #struct_name::new();
                 }
             } else {
                 quote! {
                     impl #struct_name { #method }
+                    #(#overrides)*
 
                     // Get encoded constructor args
                     let calldata = eth_riscv_runtime::msg_data();
 
-                    let (#(#arg_names),*) = <(#(#arg_types),*)>::abi_decode(&calldata, true)
-                        .expect("Failed to decode constructor args");
+                    #decode_args
                     #struct_name::new(#(#arg_names),*);
                 }
             }
@@ -635,26 +1378,30 @@ pub fn generate_deployment_code(
         },
     };
 
-    quote! {
+    Ok(quote! {
         use alloc::vec::Vec;
         use alloy_core::primitives::U32;
 
         #[no_mangle]
         pub extern "C" fn main() -> ! {
+            // Write the `#[init]` defaults of the storage, which the constructor can override
+            {
+                use eth_riscv_runtime::__private::InitDefaults as _;
+                #struct_name::__init_defaults();
+            }
+
             #constructor_code
 
             // Return runtime code
             let runtime: &[u8] = include_bytes!("../target/riscv64imac-unknown-none-elf/release/runtime");
-            let mut prepended_runtime = Vec::with_capacity(1 + runtime.len());
-            prepended_runtime.push(0xff);
+            let header = eth_riscv_runtime::header::Header::current().encode();
+            let mut prepended_runtime = Vec::with_capacity(header.len() + runtime.len());
+            prepended_runtime.extend_from_slice(&header);
             prepended_runtime.extend_from_slice(runtime);
 
-            let prepended_runtime_slice: &[u8] = &prepended_runtime;
-            let result_ptr = prepended_runtime_slice.as_ptr() as u64;
-            let result_len = prepended_runtime_slice.len() as u64;
-            eth_riscv_runtime::return_riscv(result_ptr, result_len);
+            eth_riscv_runtime::return_with(&prepended_runtime);
         }
-    }
+    })
 }
 
 #[cfg(test)]
@@ -715,6 +1462,7 @@ mod tests {
             (parse_quote!(String), DynSolType::String),
             (parse_quote!(str), DynSolType::String),
             (parse_quote!(Bytes), DynSolType::Bytes),
+            (parse_quote!(CalldataReader), DynSolType::Bytes),
         ];
 
         for (rust_type, expected_sol_type) in test_cases {
@@ -783,6 +1531,17 @@ mod tests {
             DynSolType::Array(Box::new(DynSolType::Bool))
         );
 
+        // Arrays of dynamic elements, and byte vectors
+        assert_eq!(
+            rust_type_to_sol_type(&parse_quote!(Vec<Bytes>)).unwrap(),
+            DynSolType::Array(Box::new(DynSolType::Bytes))
+        );
+
+        assert_eq!(
+            rust_type_to_sol_type(&parse_quote!(Vec<u8>)).unwrap(),
+            DynSolType::Bytes
+        );
+
         // Fixed-size arrays
         assert_eq!(
             rust_type_to_sol_type(&parse_quote!([U256; 5])).unwrap(),
@@ -793,6 +1552,17 @@ mod tests {
             rust_type_to_sol_type(&parse_quote!([Bool; 3])).unwrap(),
             DynSolType::FixedArray(Box::new(DynSolType::Bool), 3)
         );
+
+        // Byte arrays
+        assert_eq!(
+            rust_type_to_sol_type(&parse_quote!([u8; 4])).unwrap(),
+            DynSolType::FixedBytes(4)
+        );
+
+        assert_eq!(
+            rust_type_to_sol_type(&parse_quote!([u8; 33])).unwrap(),
+            DynSolType::FixedArray(Box::new(DynSolType::Uint(8)), 33)
+        );
     }
 
     #[test]
@@ -849,6 +1619,43 @@ mod tests {
         assert!(rust_type_to_sol_type(&parse_quote!(Result<U256>)).is_err());
     }
 
+    #[test]
+    fn test_static_abi_types() {
+        let static_types: Vec<Type> = vec![
+            parse_quote!(bool),
+            parse_quote!(Address),
+            parse_quote!(U256),
+            parse_quote!(B256),
+            parse_quote!(u64),
+            parse_quote!(i128),
+            parse_quote!(()),
+            parse_quote!((Address, (U256, bool))),
+        ];
+        for ty in &static_types {
+            assert!(is_static_abi(ty), "{}", quote!(#ty));
+            assert!(is_fixed_size(&rust_type_to_sol_type(ty).unwrap()));
+        }
+
+        // Fixed-size, but not encoded on the stack
+        let fixed_size: Vec<Type> = vec![parse_quote!([U256; 3]), parse_quote!(Function)];
+        for ty in &fixed_size {
+            assert!(!is_static_abi(ty), "{}", quote!(#ty));
+            assert!(is_fixed_size(&rust_type_to_sol_type(ty).unwrap()));
+        }
+
+        let dynamic_types: Vec<Type> = vec![
+            parse_quote!(Bytes),
+            parse_quote!(String),
+            parse_quote!(Vec<U256>),
+            parse_quote!((Address, String)),
+            parse_quote!([Bytes; 2]),
+        ];
+        for ty in &dynamic_types {
+            assert!(!is_static_abi(ty), "{}", quote!(#ty));
+            assert!(!is_fixed_size(&rust_type_to_sol_type(ty).unwrap()));
+        }
+    }
+
     #[test]
     fn test_fn_selector() {
         // No arguments
@@ -991,4 +1798,50 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_get_pinned_slot() {
+        let attrs: Vec<syn::Attribute> = vec![parse_quote!(#[slot(3)])];
+        assert_eq!(get_pinned_slot(&attrs).unwrap(), Some(U256::from(3)));
+
+        // EIP-1967 implementation slot
+        let attrs: Vec<syn::Attribute> = vec![parse_quote!(
+            #[slot(0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc)]
+        )];
+        assert_eq!(
+            get_pinned_slot(&attrs).unwrap(),
+            Some(U256::from_be_bytes(
+                alloy_core::primitives::keccak256("eip1967.proxy.implementation").0
+            ) - U256::from(1))
+        );
+
+        let attrs: Vec<syn::Attribute> = vec![parse_quote!(#[doc = "not pinned"])];
+        assert_eq!(get_pinned_slot(&attrs).unwrap(), None);
+    }
+
+    #[test]
+    fn test_written_fields() {
+        let struct_name: Ident = parse_quote!(ERC20);
+        let method: ImplItemMethod = parse_quote! {
+            pub fn new(owner: Address, decimals: U256) -> Self {
+                let mut erc20 = ERC20::default();
+                erc20.owner.write(owner);
+                erc20.decimals.write(decimals);
+                erc20.owner.write(owner);
+                erc20.balance_of[owner].write(U256::from(1));
+                other.total_supply.write(U256::ZERO);
+                erc20
+            }
+        };
+        assert_eq!(written_fields(&struct_name, &method), ["owner", "decimals"]);
+
+        let method: ImplItemMethod = parse_quote! {
+            pub fn new() -> Self {
+                let mut storage: Self = Self::default();
+                storage.total_supply.write(U256::from(1));
+                storage
+            }
+        };
+        assert_eq!(written_fields(&struct_name, &method), ["total_supply"]);
+    }
 }
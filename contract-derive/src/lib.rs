@@ -1,71 +1,86 @@
 extern crate proc_macro;
-use alloy_core::primitives::U256;
+use alloy_core::primitives::{keccak256, U256};
 use alloy_sol_types::SolValue;
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 use syn::{
-    parse_macro_input, Data, DeriveInput, Fields, ImplItem, ImplItemMethod,
-    ItemImpl, ItemTrait, ReturnType, TraitItem,
+    parse_macro_input, Data, DeriveInput, Fields, Ident, ImplItem, ImplItemMethod,
+    ItemImpl, ItemTrait, ReturnType, TraitItem, Type,
 };
 
+mod debug;
 mod helpers;
-use crate::helpers::{InterfaceArgs, MethodInfo};
+use crate::helpers::{ContractArgs, InterfaceArgs, MethodInfo};
 
 #[proc_macro_derive(Error)]
 pub fn error_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    expand_error(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+// Variant of an `Error` enum, whose fields are `None` for unit variants
+struct ErrorVariant<'a> {
+    ident: &'a Ident,
+    fields: Option<Vec<&'a Type>>,
+    signature: String,
+}
+
+fn expand_error(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &input.ident;
 
-    let variants = if let Data::Enum(data) = &input.data {
-        &data.variants
-    } else {
-        panic!("`Error` must be an enum");
+    let Data::Enum(data) = &input.data else {
+        return Err(syn::Error::new_spanned(name, "`Error` can only be derived for enums"));
     };
 
-    // Generate error encoding for each variant
-    let encode_arms = variants.iter().map(|variant| {
+    let mut variants = Vec::new();
+    for variant in &data.variants {
         let variant_name = &variant.ident;
-
-        let signature = match &variant.fields {
-            Fields::Unit => {
-                format!("{}::{}", name, variant_name)
-            }
+        let (fields, signature) = match &variant.fields {
+            Fields::Unit => (None, format!("{}::{}", name, variant_name)),
             Fields::Unnamed(fields) => {
-                let type_names: Vec<_> = fields
-                    .unnamed
+                let types: Vec<_> = fields.unnamed.iter().map(|f| &f.ty).collect();
+                let type_names = types
                     .iter()
-                    .map(|f| {
-                        helpers::rust_type_to_sol_type(&f.ty)
-                            .expect("Unknown type")
-                            .sol_type_name()
-                            .into_owned()
-                    })
-                    .collect();
+                    .map(|ty| helpers::sol_type(ty).map(|ty| ty.sol_type_name().into_owned()))
+                    .collect::<syn::Result<Vec<_>>>()?;
 
-                format!("{}::{}({})", name, variant_name, type_names.join(","))
+                (Some(types), format!("{}::{}({})", name, variant_name, type_names.join(",")))
+            }
+            Fields::Named(fields) => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "named fields are not supported in errors, use a tuple variant instead",
+                ))
             }
-            Fields::Named(_) => panic!("Named fields are not supported"),
         };
 
+        variants.push(ErrorVariant { ident: variant_name, fields, signature });
+    }
+
+    // Generate error encoding for each variant
+    let encode_arms = variants.iter().map(|variant| {
+        let variant_name = variant.ident;
+        let signature = &variant.signature;
+
         let pattern = match &variant.fields {
-            Fields::Unit => quote! { #name::#variant_name },
-            Fields::Unnamed(fields) => {
-                let vars: Vec<_> = (0..fields.unnamed.len())
+            None => quote! { #name::#variant_name },
+            Some(fields) => {
+                let vars: Vec<_> = (0..fields.len())
                     .map(|i| format_ident!("_{}", i))
                     .collect();
                 quote! { #name::#variant_name(#(#vars),*) }
             }
-            Fields::Named(_) => panic!("Named fields are not supported"),
         };
 
         // non-unit variants must encode the data
         let data = match &variant.fields {
-            Fields::Unit => quote! {},
-            Fields::Unnamed(fields) => {
-                let vars = (0..fields.unnamed.len()).map(|i| format_ident!("_{}", i));
+            None => quote! {},
+            Some(fields) => {
+                let vars = (0..fields.len()).map(|i| format_ident!("_{}", i));
                 quote! { #( res.extend_from_slice(&#vars.abi_encode()); )* }
             }
-            Fields::Named(_) => panic!("Named fields are not supported"),
         };
 
         quote! {
@@ -81,56 +96,33 @@ pub fn error_derive(input: TokenStream) -> TokenStream {
 
     // Generate error decoding for each variant
     let decode_arms = variants.iter().map(|variant| {
-        let variant_name = &variant.ident;
-        
-        let signature = match &variant.fields {
-            Fields::Unit => {
-                format!("{}::{}", name, variant_name)
-            },
-            Fields::Unnamed(fields) => {
-                let type_names: Vec<_> = fields.unnamed.iter()
-                    .map(|f| helpers::rust_type_to_sol_type(&f.ty)
-                        .expect("Unknown type")
-                        .sol_type_name()
-                        .into_owned()
-                    ).collect();
-                
-                format!("{}::{}({})",
-                    name,
-                    variant_name,
-                    type_names.join(",")
-                )
-            },
-            Fields::Named(_) => panic!("Named fields are not supported"),
-        };
-
+        let variant_name = variant.ident;
+        let signature = &variant.signature;
         let selector_bytes = quote!{ &keccak256(#signature.as_bytes())[..4].to_vec() };
 
         match &variant.fields {
-            Fields::Unit => quote! { selector if selector == #selector_bytes => #name::#variant_name },
-            Fields::Unnamed(fields) => {
-                let field_types: Vec<_> = fields.unnamed.iter().map(|f| &f.ty).collect();
-                let indices: Vec<_> = (0..fields.unnamed.len()).collect();
+            None => quote! { selector if selector == #selector_bytes => Some(#name::#variant_name) },
+            Some(field_types) => {
+                let vars: Vec<_> = (0..field_types.len()).map(|i| format_ident!("_{}", i)).collect();
+                // fields are encoded back to back, so they must be decoded as a sequence
                 quote!{ selector if selector == #selector_bytes => {
-                    let mut values = Vec::new();
-                    #( values.push(<#field_types>::abi_decode(data.unwrap(), true).expect("Unable to decode")); )*
-                    #name::#variant_name(#(values[#indices]),*)
+                    let (#(#vars,)*) = <(#(#field_types,)*)>::abi_decode(data?, validate).ok()?;
+                    Some(#name::#variant_name(#(#vars),*))
                 }} 
             },
-            Fields::Named(_) => panic!("Named fields are not supported"),
         }
     });
 
     // Generate `Debug` implementation for each variant
     let debug_arms = variants.iter().map(|variant| {
-        let variant_name = &variant.ident;
+        let variant_name = variant.ident;
 
         match &variant.fields {
-            Fields::Unit => quote! {
+            None => quote! {
                 #name::#variant_name => { f.write_str(stringify!(#variant_name)) }
             },
-            Fields::Unnamed(fields) => {
-                let vars: Vec<_> = (0..fields.unnamed.len())
+            Some(fields) => {
+                let vars: Vec<_> = (0..fields.len())
                     .map(|i| format_ident!("_{}", i))
                     .collect();
                 quote! {
@@ -141,7 +133,6 @@ pub fn error_derive(input: TokenStream) -> TokenStream {
                     }
                 }
             }
-            Fields::Named(_) => panic!("Named fields are not supported"),
         }
     });
 
@@ -154,18 +145,17 @@ pub fn error_derive(input: TokenStream) -> TokenStream {
                 match self { #(#encode_arms),* }
             }
 
-            fn abi_decode(bytes: &[u8], validate: bool) -> Self {
+            fn try_abi_decode(bytes: &[u8], validate: bool) -> Option<Self> {
                 use alloy_core::primitives::keccak256;
                 use alloy_sol_types::SolValue;
                 use alloc::vec::Vec;
 
-                if bytes.len() < 4 { panic!("Invalid error length") };
-                let selector = &bytes[..4];
+                let selector = bytes.get(..4)?;
                 let data = if bytes.len() > 4 { Some(&bytes[4..]) } else { None };
 
                 match selector {
                     #(#decode_arms),*,
-                    _ => panic!("Unknown error")
+                    _ => None
                 }
             }
         }
@@ -177,24 +167,40 @@ pub fn error_derive(input: TokenStream) -> TokenStream {
         }
     };
 
-    TokenStream::from(expanded)
+    Ok(expanded)
 }
 
 #[proc_macro_derive(Event, attributes(indexed))]
 pub fn event_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    expand_event(&input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand_event(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &input.ident;
 
-    let fields = if let Data::Struct(data) = &input.data {
-        if let Fields::Named(fields) = &data.fields {
-            &fields.named
-        } else {
-            panic!("Event must have named fields");
-        }
-    } else {
-        panic!("Event must be a struct");
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            Fields::Unnamed(fields) => {
+                return Err(syn::Error::new_spanned(fields, "`Event` requires named fields"))
+            }
+            Fields::Unit => {
+                return Err(syn::Error::new_spanned(name, "`Event` requires named fields"))
+            }
+        },
+        _ => return Err(syn::Error::new_spanned(name, "`Event` can only be derived for structs")),
     };
 
+    // `#[indexed]` is a marker, so arguments are most likely a mistake
+    for attr in fields.iter().flat_map(|f| &f.attrs) {
+        if attr.path.is_ident("indexed") && !attr.tokens.is_empty() {
+            return Err(syn::Error::new_spanned(attr, "`#[indexed]` doesn't take arguments"));
+        }
+    }
+
     // Collect iterators into vectors
     let field_names: Vec<_> = fields.iter().map(|f| &f.ident).collect();
     let field_types: Vec<_> = fields.iter().map(|f| &f.ty).collect();
@@ -219,17 +225,25 @@ pub fn event_derive(input: TokenStream) -> TokenStream {
         }
 
         impl eth_riscv_runtime::log::Event for #name {
-            fn encode_log(&self) -> (alloc::vec::Vec<u8>, alloc::vec::Vec<[u8; 32]>) {
-                use alloy_sol_types::SolValue;
-                use alloy_core::primitives::{keccak256, B256};
-                use alloc::vec::Vec;
+            fn encode_log(
+                &self,
+            ) -> (
+                eth_riscv_runtime::__private::alloc::vec::Vec<u8>,
+                eth_riscv_runtime::__private::alloc::vec::Vec<[u8; 32]>,
+            ) {
+                // Through the runtime, so that events can be defined in any crate depending on it
+                use eth_riscv_runtime::__private::{
+                    alloc::{vec, vec::Vec},
+                    alloy_core::primitives::{keccak256, B256},
+                    alloy_sol_types::SolValue,
+                };
 
                 let mut signature = Vec::new();
                 signature.extend_from_slice(Self::NAME.as_bytes());
                 signature.extend_from_slice(b"(");
 
                 let mut first = true;
-                let mut topics = alloc::vec![B256::default()];
+                let mut topics = vec![B256::default()];
                 let mut data = Vec::new();
 
                 #(
@@ -241,7 +255,12 @@ pub fn event_derive(input: TokenStream) -> TokenStream {
 
                     let field_name = stringify!(#field_names);
                     if Self::INDEXED_FIELDS.contains(&field_name) && topics.len() < 4 {
-                        topics.push(B256::from_slice(&encoded));
+                        // Dynamic values don't fit in a topic, so their hash is indexed instead
+                        if encoded.len() == 32 {
+                            topics.push(B256::from_slice(&encoded));
+                        } else {
+                            topics.push(keccak256(self.#field_names.abi_encode_packed()));
+                        }
                     } else {
                         data.extend_from_slice(&encoded);
                     }
@@ -255,34 +274,61 @@ pub fn event_derive(input: TokenStream) -> TokenStream {
         }
     };
 
-    TokenStream::from(expanded)
+    Ok(expanded)
 }
 
 #[proc_macro_attribute]
-pub fn show_streams(attr: TokenStream, item: TokenStream) -> TokenStream {
-    println!("attr: \"{}\"", attr);
-    println!("item: \"{}\"", item);
-    item
+pub fn contract(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemImpl);
+    expand_contract(attr.into(), &input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
 }
 
-#[proc_macro_attribute]
-pub fn contract(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(item as ItemImpl);
-    let struct_name = if let syn::Type::Path(type_path) = &*input.self_ty {
-        &type_path.path.segments.first().unwrap().ident
-    } else {
-        panic!("Expected a struct.");
-    };
+// Rejects arguments passed to attributes that don't take any
+fn no_attr_args(attr: proc_macro2::TokenStream, name: &str) -> syn::Result<()> {
+    if attr.is_empty() {
+        return Ok(());
+    }
+
+    Err(syn::Error::new_spanned(attr, format!("`#[{}]` doesn't take arguments", name)))
+}
+
+// Name of the contract struct an impl block is for
+fn contract_ident<'a>(input: &'a ItemImpl, attr_name: &str) -> syn::Result<&'a Ident> {
+    match &*input.self_ty {
+        syn::Type::Path(type_path) if type_path.qself.is_none() => {
+            Ok(&type_path.path.segments.last().ok_or_else(|| {
+                syn::Error::new_spanned(type_path, "expected the name of the contract struct")
+            })?.ident)
+        }
+        ty => Err(syn::Error::new_spanned(
+            ty,
+            format!("`#[{}]` must be applied to the impl block of a struct", attr_name),
+        )),
+    }
+}
+
+fn expand_contract(
+    attr: proc_macro2::TokenStream,
+    input: &ItemImpl,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let args: ContractArgs = syn::parse2(attr)?;
+    let struct_name = contract_ident(input, "contract")?;
 
     let mut constructor = None;
+    let mut fallback = None;
     let mut public_methods: Vec<&ImplItemMethod> = Vec::new();
     let mut private_methods: Vec<&ImplItemMethod> = Vec::new();
 
     // Iterate over the items in the impl block to find pub methods + constructor
     for item in input.items.iter() {
         if let ImplItem::Method(method) = item {
+            helpers::check_payable_attr(&method.attrs)?;
             if method.sig.ident == "new" {
                 constructor = Some(method);
+            } else if method.sig.ident == "fallback" {
+                fallback = Some(method);
             } else if let syn::Visibility::Public(_) = method.vis {
                 public_methods.push(method);
             } else {
@@ -291,86 +337,130 @@ pub fn contract(_attr: TokenStream, item: TokenStream) -> TokenStream {
         }
     }
 
+    // Only the dispatched methods have a selector to check the budget of
+    for method in constructor.iter().chain(fallback.iter()).chain(private_methods.iter()) {
+        if helpers::get_max_gas(&method.attrs)?.is_some() {
+            return Err(syn::Error::new_spanned(
+                &method.sig.ident,
+                "`#[max_gas]` only applies to the public methods of a contract",
+            ));
+        }
+    }
+
+    // Private methods stay visible to the module of the contract, which its `#[contract_impl]`
+    // blocks are expanded in
     let inner_methods: Vec<_> = private_methods
         .iter()
-        .map(|method| quote! { #method })
+        .map(|method| {
+            let mut method = (*method).clone();
+            if let syn::Visibility::Inherited = method.vis {
+                method.vis = syn::parse_quote! { pub(super) };
+            }
+            quote! { #method }
+        })
         .collect();
     let input_methods: Vec<_> = public_methods
         .iter()
+        .map(|method| helpers::strip_calldata_attrs(method))
         .map(|method| quote! { #method })
         .collect();
-    let match_arms: Vec<_> = public_methods.iter().map(|method| {
-        let method_name = &method.sig.ident;
-        let method_info = MethodInfo::from(*method);
-        let method_selector = u32::from_be_bytes(
-            helpers::generate_fn_selector(&method_info, None)
-                .expect("Unable to generate fn selector")
-        );
-        let (arg_names, arg_types) = helpers::get_arg_props_skip_first(&method_info);
-
-        // Check if there are payable methods
-        let checks = if !is_payable(&method) {
-            quote! {
-                if eth_riscv_runtime::msg_value() > U256::from(0) {
-                    panic!("Non-payable function");
-                }
+    let selectors = public_methods
+        .iter()
+        .map(|method| {
+            let selector = helpers::generate_fn_selector(&MethodInfo::from(*method), None)?;
+            Ok(u32::from_be_bytes(selector))
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+    let match_arms = public_methods
+        .iter()
+        .zip(&selectors)
+        .map(|(method, selector)| dispatch_arm(method, quote! { #selector }))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let mut budgets = Vec::new();
+    for (method, selector) in public_methods.iter().zip(&selectors) {
+        if let Some(max_gas) = helpers::get_max_gas(&method.attrs)? {
+            budgets.push((quote! { #selector }, max_gas, &method.sig.ident));
+        }
+    }
+    let max_gas_section = helpers::generate_max_gas_section(&budgets)?;
+
+    // Calldata without a known selector is handled by the `fallback` method, if any
+    let (no_selector, unknown_selector) = match fallback {
+        Some(method) => {
+            let (args, _) = helpers::get_arg_props_skip_first(&MethodInfo::from(method))?;
+            if !args.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    &method.sig.inputs,
+                    "`fallback` can't take arguments other than `self`, use `msg_data()` instead",
+                ));
             }
-        } else {
-            quote! {}
-        };
 
-        // Check if the method has a return type
-        let return_handling = match &method.sig.output {
-            ReturnType::Default => {
-                // No return value
-                quote! { self.#method_name(#( #arg_names ),*); }
-            }
-           ReturnType::Type(_,_) => {
-                match helpers::extract_wrapper_types(&method.sig.output) {
-                    helpers::WrapperType::Result(_,_) => quote! {
-                        let res = self.#method_name(#( #arg_names ),*);
-                        match res {
-                            Ok(success) => {
-                                let result_bytes = success.abi_encode();
-                                let result_size = result_bytes.len() as u64;
-                                let result_ptr = result_bytes.as_ptr() as u64;
-                                eth_riscv_runtime::return_riscv(result_ptr, result_size);
-                            }
-                            Err(err) => {
-                                eth_riscv_runtime::revert_with_error(&err.abi_encode());
-                            }
-                        }
-                    },
-                    helpers::WrapperType::Option(_) => quote! {
-                        match self.#method_name(#( #arg_names ),*) {
-                            Some(success) => {
-                                let result_bytes = success.abi_encode();
-                                let result_size = result_bytes.len() as u64;
-                                let result_ptr = result_bytes.as_ptr() as u64;
-                                eth_riscv_runtime::return_riscv(result_ptr, result_size);
-                            },
-                            None => eth_riscv_runtime::revert(),
-                        }
-                    },
-                    helpers::WrapperType::None => quote! {
-                        let result = self.#method_name(#( #arg_names ),*);
-                        let result_bytes = result.abi_encode();
-                        let result_size = result_bytes.len() as u64;
-                        let result_ptr = result_bytes.as_ptr() as u64;
-                        eth_riscv_runtime::return_riscv(result_ptr, result_size);
+            let checks = if !is_payable(method) {
+                quote! {
+                    if eth_riscv_runtime::msg_value() > U256::from(0) {
+                        eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::NON_PAYABLE_ERROR);
                     }
                 }
-            }
-        };
-
-        quote! {
-            #method_selector => {
-                let (#( #arg_names ),*) = <(#( #arg_types ),*)>::abi_decode(calldata, true).expect("abi decode failed");
+            } else {
+                quote! {}
+            };
+            let fallback_call = quote! {{
                 #checks
-                #return_handling
-            }
+                self.fallback();
+                eth_riscv_runtime::return_with(&[]);
+            }};
+            (fallback_call.clone(), fallback_call)
         }
-    }).collect();
+        None => (
+            quote! { eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::CALLDATA_DECODE_ERROR); },
+            quote! { panic!("unknown method") },
+        ),
+    };
+    let fallback = fallback.map(|method| quote! { #method });
+
+    // Selectors unknown to the contract are dispatched to the `#[contract_impl]` blocks of its
+    // interfaces, then to the `#[contract_base]` of the storage it inherits, which only return if
+    // the selector isn't theirs either
+    let interfaces = &args.interfaces;
+    let interface_names = args.names()?;
+    let dispatch_fns: Vec<_> = interface_names.iter().map(|name| helpers::dispatch_fn(name)).collect();
+    let unknown_selector = quote! {{
+        #( self.#dispatch_fns(selector, calldata); )*
+        Self::__dispatch_inherited(self, selector, calldata);
+        #unknown_selector
+    }};
+
+    // Checked at compile time, as the selectors of the interfaces are only known once expanded
+    let mut selector_checks = Vec::new();
+    for (interface, interface_name) in interfaces.iter().zip(&interface_names) {
+        for (method, selector) in public_methods.iter().zip(&selectors) {
+            let message = format!(
+                "`{}::{}` has the same selector as a method of `{}`",
+                struct_name, method.sig.ident, interface_name
+            );
+            selector_checks.push(quote! {
+                assert!(
+                    !eth_riscv_runtime::abi::has_selector(<#interface<eth_riscv_runtime::ReadOnly>>::SELECTORS, #selector),
+                    #message
+                );
+            });
+        }
+    }
+    for (i, (a, a_name)) in interfaces.iter().zip(&interface_names).enumerate() {
+        for (b, b_name) in interfaces.iter().zip(&interface_names).skip(i + 1) {
+            let message = format!("`{}` and `{}` have methods with the same selector", a_name, b_name);
+            selector_checks.push(quote! {
+                assert!(
+                    !eth_riscv_runtime::abi::shares_selector(
+                        <#a<eth_riscv_runtime::ReadOnly>>::SELECTORS,
+                        <#b<eth_riscv_runtime::ReadOnly>>::SELECTORS,
+                    ),
+                    #message
+                );
+            });
+        }
+    }
 
     let emit_helper = quote! {
         #[macro_export]
@@ -429,19 +519,24 @@ pub fn contract(_attr: TokenStream, item: TokenStream) -> TokenStream {
         &public_methods,
         &interface_name,
         None,
-    );
+    )?;
+
+    // Generate the constructor args, exported along with the interface
+    let constructor_args = helpers::generate_constructor_args(struct_name, constructor)?;
 
     // Generate initcode for deployments
-    let deployment_code = helpers::generate_deployment_code(struct_name, constructor);
+    let deployment_code = helpers::generate_deployment_code(struct_name, constructor)?;
 
-    // Generate the complete output with module structure
-    let output = quote! {
+    // Generate the complete output with module structure, split in sections for debugging
+    let prelude = quote! {
         use eth_riscv_runtime::*;
         use alloy_sol_types::SolValue;
+    };
 
-        // Deploy module
+    // Deploy module, exported in `deploy` mode
+    let deployable = quote! {
         #[cfg(feature = "deploy")]
-            pub mod deploy {
+        pub mod deploy {
             use super::*;
             use alloy_sol_types::SolValue;
             use eth_riscv_runtime::*;
@@ -450,15 +545,26 @@ pub fn contract(_attr: TokenStream, item: TokenStream) -> TokenStream {
             #deployment_code
         }
 
-        // Public interface module
+        #[cfg(feature = "deploy")]
+        pub use deploy::*;
+    };
+
+    // Public interface module, always exported when not deploying
+    let interface = quote! {
         #[cfg(not(feature = "deploy"))]
         pub mod interface {
             use super::*;
             #interface
+            #constructor_args
         }
 
-        // Generate the call method implementation privately
-        // only when not in `interface-only` mode
+        #[cfg(not(feature = "deploy"))]
+        pub use interface::*;
+    };
+
+    // Generate the call method implementation privately
+    // only when not in `interface-only` or `deploy` modes
+    let dispatcher = quote! {
         #[cfg(not(any(feature = "deploy", feature = "interface-only")))]
         #[allow(non_local_definitions)]
         #[allow(unused_imports)]
@@ -467,26 +573,32 @@ pub fn contract(_attr: TokenStream, item: TokenStream) -> TokenStream {
             use super::*;
             use alloy_sol_types::SolValue;
             use eth_riscv_runtime::*;
+            use eth_riscv_runtime::__private::InheritedDispatch as _;
 
             #emit_helper
+            #max_gas_section
 
             impl #struct_name { #(#input_methods)* }
-            impl #struct_name { #(#inner_methods)* }
+            impl #struct_name { #(#inner_methods)* #fallback }
+            const _: () = { #(#selector_checks)* };
             impl Contract for #struct_name {
                 fn call(&mut self) {
                     self.call_with_data(&msg_data());
                 }
 
                 fn call_with_data(&mut self, calldata: &[u8]) {
+                    if calldata.len() < 4 {
+                        #no_selector
+                    }
                     let selector = u32::from_be_bytes([calldata[0], calldata[1], calldata[2], calldata[3]]);
                     let calldata = &calldata[4..];
 
                     match selector {
                         #( #match_arms )*
-                        _ => panic!("unknown method"),
+                        _ => #unknown_selector,
                     }
 
-                    return_riscv(0, 0);
+                    eth_riscv_runtime::return_with(&[]);
                 }
             }
 
@@ -494,24 +606,422 @@ pub fn contract(_attr: TokenStream, item: TokenStream) -> TokenStream {
             fn main() -> ! {
                 let mut contract = #struct_name::default();
                 contract.call();
-                eth_riscv_runtime::return_riscv(0, 0)
+                eth_riscv_runtime::return_with(&[])
             }
         }
 
-        // Export initcode when `deploy` mode
-        #[cfg(feature = "deploy")]
-        pub use deploy::*;
+        #[cfg(not(any(feature = "deploy", feature = "interface-only")))]
+        pub use implementation::*;
+    };
 
-        // Always export the interface when not deploying
-        #[cfg(not(feature = "deploy"))]
-        pub use interface::*;
+    debug::dump(
+        &struct_name.to_string(),
+        &[
+            ("prelude", &prelude),
+            ("dispatcher", &dispatcher),
+            ("interface", &interface),
+            ("deployable glue", &deployable),
+        ],
+    );
+
+    let output = quote! {
+        #prelude
+        #deployable
+        #interface
+        #dispatcher
+    };
+
+    Ok(output)
+}
 
-        // Only export contract impl when not in `interface-only` or `deploy` modes
+// Implementation of an `#[interface]` by a contract, whose methods are dispatched along with the
+// ones of the contract (which must list the interface: `#[contract(IERC20)]`):
+//   ```ignore
+//   #[contract_impl]
+//   impl IERC20 for Token {
+//       fn balance_of(&self, owner: Address) -> U256 { ... }
+//   }
+//   ```
+// The interface trait is consumed by `#[interface]`, so the methods are implemented as inherent
+// ones, under the selectors of the interface
+#[proc_macro_attribute]
+pub fn contract_impl(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemImpl);
+    expand_contract_impl(attr.into(), &input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand_contract_impl(
+    attr: proc_macro2::TokenStream,
+    input: &ItemImpl,
+) -> syn::Result<proc_macro2::TokenStream> {
+    no_attr_args(attr, "contract_impl")?;
+    let struct_name = contract_ident(input, "contract_impl")?;
+    let interface = match &input.trait_ {
+        Some((None, path, _)) => path,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.self_ty,
+                "`#[contract_impl]` must be applied to the implementation of an interface: `impl IFoo for Contract`",
+            ))
+        }
+    };
+    let interface_name = &interface
+        .segments
+        .last()
+        .ok_or_else(|| syn::Error::new_spanned(interface, "expected the name of an interface"))?
+        .ident;
+
+    let methods = input
+        .items
+        .iter()
+        .map(|item| match item {
+            ImplItem::Method(method) => {
+                helpers::check_payable_attr(&method.attrs)?;
+                Ok(method)
+            }
+            item => Err(syn::Error::new_spanned(item, "interface implementations can only define methods")),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let mut input_methods = Vec::new();
+    let mut selector_consts = Vec::new();
+    let mut selector_checks = Vec::new();
+    let mut match_arms = Vec::new();
+    let mut budgets = Vec::new();
+    for method in &methods {
+        let mut input_method = helpers::strip_calldata_attrs(method);
+        input_method.vis = syn::parse_quote! { pub };
+        input_methods.push(input_method);
+
+        // Dispatched under the selector of the interface, which must match the signature of the
+        // method, whatever the naming style of the interface
+        let const_name = helpers::selector_const(&method.sig.ident);
+        selector_consts.push(quote! {
+            const #const_name: u32 = <#interface<eth_riscv_runtime::ReadOnly>>::#const_name;
+        });
+
+        let method_info = MethodInfo::from(*method);
+        let selector = u32::from_be_bytes(helpers::generate_fn_selector(&method_info, None)?);
+        let camel_case = u32::from_be_bytes(helpers::generate_fn_selector(
+            &method_info,
+            Some(helpers::InterfaceNamingStyle::CamelCase),
+        )?);
+        let message = format!(
+            "`{}::{}` doesn't match the signature of `{}::{}`",
+            struct_name, method.sig.ident, interface_name, method.sig.ident
+        );
+        selector_checks.push(quote! {
+            assert!(#const_name == #selector || #const_name == #camel_case, #message);
+        });
+
+        match_arms.push(dispatch_arm(method, quote! { #const_name })?);
+        if let Some(max_gas) = helpers::get_max_gas(&method.attrs)? {
+            budgets.push((quote! { #const_name }, max_gas, &method.sig.ident));
+        }
+    }
+    let max_gas_section = helpers::generate_max_gas_section(&budgets)?;
+
+    let method_count = methods.len();
+    let message = format!(
+        "`{}` doesn't implement every method of `{}`",
+        struct_name, interface_name
+    );
+    selector_checks.push(quote! {
+        assert!(<#interface<eth_riscv_runtime::ReadOnly>>::SELECTORS.len() == #method_count, #message);
+    });
+
+    let module_name = format_ident!("__{}_for_{}", interface_name, struct_name);
+    let dispatch_fn = helpers::dispatch_fn(interface_name);
+
+    // Only compiled along with the dispatcher of the contract
+    let implementation = quote! {
         #[cfg(not(any(feature = "deploy", feature = "interface-only")))]
-        pub use implementation::*;
+        #[allow(non_snake_case)]
+        #[allow(non_upper_case_globals)]
+        #[allow(unused_imports)]
+        #[allow(unreachable_code)]
+        mod #module_name {
+            use super::*;
+            use alloy_sol_types::SolValue;
+            use eth_riscv_runtime::*;
+
+            #(#selector_consts)*
+            const _: () = { #(#selector_checks)* };
+            #max_gas_section
+
+            impl #struct_name {
+                #(#input_methods)*
+
+                // Returns if the selector isn't one of the interface
+                #[doc(hidden)]
+                pub fn #dispatch_fn(&mut self, selector: u32, calldata: &[u8]) {
+                    match selector {
+                        #( #match_arms )*
+                        _ => return,
+                    }
+
+                    eth_riscv_runtime::return_with(&[]);
+                }
+            }
+        }
+    };
+
+    debug::dump(
+        &format!("{}_{}", struct_name, interface_name),
+        &[("interface implementation", &implementation)],
+    );
+
+    Ok(implementation)
+}
+
+// Methods of a storage struct that other contracts inherit, with `#[storage] #[inherit(ERC20Base)]`:
+//   ```ignore
+//   #[contract_base]
+//   impl ERC20Base {
+//       pub fn balance_of(&self, owner: Address) -> U256 { ... }
+//   }
+//   ```
+// The public methods are dispatched after the ones of the inheriting contract, which shadows them
+// by defining methods with the same selectors (and can still call them, through `Deref`). Unlike
+// the ones of a contract, the methods are always compiled, so that constructors can use them
+#[proc_macro_attribute]
+pub fn contract_base(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as ItemImpl);
+    expand_contract_base(attr.into(), &input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand_contract_base(
+    attr: proc_macro2::TokenStream,
+    input: &ItemImpl,
+) -> syn::Result<proc_macro2::TokenStream> {
+    no_attr_args(attr, "contract_base")?;
+    let struct_name = contract_ident(input, "contract_base")?;
+    if input.trait_.is_some() {
+        return Err(syn::Error::new_spanned(
+            &input.self_ty,
+            "`#[contract_base]` must be applied to the inherent impl block of a struct",
+        ));
+    }
+
+    let mut methods = Vec::new();
+    let mut match_arms = Vec::new();
+    let mut budgets = Vec::new();
+    for item in input.items.iter() {
+        let ImplItem::Method(method) = item else {
+            return Err(syn::Error::new_spanned(item, "contract bases can only define methods"));
+        };
+        helpers::check_payable_attr(&method.attrs)?;
+        if method.sig.ident == "fallback" {
+            return Err(syn::Error::new_spanned(
+                &method.sig.ident,
+                "a `#[contract_base]` can't have a `fallback`, define it on the inheriting contract",
+            ));
+        }
+        methods.push(helpers::strip_calldata_attrs(method));
+
+        let dispatched = matches!(method.vis, syn::Visibility::Public(_)) && method.sig.ident != "new";
+        let max_gas = helpers::get_max_gas(&method.attrs)?;
+        if !dispatched {
+            if max_gas.is_some() {
+                return Err(syn::Error::new_spanned(
+                    &method.sig.ident,
+                    "`#[max_gas]` only applies to the public methods of a contract",
+                ));
+            }
+            continue;
+        }
+
+        let selector = u32::from_be_bytes(helpers::generate_fn_selector(&MethodInfo::from(method), None)?);
+        match_arms.push(dispatch_arm(method, quote! { #selector })?);
+        if let Some(max_gas) = max_gas {
+            budgets.push((quote! { #selector }, max_gas, &method.sig.ident));
+        }
+    }
+    let max_gas_section = helpers::generate_max_gas_section(&budgets)?;
+
+    let module_name = format_ident!("__base_{}", struct_name);
+    let output = quote! {
+        #[allow(dead_code)]
+        impl #struct_name { #(#methods)* }
+
+        // Only compiled along with the dispatcher of the inheriting contract
+        #[cfg(not(any(feature = "deploy", feature = "interface-only")))]
+        #[allow(non_snake_case)]
+        #[allow(unused_imports)]
+        #[allow(unreachable_code)]
+        mod #module_name {
+            use super::*;
+            use alloy_sol_types::SolValue;
+            use eth_riscv_runtime::*;
+            use eth_riscv_runtime::__private::InheritedDispatch as _;
+
+            #max_gas_section
+
+            impl #struct_name {
+                // Returns if the selector isn't one of the base, nor of the storage it inherits
+                #[doc(hidden)]
+                pub fn __dispatch_base(&mut self, selector: u32, calldata: &[u8]) {
+                    match selector {
+                        #( #match_arms )*
+                        _ => return Self::__dispatch_inherited(self, selector, calldata),
+                    }
+
+                    eth_riscv_runtime::return_with(&[]);
+                }
+            }
+        }
     };
 
-    TokenStream::from(output)
+    debug::dump(&struct_name.to_string(), &[("contract base", &output)]);
+
+    Ok(output)
+}
+
+// Match arm of a dispatcher, which decodes the args of `method`, calls it and returns its encoded
+// output when the selector matches `pattern`
+fn dispatch_arm(
+    method: &ImplItemMethod,
+    pattern: proc_macro2::TokenStream,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let method_name = &method.sig.ident;
+    let method_info = MethodInfo::from(method);
+    let (arg_names, arg_types) = helpers::get_arg_props_skip_first(&method_info)?;
+    let streamed = helpers::get_calldata_args(method)?;
+
+    let is_static = arg_types
+        .iter()
+        .map(|ty| helpers::sol_type(ty))
+        .collect::<syn::Result<Vec<_>>>()?
+        .iter()
+        .all(helpers::is_fixed_size);
+
+    let decode_args = if is_static {
+        // Fixed-size args are decoded in place, without allocating
+        quote! {
+            let (#( #arg_names ),*) = match eth_riscv_runtime::abi::decode_static::<(#( #arg_types ),*)>(calldata) {
+                Some(args) => args,
+                None => eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::CALLDATA_DECODE_ERROR),
+            };
+        }
+    } else if !streamed.contains(&true) {
+        quote! {
+            let (#( #arg_names ),*) = match <(#( #arg_types ),*)>::abi_decode(calldata, true) {
+                Ok(args) => args,
+                Err(_) => eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::CALLDATA_DECODE_ERROR),
+            };
+        }
+    } else {
+        // `#[calldata]` args are decoded as their head, i.e. the offset of their encoding,
+        // which their reader is built from. As their data isn't decoded, the args can't be
+        // validated against their re-encoding. Several args are encoded as a (dynamic) tuple,
+        // behind an offset, unlike a single one
+        let params_start: u64 = if arg_names.len() > 1 { 32 } else { 0 };
+        let calldata_start = 4 + params_start;
+        let head_types = arg_types.iter().zip(&streamed).map(|(ty, is_streamed)| {
+            if *is_streamed {
+                quote! { alloy_core::primitives::U256 }
+            } else {
+                quote! { #ty }
+            }
+        });
+        let readers = arg_names.iter().zip(&streamed).filter(|(_, is_streamed)| **is_streamed).map(|(name, _)| quote! {
+            let #name = match eth_riscv_runtime::CalldataReader::from_param(#calldata_start, #name) {
+                Some(reader) => reader,
+                None => eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::CALLDATA_DECODE_ERROR),
+            };
+        });
+        quote! {
+            let params = calldata.get(#params_start as usize..).unwrap_or_default();
+            let (#( #arg_names, )*) = match <(#( #head_types, )*)>::abi_decode_params(params, false) {
+                Ok(args) => args,
+                Err(_) => eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::CALLDATA_DECODE_ERROR),
+            };
+            #( #readers )*
+        }
+    };
+
+    // Check if there are payable methods
+    let checks = if !is_payable(method) {
+        quote! {
+            if eth_riscv_runtime::msg_value() > U256::from(0) {
+                eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::NON_PAYABLE_ERROR);
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Check if the method has a return type
+    let return_handling = match &method.sig.output {
+        ReturnType::Default => {
+            // No return value
+            quote! { self.#method_name(#( #arg_names ),*); }
+        }
+       ReturnType::Type(_,_) => {
+            match helpers::extract_wrapper_types(&method.sig.output) {
+                helpers::WrapperType::Result(ok_type, _) => {
+                    let return_success = return_encoded(quote! { success }, &ok_type);
+                    quote! {
+                    let res = self.#method_name(#( #arg_names ),*);
+                    match res {
+                        Ok(success) => {
+                            #return_success
+                        }
+                        Err(err) => {
+                            eth_riscv_runtime::revert_with(&err.abi_encode());
+                        }
+                    }
+                }},
+                helpers::WrapperType::Option(inner_type) => {
+                    let return_success = return_encoded(quote! { success }, &inner_type);
+                    quote! {
+                    match self.#method_name(#( #arg_names ),*) {
+                        Some(success) => {
+                            #return_success
+                        },
+                        None => eth_riscv_runtime::revert(),
+                    }
+                }},
+                helpers::WrapperType::None => {
+                    let ReturnType::Type(_, ty) = &method.sig.output else { unreachable!() };
+                    let return_result = return_encoded(quote! { result }, &quote! { #ty });
+                    quote! {
+                    let result = self.#method_name(#( #arg_names ),*);
+                    #return_result
+                }}
+            }
+        }
+    };
+
+    Ok(quote! {
+        #pattern => {
+            #decode_args
+            #checks
+            #return_handling
+        }
+    })
+}
+
+// Returns `value`, of type `ty`, ABI-encoded. Fixed-size values are encoded in a stack buffer, while
+// the rest fall back to `abi_encode`
+fn return_encoded(
+    value: proc_macro2::TokenStream,
+    ty: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let is_static = syn::parse2::<syn::Type>(ty.clone()).is_ok_and(|ty| helpers::is_static_abi(&ty));
+    if !is_static {
+        return quote! { eth_riscv_runtime::return_with(&#value.abi_encode()); };
+    }
+
+    quote! {
+        let mut output = [0u8; <#ty as eth_riscv_runtime::abi::StaticAbi>::SIZE];
+        eth_riscv_runtime::abi::StaticAbi::encode_to(&#value, &mut output);
+        eth_riscv_runtime::return_with(&output);
+    }
 }
 
 // Empty macro to mark a method as payable
@@ -520,89 +1030,360 @@ pub fn payable(_attr: TokenStream, item: TokenStream) -> TokenStream {
     item
 }
 
+// Empty macro to set the gas budget of a method, e.g. `#[max_gas(60_000)]`, which the calls to
+// it are checked against by the r55 test harness
+#[proc_macro_attribute]
+pub fn max_gas(_attr: TokenStream, item: TokenStream) -> TokenStream {
+    item
+}
+
 // Check if a method is tagged with the payable attribute
 fn is_payable(method: &syn::ImplItemMethod) -> bool {
-    method.attrs.iter().any(|attr| {
-        if let Ok(syn::Meta::Path(path)) = attr.parse_meta() {
-            if let Some(segment) = path.segments.first() {
-                return segment.ident == "payable";
-            }
-        }
-        false
-    })
+    helpers::has_payable_attr(&method.attrs)
 }
 
 #[proc_macro_attribute]
 pub fn interface(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = parse_macro_input!(item as ItemTrait);
     let args = parse_macro_input!(attr as InterfaceArgs);
+    expand_interface(&input, args)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand_interface(
+    input: &ItemTrait,
+    args: InterfaceArgs,
+) -> syn::Result<proc_macro2::TokenStream> {
     let trait_name = &input.ident;
 
-    let methods: Vec<_> = input
+    let methods = input
         .items
         .iter()
-        .map(|item| {
-            if let TraitItem::Method(method) = item {
-                method
-            } else {
-                panic!("Expected methods arguments")
-            }
+        .map(|item| match item {
+            TraitItem::Method(method) => Ok(method),
+            item => Err(syn::Error::new_spanned(item, "interfaces can only declare methods")),
         })
-        .collect();
+        .collect::<syn::Result<Vec<_>>>()?;
 
     // Generate intreface implementation
-    let interface = helpers::generate_interface(&methods, trait_name, args.rename);
-    let output = quote! { #interface };
+    let interface = helpers::generate_interface(&methods, trait_name, args.rename)?;
+    debug::dump(&trait_name.to_string(), &[("interface", &interface)]);
 
-    TokenStream::from(output)
+    Ok(quote! { #interface })
 }
 
 #[proc_macro_attribute]
-pub fn storage(_attr: TokenStream, input: TokenStream) -> TokenStream {
+pub fn storage(attr: TokenStream, input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
+    expand_storage(attr.into(), &input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand_storage(
+    attr: proc_macro2::TokenStream,
+    input: &DeriveInput,
+) -> syn::Result<proc_macro2::TokenStream> {
+    no_attr_args(attr, "storage")?;
     let name = &input.ident;
     let vis = &input.vis;
+    let base = helpers::get_inherit(&input.attrs)?;
 
     let fields = match &input.data {
         Data::Struct(data) => match &data.fields {
             Fields::Named(fields) => &fields.named,
+            fields if base.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "a `#[storage]` struct that inherits another must have named fields",
+                ))
+            }
             _ => {
                 let output = quote! {
                     #vis struct #name;
                     impl #name { pub fn new() -> Self { Self {} } }
                 };
-                return TokenStream::from(output);
+                return Ok(output);
             }
         },
-        _ => panic!("Storage derive only works on structs"),
+        _ => return Err(syn::Error::new_spanned(name, "`#[storage]` can only be applied to structs")),
     };
 
-    // Generate the struct definition with the same fields
+    // Generate the struct definition with the same fields, after the inherited storage if any
+    let base_field = base.as_ref().map(|base| quote! { pub __base: #base, });
     let struct_fields = fields.iter().map(|f| {
         let name = &f.ident;
         let ty = &f.ty;
         quote! { pub #name: #ty }
     });
 
-    // Generate initialization code for each field
+    // Generate initialization code for each field. Fields pinned with `#[slot(..)]` use that slot,
+    // gaps reserve the next `#[gap(..)]` slots, while the others are laid out sequentially (pinned
+    // fields don't shift them), after the slots of the inherited storage
     // TODO: PoC uses a naive strategy. Enhance to support complex types like tuples or custom structs.
-    let init_fields = fields.iter().enumerate().map(|(i, f)| {
+    let base_slots = base.as_ref().map(|base| quote! { <#base>::__STORAGE_SLOTS + });
+    let sequential = |slot: u64| quote! { #base_slots #slot, 0, 0, 0 };
+    let base_init = base.as_ref().map(|base| quote! { __base: <#base as Default>::default(), });
+    let mut next_slot = 0u64;
+    let init_fields = fields.iter().map(|f| {
         let name = &f.ident;
-        let slot = U256::from(i);
-        let [limb0, limb1, limb2, limb3] = slot.as_limbs();
-        quote! { #name: StorageLayout::allocate(#limb0, #limb1, #limb2, #limb3) }
+        if let (Some(_), Some(attr)) = (
+            helpers::get_gap_size(&f.attrs)?,
+            f.attrs.iter().find(|attr| attr.path.is_ident("init")),
+        ) {
+            return Err(syn::Error::new_spanned(attr, "a `#[gap]` can't have an initial value"));
+        }
+        let slot = match (helpers::get_pinned_slot(&f.attrs)?, helpers::get_gap_size(&f.attrs)?) {
+            (Some(_), Some(_)) => {
+                return Err(syn::Error::new_spanned(name, "a `#[gap]` can't be pinned to a slot"))
+            }
+            (Some(slot), None) => {
+                let [limb0, limb1, limb2, limb3] = slot.as_limbs();
+                quote! { #limb0, #limb1, #limb2, #limb3 }
+            }
+            (None, Some(size)) => {
+                next_slot += size;
+                sequential(next_slot - size)
+            }
+            (None, None) => {
+                next_slot += 1;
+                sequential(next_slot - 1)
+            }
+        };
+        Ok(quote! {
+            #name: eth_riscv_runtime::types::StorageLayout::allocate(#slot)
+        })
+    }).collect::<syn::Result<Vec<_>>>()?;
+
+    // `#[init(..)]` defaults, written by the deploy code before the constructor runs (the inherited
+    // ones first). Every field gets a hidden method, deprecated when it has a default, so that a
+    // constructor writing to it warns about overriding the default
+    let mut init_writes: Vec<_> = base
+        .iter()
+        .map(|base| quote! { <#base>::__init_defaults(); })
+        .collect();
+    let mut init_consts = Vec::new();
+    for f in fields.iter() {
+        let Some(name) = &f.ident else { continue };
+        let init_const = helpers::init_const(name);
+        let deprecated = match helpers::get_init(&f.attrs)? {
+            Some(value) => {
+                init_writes.push(quote! {
+                    eth_riscv_runtime::types::DirectStorage::write(&mut storage.#name, #value);
+                });
+                let note = format!("`{}` has an `#[init]` default, which the constructor overrides", name);
+                Some(quote! { #[deprecated(note = #note)] })
+            }
+            None => None,
+        };
+        init_consts.push(quote! {
+            #[doc(hidden)]
+            #deprecated
+            pub fn #init_const(&self) {}
+        });
+    }
+
+    // Exported along with the layout, to tell the versions of an upgradeable contract apart
+    let version = helpers::get_storage_version(&input.attrs)?.map(|version| {
+        quote! {
+            impl #name { pub const STORAGE_VERSION: u64 = #version; }
+        }
+    });
+
+    // The inherited storage is reachable through the struct, and so are its `#[contract_base]`
+    // methods, which handle the selectors that the contract doesn't
+    let inherit = base.as_ref().map(|base| {
+        quote! {
+            impl core::ops::Deref for #name {
+                type Target = #base;
+                fn deref(&self) -> &#base { &self.__base }
+            }
+            impl core::ops::DerefMut for #name {
+                fn deref_mut(&mut self) -> &mut #base { &mut self.__base }
+            }
+
+            #[cfg(not(any(feature = "deploy", feature = "interface-only")))]
+            impl #name {
+                #[doc(hidden)]
+                pub fn __dispatch_inherited(&mut self, selector: u32, calldata: &[u8]) {
+                    self.__base.__dispatch_base(selector, calldata);
+                }
+            }
+        }
     });
 
     let expanded = quote! {
-        #vis struct #name { #(#struct_fields,)* }
+        #vis struct #name { #base_field #(#struct_fields,)* }
+        #version
+        #inherit
 
         impl Default for #name {
             fn default() -> Self {
-                Self { #(#init_fields,)* }
+                Self { #base_init #(#init_fields,)* }
+            }
+        }
+        impl #name {
+            pub fn address(&self) -> alloy_core::primitives::Address { eth_riscv_runtime::this() }
+
+            // Slots laid out sequentially, which the storage inheriting this one starts after
+            #[doc(hidden)]
+            pub const __STORAGE_SLOTS: u64 = #base_slots #next_slot;
+
+            #[doc(hidden)]
+            #[allow(unused_mut, unused_variables)]
+            pub fn __init_defaults() {
+                let mut storage = Self::default();
+                #(#init_writes)*
+            }
+            #(#init_consts)*
+        }
+    };
+
+    Ok(expanded)
+}
+
+// Turns a unit struct into a transparent upgradeable proxy, which keeps its implementation and
+// admin in the EIP-1967 slots. Calls are routed on their sender, so that the methods of the proxy
+// never shadow the ones of the implementation: every call from an account other than the admin is
+// delegated to the implementation, while the admin can only call the methods of the proxy:
+// * `implementation()` and `admin()`
+// * `upgrade_to(new_implementation)`, which emits `Upgraded(address)`
+//
+// The proxy is deployed with `new(implementation, admin)`. The implementation's constructor never
+// runs on the proxy's storage, so implementations that need to be initialized must expose a method
+// for it, which the admin can't call.
+#[proc_macro_attribute]
+pub fn proxy(attr: TokenStream, input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_proxy(attr.into(), &input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+fn expand_proxy(
+    attr: proc_macro2::TokenStream,
+    input: &DeriveInput,
+) -> syn::Result<proc_macro2::TokenStream> {
+    no_attr_args(attr, "proxy")?;
+    let name = &input.ident;
+    let vis = &input.vis;
+
+    match &input.data {
+        Data::Struct(data) if matches!(data.fields, Fields::Unit) => {}
+        Data::Struct(data) => {
+            return Err(syn::Error::new_spanned(
+                &data.fields,
+                "`#[proxy]` expects a unit struct, as its storage lives in the EIP-1967 slots",
+            ))
+        }
+        _ => return Err(syn::Error::new_spanned(name, "`#[proxy]` expects a unit struct")),
+    }
+
+    // EIP-1967 slots are the hash of a label minus one, so that their preimage is unknown
+    let eip1967_slot = |label: &str| {
+        let slot = U256::from_be_bytes(keccak256(label).0) - U256::from(1);
+        syn::LitInt::new(&format!("{:#x}", slot), proc_macro2::Span::call_site())
+    };
+    let implementation_slot = eip1967_slot("eip1967.proxy.implementation");
+    let admin_slot = eip1967_slot("eip1967.proxy.admin");
+    let upgraded_topic = keccak256("Upgraded(address)").0;
+    let selector = |signature: &str| {
+        let selector = &keccak256(signature)[..4];
+        quote! { [#(#selector),*] }
+    };
+    let implementation_selector = selector("implementation()");
+    let admin_selector = selector("admin()");
+    let upgrade_to_selector = selector("upgrade_to(address)");
+
+    let output = quote! {
+        #[contract_derive::storage]
+        #vis struct #name {
+            #[slot(#implementation_slot)]
+            implementation: eth_riscv_runtime::types::Slot<alloy_core::primitives::Address>,
+            #[slot(#admin_slot)]
+            admin: eth_riscv_runtime::types::Slot<alloy_core::primitives::Address>,
+        }
+
+        #[contract_derive::contract]
+        impl #name {
+            pub fn new(
+                implementation: alloy_core::primitives::Address,
+                admin: alloy_core::primitives::Address,
+            ) -> Self {
+                use eth_riscv_runtime::types::DirectStorage;
+
+                let mut proxy = #name::default();
+                proxy.implementation.write(implementation);
+                proxy.admin.write(admin);
+                proxy
+            }
+
+            fn upgrade_to(&mut self, new_implementation: alloy_core::primitives::Address) {
+                use eth_riscv_runtime::types::DirectStorage;
+
+                if eth_riscv_runtime::ext_code_size(new_implementation) == 0 {
+                    eth_riscv_runtime::revert_with_reason("Implementation without code");
+                }
+
+                self.implementation.write(new_implementation);
+                eth_riscv_runtime::emit_log(
+                    &[],
+                    &[
+                        alloy_core::primitives::B256::new([#(#upgraded_topic),*]),
+                        new_implementation.into_word(),
+                    ],
+                );
+            }
+
+            // Delegates the calls of any account but the admin to the implementation, and passes
+            // their output (or revert data) through. The admin's are dispatched to the methods of
+            // the proxy instead
+            #[contract_derive::payable]
+            fn fallback(&mut self) {
+                use eth_riscv_runtime::{abi::StaticAbi, types::DirectStorage};
+
+                let admin = self.admin.read();
+                if eth_riscv_runtime::msg_sender() != admin {
+                    match eth_riscv_runtime::try_delegatecall_contract(
+                        self.implementation.read(),
+                        eth_riscv_runtime::msg_data(),
+                        None,
+                    ) {
+                        Ok(output) => eth_riscv_runtime::return_with(&output),
+                        Err(revert_data) => eth_riscv_runtime::revert_with(&revert_data),
+                    }
+                }
+
+                if eth_riscv_runtime::msg_value() > alloy_core::primitives::U256::ZERO {
+                    eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::NON_PAYABLE_ERROR);
+                }
+                let calldata = eth_riscv_runtime::msg_data();
+                let (selector, args) = match calldata.split_first_chunk::<4>() {
+                    Some(split) => split,
+                    None => eth_riscv_runtime::revert_with_reason("Admin can't call the implementation"),
+                };
+                let mut output = [0u8; 32];
+                match *selector {
+                    #implementation_selector if args.is_empty() => {
+                        self.implementation.read().encode_to(&mut output)
+                    }
+                    #admin_selector if args.is_empty() => admin.encode_to(&mut output),
+                    #upgrade_to_selector => {
+                        let Some((new_implementation,)) =
+                            eth_riscv_runtime::abi::decode_static::<(alloy_core::primitives::Address,)>(args)
+                        else {
+                            eth_riscv_runtime::revert_with_reason(eth_riscv_runtime::error::CALLDATA_DECODE_ERROR);
+                        };
+                        self.upgrade_to(new_implementation);
+                        true.encode_to(&mut output)
+                    }
+                    _ => eth_riscv_runtime::revert_with_reason("Admin can't call the implementation"),
+                }
+                eth_riscv_runtime::return_with(&output);
             }
         }
-        impl #name { pub fn address(&self) -> Address { eth_riscv_runtime::this() } }
     };
 
-    TokenStream::from(expanded)
+    Ok(output)
 }
@@ -0,0 +1,7 @@
+// Locks in the diagnostics of the macros, which should point at the offending item.
+// Run with `TRYBUILD=overwrite cargo test --test ui` to update the expected outputs.
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,13 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Token;
+
+#[contract("erc20")]
+impl Token {
+    pub fn decimals(&self) -> u8 {
+        18
+    }
+}
+
+fn main() {}
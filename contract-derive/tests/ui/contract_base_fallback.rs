@@ -0,0 +1,15 @@
+use contract_derive::contract_base;
+
+#[derive(Default)]
+pub struct TokenBase;
+
+#[contract_base]
+impl TokenBase {
+    pub fn decimals(&self) -> u8 {
+        18
+    }
+
+    fn fallback(&mut self) {}
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Verifier;
+
+#[contract]
+impl Verifier {
+    pub fn verify(&self, #[calldata] proof: Vec<u8>) -> bool {
+        proof.is_empty()
+    }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Token;
+
+#[contract]
+impl Token {
+    pub fn new(#[default(18)] decimals: u64, owner: u64) -> Self {
+        let _ = (decimals, owner);
+        Token
+    }
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Vault;
+
+#[contract]
+impl Vault {
+    fn fallback(&mut self, data: u64) {}
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use contract_derive::contract_impl;
+
+#[derive(Default)]
+pub struct Token;
+
+#[contract_impl]
+impl IERC20Metadata for Token {
+    const DECIMALS: u8 = 18;
+}
+
+fn main() {}
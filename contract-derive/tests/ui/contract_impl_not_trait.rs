@@ -0,0 +1,13 @@
+use contract_derive::contract_impl;
+
+#[derive(Default)]
+pub struct Token;
+
+#[contract_impl]
+impl Token {
+    fn decimals(&self) -> u8 {
+        18
+    }
+}
+
+fn main() {}
@@ -0,0 +1,14 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Token;
+
+#[contract]
+impl Token {
+    #[contract_derive::max_gas("a lot")]
+    pub fn transfer(&mut self, amount: u64) {
+        let _ = amount;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Token;
+
+#[contract]
+impl Token {
+    pub fn transfer(&mut self, amount: u64) {
+        self.move_balance(amount);
+    }
+
+    #[contract_derive::max_gas(10_000)]
+    fn move_balance(&mut self, amount: u64) {
+        let _ = amount;
+    }
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Token;
+
+#[contract]
+impl Token {
+    pub fn total_supply(amount: u64) -> u64 {
+        amount
+    }
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Vault;
+
+#[contract]
+impl Vault {
+    #[payable(true)]
+    pub fn deposit(&mut self) {}
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use contract_derive::contract;
+
+#[derive(Default)]
+pub struct Token;
+
+#[contract]
+impl Token {
+    pub fn scale(&self, factor: f32) -> bool {
+        factor > 1.0
+    }
+}
+
+fn main() {}
@@ -0,0 +1,6 @@
+use contract_derive::Error;
+
+#[derive(Error)]
+pub struct TokenError;
+
+fn main() {}
@@ -0,0 +1,8 @@
+use contract_derive::Error;
+
+#[derive(Error)]
+pub enum TokenError {
+    InvalidRate(f64),
+}
+
+fn main() {}
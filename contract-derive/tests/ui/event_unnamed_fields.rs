@@ -0,0 +1,6 @@
+use contract_derive::Event;
+
+#[derive(Event)]
+pub struct Transfer(u64);
+
+fn main() {}
@@ -0,0 +1,8 @@
+use contract_derive::interface;
+
+#[interface(snake_case)]
+trait IToken {
+    fn balance_of(&self, owner: u64) -> u64;
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use contract_derive::interface;
+
+#[interface]
+trait IToken {
+    fn total_supply() -> u64;
+}
+
+fn main() {}
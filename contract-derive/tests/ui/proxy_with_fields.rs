@@ -0,0 +1,8 @@
+use contract_derive::proxy;
+
+#[proxy]
+pub struct Proxy {
+    owner: u64,
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use contract_derive::storage;
+
+#[storage]
+pub struct Counter {
+    count: u64,
+    #[gap(0)]
+    __gap: u64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use contract_derive::storage;
+
+#[storage]
+#[inherit]
+pub struct FeeToken {
+    fee_bps: u64,
+}
+
+fn main() {}
@@ -0,0 +1,12 @@
+use contract_derive::storage;
+
+#[storage]
+pub struct Counter {
+    #[init(1)]
+    count: u64,
+    #[init(0)]
+    #[gap(10)]
+    __gap: u64,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use contract_derive::storage;
+
+#[storage]
+pub struct Counter {
+    #[slot("count")]
+    count: u64,
+}
+
+fn main() {}
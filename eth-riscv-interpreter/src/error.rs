@@ -9,4 +9,10 @@ pub enum Error {
     /// [`goblin`] crate error representation
     #[error(transparent)]
     GoblinError(#[from] goblin::error::Error),
+    /// A loadable segment is outside of the ELF, or doesn't fit the interpreter RAM
+    #[error("Invalid ELF segment")]
+    InvalidSegment,
+    /// The call data doesn't fit the calldata region
+    #[error("Call data too large: {0} bytes")]
+    CalldataTooLarge(usize),
 }
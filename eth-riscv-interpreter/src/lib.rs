@@ -3,50 +3,84 @@ use rvemu::{bus::DRAM_BASE, dram::DRAM_SIZE, emulator::Emulator};
 mod error;
 pub use error::{Error, Result};
 
-pub fn setup_from_elf(elf_data: &[u8], call_data: &[u8]) -> Result<Emulator> {
-    let elf = goblin::elf::Elf::parse(elf_data)?;
+// Region at the start of the DRAM that holds the call data, prefixed by its length
+const CALLDATA_REGION: usize = 1024 * 1024;
+
+/// Largest call data a program can be instantiated with, which fits the calldata region along with
+/// its length.
+pub const MAX_CALLDATA_SIZE: usize = CALLDATA_REGION - 8;
+
+/// Program loaded from an ELF: the initial image of the memory (with an empty calldata region) and
+/// its entry point, which emulators are instantiated from without parsing the ELF again.
+#[derive(Debug, Clone)]
+pub struct Program {
+    image: Vec<u8>,
+    entry: u64,
+}
 
-    // Allocate 1MB for the call data
-    let mut mem = vec![0; 1024 * 1024];
-    {
-        assert!(call_data.len() < mem.len() - 8);
+impl Program {
+    pub fn load(elf_data: &[u8]) -> Result<Self> {
+        let elf = goblin::elf::Elf::parse(elf_data)?;
+        let mut image = vec![0; CALLDATA_REGION];
+        load_sections(&mut image, &elf, elf_data)?;
 
-        let (size_bytes, data_bytes) = mem.split_at_mut(8);
-        size_bytes.copy_from_slice(&(call_data.len() as u64).to_le_bytes());
-        data_bytes[..call_data.len()].copy_from_slice(call_data);
+        Ok(Self {
+            image,
+            entry: elf.header.e_entry,
+        })
     }
 
-    load_sections(&mut mem, &elf, elf_data);
+    /// Emulator running the program with `call_data`. Its memory is initialized from the image,
+    /// so nothing written by previous instances (e.g. to their heap) is visible to it. Fails if
+    /// `call_data` is longer than `MAX_CALLDATA_SIZE`.
+    pub fn instantiate(&self, call_data: &[u8]) -> Result<Emulator> {
+        if call_data.len() > MAX_CALLDATA_SIZE {
+            return Err(Error::CalldataTooLarge(call_data.len()));
+        }
 
-    let mut emu = Emulator::new();
+        let mut mem = self.image.clone();
+        let (size_bytes, data_bytes) = mem.split_at_mut(8);
+        size_bytes.copy_from_slice(&(call_data.len() as u64).to_le_bytes());
+        data_bytes[..call_data.len()].copy_from_slice(call_data);
 
-    emu.initialize_dram(mem);
-    emu.initialize_pc(elf.header.e_entry);
+        let mut emu = Emulator::new();
+        emu.initialize_dram(mem);
+        emu.initialize_pc(self.entry);
+        Ok(emu)
+    }
+}
 
-    Ok(emu)
+pub fn setup_from_elf(elf_data: &[u8], call_data: &[u8]) -> Result<Emulator> {
+    Program::load(elf_data)?.instantiate(call_data)
 }
 
-fn load_sections(mem: &mut Vec<u8>, elf: &goblin::elf::Elf, elf_data: &[u8]) {
+fn load_sections(mem: &mut Vec<u8>, elf: &goblin::elf::Elf, elf_data: &[u8]) -> Result<()> {
     for ph in &elf.program_headers {
         if ph.p_type == goblin::elf::program_header::PT_LOAD {
-            // The interpreter RAM is DRAM_SIZE starting at DRAM_BASE
-            assert!(ph.p_vaddr >= DRAM_BASE);
-            assert!(ph.p_memsz <= DRAM_SIZE);
-
-            let start_vec = (ph.p_vaddr - DRAM_BASE) as usize;
-            let start_offset = ph.p_offset as usize;
-
+            // The interpreter RAM is DRAM_SIZE starting at DRAM_BASE, and the segment data must be
+            // within the ELF and fit the memory it's loaded to
+            let start_vec = ph
+                .p_vaddr
+                .checked_sub(DRAM_BASE)
+                .filter(|start| start.saturating_add(ph.p_memsz) <= DRAM_SIZE)
+                .ok_or(Error::InvalidSegment)? as usize;
             let end_vec = start_vec + ph.p_memsz as usize;
+            let data = usize::try_from(ph.p_offset)
+                .ok()
+                .and_then(|start| elf_data.get(start..start.checked_add(ph.p_filesz as usize)?))
+                .filter(|data| data.len() as u64 <= ph.p_memsz)
+                .ok_or(Error::InvalidSegment)?;
+
             if mem.len() < end_vec {
                 mem.resize(end_vec, 0);
             }
 
             // The data available to copy may be smaller than the required size
-            let size_to_copy = ph.p_filesz as usize;
-            mem[start_vec..(start_vec + size_to_copy)]
-                .copy_from_slice(&elf_data[start_offset..(start_offset + size_to_copy)]);
+            mem[start_vec..(start_vec + data.len())].copy_from_slice(data);
         }
     }
+
+    Ok(())
 }
 
 #[cfg(test)]
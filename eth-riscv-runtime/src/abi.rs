@@ -0,0 +1,177 @@
+//! ABI decoding and encoding of fixed-size values in place, which the generated dispatchers use to
+//! handle the arguments and return values of most methods without allocating, along with the
+//! compile-time checks of their selectors.
+
+use alloy_core::primitives::{Address, FixedBytes, I256, U256};
+use alloy_sol_types::{abi, SolType, SolValue};
+
+/// Decodes fixed-size values, reading them in place.
+///
+/// Same as `T::abi_decode(data, true)`, without the re-encoding (and allocation) of its validation:
+/// type-checked values are encoded canonically, so it is enough for `data` to have the size of
+/// their encoding. Returns `None` for dynamic types.
+pub fn decode_static<T>(data: &[u8]) -> Option<T>
+where
+    T: SolValue + From<<T::SolType as SolType>::RustType>,
+{
+    if T::SolType::ENCODED_SIZE != Some(data.len()) {
+        return None;
+    }
+
+    let token = abi::decode::<<T::SolType as SolType>::Token<'_>>(data, false).ok()?;
+    T::SolType::type_check(&token).ok()?;
+    Some(T::SolType::detokenize(token).into())
+}
+
+/// Values whose ABI encoding has a fixed size, and can be written to a buffer on the stack.
+pub trait StaticAbi {
+    /// Size of the encoding, in bytes.
+    const SIZE: usize;
+
+    /// Writes the encoding of the value to `out`, which must be `SIZE` bytes long.
+    fn encode_to(&self, out: &mut [u8]);
+}
+
+impl StaticAbi for () {
+    const SIZE: usize = 0;
+
+    fn encode_to(&self, _out: &mut [u8]) {}
+}
+
+impl StaticAbi for bool {
+    const SIZE: usize = 32;
+
+    fn encode_to(&self, out: &mut [u8]) {
+        out.fill(0);
+        out[31] = *self as u8;
+    }
+}
+
+impl StaticAbi for Address {
+    const SIZE: usize = 32;
+
+    fn encode_to(&self, out: &mut [u8]) {
+        out[..12].fill(0);
+        out[12..].copy_from_slice(self.as_slice());
+    }
+}
+
+// Left-aligned, padded with zeroes
+impl<const N: usize> StaticAbi for FixedBytes<N> {
+    const SIZE: usize = 32;
+
+    fn encode_to(&self, out: &mut [u8]) {
+        out[..N].copy_from_slice(self.as_slice());
+        out[N..].fill(0);
+    }
+}
+
+impl StaticAbi for U256 {
+    const SIZE: usize = 32;
+
+    fn encode_to(&self, out: &mut [u8]) {
+        out.copy_from_slice(&self.to_be_bytes::<32>());
+    }
+}
+
+// Two's complement
+impl StaticAbi for I256 {
+    const SIZE: usize = 32;
+
+    fn encode_to(&self, out: &mut [u8]) {
+        out.copy_from_slice(&self.to_be_bytes::<32>());
+    }
+}
+
+// Right-aligned, as a big-endian word
+macro_rules! impl_static_abi_uint {
+    ($($ty:ty),*) => {
+        $(
+            impl StaticAbi for $ty {
+                const SIZE: usize = 32;
+
+                fn encode_to(&self, out: &mut [u8]) {
+                    let bytes = self.to_be_bytes();
+                    out[..32 - bytes.len()].fill(0);
+                    out[32 - bytes.len()..].copy_from_slice(&bytes);
+                }
+            }
+        )*
+    };
+}
+
+// Sign-extended to a whole word
+macro_rules! impl_static_abi_int {
+    ($($ty:ty),*) => {
+        $(
+            impl StaticAbi for $ty {
+                const SIZE: usize = 32;
+
+                fn encode_to(&self, out: &mut [u8]) {
+                    let bytes = self.to_be_bytes();
+                    out[..32 - bytes.len()].fill(if *self < 0 { 0xff } else { 0 });
+                    out[32 - bytes.len()..].copy_from_slice(&bytes);
+                }
+            }
+        )*
+    };
+}
+
+impl_static_abi_uint!(u16, u32, u64, u128);
+impl_static_abi_int!(i8, i16, i32, i64, i128);
+
+// The members of a fixed-size tuple are encoded one after the other
+macro_rules! impl_static_abi_tuple {
+    ($($ty:ident),+) => {
+        impl<$($ty: StaticAbi),+> StaticAbi for ($($ty,)+) {
+            const SIZE: usize = 0 $(+ $ty::SIZE)+;
+
+            #[allow(non_snake_case)]
+            fn encode_to(&self, out: &mut [u8]) {
+                let ($($ty,)+) = self;
+                let mut offset = 0;
+                $(
+                    $ty.encode_to(&mut out[offset..offset + <$ty as StaticAbi>::SIZE]);
+                    offset += <$ty as StaticAbi>::SIZE;
+                )+
+                let _ = offset;
+            }
+        }
+    };
+}
+
+impl_static_abi_tuple!(A);
+impl_static_abi_tuple!(A, B);
+impl_static_abi_tuple!(A, B, C);
+impl_static_abi_tuple!(A, B, C, D);
+impl_static_abi_tuple!(A, B, C, D, E);
+impl_static_abi_tuple!(A, B, C, D, E, F);
+impl_static_abi_tuple!(A, B, C, D, E, F, G);
+impl_static_abi_tuple!(A, B, C, D, E, F, G, H);
+
+/// Whether `selectors` contains `selector`. Used by the generated dispatchers to check, at compile
+/// time, that the methods of a contract don't collide with the ones of the interfaces it implements.
+pub const fn has_selector(selectors: &[u32], selector: u32) -> bool {
+    let mut i = 0;
+    while i < selectors.len() {
+        if selectors[i] == selector {
+            return true;
+        }
+        i += 1;
+    }
+
+    false
+}
+
+/// Whether two sets of selectors have a selector in common.
+pub const fn shares_selector(a: &[u32], b: &[u32]) -> bool {
+    let mut i = 0;
+    while i < a.len() {
+        if has_selector(b, a[i]) {
+            return true;
+        }
+        i += 1;
+    }
+
+    false
+}
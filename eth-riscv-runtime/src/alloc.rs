@@ -1,6 +1,10 @@
-//! A very simple global allocator.
+//! A small global allocator.
 //!
-//! Allocates on a global array and never deallocates.
+//! Allocates on a global array by bumping a pointer. Freed blocks at the top of the heap move the
+//! pointer back down, while the rest are kept in an address ordered free list, where adjacent
+//! blocks are merged and which later allocations draw from.
+//! Blocks are aligned and sized to multiples of `MIN_BLOCK`, so that any freed block can hold a
+//! free list node.
 
 use core::{
     alloc::{GlobalAlloc, Layout},
@@ -8,65 +12,208 @@ use core::{
     ptr::{self, addr_of},
 };
 
+const MIN_BLOCK: usize = 16;
+
+struct FreeBlock {
+    size: usize,
+    next: *mut FreeBlock,
+}
+
 // Force C representation so that the large buffer is at the end.
 // This might avoid access to memory with large gaps.
 #[repr(C)]
 pub struct FixedMemoryAllocator<const SIZE: usize> {
     next_available: Cell<usize>,
+    free_list: Cell<*mut FreeBlock>,
     mem_buffer: [u8; SIZE],
 }
 
+fn block_size(size: usize) -> usize {
+    (size.max(MIN_BLOCK) + MIN_BLOCK - 1) & !(MIN_BLOCK - 1)
+}
+
 impl<const SIZE: usize> FixedMemoryAllocator<SIZE> {
     const fn new() -> Self {
         Self {
             mem_buffer: [0; SIZE],
             next_available: Cell::new(0),
+            free_list: Cell::new(ptr::null_mut()),
         }
     }
-}
 
-unsafe impl<const SIZE: usize> GlobalAlloc for FixedMemoryAllocator<SIZE> {
-    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-        self.alloc_zeroed(layout)
+    /// Bytes of the buffer below the bump pointer, either in use or in the free list.
+    pub fn heap_usage(&self) -> usize {
+        self.next_available.get()
     }
 
-    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
-        // Start address of the allocation array:
-        let array_start = addr_of!(self.mem_buffer) as usize;
+    fn array_start(&self) -> usize {
+        addr_of!(self.mem_buffer) as usize
+    }
 
-        // Address of the next free space:
-        let next_ptr = array_start + self.next_available.get();
+    fn top(&self) -> usize {
+        self.array_start() + self.next_available.get()
+    }
 
+    unsafe fn bump(&self, size: usize, align: usize) -> *mut u8 {
         // Align the pointer.
-        let aligned_ptr = (next_ptr + layout.align() - 1) & !(layout.align() - 1);
+        let aligned_ptr = (self.top() + align - 1) & !(align - 1);
 
         // Where this allocated space ends:
-        let end_of_allocation_ptr = aligned_ptr + layout.size();
+        let end_of_allocation_ptr = aligned_ptr + size;
 
         // Calculates where the next allocation with start:
-        let new_next_available = end_of_allocation_ptr - array_start;
+        let new_next_available = end_of_allocation_ptr - self.array_start();
 
         if new_next_available <= SIZE {
+            // Keep the alignment padding reusable, so that it doesn't pin the blocks below it
+            let padding = aligned_ptr - self.top();
             self.next_available.set(new_next_available);
+            if padding >= MIN_BLOCK {
+                self.free(aligned_ptr - padding, padding);
+            }
+
             aligned_ptr as *mut u8
         } else {
             ptr::null_mut()
         }
     }
 
-    unsafe fn dealloc(&self, _: *mut u8, _: Layout) {
-        // Do nothing. This allocator never deallocates.
+    /// First-fit search of the free list. The unused tail of the block is freed again.
+    unsafe fn take_free(&self, size: usize, align: usize) -> *mut u8 {
+        let mut prev: *mut FreeBlock = ptr::null_mut();
+        let mut curr = self.free_list.get();
+
+        while !curr.is_null() {
+            let (block_size, next) = ((*curr).size, (*curr).next);
+            if block_size >= size && curr as usize % align == 0 {
+                self.unlink(prev, next);
+                if block_size - size >= MIN_BLOCK {
+                    self.free(curr as usize + size, block_size - size);
+                }
+                return curr as *mut u8;
+            }
+
+            prev = curr;
+            curr = next;
+        }
+
+        ptr::null_mut()
+    }
+
+    /// Points `prev` (or the list head, if null) to `next`.
+    unsafe fn unlink(&self, prev: *mut FreeBlock, next: *mut FreeBlock) {
+        if prev.is_null() {
+            self.free_list.set(next);
+        } else {
+            (*prev).next = next;
+        }
+    }
+
+    /// Inserts a block in the (address ordered) free list, merging it with its free neighbours.
+    unsafe fn free(&self, addr: usize, size: usize) {
+        let (mut before_prev, mut prev, mut curr) =
+            (ptr::null_mut(), ptr::null_mut::<FreeBlock>(), self.free_list.get());
+        while !curr.is_null() && (curr as usize) < addr {
+            before_prev = prev;
+            prev = curr;
+            curr = (*curr).next;
+        }
+        let merges_prev = !prev.is_null() && prev as usize + (*prev).size == addr;
+
+        // The top of the heap is given back to the bump pointer, along with the free block below
+        if addr + size == self.top() {
+            if merges_prev {
+                self.unlink(before_prev, ptr::null_mut());
+                self.next_available.set(prev as usize - self.array_start());
+            } else {
+                self.next_available.set(addr - self.array_start());
+            }
+            return;
+        }
+
+        let (size, next) = if !curr.is_null() && addr + size == curr as usize {
+            (size + (*curr).size, (*curr).next)
+        } else {
+            (size, curr)
+        };
+
+        if merges_prev {
+            (*prev).size += size;
+            (*prev).next = next;
+        } else {
+            let block = addr as *mut FreeBlock;
+            block.write(FreeBlock { size, next });
+            self.unlink(prev, block);
+        }
+    }
+}
+
+unsafe impl<const SIZE: usize> GlobalAlloc for FixedMemoryAllocator<SIZE> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let (size, align) = (block_size(layout.size()), layout.align().max(MIN_BLOCK));
+
+        let ptr = self.take_free(size, align);
+        if !ptr.is_null() {
+            return ptr;
+        }
+
+        self.bump(size, align)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        // Freed blocks are reused, so the memory can't be assumed to be zeroed
+        let ptr = self.alloc(layout);
+        if !ptr.is_null() {
+            ptr::write_bytes(ptr, 0, layout.size());
+        }
+
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.free(ptr as usize, block_size(layout.size()));
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let (old_block, new_block) = (block_size(layout.size()), block_size(new_size));
+
+        // Shrink in place, freeing the tail
+        if new_block <= old_block {
+            if old_block - new_block >= MIN_BLOCK {
+                self.free(ptr as usize + new_block, old_block - new_block);
+            }
+            return ptr;
+        }
+
+        // Grow in place when the block is the most recent allocation
+        if ptr as usize + old_block == self.top() {
+            let new_next_available = ptr as usize + new_block - self.array_start();
+            if new_next_available <= SIZE {
+                self.next_available.set(new_next_available);
+                return ptr;
+            }
+        }
+
+        let new_ptr = self.alloc(Layout::from_size_align_unchecked(new_size, layout.align()));
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, layout.size());
+            self.dealloc(ptr, layout);
+        }
+
+        new_ptr
     }
 }
 
 #[global_allocator]
 pub static mut GLOBAL: FixedMemoryAllocator<{ 1024 * 1024 * 10 }> = FixedMemoryAllocator::new();
 
+/// Bytes of the heap currently reserved by the global allocator.
+pub fn heap_usage() -> usize {
+    unsafe { (*addr_of!(GLOBAL)).heap_usage() }
+}
+
 #[alloc_error_handler]
-fn alloc_error(layout: Layout) -> ! {
-    panic!(
-        "memory allocation of {} bytes with alignment {} failed",
-        layout.size(),
-        layout.align()
-    );
+fn alloc_error(_: Layout) -> ! {
+    // Same as Solidity, instead of panicking (which needs to allocate the message)
+    crate::error::revert_with_panic(crate::error::PANIC_ALLOCATION_FAILURE)
 }
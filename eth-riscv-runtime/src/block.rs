@@ -1,4 +1,4 @@
-use alloy_core::primitives::U256;
+use alloy_core::primitives::{Address, B256, U256};
 use eth_riscv_syscalls::Syscall;
 use core::arch::asm;
 
@@ -57,4 +57,73 @@ pub fn number() -> U256 {
         asm!("ecall", lateout("a0") first, lateout("a1") second, lateout("a2") third, lateout("a3") fourth, in("t0") u8::from(Syscall::Number));
     }
     U256::from_limbs([first, second, third, fourth])
-}
\ No newline at end of file
+}
+// Returns current block coinbase (the fee recipient)
+pub fn coinbase() -> Address {
+    let (first, second, third): (u64, u64, u64);
+    unsafe {
+        asm!("ecall", lateout("a0") first, lateout("a1") second, lateout("a2") third, in("t0") u8::from(Syscall::Coinbase));
+    }
+    let mut bytes = [0u8; 20];
+    bytes[0..8].copy_from_slice(&first.to_be_bytes());
+    bytes[8..16].copy_from_slice(&second.to_be_bytes());
+    bytes[16..20].copy_from_slice(&third.to_be_bytes()[..4]);
+    Address::from_slice(&bytes)
+}
+
+// Returns current block randomness beacon (EIP-4399), or the difficulty before the merge
+pub fn prevrandao() -> U256 {
+    let first: u64;
+    let second: u64;
+    let third: u64;
+    let fourth: u64;
+    unsafe {
+        asm!("ecall", lateout("a0") first, lateout("a1") second, lateout("a2") third, lateout("a3") fourth, in("t0") u8::from(Syscall::PrevRandao));
+    }
+    U256::from_limbs([first, second, third, fourth])
+}
+
+// Returns current block blob base fee (EIP-7516)
+pub fn blob_base_fee() -> U256 {
+    let first: u64;
+    let second: u64;
+    let third: u64;
+    let fourth: u64;
+    unsafe {
+        asm!("ecall", lateout("a0") first, lateout("a1") second, lateout("a2") third, lateout("a3") fourth, in("t0") u8::from(Syscall::BlobBaseFee));
+    }
+    U256::from_limbs([first, second, third, fourth])
+}
+
+// Typed counterparts of the getters above, for the fields that a `u64` or a `B256` holds: the block
+// number, timestamp and gas limit saturate at `u64::MAX`, which they don't reach in practice
+
+// Returns current block number
+pub fn block_number() -> u64 {
+    number().saturating_to()
+}
+
+// Returns current block timestamp in seconds since Unix epoch
+pub fn block_timestamp() -> u64 {
+    timestamp().saturating_to()
+}
+
+// Returns current block base fee (EIP-3198 and EIP-1559)
+pub fn block_basefee() -> U256 {
+    base_fee()
+}
+
+// Returns current block randomness beacon (EIP-4399), or the difficulty before the merge
+pub fn block_prevrandao() -> B256 {
+    B256::from(prevrandao())
+}
+
+// Returns current block coinbase (the fee recipient)
+pub fn block_coinbase() -> Address {
+    coinbase()
+}
+
+// Returns current block gas limit
+pub fn block_gas_limit() -> u64 {
+    gas_limit().saturating_to()
+}
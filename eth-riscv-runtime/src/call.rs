@@ -7,23 +7,51 @@ use eth_riscv_syscalls::Syscall;
 // Concrete types implementing the context traits
 pub struct ReadOnly;
 pub struct ReadWrite;
+/// Context of an interface whose calls are delegatecalls (see `delegate` on interfaces), which
+/// can't send value.
+pub struct Delegate;
 
 // Marker traits to determine call context
-pub trait CallCtx {}
+pub trait CallCtx {
+    /// Whether the calls made in this context are delegatecalls.
+    const DELEGATE: bool = false;
+}
 pub trait StaticCtx: CallCtx {}
 pub trait MutableCtx: StaticCtx {}
+/// Mutable contexts whose calls can send value, i.e. all of them but `Delegate`.
+pub trait ValueCtx: MutableCtx {}
 
 impl CallCtx for ReadOnly {}
 impl CallCtx for ReadWrite {}
+impl CallCtx for Delegate {
+    const DELEGATE: bool = true;
+}
 impl StaticCtx for ReadOnly {}
 impl StaticCtx for ReadWrite {}
+impl StaticCtx for Delegate {}
 impl MutableCtx for ReadWrite {}
+impl MutableCtx for Delegate {}
+impl ValueCtx for ReadWrite {}
 
 // Marker trait to connect contract method context with call ctx
 pub trait MethodCtx { type Allowed: CallCtx; }
 impl<'a, T> MethodCtx for &'a T { type Allowed = ReadOnly; }
 impl<'a, T> MethodCtx for &'a mut T { type Allowed = ReadWrite; }
 
+/// Contexts allowed to modify the state: the `&mut self` of contract methods. Taken (by reference)
+/// by library code that needs it, like `log::emit_with`, so that it can't be called from view
+/// methods:
+///   ```ignore
+///   pub fn checkpoint(ctx: &impl MutableCallCtx, value: U256) {
+///       log::emit_with(ctx, Checkpoint::new(value));
+///   }
+///
+///   // In a `&mut self` method of a contract
+///   checkpoint(&self, value);
+///   ```
+pub trait MutableCallCtx: MethodCtx<Allowed = ReadWrite> {}
+impl<T: MethodCtx<Allowed = ReadWrite>> MutableCallCtx for T {}
+
 // Types and traits to build a MethodCtx-aware interface
 pub struct InterfaceBuilder<I> {
     pub address: Address,
@@ -32,6 +60,15 @@ pub struct InterfaceBuilder<I> {
 
 pub trait InitInterface: Sized {
     fn new(address: Address) -> InterfaceBuilder<Self>;
+
+    /// Same as `new`, but returns `None` if there is no code at `address` (e.g. an EOA), as calls
+    /// to it would always succeed with empty return data.
+    fn new_checked(address: Address) -> Option<InterfaceBuilder<Self>> {
+        if ext_code_size(address) == 0 {
+            return None;
+        }
+        Some(Self::new(address))
+    }
 }
 
 // Key change: Add trait to convert between interface types
@@ -69,45 +106,153 @@ pub trait Contract {
     fn call_with_data(&mut self, calldata: &[u8]);
 }
 
+/// Gas limit that forwards all the available gas to the callee (63/64 of it, after EIP-150).
+pub const ALL_GAS: u64 = 0;
+
 pub fn call_contract(
     addr: Address,
-    value: u64,
+    value: U256,
     data: &[u8],
     ret_size: Option<u64>,
+    gas_limit: Option<u64>,
 ) -> Bytes {
     // Perform the call without writing return data into (REVM) memory
-    call(addr, value, data.as_ptr() as u64, data.len() as u64);
+    call(addr, value, data.as_ptr() as u64, data.len() as u64, gas_limit.unwrap_or(ALL_GAS));
     // Load call output to memory
     handle_call_output(ret_size)
 }
 
-pub fn call(addr: Address, value: u64, data_offset: u64, data_size: u64) {
+/// Raw outcome of a call: whether it succeeded, and its output (or revert data) as is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawCallResult {
+    pub success: bool,
+    pub data: Bytes,
+}
+
+impl From<Result<Bytes, Bytes>> for RawCallResult {
+    fn from(result: Result<Bytes, Bytes>) -> Self {
+        match result {
+            Ok(data) => Self { success: true, data },
+            Err(data) => Self { success: false, data },
+        }
+    }
+}
+
+/// Performs a call, returning its output if it succeeds, or its revert data otherwise.
+/// The callee running out of gas, e.g. when a `gas_limit` is set, fails the call with no data.
+pub fn try_call_contract(
+    addr: Address,
+    value: U256,
+    data: &[u8],
+    gas_limit: Option<u64>,
+) -> Result<Bytes, Bytes> {
+    let gas_limit = gas_limit.unwrap_or(ALL_GAS);
+    let success = call(addr, value, data.as_ptr() as u64, data.len() as u64, gas_limit);
+    let output = return_data();
+    if success { Ok(output) } else { Err(output) }
+}
+
+/// Returns whether the call succeeded. Its output can be read with `return_data`.
+/// The callee gets at most `gas_limit` gas, or all the available gas with `ALL_GAS`.
+pub fn call(addr: Address, value: U256, data_offset: u64, data_size: u64, gas_limit: u64) -> bool {
     let addr: U256 = addr.into_word().into();
     let addr = addr.as_limbs();
+    // The low limb of the value stays in a3, where versions before 4 passed it whole
+    let value = value.as_limbs();
+    let success: u64;
     unsafe {
         asm!(
             "ecall",
-            in("a0") addr[0], in("a1") addr[1], in("a2") addr[2],
-            in("a3") value, in("a4") data_offset, in("a5") data_size,
+            inlateout("a0") addr[0] => success, in("a1") addr[1], in("a2") addr[2],
+            in("a3") value[0], in("a4") data_offset, in("a5") data_size, in("a6") gas_limit,
+            in("a7") value[1], in("t1") value[2], in("t2") value[3],
             in("t0") u8::from(Syscall::Call)
         );
     }
+    success != 0
 }
 
-pub fn staticcall_contract(addr: Address, value: u64, data: &[u8], ret_size: Option<u64>) -> Bytes {
+pub fn staticcall_contract(
+    addr: Address,
+    value: U256,
+    data: &[u8],
+    ret_size: Option<u64>,
+    gas_limit: Option<u64>,
+) -> Bytes {
     // Perform the staticcall without writing return data into (REVM) memory
-    staticcall(addr, value, data.as_ptr() as u64, data.len() as u64);
+    staticcall(addr, value, data.as_ptr() as u64, data.len() as u64, gas_limit.unwrap_or(ALL_GAS));
+    // Load call output to memory
+    handle_call_output(ret_size)
+}
+
+/// Performs a staticcall, returning its output if it succeeds, or its revert data otherwise.
+/// The callee running out of gas, e.g. when a `gas_limit` is set, fails the call with no data.
+pub fn try_staticcall_contract(
+    addr: Address,
+    value: U256,
+    data: &[u8],
+    gas_limit: Option<u64>,
+) -> Result<Bytes, Bytes> {
+    let gas_limit = gas_limit.unwrap_or(ALL_GAS);
+    let success = staticcall(addr, value, data.as_ptr() as u64, data.len() as u64, gas_limit);
+    let output = return_data();
+    if success { Ok(output) } else { Err(output) }
+}
+
+/// Runs the code at `addr` with the storage, balance, caller and value of the current contract.
+pub fn delegatecall_contract(
+    addr: Address,
+    data: &[u8],
+    ret_size: Option<u64>,
+    gas_limit: Option<u64>,
+) -> Bytes {
+    // Perform the delegatecall without writing return data into (REVM) memory
+    delegatecall(addr, data.as_ptr() as u64, data.len() as u64, gas_limit.unwrap_or(ALL_GAS));
     // Load call output to memory
     handle_call_output(ret_size)
 }
 
+/// Performs a delegatecall, returning its output if it succeeds, or its revert data otherwise.
+/// The code at `addr` runs with the storage, balance, caller and value of the current contract.
+pub fn try_delegatecall_contract(
+    addr: Address,
+    data: &[u8],
+    gas_limit: Option<u64>,
+) -> Result<Bytes, Bytes> {
+    let gas_limit = gas_limit.unwrap_or(ALL_GAS);
+    let success = delegatecall(addr, data.as_ptr() as u64, data.len() as u64, gas_limit);
+    let output = return_data();
+    if success { Ok(output) } else { Err(output) }
+}
+
+/// Returns whether the delegatecall succeeded. Its output can be read with `return_data`.
+/// The callee gets at most `gas_limit` gas, or all the available gas with `ALL_GAS`.
+pub fn delegatecall(addr: Address, data_offset: u64, data_size: u64, gas_limit: u64) -> bool {
+    let addr: U256 = addr.into_word().into();
+    let addr = addr.as_limbs();
+    let success: u64;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") addr[0] => success, in("a1") addr[1], in("a2") addr[2],
+            in("a4") data_offset, in("a5") data_size, in("a6") gas_limit,
+            in("t0") u8::from(Syscall::DelegateCall)
+        );
+    }
+    success != 0
+}
+
+/// Output (or revert data) of the last call, whatever its size.
+pub fn return_data() -> Bytes {
+    handle_call_output(None)
+}
+
 fn handle_call_output(ret_size: Option<u64>) -> Bytes {
-    // Figure out return data size + initialize memory location
-    let ret_size = match ret_size {
-        Some(size) => size,
-        None => return_data_size(),
-    };
-  
+    // Figure out return data size + initialize memory location. An expected size past the end of
+    // the return data is zero-filled, as copying it would halt the frame
+    let available = return_data_size();
+    let ret_size = ret_size.unwrap_or(available);
+
     if ret_size == 0 {
         return Bytes::default()
     };
@@ -115,35 +260,48 @@ fn handle_call_output(ret_size: Option<u64>) -> Bytes {
     let mut ret_data = Vec::with_capacity(ret_size as usize);
     ret_data.resize(ret_size as usize, 0);
 
-    // Copy the return data from the interpreter's buffer
-    let (offset, chunks, remainder) = (ret_data.as_ptr() as u64, ret_size / 32, ret_size % 32);
-
-    // handle full chunks
-    for i in 0..chunks {
-        let step = i * 32;
-        return_data_copy(offset + step, step, 32);
-    };
-
-    // handle potential last partial-chunk
-    if remainder != 0 {
-        let step = chunks * 32;
-        return_data_copy(offset + step, step, remainder);
-    };
+    // Copy the whole return data from the interpreter's buffer at once
+    let copy_size = ret_size.min(available);
+    if copy_size > 0 {
+        return_data_copy(ret_data.as_mut_ptr() as u64, 0, copy_size);
+    }
 
     Bytes::from(ret_data)
 }
 
-pub fn staticcall(addr: Address, value: u64, data_offset: u64, data_size: u64) {
+/// Returns whether the call succeeded. Its output can be read with `return_data`.
+/// The callee gets at most `gas_limit` gas, or all the available gas with `ALL_GAS`.
+pub fn staticcall(addr: Address, value: U256, data_offset: u64, data_size: u64, gas_limit: u64) -> bool {
     let addr: U256 = addr.into_word().into();
     let addr = addr.as_limbs();
+    // The low limb of the value stays in a3, where versions before 4 passed it whole
+    let value = value.as_limbs();
+    let success: u64;
     unsafe {
         asm!(
             "ecall",
-            in("a0") addr[0], in("a1") addr[1], in("a2") addr[2],
-            in("a3") value, in("a4") data_offset, in("a5") data_size,
+            inlateout("a0") addr[0] => success, in("a1") addr[1], in("a2") addr[2],
+            in("a3") value[0], in("a4") data_offset, in("a5") data_size, in("a6") gas_limit,
+            in("a7") value[1], in("t1") value[2], in("t2") value[3],
             in("t0") u8::from(Syscall::StaticCall)
         );
     }
+    success != 0
+}
+
+/// Size of the code at `addr`, which is zero for EOAs (and contracts under construction).
+pub fn ext_code_size(addr: Address) -> u64 {
+    let addr: U256 = addr.into_word().into();
+    let addr = addr.as_limbs();
+    let size: u64;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("a0") addr[0] => size, in("a1") addr[1], in("a2") addr[2],
+            in("t0") u8::from(Syscall::ExtCodeSize)
+        );
+    }
+    size
 }
 
 pub fn return_data_size() -> u64 {
@@ -0,0 +1,104 @@
+//! Streaming access to the calldata, for `bytes` arguments too large to be decoded into the heap.
+
+use alloc::vec::Vec;
+use alloy_core::primitives::U256;
+
+use crate::msg_data;
+
+/// Size of the calldata of the current frame.
+pub fn calldata_size() -> u64 {
+    msg_data().len() as u64
+}
+
+/// Copies the calldata starting at `offset` into `dest`. Bytes past the end of the calldata are
+/// zeroes, as in the EVM.
+///
+/// The calldata is read in place, from the region of memory the host writes it to when it sets up
+/// the frame, so no syscall is issued.
+pub fn calldata_copy(dest: &mut [u8], offset: u64) {
+    let calldata = msg_data();
+    let start = usize::try_from(offset).map_or(calldata.len(), |offset| offset.min(calldata.len()));
+    let end = calldata.len().min(start.saturating_add(dest.len()));
+    let (copied, padding) = dest.split_at_mut(end - start);
+    copied.copy_from_slice(&calldata[start..end]);
+    padding.fill(0);
+}
+
+/// `bytes` argument left in the calldata, and copied on demand.
+///
+/// Methods receive one instead of an owned `Bytes` when the argument is tagged with `#[calldata]`,
+/// which keeps large payloads (proofs, batches...) out of the heap:
+///   ```ignore
+///   pub fn verify(&self, #[calldata] proof: CalldataReader) -> bool {
+///       let mut node = [0u8; 32];
+///       for offset in (0..proof.len()).step_by(32) {
+///           proof.read_into(offset, &mut node);
+///           ...
+///       }
+///   }
+///   ```
+///
+/// The argument is still a `bytes` in the method's selector and interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CalldataReader {
+    // Calldata offset of the first byte
+    start: u64,
+    len: u64,
+}
+
+impl CalldataReader {
+    /// Reader of the `bytes` whose encoding (its length word, followed by the data) starts at
+    /// `offset` of the calldata, if it fits in the calldata.
+    pub fn at(offset: u64) -> Option<Self> {
+        let mut word = [0u8; 32];
+        calldata_copy(&mut word, offset);
+        let len = u64::try_from(U256::from_be_bytes(word)).ok()?;
+
+        let start = offset.checked_add(32)?;
+        if start.checked_add(len)? > calldata_size() {
+            return None;
+        }
+
+        Some(Self { start, len })
+    }
+
+    /// Reader of an argument whose head (i.e. the offset of its encoding, relative to the start of
+    /// the arguments) is `head`, with the arguments starting at `params_start` of the calldata.
+    #[doc(hidden)]
+    pub fn from_param(params_start: u64, head: U256) -> Option<Self> {
+        let offset = u64::try_from(head).ok()?.checked_add(params_start)?;
+        Self::at(offset)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The whole argument, borrowed in place from the calldata.
+    pub fn as_slice(&self) -> &'static [u8] {
+        &msg_data()[self.start as usize..(self.start + self.len) as usize]
+    }
+
+    /// Copies the bytes at `offset..offset + buf.len()` into `buf`.
+    ///
+    /// Panics if the range doesn't fit in the argument.
+    pub fn read_into(&self, offset: usize, buf: &mut [u8]) {
+        match offset.checked_add(buf.len()) {
+            Some(end) if end <= self.len() => calldata_copy(buf, self.start + offset as u64),
+            _ => panic!("calldata read out of bounds"),
+        }
+    }
+
+    /// Copies the bytes at `offset..offset + len`.
+    ///
+    /// Panics if the range doesn't fit in the argument.
+    pub fn read_slice(&self, offset: usize, len: usize) -> Vec<u8> {
+        let mut buf = ext_alloc::vec![0u8; len];
+        self.read_into(offset, &mut buf);
+        buf
+    }
+}
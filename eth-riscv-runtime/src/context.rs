@@ -0,0 +1,50 @@
+//! Block and tx context, loaded with a single syscall.
+
+use alloy_core::primitives::{Address, U256};
+use core::{arch::asm, ptr::addr_of_mut};
+use eth_riscv_syscalls::Syscall;
+
+/// Size of the buffer filled by the `Context` syscall: 7 big-endian words.
+const CONTEXT_SIZE: usize = 7 * 32;
+
+static mut CONTEXT: Option<Context> = None;
+
+/// Block and tx fields of the current frame.
+///
+/// Unlike the individual accessors (`block::timestamp`, `tx::origin`...), which issue a syscall
+/// each, all the fields are fetched at once by the first `Context::load` of the frame and cached
+/// for the rest of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Context {
+    pub timestamp: U256,
+    pub number: U256,
+    pub gas_limit: U256,
+    pub base_fee: U256,
+    pub gas_price: U256,
+    pub chain_id: u64,
+    pub origin: Address,
+}
+
+impl Context {
+    pub fn load() -> Self {
+        unsafe { *(*addr_of_mut!(CONTEXT)).get_or_insert_with(Self::fetch) }
+    }
+
+    fn fetch() -> Self {
+        let mut buffer = [0u8; CONTEXT_SIZE];
+        unsafe {
+            asm!("ecall", in("a0") buffer.as_mut_ptr() as u64, in("t0") u8::from(Syscall::Context));
+        }
+
+        let word = |i: usize| U256::from_be_slice(&buffer[i * 32..(i + 1) * 32]);
+        Self {
+            timestamp: word(0),
+            number: word(1),
+            gas_limit: word(2),
+            base_fee: word(3),
+            gas_price: word(4),
+            chain_id: word(5).to(),
+            origin: Address::from_slice(&buffer[6 * 32 + 12..]),
+        }
+    }
+}
@@ -1,15 +1,31 @@
 extern crate alloc;
-use alloy_core::primitives::{Address, Bytes, U32};
+use alloy_core::primitives::{Address, Bytes, B256, U256, U32};
 use alloy_sol_types::{SolType, SolValue};
 use ext_alloc::vec::Vec;
 use core::{arch::asm, marker::PhantomData, u64};
-use eth_riscv_syscalls::Syscall;
+use eth_riscv_syscalls::{header::Header, Syscall};
 
 use crate::{FromBuilder, InitInterface, MethodCtx, ReadWrite};
 
+/// Constructor args accepted by the deployment of the contract with interface `I`: its full
+/// `ConstructorArgs`, or the shorter tuples left when its trailing args have a `#[default]`.
+///
+/// Implemented by the interface module exported along with the contract.
+pub trait DeployArgs<I>:
+    SolValue + core::convert::From<<<Self as SolValue>::SolType as SolType>::RustType>
+{
+}
+
 pub trait Deployable {
     type Interface: InitInterface;
 
+    /// Constructor args of the contract, as exported along with its interface
+    type ConstructorArgs: DeployArgs<Self::Interface>;
+
+    /// Shortest constructor args accepted by the contract, without the trailing ones with a
+    /// `#[default]` (same as `ConstructorArgs` if there are none)
+    type RequiredArgs: DeployArgs<Self::Interface>;
+
     /// Returns the contract's runtime bytecode
     fn __runtime() -> &'static [u8];
 
@@ -18,17 +34,45 @@ pub trait Deployable {
         Bytes::from(Self::__runtime())
     }
 
-    // Creates a deployment builder that captures the constructor args
-    fn deploy<Args>(args: Args) -> DeploymentBuilder<Self, Args> 
-    where 
+    // Creates a deployment builder that captures the constructor args, which may leave out the
+    // trailing args with a default
+    fn deploy<A: DeployArgs<Self::Interface>>(args: A) -> DeploymentBuilder<Self, A>
+    where
+        Self: Sized,
+    {
+        Self::deploy_with_value(args, U256::ZERO)
+    }
+
+    // Creates a deployment builder that captures the constructor args, and funds the new contract
+    // with `value` (taken from the deploying contract's balance)
+    fn deploy_with_value<A: DeployArgs<Self::Interface>>(
+        args: A,
+        value: U256,
+    ) -> DeploymentBuilder<Self, A>
+    where
         Self: Sized,
-        Args: SolValue + core::convert::From<<<Args as SolValue>::SolType as SolType>::RustType>
     {
         DeploymentBuilder {
             args,
+            value,
+            salt: None,
             _phantom: PhantomData,
         }
-    } 
+    }
+
+    // Creates a deployment builder that deploys the contract with CREATE2, so that its address only
+    // depends on the deployer, the `salt` and the initcode (see `compute_create2_address`)
+    fn deploy2<A: DeployArgs<Self::Interface>>(args: A, salt: B256) -> DeploymentBuilder<Self, A>
+    where
+        Self: Sized,
+    {
+        DeploymentBuilder {
+            args,
+            value: U256::ZERO,
+            salt: Some(salt),
+            _phantom: PhantomData,
+        }
+    }
 }
 
 pub struct DeploymentBuilder<D: Deployable + ?Sized, Args> 
@@ -36,6 +80,8 @@ where
     Args: SolValue + core::convert::From<<<Args as SolValue>::SolType as SolType>::RustType>
 {
     args: Args,
+    value: U256,
+    salt: Option<B256>,
     _phantom: PhantomData<D>,
 }
 
@@ -43,39 +89,47 @@ impl<D: Deployable, Args> DeploymentBuilder<D, Args>
 where
     Args: SolValue + core::convert::From<<<Args as SolValue>::SolType as SolType>::RustType>
 {
-
-    // Return the interface with the appropriate context
-    pub fn with_ctx<M, T>(self, ctx: M) -> T 
-    where
-        M: MethodCtx<Allowed = ReadWrite>, // Constrain to mutable contexts only
-        D::Interface: InitInterface,
-        T: FromBuilder<Context = M::Allowed>,
-        D::Interface: crate::IntoInterface<T>
-    {
+    /// R55 initcode of the deployment: [header][codesize][bytecode][constructor_args]
+    pub fn init_code(&self) -> Vec<u8> {
         let bytecode = D::__runtime();
         let encoded_args = self.args.abi_encode();
-
-        // Craft R55 initcode: [0xFF][codesize][bytecode][constructor_args]
         let codesize = U32::from(bytecode.len());
 
         let mut init_code = Vec::new();
-        init_code.push(0xff);
+        init_code.extend_from_slice(&Header::current().encode());
         init_code.extend_from_slice(&Bytes::from(codesize.to_be_bytes_vec()));
         init_code.extend_from_slice(&bytecode);
         init_code.extend_from_slice(&encoded_args);
+        init_code
+    }
+
+    /// Hash of the initcode, which CREATE2 derives the address of the contract from.
+    pub fn init_code_hash(&self) -> B256 {
+        crate::keccak256(&self.init_code())
+    }
 
+    // Return the interface with the appropriate context
+    pub fn with_ctx<M, T>(self, ctx: M) -> T 
+    where
+        M: MethodCtx<Allowed = ReadWrite>, // Constrain to mutable contexts only
+        D::Interface: InitInterface,
+        T: FromBuilder<Context = M::Allowed>,
+        D::Interface: crate::IntoInterface<T>
+    {
+        let init_code = self.init_code();
         let offset = init_code.as_ptr() as u64;
         let size = init_code.len() as u64;
 
-        // TODO: think of an ergonomic API to handle deployments with values
-        create(0, offset, size);
+        match self.salt {
+            Some(salt) => create2(self.value, offset, size, salt),
+            None => create(self.value, offset, size),
+        }
 
         // Get deployment address
-        let mut ret_data = Vec::with_capacity(20);
-        ret_data.resize(20 as usize, 0);
-        return_create_address(ret_data.as_ptr() as u64);
-
-        let address = Address::from_slice(&ret_data);
+        let address = created_address();
+        if address == Address::ZERO {
+            panic!("Contract creation failed");
+        }
         
         // Create the interface builder
         let builder = D::Interface::new(address);
@@ -85,16 +139,107 @@ where
     }
 }
 
-fn create(value: u64, data_offset: u64, data_size: u64) {
+// EIP-1167 minimal proxy: the initcode returns the 45-byte runtime, which delegates every call to
+// the 20-byte implementation address inserted between the prefix and the suffix
+const CLONE_PREFIX: [u8; 20] = [
+    0x3d, 0x60, 0x2d, 0x80, 0x60, 0x0a, 0x3d, 0x39, 0x81, 0xf3, // initcode
+    0x36, 0x3d, 0x3d, 0x37, 0x3d, 0x3d, 0x3d, 0x36, 0x3d, 0x73, // runtime, up to `PUSH20`
+];
+const CLONE_SUFFIX: [u8; 15] = [
+    0x5a, 0xf4, 0x3d, 0x82, 0x80, 0x3e, 0x90, 0x3d, 0x91, 0x60, 0x2b, 0x57, 0xfd, 0x5b, 0xf3,
+];
+
+/// Initcode of an EIP-1167 minimal proxy (clone) of `implementation`.
+pub fn clone_init_code(implementation: Address) -> [u8; 55] {
+    let mut init_code = [0u8; 55];
+    init_code[..20].copy_from_slice(&CLONE_PREFIX);
+    init_code[20..40].copy_from_slice(implementation.as_slice());
+    init_code[40..].copy_from_slice(&CLONE_SUFFIX);
+    init_code
+}
+
+/// Deploys an EIP-1167 minimal proxy of `implementation`, and returns its address.
+///
+/// The clone delegates every call to the implementation, but has its own storage and balance.
+/// As it doesn't run the implementation's constructor, any initialization must be done with a
+/// regular call afterwards.
+pub fn clone(implementation: Address) -> Address {
+    let init_code = clone_init_code(implementation);
+    create(U256::ZERO, init_code.as_ptr() as u64, init_code.len() as u64);
+
+    let address = created_address();
+    if address == Address::ZERO {
+        panic!("Clone creation failed");
+    }
+    address
+}
+
+/// Same as `clone`, but deploys the proxy with CREATE2, so that its address only depends on the
+/// deployer, the `implementation` and the `salt` (see `predict_clone_address`). Reusing a salt for
+/// the same implementation fails the creation.
+pub fn clone_deterministic(implementation: Address, salt: B256) -> Address {
+    let init_code = clone_init_code(implementation);
+    create2(U256::ZERO, init_code.as_ptr() as u64, init_code.len() as u64, salt);
+
+    let address = created_address();
+    if address == Address::ZERO {
+        panic!("Clone creation failed");
+    }
+    address
+}
+
+/// Deploys a contract from raw `init_code` (e.g. EVM bytecode), sending it `value`, and returns its
+/// address, which is zero if the creation failed.
+pub fn create_raw(value: U256, init_code: &[u8]) -> Address {
+    create(value, init_code.as_ptr() as u64, init_code.len() as u64);
+    created_address()
+}
+
+/// Address of a contract deployed with CREATE2 by `deployer`, from the keccak256 hash of its initcode
+/// (see `DeploymentBuilder::init_code_hash`).
+pub fn compute_create2_address(deployer: Address, salt: B256, init_code_hash: B256) -> Address {
+    deployer.create2(salt, init_code_hash)
+}
+
+/// Address of the clone of `implementation` deployed by `deployer` with `clone_deterministic`.
+pub fn predict_clone_address(implementation: Address, salt: B256, deployer: Address) -> Address {
+    deployer.create2_from_code(salt, clone_init_code(implementation))
+}
+
+fn create(value: U256, data_offset: u64, data_size: u64) {
+    let value = value.as_limbs();
     unsafe {
         asm!(
             "ecall",
-            in("a0") value, in("a1") data_offset, in("a2") data_size,
+            in("a0") value[0], in("a1") value[1], in("a2") value[2], in("a3") value[3],
+            in("a4") data_offset, in("a5") data_size,
             in("t0") u8::from(Syscall::Create)
         );
     }
 }
 
+fn create2(value: U256, data_offset: u64, data_size: u64, salt: B256) {
+    let value = value.as_limbs();
+    let salt = U256::from_be_bytes(salt.0);
+    let salt = salt.as_limbs();
+    unsafe {
+        asm!(
+            "ecall",
+            in("a0") value[0], in("a1") value[1], in("a2") value[2], in("a3") value[3],
+            in("a4") data_offset, in("a5") data_size,
+            in("a6") salt[0], in("a7") salt[1], in("t1") salt[2], in("t2") salt[3],
+            in("t0") u8::from(Syscall::Create2)
+        );
+    }
+}
+
+/// Address created by the last create, which is zero if it failed.
+fn created_address() -> Address {
+    let mut ret_data = [0u8; 20];
+    return_create_address(ret_data.as_mut_ptr() as u64);
+    Address::from_slice(&ret_data)
+}
+
 fn return_create_address(data_offset: u64) {
     unsafe {
         asm!(
@@ -0,0 +1,136 @@
+//! EIP-712 hashing of typed structured data, to verify signatures of off-chain messages such as
+//! permits or orders.
+//!   ```
+//!   let permit_typehash = keccak256(
+//!       b"Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)",
+//!   );
+//!   let fields = (owner, spender, value, nonce, deadline).abi_encode();
+//!
+//!   let domain = Domain::current("MyToken", "1");
+//!   let digest = eip712::digest(&domain, eip712::hash_struct(permit_typehash, &fields));
+//!   ```
+
+extern crate alloc;
+use alloc::string::String;
+use alloy_core::primitives::{Address, B256, U256};
+
+use crate::{block, keccak256, keccak256_concat, this};
+
+/// EIP-712 domain of a signing scheme. Only the fields that are set are part of the domain, both in
+/// its type and in its encoding.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Domain<'a> {
+    pub name: Option<&'a str>,
+    pub version: Option<&'a str>,
+    pub chain_id: Option<u64>,
+    pub verifying_contract: Option<Address>,
+    pub salt: Option<B256>,
+}
+
+impl<'a> Domain<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Domain of the current contract on the current chain, with the usual name and version.
+    pub fn current(name: &'a str, version: &'a str) -> Self {
+        Self::new()
+            .name(name)
+            .version(version)
+            .chain_id(block::chain_id())
+            .verifying_contract(this())
+    }
+
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    pub fn version(mut self, version: &'a str) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    pub fn verifying_contract(mut self, verifying_contract: Address) -> Self {
+        self.verifying_contract = Some(verifying_contract);
+        self
+    }
+
+    pub fn salt(mut self, salt: B256) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    /// Type hash of the domain, i.e. the hash of `EIP712Domain(..)` with the fields that are set.
+    pub fn type_hash(&self) -> B256 {
+        let fields = [
+            (self.name.is_some(), "string name"),
+            (self.version.is_some(), "string version"),
+            (self.chain_id.is_some(), "uint256 chainId"),
+            (self.verifying_contract.is_some(), "address verifyingContract"),
+            (self.salt.is_some(), "bytes32 salt"),
+        ];
+
+        let mut ty = String::from("EIP712Domain(");
+        for (i, (_, field)) in fields.iter().filter(|(set, _)| *set).enumerate() {
+            if i > 0 {
+                ty.push(',');
+            }
+            ty.push_str(field);
+        }
+        ty.push(')');
+
+        keccak256(ty.as_bytes())
+    }
+
+    /// Domain separator, i.e. the struct hash of the domain.
+    pub fn separator(&self) -> B256 {
+        // Type hash + up to 5 words
+        let mut encoded = [0u8; 192];
+        encoded[..32].copy_from_slice(self.type_hash().as_slice());
+
+        let mut words = [B256::ZERO; 5];
+        let mut len = 0;
+        if let Some(name) = self.name {
+            words[len] = keccak256(name.as_bytes());
+            len += 1;
+        }
+        if let Some(version) = self.version {
+            words[len] = keccak256(version.as_bytes());
+            len += 1;
+        }
+        if let Some(chain_id) = self.chain_id {
+            words[len] = U256::from(chain_id).into();
+            len += 1;
+        }
+        if let Some(verifying_contract) = self.verifying_contract {
+            words[len] = verifying_contract.into_word();
+            len += 1;
+        }
+        if let Some(salt) = self.salt {
+            words[len] = salt;
+            len += 1;
+        }
+
+        for (i, word) in words[..len].iter().enumerate() {
+            encoded[32 * (i + 1)..32 * (i + 2)].copy_from_slice(word.as_slice());
+        }
+        keccak256(&encoded[..32 * (len + 1)])
+    }
+}
+
+/// Struct hash of a message: the hash of its type hash, followed by its ABI-encoded fields (with
+/// dynamic values, like strings or nested structs, already replaced by their hashes).
+pub fn hash_struct(type_hash: B256, encoded_fields: &[u8]) -> B256 {
+    keccak256_concat(&[type_hash.as_slice(), encoded_fields])
+}
+
+/// Digest to sign, or to recover the signer from: `keccak256("\x19\x01" ‖ domainSeparator ‖ structHash)`.
+pub fn digest(domain: &Domain, struct_hash: B256) -> B256 {
+    keccak256_concat(&[b"\x19\x01", domain.separator().as_slice(), struct_hash.as_slice()])
+}
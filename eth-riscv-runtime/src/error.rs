@@ -1,20 +1,243 @@
 extern crate alloc;
-use alloc::vec::Vec;
-use core::arch::asm;
+use alloc::{string::String, vec::Vec};
+use alloy_core::primitives::{Bytes, U256};
+use alloy_sol_types::{Panic, Revert, SolError};
+use core::{arch::asm, convert::Infallible, fmt};
 use crate::Syscall;
 
-pub trait Error {
+pub trait Error: Sized {
     fn abi_encode(&self) -> Vec<u8>;
-    fn abi_decode(bytes: &[u8], validate: bool) -> Self;
+
+    /// Decodes the error, or returns `None` if the data doesn't match any of its variants.
+    fn try_abi_decode(bytes: &[u8], validate: bool) -> Option<Self>;
+
+    fn abi_decode(bytes: &[u8], validate: bool) -> Self {
+        Self::try_abi_decode(bytes, validate).unwrap_or_else(|| panic!("Unknown error"))
+    }
+}
+
+/// Error of a call made through a generated interface.
+///
+/// Besides the error declared by the callee, it can revert with anything else: Solidity's
+/// `Error(string)` and `Panic(uint256)`, or errors bubbled up from other contracts. Calls without
+/// a declared error (e.g. plain value transfers) default to `Infallible`.
+pub enum CallError<E = Infallible> {
+    /// The error declared by the callee method.
+    Custom(E),
+    /// Solidity's `Error(string)`, like the reverts of `require!`.
+    Revert(String),
+    /// Solidity's `Panic(uint256)`, like the reverts of checked arithmetic.
+    Panic(U256),
+    /// Any other revert data, as is.
+    Unknown(Bytes),
+}
+
+impl<E: Error> CallError<E> {
+    /// Decodes the revert data of a call.
+    pub fn decode(data: &[u8]) -> Self {
+        if let Some(err) = E::try_abi_decode(data, true) {
+            return Self::Custom(err);
+        }
+
+        match data.get(..4) {
+            Some(selector) if selector == REVERT_SELECTOR => {
+                if let Ok(revert) = Revert::abi_decode(data, true) {
+                    return Self::Revert(revert.reason);
+                }
+            }
+            Some(selector) if selector == PANIC_SELECTOR => {
+                if let Ok(panic) = Panic::abi_decode(data, true) {
+                    return Self::Panic(panic.code);
+                }
+            }
+            _ => {}
+        }
+
+        Self::Unknown(Bytes::copy_from_slice(data))
+    }
+}
+
+// No revert data decodes as `Infallible`, so it always ends up in the other variants of `CallError`
+impl Error for Infallible {
+    fn abi_encode(&self) -> Vec<u8> {
+        match *self {}
+    }
+
+    fn try_abi_decode(_bytes: &[u8], _validate: bool) -> Option<Self> {
+        None
+    }
+}
+
+// Reverting with a `CallError` bubbles up the original revert data
+impl<E: Error> Error for CallError<E> {
+    fn abi_encode(&self) -> Vec<u8> {
+        match self {
+            Self::Custom(err) => err.abi_encode(),
+            Self::Revert(reason) => Revert::from(reason.as_str()).abi_encode(),
+            Self::Panic(code) => Panic { code: *code }.abi_encode(),
+            Self::Unknown(data) => data.to_vec(),
+        }
+    }
+
+    fn try_abi_decode(bytes: &[u8], _validate: bool) -> Option<Self> {
+        Some(Self::decode(bytes))
+    }
+}
+
+// The declared error is formatted as is, so that `expect` messages don't change
+impl<E: fmt::Debug> fmt::Debug for CallError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Custom(err) => err.fmt(f),
+            Self::Revert(reason) => f.debug_tuple("Revert").field(reason).finish(),
+            Self::Panic(code) => f.debug_tuple("Panic").field(code).finish(),
+            Self::Unknown(data) => f.debug_tuple("Unknown").field(data).finish(),
+        }
+    }
+}
+
+pub fn revert() -> ! { revert_with(&[]) }
+pub fn revert_with_error(data: &[u8]) -> ! { revert_with(data) }
+
+/// Reverts with `data` as is, which doesn't need to be an ABI-encoded error.
+pub fn revert_with(data: &[u8]) -> ! {
+    exit_with(Syscall::Revert, data)
+}
+
+/// Reverts with `data` as the outcome of a panic (including Solidity's `Panic(uint256)` errors),
+/// which the host tells apart from explicit reverts.
+pub fn panic_with(data: &[u8]) -> ! {
+    exit_with(Syscall::Panic, data)
 }
 
-pub fn revert() -> ! { revert_with_error(Vec::new().as_slice()) }
-pub fn revert_with_error(data: &[u8]) -> ! {
+fn exit_with(syscall: Syscall, data: &[u8]) -> ! {
     let (offset, size) = (data.as_ptr() as u64, data.len() as u64);
     unsafe {
         asm!("ecall",
             in("a0") offset, in("a1") size,
-            in("t0") u8::from(Syscall::Revert));
+            in("t0") u8::from(syscall));
     }
     unreachable!()
 }
+
+/// Reason of the revert issued when the calldata can't be decoded.
+pub const CALLDATA_DECODE_ERROR: &str = "failed to decode calldata";
+
+/// Reason of the revert issued when value is sent to a method that isn't `#[payable]`.
+pub const NON_PAYABLE_ERROR: &str = "non-payable function";
+
+/// Reverts with Solidity's `Error(string)` error.
+pub fn revert_with_reason(reason: &str) -> ! {
+    revert_with_error(&Revert::from(reason).abi_encode())
+}
+
+/// Selector of Solidity's `Error(string)` error.
+pub const REVERT_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+/// Size of the `Error(string)` payload for a reason of `len` bytes: selector, offset, length and
+/// the reason padded to a whole word.
+pub const fn revert_reason_size(len: usize) -> usize {
+    4 + 32 + 32 + len.div_ceil(32) * 32
+}
+
+/// ABI-encodes `Error(reason)` at compile time, so that constant reasons don't need to be
+/// formatted (nor allocated) at runtime. `N` must be `revert_reason_size(reason.len())`.
+pub const fn encode_revert_reason<const N: usize>(reason: &str) -> [u8; N] {
+    let reason = reason.as_bytes();
+    assert!(N == revert_reason_size(reason.len()), "invalid payload size");
+
+    let mut data = [0u8; N];
+    let mut i = 0;
+    while i < 4 {
+        data[i] = REVERT_SELECTOR[i];
+        i += 1;
+    }
+
+    // The string is the only (dynamic) param, so its data starts right after the offset word
+    data[4 + 31] = 0x20;
+
+    let mut i = 0;
+    let len = reason.len();
+    while i < 8 {
+        data[4 + 63 - i] = (len >> (8 * i)) as u8;
+        i += 1;
+    }
+
+    let mut i = 0;
+    while i < len {
+        data[4 + 64 + i] = reason[i];
+        i += 1;
+    }
+
+    data
+}
+
+/// Reverts with `Error(reason)` if the condition doesn't hold. The reason must be a string
+/// literal, which is encoded at compile time.
+///
+/// The reason can also be a template, whose `{}` (or `{:x}`, in hex) placeholders are replaced
+/// by the arguments that follow it. It is then formatted on the stack when the condition fails, up
+/// to `MESSAGE_CAPACITY` bytes, see `message::Message`:
+///   ```ignore
+///   require!(amount <= balance, "amount {} exceeds the balance of {}", amount, owner);
+///   ```
+///
+/// Being a revert, it can be used in any method, regardless of its return type.
+#[macro_export]
+macro_rules! require {
+    ($cond:expr, $reason:literal $(,)?) => {
+        if !$cond {
+            const PAYLOAD: [u8; $crate::error::revert_reason_size($reason.len())] =
+                $crate::error::encode_revert_reason($reason);
+            $crate::revert_with_error(&PAYLOAD);
+        }
+    };
+    ($cond:expr, $template:literal, $($arg:expr),+ $(,)?) => {
+        if !$cond {
+            const _: () = assert!(
+                $crate::message::placeholders($template) == [$(stringify!($arg)),+].len(),
+                "the number of arguments doesn't match the placeholders of the template"
+            );
+            $crate::message::Message::format(
+                $template,
+                &[$(&$arg as &dyn $crate::message::MessageArg),+],
+            )
+            .revert();
+        }
+    };
+}
+
+/// Returns early with `Err(err)` if the condition doesn't hold, converting the error with `From`
+/// like the `?` operator does.
+///
+/// Only usable in methods that return a `Result`, whose `Err` variant is then encoded and reverted
+/// with by the dispatcher.
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $err:expr $(,)?) => {
+        if !$cond {
+            return Err(::core::convert::From::from($err));
+        }
+    };
+}
+
+/// Selector of Solidity's `Panic(uint256)` error.
+pub const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+// Panic codes, as defined by Solidity
+pub const PANIC_ARITHMETIC: u8 = 0x11;
+pub const PANIC_DIVISION_BY_ZERO: u8 = 0x12;
+pub const PANIC_ALLOCATION_FAILURE: u8 = 0x41;
+
+/// Revert data of the panics of contracts built with the `panic-code` feature, instead of their
+/// formatted message. Keeps the formatting machinery out of the binary.
+pub const PANIC_CODE: [u8; 4] = *b"r55!";
+
+/// Reverts with Solidity's `Panic(uint256)` error. The payload is built on the stack, so that it
+/// can be used even when the heap is exhausted.
+pub fn revert_with_panic(code: u8) -> ! {
+    let mut data = [0u8; 36];
+    data[..4].copy_from_slice(&PANIC_SELECTOR);
+    data[35] = code;
+    panic_with(&data)
+}
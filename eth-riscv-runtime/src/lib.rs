@@ -2,36 +2,101 @@
 #![no_main]
 #![feature(alloc_error_handler, maybe_uninit_write_slice, round_char_boundary)]
 
-use alloy_core::primitives::{Address, U256};
-use core::{arch::asm, fmt::Write, panic::PanicInfo, slice};
+use alloy_core::primitives::{Address, B256, U256};
+use core::{arch::asm, panic::PanicInfo, slice};
 pub use riscv_rt::entry;
 extern crate alloc as ext_alloc;
 
 mod alloc;
+pub use alloc::heap_usage;
+pub mod abi;
 pub mod block;
+pub use block::{
+    block_basefee, block_coinbase, block_gas_limit, block_number, block_prevrandao,
+    block_timestamp,
+};
+pub mod storage;
 pub mod tx;
 pub mod types;
 
+pub mod context;
+pub use context::Context;
+
 pub mod create;
 pub use create::Deployable;
 
+pub mod eip712;
+
+pub use eth_riscv_syscalls::header;
+
 pub mod error;
-pub use error::{revert, revert_with_error, Error};
+pub use error::{
+    panic_with, revert, revert_with, revert_with_error, revert_with_panic, revert_with_reason,
+    CallError, Error,
+};
 
 pub mod log;
-pub use log::{emit_log, Event};
+pub use log::{emit_log, emit_with, Event};
+
+pub mod message;
+pub use message::{Message, MessageArg};
+
+pub mod math;
+pub use math::{addmod, mulmod, CheckedMath, Wrapping};
 
 pub mod call;
 pub use call::*;
 
+pub mod calldata;
+pub use calldata::CalldataReader;
+
+pub mod multicall;
+pub use multicall::Multicall;
+
+pub mod sol;
+pub use sol::SolCallExt;
+
+// Dependencies of the code generated by `contract-derive`, re-exported so that it compiles in crates
+// that don't depend on them directly
+#[doc(hidden)]
+pub mod __private {
+    pub use alloy_core;
+    pub use alloy_sol_types;
+    pub use ext_alloc as alloc;
+
+    /// Fallback of the `#[init]` defaults written at deployment, for contracts without a
+    /// `#[storage]` struct. The `__init_defaults` generated by `#[storage]` is inherent, so it takes
+    /// precedence over this one.
+    pub trait InitDefaults {
+        fn __init_defaults() {}
+    }
+    impl<T> InitDefaults for T {}
+
+    /// Fallback of the dispatch to an inherited `#[contract_base]`, for contracts that don't
+    /// `#[inherit]` one: selectors unknown to the contract are left unhandled.
+    pub trait InheritedDispatch {
+        fn __dispatch_inherited(&mut self, _selector: u32, _calldata: &[u8]) {}
+    }
+    impl<T> InheritedDispatch for T {}
+}
+
 const CALLDATA_ADDRESS: usize = 0x8000_0000;
 
 pub unsafe fn slice_from_raw_parts(address: usize, length: usize) -> &'static [u8] {
     slice::from_raw_parts(address as *const u8, length)
 }
 
+#[cfg(feature = "panic-code")]
+#[panic_handler]
+fn panic(_info: &PanicInfo<'_>) -> ! {
+    error::panic_with(&error::PANIC_CODE)
+}
+
+#[cfg(not(feature = "panic-code"))]
 #[panic_handler]
 unsafe fn panic(info: &PanicInfo<'_>) -> ! {
+    use core::fmt::Write;
+
     static mut IS_PANICKING: bool = false;
 
     if !IS_PANICKING {
@@ -43,9 +108,9 @@ unsafe fn panic(info: &PanicInfo<'_>) -> ! {
 
         // Convert to bytes and revert
         let msg = message.into_bytes();
-        revert_with_error(&msg);
+        error::panic_with(&msg);
     } else {
-        revert_with_error("Panic handler has panicked!".as_bytes())
+        error::panic_with("Panic handler has panicked!".as_bytes())
     }
 }
 
@@ -58,6 +123,11 @@ pub fn return_riscv(addr: u64, offset: u64) -> ! {
     unreachable!()
 }
 
+/// Ends the execution, returning `data` as is. Unlike the dispatcher, it doesn't ABI-encode it.
+pub fn return_with(data: &[u8]) -> ! {
+    return_riscv(data.as_ptr() as u64, data.len() as u64)
+}
+
 pub fn sload(key: U256) -> U256 {
     let key = key.as_limbs();
     let (val0, val1, val2, val3): (u64, u64, u64, u64);
@@ -85,7 +155,66 @@ pub fn sstore(key: U256, value: U256) {
     }
 }
 
-pub fn keccak256(offset: u64, size: u64) -> U256 {
+/// Reads the storage slots at `keys` with a single syscall, each key being charged as a `SLOAD`.
+pub fn sload_many(keys: &[U256]) -> ext_alloc::vec::Vec<U256> {
+    let keys: ext_alloc::vec::Vec<[u64; 4]> = keys.iter().map(|key| *key.as_limbs()).collect();
+    let mut values = ext_alloc::vec![[0u64; 4]; keys.len()];
+    unsafe {
+        asm!(
+            "ecall",
+            in("a0") keys.as_ptr() as u64, in("a1") keys.len() as u64,
+            in("a2") values.as_mut_ptr() as u64,
+            in("t0") u8::from(Syscall::SLoadMany)
+        );
+    }
+    values.into_iter().map(U256::from_limbs).collect()
+}
+
+/// Writes the `(key, value)` entries to storage, in order, with a single syscall. Each entry is
+/// charged as a `SSTORE`.
+pub fn sstore_many(entries: &[(U256, U256)]) {
+    let words: ext_alloc::vec::Vec<[u64; 4]> = entries
+        .iter()
+        .flat_map(|(key, value)| [*key.as_limbs(), *value.as_limbs()])
+        .collect();
+    unsafe {
+        asm!(
+            "ecall",
+            in("a0") words.as_ptr() as u64, in("a1") entries.len() as u64,
+            in("t0") u8::from(Syscall::SStoreMany)
+        );
+    }
+}
+
+pub fn tload(key: U256) -> U256 {
+    let key = key.as_limbs();
+    let (val0, val1, val2, val3): (u64, u64, u64, u64);
+    unsafe {
+        asm!(
+            "ecall",
+            lateout("a0") val0, lateout("a1") val1, lateout("a2") val2, lateout("a3") val3,
+            in("a0") key[0], in("a1") key[1], in("a2") key[2], in("a3") key[3],
+            in("t0") u8::from(Syscall::TLoad));
+    }
+    U256::from_limbs([val0, val1, val2, val3])
+}
+
+pub fn tstore(key: U256, value: U256) {
+    let key = key.as_limbs();
+    let value = value.as_limbs();
+
+    unsafe {
+        asm!(
+            "ecall",
+            in("a0") key[0], in("a1") key[1], in("a2") key[2], in("a3") key[3],
+            in("a4") value[0], in("a5") value[1], in("a6") value[2], in("a7") value[3],
+            in("t0") u8::from(Syscall::TStore)
+        );
+    }
+}
+
+/// Hashes `size` bytes of memory starting at `offset`.
+pub fn keccak256_raw(offset: u64, size: u64) -> U256 {
     let (first, second, third, fourth): (u64, u64, u64, u64);
     unsafe {
         asm!(
@@ -102,6 +231,47 @@ pub fn keccak256(offset: u64, size: u64) -> U256 {
     U256::from_limbs([first, second, third, fourth])
 }
 
+/// Hashes `data` with the keccak256 syscall.
+pub fn keccak256(data: &[u8]) -> B256 {
+    B256::from(keccak256_raw(data.as_ptr() as u64, data.len() as u64))
+}
+
+/// The raw-memory `keccak256(offset, size)` of earlier runtimes, whose name is now taken by the
+/// slice-based `keccak256`. Code written against it keeps building by importing it from here.
+pub mod legacy {
+    use alloy_core::primitives::U256;
+
+    #[deprecated(note = "use `keccak256(&[u8])`, or `keccak256_raw` to hash raw memory")]
+    pub fn keccak256(offset: u64, size: u64) -> U256 {
+        super::keccak256_raw(offset, size)
+    }
+}
+
+/// Size of the stack buffer used by `keccak256_concat`, enough for a few words.
+const CONCAT_BUFFER_SIZE: usize = 128;
+
+/// Hashes the concatenation of `parts`. Small inputs, like the key and slot of a mapping entry,
+/// are concatenated on the stack so that they don't need to be allocated.
+pub fn keccak256_concat(parts: &[&[u8]]) -> B256 {
+    if let [data] = parts {
+        return keccak256(data);
+    }
+
+    let size = parts.iter().map(|part| part.len()).sum();
+    if size > CONCAT_BUFFER_SIZE {
+        return keccak256(&parts.concat());
+    }
+
+    let mut buffer = [0u8; CONCAT_BUFFER_SIZE];
+    let mut offset = 0;
+    for part in parts {
+        buffer[offset..offset + part.len()].copy_from_slice(part);
+        offset += part.len();
+    }
+
+    keccak256(&buffer[..size])
+}
+
 pub fn this() -> Address {
     let (first, second, third): (u64, u64, u64);
     unsafe {
@@ -126,6 +296,17 @@ pub fn msg_sender() -> Address {
     Address::from_slice(&bytes)
 }
 
+/// Sender of the transaction: the account that started the call chain, which is also the
+/// `msg_sender` of the first call only.
+pub fn tx_origin() -> Address {
+    tx::origin()
+}
+
+/// Id of the chain, as signed by EIP-155 transactions and EIP-712 messages.
+pub fn chain_id() -> u64 {
+    block::chain_id()
+}
+
 pub fn msg_value() -> U256 {
     let (first, second, third, fourth): (u64, u64, u64, u64);
     unsafe {
@@ -134,9 +315,33 @@ pub fn msg_value() -> U256 {
     U256::from_limbs([first, second, third, fourth])
 }
 
+/// Balance of the current contract.
+pub fn self_balance() -> U256 {
+    let (first, second, third, fourth): (u64, u64, u64, u64);
+    unsafe {
+        asm!("ecall", lateout("a0") first, lateout("a1") second, lateout("a2") third, lateout("a3") fourth, in("t0") u8::from(Syscall::SelfBalance));
+    }
+    U256::from_limbs([first, second, third, fourth])
+}
+
+/// Gas left in the current frame. Calls forward at most 63/64 of it, unless capped with a
+/// `gas_limit` (e.g. `with_gas` on an interface), in which case the gas the callee doesn't use is
+/// given back.
+pub fn gas_left() -> u64 {
+    let gas: u64;
+    unsafe {
+        asm!("ecall", lateout("a0") gas, in("t0") u8::from(Syscall::Gas));
+    }
+    gas
+}
+
+/// Selector of the current call: the first 4 bytes of its calldata, or zeroes if the calldata is
+/// shorter than that (i.e. fallback or receive calls).
 pub fn msg_sig() -> [u8; 4] {
-    let sig = unsafe { slice_from_raw_parts(CALLDATA_ADDRESS + 8, 4) };
-    sig.try_into().unwrap()
+    match msg_data().get(..4) {
+        Some(sig) => [sig[0], sig[1], sig[2], sig[3]],
+        None => [0; 4],
+    }
 }
 
 pub fn msg_data() -> &'static [u8] {
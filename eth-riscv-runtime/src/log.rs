@@ -2,7 +2,7 @@ extern crate alloc;
 use alloc::vec::Vec;
 use alloy_core::primitives::B256;
 use core::arch::asm;
-use crate::Syscall;
+use crate::{MutableCallCtx, Syscall};
 
 pub trait Event {
     fn encode_log(&self) -> (Vec<u8>, Vec<[u8; 32]>);
@@ -13,9 +13,16 @@ pub fn emit<T: Event>(event: T) {
     emit_log(&data, &topics.iter().map(|t| B256::from_slice(t)).collect::<Vec<_>>());
 }
 
+/// Emits `event`, from a context allowed to modify the state. Meant for library code, which can't
+/// tell whether it runs in a view method otherwise, see `MutableCallCtx`.
+pub fn emit_with<T: Event>(_ctx: &impl MutableCallCtx, event: T) {
+    emit(event)
+}
+
+/// Emits a log with `data` and up to 4 `topics` (LOG0 to LOG4), the ones past the 4th being left out.
 pub fn emit_log(data: &[u8], topics: &[B256]) {
-    let mut all_topics = [0u8; 96];
-    let topics = &topics[..topics.len().min(3)];
+    let mut all_topics = [0u8; 128];
+    let topics = &topics[..topics.len().min(4)];
     for (i, topic) in topics.iter().enumerate() {
         let start = i * 32;
         all_topics[start..start + 32].copy_from_slice(topic.as_ref());
@@ -0,0 +1,89 @@
+//! Solidity 0.8 style arithmetic.
+
+use alloy_core::primitives::U256;
+use core::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+
+use crate::error::{revert_with_panic, PANIC_ARITHMETIC, PANIC_DIVISION_BY_ZERO};
+
+/// Checked arithmetic that reverts like Solidity 0.8 instead of wrapping: with `Panic(0x11)` on
+/// overflow or underflow, and with `Panic(0x12)` on division by zero.
+///
+/// Wrapping behaviour is still available through the explicit `wrapping_*` methods.
+pub trait CheckedMath: Sized {
+    fn cadd(self, rhs: Self) -> Self;
+    fn csub(self, rhs: Self) -> Self;
+    fn cmul(self, rhs: Self) -> Self;
+    fn cdiv(self, rhs: Self) -> Self;
+}
+
+macro_rules! impl_checked_math {
+    ($($ty:ty),*) => {$(
+        impl CheckedMath for $ty {
+            fn cadd(self, rhs: Self) -> Self {
+                self.checked_add(rhs).unwrap_or_else(|| revert_with_panic(PANIC_ARITHMETIC))
+            }
+
+            fn csub(self, rhs: Self) -> Self {
+                self.checked_sub(rhs).unwrap_or_else(|| revert_with_panic(PANIC_ARITHMETIC))
+            }
+
+            fn cmul(self, rhs: Self) -> Self {
+                self.checked_mul(rhs).unwrap_or_else(|| revert_with_panic(PANIC_ARITHMETIC))
+            }
+
+            fn cdiv(self, rhs: Self) -> Self {
+                self.checked_div(rhs).unwrap_or_else(|| revert_with_panic(PANIC_DIVISION_BY_ZERO))
+            }
+        }
+    )*};
+}
+
+impl_checked_math!(u64, u128, U256);
+
+/// Wrapping arithmetic, like Solidity's `unchecked {}` blocks: the operators of `Wrapping` values
+/// wrap on overflow and underflow, so they compile down to plain arithmetic without any branch.
+///
+/// Only the operations on `Wrapping` values are unchecked, and the inner value (`.0`) is back to
+/// the regular (or `CheckedMath`) semantics. Division is left out, as it still reverts on zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Wrapping<T>(pub T);
+
+macro_rules! impl_wrapping_op {
+    ($ty:ty, $op:ident, $fn:ident, $op_assign:ident, $fn_assign:ident, $wrapping_fn:ident) => {
+        impl $op for Wrapping<$ty> {
+            type Output = Self;
+
+            fn $fn(self, rhs: Self) -> Self {
+                Wrapping(self.0.$wrapping_fn(rhs.0))
+            }
+        }
+
+        impl $op_assign for Wrapping<$ty> {
+            fn $fn_assign(&mut self, rhs: Self) {
+                self.0 = self.0.$wrapping_fn(rhs.0);
+            }
+        }
+    };
+}
+
+macro_rules! impl_wrapping_math {
+    ($($ty:ty),*) => {$(
+        impl_wrapping_op!($ty, Add, add, AddAssign, add_assign, wrapping_add);
+        impl_wrapping_op!($ty, Sub, sub, SubAssign, sub_assign, wrapping_sub);
+        impl_wrapping_op!($ty, Mul, mul, MulAssign, mul_assign, wrapping_mul);
+    )*};
+}
+
+impl_wrapping_math!(u64, u128, U256);
+
+/// `(a + b) % n` without intermediate overflow, like the `ADDMOD` opcode. Returns zero if `n` is
+/// zero.
+pub fn addmod(a: U256, b: U256, n: U256) -> U256 {
+    a.add_mod(b, n)
+}
+
+/// `(a * b) % n` with a full 512-bit intermediate product, like the `MULMOD` opcode. Returns zero
+/// if `n` is zero.
+pub fn mulmod(a: U256, b: U256, n: U256) -> U256 {
+    a.mul_mod(b, n)
+}
@@ -0,0 +1,306 @@
+//! Formatting of revert messages into a buffer on the stack, without `core::fmt`: its machinery
+//! (and the allocation of the formatted string) weighs more than most contracts.
+
+use alloy_core::primitives::{Address, FixedBytes, I256, U256};
+
+use crate::error::{revert_reason_size, revert_with_error, REVERT_SELECTOR};
+
+/// Maximum size of a formatted message, in bytes. Longer messages are truncated, and end with "…".
+pub const MESSAGE_CAPACITY: usize = 256;
+
+const ELLIPSIS: &str = "…";
+
+// The message is written right where it goes in the `Error(string)` payload: after the selector,
+// the offset and the length words
+const MESSAGE_START: usize = 4 + 32 + 32;
+
+/// Message formatted on the stack, along with its `Error(string)` encoding:
+///   ```ignore
+///   let mut message = Message::new();
+///   message.push_str("amount ");
+///   amount.write_to(&mut message, false);
+///   message.revert();
+///   ```
+///
+/// Mostly used through `require!`, which formats it from a template.
+pub struct Message {
+    payload: [u8; revert_reason_size(MESSAGE_CAPACITY)],
+    len: usize,
+    truncated: bool,
+}
+
+impl Default for Message {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Message {
+    pub const fn new() -> Self {
+        Self {
+            payload: [0; revert_reason_size(MESSAGE_CAPACITY)],
+            len: 0,
+            truncated: false,
+        }
+    }
+
+    /// Formats `template`, replacing each `{}` with the next argument (or `{:x}`, with its hex
+    /// representation). Braces are escaped by doubling them, as with `format!`.
+    pub fn format(template: &str, args: &[&dyn MessageArg]) -> Self {
+        let mut message = Self::new();
+        let mut args = args.iter();
+        let mut rest = template;
+        while let Some(i) = rest.find(['{', '}']) {
+            message.push_str(&rest[..i]);
+            rest = &rest[i..];
+
+            let (hex, len) = if rest.starts_with("{{") || rest.starts_with("}}") {
+                message.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            } else if rest.starts_with("{}") {
+                (false, 2)
+            } else if rest.starts_with("{:x}") {
+                (true, 4)
+            } else {
+                // Not a placeholder, kept as is
+                message.push_str(&rest[..1]);
+                rest = &rest[1..];
+                continue;
+            };
+
+            if let Some(arg) = args.next() {
+                arg.write_to(&mut message, hex);
+            }
+            rest = &rest[len..];
+        }
+        message.push_str(rest);
+
+        message
+    }
+
+    /// Appends `s`, truncating the message if it doesn't fit.
+    pub fn push_str(&mut self, s: &str) {
+        self.push_bytes(s.as_bytes());
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        let available = MESSAGE_CAPACITY - self.len;
+        if bytes.len() > available {
+            self.truncated = true;
+        }
+
+        let len = bytes.len().min(available);
+        let start = MESSAGE_START + self.len;
+        self.payload[start..start + len].copy_from_slice(&bytes[..len]);
+        self.len += len;
+    }
+
+    /// The formatted message, ending with "…" if it was truncated.
+    pub fn as_str(&mut self) -> &str {
+        self.seal();
+        // Only made of `&str`s and ASCII, cut at char boundaries
+        unsafe { core::str::from_utf8_unchecked(&self.payload[MESSAGE_START..][..self.len]) }
+    }
+
+    /// Reverts with `Error(message)`.
+    pub fn revert(mut self) -> ! {
+        self.seal();
+
+        self.payload[..4].copy_from_slice(&REVERT_SELECTOR);
+        self.payload[4 + 31] = 0x20;
+        self.payload[4 + 56..4 + 64].copy_from_slice(&(self.len as u64).to_be_bytes());
+        revert_with_error(&self.payload[..revert_reason_size(self.len)])
+    }
+
+    // Replaces the end of a truncated message with the ellipsis, without splitting a char
+    fn seal(&mut self) {
+        if !self.truncated {
+            return;
+        }
+
+        let mut len = self.len.min(MESSAGE_CAPACITY - ELLIPSIS.len());
+        while len > 0 && self.payload[MESSAGE_START + len] & 0xc0 == 0x80 {
+            len -= 1;
+        }
+        self.len = len;
+        self.truncated = false;
+        self.push_str(ELLIPSIS);
+    }
+}
+
+/// Number of placeholders (`{}` or `{:x}`) of a template, checked against the number of arguments
+/// at compile time by `require!`.
+pub const fn placeholders(template: &str) -> usize {
+    let bytes = template.as_bytes();
+    let (mut count, mut i) = (0, 0);
+    while i < bytes.len() {
+        if i + 1 < bytes.len() && (bytes[i] == b'{' || bytes[i] == b'}') && bytes[i + 1] == bytes[i]
+        {
+            i += 2;
+            continue;
+        }
+        if bytes[i] == b'{' && i + 1 < bytes.len() && bytes[i + 1] == b'}' {
+            count += 1;
+        } else if bytes[i] == b'{'
+            && i + 3 < bytes.len()
+            && bytes[i + 1] == b':'
+            && bytes[i + 2] == b'x'
+            && bytes[i + 3] == b'}'
+        {
+            count += 1;
+        }
+        i += 1;
+    }
+
+    count
+}
+
+/// Values that can be interpolated in a `Message`.
+pub trait MessageArg {
+    /// Appends the value, in hex (`0x`-prefixed) if `hex` is set.
+    fn write_to(&self, message: &mut Message, hex: bool);
+}
+
+impl<T: MessageArg + ?Sized> MessageArg for &T {
+    fn write_to(&self, message: &mut Message, hex: bool) {
+        (**self).write_to(message, hex)
+    }
+}
+
+impl MessageArg for str {
+    fn write_to(&self, message: &mut Message, _hex: bool) {
+        message.push_str(self)
+    }
+}
+
+impl MessageArg for bool {
+    fn write_to(&self, message: &mut Message, _hex: bool) {
+        message.push_str(if *self { "true" } else { "false" })
+    }
+}
+
+// Decimal by default, or the minimal hex representation
+impl MessageArg for U256 {
+    fn write_to(&self, message: &mut Message, hex: bool) {
+        if hex {
+            write_hex(message, &self.to_be_bytes::<32>(), true);
+            return;
+        }
+
+        // Split into chunks of 19 digits, the most that fit in a `u64`
+        const CHUNK: u64 = 10_000_000_000_000_000_000;
+        let mut chunks = [0u64; 5];
+        let (mut n, mut value) = (0, *self);
+        loop {
+            let (rest, chunk) = value.div_rem(U256::from(CHUNK));
+            chunks[n] = chunk.to();
+            n += 1;
+            if rest.is_zero() {
+                break;
+            }
+            value = rest;
+        }
+
+        write_decimal(message, chunks[n - 1] as u128, 0);
+        for chunk in chunks[..n - 1].iter().rev() {
+            write_decimal(message, *chunk as u128, 19);
+        }
+    }
+}
+
+impl MessageArg for I256 {
+    fn write_to(&self, message: &mut Message, hex: bool) {
+        if hex {
+            // Two's complement
+            write_hex(message, &self.to_be_bytes::<32>(), true);
+            return;
+        }
+
+        if self.is_negative() {
+            message.push_str("-");
+        }
+        self.unsigned_abs().write_to(message, false)
+    }
+}
+
+// Always the full 20 bytes, lowercase (i.e. without the EIP-55 checksum)
+impl MessageArg for Address {
+    fn write_to(&self, message: &mut Message, _hex: bool) {
+        write_hex(message, self.as_slice(), false)
+    }
+}
+
+impl<const N: usize> MessageArg for FixedBytes<N> {
+    fn write_to(&self, message: &mut Message, _hex: bool) {
+        write_hex(message, self.as_slice(), false)
+    }
+}
+
+macro_rules! impl_message_arg_uint {
+    ($($ty:ty),*) => {
+        $(
+            impl MessageArg for $ty {
+                fn write_to(&self, message: &mut Message, hex: bool) {
+                    if hex {
+                        write_hex(message, &self.to_be_bytes(), true)
+                    } else {
+                        write_decimal(message, *self as u128, 0)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_message_arg_int {
+    ($($ty:ty),*) => {
+        $(
+            impl MessageArg for $ty {
+                fn write_to(&self, message: &mut Message, hex: bool) {
+                    if hex {
+                        // Two's complement
+                        write_hex(message, &self.to_be_bytes(), true)
+                    } else {
+                        if *self < 0 {
+                            message.push_str("-");
+                        }
+                        write_decimal(message, self.unsigned_abs() as u128, 0)
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_message_arg_uint!(u8, u16, u32, u64, u128, usize);
+impl_message_arg_int!(i8, i16, i32, i64, i128, isize);
+
+// Writes `value` in decimal, left-padded with zeroes to `width` digits
+fn write_decimal(message: &mut Message, mut value: u128, width: usize) {
+    let mut digits = [0u8; 39];
+    let mut i = digits.len();
+    while value > 0 || digits.len() - i < width.max(1) {
+        i -= 1;
+        digits[i] = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+    message.push_bytes(&digits[i..]);
+}
+
+// Writes `0x` followed by the hex of the big-endian `bytes`, without its leading zeroes if `trim`
+fn write_hex(message: &mut Message, bytes: &[u8], trim: bool) {
+    const HEX: &[u8; 16] = b"0123456789abcdef";
+
+    message.push_str("0x");
+    let nibbles = bytes.iter().flat_map(|byte| [byte >> 4, byte & 0xf]);
+    let last = (bytes.len() * 2).saturating_sub(1);
+    let mut started = !trim;
+    for (i, nibble) in nibbles.enumerate() {
+        // At least one digit
+        started |= nibble != 0 || i == last;
+        if started {
+            message.push_bytes(&[HEX[nibble as usize]]);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+extern crate alloc;
+use alloc::vec::Vec;
+use alloy_core::primitives::{Address, Bytes, U256};
+
+use crate::{return_data_copy, return_data_size, staticcall, ALL_GAS};
+
+/// A batch of static calls, to the same or different targets, which are executed one after the
+/// other. Their calldata can be built with the `encode_<method>` functions of the interfaces.
+///
+/// The outputs of all the calls are copied into a single buffer, which the returned `Bytes` share.
+#[derive(Debug, Default, Clone)]
+pub struct Multicall {
+    calls: Vec<(Address, Bytes)>,
+}
+
+impl Multicall {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a static call to `target` with `calldata` to the batch.
+    pub fn add(&mut self, target: Address, calldata: Bytes) -> &mut Self {
+        self.calls.push((target, calldata));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.calls.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.calls.is_empty()
+    }
+
+    /// Executes the calls in order, and returns whether each of them succeeded along with its
+    /// output (or revert data). A failing call doesn't prevent the next ones from being executed.
+    pub fn call(&self) -> Vec<(bool, Bytes)> {
+        let mut outcomes = Vec::with_capacity(self.calls.len());
+        let mut buffer = Vec::new();
+
+        for (target, calldata) in &self.calls {
+            let success = staticcall(
+                *target,
+                U256::ZERO,
+                calldata.as_ptr() as u64,
+                calldata.len() as u64,
+                ALL_GAS,
+            );
+
+            // Append the output to the shared buffer
+            let start = buffer.len();
+            let size = return_data_size() as usize;
+            if size != 0 {
+                buffer.resize(start + size, 0);
+                return_data_copy(buffer[start..].as_mut_ptr() as u64, 0, size as u64);
+            }
+            outcomes.push((success, start..start + size));
+        }
+
+        let buffer = Bytes::from(buffer);
+        outcomes
+            .into_iter()
+            .map(|(success, range)| (success, Bytes::from(buffer.slice(range))))
+            .collect()
+    }
+}
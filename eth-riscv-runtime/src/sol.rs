@@ -0,0 +1,66 @@
+//! Bridge to call other contracts through the call types generated by alloy's `sol!` macro, so
+//! that existing Solidity interface definitions don't need to be duplicated as r55 interfaces.
+
+use alloy_core::primitives::{Address, Bytes, U256};
+use alloy_sol_types::SolCall;
+
+use crate::call::{try_call_contract, try_staticcall_contract, CallCtx, MethodCtx, ReadOnly, ReadWrite};
+
+/// Call scheme allowed by a context: read-only contexts can only staticcall, while mutable ones
+/// make regular calls.
+pub trait CallScheme: CallCtx {
+    fn call(addr: Address, data: &[u8]) -> Result<Bytes, Bytes>;
+}
+
+impl CallScheme for ReadOnly {
+    fn call(addr: Address, data: &[u8]) -> Result<Bytes, Bytes> {
+        try_staticcall_contract(addr, U256::ZERO, data, None)
+    }
+}
+
+impl CallScheme for ReadWrite {
+    fn call(addr: Address, data: &[u8]) -> Result<Bytes, Bytes> {
+        try_call_contract(addr, U256::ZERO, data, None)
+    }
+}
+
+/// Extension of `sol!` call types to perform them from a contract.
+///   ```
+///   sol! {
+///       interface ISimpleStorage {
+///           function get() external view returns (uint256);
+///           function set(uint256 num) external;
+///       }
+///   }
+///
+///   pub fn set(&mut self, target: Address, value: U256) -> bool {
+///       ISimpleStorage::setCall { num: value }.call_on(target, self).is_ok()
+///   }
+///   ```
+///
+/// The output is decoded into the `Return` type of the call. If the call fails, the revert data
+/// is returned as is for the caller to decode. Output that doesn't decode into the return type
+/// (e.g. the empty output of a call to an EOA) is also returned as an error.
+pub trait SolCallExt: SolCall {
+    /// Performs the call with the scheme of the context: a staticcall from read-only methods
+    /// (`&self`), and a regular call from mutable ones (`&mut self`).
+    ///
+    /// `sol!` call types don't carry the mutability declared in Solidity, so `view` functions
+    /// called from mutable methods should use `staticcall_on` instead.
+    fn call_on<M>(&self, addr: Address, _ctx: M) -> Result<Self::Return, Bytes>
+    where
+        M: MethodCtx,
+        M::Allowed: CallScheme,
+    {
+        let output = <M::Allowed as CallScheme>::call(addr, &self.abi_encode())?;
+        Self::abi_decode_returns(&output, true).map_err(|_| output)
+    }
+
+    /// Performs the call as a staticcall, whatever the context.
+    fn staticcall_on<M: MethodCtx>(&self, addr: Address, _ctx: M) -> Result<Self::Return, Bytes> {
+        let output = try_staticcall_contract(addr, U256::ZERO, &self.abi_encode(), None)?;
+        Self::abi_decode_returns(&output, true).map_err(|_| output)
+    }
+}
+
+impl<T: SolCall> SolCallExt for T {}
@@ -0,0 +1,25 @@
+//! Raw access to storage slots, for proxies, diamonds and migrations.
+//!
+//! These functions bypass the typed layout of the `storage` macro: nothing prevents a raw write from
+//! overwriting a slot owned by a field (or a mapping entry), nor a raw read from interpreting it
+//! with the wrong type. Prefer the typed fields whenever possible.
+
+use alloy_core::primitives::U256;
+
+use crate::{sload, sstore, MethodCtx, ReadWrite};
+
+/// Reads the raw word stored at `slot`.
+pub fn read(slot: U256) -> U256 {
+    sload(slot)
+}
+
+/// Writes `value` at `slot`.
+///
+/// Takes the context of the calling method (i.e. `&mut *self`), just like interfaces do, so that
+/// it can't be used from `&self` methods.
+pub fn write<M>(_ctx: M, slot: U256, value: U256)
+where
+    M: MethodCtx<Allowed = ReadWrite>,
+{
+    sstore(slot, value)
+}
@@ -0,0 +1,203 @@
+use ext_alloc::{string::String, vec::Vec};
+
+use alloy_core::primitives::Bytes;
+
+use super::*;
+
+/// Dynamic-sized `bytes`, stored with Solidity's layout, from its base slot:
+///  - values shorter than 32 bytes are stored in the base slot (left-aligned), along with
+///    `2 * len` in its lowest byte.
+///  - longer values only store `2 * len + 1` in the base slot, and their data in the consecutive
+///    slots starting at `keccak256(base_slot)`.
+///
+/// When used as the value of a `Mapping`, the base slot is the one derived from the entry's key.
+/// Writes only `SSTORE` the words that change, and clear the data slots that the previous value
+/// used beyond the new one.
+#[derive(Default)]
+pub struct StorageBytes {
+    id: U256,
+}
+
+/// Dynamic-sized `string`, with the same layout as `StorageBytes`.
+#[derive(Default)]
+pub struct StorageString {
+    id: U256,
+}
+
+impl StorageLayout for StorageBytes {
+    fn allocate(first: u64, second: u64, third: u64, fourth: u64) -> Self {
+        Self {
+            id: U256::from_limbs([first, second, third, fourth]),
+        }
+    }
+}
+
+impl StorageLayout for StorageString {
+    fn allocate(first: u64, second: u64, third: u64, fourth: u64) -> Self {
+        Self {
+            id: U256::from_limbs([first, second, third, fourth]),
+        }
+    }
+}
+
+impl StorageStorable for StorageBytes {
+    type Value = Bytes;
+
+    fn __read(key: U256) -> Self::Value {
+        Bytes::from(read_bytes(key))
+    }
+
+    fn __write(key: U256, value: Self::Value) {
+        write_bytes(key, &value);
+    }
+
+    fn __clear(key: U256) {
+        write_bytes(key, &[]);
+    }
+}
+
+impl StorageStorable for StorageString {
+    type Value = String;
+
+    fn __read(key: U256) -> Self::Value {
+        String::from_utf8(read_bytes(key)).unwrap_or_else(|_| revert())
+    }
+
+    fn __write(key: U256, value: Self::Value) {
+        write_bytes(key, value.as_bytes());
+    }
+
+    fn __clear(key: U256) {
+        write_bytes(key, &[]);
+    }
+}
+
+impl DirectStorage<Bytes> for StorageBytes {
+    fn read(&self) -> Bytes {
+        Self::__read(self.id)
+    }
+
+    fn write(&mut self, value: Bytes) {
+        Self::__write(self.id, value)
+    }
+}
+
+impl DirectStorage<String> for StorageString {
+    fn read(&self) -> String {
+        Self::__read(self.id)
+    }
+
+    fn write(&mut self, value: String) {
+        Self::__write(self.id, value)
+    }
+}
+
+impl StorageBytes {
+    /// Length of the value, which only reads its base slot.
+    pub fn len(&self) -> usize {
+        header(sload(self.id)).0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clears the length and the data slots of the value.
+    pub fn delete(&mut self) {
+        Self::__clear(self.id)
+    }
+}
+
+impl StorageString {
+    /// Length of the value in bytes, which only reads its base slot.
+    pub fn len(&self) -> usize {
+        header(sload(self.id)).0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Clears the length and the data slots of the value.
+    pub fn delete(&mut self) {
+        Self::__clear(self.id)
+    }
+}
+
+// Length of the value whose base slot holds `word`, and whether its data is stored out of it
+fn header(word: U256) -> (usize, bool) {
+    if word.bit(0) {
+        ((word >> 1usize).saturating_to::<usize>(), true)
+    } else {
+        ((word.byte(0) >> 1) as usize, false)
+    }
+}
+
+// First slot of the data of a long value
+fn data_slot(base: U256) -> U256 {
+    keccak256(&base.to_be_bytes::<32>()).into()
+}
+
+// Number of data slots used by a long value of `len` bytes
+fn data_slots(len: usize, long: bool) -> usize {
+    if long {
+        len.div_ceil(32)
+    } else {
+        0
+    }
+}
+
+fn read_bytes(base: U256) -> Vec<u8> {
+    let word = sload(base);
+    let (len, long) = header(word);
+    if !long {
+        return word.to_be_bytes::<32>()[..len].to_vec();
+    }
+
+    let first = data_slot(base);
+    let mut data = Vec::with_capacity(len);
+    for i in 0..data_slots(len, long) {
+        let chunk: [u8; 32] = sload(first + U256::from(i)).to_be_bytes();
+        data.extend_from_slice(&chunk[..(len - 32 * i).min(32)]);
+    }
+    data
+}
+
+fn write_bytes(base: U256, data: &[u8]) {
+    let previous = sload(base);
+    let (previous_len, previous_long) = header(previous);
+
+    let (header_word, used) = if data.len() < 32 {
+        let mut word = [0u8; 32];
+        word[..data.len()].copy_from_slice(data);
+        word[31] = (data.len() * 2) as u8;
+        (U256::from_be_bytes(word), 0)
+    } else {
+        (U256::from(data.len() * 2 + 1), data.len().div_ceil(32))
+    };
+    if header_word != previous {
+        sstore(base, header_word);
+    }
+
+    let stale = data_slots(previous_len, previous_long);
+    if used == 0 && stale == 0 {
+        return;
+    }
+
+    let first = data_slot(base);
+    for (i, chunk) in data.chunks(32).take(used).enumerate() {
+        let mut word = [0u8; 32];
+        word[..chunk.len()].copy_from_slice(chunk);
+        store_if_changed(first + U256::from(i), U256::from_be_bytes(word));
+    }
+    for i in used..stale {
+        store_if_changed(first + U256::from(i), U256::ZERO);
+    }
+}
+
+// Only `SSTORE`s words that differ from the stored ones
+fn store_if_changed(key: U256, word: U256) {
+    if sload(key) != word {
+        sstore(key, word);
+    }
+}
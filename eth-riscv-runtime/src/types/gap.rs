@@ -0,0 +1,16 @@
+use super::*;
+
+/// Slots reserved in a `#[storage]` struct with `#[gap(N)]`, so that later versions of an
+/// upgradeable contract can add fields there without shifting the following ones:
+///   ```ignore
+///   #[gap(10)]
+///   __gap: Gap,
+///   ```
+/// The reserved slots can't be accessed.
+pub struct Gap;
+
+impl StorageLayout for Gap {
+    fn allocate(_first: u64, _second: u64, _third: u64, _fourth: u64) -> Self {
+        Self
+    }
+}
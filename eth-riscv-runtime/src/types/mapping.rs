@@ -1,47 +1,124 @@
 use core::{
-    alloc::{GlobalAlloc, Layout},
+    cell::{Cell, UnsafeCell},
     marker::PhantomData,
     ops::{Add, AddAssign, Deref, DerefMut, Index, IndexMut, Sub, SubAssign}, 
+    ptr::NonNull,
 };
 
-use crate::alloc::GLOBAL;
+use ext_alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
 
-use super::*;
+use super::{slot::{decode_word, encode_word}, *};
+
+/// Guards (or nested mappings) handed out by indexing a mapping, one per storage key. They are
+/// kept until the mapping is borrowed mutably, when none of them can still be referenced, or
+/// dropped: the references returned by `Index` are valid for as long as the mapping is borrowed,
+/// and accessing the same keys again (e.g. in a loop) doesn't grow the heap.
+#[derive(Default)]
+struct Guards {
+    // Raw pointers rather than boxes, so that the map can be updated while guards are referenced
+    entries: UnsafeCell<BTreeMap<U256, NonNull<dyn Erased>>>,
+}
+
+trait Erased {}
+impl<T> Erased for T {}
+
+impl Guards {
+    /// Guard of `key`, created with `new` unless it was already handed out since the last reset.
+    ///
+    /// SAFETY: a given `Guards` must only ever hold values of type `T`.
+    unsafe fn get_or_insert<T: 'static>(&self, key: U256, new: impl FnOnce() -> T) -> &T {
+        let entries = &mut *self.entries.get();
+        let entry = entries
+            .entry(key)
+            .or_insert_with(|| NonNull::from(Box::leak(Box::new(new()) as Box<dyn Erased>)));
+        entry.cast::<T>().as_ref()
+    }
+
+    /// Drops the guards handed out so far, which can't be referenced anymore given `&mut self`, and
+    /// returns a new one for `key`.
+    ///
+    /// SAFETY: a given `Guards` must only ever hold values of type `T`.
+    unsafe fn reset<T: 'static>(&mut self, key: U256, value: T) -> &mut T {
+        self.clear();
+        let entry = NonNull::from(Box::leak(Box::new(value) as Box<dyn Erased>));
+        self.entries.get_mut().insert(key, entry);
+        entry.cast::<T>().as_mut()
+    }
+
+    fn clear(&mut self) {
+        for (_, entry) in core::mem::take(self.entries.get_mut()) {
+            // SAFETY: leaked by `get_or_insert` or `reset`, and no longer referenced
+            drop(unsafe { Box::from_raw(entry.as_ptr()) });
+        }
+    }
+}
+
+impl Drop for Guards {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
 
 /// Implements a Solidity-like Mapping type.
 #[derive(Default)]
 pub struct Mapping<K, V> {
     id: U256,
+    guards: Guards,
     _pd: PhantomData<(K, V)>,
 }
 
-impl<K, V> StorageLayout for Mapping<K, V> {
-    fn allocate(first: u64, second: u64, third: u64, fourth: u64) -> Self {
+impl<K, V> Mapping<K, V> {
+    fn new(id: U256) -> Self {
         Self {
-            id: U256::from_limbs([first, second, third, fourth]),
-            _pd: PhantomData::default(),
+            id,
+            guards: Guards::default(),
+            _pd: PhantomData,
         }
     }
 }
 
+impl<K, V> StorageLayout for Mapping<K, V> {
+    fn allocate(first: u64, second: u64, third: u64, fourth: u64) -> Self {
+        Self::new(U256::from_limbs([first, second, third, fourth]))
+    }
+}
+
 impl<K, V> Mapping<K, V>
 where
     K: SolValue,
 {
-    fn encode_key(&self, key: K) -> U256 {
+    fn encode_key(&self, key: &K) -> U256 {
         let key_bytes = key.abi_encode();
         let id_bytes: [u8; 32] = self.id.to_be_bytes();
 
-        // Concatenate the key bytes and id bytes
-        let mut concatenated = Vec::with_capacity(key_bytes.len() + id_bytes.len());
-        concatenated.extend_from_slice(&key_bytes);
-        concatenated.extend_from_slice(&id_bytes);
+        // Hash the concatenation of the key bytes and id bytes
+        keccak256_concat(&[&key_bytes, &id_bytes]).into()
+    }
+}
 
-        // Call the keccak256 syscall with the concatenated bytes
-        let offset = concatenated.as_ptr() as u64;
-        let size = concatenated.len() as u64;
+/// Batched accesses to a mapping of single-slot values, for code that touches many of its keys at
+/// once (e.g. settlements). The slots of all the keys are derived first, and then read or written
+/// with a single syscall, without creating a guard per entry.
+impl<K, V> Mapping<K, Slot<V>>
+where
+    K: SolValue,
+    V: SolValue + core::convert::From<<<V as SolValue>::SolType as SolType>::RustType>,
+{
+    /// Reads the values of `keys`, in the same order. Same as reading `mapping[key]` for each key.
+    pub fn read_many(&self, keys: &[K]) -> Vec<V> {
+        let slots: Vec<U256> = keys.iter().map(|key| self.encode_key(key)).collect();
+        sload_many(&slots).into_iter().map(decode_word).collect()
+    }
 
-        keccak256(offset, size)
+    /// Writes the `(key, value)` entries, in order, so that the last write of a repeated key wins.
+    /// Same as writing `mapping[key]` for each entry, except that values already stored are
+    /// written again (see `MappingGuard::force_write`).
+    pub fn write_many(&mut self, entries: &[(K, V)]) {
+        let entries: Vec<(U256, U256)> = entries
+            .iter()
+            .map(|(key, value)| (self.encode_key(key), encode_word(value)))
+            .collect();
+        sstore_many(&entries);
     }
 }
 
@@ -49,12 +126,18 @@ where
 /// 
 /// This type is returned when indexing into a `Mapping` and provides methods
 /// to read/write from the underlying storage location.
+///
+/// The guard remembers the value it last read or wrote, so that writing it back unchanged (e.g.
+/// `guard += U256::ZERO`) doesn't `SSTORE`. Use `force_write` to store it anyway, e.g. if the
+/// entry may have been changed by a reentrant call since the guard read it.
 pub struct MappingGuard<V>
 where
     V: StorageStorable,
     V::Value: SolValue + core::convert::From<<<V::Value as SolValue>::SolType as SolType>::RustType>,
 {
     storage_key: U256,
+    /// Digest of the stored value, if known
+    stored: Cell<Option<B256>>,
     _phantom: PhantomData<V>,
 }
 
@@ -66,9 +149,35 @@ where
     pub fn new(storage_key: U256) -> Self {
         Self {
             storage_key,
+            stored: Cell::new(None),
             _phantom: PhantomData,
         }
     }
+
+    /// Writes the input value to storage (`SSTORE`), even if it is the one already stored.
+    pub fn force_write(&mut self, value: V::Value) {
+        self.stored.set(Some(digest(&value)));
+        V::__write(self.storage_key, value);
+    }
+
+    /// Deletes the entry, clearing all the slots used by its value (e.g. both the length and the
+    /// data slots of a `StorageString`).
+    pub fn delete(&mut self) {
+        self.stored.set(None);
+        V::__clear(self.storage_key);
+    }
+}
+
+// Identifies a value by its encoding, which is hashed if it spans more than a word
+fn digest<T: SolValue>(value: &T) -> B256 {
+    let encoded = value.abi_encode();
+    if encoded.len() > 32 {
+        return keccak256(&encoded);
+    }
+
+    let mut word = [0u8; 32];
+    word[..encoded.len()].copy_from_slice(&encoded);
+    B256::from(word)
 }
 
 impl<V> IndirectStorage<V> for MappingGuard<V>
@@ -76,14 +185,23 @@ where
     V: StorageStorable,
     V::Value: SolValue + core::convert::From<<<V::Value as SolValue>::SolType as SolType>::RustType>,
 {
-    /// Writes the input value to storage (`SSTORE`) at the location specified by this guard.
+    /// Writes the input value to storage (`SSTORE`) at the location specified by this guard,
+    /// unless it is the value that the guard last read or wrote.
     fn write(&mut self, value: V::Value) {
+        let digest = digest(&value);
+        if self.stored.get() == Some(digest) {
+            return;
+        }
+
+        self.stored.set(Some(digest));
         V::__write(self.storage_key, value);
     }
 
     /// Reads the value from storage (`SLOAD`) at the location specified by this guard.
     fn read(&self) -> V::Value {
-        V::__read(self.storage_key)
+        let value = V::__read(self.storage_key);
+        self.stored.set(Some(digest(&value)));
+        value
     }
 }
 
@@ -97,25 +215,12 @@ where
     type Output = MappingGuard<V>;
 
     fn index(&self, key: K) -> &Self::Output {
-        let storage_key = self.encode_key(key);
+        let storage_key = self.encode_key(&key);
 
-        // Create the guard
-        let guard = MappingGuard::<V>::new(storage_key);
-
-        // Manually handle memory using the global allocator
+        // SAFETY: the guards of a mapping of `V` values are all `MappingGuard<V>`
         unsafe {
-            // Calculate layout for the guard which holds the mapping key
-            let layout = Layout::new::<MappingGuard<V>>();
-
-            // Allocate using the `GLOBAL` fixed memory allocator
-            #[allow(static_mut_refs)]
-            let ptr = GLOBAL.alloc(layout) as *mut MappingGuard<V>;
-
-            // Write the guard to the allocated memory
-            ptr.write(guard);
-
-            // Return a reference with 'static lifetime (`GLOBAL` never deallocates)
-            &*ptr
+            self.guards
+                .get_or_insert(storage_key, || MappingGuard::<V>::new(storage_key))
         }
     }
 }
@@ -128,25 +233,12 @@ where
     V::Value: SolValue + core::convert::From<<<V::Value as SolValue>::SolType as SolType>::RustType> + 'static,
 {
     fn index_mut(&mut self, key: K) -> &mut Self::Output {
-        let storage_key = self.encode_key(key);
-
-        // Create the guard
-        let guard = MappingGuard::<V>::new(storage_key);
+        let storage_key = self.encode_key(&key);
 
-        // Manually handle memory using the global allocator
+        // SAFETY: the guards of a mapping of `V` values are all `MappingGuard<V>`
         unsafe {
-            // Calculate layout for the guard which holds the mapping key
-            let layout = Layout::new::<MappingGuard<V>>();
-
-            // Allocate using the `GLOBAL` fixed memory allocator
-            #[allow(static_mut_refs)]
-            let ptr = GLOBAL.alloc(layout) as *mut MappingGuard<V>;
-
-            // Write the guard to the allocated memory
-            ptr.write(guard);
-
-            // Return a reference with 'static lifetime (`GLOBAL` never deallocates)
-            &mut *ptr
+            self.guards
+                .reset(storage_key, MappingGuard::<V>::new(storage_key))
         }
     }
 }
@@ -180,27 +272,13 @@ where
     type Output = NestedMapping<K2, V>;
 
     fn index(&self, key: K1) -> &Self::Output {
-        let id = self.encode_key(key);
-
-        // Create the nested mapping
-        let mapping = Mapping { id, _pd: PhantomData };
-        let nested = NestedMapping { mapping };
+        let id = self.encode_key(&key);
 
-        // Manually handle memory using the global allocator
+        // SAFETY: the guards of a mapping of mappings are all `NestedMapping<K2, V>`
         unsafe {
-            // Calculate layout for the nested mapping
-            // which is an intermediate object that links to the inner-most mapping guard
-            let layout = Layout::new::<NestedMapping<K2, V>>();
-
-            // Allocate using the `GLOBAL` fixed memory allocator
-            #[allow(static_mut_refs)]
-            let ptr = GLOBAL.alloc(layout) as *mut NestedMapping<K2, V>;
-
-            // Write the nested mapping to the allocated memory
-            ptr.write(nested);
-
-            // Return a reference with 'static lifetime (`GLOBAL` never deallocates)
-            &*ptr
+            self.guards.get_or_insert(id, || NestedMapping {
+                mapping: Mapping::new(id),
+            })
         }
     }
 }
@@ -213,27 +291,13 @@ where
     V: 'static,
 {
     fn index_mut(&mut self, key: K1) -> &mut Self::Output {
-        let id = self.encode_key(key);
-
-        // Create the nested mapping
-        let mapping = Mapping { id, _pd: PhantomData };
-        let nested = NestedMapping { mapping };
+        let id = self.encode_key(&key);
 
-        // Manually handle memory using the global allocator
+        // SAFETY: the guards of a mapping of mappings are all `NestedMapping<K2, V>`
         unsafe {
-            // Calculate layout for the nested mapping
-            // which is an intermediate object that links to the inner-most mapping guard
-            let layout = Layout::new::<NestedMapping<K2, V>>();
-
-            // Allocate using the `GLOBAL` fixed memory allocator
-            #[allow(static_mut_refs)]
-            let ptr = GLOBAL.alloc(layout) as *mut NestedMapping<K2, V>;
-
-            // Write the nested mapping to the allocated memory
-            ptr.write(nested);
-
-            // Return a reference with 'static lifetime (`GLOBAL` never deallocates)
-            &mut *ptr
+            self.guards.reset(id, NestedMapping {
+                mapping: Mapping::new(id),
+            })
         }
     }
 }
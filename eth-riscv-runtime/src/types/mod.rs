@@ -5,9 +5,6 @@ use crate::*;
 
 use alloy_sol_types::{SolType, SolValue};
 
-extern crate alloc;
-use alloc::vec::Vec;
-
 mod mapping;
 pub use mapping::Mapping;
 
@@ -17,6 +14,17 @@ pub use slot::Slot;
 mod lock;
 pub use lock::Lock;
 
+mod gap;
+pub use gap::Gap;
+
+mod bytes;
+pub use bytes::{StorageBytes, StorageString};
+
+mod reentrancy;
+pub use reentrancy::{
+    ReentrancyGuard, ReentrancyLock, REENTRANT_CALL_ERROR, REENTRANT_CALL_SELECTOR,
+};
+
 ///  STORAGE TYPES:
 ///  > Must implement the following traits:
 ///     - `StorageLayout`: Allows the `storage` macro to allocate a storage slot.
@@ -25,6 +33,8 @@ pub use lock::Lock;
 ///     - `IndirectStorage`:  Exposes read and write capabilities of values that are gated by a guard.
 ///  > Unless it is a wrapper type (like `Mapping`) it must implement the following traits:
 ///     - `StorageStorable`: Allows db storage reads and writes with abi de/encoding.
+///  > Dynamic-sized types (like `StorageString`) span several slots from the one they are given, and
+///    can also be the value of a `Mapping`, in which case their layout starts at the entry's slot.
 
 
 // TODO: enhance `storage` macro to handle complex types (like tuples or custom structs)
@@ -40,6 +50,11 @@ pub trait StorageStorable {
 
     fn __read(key: U256) -> Self::Value;
     fn __write(key: U256, value: Self::Value);
+
+    /// Clears all the slots used by the value stored at `key`.
+    fn __clear(key: U256) {
+        sstore(key, U256::ZERO);
+    }
 }
 
 /// Public interface for interacting with direct storage types (like `Slot`)
@@ -0,0 +1,55 @@
+use super::*;
+
+/// Signature of the error reverted with when a `ReentrancyLock` is already held (same as
+/// OpenZeppelin's `ReentrancyGuard`).
+pub const REENTRANT_CALL_ERROR: &str = "ReentrancyGuardReentrantCall()";
+
+/// Selector of `REENTRANT_CALL_ERROR`, which is the whole revert data.
+pub const REENTRANT_CALL_SELECTOR: [u8; 4] = [0x3e, 0xe5, 0xae, 0xb5];
+
+/// A reentrancy lock backed by transient storage (EIP-1153).
+///
+/// Unlike `Lock<E>`, it doesn't need to be threaded through the contract methods: `enter()` can be
+/// called from any code, including shared modules, and reverts by itself if the lock is held.
+/// As transient storage is cleared at the end of every transaction, a lock can never remain held
+/// across transactions.
+pub struct ReentrancyLock {
+    id: U256,
+}
+
+impl StorageLayout for ReentrancyLock {
+    fn allocate(first: u64, second: u64, third: u64, fourth: u64) -> Self {
+        Self {
+            id: U256::from_limbs([first, second, third, fourth]),
+        }
+    }
+}
+
+impl ReentrancyLock {
+    /// Acquires the lock, returning a guard that releases it when dropped.
+    /// Reverts with `ReentrancyGuardReentrantCall()` if the lock is already held.
+    pub fn enter(&self) -> ReentrancyGuard {
+        if self.is_entered() {
+            revert_with_error(&REENTRANT_CALL_SELECTOR);
+        }
+
+        tstore(self.id, U256::from(1));
+        ReentrancyGuard { id: self.id }
+    }
+
+    /// Checks if the lock is currently held
+    pub fn is_entered(&self) -> bool {
+        tload(self.id) != U256::ZERO
+    }
+}
+
+/// A guard that releases its `ReentrancyLock` when dropped
+pub struct ReentrancyGuard {
+    id: U256,
+}
+
+impl Drop for ReentrancyGuard {
+    fn drop(&mut self) {
+        tstore(self.id, U256::ZERO);
+    }
+}
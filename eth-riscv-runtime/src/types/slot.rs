@@ -31,18 +31,31 @@ where
     type Value = V;
 
     fn __read(key: U256) -> Self::Value {
-        let bytes: [u8; 32] = sload(key).to_be_bytes();
-        V::abi_decode(&bytes, false).unwrap_or_else(|_| revert())
+        decode_word(sload(key))
     }
 
     fn __write(key: U256, value: Self::Value) {
-        let bytes = value.abi_encode();
-        let mut padded = [0u8; 32];
-        padded[..bytes.len()].copy_from_slice(&bytes);
-        sstore(key, U256::from_be_bytes(padded));
+        sstore(key, encode_word(&value));
     }
 }
 
+/// Decodes the value stored in a slot.
+pub(crate) fn decode_word<V>(word: U256) -> V
+where
+    V: SolValue + core::convert::From<<<V as SolValue>::SolType as SolType>::RustType>,
+{
+    let bytes: [u8; 32] = word.to_be_bytes();
+    V::abi_decode(&bytes, false).unwrap_or_else(|_| revert())
+}
+
+/// Encodes a value into the word stored in its slot.
+pub(crate) fn encode_word<V: SolValue>(value: &V) -> U256 {
+    let bytes = value.abi_encode();
+    let mut padded = [0u8; 32];
+    padded[..bytes.len()].copy_from_slice(&bytes);
+    U256::from_be_bytes(padded)
+}
+
 impl<V> DirectStorage<V> for Slot<V>
 where
     Self: StorageStorable<Value = V>,
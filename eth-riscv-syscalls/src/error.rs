@@ -7,4 +7,10 @@ pub enum Error {
     UnknownOpcode(u8),
     #[error("Parse error for syscall string. Input: {input}")]
     ParseError { input: Cow<'static, str> },
+    #[error("Unsupported R55 bytecode version: {0}")]
+    UnsupportedVersion(u8),
+    #[error("Truncated R55 bytecode")]
+    Truncated,
+    #[error("Call data too large for R55 code: {0} bytes")]
+    CalldataTooLarge(usize),
 }
@@ -0,0 +1,160 @@
+//! Header prepended to R55 bytecode, which tells it apart from EVM code and lets hosts know which
+//! format (syscall ABI and initcode layout) the contract was built for.
+//!
+//! ```text
+//! [MAGIC][version][flags][ELF]                                     deployed code
+//! [MAGIC][version][flags][codesize][deploy code][constructor_args] initcode
+//! ```
+//!
+//! Version 0 artifacts only have the magic byte, which is then directly followed by the ELF
+//! (`0x7f`) or, in initcode, by the big-endian codesize (whose first byte is zero). Since neither
+//! byte is a valid version, they are still recognized.
+//!
+//! The version also pins the syscall ABI the code was built against, so that hosts keep running
+//! code built before a syscall was added or changed with the semantics it expects:
+//!
+//! - 0: single byte prefix
+//! - 1: versioned header, with flags
+//! - 2: adds the `Panic` syscall, which the runtime issues instead of a revert when it panics
+//! - 3: adds the `SLoadMany` and `SStoreMany` syscalls, which batch the storage accesses of a
+//!   mapping
+//! - 4: `Call` and `StaticCall` take a 256-bit value, whose high limbs are passed in `a7`, `t1`
+//!   and `t2` (see `WIDE_CALL_VALUE`)
+//! - 5: adds the `Gas` syscall, and calls with a gas cap of zero forward all the available gas
+//!   (see `ZERO_GAS_CAP`)
+//!
+//! Hosts support every version up to `VERSION`, and refuse later ones.
+
+use crate::Error;
+
+/// First byte of any R55 bytecode.
+pub const MAGIC: u8 = 0xff;
+/// Format version emitted by this crate.
+pub const VERSION: u8 = 5;
+/// First version whose calls pass their value as a 256-bit word, rather than a 64-bit one in `a3`.
+pub const WIDE_CALL_VALUE: u8 = 4;
+/// First version whose calls forward all the available gas when their gas cap (`a6`) is zero,
+/// rather than none of it. Earlier code passes `u64::MAX` for that.
+pub const ZERO_GAS_CAP: u8 = 5;
+/// Size of the header, for the current version.
+pub const HEADER_SIZE: usize = 3;
+
+const ELF_MAGIC: u8 = 0x7f;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub version: u8,
+    /// Reserved for future use, always zero for now.
+    pub flags: u8,
+}
+
+impl Header {
+    /// Header of the current format.
+    pub const fn current() -> Self {
+        Self {
+            version: VERSION,
+            flags: 0,
+        }
+    }
+
+    pub const fn encode(&self) -> [u8; HEADER_SIZE] {
+        [MAGIC, self.version, self.flags]
+    }
+
+    /// Size of the header in the bytecode.
+    pub const fn size(&self) -> usize {
+        match self.version {
+            0 => 1,
+            _ => HEADER_SIZE,
+        }
+    }
+
+    /// Parses the header of `code`, returning it along with the code that follows it.
+    /// Returns `Ok(None)` if the code isn't R55 bytecode, and an error if its version is unknown or
+    /// if it ends within the header.
+    pub fn parse(code: &[u8]) -> Result<Option<(Self, &[u8])>, Error> {
+        let Some((&MAGIC, rest)) = code.split_first() else {
+            return Ok(None);
+        };
+
+        let header = match rest {
+            [0 | ELF_MAGIC, ..] | [] => Self {
+                version: 0,
+                flags: 0,
+            },
+            [_] => return Err(Error::Truncated),
+            [version @ 1..=VERSION, flags, ..] => Self {
+                version: *version,
+                flags: *flags,
+            },
+            [version, ..] => return Err(Error::UnsupportedVersion(*version)),
+        };
+
+        Ok(Some((header, &code[header.size()..])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_current_header() {
+        let mut code = Header::current().encode().to_vec();
+        code.extend_from_slice(&[ELF_MAGIC, b'E', b'L', b'F']);
+
+        let (header, rest) = Header::parse(&code).unwrap().unwrap();
+        assert_eq!(header, Header::current());
+        assert_eq!(rest, &[ELF_MAGIC, b'E', b'L', b'F']);
+    }
+
+    #[test]
+    fn test_parse_version_0() {
+        // Deployed code: the magic byte followed by the ELF
+        let code = [MAGIC, ELF_MAGIC, b'E', b'L', b'F'];
+        let (header, rest) = Header::parse(&code).unwrap().unwrap();
+        assert_eq!(header.version, 0);
+        assert_eq!(rest, &code[1..]);
+
+        // Initcode: the magic byte followed by the codesize
+        let initcode = [MAGIC, 0, 0, 0x10, 0x00, MAGIC];
+        let (header, rest) = Header::parse(&initcode).unwrap().unwrap();
+        assert_eq!(header.version, 0);
+        assert_eq!(rest, &initcode[1..]);
+    }
+
+    #[test]
+    fn test_parse_version_1() {
+        let code = [MAGIC, 1, 0, ELF_MAGIC];
+        let (header, rest) = Header::parse(&code).unwrap().unwrap();
+        assert_eq!(
+            header,
+            Header {
+                version: 1,
+                flags: 0
+            }
+        );
+        assert_eq!(rest, &[ELF_MAGIC]);
+    }
+
+    #[test]
+    fn test_parse_unknown_version() {
+        assert!(matches!(
+            Header::parse(&[MAGIC, VERSION + 1, 0, ELF_MAGIC]),
+            Err(Error::UnsupportedVersion(v)) if v == VERSION + 1
+        ));
+        assert!(Header::parse(&[0x60, 0x80]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_truncated_header() {
+        assert!(matches!(
+            Header::parse(&[MAGIC, VERSION]),
+            Err(Error::Truncated)
+        ));
+        assert!(matches!(
+            Header::parse(&[MAGIC, VERSION + 1]),
+            Err(Error::Truncated)
+        ));
+    }
+}
@@ -5,9 +5,12 @@ extern crate alloc;
 mod error;
 pub use error::Error;
 
+pub mod header;
+pub mod metadata;
+
 macro_rules! syscalls {
     ($(($num:expr, $identifier:ident, $name:expr)),* $(,)?) => {
-        #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+        #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
         #[repr(u8)]
         pub enum Syscall {
             $($identifier = $num),*
@@ -52,21 +55,43 @@ macro_rules! syscalls {
 // Generate `Syscall` enum with supported syscalls and their ids.
 //
 // The opcode for each syscall matches the corresponding EVM opcode,
-// as described on https://www.evm.codes.
+// as described on https://www.evm.codes. An `ecall` with any other
+// id, or with the id of a syscall that the header version of the code
+// predates, halts the frame and consumes all its gas, like an unknown opcode.
 //
 // t0: 0x20, opcode for keccak256, a0: offset, a1: size, returns keccak256 hash
 // t0: 0x30, opcode for address, returns an address
 // t0: 0x32, opcode for origin, returns an address
 // t0: 0x33, opcode for caller, returns an address
 // t0: 0x34, opcode for callvalue, a0: first limb, a1: second limb, a2: third limb, a3: fourth limb, returns 256-bit value
+// t0: 0x36, opcode for calldatasize, returns 64-bit value
+// t0: 0x37, opcode for calldatacopy, a0: memory offset, a1: calldata offset, a2: size, returns nothing
+//     (bytes past the end of the calldata are zeroes, as in the EVM)
 // t0: 0x3A, opcode for gasprice, returns 256-bit value
+// t0: 0x3B, opcode for extcodesize, a0-a2: address, returns 64-bit value
 // t0: 0x3d, opcode for returndatasize, returns 64-bit value
 // t0: 0x3e, opcode for returndatacopy, a0: memory offset, a1: return data offset, a2: return data size, returns nothing
+//     (reading past the end of the return data halts the frame, as in the EVM)
+// t0: 0x41, opcode for coinbase, returns an address
+// t0: 0x44, opcode for prevrandao (difficulty before the merge), returns 256-bit value
+// t0: 0x47, opcode for selfbalance, returns 256-bit value
+// t0: 0x4A, opcode for blobbasefee, returns 256-bit value
 // t0: 0x54, opcode for sload, a0: storage key, returns 256-bit value
 // t0: 0x55, opcode for sstore, a0-a3: 256-bit storage key, a4-a7: 256-bit storage value, returns nothing
-// t0: 0xf0, opcode for create, args: a0: 64-bit value, a1: calldata offset, a2: calldata size, returns an address
-// t0: 0xf1, opcode for call, args: a0-a2: address, a3: 64-bit value, a4: calldata offset, a5: calldata size
-// t0: 0xfa, opcode for staticcall, args: a0-a2: address, a3: 64-bit value, a4: calldata offset, a5: calldata size
+// t0: 0x5A, opcode for gas, returns the 64-bit gas left in the current frame (since version 5)
+// t0: 0x5C, opcode for tload, a0-a3: 256-bit transient storage key, returns 256-bit value
+// t0: 0x5D, opcode for tstore, a0-a3: 256-bit transient storage key, a4-a7: 256-bit value, returns nothing
+// t0: 0xf0, opcode for create, args: a0-a3: 256-bit value, a4: calldata offset, a5: calldata size, returns nothing
+//     (the created address is then retrieved with 0x01)
+// t0: 0xf5, opcode for create2, args: a0-a3: 256-bit value, a4: calldata offset, a5: calldata size,
+//     a6, a7, t1, t2: 256-bit salt, returns nothing (the created address is then retrieved with 0x01)
+// t0: 0xf1, opcode for call, args: a0-a2: address, a3, a7, t1, t2: 256-bit value (a3 only, as a
+//     64-bit value, before version 4), a4: calldata offset, a5: calldata size, a6: gas limit (capped
+//     to the forwardable gas, zero forwards all of it since version 5), returns whether the call
+//     succeeded
+// t0: 0xf4, opcode for delegatecall, args: same as call, except that the value is ignored (the
+//     value of the current call is kept), returns whether the call succeeded
+// t0: 0xfa, opcode for staticcall, args: same as call, returns whether the call succeeded
 // t0: 0xf3, opcode for return, a0: memory address of data, a1: length of data in bytes, doesn't return
 // t0: 0xfd, opcode for revert, doesn't return
 //
@@ -74,6 +99,16 @@ macro_rules! syscalls {
 // Because of that, they use (unused) EVM opcodes which RISC-V already implements.
 //
 // t0: 0x01, used to retrieve the created address cached in `RVEmu`
+// t0: 0x02, used to load the whole block and tx context at once, a0: memory offset of a 224-byte
+//     buffer, where timestamp, number, gaslimit, basefee, gasprice, chainid and origin are written
+//     as 32-byte big-endian words, returns nothing
+// t0: 0x03, same as revert, issued by the runtime when the contract panics, a0: memory address of
+//     data, a1: length of data in bytes, doesn't return (since version 2, older runtimes revert)
+// t0: 0x04, batched sload, a0: memory offset of the keys, a1: number of keys, a2: memory offset
+//     where the values are written, returns nothing (since version 3). Keys and values are 32-byte
+//     words, laid out as the little-endian limbs of a `U256`
+// t0: 0x05, batched sstore, a0: memory offset of the entries, a1: number of entries, returns
+//     nothing (since version 3). Each entry is a key word followed by its value word
 
 syscalls!(
     // EVM opcodes
@@ -82,22 +117,83 @@ syscalls!(
     (0x32, Origin, "origin"),
     (0x33, Caller, "caller"),
     (0x34, CallValue, "callvalue"),
+    (0x36, CallDataSize, "calldatasize"),
+    (0x37, CallDataCopy, "calldatacopy"),
     (0x3A, GasPrice, "gasprice"),
+    (0x3B, ExtCodeSize, "extcodesize"),
     (0x3D, ReturnDataSize, "returndatasize"),
     (0x3E, ReturnDataCopy, "returndatacopy"),
+    (0x41, Coinbase, "coinbase"),
     (0x42, Timestamp, "timestamp"),
     (0x43, Number, "number"),
+    (0x44, PrevRandao, "prevrandao"),
     (0x45, GasLimit, "gaslimit"),
     (0x46, ChainId, "chainid"),
+    (0x47, SelfBalance, "selfbalance"),
     (0x48, BaseFee, "basefee"),
+    (0x4A, BlobBaseFee, "blobbasefee"),
     (0x54, SLoad, "sload"),
     (0x55, SStore, "sstore"),
+    (0x5A, Gas, "gas"),
+    (0x5C, TLoad, "tload"),
+    (0x5D, TStore, "tstore"),
     (0xf0, Create, "create"),
+    (0xf5, Create2, "create2"),
     (0xf1, Call, "call"),
+    (0xf4, DelegateCall, "delegatecall"),
     (0xfa, StaticCall, "staticcall"),
     (0xf3, Return, "return"),
     (0xfd, Revert, "revert"),
     (0xA0, Log, "log"),
     // R55 exceptions
     (0x01, ReturnCreateAddress, "returncreateaddress"),
+    (0x02, Context, "context"),
+    (0x03, Panic, "panic"),
+    (0x04, SLoadMany, "sloadmany"),
+    (0x05, SStoreMany, "sstoremany"),
 );
+
+impl Syscall {
+    /// Header version that introduced the syscall, which code stamped with an older version can't
+    /// use (see `header::VERSION`).
+    pub const fn introduced_in(&self) -> u8 {
+        match self {
+            Self::Panic => 2,
+            Self::SLoadMany | Self::SStoreMany => 3,
+            Self::Gas => 5,
+            _ => 0,
+        }
+    }
+
+    /// Syscall with the given id in the syscall ABI of the header `version`, if any. Ids that the
+    /// version doesn't define, including the ones of later syscalls, aren't recognized.
+    pub fn decode(version: u8, id: u64) -> Option<Self> {
+        let syscall = Self::try_from(u8::try_from(id).ok()?).ok()?;
+        (syscall.introduced_in() <= version).then_some(syscall)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_per_version() {
+        assert_eq!(Syscall::decode(header::VERSION, 0x03), Some(Syscall::Panic));
+        assert_eq!(Syscall::decode(1, 0x03), None);
+        assert_eq!(
+            Syscall::decode(header::VERSION, 0x04),
+            Some(Syscall::SLoadMany)
+        );
+        assert_eq!(Syscall::decode(2, 0x04), None);
+        assert_eq!(Syscall::decode(2, 0x05), None);
+        assert_eq!(Syscall::decode(header::VERSION, 0x5a), Some(Syscall::Gas));
+        assert_eq!(Syscall::decode(4, 0x5a), None);
+        assert_eq!(Syscall::decode(0, 0xf3), Some(Syscall::Return));
+        assert_eq!(Syscall::decode(1, 0xf3), Some(Syscall::Return));
+
+        // Ids are full registers, which aren't truncated
+        assert_eq!(Syscall::decode(header::VERSION, 0x1f3), None);
+        assert_eq!(Syscall::decode(header::VERSION, 0xee), None);
+    }
+}
@@ -0,0 +1,332 @@
+//! Metadata trailer that `r55-compile` appends to the runtime bytecode, analogous to the one of
+//! solc: a CBOR map followed by its length as a 2-byte big-endian integer.
+//!
+//! ```text
+//! [header][ELF][CBOR map { "source": bytes32, "r55": text, "toolchain": text, "maxGas"? }][length]
+//! ```
+//!
+//! `maxGas` is only present if some methods have a `#[max_gas]` budget, as an array of
+//! `[selector: bytes4, max_gas: uint, name: text]` arrays.
+//!
+//! The trailer isn't loaded by the emulator, but it is part of the deployed code (and thus of its
+//! size), like on the EVM.
+
+use alloc::{string::String, vec::Vec};
+
+const SOURCE_KEY: &str = "source";
+const VERSION_KEY: &str = "r55";
+const TOOLCHAIN_KEY: &str = "toolchain";
+const MAX_GAS_KEY: &str = "maxGas";
+
+/// ELF section where `#[contract]` emits the `#[max_gas]` budgets of the methods, which
+/// `r55-compile` moves to the trailer. See `GasBudget::parse_section` for its layout.
+pub const MAX_GAS_SECTION: &str = ".r55.max_gas";
+
+// CBOR major types
+const MAJOR_UINT: u8 = 0;
+const MAJOR_BYTES: u8 = 2 << 5;
+const MAJOR_TEXT: u8 = 3 << 5;
+const MAJOR_ARRAY: u8 = 4 << 5;
+const MAJOR_MAP: u8 = 5 << 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// Hash of the sources the contract was compiled from
+    pub source_hash: [u8; 32],
+    /// Version of `r55-compile`
+    pub version: String,
+    /// Rust toolchain used to compile the contract
+    pub toolchain: String,
+    /// Gas budgets of the methods annotated with `#[max_gas]`
+    pub max_gas: Vec<GasBudget>,
+}
+
+/// Gas budget of a method, which the calls to it must not exceed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasBudget {
+    pub selector: [u8; 4],
+    pub max_gas: u64,
+    /// Name of the method, as in the contract's source
+    pub name: String,
+}
+
+impl GasBudget {
+    /// Parses the contents of the `MAX_GAS_SECTION` of a binary, made of one entry per method:
+    ///   ```text
+    ///   [selector: 4 bytes][max_gas: 8 bytes, big-endian][name length: 1 byte][name]
+    ///   ```
+    pub fn parse_section(mut data: &[u8]) -> Option<Vec<Self>> {
+        let mut budgets = Vec::new();
+        while !data.is_empty() {
+            let (fixed, rest) = data.split_at_checked(13)?;
+            let (name, rest) = rest.split_at_checked(fixed[12] as usize)?;
+            budgets.push(Self {
+                selector: fixed[..4].try_into().ok()?,
+                max_gas: u64::from_be_bytes(fixed[4..12].try_into().ok()?),
+                name: String::from(core::str::from_utf8(name).ok()?),
+            });
+            data = rest;
+        }
+
+        Some(budgets)
+    }
+}
+
+impl Metadata {
+    /// Encodes the trailer: the CBOR map and its length.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut cbor = Vec::new();
+        let entries = if self.max_gas.is_empty() { 3 } else { 4 };
+        encode_header(&mut cbor, MAJOR_MAP, entries);
+        encode_item(&mut cbor, MAJOR_TEXT, SOURCE_KEY.as_bytes());
+        encode_item(&mut cbor, MAJOR_BYTES, &self.source_hash);
+        encode_item(&mut cbor, MAJOR_TEXT, VERSION_KEY.as_bytes());
+        encode_item(&mut cbor, MAJOR_TEXT, self.version.as_bytes());
+        encode_item(&mut cbor, MAJOR_TEXT, TOOLCHAIN_KEY.as_bytes());
+        encode_item(&mut cbor, MAJOR_TEXT, self.toolchain.as_bytes());
+        if !self.max_gas.is_empty() {
+            encode_item(&mut cbor, MAJOR_TEXT, MAX_GAS_KEY.as_bytes());
+            encode_header(&mut cbor, MAJOR_ARRAY, self.max_gas.len() as u64);
+            for budget in &self.max_gas {
+                encode_header(&mut cbor, MAJOR_ARRAY, 3);
+                encode_item(&mut cbor, MAJOR_BYTES, &budget.selector);
+                encode_header(&mut cbor, MAJOR_UINT, budget.max_gas);
+                encode_item(&mut cbor, MAJOR_TEXT, budget.name.as_bytes());
+            }
+        }
+
+        let length = cbor.len() as u16;
+        cbor.extend_from_slice(&length.to_be_bytes());
+        cbor
+    }
+
+    /// Decodes the trailer at the end of `code`, if it has a valid one.
+    pub fn decode(code: &[u8]) -> Option<Self> {
+        split(code).map(|(_, metadata)| metadata)
+    }
+
+    /// Returns `code` without its trailer. Code without a valid trailer is returned as is.
+    pub fn strip(code: &[u8]) -> &[u8] {
+        split(code).map_or(code, |(code, _)| code)
+    }
+
+    /// Budget of the method with the given selector, if it has one.
+    pub fn max_gas_of(&self, selector: &[u8]) -> Option<&GasBudget> {
+        self.max_gas
+            .iter()
+            .find(|budget| budget.selector.as_slice() == selector)
+    }
+}
+
+fn split(code: &[u8]) -> Option<(&[u8], Metadata)> {
+    let (rest, length) = code.split_at_checked(code.len().checked_sub(2)?)?;
+    let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+    let (code, cbor) = rest.split_at_checked(rest.len().checked_sub(length)?)?;
+
+    let mut decoder = Decoder(cbor);
+    let entries = decoder.header(MAJOR_MAP)?;
+    if entries != 3 && entries != 4 {
+        return None;
+    }
+
+    let mut metadata = Metadata {
+        source_hash: [0; 32],
+        version: String::new(),
+        toolchain: String::new(),
+        max_gas: Vec::new(),
+    };
+    for key in [SOURCE_KEY, VERSION_KEY, TOOLCHAIN_KEY] {
+        if decoder.item(MAJOR_TEXT)? != key.as_bytes() {
+            return None;
+        }
+        match key {
+            SOURCE_KEY => metadata.source_hash = decoder.item(MAJOR_BYTES)?.try_into().ok()?,
+            VERSION_KEY => metadata.version = decoder.text()?,
+            _ => metadata.toolchain = decoder.text()?,
+        }
+    }
+    if entries == 4 {
+        if decoder.item(MAJOR_TEXT)? != MAX_GAS_KEY.as_bytes() {
+            return None;
+        }
+        for _ in 0..decoder.header(MAJOR_ARRAY)? {
+            if decoder.header(MAJOR_ARRAY)? != 3 {
+                return None;
+            }
+            metadata.max_gas.push(GasBudget {
+                selector: decoder.item(MAJOR_BYTES)?.try_into().ok()?,
+                max_gas: decoder.header(MAJOR_UINT)?,
+                name: decoder.text()?,
+            });
+        }
+    }
+
+    // The whole map must be consumed
+    decoder.0.is_empty().then_some((code, metadata))
+}
+
+// Writes the initial byte of an item and its argument: the length of byte strings, texts and
+// containers, or the value of unsigned integers
+fn encode_header(out: &mut Vec<u8>, major: u8, value: u64) {
+    match value {
+        0..24 => out.push(major | value as u8),
+        24..0x100 => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..0x10000 => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x10000..0x1_0000_0000 => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+fn encode_item(out: &mut Vec<u8>, major: u8, data: &[u8]) {
+    encode_header(out, major, data.len() as u64);
+    out.extend_from_slice(data);
+}
+
+/// Minimal CBOR decoder, limited to what `Metadata::encode` emits.
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let (taken, rest) = self.0.split_at_checked(n)?;
+        self.0 = rest;
+        Some(taken)
+    }
+
+    /// Reads the header of an item of the given major type, returning its argument (the length
+    /// of the item, or the value of an unsigned integer).
+    fn header(&mut self, major: u8) -> Option<u64> {
+        let initial = self.take(1)?[0];
+        if initial & 0xe0 != major {
+            return None;
+        }
+
+        let size = match initial & 0x1f {
+            value @ 0..24 => return Some(value as u64),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            _ => return None,
+        };
+        let mut value = [0; 8];
+        value[8 - size..].copy_from_slice(self.take(size)?);
+        Some(u64::from_be_bytes(value))
+    }
+
+    fn item(&mut self, major: u8) -> Option<&'a [u8]> {
+        let len = self.header(major)?;
+        self.take(len.try_into().ok()?)
+    }
+
+    fn text(&mut self) -> Option<String> {
+        let text = self.item(MAJOR_TEXT)?;
+        core::str::from_utf8(text).ok().map(String::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn metadata() -> Metadata {
+        Metadata {
+            source_hash: [0xab; 32],
+            version: "0.1.0".into(),
+            toolchain: "nightly-2025-01-07".into(),
+            max_gas: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_metadata_roundtrip() {
+        let elf = [0x7f, b'E', b'L', b'F', 0, 1, 2, 3];
+        let mut code = elf.to_vec();
+        code.extend_from_slice(&metadata().encode());
+
+        assert_eq!(Metadata::decode(&code), Some(metadata()));
+        assert_eq!(Metadata::strip(&code), &elf);
+    }
+
+    #[test]
+    fn test_metadata_with_max_gas() {
+        let elf = [0x7f, b'E', b'L', b'F', 0, 1, 2, 3];
+        let metadata = Metadata {
+            max_gas: vec![
+                GasBudget {
+                    selector: [0xa9, 0x05, 0x9c, 0xbb],
+                    max_gas: 60_000,
+                    name: "transfer".into(),
+                },
+                GasBudget {
+                    selector: [0x09, 0x5e, 0xa7, 0xb3],
+                    max_gas: u64::MAX,
+                    name: "approve".into(),
+                },
+            ],
+            ..metadata()
+        };
+        let mut code = elf.to_vec();
+        code.extend_from_slice(&metadata.encode());
+
+        let decoded = Metadata::decode(&code).unwrap();
+        assert_eq!(decoded, metadata);
+        assert_eq!(
+            decoded
+                .max_gas_of(&[0xa9, 0x05, 0x9c, 0xbb])
+                .unwrap()
+                .max_gas,
+            60_000
+        );
+        assert_eq!(decoded.max_gas_of(&[0, 0, 0, 0]), None);
+        assert_eq!(Metadata::strip(&code), &elf);
+    }
+
+    #[test]
+    fn test_parse_max_gas_section() {
+        let mut section = vec![0xa9, 0x05, 0x9c, 0xbb];
+        section.extend_from_slice(&60_000u64.to_be_bytes());
+        section.push(8);
+        section.extend_from_slice(b"transfer");
+        section.extend_from_slice(&[0x09, 0x5e, 0xa7, 0xb3]);
+        section.extend_from_slice(&1u64.to_be_bytes());
+        section.push(0);
+
+        let budgets = GasBudget::parse_section(&section).unwrap();
+        assert_eq!(budgets.len(), 2);
+        assert_eq!(budgets[0].name, "transfer");
+        assert_eq!(budgets[0].max_gas, 60_000);
+        assert_eq!(budgets[1].selector, [0x09, 0x5e, 0xa7, 0xb3]);
+        assert_eq!(budgets[1].name, "");
+
+        // Truncated entries are rejected
+        assert_eq!(
+            GasBudget::parse_section(&section[..section.len() - 1]),
+            None
+        );
+        assert_eq!(GasBudget::parse_section(&[]), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_code_without_metadata() {
+        let elf = [0x7f, b'E', b'L', b'F', 0, 1, 2, 3];
+        assert_eq!(Metadata::decode(&elf), None);
+        assert_eq!(Metadata::strip(&elf), &elf);
+        assert_eq!(Metadata::strip(&[]), &[]);
+
+        // A truncated trailer isn't recognized
+        let mut code = elf.to_vec();
+        code.extend_from_slice(&metadata().encode());
+        code.remove(elf.len());
+        assert_eq!(Metadata::decode(&code), None);
+    }
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+use core::default::Default;
+
+use contract_derive::{contract, storage};
+use eth_riscv_runtime::types::*;
+
+use alloy_core::primitives::{Address, B256};
+
+extern crate alloc;
+
+// Factory deploying EIP-1167 clones of a single implementation, used by the r55 test suite
+#[storage]
+pub struct CloneFactory {
+    implementation: Slot<Address>,
+}
+
+#[contract]
+impl CloneFactory {
+    pub fn new(implementation: Address) -> Self {
+        let mut factory = CloneFactory::default();
+        factory.implementation.write(implementation);
+        factory
+    }
+
+    pub fn implementation(&self) -> Address {
+        self.implementation.read()
+    }
+
+    // Deploys a clone with CREATE
+    pub fn create_clone(&mut self) -> Address {
+        eth_riscv_runtime::create::clone(self.implementation.read())
+    }
+
+    // Deploys a clone with CREATE2, at the address returned by `predict_clone`
+    pub fn create_clone_deterministic(&mut self, salt: B256) -> Address {
+        eth_riscv_runtime::create::clone_deterministic(self.implementation.read(), salt)
+    }
+
+    pub fn predict_clone(&self, salt: B256) -> Address {
+        eth_riscv_runtime::create::predict_clone_address(self.implementation.read(), salt, this())
+    }
+}
@@ -0,0 +1,20 @@
+//! Debug events shared by the example contracts. Plain library code rather than a contract: the
+//! events are emitted on behalf of the contract calling it.
+#![no_std]
+
+use alloy_core::primitives::{keccak256, B256, U256};
+use contract_derive::Event;
+use eth_riscv_runtime::{log, MutableCallCtx};
+
+#[derive(Event)]
+pub struct DebugLog {
+    #[indexed]
+    pub tag: B256,
+    pub value: U256,
+}
+
+/// Emits `DebugLog(keccak256(tag), value)`. Only callable from methods that can modify the state,
+/// e.g. with the `&self` of a `&mut self` method.
+pub fn debug_log(ctx: &impl MutableCallCtx, tag: &str, value: U256) {
+    log::emit_with(ctx, DebugLog::new(keccak256(tag), value));
+}
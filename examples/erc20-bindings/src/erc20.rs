@@ -0,0 +1,76 @@
+//! Bindings of the `ERC20` contract (`examples/erc20/src/lib.rs`).
+//!
+//! Generated by `r55-compile --emit-bindings`: don't edit them, re-run the command instead.
+
+use alloy::primitives::U256;
+
+alloy::sol! {
+    #[sol(rpc)]
+    interface ERC20 {
+        function mint(address to, uint256 amount) external payable returns (bool);
+        function deposit() external payable returns (bool);
+        function approve(address spender, uint256 amount) external returns (bool);
+        function transfer(address to, uint256 amount) external returns (bool);
+        function transfer_from(address from, address to, uint256 amount) external returns (bool);
+        function transfer_ownership(address new_owner) external returns (bool);
+        function debug_supply() external returns (uint256);
+        function owner() external view returns (address);
+        function decimals() external view returns (uint256);
+        function total_supply() external view returns (uint256);
+        function balance_of(address owner) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function callers() external view returns (address, address);
+
+        event Transfer(address indexed from, address indexed to, uint256 amount);
+        event Approval(address indexed owner, address indexed spender, uint256 amount);
+        event OwnershipTransferred(address indexed from, address indexed to);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ERC20Error {
+    OnlyOwner,
+    InsufficientBalance(U256),
+    InsufficientAllowance(U256),
+    SelfApproval,
+    SelfTransfer,
+    ZeroAmount,
+    ZeroAddress,
+}
+
+impl ERC20Error {
+    /// Decodes the revert data of a call, unless it isn't one of these errors
+    pub fn abi_decode(data: &[u8]) -> Option<Self> {
+        use alloy::sol_types::SolValue;
+
+        let (selector, data) = data.split_first_chunk::<4>()?;
+        match selector {
+            // ERC20Error::OnlyOwner
+            [0x0a, 0xce, 0x66, 0x39] => Some(Self::OnlyOwner),
+            // ERC20Error::InsufficientBalance(uint256)
+            [0x55, 0xbc, 0x84, 0xf6] => {
+                let (field0,) = <(U256,)>::abi_decode(data).ok()?;
+                Some(Self::InsufficientBalance(field0))
+            }
+            // ERC20Error::InsufficientAllowance(uint256)
+            [0x47, 0xd8, 0xf2, 0x7d] => {
+                let (field0,) = <(U256,)>::abi_decode(data).ok()?;
+                Some(Self::InsufficientAllowance(field0))
+            }
+            // ERC20Error::SelfApproval
+            [0x91, 0x73, 0xbe, 0xea] => Some(Self::SelfApproval),
+            // ERC20Error::SelfTransfer
+            [0xda, 0xdd, 0x74, 0xf0] => Some(Self::SelfTransfer),
+            // ERC20Error::ZeroAmount
+            [0x54, 0x9b, 0x54, 0xc9] => Some(Self::ZeroAmount),
+            // ERC20Error::ZeroAddress
+            [0x99, 0xc5, 0xd9, 0x1e] => Some(Self::ZeroAddress),
+            _ => None,
+        }
+    }
+
+    /// Decodes the revert data of a failed call, if any
+    pub fn from_call_error(error: &alloy::contract::Error) -> Option<Self> {
+        Self::abi_decode(&error.as_revert_data()?)
+    }
+}
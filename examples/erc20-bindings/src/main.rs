@@ -0,0 +1,132 @@
+//! Transfers tokens of a deployed `ERC20` contract, through its alloy bindings:
+//!   RPC_URL=<url> PRIVATE_KEY=<key> cargo run -- <token> <to> <amount>
+//!
+//! `src/erc20.rs` is rendered from the contract's sources, from the root of the repo:
+//!   cargo compile -- --emit-bindings target/bindings && cp target/bindings/erc20.rs examples/erc20-bindings/src/
+
+mod erc20;
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::ProviderBuilder,
+    signers::local::PrivateKeySigner,
+};
+use erc20::{ERC20Error, ERC20};
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let mut arg = |name: &str| args.next().ok_or_else(|| eyre::eyre!("Missing <{}>", name));
+    let token: Address = arg("token")?.parse()?;
+    let to: Address = arg("to")?.parse()?;
+    let amount: U256 = arg("amount")?.parse()?;
+
+    let signer: PrivateKeySigner = std::env::var("PRIVATE_KEY")?.parse()?;
+    let provider = ProviderBuilder::new()
+        .wallet(signer)
+        .connect_http(std::env::var("RPC_URL")?.parse()?);
+    let erc20 = ERC20::new(token, provider);
+
+    let pending = match erc20.transfer(to, amount).send().await {
+        Ok(pending) => pending,
+        Err(err) => match ERC20Error::from_call_error(&err) {
+            Some(error) => eyre::bail!("Transfer reverted with {:?}", error),
+            None => return Err(err.into()),
+        },
+    };
+
+    let receipt = pending.get_receipt().await?;
+    if let Some(transfer) = receipt.decoded_log::<ERC20::Transfer>() {
+        println!(
+            "Transferred {} from {} to {} in {}",
+            transfer.amount, transfer.from, transfer.to, receipt.transaction_hash
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        primitives::{address, keccak256},
+        sol_types::{SolEvent, SolValue},
+        transports::mock::Asserter,
+    };
+
+    const TOKEN: Address = address!("0x000000000000000000000000000000000000dead");
+    const ALICE: Address = address!("0x000000000000000000000000000000000000a11c");
+    const BOB: Address = address!("0x0000000000000000000000000000000000000b0b");
+
+    // Calldata built by the interface that `#[contract]` generates (`IERC20`), as checked by the
+    // r55 tests
+    fn interface_calldata(method: &str) -> Vec<u8> {
+        include_str!("../interface-calldata.txt")
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(' '))
+            .find(|(name, _)| *name == method)
+            .map(|(_, calldata)| alloy::hex::decode(calldata).unwrap())
+            .unwrap_or_else(|| panic!("No calldata for {method}"))
+    }
+
+    #[test]
+    fn test_calldata() {
+        // Never sends a request
+        let provider = ProviderBuilder::new().connect_mocked_client(Asserter::new());
+        let erc20 = ERC20::new(TOKEN, provider);
+        let amount = U256::from(1_000);
+
+        assert_eq!(
+            erc20.transfer(BOB, amount).calldata()[..],
+            interface_calldata("transfer")
+        );
+        assert_eq!(
+            erc20.transfer_from(ALICE, BOB, amount).calldata()[..],
+            interface_calldata("transfer_from")
+        );
+        assert_eq!(
+            erc20.balance_of(ALICE).calldata()[..],
+            interface_calldata("balance_of")
+        );
+        assert_eq!(
+            erc20.total_supply().calldata()[..],
+            interface_calldata("total_supply")
+        );
+    }
+
+    #[test]
+    fn test_events() {
+        assert_eq!(
+            ERC20::Transfer::SIGNATURE_HASH,
+            keccak256("Transfer(address,address,uint256)")
+        );
+        assert_eq!(
+            ERC20::OwnershipTransferred::SIGNATURE_HASH,
+            keccak256("OwnershipTransferred(address,address)")
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        // As encoded by `#[derive(Error)]`
+        let revert_data = [
+            &keccak256("ERC20Error::InsufficientBalance(uint256)")[..4],
+            &U256::from(5).abi_encode()[..],
+        ]
+        .concat();
+        assert_eq!(
+            ERC20Error::abi_decode(&revert_data),
+            Some(ERC20Error::InsufficientBalance(U256::from(5)))
+        );
+        assert_eq!(
+            ERC20Error::abi_decode(&keccak256("ERC20Error::ZeroAmount")[..4]),
+            Some(ERC20Error::ZeroAmount)
+        );
+
+        // `Error(string)`
+        let revert_data = [&[0x08, 0xc3, 0x79, 0xa0][..], &"reason".abi_encode()].concat();
+        assert_eq!(ERC20Error::abi_decode(&revert_data), None);
+    }
+}
@@ -7,6 +7,7 @@ use contract_derive::{contract, payable, storage, Event, Error};
 use eth_riscv_runtime::types::*;
 
 use alloy_core::primitives::{Address, U256};
+use debug_log::debug_log;
 
 extern crate alloc;
 
@@ -56,21 +57,23 @@ pub struct ERC20 {
     balance_of: Mapping<Address, Slot<U256>>,
     allowance_of: Mapping<Address, Mapping<Address, Slot<U256>>>,
     owner: Slot<Address>,
+    decimals: Slot<U256>,
     // TODO: handle string storage
     // name: String, 
     // symbol: String,
-    // decimals: u8,
 }
 
 #[contract]
 impl ERC20 {
     // -- CONSTRUCTOR ----------------------------------------------------------
-    pub fn new(owner: Address) -> Self {
+    // Deployments can leave out `decimals`, which defaults to 18
+    pub fn new(owner: Address, #[default(U256::from(18))] decimals: U256) -> Self {
         // Init the contract
         let mut erc20 = ERC20::default();
 
         // Update state
         erc20.owner.write(owner);
+        erc20.decimals.write(decimals);
 
         // Return the initialized contract
         erc20
@@ -95,6 +98,23 @@ impl ERC20 {
         Ok(true)
     }
 
+    // Wraps the ETH sent with the call into an equal amount of tokens, like WETH
+    #[payable]
+    pub fn deposit(&mut self) -> Result<bool, ERC20Error> {
+        let (to, amount) = (msg_sender(), msg_value());
+
+        // Perform sanity checks
+        if amount == U256::ZERO { return Err(ERC20Error::ZeroAmount) };
+
+        // Update state
+        self.balance_of[to] += amount;
+        self.total_supply += amount;
+
+        // Emit event + return
+        log::emit(Transfer::new(Address::ZERO, to, amount));
+        Ok(true)
+    }
+
     pub fn approve(&mut self, spender: Address, amount: U256) -> Result<bool, ERC20Error> {
         let owner = msg_sender();
 
@@ -175,11 +195,22 @@ impl ERC20 {
         Ok(true)
     }
 
+    // Logs the total supply, through the `debug-log` library shared with other contracts
+    pub fn debug_supply(&mut self) -> U256 {
+        let supply = self.total_supply.read();
+        debug_log(&self, "erc20.total_supply", supply);
+        supply
+    }
+
     // -- READ-ONLY FUNCTIONS --------------------------------------------------
     pub fn owner(&self) -> Address {
         self.owner.read()
     }
 
+    pub fn decimals(&self) -> U256 {
+        self.decimals.read()
+    }
+
     pub fn total_supply(&self) -> U256 {
         self.total_supply.read()
     }
@@ -191,4 +222,9 @@ impl ERC20 {
     pub fn allowance(&self, owner: Address, spender: Address) -> U256 {
         self.allowance_of[owner][spender].read()
     }
+
+    // Caller of the current call and sender of the tx, which differ when called through a contract
+    pub fn callers(&self) -> (Address, Address) {
+        (msg_sender(), tx_origin())
+    }
 }
@@ -1,12 +1,13 @@
 //! Auto-generated based on Cargo.toml dependencies
 //! This file provides `Deployable` implementations for contract dependencies
-//! TODO (phase-2): rather than using `fn deploy(args: Args)`, figure out the constructor selector from the contract dependency
+//! Constructor args are type-checked against the ones exported by each dependency, which may
+//! leave out the trailing args with a default
 
 use alloy_core::primitives::{Address, Bytes};
 use eth_riscv_runtime::{create::Deployable, InitInterface, ReadOnly};
 use core::include_bytes;
 
-use erc20::IERC20;
+use erc20::{IERC20, ERC20ConstructorArgs, ERC20RequiredArgs};
 
 const ERC20_BYTECODE: &'static [u8] = include_bytes!("../../../../r55-output-bytecode/erc20.bin");
 
@@ -14,6 +15,8 @@ pub struct ERC20;
 
 impl Deployable for ERC20 {
     type Interface = IERC20<ReadOnly>;
+    type ConstructorArgs = ERC20ConstructorArgs;
+    type RequiredArgs = ERC20RequiredArgs;
 
     fn __runtime() -> &'static [u8] {
         ERC20_BYTECODE
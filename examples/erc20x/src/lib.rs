@@ -3,16 +3,29 @@
 
 use core::default::Default;
 
-use alloy_core::primitives::{Address, U256, Bytes};
-use contract_derive::{contract, show_streams};
+use alloy_core::primitives::{Address, B256, U256, Bytes};
+use alloy_sol_types::sol;
+use contract_derive::{contract, payable};
+use debug_log::debug_log;
 
 extern crate alloc;
+use alloc::vec::Vec;
 
 use erc20::{ERC20Error, IERC20};
 
 mod deployable;
 use deployable::ERC20;
 
+// Solidity interface of the `SimpleStorage` fixture, called through the `sol!` bridge
+sol! {
+    interface ISimpleStorage {
+        function get() external view returns (uint256);
+        function set(uint256 num) external;
+        // Not implemented by the fixture, so calling it reverts
+        function reset() external;
+    }
+}
+
 #[derive(Default, )]
 pub struct ERC20x;
 
@@ -26,20 +39,104 @@ impl ERC20x {
         (token.address(), owner)
     }
 
+    // Deploys a new ERC20 token instance, without leaving out the decimals
+    pub fn x_deploy_with_decimals(&mut self, owner: Address, decimals: U256) -> Address {
+        let token = ERC20::deploy((owner, decimals)).with_ctx(self);
+        token.address()
+    }
+
+    // Deploys a new ERC20 token instance with CREATE2, at the address predicted by `x_predict`
+    pub fn x_deploy2(&mut self, owner: Address, salt: B256) -> Address {
+        let token = ERC20::deploy2(owner, salt).with_ctx(self);     // IERC20<ReadWrite>
+        token.address()
+    }
+
+    // Address of the ERC20 token deployed by `x_deploy2`, without deploying it
+    pub fn x_predict(&self, owner: Address, salt: B256) -> Address {
+        let init_code_hash = ERC20::deploy2(owner, salt).init_code_hash();
+        eth_riscv_runtime::create::compute_create2_address(this(), salt, init_code_hash)
+    }
+
+    // Fails to deploy a new ERC20 token instance, as its constructor only takes the owner and the decimals
+    // pub fn x_deploy_fails(&mut self, owner: Address) -> Address {
+    //     let token = ERC20::deploy((owner, U256::ZERO, U256::ZERO)).with_ctx(self);  // `DeployArgs<IERC20<ReadOnly>>` is not implemented for `(Address, U256, U256)`
+    //     token.address()
+    // }
+
     // Performs a staticcall to an ERC20
     pub fn x_balance_of(&self, owner: Address, token_addr: Address) -> Option<U256> {
         let token = IERC20::new(token_addr).with_ctx(self);         // IERC20<ReadOnly>
         token.balance_of(owner)
     }
 
+    // Logs the balance of `owner` in an ERC20, through the `debug-log` library shared with it
+    pub fn x_debug_balance(&mut self, owner: Address, token_addr: Address) -> U256 {
+        let token = IERC20::new(token_addr).with_ctx(&*self);     // IERC20<ReadOnly>
+        let balance = token.balance_of(owner).expect("ERC20 call failed");
+        debug_log(&self, "erc20x.balance_of", balance);
+        balance
+    }
+
+    // Checks that there is code at the ERC20 address before interacting with it
+    pub fn x_is_contract(&self, token_addr: Address) -> bool {
+        IERC20::new_checked(token_addr).is_some()
+    }
+
+    // Reads the balance and allowance of `owner`, and the total supply of an ERC20, in a single batch
+    pub fn x_summary(&self, owner: Address, spender: Address, token_addr: Address) -> (U256, U256, U256) {
+        let token = IERC20::new(token_addr).with_ctx(self);         // IERC20<ReadOnly>
+        let mut multicall = Multicall::new();
+        multicall
+            .add(token_addr, token.encode_balance_of(owner))
+            .add(token_addr, token.encode_allowance(owner, spender))
+            .add(token_addr, token.encode_total_supply());
+
+        let values: Vec<U256> = multicall
+            .call()
+            .into_iter()
+            .map(|(success, output)| match success {
+                true => U256::abi_decode(&output, true).expect("Unable to decode output"),
+                false => panic!("ERC20 call failed"),
+            })
+            .collect();
+        (values[0], values[1], values[2])
+    }
+
+    // Same as `x_summary`, but with a separate interface call for each value
+    pub fn x_summary_separate(&self, owner: Address, spender: Address, token_addr: Address) -> (U256, U256, U256) {
+        let token = IERC20::new(token_addr).with_ctx(self);         // IERC20<ReadOnly>
+        (
+            token.balance_of(owner).expect("ERC20 call failed"),
+            token.allowance(owner, spender).expect("ERC20 call failed"),
+            token.total_supply().expect("ERC20 call failed"),
+        )
+    }
+
+    // Calldata that the ERC20 interface builds for a few of its methods, without calling them
+    pub fn x_interface_calldata(&mut self, from: Address, to: Address, amount: U256) -> (Bytes, Bytes, Bytes, Bytes) {
+        let token = IERC20::new(Address::ZERO).with_ctx(self);      // IERC20<ReadWrite>
+        (
+            token.encode_transfer(to, amount),
+            token.encode_transfer_from(from, to, amount),
+            token.encode_balance_of(from),
+            token.encode_total_supply(),
+        )
+    }
+
+    // Reads the callers seen by an ERC20, which this contract calls on behalf of the tx sender
+    pub fn x_callers(&self, token_addr: Address) -> (Address, Address) {
+        let token = IERC20::new(token_addr).with_ctx(self);         // IERC20<ReadOnly>
+        token.callers().expect("ERC20 call failed")
+    }
+
     // Performs a (mutable) call to an ERC20
-    pub fn x_mint(&mut self, to: Address, amount: U256, token_addr: Address) -> Result<bool, ERC20Error> {
+    pub fn x_mint(&mut self, to: Address, amount: U256, token_addr: Address) -> Result<bool, CallError<ERC20Error>> {
         let mut token = IERC20::new(token_addr).with_ctx(self);     // IERC20<ReadWrite>
         token.mint(to, amount)
     }
 
     // Fails to perform a (mutable) call to an ERC20, due to the lack of mutability in the ERC20x method
-    // pub fn x_mint_fails(&self, to: Address, token_addr: Address) -> Result<bool, ERC20Error> {
+    // pub fn x_mint_fails(&self, to: Address, token_addr: Address) -> Result<bool, CallError<ERC20Error>> {
     //     let mut token = IERC20::new(token_addr).with_ctx(self);  // IERC20<ReadOnly>
     //     token.mint(to, amount)
     // }
@@ -50,18 +147,51 @@ impl ERC20x {
         from: Address,
         amount: U256,
         token_addr: Address
-    ) -> Result<bool, ERC20Error> {
+    ) -> Result<bool, CallError<ERC20Error>> {
         let mut token = IERC20::new(token_addr).with_ctx(self);     // IERC20<ReadWrite>
         let to = msg_sender();
 
         // easily leverage rust's `Result<T, E>` enum to deal with call reverts
         match token.transfer_from(from, to, amount) {
-            Err(ERC20Error::InsufficientBalance(max)) => token.transfer_from(from, to, max),
-            Err(ERC20Error::InsufficientAllowance(max)) => token.transfer_from(from, to, max),
+            Err(CallError::Custom(ERC20Error::InsufficientBalance(max))) => token.transfer_from(from, to, max),
+            Err(CallError::Custom(ERC20Error::InsufficientAllowance(max))) => token.transfer_from(from, to, max),
             other => other
         }
     }
 
+    // Transfers tokens held by the contract, and returns the raw outcome of the call
+    pub fn x_try_transfer(&mut self, to: Address, amount: U256, token_addr: Address) -> (bool, Bytes) {
+        let mut token = IERC20::new(token_addr).with_ctx(self);     // IERC20<ReadWrite>
+        let result = token.try_transfer(to, amount);
+        (result.success, result.data)
+    }
+
+    // Wraps the ETH sent with the call, by forwarding it to the token's payable `deposit()`
+    #[payable]
+    pub fn x_wrap(&mut self, token_addr: Address) -> Result<bool, CallError<ERC20Error>> {
+        let token = IERC20::new(token_addr).with_ctx(self);         // IERC20<ReadWrite>
+        token.with_value(msg_value()).deposit()
+    }
+
+    // Sets a value on a Solidity contract, and reads it back with a staticcall
+    pub fn x_sol_set(&mut self, target: Address, value: U256) -> U256 {
+        ISimpleStorage::setCall { num: value }.call_on(target, &mut *self).expect("set() failed");
+        ISimpleStorage::getCall {}.staticcall_on(target, self).expect("get() failed")._0
+    }
+
+    // Reads a value from a Solidity contract (staticcall, as the method is read-only)
+    pub fn x_sol_get(&self, target: Address) -> U256 {
+        ISimpleStorage::getCall {}.call_on(target, self).expect("get() failed")._0
+    }
+
+    // Calls a function that a Solidity contract doesn't implement, and returns its revert data
+    pub fn x_sol_reset(&mut self, target: Address) -> (bool, Bytes) {
+        match (ISimpleStorage::resetCall {}).call_on(target, self) {
+            Ok(_) => (true, Bytes::new()),
+            Err(revert_data) => (false, revert_data),
+        }
+    }
+
     // Always reverts with a str msg
     pub fn panics(&self) { panic!("This function always panics"); }
 
@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+use core::default::Default;
+
+use contract_derive::{contract, storage};
+use eth_riscv_runtime::types::*;
+
+use alloy_core::primitives::{Address, Bytes, B256, U256};
+
+extern crate alloc;
+
+// `bytes4(keccak256("onERC721Received(address,address,uint256,bytes)"))`
+const ON_ERC721_RECEIVED: [u8; 4] = [0x15, 0x0b, 0x7a, 0x02];
+
+// Recipient of ERC721 safe transfers, used by the r55 test suite. Depending on `accept`, it either
+// acknowledges the transfers or answers the hook with a wrong value
+#[storage]
+pub struct ERC721Receiver {
+    accept: Slot<bool>,
+    last_operator: Slot<Address>,
+    last_from: Slot<Address>,
+    last_id: Slot<U256>,
+    last_data_hash: Slot<B256>,
+}
+
+#[contract]
+impl ERC721Receiver {
+    pub fn new(accept: bool) -> Self {
+        let mut receiver = ERC721Receiver::default();
+        receiver.accept.write(accept);
+        receiver
+    }
+
+    #[allow(non_snake_case)]
+    pub fn onERC721Received(&mut self, operator: Address, from: Address, id: U256, data: Bytes) -> [u8; 4] {
+        if !self.accept.read() {
+            return [0; 4];
+        }
+
+        self.last_operator.write(operator);
+        self.last_from.write(from);
+        self.last_id.write(id);
+        self.last_data_hash.write(keccak256(&data));
+
+        ON_ERC721_RECEIVED
+    }
+
+    // Returns the operator, sender, id and data hash of the last accepted transfer
+    pub fn last_received(&self) -> (Address, Address, U256, B256) {
+        (
+            self.last_operator.read(),
+            self.last_from.read(),
+            self.last_id.read(),
+            self.last_data_hash.read(),
+        )
+    }
+}
@@ -3,7 +3,7 @@
 
 use core::default::Default;
 
-use contract_derive::{contract, payable, storage, Event, Error};
+use contract_derive::{contract, interface, payable, storage, Event, Error};
 use eth_riscv_runtime::types::*;
 
 use alloy_core::primitives::{address, Address, U256, Bytes};
@@ -56,10 +56,22 @@ pub enum ERC721Error {
     NotMinted,
     OnlyOwner,
     Unauthorized,
+    UnsafeRecipient,
     WrongFrom,
     ZeroAddress,
 }
 
+// -- RECEIVER HOOK ------------------------------------------------------------
+// Value that receivers must return to accept a safe transfer:
+// `bytes4(keccak256("onERC721Received(address,address,uint256,bytes)"))`
+pub const ON_ERC721_RECEIVED: [u8; 4] = [0x15, 0x0b, 0x7a, 0x02];
+
+#[interface]
+trait IERC721Receiver {
+    #[allow(non_snake_case)]
+    fn onERC721Received(&mut self, operator: Address, from: Address, id: U256, data: Bytes) -> [u8; 4];
+}
+
 // -- CONTRACT -----------------------------------------------------------------
 #[storage]
 pub struct ERC721 {
@@ -69,6 +81,7 @@ pub struct ERC721 {
     approval_of: Mapping<U256, Slot<Address>>,
     is_operator: Mapping<Address, Mapping<Address, Slot<bool>>>,
     owner: Slot<Address>,
+    token_uri: Mapping<U256, StorageString>,
     // TODO: handle string storage
     // name: String, 
     // symbol: String,
@@ -158,6 +171,54 @@ impl ERC721 {
         Ok(true)
     }
 
+    pub fn safe_transfer_from(&mut self, from: Address, to: Address, id: U256, data: Bytes) -> Result<bool, ERC721Error> {
+        self.transfer_from(from, to, id)?;
+
+        // Contract recipients must acknowledge the transfer, EOAs have no hook to call
+        if let Some(receiver) = IERC721Receiver::new_checked(to) {
+            let mut receiver = receiver.with_ctx(self);
+            match receiver.onERC721Received(msg_sender(), from, id, data) {
+                Some(ON_ERC721_RECEIVED) => {}
+                _ => return Err(ERC721Error::UnsafeRecipient),
+            }
+        }
+
+        Ok(true)
+    }
+
+    pub fn burn(&mut self, id: U256) -> Result<bool, ERC721Error> {
+        let owner = self.owner_of[id].read();
+        if owner == Address::ZERO { return Err(ERC721Error::NotMinted) };
+
+        // Check authorization
+        let sender = msg_sender();
+        if sender != owner && !self.is_operator[owner][sender].read() {
+            return Err(ERC721Error::Unauthorized);
+        }
+
+        // Update state, clearing the metadata of the token
+        self.owner_of[id].write(Address::ZERO);
+        self.approval_of[id].write(Address::ZERO);
+        self.token_uri[id].delete();
+
+        self.balance_of[owner] -= U256::from(1);
+        self.total_supply -= U256::from(1);
+
+        // Emit event + return
+        log::emit(Transfer::new(owner, Address::ZERO, id));
+        Ok(true)
+    }
+
+    pub fn set_token_uri(&mut self, id: U256, uri: String) -> Result<bool, ERC721Error> {
+        // Perform sanity checks
+        if msg_sender() != self.owner.read() { return Err(ERC721Error::OnlyOwner) };
+        if self.owner_of[id].read() == Address::ZERO { return Err(ERC721Error::NotMinted) };
+
+        // Update state
+        self.token_uri[id].write(uri);
+        Ok(true)
+    }
+
     pub fn transfer_ownership(&mut self, new_owner: Address) -> Result<bool, ERC721Error> {
         // Perform safety check 
         let from = msg_sender();
@@ -191,6 +252,13 @@ impl ERC721 {
         Ok(self.balance_of[owner].read())
     }
 
+    pub fn token_uri(&self, id: U256) -> Result<String, ERC721Error> {
+        if self.owner_of[id].read() == Address::ZERO {
+            return Err(ERC721Error::NotMinted);
+        }
+        Ok(self.token_uri[id].read())
+    }
+
     pub fn get_approved(&self, id: U256) -> Address {
         self.approval_of[id].read()
     }
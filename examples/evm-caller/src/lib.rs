@@ -21,7 +21,7 @@ trait ISimpleStorage {
 #[contract]
 impl EVMCaller {
     pub fn x_set(&mut self, target: Address, value: U256) {
-        ISimpleStorage::new(target).with_ctx(self).set(value);
+        ISimpleStorage::new(target).with_ctx(self).set(value).expect("Unable to set value");
     }
 
     pub fn x_get(&self, target: Address) -> U256 {
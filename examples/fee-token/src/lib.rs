@@ -0,0 +1,109 @@
+#![no_std]
+#![no_main]
+
+use core::default::Default;
+
+use contract_derive::{contract, contract_base, storage, Error, Event};
+use eth_riscv_runtime::{types::*, *};
+
+use alloy_core::primitives::{Address, U256};
+
+extern crate alloc;
+
+// Token that takes a fee on transfers, used by the r55 test suite. The ERC20 logic lives in
+// `ERC20Base`, which `FeeToken` inherits: `balance_of` is dispatched to the base, while `transfer`
+// is overridden to pay the fee to the treasury first
+
+#[derive(Event)]
+pub struct Transfer {
+    #[indexed]
+    pub from: Address,
+    #[indexed]
+    pub to: Address,
+    pub amount: U256,
+}
+
+#[derive(Error)]
+pub enum ERC20Error {
+    InsufficientBalance(U256),
+}
+
+#[storage]
+pub struct ERC20Base {
+    total_supply: Slot<U256>,
+    balance_of: Mapping<Address, Slot<U256>>,
+}
+
+#[contract_base]
+impl ERC20Base {
+    pub fn total_supply(&self) -> U256 {
+        self.total_supply.read()
+    }
+
+    pub fn balance_of(&self, owner: Address) -> U256 {
+        self.balance_of[owner].read()
+    }
+
+    pub fn transfer(&mut self, to: Address, amount: U256) -> Result<bool, ERC20Error> {
+        self.move_balance(msg_sender(), to, amount)?;
+        Ok(true)
+    }
+
+    fn mint(&mut self, to: Address, amount: U256) {
+        self.total_supply += amount;
+        self.balance_of[to] += amount;
+
+        log::emit(Transfer::new(Address::ZERO, to, amount));
+    }
+
+    fn move_balance(&mut self, from: Address, to: Address, amount: U256) -> Result<(), ERC20Error> {
+        let from_balance = self.balance_of[from].read();
+        if from_balance < amount {
+            return Err(ERC20Error::InsufficientBalance(from_balance));
+        }
+
+        self.balance_of[from].write(from_balance - amount);
+        self.balance_of[to] += amount;
+
+        log::emit(Transfer::new(from, to, amount));
+        Ok(())
+    }
+}
+
+// Its fields are laid out after the ones of the base
+#[storage]
+#[inherit(ERC20Base)]
+pub struct FeeToken {
+    treasury: Slot<Address>,
+    fee_bps: Slot<U256>,
+}
+
+#[contract]
+impl FeeToken {
+    pub fn new(owner: Address, supply: U256, treasury: Address, fee_bps: U256) -> Self {
+        let mut token = FeeToken::default();
+
+        token.mint(owner, supply);
+        token.treasury.write(treasury);
+        token.fee_bps.write(fee_bps);
+
+        token
+    }
+
+    // Shadows `ERC20Base::transfer`, which moves what's left after the fee
+    pub fn transfer(&mut self, to: Address, amount: U256) -> Result<bool, ERC20Error> {
+        let fee = amount * self.fee_bps.read() / U256::from(10_000);
+        let treasury = self.treasury.read();
+
+        self.move_balance(msg_sender(), treasury, fee)?;
+        ERC20Base::transfer(self, to, amount - fee)
+    }
+
+    pub fn treasury(&self) -> Address {
+        self.treasury.read()
+    }
+
+    pub fn fee_bps(&self) -> U256 {
+        self.fee_bps.read()
+    }
+}
@@ -0,0 +1,39 @@
+extern crate alloc;
+use core::default::Default;
+
+use contract_derive::{contract, storage};
+use eth_riscv_runtime::{types::*, *};
+
+use alloy_core::primitives::{Address, U256};
+
+#[storage]
+pub struct FormattedReasons {
+    balance_of: Mapping<Address, Slot<U256>>,
+}
+
+#[contract]
+impl FormattedReasons {
+    pub fn new() -> Self {
+        FormattedReasons::default()
+    }
+
+    pub fn deposit(&mut self, amount: U256) {
+        self.balance_of[msg_sender()] += amount;
+    }
+
+    // Returns the balance left
+    pub fn withdraw(&mut self, amount: U256) -> U256 {
+        let owner = msg_sender();
+        let balance = self.balance_of[owner].read();
+        require!(
+            amount <= balance,
+            "balance of {} is {}, below {}",
+            owner,
+            balance,
+            amount
+        );
+
+        self.balance_of[owner].write(balance - amount);
+        balance - amount
+    }
+}
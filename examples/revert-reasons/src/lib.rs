@@ -0,0 +1,7 @@
+#![no_std]
+#![no_main]
+
+// Two vaults that only differ in the reason they revert with on overdrawn withdrawals: a constant
+// one, or one formatted with `require!` args. The r55 test suite compares their code size
+pub mod formatted;
+pub mod plain;
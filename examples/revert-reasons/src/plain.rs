@@ -0,0 +1,33 @@
+extern crate alloc;
+use core::default::Default;
+
+use contract_derive::{contract, storage};
+use eth_riscv_runtime::{types::*, *};
+
+use alloy_core::primitives::{Address, U256};
+
+#[storage]
+pub struct PlainReasons {
+    balance_of: Mapping<Address, Slot<U256>>,
+}
+
+#[contract]
+impl PlainReasons {
+    pub fn new() -> Self {
+        PlainReasons::default()
+    }
+
+    pub fn deposit(&mut self, amount: U256) {
+        self.balance_of[msg_sender()] += amount;
+    }
+
+    // Returns the balance left
+    pub fn withdraw(&mut self, amount: U256) -> U256 {
+        let owner = msg_sender();
+        let balance = self.balance_of[owner].read();
+        require!(amount <= balance, "insufficient balance");
+
+        self.balance_of[owner].write(balance - amount);
+        balance - amount
+    }
+}
@@ -0,0 +1,78 @@
+extern crate alloc;
+use core::default::Default;
+
+use contract_derive::{contract, contract_impl, storage};
+use eth_riscv_runtime::{types::*, *};
+
+use alloy_core::primitives::{Address, U256};
+
+use crate::{Approval, IERC20Std, Transfer};
+
+// Token whose whole supply is minted on deployment
+#[storage]
+pub struct FixedToken {
+    total_supply: Slot<U256>,
+    balance_of: Mapping<Address, Slot<U256>>,
+    allowance_of: Mapping<Address, Mapping<Address, Slot<U256>>>,
+}
+
+#[contract(IERC20Std)]
+impl FixedToken {
+    pub fn new(owner: Address, supply: U256) -> Self {
+        let mut token = FixedToken::default();
+
+        token.total_supply.write(supply);
+        token.balance_of[owner].write(supply);
+
+        log::emit(Transfer::new(Address::ZERO, owner, supply));
+        token
+    }
+
+    fn move_balance(&mut self, from: Address, to: Address, amount: U256) {
+        let from_balance = self.balance_of[from].read();
+        require!(from_balance >= amount, "insufficient balance");
+
+        self.balance_of[from].write(from_balance - amount);
+        self.balance_of[to] += amount;
+
+        log::emit(Transfer::new(from, to, amount));
+    }
+}
+
+#[contract_impl]
+impl IERC20Std for FixedToken {
+    fn total_supply(&self) -> U256 {
+        self.total_supply.read()
+    }
+
+    fn balance_of(&self, owner: Address) -> U256 {
+        self.balance_of[owner].read()
+    }
+
+    fn allowance(&self, owner: Address, spender: Address) -> U256 {
+        self.allowance_of[owner][spender].read()
+    }
+
+    fn transfer(&mut self, to: Address, amount: U256) -> bool {
+        self.move_balance(msg_sender(), to, amount);
+        true
+    }
+
+    fn approve(&mut self, spender: Address, amount: U256) -> bool {
+        let owner = msg_sender();
+        self.allowance_of[owner][spender].write(amount);
+
+        log::emit(Approval::new(owner, spender, amount));
+        true
+    }
+
+    fn transfer_from(&mut self, from: Address, to: Address, amount: U256) -> bool {
+        let spender = msg_sender();
+        let allowance = self.allowance_of[from][spender].read();
+        require!(allowance >= amount, "insufficient allowance");
+
+        self.allowance_of[from][spender].write(allowance - amount);
+        self.move_balance(from, to, amount);
+        true
+    }
+}
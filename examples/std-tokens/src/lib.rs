@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+pub mod fixed;
+pub mod mintable;
+
+use alloy_core::primitives::{Address, U256};
+use contract_derive::{interface, Event};
+
+// Two tokens implementing the same interface, used by the r55 test suite. The methods of the
+// interface are dispatched under the standard ERC20 selectors (e.g. `balanceOf(address)`)
+#[interface("camelCase")]
+trait IERC20Std {
+    fn total_supply(&self) -> U256;
+    fn balance_of(&self, owner: Address) -> U256;
+    fn allowance(&self, owner: Address, spender: Address) -> U256;
+    fn transfer(&mut self, to: Address, amount: U256) -> bool;
+    fn approve(&mut self, spender: Address, amount: U256) -> bool;
+    fn transfer_from(&mut self, from: Address, to: Address, amount: U256) -> bool;
+}
+
+#[derive(Event)]
+pub struct Transfer {
+    #[indexed]
+    pub from: Address,
+    #[indexed]
+    pub to: Address,
+    pub amount: U256,
+}
+
+#[derive(Event)]
+pub struct Approval {
+    #[indexed]
+    pub owner: Address,
+    #[indexed]
+    pub spender: Address,
+    pub amount: U256,
+}
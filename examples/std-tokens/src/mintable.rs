@@ -0,0 +1,93 @@
+extern crate alloc;
+use core::default::Default;
+
+use contract_derive::{contract, contract_impl, storage};
+use eth_riscv_runtime::{types::*, *};
+
+use alloy_core::primitives::{Address, U256};
+
+use crate::{Approval, IERC20Std, Transfer};
+
+// Token minted on demand by its owner
+#[storage]
+pub struct MintableToken {
+    owner: Slot<Address>,
+    total_supply: Slot<U256>,
+    balance_of: Mapping<Address, Slot<U256>>,
+    allowance_of: Mapping<Address, Mapping<Address, Slot<U256>>>,
+}
+
+#[contract(IERC20Std)]
+impl MintableToken {
+    pub fn new(owner: Address) -> Self {
+        let mut token = MintableToken::default();
+        token.owner.write(owner);
+        token
+    }
+
+    pub fn mint(&mut self, to: Address, amount: U256) -> bool {
+        require!(msg_sender() == self.owner.read(), "only owner");
+
+        self.total_supply += amount;
+        self.balance_of[to] += amount;
+
+        log::emit(Transfer::new(Address::ZERO, to, amount));
+        true
+    }
+
+    // Balance of `owner` on any `IERC20Std` token, e.g. the fixed one
+    pub fn balance_on(&self, token: Address, owner: Address) -> U256 {
+        IERC20Std::new(token)
+            .with_ctx(self)
+            .balance_of(owner)
+            .expect("Unable to get balance")
+    }
+
+    fn move_balance(&mut self, from: Address, to: Address, amount: U256) {
+        let from_balance = self.balance_of[from].read();
+        require!(from_balance >= amount, "insufficient balance");
+
+        self.balance_of[from].write(from_balance - amount);
+        self.balance_of[to] += amount;
+
+        log::emit(Transfer::new(from, to, amount));
+    }
+}
+
+#[contract_impl]
+impl IERC20Std for MintableToken {
+    fn total_supply(&self) -> U256 {
+        self.total_supply.read()
+    }
+
+    fn balance_of(&self, owner: Address) -> U256 {
+        self.balance_of[owner].read()
+    }
+
+    fn allowance(&self, owner: Address, spender: Address) -> U256 {
+        self.allowance_of[owner][spender].read()
+    }
+
+    fn transfer(&mut self, to: Address, amount: U256) -> bool {
+        self.move_balance(msg_sender(), to, amount);
+        true
+    }
+
+    fn approve(&mut self, spender: Address, amount: U256) -> bool {
+        let owner = msg_sender();
+        self.allowance_of[owner][spender].write(amount);
+
+        log::emit(Approval::new(owner, spender, amount));
+        true
+    }
+
+    fn transfer_from(&mut self, from: Address, to: Address, amount: U256) -> bool {
+        let spender = msg_sender();
+        let allowance = self.allowance_of[from][spender].read();
+        require!(allowance >= amount, "insufficient allowance");
+
+        self.allowance_of[from][spender].write(allowance - amount);
+        self.move_balance(from, to, amount);
+        true
+    }
+}
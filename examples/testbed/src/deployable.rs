@@ -0,0 +1,25 @@
+//! Auto-generated based on Cargo.toml dependencies
+//! This file provides `Deployable` implementations for contract dependencies
+//! Constructor args are type-checked against the ones exported by each dependency, which may
+//! leave out the trailing args with a default
+
+use alloy_core::primitives::{Address, Bytes};
+use eth_riscv_runtime::{create::Deployable, InitInterface, ReadOnly};
+use core::include_bytes;
+
+use vault::{IVault, VaultConstructorArgs, VaultRequiredArgs};
+
+const VAULT_BYTECODE: &'static [u8] = include_bytes!("../../../../r55-output-bytecode/vault.bin");
+
+pub struct Vault;
+
+impl Deployable for Vault {
+    type Interface = IVault<ReadOnly>;
+    type ConstructorArgs = VaultConstructorArgs;
+    type RequiredArgs = VaultRequiredArgs;
+
+    fn __runtime() -> &'static [u8] {
+        VAULT_BYTECODE
+    }
+}
+
@@ -0,0 +1,732 @@
+#![no_std]
+#![no_main]
+
+use core::default::Default;
+
+use alloc::{string::String, vec::Vec};
+
+use alloy_core::primitives::{Address, Bytes, B256, U256};
+use contract_derive::{contract, interface, max_gas, payable, storage, Error, Event};
+use eth_riscv_runtime::types::*;
+
+extern crate alloc;
+
+mod deployable;
+use deployable::Vault;
+
+// Start of the RISC-V DRAM, where the calldata region lives
+const DRAM_START: u64 = 0x8000_0000;
+
+#[derive(Error)]
+pub enum TestbedError {
+    // 4-byte selector + 2 words = 68 bytes, not a multiple of 32
+    Pair(U256, U256),
+    Zero,
+    LengthMismatch,
+}
+
+// Event with an indexed dynamic field, which is only logged as a hash
+#[derive(Event)]
+pub struct Note {
+    #[indexed]
+    pub tag: String,
+    #[indexed]
+    pub author: Address,
+    pub message: String,
+}
+
+// Event with as many indexed fields as fit in a log, emitted with LOG4
+#[derive(Event)]
+pub struct Routed {
+    #[indexed]
+    pub from: Address,
+    #[indexed]
+    pub to: Address,
+    #[indexed]
+    pub id: U256,
+    pub amount: U256,
+}
+
+// Any account that can be paid, contract or not
+#[interface]
+trait IPayee {}
+
+// Contract used by the r55 test suite to exercise the runtime and the host syscalls
+#[storage]
+pub struct Testbed {
+    lock: ReentrancyLock,
+    implementation: Slot<Address>,
+    balances: Mapping<Address, Slot<U256>>,
+    // Written at deployment, as the testbed has no constructor
+    #[init(U256::from(1_000_000))]
+    total_supply: Slot<U256>,
+}
+
+#[contract]
+impl Testbed {
+    // Hashes `size` bytes of raw memory, so that the cost only depends on the syscall size
+    pub fn hash_memory(&self, size: u64) -> U256 {
+        eth_riscv_runtime::keccak256_raw(DRAM_START, size)
+    }
+
+    pub fn hash(&self, data: Bytes) -> B256 {
+        keccak256(&data)
+    }
+
+    pub fn hash_concat(&self, a: Bytes, b: Bytes) -> B256 {
+        keccak256_concat(&[&a, &b])
+    }
+
+    // Emits a log with `topics` topics and `size` bytes of data, both read from raw memory
+    pub fn log_memory(&self, topics: u64, size: u64) {
+        eth_riscv_runtime::log::log(DRAM_START, size, DRAM_START, topics);
+    }
+
+    // Reads the block base fee, which is only available since London
+    pub fn base_fee(&self) -> U256 {
+        eth_riscv_runtime::block::base_fee()
+    }
+
+    // Reads the effective gas price of the tx
+    pub fn gas_price(&self) -> U256 {
+        eth_riscv_runtime::tx::gas_price()
+    }
+
+    // Returns `size` raw bytes of memory, skipping the ABI encoding
+    pub fn return_raw(&self, size: u64) {
+        eth_riscv_runtime::return_riscv(DRAM_START, size)
+    }
+
+    // Reverts with `size` raw bytes of memory, skipping the ABI encoding
+    pub fn revert_raw(&self, size: u64) {
+        let data = unsafe { eth_riscv_runtime::slice_from_raw_parts(DRAM_START as usize, size as usize) };
+        eth_riscv_runtime::revert_with_error(data)
+    }
+
+    // Always fails with a 68-byte error
+    pub fn fail_pair(&self, a: U256, b: U256) -> Result<bool, TestbedError> {
+        Err(TestbedError::Pair(a, b))
+    }
+
+    // Calls `target` and returns its raw output (or revert data), whatever its size
+    pub fn forward(&self, target: Address, calldata: Bytes) -> Bytes {
+        eth_riscv_runtime::call_contract(target, U256::ZERO, &calldata, None, None)
+    }
+
+    // Same as `forward`, but returns exactly `ret_size` bytes of the output
+    pub fn forward_sized(&self, target: Address, calldata: Bytes, ret_size: u64) -> Bytes {
+        eth_riscv_runtime::call_contract(target, U256::ZERO, &calldata, Some(ret_size), None)
+    }
+
+    // Calls `target`, then copies the first `words` words of its return data into a buffer of fixed
+    // size, so that only the size of the copy varies
+    pub fn copy_return_data(&self, target: Address, calldata: Bytes, words: u64) {
+        let _ = eth_riscv_runtime::call(
+            target,
+            U256::ZERO,
+            calldata.as_ptr() as u64,
+            calldata.len() as u64,
+            eth_riscv_runtime::ALL_GAS,
+        );
+        let mut buffer = Vec::<u8>::with_capacity(100 * 32);
+        eth_riscv_runtime::return_data_copy(buffer.as_mut_ptr() as u64, 0, words * 32);
+    }
+
+    // Calls `fail_pair` on `target` through its interface, and returns the decoded error fields
+    pub fn forward_pair(&self, target: Address, a: U256, b: U256) -> (U256, U256) {
+        let testbed = ITestbed::new(target).with_ctx(self);
+        match testbed.fail_pair(a, b) {
+            Err(CallError::Custom(TestbedError::Pair(a, b))) => (a, b),
+            Err(_) => panic!("Unexpected error"),
+            Ok(_) => panic!("Call succeeded"),
+        }
+    }
+
+    // Builds and drops `rounds` pairs of interleaved growing Vecs, and returns the heap usage
+    pub fn alloc_stress(&self, rounds: u64) -> u64 {
+        for round in 0..rounds {
+            let (mut words, mut bytes) = (Vec::new(), Vec::new());
+            for i in 0..64 {
+                words.push(round + i);
+                bytes.push(i as u8);
+            }
+
+            let expected = 64 * round + 63 * 64 / 2;
+            if words.iter().sum::<u64>() != expected || bytes[63] != 63 {
+                panic!("Corrupted allocation");
+            }
+        }
+
+        eth_riscv_runtime::heap_usage() as u64
+    }
+
+    // Reads `n` balances (cycling through 256 owners), and returns their sum and the heap usage
+    pub fn sum_balances(&self, n: u64) -> (U256, u64) {
+        let mut sum = U256::ZERO;
+        for i in 0..n {
+            sum += self.balances[Address::with_last_byte(i as u8)].read();
+        }
+
+        (sum, eth_riscv_runtime::heap_usage() as u64)
+    }
+
+    // Reads the balance of `owner` through a guard taken before `n` other balances are read
+    pub fn held_balance(&self, owner: Address, n: u64) -> U256 {
+        let guard = &self.balances[owner];
+        for i in 0..n {
+            self.balances[Address::with_last_byte(i as u8)].read();
+        }
+
+        guard.read()
+    }
+
+    // Returns the heap usage when the method is entered, i.e. after its args are decoded
+    pub fn heap_usage_on_entry(&self, _owner: Address, _amount: U256) -> u64 {
+        eth_riscv_runtime::heap_usage() as u64
+    }
+
+    // Reserves `size` bytes on the heap, which can exceed its capacity
+    pub fn alloc_bytes(&self, size: u64) -> u64 {
+        let bytes = core::hint::black_box(Vec::<u8>::with_capacity(size as usize));
+        bytes.capacity() as u64
+    }
+
+    // Fills `size` bytes of the heap with 0xff, which are left there when the frame returns
+    pub fn dirty_heap(&self, size: u64) -> u64 {
+        let bytes = core::hint::black_box(alloc::vec![0xff_u8; size as usize]);
+        bytes.len() as u64
+    }
+
+    // Counts the nonzero bytes of a fresh `size`-byte allocation, before anything is written to it
+    pub fn heap_garbage(&self, size: u64) -> u64 {
+        let bytes = core::hint::black_box(Vec::<u8>::with_capacity(size as usize));
+        (0..size as usize)
+            .filter(|&i| unsafe { core::ptr::read_volatile(bytes.as_ptr().add(i)) } != 0)
+            .count() as u64
+    }
+
+    // Returns the selector it was called with
+    pub fn own_selector(&self) -> u32 {
+        u32::from_be_bytes(msg_sig())
+    }
+
+    // Issues an `ecall` with `id` in t0, bypassing the runtime's syscall wrappers
+    pub fn raw_ecall(&self, id: u64) {
+        unsafe { core::arch::asm!("ecall", in("t0") id) }
+    }
+
+    // Calls `target`, and returns whether the call succeeded with its raw output (or revert data)
+    pub fn try_forward(&self, target: Address, calldata: Bytes) -> (bool, Bytes) {
+        match eth_riscv_runtime::try_call_contract(target, U256::ZERO, &calldata, None) {
+            Ok(output) => (true, output),
+            Err(data) => (false, data),
+        }
+    }
+
+    // Solidity 0.8 style addition, which reverts on overflow
+    pub fn checked_add(&self, a: U256, b: U256) -> U256 {
+        a.cadd(b)
+    }
+
+    // Solidity 0.8 style division, which reverts on division by zero
+    pub fn checked_div(&self, a: U256, b: U256) -> U256 {
+        a.cdiv(b)
+    }
+
+    // Explicit opt-out of the checks
+    pub fn wrapping_add(&self, a: U256, b: U256) -> U256 {
+        a.wrapping_add(b)
+    }
+
+    // Solidity's `unchecked { a - b }`
+    pub fn unchecked_sub(&self, a: U256, b: U256) -> U256 {
+        (Wrapping(a) - Wrapping(b)).0
+    }
+
+    // Sums `0..n` with checked additions
+    pub fn checked_sum(&self, n: u64) -> u64 {
+        let mut sum = 0u64;
+        for i in 0..n {
+            sum = sum.cadd(core::hint::black_box(i));
+        }
+        sum
+    }
+
+    // Sums `0..n` with unchecked additions
+    pub fn unchecked_sum(&self, n: u64) -> u64 {
+        let mut sum = Wrapping(0u64);
+        for i in 0..n {
+            sum += Wrapping(core::hint::black_box(i));
+        }
+        sum.0
+    }
+
+    // Reverts with `Error("value must be positive")` on zero
+    pub fn require_positive(&self, value: U256) -> U256 {
+        require!(value > U256::ZERO, "value must be positive");
+        value
+    }
+
+    // Returns the custom `Zero` error on zero
+    pub fn ensure_positive(&self, value: U256) -> Result<U256, TestbedError> {
+        ensure!(value > U256::ZERO, TestbedError::Zero);
+        Ok(value)
+    }
+
+    // Reverts with a formatted `Error` if `amount` exceeds 100
+    pub fn require_formatted(&self, amount: U256, owner: Address) -> U256 {
+        require!(
+            amount <= U256::from(100),
+            "amount {} ({:x}) too large for {}",
+            amount,
+            amount,
+            owner
+        );
+        amount
+    }
+
+    // Always reverts with a message longer than `MESSAGE_CAPACITY`
+    pub fn require_truncated(&self, owner: Address) {
+        require!(
+            false,
+            "{} {} {} {} {} {} {} {}",
+            owner,
+            owner,
+            owner,
+            owner,
+            owner,
+            owner,
+            owner,
+            owner
+        );
+    }
+
+    // `a - b`, reverting in a different way for each kind of failure
+    pub fn sub_nonzero(&self, a: U256, b: U256) -> Result<U256, TestbedError> {
+        require!(b != U256::MAX, "b is too large");
+        let diff = a.csub(b);
+        ensure!(diff > U256::ZERO, TestbedError::Zero);
+        Ok(diff)
+    }
+
+    // Calls `sub_nonzero` on `target` through its interface, and returns how the call failed:
+    // 0 on success, 1 for the declared error, 2 for `Error(string)`, 3 for a panic, along with
+    // the result, the panic code or the revert reason
+    pub fn sub_nonzero_outcome(&self, target: Address, a: U256, b: U256) -> (u64, U256, String) {
+        let testbed = ITestbed::new(target).with_ctx(self);
+        match testbed.sub_nonzero(a, b) {
+            Ok(diff) => (0, diff, String::new()),
+            Err(CallError::Custom(_)) => (1, U256::ZERO, String::new()),
+            Err(CallError::Revert(reason)) => (2, U256::ZERO, reason),
+            Err(CallError::Panic(code)) => (3, code, String::new()),
+            Err(CallError::Unknown(_)) => (4, U256::ZERO, String::new()),
+        }
+    }
+
+    // Calls `checked_sum` on `target` with at most `gas` gas, and returns whether it succeeded
+    pub fn capped_sum(&self, target: Address, n: u64, gas: u64) -> (bool, u64) {
+        let testbed = ITestbed::new(target).with_ctx(self).with_gas(gas);
+        match testbed.checked_sum(n) {
+            Some(sum) => (true, sum),
+            None => (false, 0),
+        }
+    }
+
+    // Gas spent by the caller on a `capped_sum`-like call, measured with `gas_left`
+    pub fn capped_sum_cost(&self, target: Address, n: u64, gas: u64) -> (bool, u64) {
+        let testbed = ITestbed::new(target).with_ctx(self).with_gas(gas);
+        let before = eth_riscv_runtime::gas_left();
+        let ok = testbed.checked_sum(n).is_some();
+        (ok, before - eth_riscv_runtime::gas_left())
+    }
+
+    // Gas left when the method is entered, before it runs anything
+    pub fn gas_left_on_entry(&self) -> u64 {
+        eth_riscv_runtime::gas_left()
+    }
+
+    pub fn add_mod(&self, a: U256, b: U256, n: U256) -> U256 {
+        addmod(a, b, n)
+    }
+
+    pub fn mul_mod(&self, a: U256, b: U256, n: U256) -> U256 {
+        mulmod(a, b, n)
+    }
+
+    // Calls `target` while holding the reentrancy lock, and returns its raw output
+    pub fn guarded_call(&self, target: Address, calldata: Bytes) -> Bytes {
+        let _guard = self.lock.enter();
+        eth_riscv_runtime::call_contract(target, U256::ZERO, &calldata, None, None)
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.lock.is_entered()
+    }
+
+    // Reads the block and tx context with a syscall per field
+    pub fn context_fields(&self) -> (U256, U256, u64, Address, U256) {
+        (
+            eth_riscv_runtime::block::timestamp(),
+            eth_riscv_runtime::block::number(),
+            eth_riscv_runtime::block::chain_id(),
+            eth_riscv_runtime::tx::origin(),
+            eth_riscv_runtime::block::base_fee(),
+        )
+    }
+
+    // Reads the block and tx context with a single syscall, the second load being cached
+    pub fn context_batched(&self) -> (U256, U256, u64, Address, U256) {
+        let (timestamp, number) = (Context::load().timestamp, Context::load().number);
+        let context = Context::load();
+        (
+            timestamp,
+            number,
+            context.chain_id,
+            context.origin,
+            context.base_fee,
+        )
+    }
+
+    // Reads every field of the block: number, timestamp, gas limit, base fee, coinbase,
+    // prevrandao and blob base fee
+    pub fn block_fields(&self) -> (U256, U256, U256, U256, Address, U256, U256) {
+        (
+            eth_riscv_runtime::block::number(),
+            eth_riscv_runtime::block::timestamp(),
+            eth_riscv_runtime::block::gas_limit(),
+            eth_riscv_runtime::block::base_fee(),
+            eth_riscv_runtime::block::coinbase(),
+            eth_riscv_runtime::block::prevrandao(),
+            eth_riscv_runtime::block::blob_base_fee(),
+        )
+    }
+
+    // Returns the current timestamp, or reverts once the block is past `deadline`
+    pub fn before_deadline(&self, deadline: u64) -> u64 {
+        let now = eth_riscv_runtime::block_timestamp();
+        eth_riscv_runtime::require!(now <= deadline, "deadline expired");
+        now
+    }
+
+    // EIP-712 domain separator of the testbed, as a token named "Testbed" would define it
+    pub fn domain_separator(&self) -> B256 {
+        eip712::Domain::current("Testbed", "1").separator()
+    }
+
+    // EIP-712 digest of an ERC-2612 permit, to be signed by the owner
+    pub fn permit_digest(
+        &self,
+        owner: Address,
+        spender: Address,
+        value: U256,
+        nonce: U256,
+        deadline: U256,
+    ) -> B256 {
+        let type_hash = keccak256(
+            b"Permit(address owner,address spender,uint256 value,uint256 nonce,uint256 deadline)",
+        );
+        let fields = (owner, spender, value, nonce, deadline).abi_encode();
+
+        let domain = eip712::Domain::current("Testbed", "1");
+        eip712::digest(&domain, eip712::hash_struct(type_hash, &fields))
+    }
+
+    pub fn set_balance(&mut self, owner: Address, amount: U256) {
+        self.balances[owner].write(amount);
+    }
+
+    pub fn balance_of(&self, owner: Address) -> U256 {
+        self.balances[owner].read()
+    }
+
+    // Doesn't `SSTORE` when `amount` is zero, as the balance is unchanged
+    pub fn add_balance(&mut self, owner: Address, amount: U256) {
+        self.balances[owner] += amount;
+    }
+
+    // Writes the balance back even though it is unchanged
+    pub fn touch_balance(&mut self, owner: Address) {
+        let balance = &mut self.balances[owner];
+        let amount = balance.read();
+        balance.force_write(amount);
+    }
+
+    // Well within its budget, to check that budgeted calls are recorded
+    #[max_gas(100_000)]
+    pub fn budgeted_balance_of(&self, owner: Address) -> U256 {
+        self.balances[owner].read()
+    }
+
+    // Deliberately over budget: a cold `SSTORE` alone costs more
+    #[max_gas(1_000)]
+    pub fn over_budget_write(&mut self, owner: Address, amount: U256) {
+        self.balances[owner].write(amount);
+    }
+
+    // Writes the balance and reverts, undoing the write
+    pub fn set_balance_and_revert(&mut self, owner: Address, amount: U256) {
+        self.balances[owner].write(amount);
+        eth_riscv_runtime::revert();
+    }
+
+    // Sets the balance through an external call to itself, and returns the balance it then reads
+    pub fn set_balance_via_self(&mut self, owner: Address, amount: U256) -> U256 {
+        let mut testbed = ITestbed::new(this()).with_ctx(self);
+        testbed.set_balance(owner, amount).expect("Self call failed");
+        self.balances[owner].read()
+    }
+
+    // Sets the balance, then calls itself with a write that reverts. Returns whether the inner call
+    // succeeded, and the balance read afterwards
+    pub fn set_balance_then_revert_via_self(
+        &mut self,
+        owner: Address,
+        amount: U256,
+    ) -> (bool, U256) {
+        self.balances[owner].write(amount);
+
+        let mut testbed = ITestbed::new(this()).with_ctx(self);
+        let success = testbed.set_balance_and_revert(owner, amount + U256::from(1)).is_ok();
+        (success, self.balances[owner].read())
+    }
+
+    // Sets the balance, failing on a zero amount
+    pub fn set_nonzero_balance(
+        &mut self,
+        owner: Address,
+        amount: U256,
+    ) -> Result<(), TestbedError> {
+        if amount == U256::ZERO {
+            return Err(TestbedError::Zero);
+        }
+        self.balances[owner].write(amount);
+        Ok(())
+    }
+
+    // Calls `set_nonzero_balance` on `target`. Returns 0 if it succeeded, 1 if it failed with `Zero`,
+    // and 2 otherwise
+    pub fn x_set_nonzero_balance(&mut self, target: Address, owner: Address, amount: U256) -> u64 {
+        let mut testbed = ITestbed::new(target).with_ctx(self);
+        match testbed.set_nonzero_balance(owner, amount) {
+            Ok(()) => 0,
+            Err(CallError::Custom(TestbedError::Zero)) => 1,
+            Err(_) => 2,
+        }
+    }
+
+    // Sets the balance of each recipient to its amount
+    pub fn airdrop(
+        &mut self,
+        recipients: Vec<Address>,
+        amounts: Vec<U256>,
+    ) -> Result<(), TestbedError> {
+        if recipients.len() != amounts.len() {
+            return Err(TestbedError::LengthMismatch);
+        }
+        for (recipient, amount) in recipients.into_iter().zip(amounts) {
+            self.balances[recipient].write(amount);
+        }
+        Ok(())
+    }
+
+    pub fn balances_of(&self, owners: Vec<Address>) -> Vec<U256> {
+        owners.into_iter().map(|owner| self.balances[owner].read()).collect()
+    }
+
+    // Same as `airdrop`, with a single batched write
+    pub fn airdrop_batched(
+        &mut self,
+        recipients: Vec<Address>,
+        amounts: Vec<U256>,
+    ) -> Result<(), TestbedError> {
+        if recipients.len() != amounts.len() {
+            return Err(TestbedError::LengthMismatch);
+        }
+        let entries: Vec<(Address, U256)> = recipients.into_iter().zip(amounts).collect();
+        self.balances.write_many(&entries);
+        Ok(())
+    }
+
+    // Same as `balances_of`, with a single batched read
+    pub fn balances_of_batched(&self, owners: Vec<Address>) -> Vec<U256> {
+        self.balances.read_many(&owners)
+    }
+
+    // Airdrops on `target`, then reads the balances back, both through its interface
+    pub fn x_airdrop(
+        &mut self,
+        target: Address,
+        recipients: Vec<Address>,
+        amounts: Vec<U256>,
+    ) -> Vec<U256> {
+        let mut testbed = ITestbed::new(target).with_ctx(self);
+        testbed.airdrop(recipients.clone(), amounts).expect("Airdrop failed");
+        testbed.balances_of(recipients).expect("Call failed")
+    }
+
+    // Returns the number of `items`, and their concatenation
+    pub fn concat(&self, items: Vec<Bytes>) -> (u64, Bytes) {
+        let data: Vec<u8> = items.iter().flat_map(|item| item.iter().copied()).collect();
+        (items.len() as u64, data.into())
+    }
+
+    // Reads the balance of `owner` in `target`, through its interface
+    pub fn peek_balance(&self, target: Address, owner: Address) -> U256 {
+        let testbed = ITestbed::new(target).with_ctx(self);
+        testbed.balance_of(owner).expect("Call failed")
+    }
+
+    // Sets the balance in this contract, then calls `set_balance_and_revert` on `target`. Returns
+    // whether the call succeeded
+    pub fn poke_and_revert(&mut self, target: Address, owner: Address, amount: U256) -> bool {
+        self.balances[owner].write(amount);
+
+        let mut testbed = ITestbed::new(target).with_ctx(self);
+        testbed.set_balance_and_revert(owner, amount).is_ok()
+    }
+
+    // Sets the balance, then reenters through `via` to read it, and to overwrite it in a frame that
+    // reverts. Returns the balance read by the reentrant frame, whether the overwrite succeeded, and
+    // the balance read afterwards
+    pub fn reentrant_balance(
+        &mut self,
+        via: Address,
+        owner: Address,
+        amount: U256,
+    ) -> (U256, bool, U256) {
+        self.balances[owner].write(amount);
+
+        let mut testbed = ITestbed::new(via).with_ctx(self);
+        let seen = testbed.peek_balance(this(), owner).expect("Call failed");
+        let overwritten = testbed
+            .poke_and_revert(this(), owner, amount + U256::from(1))
+            .expect("Call failed");
+
+        (seen, overwritten, self.balances[owner].read())
+    }
+
+    // Hashes the payload in `window`-byte chunks (see `hash_windows`). Returns the hash and the
+    // heap usage, which includes the decoded payload
+    pub fn hash_windows_owned(&self, payload: Bytes, window: u64) -> (B256, u64) {
+        let hash = hash_windows(payload.len(), window as usize, |offset, buf| {
+            buf.copy_from_slice(&payload[offset..offset + buf.len()])
+        });
+        (hash, eth_riscv_runtime::heap_usage() as u64)
+    }
+
+    // Same as `hash_windows_owned`, with the payload read from the calldata as it is hashed
+    pub fn hash_windows_streamed(
+        &self,
+        #[calldata] payload: CalldataReader,
+        window: u64,
+    ) -> (B256, u64) {
+        let hash = hash_windows(payload.len(), window as usize, |offset, buf| {
+            payload.read_into(offset, buf)
+        });
+        (hash, eth_riscv_runtime::heap_usage() as u64)
+    }
+
+    // Stores the sum of `0..=depth` as the balance of `owner`, computed recursively
+    pub fn store_recursive_sum(&mut self, owner: Address, depth: u64) -> U256 {
+        let sum = U256::from(recursive_sum(depth));
+        self.balances[owner].write(sum);
+        sum
+    }
+
+    pub fn read_slot(&self, slot: U256) -> U256 {
+        storage::read(slot)
+    }
+
+    pub fn write_slot(&mut self, slot: U256, value: U256) {
+        storage::write(self, slot, value);
+    }
+
+    // Deploys a vault owned by the caller, funded with the value sent to this method
+    #[payable]
+    pub fn deploy_vault(&mut self) -> Address {
+        let vault = Vault::deploy_with_value(msg_sender(), msg_value()).with_ctx(self);
+        vault.address()
+    }
+
+    // Deploys raw initcode, returning the zero address if the creation fails
+    pub fn create_raw(&mut self, init_code: Bytes) -> Address {
+        eth_riscv_runtime::create::create_raw(U256::ZERO, &init_code)
+    }
+
+    // Sends `amount` to `target` through an interface handle, with a gas cap unless `gas` is zero.
+    // Returns whether the transfer succeeded, and its revert data
+    #[payable]
+    pub fn pay(&mut self, target: Address, amount: U256, gas: u64) -> (bool, Bytes) {
+        let mut payee = IPayee::new(target).with_ctx(self);
+        if gas != 0 {
+            payee = payee.with_gas(gas);
+        }
+
+        match payee.send(amount) {
+            Ok(()) => (true, Bytes::new()),
+            Err(err) => (false, Bytes::from(eth_riscv_runtime::Error::abi_encode(&err))),
+        }
+    }
+
+    // Tries to send `amount` of its own balance to `target`, and records the attempt in `balances`
+    // whatever its outcome. Returns whether the transfer succeeded
+    pub fn send_and_record(&mut self, target: Address, amount: U256) -> bool {
+        let mut payee = IPayee::new(target).with_ctx(self);
+        let sent = payee.send(amount).is_ok();
+
+        self.balances[target].write(amount);
+        sent
+    }
+
+    pub fn note(&mut self, tag: String, message: String) {
+        log::emit(Note::new(tag, msg_sender(), message));
+    }
+
+    pub fn route(&mut self, to: Address, id: U256, amount: U256) {
+        log::emit(Routed::new(msg_sender(), to, id, amount));
+    }
+
+    // Sets the contract that the fallback forwards unknown calls to
+    pub fn set_implementation(&mut self, implementation: Address) {
+        self.implementation.write(implementation);
+    }
+
+    // Forwards the calldata to the implementation, and passes its output (or revert data) through
+    fn fallback(&mut self) {
+        let implementation = self.implementation.read();
+        if implementation == Address::ZERO {
+            eth_riscv_runtime::revert();
+        }
+
+        match eth_riscv_runtime::try_call_contract(implementation, U256::ZERO, msg_data(), None) {
+            Ok(output) => eth_riscv_runtime::return_with(&output),
+            Err(revert_data) => eth_riscv_runtime::revert_with(&revert_data),
+        }
+    }
+}
+
+// Keeps a 512-byte buffer alive in each frame, so that deep enough recursions overflow the stack
+#[inline(never)]
+fn recursive_sum(depth: u64) -> u64 {
+    let frame = core::hint::black_box([0u8; 512]);
+    if depth == 0 {
+        return 0;
+    }
+
+    depth + recursive_sum(depth - 1) + u64::from(frame[depth as usize % 512])
+}
+
+// Hashes `len` bytes, read with `read` in `window`-byte chunks, each chunk being hashed along with
+// the hash of the previous ones
+fn hash_windows(len: usize, window: usize, read: impl Fn(usize, &mut [u8])) -> B256 {
+    let mut buffer = alloc::vec![0u8; 32 + window];
+    let mut hash = B256::ZERO;
+    for offset in (0..len).step_by(window) {
+        let size = window.min(len - offset);
+        buffer[..32].copy_from_slice(hash.as_slice());
+        read(offset, &mut buffer[32..32 + size]);
+        hash = keccak256(&buffer[..32 + size]);
+    }
+
+    hash
+}
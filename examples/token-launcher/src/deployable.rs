@@ -0,0 +1,25 @@
+//! Auto-generated based on Cargo.toml dependencies
+//! This file provides `Deployable` implementations for contract dependencies
+//! Constructor args are type-checked against the ones exported by each dependency, which may
+//! leave out the trailing args with a default
+
+use alloy_core::primitives::{Address, Bytes};
+use eth_riscv_runtime::{create::Deployable, InitInterface, ReadOnly};
+use core::include_bytes;
+
+use erc20::{IERC20, ERC20ConstructorArgs, ERC20RequiredArgs};
+
+const ERC20_BYTECODE: &'static [u8] = include_bytes!("../../../../r55-output-bytecode/erc20.bin");
+
+pub struct ERC20;
+
+impl Deployable for ERC20 {
+    type Interface = IERC20<ReadOnly>;
+    type ConstructorArgs = ERC20ConstructorArgs;
+    type RequiredArgs = ERC20RequiredArgs;
+
+    fn __runtime() -> &'static [u8] {
+        ERC20_BYTECODE
+    }
+}
+
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+use core::default::Default;
+
+use contract_derive::{contract, storage};
+use eth_riscv_runtime::types::*;
+
+use alloy_core::primitives::{Address, U256};
+
+extern crate alloc;
+
+use erc20::IERC20;
+
+mod deployable;
+use deployable::ERC20;
+
+// Contract that deploys its own ERC20 token from the constructor, used by the r55 test suite
+#[storage]
+pub struct TokenLauncher {
+    token: Slot<Address>,
+    token_owner: Slot<Address>,
+}
+
+#[contract]
+impl TokenLauncher {
+    // Deploys a token owned by `owner`. A non-zero `seed` is sent along with the deployment, which
+    // fails as the launcher has no balance (and the token constructor isn't payable), and aborts
+    // the deployment of the launcher
+    pub fn new(owner: Address, seed: U256) -> Self {
+        let mut launcher = TokenLauncher::default();
+
+        let token = ERC20::deploy_with_value(owner, seed).with_ctx(&mut launcher);
+        let token_owner = token.owner().expect("Unable to get owner");
+
+        launcher.token.write(token.address());
+        launcher.token_owner.write(token_owner);
+
+        launcher
+    }
+
+    pub fn token(&self) -> Address {
+        self.token.read()
+    }
+
+    // Owner of the token, as read by the constructor from the freshly deployed token
+    pub fn token_owner(&self) -> Address {
+        self.token_owner.read()
+    }
+}
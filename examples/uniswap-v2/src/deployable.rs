@@ -1,12 +1,13 @@
 //! Auto-generated based on Cargo.toml dependencies
 //! This file provides `Deployable` implementations for contract dependencies
-//! TODO (phase-2): rather than using `fn deploy(args: Args)`, figure out the constructor selector from the contract dependency
+//! Constructor args are type-checked against the ones exported by each dependency, which may
+//! leave out the trailing args with a default
 
 use alloy_core::primitives::{Address, Bytes};
 use eth_riscv_runtime::{create::Deployable, InitInterface, ReadOnly};
 use core::include_bytes;
 
-use crate::pair::IUniswapV2Pair;
+use crate::pair::{IUniswapV2Pair, UniswapV2PairConstructorArgs, UniswapV2PairRequiredArgs};
 
 const UNISWAP_V2_PAIR_BYTECODE: &'static [u8] = include_bytes!("../../../../r55-output-bytecode/uniswap-v2-pair.bin");
 
@@ -14,6 +15,8 @@ pub struct UniswapV2Pair;
 
 impl Deployable for UniswapV2Pair {
     type Interface = IUniswapV2Pair<ReadOnly>;
+    type ConstructorArgs = UniswapV2PairConstructorArgs;
+    type RequiredArgs = UniswapV2PairRequiredArgs;
 
     fn __runtime() -> &'static [u8] {
         UNISWAP_V2_PAIR_BYTECODE
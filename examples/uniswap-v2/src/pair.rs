@@ -450,7 +450,7 @@ impl UniswapV2Pair {
             IUniswapV2Callee::new(to)
                 .with_ctx(&mut *self)
                 .uniswap_v2_call(msg_sender(), amount0_out, amount1_out, data)
-                .ok_or(UniswapV2PairError::FailedCallback)?;
+                .map_err(|_| UniswapV2PairError::FailedCallback)?;
         }
 
         // Get balances after transfers and possibly callback
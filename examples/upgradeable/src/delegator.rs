@@ -0,0 +1,43 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+use core::default::Default;
+
+use contract_derive::{contract, interface, storage};
+use eth_riscv_runtime::types::*;
+
+use alloy_core::primitives::{Address, U256};
+
+// Counter of the implementations (see `v1` and `v2`)
+#[interface]
+trait ICounter {
+    fn increment(&mut self) -> U256;
+}
+
+// Counter whose increments run the code of an implementation on its own storage, by delegating
+// them through the interface of the implementation. `count` has the slot it has there
+#[storage]
+pub struct Delegator {
+    count: Slot<U256>,
+    implementation: Slot<Address>,
+}
+
+#[contract]
+impl Delegator {
+    pub fn new(implementation: Address) -> Self {
+        let mut delegator = Delegator::default();
+        delegator.implementation.write(implementation);
+        delegator
+    }
+
+    pub fn increment(&mut self) -> U256 {
+        let implementation = self.implementation.read();
+        let mut counter = ICounter::new(implementation).with_ctx(self).delegate();
+        counter.increment().expect("Delegatecall failed")
+    }
+
+    pub fn count(&self) -> U256 {
+        self.count.read()
+    }
+}
@@ -0,0 +1,7 @@
+#![no_std]
+#![no_main]
+
+pub mod delegator;
+pub mod proxy;
+pub mod v1;
+pub mod v2;
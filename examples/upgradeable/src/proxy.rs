@@ -0,0 +1,10 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use contract_derive::proxy;
+
+// Transparent EIP-1967 proxy in front of the counter implementations (see `v1` and `v2`)
+#[proxy]
+pub struct Proxy;
@@ -0,0 +1,38 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+use core::default::Default;
+
+use contract_derive::{contract, storage};
+use eth_riscv_runtime::types::*;
+
+use alloy_core::primitives::U256;
+
+// First version of the counter
+#[storage]
+#[storage_version(1)]
+pub struct CounterV1 {
+    count: Slot<U256>,
+}
+
+#[contract]
+impl CounterV1 {
+    pub fn new() -> Self {
+        CounterV1::default()
+    }
+
+    pub fn increment(&mut self) -> U256 {
+        let count = self.count.read() + U256::from(1);
+        self.count.write(count);
+        count
+    }
+
+    pub fn count(&self) -> U256 {
+        self.count.read()
+    }
+
+    pub fn version(&self) -> u64 {
+        1
+    }
+}
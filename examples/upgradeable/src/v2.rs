@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+use core::default::Default;
+
+use contract_derive::{contract, storage};
+use eth_riscv_runtime::types::*;
+
+use alloy_core::primitives::U256;
+
+// Second version of the counter, with a configurable step. New fields must be appended, so that
+// the storage written by `CounterV1` is kept when upgrading
+#[storage]
+#[storage_version(2)]
+pub struct CounterV2 {
+    count: Slot<U256>,
+    step: Slot<U256>,
+}
+
+#[contract]
+impl CounterV2 {
+    pub fn new() -> Self {
+        CounterV2::default()
+    }
+
+    // Increments by the step, or by one if it was never set (i.e. right after the upgrade)
+    pub fn increment(&mut self) -> U256 {
+        let step = self.step.read().max(U256::from(1));
+        let count = self.count.read() + step;
+        self.count.write(count);
+        count
+    }
+
+    pub fn count(&self) -> U256 {
+        self.count.read()
+    }
+
+    pub fn set_step(&mut self, step: U256) -> bool {
+        self.step.write(step);
+        true
+    }
+
+    pub fn step(&self) -> U256 {
+        self.step.read()
+    }
+
+    pub fn version(&self) -> u64 {
+        2
+    }
+}
@@ -0,0 +1,56 @@
+#![no_std]
+#![no_main]
+
+use core::default::Default;
+
+use contract_derive::{contract, payable, storage};
+use eth_riscv_runtime::types::*;
+
+use alloy_core::primitives::{Address, U256};
+
+extern crate alloc;
+
+// Contract that can be funded at deployment, used by the r55 test suite
+#[storage]
+pub struct Vault {
+    owner: Slot<Address>,
+    initial_balance: Slot<U256>,
+    received: Slot<U256>,
+}
+
+#[contract]
+impl Vault {
+    pub fn new(owner: Address) -> Self {
+        let mut vault = Vault::default();
+
+        // The value sent with the deployment is already credited to the contract
+        vault.owner.write(owner);
+        vault.initial_balance.write(self_balance());
+
+        vault
+    }
+
+    pub fn owner(&self) -> Address {
+        self.owner.read()
+    }
+
+    pub fn initial_balance(&self) -> U256 {
+        self.initial_balance.read()
+    }
+
+    pub fn balance(&self) -> U256 {
+        self_balance()
+    }
+
+    // Value received through plain transfers, after the deployment
+    pub fn received(&self) -> U256 {
+        self.received.read()
+    }
+
+    // Accepts plain value transfers
+    #[payable]
+    fn fallback(&mut self) {
+        let received = self.received.read() + msg_value();
+        self.received.write(received);
+    }
+}
@@ -3,7 +3,9 @@
 MEMORY
 {
   CALL_DATA : ORIGIN = 0x80000000, LENGTH = 1M
-  STACK : ORIGIN = 0x80100000, LENGTH = 2M
+  /* Bottom of the stack, which the host fills with a canary to catch overflows */
+  STACK_GUARD : ORIGIN = 0x80100000, LENGTH = 4K
+  STACK : ORIGIN = 0x80101000, LENGTH = 2044K
   REST_OF_RAM : ORIGIN = 0x80300000, LENGTH = 1021M
 }
 
@@ -14,6 +16,11 @@ SECTIONS
     *(.eh_frame_hdr)
     *(.eh_frame.*)
   }
+
+  /* `#[max_gas]` budgets, which r55-compile moves to the metadata trailer. Never loaded */
+  .r55.max_gas (INFO) : {
+    KEEP(*(.r55.max_gas))
+  }
 }
 
 REGION_ALIAS("REGION_TEXT", REST_OF_RAM);
@@ -0,0 +1,1146 @@
+//! Off-chain bindings of the contracts, for deployment scripts and backends. `--emit-bindings`
+//! renders a module per contract, with:
+//! * an alloy `sol!` interface, whose `#[sol(rpc)]` instance has a typed call builder per method
+//!   and an event filter per event, e.g. `ERC20::new(address, provider).transfer(to, amount)`
+//! * a decoder per `#[derive(Error)]` enum, as r55 errors aren't selected like Solidity ones
+//! * a calldata encoder per method that r55 doesn't encode like Solidity (see `Function::tupled`)
+//!
+//! The ABI is extracted from the sources, and mirrors the encoding of the `#[contract]` macro.
+
+use alloy_primitives::keccak256;
+use quote::ToTokens;
+use std::{collections::BTreeSet, fmt::Write, fs, path::Path};
+use syn::{
+    Attribute, Fields, FnArg, ImplItem, ImplItemMethod, Item, ItemImpl, Pat, PathArguments,
+    ReturnType, Type,
+};
+use tracing::warn;
+
+use crate::{
+    helpers::{get_struct_name, has_contract_attribute, has_proxy_attribute},
+    types::{CompileError, ContractProject, ContractTarget},
+};
+
+/// Public interface of a contract
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractAbi {
+    pub contract: String,
+    pub functions: Vec<Function>,
+    pub events: Vec<Event>,
+    pub errors: Vec<ErrorEnum>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Function {
+    /// Name of the selector, i.e. the method's or its camel-cased version for `#[interface]`s
+    /// with that naming style
+    pub name: String,
+    pub inputs: Vec<Param>,
+    pub outputs: Vec<AbiType>,
+    pub mutability: Mutability,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mutability {
+    View,
+    NonPayable,
+    Payable,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Param {
+    pub name: String,
+    pub ty: AbiType,
+}
+
+/// Solidity type of a Rust type, and the Rust type the bindings use for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbiType {
+    pub sol: String,
+    pub rust: String,
+    pub dynamic: bool,
+    /// Types of `rust` imported from `alloy::primitives`
+    pub primitives: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    pub name: String,
+    pub fields: Vec<EventField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventField {
+    pub name: String,
+    pub ty: AbiType,
+    pub indexed: bool,
+}
+
+/// `#[derive(Error)]` enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorEnum {
+    pub name: String,
+    pub variants: Vec<ErrorVariant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorVariant {
+    pub name: String,
+    /// `None` for unit variants
+    pub fields: Option<Vec<AbiType>>,
+}
+
+impl ContractAbi {
+    /// Extracts the interface of a contract target. Its events and errors are the ones declared
+    /// next to it, or in the root module of its project
+    pub fn from_target(
+        project: &ContractProject,
+        target: &ContractTarget,
+    ) -> Result<Self, CompileError> {
+        let mut items = syn::parse_file(&fs::read_to_string(&target.source_file)?)?.items;
+        let lib_path = project.path.join("src").join("lib.rs");
+        if target.source_file != lib_path {
+            items.extend(syn::parse_file(&fs::read_to_string(&lib_path)?)?.items);
+        }
+
+        Self::from_items(&target.ident, &items)
+    }
+
+    fn from_items(contract: &str, items: &[Item]) -> Result<Self, CompileError> {
+        let mut abi = Self {
+            contract: contract.into(),
+            functions: Vec::new(),
+            events: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        for item in items {
+            match item {
+                Item::Impl(item_impl)
+                    if get_struct_name(item_impl).as_deref() == Some(contract) =>
+                {
+                    abi.functions.extend(impl_functions(item_impl, items)?);
+                }
+                Item::Struct(item_struct)
+                    if item_struct.ident == contract && has_proxy_attribute(&item_struct.attrs) =>
+                {
+                    abi.functions.extend(proxy_functions());
+                    abi.events.push(upgraded_event());
+                }
+                Item::Struct(item_struct) if derives(&item_struct.attrs, "Event") => {
+                    let Fields::Named(named) = &item_struct.fields else {
+                        continue;
+                    };
+                    let fields = named
+                        .named
+                        .iter()
+                        .map(|field| {
+                            Ok(EventField {
+                                name: field.ident.as_ref().unwrap().to_string(),
+                                ty: AbiType::from_rust(&field.ty)?,
+                                indexed: field.attrs.iter().any(|a| a.path.is_ident("indexed")),
+                            })
+                        })
+                        .collect::<Result<_, CompileError>>()?;
+                    abi.events.push(Event {
+                        name: item_struct.ident.to_string(),
+                        fields,
+                    });
+                }
+                Item::Enum(item_enum) if derives(&item_enum.attrs, "Error") => {
+                    let variants = item_enum
+                        .variants
+                        .iter()
+                        .map(|variant| {
+                            let fields = match &variant.fields {
+                                Fields::Unnamed(fields) => Some(
+                                    fields
+                                        .unnamed
+                                        .iter()
+                                        .map(|field| AbiType::from_rust(&field.ty))
+                                        .collect::<Result<_, _>>()?,
+                                ),
+                                _ => None,
+                            };
+                            Ok(ErrorVariant {
+                                name: variant.ident.to_string(),
+                                fields,
+                            })
+                        })
+                        .collect::<Result<_, CompileError>>()?;
+                    abi.errors.push(ErrorEnum {
+                        name: item_enum.ident.to_string(),
+                        variants,
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        // Selectors that the contract doesn't handle fall through to the storage it inherits
+        for function in inherited_functions(contract, items)? {
+            if !abi
+                .functions
+                .iter()
+                .any(|f| f.signature() == function.signature())
+            {
+                abi.functions.push(function);
+            }
+        }
+
+        // The root module is parsed after the contract's, and may declare the same events again
+        let mut names = BTreeSet::new();
+        abi.events.retain(|event| names.insert(event.name.clone()));
+        names.clear();
+        abi.errors.retain(|error| names.insert(error.name.clone()));
+
+        Ok(abi)
+    }
+
+    /// Renders the bindings, as the Rust module of a crate depending on `alloy` (with the
+    /// `contract` feature). `source` is the path of the contract, for the header
+    pub fn render(&self, source: &str) -> Result<String, CompileError> {
+        let mut out = String::new();
+        writeln!(
+            out,
+            "//! Bindings of the `{}` contract (`{}`).\n//!\n//! Generated by `r55-compile \
+             --emit-bindings`: don't edit them, re-run the command instead.",
+            self.contract, source
+        )?;
+
+        let primitives = self
+            .errors
+            .iter()
+            .flat_map(|error| &error.variants)
+            .flat_map(|variant| variant.fields.iter().flatten())
+            .chain(
+                self.functions
+                    .iter()
+                    .filter(|f| f.tupled())
+                    .flat_map(|f| f.inputs.iter().map(|param| &param.ty)),
+            )
+            .flat_map(|ty| ty.primitives.iter().cloned())
+            .chain(
+                self.functions
+                    .iter()
+                    .any(|f| f.tupled())
+                    .then(|| "Bytes".into()),
+            )
+            .collect::<BTreeSet<_>>();
+        match primitives.into_iter().collect::<Vec<_>>().as_slice() {
+            [] => {}
+            [primitive] => writeln!(out, "\nuse alloy::primitives::{};", primitive)?,
+            primitives => writeln!(
+                out,
+                "\nuse alloy::primitives::{{{}}};",
+                primitives.join(", ")
+            )?,
+        }
+
+        writeln!(out, "\nalloy::sol! {{")?;
+        writeln!(out, "    #[sol(rpc)]")?;
+        writeln!(out, "    interface {} {{", self.contract)?;
+        for function in self.functions.iter().filter(|f| !f.tupled()) {
+            writeln!(out, "        {}", function.render_sol())?;
+        }
+        if self.functions.iter().any(|f| !f.tupled()) && !self.events.is_empty() {
+            writeln!(out)?;
+        }
+        for event in &self.events {
+            writeln!(out, "        {}", event.render_sol())?;
+        }
+        writeln!(out, "    }}")?;
+        writeln!(out, "}}")?;
+
+        for function in self.functions.iter().filter(|f| f.tupled()) {
+            function.render_calldata(&mut out)?;
+        }
+        for error in &self.errors {
+            error.render(&mut out)?;
+        }
+
+        Ok(out)
+    }
+}
+
+impl Function {
+    fn from_method(method: &ImplItemMethod, camel_case: bool) -> Result<Self, CompileError> {
+        let mutability = match method.sig.inputs.first() {
+            Some(FnArg::Receiver(receiver)) if receiver.mutability.is_none() => Mutability::View,
+            _ if has_attribute(&method.attrs, "payable") => Mutability::Payable,
+            _ => Mutability::NonPayable,
+        };
+
+        let inputs = method
+            .sig
+            .inputs
+            .iter()
+            .skip(1)
+            .enumerate()
+            .map(|(i, arg)| {
+                let FnArg::Typed(pat_type) = arg else {
+                    unreachable!("only the first arg can be a receiver");
+                };
+                let name = match &*pat_type.pat {
+                    Pat::Ident(pat_ident) => pat_ident.ident.to_string(),
+                    _ => format!("arg{}", i),
+                };
+                Ok(Param {
+                    name,
+                    ty: AbiType::from_rust(&pat_type.ty)?,
+                })
+            })
+            .collect::<Result<_, CompileError>>()?;
+
+        let name = method.sig.ident.to_string();
+        Ok(Self {
+            name: if camel_case {
+                to_camel_case(&name)
+            } else {
+                name
+            },
+            inputs,
+            outputs: outputs(&method.sig.output)?,
+            mutability,
+        })
+    }
+
+    /// Canonical signature, e.g. `transfer(address,uint256)`
+    pub fn signature(&self) -> String {
+        let types = self.inputs.iter().map(|param| param.ty.sol.as_str());
+        format!("{}({})", self.name, types.collect::<Vec<_>>().join(","))
+    }
+
+    /// Whether the args are decoded as a tuple, behind an offset, rather than as the params of a
+    /// Solidity function: the case of several args, one of them being dynamic
+    pub fn tupled(&self) -> bool {
+        self.inputs.len() > 1 && self.inputs.iter().any(|param| param.ty.dynamic)
+    }
+
+    fn render_sol(&self) -> String {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|param| format!("{} {}", param.ty.sol, param.name))
+            .collect::<Vec<_>>();
+        let mut sol = format!("function {}({}) external", self.name, inputs.join(", "));
+        match self.mutability {
+            Mutability::View => sol.push_str(" view"),
+            Mutability::NonPayable => {}
+            Mutability::Payable => sol.push_str(" payable"),
+        }
+        if !self.outputs.is_empty() {
+            let outputs = self.outputs.iter().map(|ty| ty.sol.as_str());
+            write!(sol, " returns ({})", outputs.collect::<Vec<_>>().join(", ")).unwrap();
+        }
+        sol.push(';');
+        sol
+    }
+
+    // Encoder of the calldata of a `tupled` method, which `sol!` call builders can't produce
+    fn render_calldata(&self, out: &mut String) -> Result<(), CompileError> {
+        let args = self
+            .inputs
+            .iter()
+            .map(|param| format!("{}: {}", param.name, param.ty.rust))
+            .collect::<Vec<_>>();
+        let names = self.inputs.iter().map(|param| param.name.as_str());
+
+        writeln!(out, "\n/// Calldata of `{}`.", self.signature())?;
+        writeln!(
+            out,
+            "///\n/// Its args are encoded as a tuple, behind an offset, rather than as the params of a\n\
+             /// Solidity function"
+        )?;
+        if self.name.chars().any(|c| c.is_ascii_uppercase()) {
+            writeln!(out, "#[allow(non_snake_case)]")?;
+        }
+
+        // Wrapped like rustfmt would
+        let signature = format!(
+            "pub fn {}_calldata({}) -> Bytes {{",
+            self.name,
+            args.join(", ")
+        );
+        if signature.len() <= 100 {
+            writeln!(out, "{}", signature)?;
+        } else {
+            writeln!(out, "pub fn {}_calldata(", self.name)?;
+            for arg in &args {
+                writeln!(out, "    {},", arg)?;
+            }
+            writeln!(out, ") -> Bytes {{")?;
+        }
+        writeln!(out, "    use alloy::sol_types::SolValue;\n")?;
+
+        let (selector, args) = (
+            format!("&[{}][..]", selector_literal(&self.signature())),
+            format!("&({}).abi_encode()", names.collect::<Vec<_>>().join(", ")),
+        );
+        // As rustfmt would, the array is broken if its items are longer than 60 chars, and the chain
+        // always is (the selector alone is half of that)
+        if selector.len() + ", ".len() + args.len() > 60 {
+            writeln!(
+                out,
+                "    [\n        {},\n        {},\n    ]",
+                selector, args
+            )?;
+            writeln!(out, "    .concat()\n    .into()")?;
+        } else {
+            writeln!(out, "    [{}, {}]", selector, args)?;
+            writeln!(out, "        .concat()\n        .into()")?;
+        }
+        writeln!(out, "}}")?;
+        Ok(())
+    }
+}
+
+impl Event {
+    /// Canonical signature, whose hash is the first topic of the event
+    pub fn signature(&self) -> String {
+        let types = self.fields.iter().map(|field| field.ty.sol.as_str());
+        format!("{}({})", self.name, types.collect::<Vec<_>>().join(","))
+    }
+
+    fn render_sol(&self) -> String {
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| {
+                let indexed = if field.indexed { " indexed" } else { "" };
+                format!("{}{} {}", field.ty.sol, indexed, field.name)
+            })
+            .collect::<Vec<_>>();
+        format!("event {}({});", self.name, fields.join(", "))
+    }
+}
+
+impl ErrorEnum {
+    // NOTE: must be kept in sync with the encoding of `#[derive(Error)]`: the selector of
+    // `Enum::Variant(types)` (or `Enum::Variant` for unit variants), followed by the fields
+    fn render(&self, out: &mut String) -> Result<(), CompileError> {
+        writeln!(out, "\n#[derive(Debug, Clone, PartialEq, Eq)]")?;
+        writeln!(out, "pub enum {} {{", self.name)?;
+        for variant in &self.variants {
+            match &variant.fields {
+                None => writeln!(out, "    {},", variant.name)?,
+                Some(fields) => {
+                    let types = fields.iter().map(|ty| ty.rust.as_str());
+                    writeln!(
+                        out,
+                        "    {}({}),",
+                        variant.name,
+                        types.collect::<Vec<_>>().join(", ")
+                    )?
+                }
+            }
+        }
+        writeln!(out, "}}")?;
+
+        writeln!(out, "\nimpl {} {{", self.name)?;
+        writeln!(
+            out,
+            "    /// Decodes the revert data of a call, unless it isn't one of these errors"
+        )?;
+        writeln!(out, "    pub fn abi_decode(data: &[u8]) -> Option<Self> {{")?;
+        if self.variants.iter().any(|v| v.fields.is_some()) {
+            writeln!(out, "        use alloy::sol_types::SolValue;\n")?;
+        }
+        if self.variants.iter().any(|v| v.fields.is_some()) {
+            writeln!(
+                out,
+                "        let (selector, data) = data.split_first_chunk::<4>()?;"
+            )?;
+        } else {
+            writeln!(out, "        let selector = data.first_chunk::<4>()?;")?;
+        }
+        writeln!(out, "        match selector {{")?;
+        for variant in &self.variants {
+            let signature = self.signature(variant);
+            writeln!(out, "            // {}", signature)?;
+            match &variant.fields {
+                None => writeln!(
+                    out,
+                    "            [{}] => Some(Self::{}),",
+                    selector_literal(&signature),
+                    variant.name
+                )?,
+                Some(fields) => {
+                    let vars = (0..fields.len())
+                        .map(|i| format!("field{}", i))
+                        .collect::<Vec<_>>();
+                    let types = fields.iter().map(|ty| ty.rust.clone()).collect::<Vec<_>>();
+                    writeln!(out, "            [{}] => {{", selector_literal(&signature))?;
+                    writeln!(
+                        out,
+                        "                let {} = <{}>::abi_decode(data).ok()?;",
+                        rust_tuple(&vars),
+                        rust_tuple(&types)
+                    )?;
+                    writeln!(
+                        out,
+                        "                Some(Self::{}({}))",
+                        variant.name,
+                        vars.join(", ")
+                    )?;
+                    writeln!(out, "            }}")?;
+                }
+            }
+        }
+        writeln!(out, "            _ => None,")?;
+        writeln!(out, "        }}")?;
+        writeln!(out, "    }}")?;
+
+        writeln!(
+            out,
+            "\n    /// Decodes the revert data of a failed call, if any"
+        )?;
+        writeln!(
+            out,
+            "    pub fn from_call_error(error: &alloy::contract::Error) -> Option<Self> {{"
+        )?;
+        writeln!(out, "        Self::abi_decode(&error.as_revert_data()?)")?;
+        writeln!(out, "    }}")?;
+        writeln!(out, "}}")?;
+        Ok(())
+    }
+
+    /// Signature of `variant`, whose hash selects it in the revert data
+    pub fn signature(&self, variant: &ErrorVariant) -> String {
+        match &variant.fields {
+            None => format!("{}::{}", self.name, variant.name),
+            Some(fields) => {
+                let types = fields.iter().map(|ty| ty.sol.as_str());
+                format!(
+                    "{}::{}({})",
+                    self.name,
+                    variant.name,
+                    types.collect::<Vec<_>>().join(",")
+                )
+            }
+        }
+    }
+}
+
+impl AbiType {
+    // NOTE: must be kept in sync with `rust_type_to_sol_type` of `contract-derive`
+    pub fn from_rust(ty: &Type) -> Result<Self, CompileError> {
+        let unsupported = |reason: &str| {
+            let ty_str = ty.to_token_stream().to_string().replace(' ', "");
+            CompileError::SynError(syn::Error::new_spanned(
+                ty,
+                format!(
+                    "`{}` can't be mapped to a Solidity type: {}",
+                    ty_str, reason
+                ),
+            ))
+        };
+        let simple = |sol: &str, rust: &str, dynamic: bool| Self {
+            sol: sol.into(),
+            rust: rust.into(),
+            dynamic,
+            primitives: BTreeSet::new(),
+        };
+        let primitive = |sol: String, rust: String| Self {
+            sol,
+            primitives: BTreeSet::from([rust.clone()]),
+            rust,
+            dynamic: false,
+        };
+
+        match ty {
+            Type::Path(type_path) => {
+                let segment = type_path
+                    .path
+                    .segments
+                    .last()
+                    .ok_or_else(|| unsupported("Empty type path"))?;
+                let name = segment.ident.to_string();
+
+                match name.as_str() {
+                    "Address" | "Function" => Ok(primitive(name.to_lowercase(), name)),
+                    "bool" | "Bool" => Ok(simple("bool", "bool", false)),
+                    "String" | "str" => Ok(simple("string", "String", true)),
+                    "Bytes" | "CalldataReader" => {
+                        let mut bytes = primitive("bytes".into(), "Bytes".into());
+                        bytes.dynamic = true;
+                        Ok(bytes)
+                    }
+                    b if b.starts_with('B') => {
+                        let size = b[1..]
+                            .parse::<usize>()
+                            .map_err(|_| unsupported("Invalid fixed bytes size"))?;
+                        if size == 0 || size > 256 {
+                            return Err(unsupported("Invalid fixed bytes size (between 1-32)"));
+                        }
+                        let mut bytes =
+                            primitive(format!("bytes{}", size / 8), "FixedBytes".into());
+                        bytes.rust = format!("FixedBytes<{}>", size / 8);
+                        Ok(bytes)
+                    }
+                    n if n.to_lowercase().starts_with('u') || n.to_lowercase().starts_with('i') => {
+                        let (kind, std) = if n.to_lowercase().starts_with('u') {
+                            ("uint", ["u8", "u16", "u32", "u64", "u128"])
+                        } else {
+                            ("int", ["i8", "i16", "i32", "i64", "i128"])
+                        };
+                        let size = n[1..]
+                            .parse::<usize>()
+                            .map_err(|_| unsupported("Invalid integer size"))?;
+                        if size == 0 || size > 256 || size % 8 != 0 {
+                            return Err(unsupported(
+                                "Invalid integer size (multiple of 8, max 256)",
+                            ));
+                        }
+                        let sol = format!("{}{}", kind, size);
+                        if std.contains(&n) {
+                            Ok(simple(&sol, n, false))
+                        } else {
+                            Ok(primitive(sol, n.into()))
+                        }
+                    }
+                    "Vec" => {
+                        let PathArguments::AngleBracketed(args) = &segment.arguments else {
+                            return Err(unsupported("Unsupported type"));
+                        };
+                        let Some(syn::GenericArgument::Type(inner)) = args.args.first() else {
+                            return Err(unsupported("Invalid Vec type argument"));
+                        };
+                        let inner = Self::from_rust(inner)?;
+                        Ok(Self {
+                            sol: format!("{}[]", inner.sol),
+                            rust: format!("Vec<{}>", inner.rust),
+                            dynamic: true,
+                            primitives: inner.primitives,
+                        })
+                    }
+                    _ => Err(unsupported("Unsupported type")),
+                }
+            }
+            Type::Array(array) => {
+                let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Int(len),
+                    ..
+                }) = &array.len
+                else {
+                    return Err(unsupported("Invalid array size expression"));
+                };
+                let len: usize = len
+                    .base10_parse()
+                    .map_err(|_| unsupported("Invalid array size"))?;
+                // `[u8; N]` is abi-encoded as `bytesN` by alloy, not as `uint8[N]`
+                let is_byte = matches!(&*array.elem, Type::Path(p) if p.path.is_ident("u8"));
+                if is_byte && len > 0 && len <= 32 {
+                    return Ok(simple(
+                        &format!("bytes{}", len),
+                        &format!("[u8; {}]", len),
+                        false,
+                    ));
+                }
+
+                let inner = Self::from_rust(&array.elem)?;
+                Ok(Self {
+                    sol: format!("{}[{}]", inner.sol, len),
+                    rust: format!("[{}; {}]", inner.rust, len),
+                    dynamic: inner.dynamic,
+                    primitives: inner.primitives,
+                })
+            }
+            Type::Tuple(tuple) => {
+                let elems = tuple
+                    .elems
+                    .iter()
+                    .map(Self::from_rust)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let sol = elems.iter().map(|ty| ty.sol.as_str()).collect::<Vec<_>>();
+                let rust = elems.iter().map(|ty| ty.rust.clone()).collect::<Vec<_>>();
+                Ok(Self {
+                    sol: format!("({})", sol.join(",")),
+                    rust: rust_tuple(&rust),
+                    dynamic: elems.iter().any(|ty| ty.dynamic),
+                    primitives: elems.into_iter().flat_map(|ty| ty.primitives).collect(),
+                })
+            }
+            _ => Err(unsupported("Unsupported type")),
+        }
+    }
+}
+
+/// Writes the bindings of every contract in `dir`, as a module named after its bytecode (e.g.
+/// `upgradeable_v2.rs`), along with a `mod.rs` declaring them
+pub fn emit_bindings(
+    projects: &[ContractProject],
+    project_root: &Path,
+    dir: &Path,
+) -> Result<(), CompileError> {
+    fs::create_dir_all(dir)?;
+
+    let mut modules = Vec::new();
+    for project in projects {
+        for target in &project.targets {
+            let source = pathdiff::diff_paths(&target.source_file, project_root)
+                .unwrap_or_else(|| target.source_file.clone());
+            let bindings = ContractAbi::from_target(project, target)?
+                .render(&source.to_string_lossy().replace('\\', "/"))?;
+
+            let module = target.generated_package.replace('-', "_");
+            fs::write(dir.join(format!("{}.rs", module)), bindings)?;
+            modules.push(module);
+        }
+    }
+
+    modules.sort();
+    let mod_rs = modules
+        .iter()
+        .map(|module| format!("pub mod {};\n", module))
+        .collect::<String>();
+    fs::write(dir.join("mod.rs"), mod_rs)?;
+    Ok(())
+}
+
+// Public methods of a `#[contract]` impl, or all the methods of a `#[contract_impl]` of a trait
+fn impl_functions(item_impl: &ItemImpl, items: &[Item]) -> Result<Vec<Function>, CompileError> {
+    let camel_case = if has_contract_attribute(&item_impl.attrs) {
+        false
+    } else if item_impl
+        .attrs
+        .iter()
+        .any(|attr| attr.path.is_ident("contract_impl"))
+    {
+        let trait_name = item_impl
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default();
+        match interface_style(&trait_name, items)? {
+            Some(camel_case) => camel_case,
+            None => {
+                warn!(
+                    "Interface `{}` not found, its methods are bound with their names",
+                    trait_name
+                );
+                false
+            }
+        }
+    } else {
+        return Ok(Vec::new());
+    };
+
+    item_impl
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            ImplItem::Method(method) => Some(method),
+            _ => None,
+        })
+        .filter(|method| {
+            let name = &method.sig.ident;
+            item_impl.trait_.is_some()
+                || (matches!(method.vis, syn::Visibility::Public(_))
+                    && name != "new"
+                    && name != "fallback")
+        })
+        .filter(|method| matches!(method.sig.inputs.first(), Some(FnArg::Receiver(_))))
+        .map(|method| Function::from_method(method, camel_case))
+        .collect()
+}
+
+// Public methods of the `#[contract_base]`s that `contract` inherits, through the `#[inherit(..)]`
+// of its storage and of the bases themselves, the nearest base first
+fn inherited_functions(contract: &str, items: &[Item]) -> Result<Vec<Function>, CompileError> {
+    let mut functions = Vec::new();
+    let mut bases = Vec::new();
+    let mut name = contract.to_string();
+    while let Some(base) = inherited_storage(&name, items)? {
+        if base == contract || bases.contains(&base) {
+            return Err(CompileError::CyclicDependency);
+        }
+
+        for item in items {
+            let Item::Impl(item_impl) = item else {
+                continue;
+            };
+            if !has_attribute(&item_impl.attrs, "contract_base")
+                || get_struct_name(item_impl).as_deref() != Some(base.as_str())
+            {
+                continue;
+            }
+
+            for method in item_impl.items.iter().filter_map(|item| match item {
+                ImplItem::Method(method) => Some(method),
+                _ => None,
+            }) {
+                if matches!(method.vis, syn::Visibility::Public(_))
+                    && method.sig.ident != "new"
+                    && matches!(method.sig.inputs.first(), Some(FnArg::Receiver(_)))
+                {
+                    functions.push(Function::from_method(method, false)?);
+                }
+            }
+        }
+
+        name = base.clone();
+        bases.push(base);
+    }
+
+    Ok(functions)
+}
+
+// Name of the storage struct that the struct `name` inherits with `#[inherit(..)]`
+fn inherited_storage(name: &str, items: &[Item]) -> Result<Option<String>, CompileError> {
+    let attr = items.iter().find_map(|item| match item {
+        Item::Struct(item_struct) if item_struct.ident == name => item_struct
+            .attrs
+            .iter()
+            .find(|attr| attr.path.is_ident("inherit")),
+        _ => None,
+    });
+    let Some(attr) = attr else {
+        return Ok(None);
+    };
+
+    let path: syn::Path = attr.parse_args()?;
+    Ok(path
+        .segments
+        .last()
+        .map(|segment| segment.ident.to_string()))
+}
+
+// Whether the `#[interface]` trait `name` is camel-cased, or `None` if it isn't declared in `items`
+fn interface_style(name: &str, items: &[Item]) -> Result<Option<bool>, CompileError> {
+    let Some(item_trait) = items.iter().find_map(|item| match item {
+        Item::Trait(item_trait) if item_trait.ident == name => Some(item_trait),
+        _ => None,
+    }) else {
+        return Ok(None);
+    };
+
+    let Some(attr) = item_trait
+        .attrs
+        .iter()
+        .find(|attr| attr.path.is_ident("interface"))
+    else {
+        return Ok(Some(false));
+    };
+    if attr.tokens.is_empty() {
+        return Ok(Some(false));
+    }
+    Ok(Some(
+        attr.parse_args::<syn::LitStr>()?.value() == "camelCase",
+    ))
+}
+
+// Return values of a method: the `Ok` (or `Some`) value of a `Result` (or an `Option`), split into
+// several values if it's a tuple. Tuples with a dynamic member are encoded behind an offset, so
+// those are returned as a single tuple
+fn outputs(output: &ReturnType) -> Result<Vec<AbiType>, CompileError> {
+    let ReturnType::Type(_, ty) = output else {
+        return Ok(Vec::new());
+    };
+
+    let mut ty = &**ty;
+    if let Type::Path(type_path) = ty {
+        let segment = type_path.path.segments.last();
+        if let Some(segment) = segment.filter(|s| s.ident == "Result" || s.ident == "Option") {
+            if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                if let Some(syn::GenericArgument::Type(inner)) = args.args.first() {
+                    ty = inner;
+                }
+            }
+        }
+    }
+
+    match ty {
+        Type::Tuple(tuple) if tuple.elems.is_empty() => Ok(Vec::new()),
+        Type::Tuple(tuple) => {
+            let elems = tuple
+                .elems
+                .iter()
+                .map(AbiType::from_rust)
+                .collect::<Result<Vec<_>, _>>()?;
+            if elems.iter().any(|ty| ty.dynamic) {
+                Ok(vec![AbiType::from_rust(ty)?])
+            } else {
+                Ok(elems)
+            }
+        }
+        ty => Ok(vec![AbiType::from_rust(ty)?]),
+    }
+}
+
+// Methods the `#[proxy]` macro dispatches for its admin, besides its constructor
+fn proxy_functions() -> Vec<Function> {
+    let address = || AbiType {
+        sol: "address".into(),
+        rust: "Address".into(),
+        dynamic: false,
+        primitives: BTreeSet::from(["Address".into()]),
+    };
+
+    vec![
+        Function {
+            name: "implementation".into(),
+            inputs: Vec::new(),
+            outputs: vec![address()],
+            mutability: Mutability::View,
+        },
+        Function {
+            name: "admin".into(),
+            inputs: Vec::new(),
+            outputs: vec![address()],
+            mutability: Mutability::View,
+        },
+        Function {
+            name: "upgrade_to".into(),
+            inputs: vec![Param {
+                name: "new_implementation".into(),
+                ty: address(),
+            }],
+            outputs: vec![AbiType {
+                sol: "bool".into(),
+                rust: "bool".into(),
+                dynamic: false,
+                primitives: BTreeSet::new(),
+            }],
+            mutability: Mutability::NonPayable,
+        },
+    ]
+}
+
+// Emitted by the `upgrade_to` method of a `#[proxy]`
+fn upgraded_event() -> Event {
+    Event {
+        name: "Upgraded".into(),
+        fields: vec![EventField {
+            name: "implementation".into(),
+            ty: AbiType {
+                sol: "address".into(),
+                rust: "Address".into(),
+                dynamic: false,
+                primitives: BTreeSet::from(["Address".into()]),
+            },
+            indexed: true,
+        }],
+    }
+}
+
+fn derives(attrs: &[Attribute], name: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("derive"))
+        .any(|attr| {
+            attr.tokens
+                .to_string()
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .any(|token| token == name)
+        })
+}
+
+fn has_attribute(attrs: &[Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == name)
+    })
+}
+
+// Rust tuple of `elems`, with the trailing comma of single-element ones
+fn rust_tuple(elems: &[String]) -> String {
+    match elems {
+        [elem] => format!("({},)", elem),
+        elems => format!("({})", elems.join(", ")),
+    }
+}
+
+// First 4 bytes of the hash of `signature`, as the elements of an array literal
+fn selector_literal(signature: &str) -> String {
+    keccak256(signature)[..4]
+        .iter()
+        .map(|byte| format!("{:#04x}", byte))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// NOTE: must be kept in sync with `to_camel_case` of `contract-derive`
+fn to_camel_case(name: &str) -> String {
+    let mut result = String::new();
+    let mut capitalize_next = false;
+    for (i, c) in name.chars().enumerate() {
+        if !c.is_alphanumeric() {
+            capitalize_next = true;
+        } else if i == 0 {
+            result.push(c.to_ascii_lowercase());
+        } else if capitalize_next {
+            result.push(c.to_ascii_uppercase());
+            capitalize_next = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn abi(contract: &str, source: &str) -> ContractAbi {
+        ContractAbi::from_items(contract, &syn::parse_file(source).unwrap().items).unwrap()
+    }
+
+    #[test]
+    fn test_contract_abi() {
+        let abi = abi(
+            "Vault",
+            "#[derive(Event)]
+            pub struct Deposit {
+                #[indexed]
+                pub owner: Address,
+                pub amount: U256,
+            }
+
+            #[derive(Error)]
+            pub enum VaultError {
+                Paused,
+                TooLarge(U256, u64),
+            }
+
+            #[contract]
+            impl Vault {
+                pub fn new(owner: Address) -> Self { Vault::default() }
+
+                #[payable]
+                pub fn deposit(&mut self) -> Result<U256, VaultError> { todo!() }
+
+                pub fn label(&mut self, ids: Vec<u64>, label: String) {}
+
+                pub fn position(&self, owner: Address) -> (U256, B256) { todo!() }
+
+                pub fn metadata(&self) -> (String, bool) { todo!() }
+
+                fn check(&self) {}
+            }",
+        );
+
+        let functions = abi
+            .functions
+            .iter()
+            .map(Function::render_sol)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            functions,
+            [
+                "function deposit() external payable returns (uint256);",
+                "function label(uint64[] ids, string label) external;",
+                "function position(address owner) external view returns (uint256, bytes32);",
+                "function metadata() external view returns ((string,bool));",
+            ]
+        );
+        assert_eq!(
+            abi.events[0].render_sol(),
+            "event Deposit(address indexed owner, uint256 amount);"
+        );
+
+        let rendered = abi.render("examples/vault/src/lib.rs").unwrap();
+        assert!(rendered.contains("use alloy::primitives::{Bytes, U256};"));
+        // Not a Solidity function, as its args are a dynamic tuple
+        assert!(!rendered.contains("function label"));
+        assert!(rendered.contains("pub fn label_calldata(ids: Vec<u64>, label: String) -> Bytes {"));
+        assert!(rendered.contains("    TooLarge(U256, u64),"));
+        assert!(rendered.contains(&format!(
+            "            // VaultError::TooLarge(uint256,uint64)\n            [{}] => {{\n                let (field0, field1) = <(U256, u64)>::abi_decode(data).ok()?;",
+            selector_literal("VaultError::TooLarge(uint256,uint64)")
+        )));
+    }
+
+    #[test]
+    fn test_contract_impl_abi() {
+        let abi = abi(
+            "Token",
+            "#[interface(\"camelCase\")]
+            trait IToken {
+                fn balance_of(&self, owner: Address) -> U256;
+            }
+
+            #[contract_impl]
+            impl IToken for Token {
+                fn balance_of(&self, owner: Address) -> U256 { todo!() }
+            }",
+        );
+
+        assert_eq!(
+            abi.functions[0].render_sol(),
+            "function balanceOf(address owner) external view returns (uint256);"
+        );
+    }
+
+    #[test]
+    fn test_inherited_abi() {
+        let abi = abi(
+            "FeeToken",
+            "#[storage]
+            pub struct ERC20Base { balances: Mapping<Address, U256> }
+
+            #[contract_base]
+            impl ERC20Base {
+                pub fn balance_of(&self, owner: Address) -> U256 { todo!() }
+                pub fn transfer(&mut self, to: Address, amount: U256) -> bool { todo!() }
+                fn spend(&mut self, amount: U256) {}
+            }
+
+            #[storage]
+            #[inherit(ERC20Base)]
+            pub struct FeeToken { fee_bps: Slot<U256> }
+
+            #[contract]
+            impl FeeToken {
+                pub fn transfer(&mut self, to: Address, amount: U256) -> bool { todo!() }
+                pub fn fee_bps(&self) -> U256 { todo!() }
+            }",
+        );
+
+        // The override shadows the method of the base, which is bound once
+        let functions = abi
+            .functions
+            .iter()
+            .map(Function::signature)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            functions,
+            [
+                "transfer(address,uint256)",
+                "fee_bps()",
+                "balance_of(address)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        let err = AbiType::from_rust(&syn::parse_str("HashMap<Address, U256>").unwrap());
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .contains("`HashMap<Address,U256>` can't be mapped to a Solidity type"));
+    }
+
+    // The bindings of the `erc20-bindings` example must be the ones of the `erc20` contract
+    #[test]
+    fn test_example_bindings_are_up_to_date() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("..");
+        let source = fs::read_to_string(root.join("examples/erc20/src/lib.rs")).unwrap();
+        let rendered = abi("ERC20", &source)
+            .render("examples/erc20/src/lib.rs")
+            .unwrap();
+
+        let example =
+            fs::read_to_string(root.join("examples/erc20-bindings/src/erc20.rs")).unwrap();
+        assert!(
+            rendered == example,
+            "examples/erc20-bindings/src/erc20.rs is stale, regenerate it with `--emit-bindings`"
+        );
+    }
+}
@@ -5,7 +5,10 @@ use tracing::{debug, info};
 use crate::{
     ast,
     helpers::{format_toml_table, get_contract_name, get_deployable_deps},
-    types::{CompileError, ContractProject, ContractTarget, GeneratedContract},
+    types::{
+        BuildProfile, CompileError, ContractProject, ContractTarget, GeneratedContract,
+        RISCV_TARGET,
+    },
 };
 
 /// Generate deployable implementation for the contract dependencies of an R55 contract.
@@ -30,7 +33,9 @@ pub fn generate_deployable(
     content.push_str(
         "//! This file provides `Deployable` implementations for contract dependencies\n",
     );
-    content.push_str("//! TODO (phase-2): rather than using `fn deploy(args: Args)`, figure out the constructor selector from the contract dependency\n\n");
+    content.push_str(
+        "//! Constructor args are type-checked against the ones exported by each dependency, which may\n//! leave out the trailing args with a default\n\n",
+    );
     content.push_str("use alloy_core::primitives::{Address, Bytes};\n");
     content.push_str("use eth_riscv_runtime::{create::Deployable, InitInterface, ReadOnly};\n");
     content.push_str("use core::include_bytes;\n\n");
@@ -43,13 +48,21 @@ pub fn generate_deployable(
 
         // For interface name, use uppercase I + camel case name (IERC20)
         let interface_name = format!("I{}", get_contract_name(dep_name));
+        let args_name = format!("{}ConstructorArgs", get_contract_name(dep_name));
+        let required_args_name = format!("{}RequiredArgs", get_contract_name(dep_name));
 
         content.push_str(
             if target_source && *same_project {
                 let original_name = contract.get_original_name(&module_name);
-                format!("use crate::{}::{};\n", original_name, interface_name)
+                format!(
+                    "use crate::{}::{{{}, {}, {}}};\n",
+                    original_name, interface_name, args_name, required_args_name
+                )
             } else {
-                format!("use {}::{};\n", module_name, interface_name)
+                format!(
+                    "use {}::{{{}, {}, {}}};\n",
+                    module_name, interface_name, args_name, required_args_name
+                )
             }
             .as_str(),
         );
@@ -84,9 +97,17 @@ pub fn generate_deployable(
         content.push_str(&format!("pub struct {};\n\n", struct_name));
         content.push_str(&format!("impl Deployable for {} {{\n", struct_name));
         content.push_str(&format!(
-            "    type Interface = {}<ReadOnly>;\n\n",
+            "    type Interface = {}<ReadOnly>;\n",
             interface_name
         ));
+        content.push_str(&format!(
+            "    type ConstructorArgs = {}ConstructorArgs;\n",
+            struct_name
+        ));
+        content.push_str(&format!(
+            "    type RequiredArgs = {}RequiredArgs;\n\n",
+            struct_name
+        ));
         content.push_str("    fn __runtime() -> &'static [u8] {\n");
         content.push_str(&format!(
             "        {}_BYTECODE\n",
@@ -122,6 +143,7 @@ pub fn generate_temp_crates(
     projects: &[ContractProject],
     temp_dir: &Path,
     project_root: &Path,
+    profile: BuildProfile,
 ) -> Result<Vec<GeneratedContract>, CompileError> {
     let mut generated_contracts = Vec::new();
 
@@ -150,7 +172,7 @@ pub fn generate_temp_crates(
             };
 
             // Populate the temp dir with the modified files from the working dir
-            generate_cargo_toml(project, target, &contract)?;
+            generate_cargo_toml(project, target, &contract, profile)?;
             decouple_contract_module(project, target, &contract.path)?;
             generate_cargo_config(&contract.path, project_root)?;
 
@@ -161,11 +183,12 @@ pub fn generate_temp_crates(
     Ok(generated_contracts)
 }
 
-/// Generate a modified `Cargo.toml` for a R55 temporary crate
+/// Generate a modified `Cargo.toml` for a R55 temporary crate, built with `profile`
 fn generate_cargo_toml(
     project: &ContractProject,
     target: &ContractTarget,
     contract: &GeneratedContract,
+    profile: BuildProfile,
 ) -> Result<(), CompileError> {
     let mut cargo_toml = String::new();
 
@@ -196,6 +219,10 @@ interface-only = []
                 let mut dep_table_adj = dep_table.clone();
                 let mut is_generated = false;
 
+                if dep_name == "eth-riscv-runtime" && !profile.runtime_features().is_empty() {
+                    add_features(&mut dep_table_adj, profile.runtime_features());
+                }
+
                 // Adjust path dependencies
                 if let Some(Value::String(rel_path)) = dep_table.get("path") {
                     if rel_path == "." {
@@ -276,10 +303,9 @@ path = "src/lib.rs"
 required-features = ["deploy"]
 
 [profile.release]
-lto = true
-opt-level = "z"
 "#,
     );
+    cargo_toml.push_str(profile.cargo_profile());
 
     // Write to file
     fs::write(contract.path.join("Cargo.toml"), cargo_toml)?;
@@ -287,7 +313,22 @@ opt-level = "z"
     Ok(())
 }
 
-/// Create `.cargo/config.toml` in the temporary crate
+/// Enables `features` in a dependency table, along with the ones it already enables
+fn add_features(dep_table: &mut toml::map::Map<String, Value>, features: &[&str]) {
+    let mut enabled = match dep_table.remove("features") {
+        Some(Value::Array(enabled)) => enabled,
+        _ => Vec::new(),
+    };
+    for feature in features {
+        let feature = Value::String(feature.to_string());
+        if !enabled.contains(&feature) {
+            enabled.push(feature);
+        }
+    }
+    dep_table.insert("features".into(), Value::Array(enabled));
+}
+
+/// Create `.cargo/config.toml` in the temporary crate, building for `RISCV_TARGET` by default
 fn generate_cargo_config(target_dir: &Path, project_root: &Path) -> Result<(), CompileError> {
     let cargo_dir = target_dir.join(".cargo");
     fs::create_dir_all(&cargo_dir)?;
@@ -302,16 +343,17 @@ fn generate_cargo_config(target_dir: &Path, project_root: &Path) -> Result<(), C
     })?;
 
     let config_content = format!(
-        r#"[target.riscv64imac-unknown-none-elf]
+        r#"[target.{triple}]
 rustflags = [
   "-C", "link-arg=-T{}",
   "-C", "llvm-args=--inline-threshold=275"
 ]
 
 [build]
-target = "riscv64imac-unknown-none-elf"
+target = "{triple}"
 "#,
-        rel_rust_rt_path.display()
+        rel_rust_rt_path.display(),
+        triple = RISCV_TARGET
     );
 
     fs::write(cargo_dir.join("config.toml"), config_content)?;
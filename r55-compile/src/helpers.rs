@@ -1,5 +1,5 @@
 use std::{collections::HashMap, fmt::Write, fs, path::Path};
-use syn::{Attribute, ItemImpl};
+use syn::{Attribute, Item, ItemImpl};
 use toml::{map::Map, Value};
 use tracing::debug;
 
@@ -90,9 +90,32 @@ pub fn sort_generated_contracts(
 
 /// Whether a an array of attributes contains `contract`
 pub fn has_contract_attribute(attrs: &[Attribute]) -> bool {
+    has_attribute(attrs, "contract")
+}
+
+/// Whether a an array of attributes contains `proxy`
+pub fn has_proxy_attribute(attrs: &[Attribute]) -> bool {
+    has_attribute(attrs, "proxy")
+}
+
+fn has_attribute(attrs: &[Attribute], name: &str) -> bool {
     attrs
         .iter()
-        .any(|attr| attr.path.segments.len() == 1 && attr.path.segments[0].ident == "contract")
+        .any(|attr| attr.path.segments.len() == 1 && attr.path.segments[0].ident == name)
+}
+
+/// Extract the name of the contract defined by an item, if any: either the struct of a `#[contract]`
+/// implementation, or a `#[proxy]` struct (which generates its own implementation)
+pub fn get_contract_ident(item: &Item) -> Option<String> {
+    match item {
+        Item::Impl(item_impl) if has_contract_attribute(&item_impl.attrs) => {
+            get_struct_name(item_impl)
+        }
+        Item::Struct(item_struct) if has_proxy_attribute(&item_struct.attrs) => {
+            Some(item_struct.ident.to_string())
+        }
+        _ => None,
+    }
 }
 
 /// Extract the struct name from an item implementation
@@ -0,0 +1,588 @@
+use alloy_primitives::{keccak256, U256};
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use std::{fmt, fs, path::Path};
+use syn::{Fields, Item, ItemStruct};
+
+use crate::{
+    helpers::has_proxy_attribute,
+    types::{CompileError, ContractProject, ContractTarget},
+};
+
+/// Storage layout of a contract, exported next to its bytecode so that the layouts of different
+/// implementation versions can be checked for compatibility before upgrading a proxy
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageLayout {
+    pub contract: String,
+    /// Set with `#[storage_version(..)]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    pub fields: Vec<StorageField>,
+    /// Slots reserved with `#[gap(..)]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub gaps: Vec<StorageGap>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub slot: U256,
+}
+
+/// `size` slots reserved from `slot` on, for the fields of later versions
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageGap {
+    pub name: String,
+    pub slot: U256,
+    pub size: u64,
+}
+
+/// Change of a layout that would corrupt the storage written by the previous version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpgradeViolation {
+    FieldRemoved {
+        name: String,
+        slot: U256,
+    },
+    FieldMoved {
+        name: String,
+        old_slot: U256,
+        new_slot: U256,
+    },
+    TypeChanged {
+        name: String,
+        slot: U256,
+        old: String,
+        new: String,
+    },
+    /// Slots of a gap that are neither reserved anymore nor used by a new field
+    GapShrunk {
+        name: String,
+        first: U256,
+        last: U256,
+    },
+    VersionDecreased {
+        old: u64,
+        new: u64,
+    },
+}
+
+impl fmt::Display for UpgradeViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FieldRemoved { name, slot } => {
+                write!(f, "field `{}` (slot {}) was removed", name, slot)
+            }
+            Self::FieldMoved {
+                name,
+                old_slot,
+                new_slot,
+            } => write!(
+                f,
+                "field `{}` moved from slot {} to slot {}",
+                name, old_slot, new_slot
+            ),
+            Self::TypeChanged {
+                name,
+                slot,
+                old,
+                new,
+            } => write!(
+                f,
+                "field `{}` (slot {}) changed its type from `{}` to `{}`",
+                name, slot, old, new
+            ),
+            Self::GapShrunk { name, first, last } if first == last => {
+                write!(f, "gap `{}` shrank, releasing slot {}", name, first)
+            }
+            Self::GapShrunk { name, first, last } => write!(
+                f,
+                "gap `{}` shrank, releasing slots {}..={}",
+                name, first, last
+            ),
+            Self::VersionDecreased { old, new } => {
+                write!(f, "storage version decreased from {} to {}", old, new)
+            }
+        }
+    }
+}
+
+impl StorageLayout {
+    /// Extracts the layout of the `#[storage]` (or `#[proxy]`) struct of a contract target. The
+    /// storage it inherits, if any, is looked up in the same module or in the root one
+    pub fn from_target(
+        project: &ContractProject,
+        target: &ContractTarget,
+    ) -> Result<Self, CompileError> {
+        let mut items = syn::parse_file(&fs::read_to_string(&target.source_file)?)?.items;
+        let lib_path = project.path.join("src").join("lib.rs");
+        if target.source_file != lib_path {
+            items.extend(syn::parse_file(&fs::read_to_string(&lib_path)?)?.items);
+        }
+
+        let item = find_struct(&items, &target.ident).ok_or_else(|| {
+            CompileError::NoContractFound(target.source_file.display().to_string())
+        })?;
+
+        if has_proxy_attribute(&item.attrs) {
+            return Ok(Self {
+                contract: target.ident.clone(),
+                version: None,
+                fields: proxy_fields(),
+                gaps: Vec::new(),
+            });
+        }
+
+        Self::from_storage(item, &items)
+    }
+
+    /// Loads a layout exported by `export`
+    pub fn load(path: &Path) -> Result<Self, CompileError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| CompileError::PathError(format!("{}: {}", path.display(), e)))?;
+
+        serde_json::from_str(&content).map_err(|e| {
+            CompileError::PathError(format!("Invalid layout {}: {}", path.display(), e))
+        })
+    }
+
+    /// Writes the layout as `<generated_package>.layout.json` in the output dir
+    pub fn export(&self, target: &ContractTarget, output_dir: &Path) -> Result<(), CompileError> {
+        let path = output_dir.join(format!("{}.layout.json", target.generated_package));
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| CompileError::PathError(format!("Failed to serialize layout: {}", e)))?;
+
+        fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Changes from `self` to `new` that would corrupt the existing storage. Fields must keep their
+    /// slot and type (they can be renamed), gaps can only shrink to make room for new fields, and
+    /// the version can't go down. New fields can be appended, or placed in the slots of a gap.
+    pub fn check_upgrade(&self, new: &StorageLayout) -> Vec<UpgradeViolation> {
+        let mut violations = Vec::new();
+        if let (Some(old), Some(new)) = (self.version, new.version) {
+            if new < old {
+                violations.push(UpgradeViolation::VersionDecreased { old, new });
+            }
+        }
+
+        for old in &self.fields {
+            // Fields are tracked by name first, so that swapped fields of the same type are caught
+            let moved = new
+                .fields
+                .iter()
+                .find(|f| f.name == old.name && f.slot != old.slot);
+            if let Some(field) = moved {
+                violations.push(UpgradeViolation::FieldMoved {
+                    name: old.name.clone(),
+                    old_slot: old.slot,
+                    new_slot: field.slot,
+                });
+                continue;
+            }
+
+            match new.fields.iter().find(|f| f.slot == old.slot) {
+                None => violations.push(UpgradeViolation::FieldRemoved {
+                    name: old.name.clone(),
+                    slot: old.slot,
+                }),
+                Some(field) if field.ty != old.ty => {
+                    violations.push(UpgradeViolation::TypeChanged {
+                        name: old.name.clone(),
+                        slot: old.slot,
+                        old: old.ty.clone(),
+                        new: field.ty.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        // The slots of the old gaps must still be reserved, or hold fields that didn't exist. The
+        // ones holding existing fields were already reported as moved
+        for gap in &self.gaps {
+            let released = (0..gap.size)
+                .map(|i| gap.slot + U256::from(i))
+                .filter(|slot| !new.gaps.iter().any(|g| g.contains(*slot)))
+                .filter(|slot| !new.fields.iter().any(|f| f.slot == *slot))
+                .collect::<Vec<_>>();
+            if let (Some(first), Some(last)) = (released.first(), released.last()) {
+                violations.push(UpgradeViolation::GapShrunk {
+                    name: gap.name.clone(),
+                    first: *first,
+                    last: *last,
+                });
+            }
+        }
+
+        violations
+    }
+
+    // Layout of a `#[storage]` struct, whose inherited storage is looked up in `items`
+    fn from_storage(item: &ItemStruct, items: &[Item]) -> Result<Self, CompileError> {
+        let version = match item
+            .attrs
+            .iter()
+            .find(|attr| attr.path.is_ident("storage_version"))
+        {
+            Some(attr) => Some(attr.parse_args::<syn::LitInt>()?.base10_parse()?),
+            None => None,
+        };
+        let (fields, gaps, _) = storage_fields(item, items, &mut Vec::new())?;
+
+        Ok(Self {
+            contract: item.ident.to_string(),
+            version,
+            fields,
+            gaps,
+        })
+    }
+}
+
+impl StorageGap {
+    fn contains(&self, slot: U256) -> bool {
+        slot >= self.slot && slot - self.slot < U256::from(self.size)
+    }
+}
+
+// Mirrors the slot allocation of the `#[storage]` macro: fields pinned with `#[slot(..)]` use that
+// slot, gaps reserve the next `#[gap(..)]` slots, while the others are laid out sequentially, after
+// the ones of the inherited storage (whose fields come first). Also returns the next sequential
+// slot, which `inheriting` (the structs being laid out) start after
+fn storage_fields<'a>(
+    item: &'a ItemStruct,
+    items: &'a [Item],
+    inheriting: &mut Vec<&'a syn::Ident>,
+) -> Result<(Vec<StorageField>, Vec<StorageGap>, u64), CompileError> {
+    if inheriting.contains(&&item.ident) {
+        return Err(CompileError::CyclicDependency);
+    }
+    inheriting.push(&item.ident);
+
+    let (mut fields, mut gaps, mut next_slot) = match inherited(item, items)? {
+        Some(base) => storage_fields(base, items, inheriting)?,
+        None => (Vec::new(), Vec::new(), 0),
+    };
+    let Fields::Named(named) = &item.fields else {
+        return Ok((fields, gaps, next_slot));
+    };
+
+    for field in named.named.iter() {
+        let name = field
+            .ident
+            .as_ref()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        if let Some(attr) = field.attrs.iter().find(|attr| attr.path.is_ident("gap")) {
+            let size = attr.parse_args::<syn::LitInt>()?.base10_parse()?;
+            gaps.push(StorageGap {
+                name,
+                slot: U256::from(next_slot),
+                size,
+            });
+            next_slot += size;
+            continue;
+        }
+
+        let slot = match field.attrs.iter().find(|attr| attr.path.is_ident("slot")) {
+            Some(attr) => {
+                let lit: syn::LitInt = attr.parse_args()?;
+                U256::from_str_radix(lit.base10_digits(), 10)
+                    .map_err(|_| syn::Error::new_spanned(&lit, "Slot exceeds 256 bits"))?
+            }
+            None => {
+                next_slot += 1;
+                U256::from(next_slot - 1)
+            }
+        };
+
+        fields.push(StorageField {
+            name,
+            ty: field.ty.to_token_stream().to_string().replace(' ', ""),
+            slot,
+        });
+    }
+
+    Ok((fields, gaps, next_slot))
+}
+
+// Storage struct that `item` inherits with `#[inherit(..)]`
+fn inherited<'a>(
+    item: &ItemStruct,
+    items: &'a [Item],
+) -> Result<Option<&'a ItemStruct>, CompileError> {
+    let Some(attr) = item.attrs.iter().find(|attr| attr.path.is_ident("inherit")) else {
+        return Ok(None);
+    };
+    let path: syn::Path = attr.parse_args()?;
+    let base = path
+        .segments
+        .last()
+        .and_then(|segment| find_struct(items, &segment.ident.to_string()))
+        .ok_or_else(|| syn::Error::new_spanned(&path, "inherited storage not found"))?;
+
+    Ok(Some(base))
+}
+
+fn find_struct<'a>(items: &'a [Item], name: &str) -> Option<&'a ItemStruct> {
+    items.iter().find_map(|item| match item {
+        Item::Struct(item_struct) if item_struct.ident == name => Some(item_struct),
+        _ => None,
+    })
+}
+
+// Layout generated by the `#[proxy]` macro, which only uses the EIP-1967 slots
+fn proxy_fields() -> Vec<StorageField> {
+    let eip1967_slot = |label: &str| U256::from_be_bytes(keccak256(label).0) - U256::from(1);
+
+    vec![
+        StorageField {
+            name: "implementation".into(),
+            ty: "Slot<Address>".into(),
+            slot: eip1967_slot("eip1967.proxy.implementation"),
+        },
+        StorageField {
+            name: "admin".into(),
+            ty: "Slot<Address>".into(),
+            slot: eip1967_slot("eip1967.proxy.admin"),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout(source: &str) -> StorageLayout {
+        StorageLayout::from_storage(&syn::parse_str(source).unwrap(), &[]).unwrap()
+    }
+
+    #[test]
+    fn test_layout_with_gap() {
+        let v1 = layout(
+            "#[storage_version(1)]
+            pub struct Token {
+                total_supply: Slot<U256>,
+                #[gap(10)]
+                __gap: Gap,
+                owner: Slot<Address>,
+            }",
+        );
+
+        assert_eq!(v1.version, Some(1));
+        assert_eq!(v1.fields[1].name, "owner");
+        assert_eq!(v1.fields[1].slot, U256::from(11));
+        assert_eq!(
+            v1.gaps,
+            vec![StorageGap {
+                name: "__gap".into(),
+                slot: U256::from(1),
+                size: 10,
+            }]
+        );
+    }
+
+    // Layout of a contract of the examples, read from its sources
+    fn example_layout(generated_package: &str) -> StorageLayout {
+        let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../examples");
+        let projects = crate::helpers::find_r55_projects(&examples).unwrap();
+        let (project, target) = projects
+            .iter()
+            .flat_map(|project| project.targets.iter().map(move |target| (project, target)))
+            .find(|(_, target)| target.generated_package == generated_package)
+            .unwrap();
+
+        StorageLayout::from_target(project, target).unwrap()
+    }
+
+    #[test]
+    fn test_upgradeable_example() {
+        let v1 = example_layout("upgradeable-v1");
+        let v2 = example_layout("upgradeable-v2");
+
+        // V2 only appends a field to V1, and bumps its version
+        assert_eq!(v1.check_upgrade(&v2), vec![]);
+        assert_eq!(
+            v2.check_upgrade(&v1),
+            vec![
+                UpgradeViolation::VersionDecreased { old: 2, new: 1 },
+                UpgradeViolation::FieldRemoved {
+                    name: "step".into(),
+                    slot: U256::from(1),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_upgrade_append() {
+        let v1 = layout("pub struct Token { total_supply: Slot<U256>, owner: Slot<Address> }");
+        let v2 = layout(
+            "pub struct Token { total_supply: Slot<U256>, owner: Slot<Address>, paused: Slot<bool> }",
+        );
+
+        assert_eq!(v1.check_upgrade(&v2), vec![]);
+        assert_eq!(
+            v2.check_upgrade(&v1),
+            vec![UpgradeViolation::FieldRemoved {
+                name: "paused".into(),
+                slot: U256::from(2),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_upgrade_reorder() {
+        let v1 = layout("pub struct Token { total_supply: Slot<U256>, cap: Slot<U256> }");
+        let v2 = layout("pub struct Token { cap: Slot<U256>, total_supply: Slot<U256> }");
+
+        let violations = v1.check_upgrade(&v2);
+        assert_eq!(
+            violations,
+            vec![
+                UpgradeViolation::FieldMoved {
+                    name: "total_supply".into(),
+                    old_slot: U256::ZERO,
+                    new_slot: U256::from(1),
+                },
+                UpgradeViolation::FieldMoved {
+                    name: "cap".into(),
+                    old_slot: U256::from(1),
+                    new_slot: U256::ZERO,
+                },
+            ]
+        );
+        assert_eq!(
+            violations[0].to_string(),
+            "field `total_supply` moved from slot 0 to slot 1"
+        );
+
+        // Renaming a field in place is fine, unlike changing its type
+        let v2 = layout("pub struct Token { total_supply: Slot<U256>, max_supply: Slot<U256> }");
+        assert_eq!(v1.check_upgrade(&v2), vec![]);
+        let v2 = layout("pub struct Token { total_supply: Slot<U256>, cap: Slot<u64> }");
+        assert_eq!(
+            v1.check_upgrade(&v2)[0].to_string(),
+            "field `cap` (slot 1) changed its type from `Slot<U256>` to `Slot<u64>`"
+        );
+    }
+
+    #[test]
+    fn test_upgrade_gap() {
+        let v1 = layout(
+            "#[storage_version(1)]
+            pub struct Token {
+                total_supply: Slot<U256>,
+                #[gap(3)]
+                __gap: Gap,
+                owner: Slot<Address>,
+            }",
+        );
+
+        // New fields take the slots of the gap, which shrinks accordingly
+        let v2 = layout(
+            "#[storage_version(2)]
+            pub struct Token {
+                total_supply: Slot<U256>,
+                cap: Slot<U256>,
+                paused: Slot<bool>,
+                #[gap(1)]
+                __gap: Gap,
+                owner: Slot<Address>,
+            }",
+        );
+        assert_eq!(v1.check_upgrade(&v2), vec![]);
+        assert_eq!(
+            v2.check_upgrade(&v1),
+            vec![
+                UpgradeViolation::VersionDecreased { old: 2, new: 1 },
+                UpgradeViolation::FieldRemoved {
+                    name: "cap".into(),
+                    slot: U256::from(1),
+                },
+                UpgradeViolation::FieldRemoved {
+                    name: "paused".into(),
+                    slot: U256::from(2),
+                },
+            ]
+        );
+
+        // Shrinking the gap without using its slots shifts the following fields
+        let v2 = layout(
+            "pub struct Token {
+                total_supply: Slot<U256>,
+                #[gap(1)]
+                __gap: Gap,
+                owner: Slot<Address>,
+            }",
+        );
+        let violations = v1.check_upgrade(&v2);
+        assert_eq!(
+            violations,
+            vec![
+                UpgradeViolation::FieldMoved {
+                    name: "owner".into(),
+                    old_slot: U256::from(4),
+                    new_slot: U256::from(2),
+                },
+                UpgradeViolation::GapShrunk {
+                    name: "__gap".into(),
+                    first: U256::from(3),
+                    last: U256::from(3),
+                },
+            ]
+        );
+        assert_eq!(
+            violations[1].to_string(),
+            "gap `__gap` shrank, releasing slot 3"
+        );
+    }
+
+    #[test]
+    fn test_layout_inherited() {
+        let file: syn::File = syn::parse_str(
+            "pub struct ERC20Base {
+                total_supply: Slot<U256>,
+                balances: Mapping<Address, U256>,
+                #[slot(100)]
+                paused: Slot<bool>,
+            }
+            #[inherit(ERC20Base)]
+            pub struct FeeToken {
+                fee_bps: Slot<U256>,
+                #[gap(2)]
+                __gap: Gap,
+                treasury: Slot<Address>,
+            }",
+        )
+        .unwrap();
+        let Item::Struct(item) = &file.items[1] else {
+            unreachable!()
+        };
+        let layout = StorageLayout::from_storage(item, &file.items).unwrap();
+
+        let slots: Vec<_> = layout
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.slot.to::<u64>()))
+            .collect();
+        assert_eq!(
+            slots,
+            [
+                ("total_supply", 0),
+                ("balances", 1),
+                ("paused", 100),
+                ("fee_bps", 2),
+                ("treasury", 5)
+            ]
+        );
+        assert_eq!(layout.gaps[0].slot, U256::from(3));
+
+        // A missing base is an error, rather than a layout that the contract doesn't have
+        let result = StorageLayout::from_storage(item, &file.items[1..]);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use alloy_primitives::{keccak256, B256};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::types::{CompileError, GeneratedContract};
+
+/// Name of the lockfile, written next to the compiled bytecode
+pub const LOCKFILE: &str = "r55.lock";
+
+/// Crates that end up in the bytecode of every contract, relative to the project root
+const SHARED_SOURCES: [&str; 3] = [
+    "contract-derive/src",
+    "eth-riscv-runtime/src",
+    "eth-riscv-syscalls/src",
+];
+
+/// Records, for each compiled contract, the hash of its bytecode and of the sources it was compiled
+/// from, so that the `r55` tests can detect bytecode that is out of date with the sources
+#[derive(Debug, Default, Serialize)]
+pub struct Lockfile {
+    pub contracts: BTreeMap<String, LockEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LockEntry {
+    /// Keccak of the `.bin` file
+    pub bytecode: B256,
+    /// Source dirs that contribute to the bytecode, relative to the project root
+    pub sources: Vec<String>,
+    /// Hash of the files under `sources`, see `source_hash`
+    pub source_hash: B256,
+}
+
+impl Lockfile {
+    pub fn add(
+        &mut self,
+        contract: &GeneratedContract,
+        bytecode: &[u8],
+        project_root: &Path,
+    ) -> Result<(), CompileError> {
+        let project = contract
+            .original_source_path
+            .strip_prefix(project_root)
+            .map_err(|_| {
+                CompileError::PathError(format!(
+                    "{} is outside of the project root",
+                    contract.original_source_path.display()
+                ))
+            })?;
+
+        let mut sources = vec![project.to_string_lossy().replace('\\', "/")];
+        sources.extend(SHARED_SOURCES.iter().map(|dir| dir.to_string()));
+
+        let entry = LockEntry {
+            bytecode: keccak256(bytecode),
+            source_hash: source_hash(project_root, &sources)?,
+            sources,
+        };
+        self.contracts.insert(contract.name.clone(), entry);
+
+        Ok(())
+    }
+
+    pub fn export(&self, output_dir: &Path) -> Result<(), CompileError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| CompileError::PathError(format!("Failed to serialize lockfile: {}", e)))?;
+
+        fs::write(output_dir.join(LOCKFILE), json)?;
+        Ok(())
+    }
+}
+
+// Hashes the `.rs` and `.toml` files under `dirs`, sorted by their path relative to `root`. Each
+// file contributes its path and its contents, so that renames are also detected.
+//
+// NOTE: must be kept in sync with `r55::lock::source_hash`
+fn source_hash(root: &Path, dirs: &[String]) -> Result<B256, CompileError> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        collect_sources(&root.join(dir), &mut files)?;
+    }
+
+    let mut files = files
+        .into_iter()
+        .map(|path| {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            (relative.to_string_lossy().replace('\\', "/"), path)
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut preimage = Vec::new();
+    for (relative, path) in files {
+        preimage.extend_from_slice(relative.as_bytes());
+        preimage.push(0);
+        preimage.extend_from_slice(&fs::read(path)?);
+        preimage.push(0);
+    }
+
+    Ok(keccak256(preimage))
+}
+
+// Skips build outputs and hidden dirs
+fn collect_sources(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), CompileError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        if path.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                collect_sources(&path, files)?;
+            }
+        } else if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("rs" | "toml")
+        ) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
@@ -1,13 +1,34 @@
 mod ast;
+mod bindings;
 mod generate;
 mod helpers;
+mod layout;
+mod lock;
+mod max_gas;
+mod report;
+mod selectors;
+mod sourcemap;
+mod strip;
 mod types;
+mod verify;
 
+use bindings::{emit_bindings, ContractAbi};
+use eth_riscv_syscalls::header::Header;
 use generate::{generate_deployable, generate_temp_crates};
 use helpers::{find_r55_projects, sort_generated_contracts};
+use layout::StorageLayout;
+use lock::Lockfile;
+use report::DeployCost;
+use selectors::SelectorTable;
+use sourcemap::SourceMap;
+use types::BuildProfile;
 
-use std::{fs, path::Path};
-use tracing::{debug, info};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info, warn};
 
 fn main() -> eyre::Result<()> {
     // Initialize logging
@@ -17,8 +38,15 @@ fn main() -> eyre::Result<()> {
         .finish();
     tracing::subscriber::set_global_default(tracing_sub)?;
 
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "check-upgrade").is_some() {
+        return check_upgrade(CheckUpgradeArgs::parse(args)?);
+    }
+
+    let args = Args::parse(args)?;
+
     // Setup output directory for the compiled bytecode
-    let project_root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let project_root = project_root();
     let output_dir = project_root.join("r55-output-bytecode");
     fs::create_dir_all(&output_dir)?;
 
@@ -27,8 +55,7 @@ fn main() -> eyre::Result<()> {
     fs::create_dir_all(&temp_dir)?;
 
     // Find all R55 projects under the examples directory
-    let target_dir = project_root.join("examples");
-    let projects = find_r55_projects(&target_dir)?;
+    let projects = find_r55_projects(&project_root.join("examples"))?;
 
     // Log discovered examples and their contracts
     info!("Found {} R55 project:", projects.len());
@@ -44,8 +71,40 @@ fn main() -> eyre::Result<()> {
         }
     }
 
+    // Bindings only depend on the sources, so the contracts aren't compiled
+    if let Some(dir) = &args.bindings {
+        emit_bindings(&projects, project_root, dir)?;
+        info!("Bindings written to {}", dir.display());
+        return Ok(());
+    }
+
+    // Export the storage layout of each contract, to check upgrade compatibility
+    for project in &projects {
+        for target in &project.targets {
+            StorageLayout::from_target(project, target)?.export(target, &output_dir)?;
+        }
+    }
+
+    // Collect the selectors of every contract, to label the traces of the host
+    let mut selectors = SelectorTable::default();
+    let mut idents = HashMap::new();
+    for project in &projects {
+        for target in &project.targets {
+            selectors.add_abi(&ContractAbi::from_target(project, target)?);
+            idents.insert(target.generated_package.clone(), target.ident.clone());
+        }
+    }
+    for (selector, signatures) in selectors.collisions() {
+        warn!(
+            "Selector collision: {} is shared by {}",
+            selector,
+            signatures.join(", ")
+        );
+    }
+
     // Generate temp version of all R55 contracts
-    let generated_contracts = generate_temp_crates(&projects, &temp_dir, project_root)?;
+    let generated_contracts =
+        generate_temp_crates(&projects, &temp_dir, project_root, args.profile)?;
     debug!("GENERATED CONTRACTS:");
     for (i, contract) in generated_contracts.iter().enumerate() {
         debug!(" {}. {:?}", i, contract);
@@ -53,6 +112,7 @@ fn main() -> eyre::Result<()> {
 
     // Compile each contract in order
     let sorted_contracts = sort_generated_contracts(generated_contracts)?;
+    let mut lockfile = Lockfile::default();
     for contract in sorted_contracts {
         info!("Generating deployable.rs for contract: {}", contract.name);
 
@@ -61,12 +121,197 @@ fn main() -> eyre::Result<()> {
         generate_deployable(&contract, false)?;
 
         // Compile deployment code and save in the file
-        info!("Compiling: {}", contract.name);
-        let deploy_bytecode = contract.compile()?;
+        info!(
+            "Compiling: {} (header version {})",
+            contract.name,
+            Header::current().version
+        );
+        let compiled = contract.compile(args.profile, args.metadata)?;
         let deploy_path = output_dir.join(format!("{}.bin", contract.name));
 
-        fs::write(deploy_path, deploy_bytecode)?;
+        // Report the sizes and the estimated cost of the deployment, and how much the profile saves
+        let cost = DeployCost::new(&compiled, args.profile);
+        info!("{}: {}", contract.name, cost.summary(args.gas_price));
+        if let Some(comparison) = cost.compare(&contract.name, &output_dir) {
+            info!("{}: {}", contract.name, comparison);
+        }
+        cost.export(&contract.name, &output_dir)?;
+
+        // Keep the symbols and the line tables stripped from the runtime, for source maps
+        if let Some(runtime_debug) = &compiled.runtime_debug {
+            info!(
+                "{}: {} bytes of symbols and debug info stripped from the runtime",
+                contract.name,
+                runtime_debug.len().saturating_sub(compiled.runtime.len())
+            );
+            let debug_path = output_dir.join(format!("{}.runtime.debug", contract.name));
+            fs::write(debug_path, runtime_debug)?;
+            if let Some(source_map) = SourceMap::from_elf(&contract.name, runtime_debug)? {
+                source_map.export(&output_dir)?;
+            }
+        }
+
+        if let Some(ident) = idents.get(&contract.name) {
+            let header = Header::current().encode();
+            selectors.add_runtime(ident, &header, &compiled.runtime);
+        }
+
+        lockfile.add(&contract, &compiled.deploy, project_root)?;
+        fs::write(deploy_path, compiled.deploy)?;
+    }
+
+    // Record the hashes of the bytecode and its sources, to detect stale bytecode in the tests
+    lockfile.export(&output_dir)?;
+    selectors.export(&output_dir)?;
+
+    Ok(())
+}
+
+fn project_root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap()
+}
+
+/// `check-upgrade`: checks that the storage layout of a new version of a contract preserves the
+/// storage of the old one, failing with every offending change otherwise
+fn check_upgrade(args: CheckUpgradeArgs) -> eyre::Result<()> {
+    let old = StorageLayout::load(&args.old)?;
+    let new = match args.new {
+        LayoutSource::File(path) => StorageLayout::load(&path)?,
+        LayoutSource::Contract(name) => {
+            let projects = find_r55_projects(&project_root().join("examples"))?;
+            let (project, target) = projects
+                .iter()
+                .flat_map(|project| project.targets.iter().map(move |target| (project, target)))
+                .find(|(_, target)| target.generated_package == name)
+                .ok_or_else(|| eyre::eyre!("Unknown contract: {}", name))?;
+            StorageLayout::from_target(project, target)?
+        }
+    };
+
+    let violations = old.check_upgrade(&new);
+    if !violations.is_empty() {
+        let violations = violations
+            .iter()
+            .map(|violation| format!("\n  - {}", violation))
+            .collect::<String>();
+        return Err(eyre::eyre!(
+            "Unsafe upgrade of {} to {}:{}",
+            old.contract,
+            new.contract,
+            violations
+        ));
     }
 
+    info!(
+        "{} can be upgraded to {}: the existing storage is preserved",
+        old.contract, new.contract
+    );
     Ok(())
 }
+
+/// Command line flags
+struct Args {
+    /// `--gas-price <gwei>`: estimate the deployment costs in ETH
+    gas_price: Option<f64>,
+    /// Cleared by `--no-metadata`, for byte-exact reproducible builds of contracts without budgets
+    metadata: bool,
+    /// `--min-size`, optionally with `--panic-code`, or `--coverage`: see `BuildProfile`
+    profile: BuildProfile,
+    /// `--emit-bindings <dir>`: write the alloy bindings of the contracts in `dir`, instead of
+    /// compiling them
+    bindings: Option<PathBuf>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> eyre::Result<Self> {
+        let mut parsed = Self {
+            gas_price: None,
+            metadata: true,
+            profile: BuildProfile::default(),
+            bindings: None,
+        };
+        let mut panic_code = false;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--gas-price" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| eyre::eyre!("Missing value for `--gas-price`"))?;
+                    let gas_price = value
+                        .parse()
+                        .map_err(|_| eyre::eyre!("Invalid gas price (in gwei): {}", value))?;
+                    parsed.gas_price = Some(gas_price);
+                }
+                "--no-metadata" => parsed.metadata = false,
+                "--min-size" | "--coverage" if parsed.profile != BuildProfile::Default => {
+                    return Err(eyre::eyre!("`--min-size` and `--coverage` are exclusive"));
+                }
+                "--min-size" => parsed.profile = BuildProfile::MinSize { panic_code: false },
+                "--coverage" => parsed.profile = BuildProfile::Coverage,
+                "--panic-code" => panic_code = true,
+                "--emit-bindings" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| eyre::eyre!("Missing value for `--emit-bindings`"))?;
+                    parsed.bindings = Some(value.into());
+                }
+                arg => return Err(eyre::eyre!("Unknown argument: {}", arg)),
+            }
+        }
+
+        if panic_code {
+            if !parsed.profile.is_min_size() {
+                return Err(eyre::eyre!("`--panic-code` requires `--min-size`"));
+            }
+            parsed.profile = BuildProfile::MinSize { panic_code };
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Command line flags of `check-upgrade`
+struct CheckUpgradeArgs {
+    /// `--old <layout.json>`: layout of the deployed version
+    old: PathBuf,
+    /// `--new <layout.json | contract>`: layout of the new version
+    new: LayoutSource,
+}
+
+enum LayoutSource {
+    /// Layout exported by a previous build
+    File(PathBuf),
+    /// Current layout of a contract, named as its bytecode (e.g. `upgradeable-v2`), extracted from
+    /// its sources
+    Contract(String),
+}
+
+impl CheckUpgradeArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> eyre::Result<Self> {
+        let (mut old, mut new) = (None, None);
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| eyre::eyre!("Missing value for `{}`", arg))
+            };
+            match arg.as_str() {
+                "--old" => old = Some(PathBuf::from(value()?)),
+                "--new" => {
+                    let value = value()?;
+                    new = Some(if value.ends_with(".json") {
+                        LayoutSource::File(value.into())
+                    } else {
+                        LayoutSource::Contract(value)
+                    });
+                }
+                arg => return Err(eyre::eyre!("Unknown argument: {}", arg)),
+            }
+        }
+
+        Ok(Self {
+            old: old.ok_or_else(|| eyre::eyre!("Missing `--old <layout.json>`"))?,
+            new: new.ok_or_else(|| eyre::eyre!("Missing `--new <layout.json | contract>`"))?,
+        })
+    }
+}
@@ -0,0 +1,101 @@
+//! Extraction of the `#[max_gas]` budgets of a contract: `#[contract]` emits them in a dedicated
+//! section of the binary, which isn't loaded (and is dropped by `--min-size` builds), so they are
+//! moved to the metadata trailer where the test harness reads them from.
+
+use eth_riscv_syscalls::metadata::{GasBudget, MAX_GAS_SECTION};
+use goblin::elf::Elf;
+
+use crate::types::CompileError;
+
+/// Budgets listed in the `MAX_GAS_SECTION` of an (unstripped) ELF binary, if any.
+pub fn read_max_gas(bytes: &[u8]) -> Result<Vec<GasBudget>, CompileError> {
+    let elf = Elf::parse(bytes)
+        .map_err(|e| CompileError::PathError(format!("Failed to parse ELF binary: {}", e)))?;
+
+    let Some(section) = elf
+        .section_headers
+        .iter()
+        .find(|sh| elf.shdr_strtab.get_at(sh.sh_name) == Some(MAX_GAS_SECTION))
+    else {
+        return Ok(Vec::new());
+    };
+
+    let data = section
+        .file_range()
+        .and_then(|range| bytes.get(range))
+        .ok_or_else(|| {
+            CompileError::PathError(format!("{} section ends past the binary", MAX_GAS_SECTION))
+        })?;
+
+    GasBudget::parse_section(data)
+        .ok_or_else(|| CompileError::PathError(format!("Malformed {} section", MAX_GAS_SECTION)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ELF64 binary without segments, with a string table and a `MAX_GAS_SECTION` of `data`
+    fn elf64(data: &[u8]) -> Vec<u8> {
+        let (ehsize, shentsize) = (64u16, 64u16);
+        let strtab = format!("\0.shstrtab\0{}\0", MAX_GAS_SECTION).into_bytes();
+        let strtab_offset = ehsize as u64;
+        let data_offset = strtab_offset + strtab.len() as u64;
+        let shoff = data_offset + data.len() as u64;
+
+        let mut elf = vec![0x7f, b'E', b'L', b'F', 2, 1, 1];
+        elf.resize(16, 0);
+        elf.extend_from_slice(&2u16.to_le_bytes()); // e_type: EXEC
+        elf.extend_from_slice(&243u16.to_le_bytes()); // e_machine: RISC-V
+        elf.extend_from_slice(&1u32.to_le_bytes()); // e_version
+        elf.extend_from_slice(&0x8030_0000u64.to_le_bytes()); // e_entry
+        elf.extend_from_slice(&0u64.to_le_bytes()); // e_phoff
+        elf.extend_from_slice(&shoff.to_le_bytes()); // e_shoff
+        elf.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+        for half in [ehsize, 56, 0, shentsize, 3, 1] {
+            elf.extend_from_slice(&half.to_le_bytes());
+        }
+
+        elf.extend_from_slice(&strtab);
+        elf.extend_from_slice(data);
+
+        // Null section, then SHT_STRTAB and SHT_PROGBITS
+        elf.resize(elf.len() + shentsize as usize, 0);
+        for (name, kind, offset, size) in [
+            (1u32, 3u32, strtab_offset, strtab.len() as u64),
+            (11, 1, data_offset, data.len() as u64),
+        ] {
+            elf.extend_from_slice(&name.to_le_bytes());
+            elf.extend_from_slice(&kind.to_le_bytes());
+            elf.extend_from_slice(&0u64.to_le_bytes()); // sh_flags
+            elf.extend_from_slice(&0u64.to_le_bytes()); // sh_addr
+            elf.extend_from_slice(&offset.to_le_bytes());
+            elf.extend_from_slice(&size.to_le_bytes());
+            elf.extend_from_slice(&[0; 8]); // sh_link, sh_info
+            elf.extend_from_slice(&1u64.to_le_bytes()); // sh_addralign
+            elf.extend_from_slice(&0u64.to_le_bytes()); // sh_entsize
+        }
+        elf
+    }
+
+    #[test]
+    fn test_read_max_gas() {
+        let mut data = vec![0xa9, 0x05, 0x9c, 0xbb];
+        data.extend_from_slice(&60_000u64.to_be_bytes());
+        data.push(8);
+        data.extend_from_slice(b"transfer");
+
+        let budgets = read_max_gas(&elf64(&data)).unwrap();
+        assert_eq!(
+            budgets,
+            vec![GasBudget {
+                selector: [0xa9, 0x05, 0x9c, 0xbb],
+                max_gas: 60_000,
+                name: "transfer".into(),
+            }]
+        );
+
+        // Truncated entry
+        assert!(read_max_gas(&elf64(&data[..10])).is_err());
+    }
+}
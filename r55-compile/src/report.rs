@@ -0,0 +1,115 @@
+use eth_riscv_syscalls::header::Header;
+use serde::Serialize;
+use std::{fs, path::Path};
+
+use crate::types::{BuildProfile, CompileError, CompiledContract};
+
+const TX_BASE: u64 = 21000;
+const TX_DATA_ZERO: u64 = 4;
+const TX_DATA_NON_ZERO: u64 = 16;
+const INITCODE_WORD: u64 = 2;
+const CODE_DEPOSIT_BYTE: u64 = 200;
+const CREATE_BASE: u64 = 32000;
+
+/// Rough cost of deploying a contract without constructor args, under the latest gas schedule.
+/// The execution of the constructor isn't included, so it is a lower bound of the gas used.
+#[derive(Debug, Serialize)]
+pub struct DeployCost {
+    /// Build profile of the contract, see `BuildProfile::name`
+    pub profile: &'static str,
+    /// Size of the initcode sent in the deployment tx
+    pub initcode_size: usize,
+    /// Size of the code stored at the contract's address
+    pub runtime_size: usize,
+    /// Base cost, calldata bytes, create surcharge and initcode words (EIP-3860)
+    pub intrinsic_gas: u64,
+    /// 200 gas per byte of stored code
+    pub code_deposit_gas: u64,
+    /// Deploying it from another contract (`CREATE`) instead: base cost, initcode words and code
+    /// deposit
+    pub create_gas: u64,
+}
+
+impl DeployCost {
+    pub fn new(contract: &CompiledContract, profile: BuildProfile) -> Self {
+        // R55 initcode: [header][codesize][deploy bytecode]
+        let header = Header::current().encode();
+        let mut initcode = header.to_vec();
+        initcode.extend_from_slice(&(contract.deploy.len() as u32).to_be_bytes());
+        initcode.extend_from_slice(&contract.deploy);
+
+        let zero_bytes = initcode.iter().filter(|b| **b == 0).count() as u64;
+        let non_zero_bytes = initcode.len() as u64 - zero_bytes;
+        let initcode_gas = INITCODE_WORD * (initcode.len() as u64).div_ceil(32);
+        let intrinsic_gas = TX_BASE
+            + CREATE_BASE
+            + zero_bytes * TX_DATA_ZERO
+            + non_zero_bytes * TX_DATA_NON_ZERO
+            + initcode_gas;
+
+        // The constructor returns the runtime prefixed with the header
+        let runtime_size = header.len() + contract.runtime.len();
+        let code_deposit_gas = CODE_DEPOSIT_BYTE * runtime_size as u64;
+
+        Self {
+            profile: profile.name(),
+            initcode_size: initcode.len(),
+            runtime_size,
+            intrinsic_gas,
+            code_deposit_gas,
+            create_gas: CREATE_BASE + initcode_gas + code_deposit_gas,
+        }
+    }
+
+    pub fn total_gas(&self) -> u64 {
+        self.intrinsic_gas + self.code_deposit_gas
+    }
+
+    /// Human-readable summary, with the cost in ETH if a gas price (in gwei) is given
+    pub fn summary(&self, gas_price_gwei: Option<f64>) -> String {
+        let mut summary = format!(
+            "initcode: {} bytes, runtime: {} bytes, deploy gas: ~{} (intrinsic: {}, code deposit: {}), from a contract: ~{}",
+            self.initcode_size,
+            self.runtime_size,
+            self.total_gas(),
+            self.intrinsic_gas,
+            self.code_deposit_gas,
+            self.create_gas
+        );
+        if let Some(gas_price) = gas_price_gwei {
+            let eth = self.total_gas() as f64 * gas_price / 1e9;
+            summary.push_str(&format!(", ~{:.6} ETH at {} gwei", eth, gas_price));
+        }
+
+        summary
+    }
+
+    /// Compares the runtime size with the one of the previous build, if it was built with another
+    /// profile, e.g. `runtime: 5230 (default) -> 3114 bytes (min-size), -40.5%`
+    pub fn compare(&self, name: &str, output_dir: &Path) -> Option<String> {
+        let path = output_dir.join(format!("{}.deploy.json", name));
+        let previous: serde_json::Value = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+        // Reports without a profile predate them, so were built with the default one
+        let profile = previous["profile"].as_str().unwrap_or("default");
+        let size = previous["runtime_size"].as_u64()?;
+        if profile == self.profile {
+            return None;
+        }
+
+        let change = (self.runtime_size as f64 / size as f64 - 1.0) * 100.0;
+        Some(format!(
+            "runtime: {} ({}) -> {} bytes ({}), {:+.1}%",
+            size, profile, self.runtime_size, self.profile, change
+        ))
+    }
+
+    /// Writes the cost as `<contract>.deploy.json` in the output dir
+    pub fn export(&self, name: &str, output_dir: &Path) -> Result<(), CompileError> {
+        let path = output_dir.join(format!("{}.deploy.json", name));
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| CompileError::PathError(format!("Failed to serialize report: {}", e)))?;
+
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
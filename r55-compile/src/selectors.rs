@@ -0,0 +1,185 @@
+//! Selector table of the compiled contracts, for the host to label call traces and reverts with
+//! signatures instead of raw selectors. It's written next to the bytecode as `selectors.json`,
+//! with the function and error selectors and the event topics of every contract, and the code hash
+//! of each deployed runtime to attribute a selector shared by several contracts.
+
+use alloy_primitives::{keccak256, B256};
+use serde::Serialize;
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{bindings::ContractAbi, types::CompileError};
+
+/// Name of the selector table, written next to the compiled bytecode
+pub const SELECTORS_FILE: &str = "selectors.json";
+
+#[derive(Debug, Default, Serialize)]
+pub struct SelectorTable {
+    /// Function selectors (e.g. `0x23b872dd`)
+    pub functions: BTreeMap<String, Vec<SelectorEntry>>,
+    /// Error selectors, e.g. of `ERC20Error::InsufficientBalance(uint256)`
+    pub errors: BTreeMap<String, Vec<SelectorEntry>>,
+    /// Event topics, i.e. the full hash of their signature
+    pub events: BTreeMap<String, Vec<SelectorEntry>>,
+    /// Contract of each runtime, by the hash of its deployed code (`[header][runtime]`)
+    pub runtimes: BTreeMap<B256, String>,
+}
+
+/// Signature of a selector, and the contracts that declare it. A selector with several entries is
+/// a collision: different signatures that hash to the same selector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelectorEntry {
+    pub signature: String,
+    pub contracts: Vec<String>,
+}
+
+impl SelectorTable {
+    /// Adds the functions, errors and events of a contract
+    pub fn add_abi(&mut self, abi: &ContractAbi) {
+        for function in &abi.functions {
+            let signature = function.signature();
+            let selector = keccak256(&signature)[..4].to_vec();
+            insert(&mut self.functions, &selector, signature, &abi.contract);
+        }
+        for error in &abi.errors {
+            for variant in &error.variants {
+                let signature = error.signature(variant);
+                let selector = keccak256(&signature)[..4].to_vec();
+                insert(&mut self.errors, &selector, signature, &abi.contract);
+            }
+        }
+        for event in &abi.events {
+            let signature = event.signature();
+            let topic = keccak256(&signature).to_vec();
+            insert(&mut self.events, &topic, signature, &abi.contract);
+        }
+    }
+
+    /// Records the deployed code of `contract`, i.e. its runtime prefixed with the R55 header
+    pub fn add_runtime(&mut self, contract: &str, header: &[u8], runtime: &[u8]) {
+        let code = [header, runtime].concat();
+        self.runtimes.insert(keccak256(code), contract.into());
+    }
+
+    /// Selectors (or topics) shared by different signatures, along with them
+    pub fn collisions(&self) -> Vec<(&str, Vec<&str>)> {
+        [&self.functions, &self.errors, &self.events]
+            .into_iter()
+            .flatten()
+            .filter(|(_, entries)| entries.len() > 1)
+            .map(|(selector, entries)| {
+                let signatures = entries.iter().map(|entry| entry.signature.as_str());
+                (selector.as_str(), signatures.collect())
+            })
+            .collect()
+    }
+
+    pub fn export(&self, output_dir: &Path) -> Result<(), CompileError> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            CompileError::PathError(format!("Failed to serialize selector table: {}", e))
+        })?;
+
+        fs::write(output_dir.join(SELECTORS_FILE), json)?;
+        Ok(())
+    }
+}
+
+// Adds `contract` to the entry of `signature`, or a new entry for it. Distinct signatures with the
+// same selector are all kept.
+fn insert(
+    table: &mut BTreeMap<String, Vec<SelectorEntry>>,
+    selector: &[u8],
+    signature: String,
+    contract: &str,
+) {
+    let entries = table
+        .entry(alloy_primitives::hex::encode_prefixed(selector))
+        .or_default();
+    match entries
+        .iter_mut()
+        .find(|entry| entry.signature == signature)
+    {
+        Some(entry) if entry.contracts.iter().any(|c| c == contract) => {}
+        Some(entry) => entry.contracts.push(contract.into()),
+        None => entries.push(SelectorEntry {
+            signature,
+            contracts: vec![contract.into()],
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::{AbiType, ErrorEnum, ErrorVariant, Function, Mutability, Param};
+
+    fn function(name: &str, types: &[&str]) -> Function {
+        Function {
+            name: name.into(),
+            inputs: types
+                .iter()
+                .map(|ty| Param {
+                    name: "arg".into(),
+                    ty: AbiType {
+                        sol: ty.to_string(),
+                        rust: String::new(),
+                        dynamic: false,
+                        primitives: Default::default(),
+                    },
+                })
+                .collect(),
+            outputs: Vec::new(),
+            mutability: Mutability::NonPayable,
+        }
+    }
+
+    fn abi(contract: &str, functions: Vec<Function>) -> ContractAbi {
+        ContractAbi {
+            contract: contract.into(),
+            functions,
+            events: Vec::new(),
+            errors: vec![ErrorEnum {
+                name: "ERC20Error".into(),
+                variants: vec![ErrorVariant {
+                    name: "ZeroAmount".into(),
+                    fields: None,
+                }],
+            }],
+        }
+    }
+
+    #[test]
+    fn test_selector_table() {
+        let mut table = SelectorTable::default();
+        table.add_abi(&abi(
+            "ERC20",
+            vec![function("transfer", &["address", "uint256"])],
+        ));
+        table.add_abi(&abi(
+            "Token",
+            vec![
+                function("transfer", &["address", "uint256"]),
+                // Same selector as `transfer(address,uint256)`
+                function("many_msg_babbage", &["bytes1"]),
+            ],
+        ));
+
+        let entries = &table.functions["0xa9059cbb"];
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].signature, "transfer(address,uint256)");
+        assert_eq!(entries[0].contracts, ["ERC20", "Token"]);
+        assert_eq!(entries[1].signature, "many_msg_babbage(bytes1)");
+        assert_eq!(entries[1].contracts, ["Token"]);
+        assert_eq!(
+            table.collisions(),
+            [(
+                "0xa9059cbb",
+                vec!["transfer(address,uint256)", "many_msg_babbage(bytes1)"]
+            )]
+        );
+
+        // Errors are shared by the contracts of a project
+        let selector =
+            alloy_primitives::hex::encode_prefixed(&keccak256("ERC20Error::ZeroAmount")[..4]);
+        assert_eq!(table.errors[&selector][0].contracts, ["ERC20", "Token"]);
+    }
+}
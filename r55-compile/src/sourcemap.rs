@@ -0,0 +1,128 @@
+//! Source maps of the runtimes: the source line of each range of PCs, read from the line tables of
+//! their debug copy. They're written next to the bytecode as `<contract>.sourcemap.json`, so that
+//! the host can map the PCs it runs (e.g. for coverage) back to the sources without parsing DWARF.
+
+use alloy_primitives::{keccak256, B256};
+use goblin::elf::{program_header::PT_LOAD, Elf};
+use serde::Serialize;
+use std::{fs, path::Path, rc::Rc};
+
+use crate::types::CompileError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceMap {
+    pub contract: String,
+    /// Hash of the loadable segments of the runtime, which its stripped and debug copies share
+    pub code_hash: B256,
+    /// Sorted by address
+    pub lines: Vec<SourceLine>,
+}
+
+/// Source line of the instructions in `start..end`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceLine {
+    pub start: u64,
+    pub end: u64,
+    pub file: String,
+    pub line: u32,
+}
+
+impl SourceMap {
+    /// Source map of the runtime binary `elf_data`, or `None` if it has no line tables.
+    pub fn from_elf(contract: &str, elf_data: &[u8]) -> Result<Option<Self>, CompileError> {
+        let elf = Elf::parse(elf_data)
+            .map_err(|e| CompileError::PathError(format!("Failed to parse ELF binary: {}", e)))?;
+        let Some(context) = line_tables(&elf, elf_data) else {
+            return Ok(None);
+        };
+
+        let mut lines = context
+            .find_location_range(0, u64::MAX)
+            .map_err(|e| CompileError::PathError(format!("Invalid line tables: {}", e)))?
+            .filter_map(|(addr, len, location)| {
+                Some(SourceLine {
+                    start: addr,
+                    end: addr + len,
+                    file: location.file?.to_string(),
+                    line: location.line?,
+                })
+            })
+            .collect::<Vec<_>>();
+        lines.sort_by_key(|line| line.start);
+
+        Ok(Some(Self {
+            contract: contract.into(),
+            code_hash: segments_hash(&elf, elf_data)?,
+            lines,
+        }))
+    }
+
+    pub fn export(&self, output_dir: &Path) -> Result<(), CompileError> {
+        let path = output_dir.join(format!("{}.sourcemap.json", self.contract));
+        let json = serde_json::to_string(self).map_err(|e| {
+            CompileError::PathError(format!("Failed to serialize source map: {}", e))
+        })?;
+
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
+
+// Line tables of the binary, if it was compiled with them
+fn line_tables(
+    elf: &Elf,
+    elf_data: &[u8],
+) -> Option<addr2line::Context<gimli::EndianRcSlice<gimli::RunTimeEndian>>> {
+    let endian = if elf.little_endian {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+    let section = |name: &str| {
+        elf.section_headers
+            .iter()
+            .find(|sh| elf.shdr_strtab.get_at(sh.sh_name) == Some(name))
+            .and_then(|sh| elf_data.get(sh.file_range()?))
+    };
+    section(".debug_line")?;
+
+    let dwarf = gimli::Dwarf::load(|id| {
+        let data = section(id.name()).unwrap_or_default();
+        Ok::<_, gimli::Error>(gimli::EndianRcSlice::new(Rc::from(data), endian))
+    })
+    .ok()?;
+    addr2line::Context::from_dwarf(dwarf).ok()
+}
+
+// Hash of the loadable segments, as the host computes it to find the source map of a runtime
+fn segments_hash(elf: &Elf, elf_data: &[u8]) -> Result<B256, CompileError> {
+    let mut preimage = Vec::new();
+    for ph in elf.program_headers.iter().filter(|ph| ph.p_type == PT_LOAD) {
+        let segment = elf_data.get(ph.file_range()).ok_or_else(|| {
+            CompileError::PathError("ELF segment ends past the binary".to_string())
+        })?;
+        preimage.extend_from_slice(&ph.p_vaddr.to_le_bytes());
+        preimage.extend_from_slice(segment);
+    }
+    Ok(keccak256(preimage))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_source_map_without_line_tables() {
+        // ELF64 header of a RISC-V binary, without segments nor sections
+        let mut elf = vec![0u8; 64];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2; // ELFCLASS64
+        elf[5] = 1; // little endian
+        elf[6] = 1; // EV_CURRENT
+        elf[18] = 0xf3; // EM_RISCV
+        elf[52] = 64; // e_ehsize
+
+        assert_eq!(SourceMap::from_elf("erc20", &elf).unwrap(), None);
+        assert!(SourceMap::from_elf("erc20", b"not an elf").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+//! Stripping of the compiled binaries for `--min-size` builds: the host only loads the segments
+//! listed in the program headers, so the symbol tables, the debug info and the section headers
+//! that follow them are dead weight in the bytecode.
+
+use goblin::elf::{program_header::PT_LOAD, Elf};
+
+use crate::types::CompileError;
+
+// Offsets of the section header fields in the ELF header: `e_shoff`, and `e_shentsize` which is
+// followed by `e_shnum` and `e_shstrndx`
+const ELF64_SHOFF: usize = 0x28;
+const ELF64_SHENTSIZE: usize = 0x3a;
+const ELF32_SHOFF: usize = 0x20;
+const ELF32_SHENTSIZE: usize = 0x2e;
+
+/// Truncates a (little-endian) ELF binary after its last loadable segment, and drops its section
+/// header table. Returns `None` if the binary has no section headers, i.e. is already stripped.
+pub fn strip_symbols(bytes: &[u8]) -> Result<Option<Vec<u8>>, CompileError> {
+    let elf = Elf::parse(bytes)
+        .map_err(|e| CompileError::PathError(format!("Failed to parse ELF binary: {}", e)))?;
+    if elf.header.e_shnum == 0 {
+        return Ok(None);
+    }
+    if !elf.little_endian {
+        return Err(CompileError::PathError(
+            "Unable to strip a big-endian ELF binary".into(),
+        ));
+    }
+
+    let headers_end =
+        elf.header.e_phoff + elf.header.e_phnum as u64 * elf.header.e_phentsize as u64;
+    let end = elf
+        .program_headers
+        .iter()
+        .filter(|ph| ph.p_type == PT_LOAD)
+        .map(|ph| ph.p_offset + ph.p_filesz)
+        .fold(headers_end, u64::max) as usize;
+    if end > bytes.len() {
+        return Err(CompileError::PathError(format!(
+            "ELF segments end past the binary ({} > {} bytes)",
+            end,
+            bytes.len()
+        )));
+    }
+
+    let mut stripped = bytes[..end].to_vec();
+    let (shoff, shentsize) = if elf.is_64 {
+        (ELF64_SHOFF..ELF64_SHOFF + 8, ELF64_SHENTSIZE)
+    } else {
+        (ELF32_SHOFF..ELF32_SHOFF + 4, ELF32_SHENTSIZE)
+    };
+    stripped[shoff].fill(0);
+    stripped[shentsize..shentsize + 6].fill(0);
+
+    Ok(Some(stripped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ELF64 binary with a single loadable segment of `code`, followed by a section header table
+    fn elf64(code: &[u8]) -> Vec<u8> {
+        let (ehsize, phentsize, shentsize) = (64u16, 56u16, 64u16);
+        let code_offset = (ehsize + phentsize) as u64;
+        let shoff = code_offset + code.len() as u64;
+
+        let mut elf = vec![0x7f, b'E', b'L', b'F', 2, 1, 1];
+        elf.resize(16, 0);
+        elf.extend_from_slice(&2u16.to_le_bytes()); // e_type: EXEC
+        elf.extend_from_slice(&243u16.to_le_bytes()); // e_machine: RISC-V
+        elf.extend_from_slice(&1u32.to_le_bytes()); // e_version
+        elf.extend_from_slice(&0x8030_0000u64.to_le_bytes()); // e_entry
+        elf.extend_from_slice(&(ehsize as u64).to_le_bytes()); // e_phoff
+        elf.extend_from_slice(&shoff.to_le_bytes()); // e_shoff
+        elf.extend_from_slice(&0u32.to_le_bytes()); // e_flags
+        for half in [ehsize, phentsize, 1, shentsize, 2, 0] {
+            elf.extend_from_slice(&half.to_le_bytes());
+        }
+
+        // PT_LOAD, R+X
+        elf.extend_from_slice(&1u32.to_le_bytes());
+        elf.extend_from_slice(&5u32.to_le_bytes());
+        elf.extend_from_slice(&code_offset.to_le_bytes());
+        elf.extend_from_slice(&0x8030_0000u64.to_le_bytes());
+        elf.extend_from_slice(&0x8030_0000u64.to_le_bytes());
+        elf.extend_from_slice(&(code.len() as u64).to_le_bytes());
+        elf.extend_from_slice(&(code.len() as u64).to_le_bytes());
+        elf.extend_from_slice(&0x1000u64.to_le_bytes());
+
+        elf.extend_from_slice(code);
+        // Null section and an empty one
+        elf.resize(elf.len() + 2 * shentsize as usize, 0);
+        elf
+    }
+
+    #[test]
+    fn test_strip_symbols() {
+        let code = [0x13, 0x00, 0x00, 0x00, 0x73, 0x00, 0x00, 0x00];
+        let original = elf64(&code);
+
+        let stripped = strip_symbols(&original).unwrap().unwrap();
+        assert_eq!(stripped.len(), 64 + 56 + code.len());
+
+        let elf = Elf::parse(&stripped).unwrap();
+        assert_eq!(elf.header.e_shnum, 0);
+        assert_eq!(elf.header.e_shoff, 0);
+        assert_eq!(elf.header.e_entry, 0x8030_0000);
+        let ph = &elf.program_headers[0];
+        assert_eq!(
+            &stripped[ph.p_offset as usize..(ph.p_offset + ph.p_filesz) as usize],
+            &code
+        );
+
+        // Already stripped
+        assert_eq!(strip_symbols(&stripped).unwrap(), None);
+    }
+}
@@ -1,3 +1,5 @@
+use alloy_primitives::Keccak256;
+use eth_riscv_syscalls::{header::Header, metadata::Metadata};
 use std::{
     collections::{HashMap, HashSet},
     fmt, fs,
@@ -10,7 +12,9 @@ use thiserror::Error;
 use toml::Value;
 use tracing::{debug, error, info};
 
-use crate::helpers::{get_struct_name, has_contract_attribute};
+use crate::{
+    helpers::get_contract_ident, max_gas::read_max_gas, strip::strip_symbols, verify::verify_binary,
+};
 
 #[derive(Debug, Error)]
 pub enum CompileError {
@@ -30,6 +34,8 @@ pub enum CompileError {
     CyclicDependency,
     #[error("Invalid import: {0}")]
     InvalidImport(&'static str),
+    #[error("Unsupported instructions: {0}")]
+    UnsupportedInstructions(String),
 }
 
 /// Represents a contract target within a project
@@ -75,6 +81,87 @@ pub struct GeneratedContract {
     pub original_source_path: PathBuf,
 }
 
+/// Toolchain the contracts are compiled with
+const TOOLCHAIN: &str = "nightly-2025-01-07";
+
+/// RISC-V target the contracts are compiled for, the only one the runtime and the host support
+pub const RISCV_TARGET: &str = "riscv64imac-unknown-none-elf";
+
+/// How the contracts are optimized
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BuildProfile {
+    /// Optimized for size, keeping the symbols and the panic messages
+    #[default]
+    Default,
+    /// `--min-size`: a single codegen unit, no panic formatting machinery, and binaries shipped
+    /// without their symbol tables. Panics abort (i.e. revert without data), unless `panic_code`
+    /// is set, in which case they revert with `eth_riscv_runtime::PANIC_CODE` instead.
+    MinSize { panic_code: bool },
+    /// `--coverage`: optimized like the default profile, but with line tables which, like the
+    /// symbols, are only kept in the debug copy of the runtime (and its source map)
+    Coverage,
+}
+
+impl BuildProfile {
+    /// Name of the profile in the reports
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Default => "default",
+            Self::MinSize { .. } => "min-size",
+            Self::Coverage => "coverage",
+        }
+    }
+
+    pub const fn is_min_size(&self) -> bool {
+        matches!(self, Self::MinSize { .. })
+    }
+
+    /// Whether the binaries are stripped, keeping a debug copy of the runtime
+    pub const fn strips_symbols(&self) -> bool {
+        !matches!(self, Self::Default)
+    }
+
+    /// `[profile.release]` of the generated crates
+    pub const fn cargo_profile(&self) -> &'static str {
+        match self {
+            Self::Default => "lto = true\nopt-level = \"z\"\n",
+            // The line tables are only kept in the debug copy of the binaries, for source maps
+            Self::MinSize { .. } => {
+                "lto = true\nopt-level = \"z\"\ncodegen-units = 1\ndebug = \"line-tables-only\"\n"
+            }
+            Self::Coverage => "lto = true\nopt-level = \"z\"\ndebug = \"line-tables-only\"\n",
+        }
+    }
+
+    /// Features of the standard library, rebuilt along with the contracts
+    pub const fn build_std_features(&self) -> Option<&'static str> {
+        match self {
+            // Panics go through the handler of the runtime, to revert with the panic code
+            Self::MinSize { panic_code: false } => Some("panic_immediate_abort"),
+            _ => None,
+        }
+    }
+
+    /// Features of `eth-riscv-runtime`
+    pub const fn runtime_features(&self) -> &'static [&'static str] {
+        match self {
+            Self::MinSize { panic_code: true } => &["panic-code"],
+            _ => &[],
+        }
+    }
+}
+
+/// Bytecode of a compiled contract
+#[derive(Debug, Clone)]
+pub struct CompiledContract {
+    /// Deployment bytecode, prefixed with the R55 header
+    pub deploy: Vec<u8>,
+    /// Runtime bytecode, without the R55 header. Includes the metadata trailer, if enabled
+    pub runtime: Vec<u8>,
+    /// Runtime binary with its symbols and line tables, if they were stripped from `runtime`
+    pub runtime_debug: Option<Vec<u8>>,
+}
+
 impl ContractTarget {
     pub fn is_self_reference(&self, dep_name: &String) -> bool {
         dep_name == &self.module || dep_name == &self.generated_package
@@ -114,37 +201,59 @@ impl GeneratedContract {
         }
     }
 
-    pub fn compile(&self) -> eyre::Result<Vec<u8>> {
+    /// Compiles the contract, returning its deployment bytecode (with the R55 header) along with
+    /// its runtime bytecode, which the deployment code embeds. Unless `with_metadata` is false,
+    /// the metadata trailer is appended to the runtime.
+    pub fn compile(
+        &self,
+        profile: BuildProfile,
+        with_metadata: bool,
+    ) -> eyre::Result<CompiledContract> {
         // First compile runtime
-        self.compile_runtime()?;
+        let (runtime, runtime_debug) = self.compile_runtime(profile, with_metadata)?;
 
         // Then compile deployment code
-        let bytecode = self.compile_deploy()?;
-        let mut prefixed_bytecode = vec![0xff]; // Add the 0xff prefix
+        let bytecode = self.compile_deploy(profile)?;
+        let mut prefixed_bytecode = Header::current().encode().to_vec();
         prefixed_bytecode.extend_from_slice(&bytecode);
 
-        Ok(prefixed_bytecode)
+        Ok(CompiledContract {
+            deploy: prefixed_bytecode,
+            runtime,
+            runtime_debug,
+        })
     }
 
-    fn compile_runtime(&self) -> eyre::Result<Vec<u8>> {
-        debug!("Compiling runtime: {}", self.name);
-
+    /// Builds one of the binaries of the crate, returning its path
+    fn build_bin(&self, bin: &str, profile: BuildProfile) -> eyre::Result<PathBuf> {
         let path = self
             .path
             .to_str()
             .ok_or_else(|| eyre::eyre!("Failed to convert path to string: {:?}", self.path))?;
 
-        let status = Command::new("cargo")
-            .arg("+nightly-2025-01-07")
+        let mut command = Command::new("cargo");
+        command
+            .arg(format!("+{}", TOOLCHAIN))
             .arg("build")
             .arg("-r")
             .arg("--lib")
             .arg("-Z")
-            .arg("build-std=core,alloc")
+            .arg("build-std=core,alloc");
+        if let Some(features) = profile.build_std_features() {
+            command
+                .arg("-Z")
+                .arg(format!("build-std-features={}", features));
+        }
+        command
             .arg("--target")
-            .arg("riscv64imac-unknown-none-elf")
+            .arg(RISCV_TARGET)
             .arg("--bin")
-            .arg("runtime")
+            .arg(bin);
+        if bin == "deploy" {
+            command.arg("--features").arg("deploy");
+        }
+
+        let status = command
             .current_dir(path)
             .status()
             .expect("Failed to execute cargo command");
@@ -156,12 +265,22 @@ impl GeneratedContract {
             info!("Cargo command completed successfully");
         }
 
-        let bin_path = PathBuf::from(path)
+        Ok(PathBuf::from(path)
             .join("target")
-            .join("riscv64imac-unknown-none-elf")
+            .join(RISCV_TARGET)
             .join("release")
-            .join("runtime");
+            .join(bin))
+    }
+
+    /// Returns the runtime bytecode and, if its symbols were stripped, the original binary
+    fn compile_runtime(
+        &self,
+        profile: BuildProfile,
+        with_metadata: bool,
+    ) -> eyre::Result<(Vec<u8>, Option<Vec<u8>>)> {
+        debug!("Compiling runtime: {}", self.name);
 
+        let bin_path = self.build_bin("runtime", profile)?;
         let mut file = fs::File::open(&bin_path).map_err(|e| {
             eyre::eyre!(
                 "Failed to open runtime binary {}: {}",
@@ -175,48 +294,68 @@ impl GeneratedContract {
         file.read_to_end(&mut bytecode)
             .map_err(|e| eyre::eyre!("Failed to read runtime binary: {}", e))?;
 
-        Ok(bytecode)
-    }
-
-    // Requires previous runtime compilation
-    fn compile_deploy(&self) -> eyre::Result<Vec<u8>> {
-        debug!("Compiling deploy: {}", self.name);
+        // The binary keeps the trailer of previous runs if cargo didn't rebuild it, so strip it
+        // before appending the new one. The deployment code embeds the binary as written here, so
+        // the symbols are stripped in place too, and a copy of the original is kept next to it.
+        let mut bytecode = Metadata::strip(&bytecode).to_vec();
+        let debug = if profile.strips_symbols() {
+            let debug_path = bin_path.with_extension("debug");
+            let debug = match strip_symbols(&bytecode)? {
+                Some(stripped) => std::mem::replace(&mut bytecode, stripped),
+                // Stripped by a previous run, whose copy is the original
+                None => fs::read(&debug_path)
+                    .map_err(|e| eyre::eyre!("Failed to read runtime debug binary: {}", e))?,
+            };
+            fs::write(&debug_path, &debug)
+                .map_err(|e| eyre::eyre!("Failed to write runtime debug binary: {}", e))?;
+            Some(debug)
+        } else {
+            None
+        };
+        let elf = debug.as_deref().unwrap_or(&bytecode);
+        verify_binary(&format!("{} (runtime)", self.name), elf)?;
+
+        if with_metadata {
+            let metadata = self.metadata(elf)?;
+            bytecode.extend_from_slice(&metadata.encode());
+        } else if !read_max_gas(elf)?.is_empty() {
+            // The harness only knows the budgets through the trailer, so they'd silently be ignored
+            return Err(eyre::eyre!(
+                "{} has `#[max_gas]` budgets, which need the metadata (drop `--no-metadata`)",
+                self.name
+            ));
+        }
+        fs::write(&bin_path, &bytecode)
+            .map_err(|e| eyre::eyre!("Failed to write runtime binary: {}", e))?;
 
-        let path = self
-            .path
-            .to_str()
-            .ok_or_else(|| eyre::eyre!("Failed to convert path to string: {:?}", self.path))?;
+        Ok((bytecode, debug))
+    }
 
-        let status = Command::new("cargo")
-            .arg("+nightly-2025-01-07")
-            .arg("build")
-            .arg("-r")
-            .arg("--lib")
-            .arg("-Z")
-            .arg("build-std=core,alloc")
-            .arg("--target")
-            .arg("riscv64imac-unknown-none-elf")
-            .arg("--bin")
-            .arg("deploy")
-            .arg("--features")
-            .arg("deploy")
-            .current_dir(path)
-            .status()
-            .expect("Failed to execute cargo command");
+    /// Metadata of the contract, with the hash of the sources of its generated crate and the
+    /// `#[max_gas]` budgets of its runtime binary, which the test harness only knows through it
+    fn metadata(&self, elf: &[u8]) -> eyre::Result<Metadata> {
+        let mut files = Vec::new();
+        collect_files(&self.path.join("src"), &mut files)?;
+        files.sort();
 
-        if !status.success() {
-            error!("Cargo command failed with status: {}", status);
-            std::process::exit(1);
-        } else {
-            info!("Cargo command completed successfully");
+        let mut hasher = Keccak256::new();
+        for file in files {
+            hasher.update(fs::read(file)?);
         }
 
-        let bin_path = PathBuf::from(path)
-            .join("target")
-            .join("riscv64imac-unknown-none-elf")
-            .join("release")
-            .join("deploy");
+        Ok(Metadata {
+            source_hash: hasher.finalize().0,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            toolchain: TOOLCHAIN.to_string(),
+            max_gas: read_max_gas(elf)?,
+        })
+    }
+
+    // Requires previous runtime compilation
+    fn compile_deploy(&self, profile: BuildProfile) -> eyre::Result<Vec<u8>> {
+        debug!("Compiling deploy: {}", self.name);
 
+        let bin_path = self.build_bin("deploy", profile)?;
         let mut file = fs::File::open(&bin_path).map_err(|e| {
             eyre::eyre!("Failed to open deploy binary {}: {}", bin_path.display(), e)
         })?;
@@ -225,6 +364,14 @@ impl GeneratedContract {
         let mut bytecode = Vec::new();
         file.read_to_end(&mut bytecode)
             .map_err(|e| eyre::eyre!("Failed to read deploy binary: {}", e))?;
+        verify_binary(&format!("{} (deploy)", self.name), &bytecode)?;
+
+        // Only the runtime is kept on chain, so there is no debug copy of the deployment code
+        if profile.strips_symbols() {
+            if let Some(stripped) = strip_symbols(&bytecode)? {
+                bytecode = stripped;
+            }
+        }
 
         Ok(bytecode)
     }
@@ -346,26 +493,22 @@ impl ContractProject {
             let module_content = fs::read_to_string(&module_path)?;
             let module_ast = syn::parse_file(&module_content)?;
 
-            // Look for `#[contract]` annotation on impl blocks
+            // Look for `#[contract]` annotation on impl blocks, or `#[proxy]` on structs
             let mut has_contract = false;
             for item in &module_ast.items {
-                if let Item::Impl(item_impl) = item {
-                    if has_contract_attribute(&item_impl.attrs) {
-                        // Found a contract
-                        if let Some(struct_name) = get_struct_name(item_impl) {
-                            has_contract = true;
-
-                            // Generate package name based on project name and module name
-                            let generated_pkg_name = format!("{}-{}", project_name, module_name);
-
-                            targets.push(ContractTarget {
-                                ident: struct_name,
-                                module: module_name.clone(),
-                                source_file: module_path.clone(),
-                                generated_package: generated_pkg_name,
-                            });
-                        }
-                    }
+                if let Some(struct_name) = get_contract_ident(item) {
+                    // Found a contract
+                    has_contract = true;
+
+                    // Generate package name based on project name and module name
+                    let generated_pkg_name = format!("{}-{}", project_name, module_name);
+
+                    targets.push(ContractTarget {
+                        ident: struct_name,
+                        module: module_name.clone(),
+                        source_file: module_path.clone(),
+                        generated_package: generated_pkg_name,
+                    });
                 }
             }
 
@@ -378,18 +521,14 @@ impl ContractProject {
         if targets.is_empty() {
             // No contracts found - try to find a contract in `lib.rs`
             for item in &lib_ast.items {
-                if let Item::Impl(item_impl) = item {
-                    if has_contract_attribute(&item_impl.attrs) {
-                        if let Some(struct_name) = get_struct_name(item_impl) {
-                            // When contract is in lib.rs, use the project name as the generated name
-                            targets.push(ContractTarget {
-                                ident: struct_name,
-                                module: String::new(),
-                                source_file: lib_rs_path.clone(),
-                                generated_package: base_package_name.clone(),
-                            });
-                        }
-                    }
+                if let Some(struct_name) = get_contract_ident(item) {
+                    // When contract is in lib.rs, use the project name as the generated name
+                    targets.push(ContractTarget {
+                        ident: struct_name,
+                        module: String::new(),
+                        source_file: lib_rs_path.clone(),
+                        generated_package: base_package_name.clone(),
+                    });
                 }
             }
         }
@@ -410,3 +549,17 @@ impl ContractProject {
         })
     }
 }
+
+/// Collects the paths of the files under `dir`, recursively
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,420 @@
+//! Post-build verification of the compiled binaries: the host emulator only implements RV64IMAC,
+//! so code relying on anything else (e.g. a float operation pulled in by a dependency) would only
+//! fail at runtime, deep in a call. The executable sections are disassembled instead, and any
+//! offending instruction is reported along with the function it belongs to.
+
+use std::fmt;
+
+use goblin::elf::{section_header::SHF_EXECINSTR, Elf};
+
+use crate::types::CompileError;
+
+/// Prefixes of the soft-float routines of `compiler-builtins` (e.g. `__muldf3`, `__floatundidf`),
+/// which are only called by code using `f32` or `f64`
+const SOFT_FLOAT_PREFIXES: [&str; 17] = [
+    "add", "sub", "mul", "div", "neg", "pow", "float", "fix", "extend", "trunc", "eq", "ne", "lt",
+    "le", "gt", "ge", "unord",
+];
+
+/// Instruction that the host can't execute, or call to a soft-float routine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Offender {
+    pub address: u64,
+    /// Demangled function containing the instruction, and the offset of the instruction in it
+    pub symbol: Option<(String, u64)>,
+    pub reason: String,
+}
+
+impl fmt::Display for Offender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.symbol {
+            Some((name, offset)) => write!(
+                f,
+                "{} in `{}`+{:#x} ({:#x})",
+                self.reason, name, offset, self.address
+            ),
+            None => write!(f, "{} at {:#x}", self.reason, self.address),
+        }
+    }
+}
+
+/// Fails with the list of offenders if the ELF binary contains code the host can't execute
+pub fn verify_binary(name: &str, elf: &[u8]) -> Result<(), CompileError> {
+    let offenders = find_offenders(elf)?;
+    if offenders.is_empty() {
+        return Ok(());
+    }
+
+    let list = offenders
+        .iter()
+        .map(|offender| format!("\n  - {}", offender))
+        .collect::<String>();
+    Err(CompileError::UnsupportedInstructions(format!(
+        "{} uses code that the R55 host can't execute (only RV64IMAC is supported):{}",
+        name, list
+    )))
+}
+
+/// Lists the unsupported instructions and the soft-float calls of an ELF binary
+pub fn find_offenders(bytes: &[u8]) -> Result<Vec<Offender>, CompileError> {
+    let elf = Elf::parse(bytes)
+        .map_err(|e| CompileError::UnsupportedInstructions(format!("invalid ELF: {}", e)))?;
+    let symbols = Symbols::new(&elf);
+
+    // Soft-float routines, by address
+    let soft_float = symbols
+        .functions
+        .iter()
+        .filter(|(_, _, name)| is_soft_float(name))
+        .map(|(start, _, name)| (*start, name.as_str()))
+        .collect::<Vec<_>>();
+
+    let mut offenders = Vec::new();
+    let mut push = |address: u64, reason: String| {
+        // Soft-float routines themselves are reported through their callers
+        let symbol = symbols.lookup(address);
+        if symbol.is_some_and(|(name, _)| is_soft_float(name)) {
+            return;
+        }
+        offenders.push(Offender {
+            address,
+            symbol: symbol.map(|(name, offset)| (name.to_string(), offset)),
+            reason,
+        });
+    };
+
+    let executable = elf
+        .section_headers
+        .iter()
+        .filter(|section| section.sh_flags & u64::from(SHF_EXECINSTR) != 0);
+    for section in executable {
+        let start = section.sh_offset as usize;
+        let Some(code) = bytes.get(start..start + section.sh_size as usize) else {
+            continue;
+        };
+
+        let mut auipc = None;
+        for (address, inst) in Instructions::new(code, section.sh_addr) {
+            if let Some(reason) = unsupported(inst, elf.is_64) {
+                push(address, reason);
+            }
+
+            // Direct calls (and tail calls) to soft-float routines
+            if let Some(target) = call_target(address, inst, auipc) {
+                if let Some((_, routine)) = soft_float.iter().find(|(start, _)| *start == target) {
+                    push(address, format!("float operation (call to `{}`)", routine));
+                }
+            }
+            auipc = match inst {
+                Instruction::Full(inst) if inst & 0x7f == 0x17 => Some((address, inst)),
+                _ => None,
+            };
+        }
+    }
+
+    // Soft-float routines with no caller found, e.g. called through a pointer
+    if offenders.is_empty() {
+        for (start, name) in soft_float {
+            offenders.push(Offender {
+                address: start,
+                symbol: Some((name.to_string(), 0)),
+                reason: "float operation".to_string(),
+            });
+        }
+    }
+
+    Ok(offenders)
+}
+
+/// Instruction of a linear sweep of a code section: compressed (16-bit) or full (32-bit)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
+    Compressed(u16),
+    Full(u32),
+}
+
+struct Instructions<'a> {
+    code: &'a [u8],
+    offset: usize,
+    base: u64,
+}
+
+impl<'a> Instructions<'a> {
+    fn new(code: &'a [u8], base: u64) -> Self {
+        Self {
+            code,
+            offset: 0,
+            base,
+        }
+    }
+}
+
+impl Iterator for Instructions<'_> {
+    type Item = (u64, Instruction);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let address = self.base + self.offset as u64;
+        let low = u16::from_le_bytes(
+            self.code
+                .get(self.offset..self.offset + 2)?
+                .try_into()
+                .ok()?,
+        );
+
+        if low & 0b11 != 0b11 {
+            self.offset += 2;
+            return Some((address, Instruction::Compressed(low)));
+        }
+
+        let bytes = self.code.get(self.offset..self.offset + 4)?;
+        self.offset += 4;
+        Some((
+            address,
+            Instruction::Full(u32::from_le_bytes(bytes.try_into().ok()?)),
+        ))
+    }
+}
+
+/// Reason why the host can't execute the instruction, if it can't
+pub fn unsupported(inst: Instruction, is_64: bool) -> Option<String> {
+    match inst {
+        Instruction::Compressed(inst) => {
+            let (quadrant, funct3) = (inst & 0b11, inst >> 13);
+            match (quadrant, funct3) {
+                // C.FLD, C.FSD and their stack-relative variants
+                (0 | 2, 0b001 | 0b101) => Some("double-precision float instruction (D)".into()),
+                // C.FLW, C.FSW and their stack-relative variants, which are C.LD/C.SD on rv64
+                (0 | 2, 0b011 | 0b111) if !is_64 => {
+                    Some("single-precision float instruction (F)".into())
+                }
+                _ => None,
+            }
+        }
+        Instruction::Full(inst) => {
+            let funct3 = (inst >> 12) & 0b111;
+            match inst & 0x7f {
+                // LOAD-FP, STORE-FP, the fused multiply-adds and OP-FP
+                0x07 | 0x27 | 0x43 | 0x47 | 0x4b | 0x4f | 0x53 => {
+                    Some("float instruction (F/D)".into())
+                }
+                // SYSTEM: ecall, ebreak, mret... are fine, CSR accesses depend on the register
+                0x73 if funct3 != 0 => {
+                    let csr = inst >> 20;
+                    (!supported_csr(csr)).then(|| format!("access to unsupported CSR {:#05x}", csr))
+                }
+                // AMO: only the word and double-word ops of the A extension
+                0x2f => {
+                    let funct5 = inst >> 27;
+                    let width_ok = funct3 == 0b010 || (funct3 == 0b011 && is_64);
+                    let op_ok = matches!(
+                        funct5,
+                        0b00010 // LR
+                            | 0b00011 // SC
+                            | 0b00001 // AMOSWAP
+                            | 0b00000 // AMOADD
+                            | 0b00100 // AMOXOR
+                            | 0b01100 // AMOAND
+                            | 0b01000 // AMOOR
+                            | 0b10000 // AMOMIN
+                            | 0b10100 // AMOMAX
+                            | 0b11000 // AMOMINU
+                            | 0b11100 // AMOMAXU
+                    );
+                    (!(width_ok && op_ok))
+                        .then(|| format!("unsupported atomic instruction {:#010x}", inst))
+                }
+                _ => None,
+            }
+        }
+    }
+}
+
+// Machine-level CSRs (used by the `riscv-rt` startup code) and the user-level counters. Float
+// CSRs (`fflags`, `frm`, `fcsr`) and supervisor CSRs aren't implemented by the host.
+fn supported_csr(csr: u32) -> bool {
+    matches!(csr, 0x300..=0x3ff | 0xb00..=0xbff | 0xc00..=0xc1f | 0xf11..=0xf15)
+}
+
+// Target of a `jal`, a `c.j`, or the `jalr` of an `auipc` + `jalr` pair (`call` and `tail`)
+fn call_target(address: u64, inst: Instruction, auipc: Option<(u64, u32)>) -> Option<u64> {
+    match inst {
+        Instruction::Full(inst) if inst & 0x7f == 0x6f => {
+            let imm = ((inst >> 31) & 1) << 20
+                | ((inst >> 12) & 0xff) << 12
+                | ((inst >> 20) & 1) << 11
+                | ((inst >> 21) & 0x3ff) << 1;
+            Some(address.wrapping_add(sign_extend(imm, 21)))
+        }
+        Instruction::Full(inst) if inst & 0x7f == 0x67 => {
+            let (auipc_address, auipc) = auipc?;
+            let rs1 = (inst >> 15) & 0x1f;
+            if (auipc >> 7) & 0x1f != rs1 {
+                return None;
+            }
+
+            let hi = sign_extend(auipc & 0xffff_f000, 32);
+            let lo = sign_extend(inst >> 20, 12);
+            Some(auipc_address.wrapping_add(hi).wrapping_add(lo))
+        }
+        Instruction::Compressed(inst) if inst & 0b11 == 0b01 && inst >> 13 == 0b101 => {
+            let inst = u32::from(inst);
+            let imm = ((inst >> 12) & 1) << 11
+                | ((inst >> 11) & 1) << 4
+                | ((inst >> 9) & 0b11) << 8
+                | ((inst >> 8) & 1) << 10
+                | ((inst >> 7) & 1) << 6
+                | ((inst >> 6) & 1) << 7
+                | ((inst >> 3) & 0b111) << 1
+                | ((inst >> 2) & 1) << 5;
+            Some(address.wrapping_add(sign_extend(imm, 12)))
+        }
+        _ => None,
+    }
+}
+
+fn sign_extend(value: u32, bits: u32) -> u64 {
+    let shift = 64 - bits;
+    (((value as u64) << shift) as i64 >> shift) as u64
+}
+
+// Soft-float routines have the mode of their float operands in their name (`sf`, `df` or `tf`),
+// unlike the integer ones (e.g. `__multi3`). Their mangled aliases are matched by their last segment.
+fn is_soft_float(name: &str) -> bool {
+    let name = name.rsplit("::").next().unwrap_or(name);
+    let Some(name) = name.strip_prefix("__") else {
+        return false;
+    };
+
+    SOFT_FLOAT_PREFIXES.iter().any(|prefix| {
+        name.strip_prefix(prefix)
+            .is_some_and(|rest| ["sf", "df", "tf"].iter().any(|mode| rest.contains(mode)))
+    })
+}
+
+/// Function symbols of an ELF, to name the offending instructions
+struct Symbols {
+    /// (start, end, demangled name), sorted by start
+    functions: Vec<(u64, u64, String)>,
+}
+
+impl Symbols {
+    fn new(elf: &Elf) -> Self {
+        let mut functions = elf
+            .syms
+            .iter()
+            .filter(|sym| sym.is_function())
+            .filter_map(|sym| {
+                let mangled = elf.strtab.get_at(sym.st_name)?;
+                let name = format!("{:#}", rustc_demangle::demangle(mangled));
+                Some((sym.st_value, sym.st_value + sym.st_size, name))
+            })
+            .collect::<Vec<_>>();
+        functions.sort();
+
+        Self { functions }
+    }
+
+    // Function containing `address`, and the offset of `address` in it. Symbols without a size
+    // (e.g. assembly) span until the next one.
+    fn lookup(&self, address: u64) -> Option<(&str, u64)> {
+        let index = self
+            .functions
+            .partition_point(|(start, _, _)| *start <= address)
+            .checked_sub(1)?;
+        let (start, end, name) = &self.functions[index];
+
+        (address < *end || start == end).then(|| (name.as_str(), address - start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{path::Path, process::Command};
+
+    use Instruction::{Compressed, Full};
+
+    #[test]
+    fn test_unsupported_instructions() {
+        // fmul.d f0, f1, f2 and c.fldsp
+        assert!(unsupported(Full(0x1220_f053), true).is_some());
+        assert!(unsupported(Compressed(0x2002), true).is_some());
+        // c.ld on rv64, c.flw on rv32
+        assert!(unsupported(Compressed(0x6000), true).is_none());
+        assert!(unsupported(Compressed(0x6000), false).is_some());
+
+        // csrr a0, fcsr is rejected, csrr a0, mhartid and ecall aren't
+        assert!(unsupported(Full(0x0030_2573), true).is_some());
+        assert!(unsupported(Full(0xf140_2573), true).is_none());
+        assert!(unsupported(Full(0x0000_0073), true).is_none());
+
+        // amoadd.w a0, a1, (a2) is part of the A extension, amocas.w isn't
+        assert!(unsupported(Full(0x00b6_252f), true).is_none());
+        assert!(unsupported(Full(0x28b6_252f), true).is_some());
+    }
+
+    #[test]
+    fn test_call_targets() {
+        // jal ra, 8 and j -4
+        assert_eq!(call_target(0x100, Full(0x0080_00ef), None), Some(0x108));
+        assert_eq!(call_target(0x100, Full(0xffdf_f06f), None), Some(0xfc));
+        // auipc ra, 0x1 + jalr ra, -4(ra)
+        let auipc = Some((0x1000, 0x0000_1097));
+        assert_eq!(call_target(0x1004, Full(0xffc0_80e7), auipc), Some(0x1ffc));
+        assert_eq!(call_target(0x1004, Full(0xffc0_80e7), None), None);
+        // c.j 4
+        assert_eq!(call_target(0x200, Compressed(0xa011), None), Some(0x204));
+    }
+
+    #[test]
+    fn test_soft_float_routines() {
+        for name in [
+            "__muldf3",
+            "__floatundidf",
+            "__fixdfsi",
+            "__truncdfsf2",
+            "__eqsf2",
+        ] {
+            assert!(is_soft_float(name), "{name}");
+        }
+        assert!(is_soft_float("compiler_builtins::float::mul::__muldf3"));
+
+        for name in [
+            "__multi3",
+            "__udivti3",
+            "__divmoddi4",
+            "__lshrti3",
+            "memcpy",
+        ] {
+            assert!(!is_soft_float(name), "{name}");
+        }
+    }
+
+    #[test]
+    #[ignore = "requires the nightly toolchain and the riscv64imac target"]
+    fn test_float_fixture_is_rejected() {
+        let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/float-contract");
+        let status = Command::new("cargo")
+            .args([
+                "+nightly-2025-01-07",
+                "build",
+                "-r",
+                "-Z",
+                "build-std=core,alloc",
+            ])
+            .args(["--bin", "runtime"])
+            .current_dir(&fixture)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let elf =
+            std::fs::read(fixture.join("target/riscv64imac-unknown-none-elf/release/runtime"))
+                .unwrap();
+        let err = verify_binary("float-contract", &elf)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("float operation"), "{err}");
+        assert!(err.contains("float_contract::scale_by"), "{err}");
+    }
+}
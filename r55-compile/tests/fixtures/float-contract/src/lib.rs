@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+use core::default::Default;
+
+use contract_derive::{contract, storage};
+use eth_riscv_runtime::types::*;
+
+use alloy_core::primitives::U256;
+
+extern crate alloc;
+
+// Contract sneaking in a float multiplication, which `r55-compile` must reject
+#[storage]
+pub struct FloatContract {
+    value: Slot<U256>,
+}
+
+#[contract]
+impl FloatContract {
+    pub fn value(&self) -> U256 {
+        self.value.read()
+    }
+
+    pub fn scale(&self, value: u64, factor: u64) -> u64 {
+        scale_by(value, factor)
+    }
+}
+
+#[inline(never)]
+fn scale_by(value: u64, factor: u64) -> u64 {
+    (core::hint::black_box(value as f64) * core::hint::black_box(factor as f64)) as u64
+}
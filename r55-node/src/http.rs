@@ -0,0 +1,104 @@
+//! Minimal HTTP/1.1 transport of the JSON-RPC requests. Connections are served one at a time and
+//! closed after their response, as the node executes the requests in order anyway.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use tracing::warn;
+
+use crate::{
+    node::{Node, MAX_INITCODE_SIZE},
+    rpc,
+};
+
+/// Largest request body accepted, which fits a transaction with the largest initcode (hex encoded)
+const MAX_BODY_SIZE: usize = 2 * MAX_INITCODE_SIZE + 64 * 1024;
+
+/// Longest request line or header accepted
+const MAX_LINE_SIZE: usize = 8 * 1024;
+
+/// Most headers accepted in a request
+const MAX_HEADERS: usize = 64;
+
+/// Serves the requests of the connections accepted by `listener` until it fails.
+pub fn serve(listener: TcpListener, node: &mut Node) -> io::Result<()> {
+    for stream in listener.incoming() {
+        if let Err(err) = handle_connection(stream?, node) {
+            warn!("Failed to serve request: {}", err);
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, node: &mut Node) -> io::Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    let request_line = read_line(&mut reader)?;
+    let method = request_line.split_whitespace().next().unwrap_or_default();
+
+    let mut content_length = 0;
+    for headers in 0.. {
+        let line = read_line(&mut reader)?;
+        if line.trim_end().is_empty() {
+            break;
+        }
+        if headers == MAX_HEADERS {
+            return Err(invalid_data("Too many headers"));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid_data("Invalid length"))?;
+            }
+        }
+    }
+
+    let (status, body) = match method {
+        "POST" if content_length > MAX_BODY_SIZE => ("413 Payload Too Large", String::new()),
+        "POST" => {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+            ("200 OK", rpc::handle_body(node, &body).to_string())
+        }
+        // Preflight of the requests sent by browser wallets
+        "OPTIONS" => ("204 No Content", String::new()),
+        _ => ("405 Method Not Allowed", String::new()),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: POST, OPTIONS\r\n\
+         Access-Control-Allow-Headers: content-type\r\n\
+         Connection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+// Line of the request head, which fails rather than buffering lines longer than `MAX_LINE_SIZE`
+fn read_line(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut line = String::new();
+    reader.take(MAX_LINE_SIZE as u64).read_line(&mut line)?;
+    if line.len() == MAX_LINE_SIZE && !line.ends_with('\n') {
+        return Err(invalid_data("Line too long"));
+    }
+
+    Ok(line)
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,18 @@
+//! JSON-RPC dev node backed by the R55 executor, so that wallets and scripts (e.g. `cast`) can
+//! deploy and call R55 contracts. State is kept in memory, and every transaction is mined in its
+//! own block.
+
+use alloy_primitives::{address, Address};
+
+pub mod http;
+pub mod node;
+pub mod rpc;
+pub mod tx;
+
+pub use node::Node;
+
+/// Account funded at genesis, whose private key is the well-known first dev key of anvil and
+/// hardhat: `DEV_PRIVATE_KEY`.
+pub const DEV_ACCOUNT: Address = address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+pub const DEV_PRIVATE_KEY: &str =
+    "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
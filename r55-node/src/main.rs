@@ -0,0 +1,123 @@
+use std::net::TcpListener;
+
+use alloy_primitives::{Address, Bytes};
+use r55::{exec::r55_initcode, get_bytecode};
+use r55_node::{http, Node, DEV_ACCOUNT, DEV_PRIVATE_KEY};
+use tracing::info;
+
+const DEFAULT_PORT: u16 = 8545;
+
+fn main() -> eyre::Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "initcode").is_some() {
+        return print_initcode(InitcodeArgs::parse(args)?);
+    }
+    let args = Args::parse(args)?;
+
+    let tracing_sub = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .with_target(false)
+        .finish();
+    tracing::subscriber::set_global_default(tracing_sub)?;
+
+    let mut accounts = vec![DEV_ACCOUNT];
+    accounts.extend(args.fund);
+    let mut node = Node::new(&accounts);
+
+    let listener = TcpListener::bind((args.host.as_str(), args.port))?;
+    info!("Chain id: {}", node.chain_id());
+    info!("Funded accounts: {:?}", accounts);
+    info!("Dev private key ({}): {}", DEV_ACCOUNT, DEV_PRIVATE_KEY);
+    info!("Listening on http://{}", listener.local_addr()?);
+
+    http::serve(listener, &mut node)?;
+    Ok(())
+}
+
+struct Args {
+    /// `--host <ip>`: interface to listen on, `127.0.0.1` by default
+    host: String,
+    /// `--port <port>`: `DEFAULT_PORT` by default
+    port: u16,
+    /// `--fund <address>` (repeatable): accounts funded at genesis, on top of `DEV_ACCOUNT`
+    fund: Vec<Address>,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> eyre::Result<Self> {
+        let mut parsed = Self {
+            host: "127.0.0.1".to_string(),
+            port: DEFAULT_PORT,
+            fund: vec![],
+        };
+
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| eyre::eyre!("Missing value for `{}`", arg))
+            };
+            match arg.as_str() {
+                "--host" => parsed.host = value()?,
+                "--port" => {
+                    let value = value()?;
+                    parsed.port = value
+                        .parse()
+                        .map_err(|_| eyre::eyre!("Invalid port: {}", value))?;
+                }
+                "--fund" => {
+                    let value = value()?;
+                    let account = value
+                        .parse()
+                        .map_err(|_| eyre::eyre!("Invalid address: {}", value))?;
+                    parsed.fund.push(account);
+                }
+                arg => return Err(eyre::eyre!("Unknown argument: {}", arg)),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// `initcode <contract> [--args <hex>]`: prints the initcode of a compiled contract, with its
+/// ABI-encoded constructor args, to be deployed with e.g. `cast send --create`.
+struct InitcodeArgs {
+    contract: String,
+    encoded_args: Option<Bytes>,
+}
+
+impl InitcodeArgs {
+    fn parse(mut args: impl Iterator<Item = String>) -> eyre::Result<Self> {
+        let contract = args
+            .next()
+            .ok_or_else(|| eyre::eyre!("Missing contract name"))?;
+        let mut encoded_args = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--args" => {
+                    let value = args
+                        .next()
+                        .ok_or_else(|| eyre::eyre!("Missing value for `--args`"))?;
+                    let bytes = value
+                        .parse()
+                        .map_err(|_| eyre::eyre!("Invalid hex args: {}", value))?;
+                    encoded_args = Some(bytes);
+                }
+                arg => return Err(eyre::eyre!("Unknown argument: {}", arg)),
+            }
+        }
+
+        Ok(Self {
+            contract,
+            encoded_args,
+        })
+    }
+}
+
+fn print_initcode(args: InitcodeArgs) -> eyre::Result<()> {
+    let bytecode = get_bytecode(&args.contract.replace('-', "_"));
+    let initcode = r55_initcode(bytecode, args.encoded_args.map(Into::into))
+        .map_err(|err| eyre::eyre!("{}", err))?;
+    println!("{}", initcode);
+    Ok(())
+}
@@ -0,0 +1,386 @@
+//! Chain served by the node: an `InMemoryDB` executed on by the R55 executor, where every
+//! transaction is mined in its own block as soon as it's received.
+
+use std::collections::HashMap;
+
+use alloy_primitives::{keccak256, Address, Bytes, TxKind, B256, U256, U64};
+use r55::{
+    error::{Error, TxResult},
+    exec::{
+        estimate_gas_with_env, BlockEnvBuilder, ExecEnv, R55Executor, TxSpec, DEFAULT_GAS_LIMIT,
+        MAX_CALLDATA_SIZE,
+    },
+};
+use revm::{
+    db::CacheDB,
+    primitives::{AccountInfo, CfgEnv, ExecutionResult, Log},
+    DatabaseRef, InMemoryDB,
+};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::tx::{self, TxError};
+
+/// Balance of the accounts funded at genesis: 10,000 ETH.
+pub const DEV_BALANCE: u128 = 10_000 * 10u128.pow(18);
+
+/// Largest initcode accepted, as R55 contracts aren't bound by the EVM code size limits. Its
+/// constructor args are bound by `MAX_CALLDATA_SIZE` too, like the input of calls.
+pub const MAX_INITCODE_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum NodeError {
+    #[error(transparent)]
+    Tx(#[from] TxError),
+    /// The call reverted, with its revert payload
+    #[error("execution reverted")]
+    Reverted(Bytes),
+    #[error("{0}")]
+    Execution(String),
+    #[error("Invalid chain id: expected {expected}, got {got}")]
+    ChainId { expected: u64, got: u64 },
+    #[error("Invalid nonce: expected {expected}, got {got}")]
+    Nonce { expected: u64, got: u64 },
+    #[error("Access lists are only supported by call transactions")]
+    CreateAccessList,
+    #[error("Input too large: {size} bytes, the limit is {max}")]
+    InputTooLarge { size: usize, max: usize },
+}
+
+/// Call executed without being mined, for `eth_call` and `eth_estimateGas`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct CallRequest {
+    pub from: Option<Address>,
+    /// `None` for deployments
+    pub to: Option<Address>,
+    pub gas: Option<U64>,
+    pub value: Option<U256>,
+    pub input: Option<Bytes>,
+    /// Legacy name of `input`, still sent by some clients
+    pub data: Option<Bytes>,
+}
+
+impl CallRequest {
+    fn calldata(&self) -> Bytes {
+        self.input.clone().or(self.data.clone()).unwrap_or_default()
+    }
+}
+
+/// Outcome of a mined transaction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Receipt {
+    pub tx_hash: B256,
+    pub tx_type: u8,
+    pub from: Address,
+    pub to: Option<Address>,
+    pub contract_address: Option<Address>,
+    pub block_number: u64,
+    pub block_hash: B256,
+    pub gas_used: u64,
+    pub effective_gas_price: U256,
+    pub status: bool,
+    pub logs: Vec<Log>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub number: u64,
+    pub hash: B256,
+    pub timestamp: u64,
+    pub gas_limit: u64,
+    pub gas_used: u64,
+    pub basefee: U256,
+    pub transactions: Vec<B256>,
+}
+
+pub struct Node {
+    db: InMemoryDB,
+    /// Fees and pending block of the transactions, which is advanced after each of them
+    env: ExecEnv,
+    chain_id: u64,
+    /// Mined blocks, by number, starting with the (empty) genesis block
+    blocks: Vec<Block>,
+    receipts: HashMap<B256, Receipt>,
+}
+
+impl Node {
+    /// Node at genesis, with `accounts` funded with `DEV_BALANCE` each.
+    pub fn new(accounts: &[Address]) -> Self {
+        let mut db = InMemoryDB::default();
+        for account in accounts {
+            db.insert_account_info(*account, AccountInfo::from_balance(U256::from(DEV_BALANCE)));
+        }
+
+        let env = ExecEnv::default();
+        let genesis = env.block();
+        env.set_block(genesis.with_number(genesis.number + U256::from(1)));
+
+        Self {
+            db,
+            env,
+            // Contracts read the chain id of revm's config, so it's the one the node reports
+            chain_id: CfgEnv::default().chain_id,
+            blocks: vec![new_block(&genesis, 0, vec![])],
+            receipts: HashMap::new(),
+        }
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    /// Number of the last mined block
+    pub fn block_number(&self) -> u64 {
+        self.blocks.len() as u64 - 1
+    }
+
+    pub fn block(&self, number: u64) -> Option<&Block> {
+        self.blocks.get(number as usize)
+    }
+
+    pub fn latest_block(&self) -> &Block {
+        self.blocks.last().expect("Genesis is always mined")
+    }
+
+    /// Base fee of the pending block, which the next transaction pays
+    pub fn basefee(&self) -> U256 {
+        self.env.block().basefee
+    }
+
+    pub fn receipt(&self, tx_hash: &B256) -> Option<&Receipt> {
+        self.receipts.get(tx_hash)
+    }
+
+    pub fn balance(&self, addr: Address) -> U256 {
+        self.account(addr)
+            .map(|info| info.balance)
+            .unwrap_or_default()
+    }
+
+    pub fn nonce(&self, addr: Address) -> u64 {
+        self.account(addr)
+            .map(|info| info.nonce)
+            .unwrap_or_default()
+    }
+
+    pub fn code(&self, addr: Address) -> Bytes {
+        let Some(info) = self.account(addr) else {
+            return Bytes::new();
+        };
+        match info.code {
+            Some(code) => code.original_bytes(),
+            None => self
+                .db
+                .code_by_hash_ref(info.code_hash)
+                .map(|code| code.original_bytes())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn storage(&self, addr: Address, slot: U256) -> U256 {
+        self.db.storage_ref(addr, slot).unwrap_or_default()
+    }
+
+    /// Output of `request` executed on top of the latest state, which is left untouched.
+    pub fn call(&self, request: &CallRequest) -> Result<Bytes, NodeError> {
+        self.execute(request, request_gas(request))
+            .map(|result| result.output.into())
+    }
+
+    fn execute(&self, request: &CallRequest, gas_limit: u64) -> Result<TxResult, NodeError> {
+        check_input_size(request.to.is_none(), &request.calldata())?;
+        let env = self.free_env();
+        let executor = R55Executor::new(&env);
+        let mut overlay = CacheDB::new(&self.db);
+        let from = request.from.unwrap_or_default();
+        let value = request.value.unwrap_or_default();
+
+        let result = match request.to {
+            Some(to) => executor.call(&mut overlay, to, request.calldata(), value, from, gas_limit),
+            None => executor.deploy_with_gas_limit(
+                &mut overlay,
+                request.calldata(),
+                value,
+                from,
+                gas_limit,
+            ),
+        };
+        result.map_err(|err| match err {
+            Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) => {
+                NodeError::Reverted(output)
+            }
+            err => NodeError::Execution(err.to_string()),
+        })
+    }
+
+    /// Minimum gas limit that `request` succeeds with, see `estimate_gas_with_env`.
+    pub fn estimate_gas(&self, request: &CallRequest) -> Result<u64, NodeError> {
+        let Some(to) = request.to else {
+            return self.estimate_create_gas(request);
+        };
+        check_input_size(false, &request.calldata())?;
+
+        let tx = TxSpec::call(
+            request.from.unwrap_or_default(),
+            to,
+            request.calldata().into(),
+        )
+        .with_value(request.value.unwrap_or_default())
+        .with_gas_limit(request_gas(request));
+        estimate_gas_with_env(&self.db, &tx, &self.free_env()).map_err(|err| match err {
+            Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) => {
+                NodeError::Reverted(output)
+            }
+            err => NodeError::Execution(err.to_string()),
+        })
+    }
+
+    // Deployments are binary searched like calls: the gas they use with the full gas limit is a
+    // lower bound, as it doesn't include their refunds nor the gas kept by nested calls
+    fn estimate_create_gas(&self, request: &CallRequest) -> Result<u64, NodeError> {
+        let mut hi = request_gas(request);
+        let mut lo = self.execute(request, hi)?.gas_used - 1;
+        while lo + 1 < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.execute(request, mid).is_ok() {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+
+        Ok(hi)
+    }
+
+    /// Executes a signed transaction, and mines it in its own block. Transactions that revert are
+    /// mined too, with a failed receipt, while invalid ones are rejected.
+    pub fn send_raw_transaction(&mut self, raw: &[u8]) -> Result<B256, NodeError> {
+        let tx = tx::decode(raw)?;
+        check_input_size(tx.to.is_create(), &tx.input)?;
+        if let Some(chain_id) = tx.chain_id.filter(|chain_id| *chain_id != self.chain_id) {
+            return Err(NodeError::ChainId {
+                expected: self.chain_id,
+                got: chain_id,
+            });
+        }
+        let nonce = self.nonce(tx.from);
+        if tx.nonce != nonce {
+            return Err(NodeError::Nonce {
+                expected: nonce,
+                got: tx.nonce,
+            });
+        }
+
+        let mut env = self
+            .env
+            .clone()
+            .with_max_fee_per_gas(U256::from(tx.max_fee_per_gas));
+        if let Some(priority_fee) = tx.max_priority_fee_per_gas {
+            env = env.with_max_priority_fee_per_gas(U256::from(priority_fee));
+        }
+        let executor = R55Executor::new(&env);
+        let result = match tx.to {
+            TxKind::Create if !tx.access_list.is_empty() => {
+                return Err(NodeError::CreateAccessList)
+            }
+            TxKind::Create => executor.deploy_with_gas_limit(
+                &mut self.db,
+                tx.input,
+                tx.value,
+                tx.from,
+                tx.gas_limit,
+            ),
+            TxKind::Call(to) => executor.call_with_access_list(
+                &mut self.db,
+                to,
+                tx.input,
+                tx.value,
+                tx.from,
+                tx.gas_limit,
+                tx.access_list,
+            ),
+        };
+        let (status, gas_used, logs, contract_address) = match result {
+            Ok(result) => (true, result.gas_used, result.logs, result.created),
+            Err(Error::UnexpectedExecResult(
+                ExecutionResult::Revert { gas_used, .. } | ExecutionResult::Halt { gas_used, .. },
+            )) => (false, gas_used, vec![], None),
+            Err(err) => return Err(NodeError::Execution(err.to_string())),
+        };
+
+        let block = new_block(&env.block(), gas_used, vec![tx.hash]);
+        info!(
+            "Mined tx {} in block {} (status: {}, gas used: {})",
+            tx.hash, block.number, status, gas_used
+        );
+        self.receipts.insert(
+            tx.hash,
+            Receipt {
+                tx_hash: tx.hash,
+                tx_type: tx.tx_type,
+                from: tx.from,
+                to: tx.to.to().copied(),
+                contract_address,
+                block_number: block.number,
+                block_hash: block.hash,
+                gas_used,
+                effective_gas_price: env.effective_gas_price(),
+                status,
+                logs,
+            },
+        );
+        self.blocks.push(block);
+        self.env.mine();
+
+        Ok(tx.hash)
+    }
+
+    fn account(&self, addr: Address) -> Option<AccountInfo> {
+        self.db.basic_ref(addr).ok().flatten()
+    }
+
+    // Env of the calls that aren't mined, which don't pay for their gas
+    fn free_env(&self) -> ExecEnv {
+        self.env.clone().with_max_fee_per_gas(self.basefee())
+    }
+}
+
+// Inputs that don't fit the emulator are rejected before they're executed
+fn check_input_size(is_create: bool, input: &[u8]) -> Result<(), NodeError> {
+    let max = if is_create {
+        MAX_INITCODE_SIZE
+    } else {
+        MAX_CALLDATA_SIZE
+    };
+    if input.len() > max {
+        return Err(NodeError::InputTooLarge {
+            size: input.len(),
+            max,
+        });
+    }
+
+    Ok(())
+}
+
+fn request_gas(request: &CallRequest) -> u64 {
+    request.gas.map_or(DEFAULT_GAS_LIMIT, |gas| gas.to())
+}
+
+fn new_block(block: &BlockEnvBuilder, gas_used: u64, transactions: Vec<B256>) -> Block {
+    let number = block.number.saturating_to();
+    Block {
+        number,
+        hash: block_hash(number),
+        timestamp: block.timestamp.saturating_to(),
+        gas_limit: block.gas_limit.saturating_to(),
+        gas_used,
+        basefee: block.basefee,
+        transactions,
+    }
+}
+
+/// Hash of block `number`. It's the one returned by the db for `BLOCKHASH`, so that contracts see
+/// the same hashes as the clients of the node.
+pub fn block_hash(number: u64) -> B256 {
+    keccak256(number.to_string().as_bytes())
+}
@@ -0,0 +1,252 @@
+//! JSON-RPC 2.0 methods served by the node. Block parameters are accepted but ignored: state is
+//! always read at the latest block.
+
+use alloy_primitives::{logs_bloom, Address, Bloom, Bytes, B256, B64, U256, U64};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::panic::{self, AssertUnwindSafe};
+use tracing::{debug, error};
+
+use crate::node::{Block, CallRequest, Node, NodeError, Receipt};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Request whose handling panicked
+const INTERNAL_ERROR: i64 = -32603;
+/// Server error, for rejected transactions and failed calls
+const SERVER_ERROR: i64 = -32000;
+/// Reverted `eth_call` or `eth_estimateGas`, with the revert payload as data (like geth)
+const EXECUTION_REVERTED: i64 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    pub data: Option<Bytes>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<NodeError> for RpcError {
+    fn from(err: NodeError) -> Self {
+        match err {
+            NodeError::Reverted(output) => Self {
+                code: EXECUTION_REVERTED,
+                message: "execution reverted".to_string(),
+                data: Some(output),
+            },
+            err => Self::new(SERVER_ERROR, err.to_string()),
+        }
+    }
+}
+
+/// Response to the JSON-RPC `body`: a single request, or a batch of them.
+pub fn handle_body(node: &mut Node, body: &[u8]) -> Value {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Array(requests)) if !requests.is_empty() => requests
+            .into_iter()
+            .map(|request| handle_request(node, request))
+            .collect(),
+        Ok(request) => handle_request(node, request),
+        Err(err) => response(
+            Value::Null,
+            Err(RpcError::new(PARSE_ERROR, err.to_string())),
+        ),
+    }
+}
+
+fn handle_request(node: &mut Node, request: Value) -> Value {
+    let id = request.get("id").cloned().unwrap_or(Value::Null);
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return response(id, Err(RpcError::new(INVALID_REQUEST, "Missing method")));
+    };
+    let params = match request.get("params") {
+        Some(Value::Array(params)) => params.clone(),
+        None | Some(Value::Null) => vec![],
+        Some(_) => {
+            return response(
+                id,
+                Err(RpcError::new(INVALID_PARAMS, "Expected positional params")),
+            )
+        }
+    };
+
+    debug!("{} {:?}", method, params);
+    // A request that hits a bug of the node (or the executor) fails on its own, rather than taking
+    // the node and its state down with it
+    let result = panic::catch_unwind(AssertUnwindSafe(|| dispatch(node, method, &params)))
+        .unwrap_or_else(|_| {
+            error!("Handling of {} panicked", method);
+            Err(RpcError::new(INTERNAL_ERROR, "Internal error"))
+        });
+    response(id, result)
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => {
+            let mut error = json!({ "code": err.code, "message": err.message });
+            if let Some(data) = err.data {
+                error["data"] = json!(data);
+            }
+            json!({ "jsonrpc": "2.0", "id": id, "error": error })
+        }
+    }
+}
+
+/// Result of `method` called with `params`.
+pub fn dispatch(node: &mut Node, method: &str, params: &[Value]) -> Result<Value, RpcError> {
+    Ok(match method {
+        "web3_clientVersion" => json!(concat!("r55-node/", env!("CARGO_PKG_VERSION"))),
+        "net_version" => json!(node.chain_id().to_string()),
+        "net_listening" => json!(true),
+        "eth_chainId" => json!(U64::from(node.chain_id())),
+        "eth_accounts" => json!([]),
+        "eth_syncing" => json!(false),
+        "eth_blockNumber" => json!(U64::from(node.block_number())),
+        "eth_gasPrice" => json!(node.basefee() + U256::from(r55::exec::DEFAULT_GAS_PRICE)),
+        "eth_maxPriorityFeePerGas" => json!(U256::from(r55::exec::DEFAULT_GAS_PRICE)),
+        "eth_feeHistory" => {
+            let percentiles =
+                param::<Vec<f64>>(params, 2).map_or(0, |percentiles| percentiles.len());
+            fee_history(node, param(params, 0)?, percentiles)
+        }
+        "eth_getBalance" => json!(node.balance(param(params, 0)?)),
+        "eth_getTransactionCount" => json!(U64::from(node.nonce(param(params, 0)?))),
+        "eth_getCode" => json!(node.code(param(params, 0)?)),
+        "eth_getStorageAt" => {
+            let slot: U256 = param(params, 1)?;
+            json!(B256::from(node.storage(param(params, 0)?, slot)))
+        }
+        "eth_call" => json!(node.call(&param::<CallRequest>(params, 0)?)?),
+        "eth_estimateGas" => json!(U64::from(node.estimate_gas(&param(params, 0)?)?)),
+        "eth_sendRawTransaction" => {
+            let raw: Bytes = param(params, 0)?;
+            json!(node.send_raw_transaction(&raw)?)
+        }
+        "eth_getTransactionReceipt" => {
+            let tx_hash: B256 = param(params, 0)?;
+            node.receipt(&tx_hash).map_or(Value::Null, receipt_json)
+        }
+        "eth_getBlockByNumber" => {
+            let number = match param::<Value>(params, 0)? {
+                Value::String(tag) if tag.starts_with("0x") => {
+                    serde_json::from_value::<U64>(Value::String(tag))
+                        .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?
+                        .to()
+                }
+                Value::String(tag) if tag == "earliest" => 0,
+                _ => node.block_number(),
+            };
+            node.block(number).map_or(Value::Null, block_json)
+        }
+        method => {
+            return Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not supported: {}", method),
+            ))
+        }
+    })
+}
+
+fn param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, RpcError> {
+    let value = params
+        .get(index)
+        .cloned()
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Missing param {}", index)))?;
+    serde_json::from_value(value)
+        .map_err(|err| RpcError::new(INVALID_PARAMS, format!("Invalid param {}: {}", index, err)))
+}
+
+// Every block has the same base fee, and pays no rewards (at any of the `percentiles`)
+fn fee_history(node: &Node, block_count: U64, percentiles: usize) -> Value {
+    let latest = node.block_number();
+    let block_count = block_count.to::<u64>().clamp(1, latest + 1);
+    let basefee = node.basefee();
+    json!({
+        "oldestBlock": U64::from(latest + 1 - block_count),
+        "baseFeePerGas": vec![basefee; block_count as usize + 1],
+        "gasUsedRatio": vec![0.0; block_count as usize],
+        "reward": vec![vec![U256::ZERO; percentiles]; block_count as usize],
+    })
+}
+
+fn receipt_json(receipt: &Receipt) -> Value {
+    let logs = receipt
+        .logs
+        .iter()
+        .enumerate()
+        .map(|(index, log)| {
+            json!({
+                "address": log.address,
+                "topics": log.topics(),
+                "data": log.data.data,
+                "blockNumber": U64::from(receipt.block_number),
+                "blockHash": receipt.block_hash,
+                "transactionHash": receipt.tx_hash,
+                "transactionIndex": U64::ZERO,
+                "logIndex": U64::from(index),
+                "removed": false,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({
+        "transactionHash": receipt.tx_hash,
+        "transactionIndex": U64::ZERO,
+        "type": U64::from(receipt.tx_type),
+        "blockNumber": U64::from(receipt.block_number),
+        "blockHash": receipt.block_hash,
+        "from": receipt.from,
+        "to": receipt.to,
+        "contractAddress": receipt.contract_address,
+        "gasUsed": U64::from(receipt.gas_used),
+        "cumulativeGasUsed": U64::from(receipt.gas_used),
+        "effectiveGasPrice": receipt.effective_gas_price,
+        "status": U64::from(receipt.status),
+        "logs": logs,
+        "logsBloom": logs_bloom(&receipt.logs),
+    })
+}
+
+// Blocks only list the hashes of their transactions, and have no state or receipts root
+fn block_json(block: &Block) -> Value {
+    let parent_hash = match block.number {
+        0 => B256::ZERO,
+        number => crate::node::block_hash(number - 1),
+    };
+
+    json!({
+        "number": U64::from(block.number),
+        "hash": block.hash,
+        "parentHash": parent_hash,
+        "timestamp": U64::from(block.timestamp),
+        "gasLimit": U64::from(block.gas_limit),
+        "gasUsed": U64::from(block.gas_used),
+        "baseFeePerGas": block.basefee,
+        "miner": Address::ZERO,
+        "difficulty": U256::ZERO,
+        "totalDifficulty": U256::ZERO,
+        "extraData": Bytes::new(),
+        "nonce": B64::ZERO,
+        "mixHash": B256::ZERO,
+        "sha3Uncles": B256::ZERO,
+        "stateRoot": B256::ZERO,
+        "transactionsRoot": B256::ZERO,
+        "receiptsRoot": B256::ZERO,
+        "logsBloom": Bloom::ZERO,
+        "size": U64::ZERO,
+        "uncles": [],
+        "transactions": block.transactions,
+    })
+}
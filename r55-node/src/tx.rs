@@ -0,0 +1,163 @@
+//! Decoding of the signed raw transactions sent with `eth_sendRawTransaction`: legacy (with or
+//! without EIP-155 replay protection), EIP-2930 and EIP-1559 transactions. The sender is recovered
+//! from the signature over the RLP of the unsigned fields.
+
+use alloy_primitives::{keccak256, Address, Bytes, PrimitiveSignature, TxKind, B256, U256};
+use alloy_rlp::{Decodable, Encodable, Header};
+use revm::primitives::{AccessList, AccessListItem};
+
+pub const LEGACY_TX_TYPE: u8 = 0;
+pub const EIP2930_TX_TYPE: u8 = 1;
+pub const EIP1559_TX_TYPE: u8 = 2;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TxError {
+    #[error("Invalid RLP: {0}")]
+    Rlp(#[from] alloy_rlp::Error),
+    #[error("Unsupported transaction type: {0}")]
+    UnsupportedType(u8),
+    #[error("Invalid signature")]
+    InvalidSignature,
+}
+
+/// Signed transaction, with its recovered sender.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedTx {
+    pub hash: B256,
+    pub from: Address,
+    pub tx_type: u8,
+    /// `None` for legacy transactions without replay protection
+    pub chain_id: Option<u64>,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    /// Gas price of legacy and EIP-2930 transactions
+    pub max_fee_per_gas: u128,
+    /// `None` for legacy and EIP-2930 transactions, which pay their full gas price
+    pub max_priority_fee_per_gas: Option<u128>,
+    pub to: TxKind,
+    pub value: U256,
+    pub input: Bytes,
+    pub access_list: AccessList,
+}
+
+/// Decodes a signed transaction from its network encoding: the RLP list of a legacy transaction,
+/// or the type byte followed by the RLP list of a typed one.
+pub fn decode(raw: &[u8]) -> Result<SignedTx, TxError> {
+    let (tx_type, mut buf) = match raw.first() {
+        Some(&byte) if byte >= 0xc0 => (LEGACY_TX_TYPE, raw),
+        Some(&byte @ (EIP2930_TX_TYPE | EIP1559_TX_TYPE)) => (byte, &raw[1..]),
+        Some(&byte) => return Err(TxError::UnsupportedType(byte)),
+        None => return Err(alloy_rlp::Error::InputTooShort.into()),
+    };
+
+    let header = Header::decode(&mut buf)?;
+    if !header.list {
+        return Err(alloy_rlp::Error::UnexpectedString.into());
+    }
+    if buf.len() != header.payload_length {
+        return Err(alloy_rlp::Error::UnexpectedLength.into());
+    }
+    let fields = buf;
+
+    let chain_id = match tx_type {
+        LEGACY_TX_TYPE => None,
+        _ => Some(u64::decode(&mut buf)?),
+    };
+    let nonce = u64::decode(&mut buf)?;
+    let (max_priority_fee_per_gas, max_fee_per_gas) = match tx_type {
+        EIP1559_TX_TYPE => (Some(u128::decode(&mut buf)?), u128::decode(&mut buf)?),
+        _ => (None, u128::decode(&mut buf)?),
+    };
+    let gas_limit = u64::decode(&mut buf)?;
+    let to = TxKind::decode(&mut buf)?;
+    let value = U256::decode(&mut buf)?;
+    let input = Bytes::decode(&mut buf)?;
+    let access_list = match tx_type {
+        LEGACY_TX_TYPE => AccessList::default(),
+        _ => decode_access_list(&mut buf)?,
+    };
+    let unsigned = &fields[..fields.len() - buf.len()];
+
+    // Legacy transactions encode the parity of the signature (and their chain id) in `v`
+    let (chain_id, y_parity) = match tx_type {
+        LEGACY_TX_TYPE => match u64::decode(&mut buf)? {
+            v @ (27 | 28) => (None, v == 28),
+            v @ 35.. => (Some((v - 35) / 2), (v - 35) % 2 == 1),
+            _ => return Err(TxError::InvalidSignature),
+        },
+        _ => (chain_id, bool::decode(&mut buf)?),
+    };
+    let signature =
+        PrimitiveSignature::new(U256::decode(&mut buf)?, U256::decode(&mut buf)?, y_parity);
+    if !buf.is_empty() {
+        return Err(alloy_rlp::Error::UnexpectedLength.into());
+    }
+
+    // High `s` values are malleable, and rejected since Homestead (EIP-2)
+    if signature.normalize_s().is_some() {
+        return Err(TxError::InvalidSignature);
+    }
+    let from = signature
+        .recover_address_from_prehash(&signing_hash(tx_type, unsigned, chain_id))
+        .map_err(|_| TxError::InvalidSignature)?;
+
+    Ok(SignedTx {
+        hash: keccak256(raw),
+        from,
+        tx_type,
+        chain_id,
+        nonce,
+        gas_limit,
+        max_fee_per_gas,
+        max_priority_fee_per_gas,
+        to,
+        value,
+        input,
+        access_list,
+    })
+}
+
+/// Hash signed by the sender: the one of the unsigned fields, followed by `[chain_id, 0, 0]` for
+/// EIP-155 legacy transactions, and preceded by the type byte for typed ones.
+pub fn signing_hash(tx_type: u8, unsigned: &[u8], chain_id: Option<u64>) -> B256 {
+    let mut eip155 = Vec::new();
+    if let (LEGACY_TX_TYPE, Some(chain_id)) = (tx_type, chain_id) {
+        chain_id.encode(&mut eip155);
+        0u8.encode(&mut eip155);
+        0u8.encode(&mut eip155);
+    }
+
+    let mut payload = Vec::new();
+    if tx_type != LEGACY_TX_TYPE {
+        payload.push(tx_type);
+    }
+    Header {
+        list: true,
+        payload_length: unsigned.len() + eip155.len(),
+    }
+    .encode(&mut payload);
+    payload.extend_from_slice(unsigned);
+    payload.extend_from_slice(&eip155);
+
+    keccak256(payload)
+}
+
+// `[[address, [storage_key, ...]], ...]`
+fn decode_access_list(buf: &mut &[u8]) -> Result<AccessList, alloy_rlp::Error> {
+    let mut items = Header::decode_bytes(buf, true)?;
+    let mut access_list = Vec::new();
+    while !items.is_empty() {
+        let mut item = Header::decode_bytes(&mut items, true)?;
+        let address = Address::decode(&mut item)?;
+        let storage_keys = Vec::<B256>::decode(&mut item)?;
+        if !item.is_empty() {
+            return Err(alloy_rlp::Error::UnexpectedLength);
+        }
+        access_list.push(AccessListItem {
+            address,
+            storage_keys,
+        });
+    }
+
+    Ok(AccessList(access_list))
+}
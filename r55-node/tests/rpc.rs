@@ -0,0 +1,329 @@
+use alloy_primitives::{address, Address, Bytes, TxKind, U256};
+use alloy_rlp::{Encodable, Header};
+use alloy_sol_types::SolValue;
+use k256::ecdsa::SigningKey;
+use r55::{
+    exec::{r55_initcode, MAX_CALLDATA_SIZE},
+    get_bytecode,
+    test_utils::{get_calldata, get_selector_from_sig},
+};
+use r55_node::{
+    http,
+    node::MAX_INITCODE_SIZE,
+    rpc,
+    tx::{self, EIP1559_TX_TYPE, LEGACY_TX_TYPE},
+    Node, DEV_ACCOUNT, DEV_PRIVATE_KEY,
+};
+use serde_json::{json, Value};
+use std::{
+    io::{Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+const BOB: Address = address!("000000000000000000000000000000000000b0b0");
+
+// Unsigned fields of a transaction, encoded by `sign`
+struct UnsignedTx {
+    tx_type: u8,
+    chain_id: u64,
+    nonce: u64,
+    to: TxKind,
+    value: U256,
+    input: Bytes,
+}
+
+impl UnsignedTx {
+    fn new(nonce: u64, to: TxKind, value: U256, input: Bytes) -> Self {
+        Self {
+            tx_type: EIP1559_TX_TYPE,
+            chain_id: 1,
+            nonce,
+            to,
+            value,
+            input,
+        }
+    }
+}
+
+// Raw transaction signed with the dev key, paying 1 gwei per gas
+fn sign(tx: &UnsignedTx) -> Bytes {
+    let gas_price = 1_000_000_000u128;
+    let gas_limit = 10_000_000u64;
+
+    let mut fields = Vec::new();
+    if tx.tx_type == EIP1559_TX_TYPE {
+        tx.chain_id.encode(&mut fields);
+    }
+    tx.nonce.encode(&mut fields);
+    if tx.tx_type == EIP1559_TX_TYPE {
+        gas_price.encode(&mut fields);
+    }
+    gas_price.encode(&mut fields);
+    gas_limit.encode(&mut fields);
+    tx.to.encode(&mut fields);
+    tx.value.encode(&mut fields);
+    tx.input.encode(&mut fields);
+    if tx.tx_type == EIP1559_TX_TYPE {
+        Vec::<Vec<u8>>::new().encode(&mut fields);
+    }
+
+    let key =
+        SigningKey::from_slice(&alloy_primitives::hex::decode(DEV_PRIVATE_KEY).unwrap()).unwrap();
+    let hash = tx::signing_hash(tx.tx_type, &fields, Some(tx.chain_id));
+    let (signature, recovery_id) = key.sign_prehash_recoverable(hash.as_slice()).unwrap();
+    let parity = recovery_id.is_y_odd() as u64;
+    match tx.tx_type {
+        LEGACY_TX_TYPE => (tx.chain_id * 2 + 35 + parity).encode(&mut fields),
+        _ => parity.encode(&mut fields),
+    }
+    U256::from_be_slice(&signature.r().to_bytes()).encode(&mut fields);
+    U256::from_be_slice(&signature.s().to_bytes()).encode(&mut fields);
+
+    let mut raw = Vec::new();
+    if tx.tx_type != LEGACY_TX_TYPE {
+        raw.push(tx.tx_type);
+    }
+    Header {
+        list: true,
+        payload_length: fields.len(),
+    }
+    .encode(&mut raw);
+    raw.extend_from_slice(&fields);
+
+    raw.into()
+}
+
+fn request(node: &mut Node, method: &str, params: Value) -> Value {
+    let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+    let response = rpc::handle_body(node, body.to_string().as_bytes());
+    assert_eq!(response["id"], 1);
+
+    response
+}
+
+fn result(node: &mut Node, method: &str, params: Value) -> Value {
+    let response = request(node, method, params);
+    assert!(
+        response.get("error").is_none(),
+        "{method} failed: {response}"
+    );
+
+    response["result"].clone()
+}
+
+fn send(node: &mut Node, tx: &UnsignedTx) -> Value {
+    let tx_hash = result(node, "eth_sendRawTransaction", json!([sign(tx)]));
+    result(node, "eth_getTransactionReceipt", json!([tx_hash]))
+}
+
+#[test]
+fn test_value_transfer() {
+    let mut node = Node::new(&[DEV_ACCOUNT]);
+    assert_eq!(result(&mut node, "eth_chainId", json!([])), "0x1");
+    assert_eq!(result(&mut node, "eth_blockNumber", json!([])), "0x0");
+
+    for tx_type in [EIP1559_TX_TYPE, LEGACY_TX_TYPE] {
+        let nonce = node.nonce(DEV_ACCOUNT);
+        let tx = UnsignedTx {
+            tx_type,
+            ..UnsignedTx::new(nonce, TxKind::Call(BOB), U256::from(42), Bytes::new())
+        };
+        let receipt = send(&mut node, &tx);
+        assert_eq!(receipt["status"], "0x1");
+        assert_eq!(receipt["from"], json!(DEV_ACCOUNT));
+        assert_eq!(receipt["gasUsed"], "0x5208");
+        assert_eq!(receipt["type"], json!(format!("0x{tx_type}")));
+    }
+
+    // Each tx is mined in its own block
+    assert_eq!(result(&mut node, "eth_blockNumber", json!([])), "0x2");
+    let block = result(&mut node, "eth_getBlockByNumber", json!(["latest", false]));
+    assert_eq!(block["number"], "0x2");
+    assert_eq!(block["transactions"].as_array().unwrap().len(), 1);
+
+    assert_eq!(
+        result(&mut node, "eth_getBalance", json!([BOB, "latest"])),
+        "0x54"
+    );
+    assert_eq!(
+        result(
+            &mut node,
+            "eth_getTransactionCount",
+            json!([DEV_ACCOUNT, "latest"])
+        ),
+        "0x2"
+    );
+}
+
+#[test]
+fn test_invalid_transactions_are_rejected() {
+    let mut node = Node::new(&[DEV_ACCOUNT]);
+
+    let replayed = UnsignedTx::new(1, TxKind::Call(BOB), U256::from(1), Bytes::new());
+    let response = request(
+        &mut node,
+        "eth_sendRawTransaction",
+        json!([sign(&replayed)]),
+    );
+    assert!(response["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("nonce"));
+
+    let other_chain = UnsignedTx {
+        chain_id: 2,
+        ..UnsignedTx::new(0, TxKind::Call(BOB), U256::from(1), Bytes::new())
+    };
+    let response = request(
+        &mut node,
+        "eth_sendRawTransaction",
+        json!([sign(&other_chain)]),
+    );
+    assert!(response["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("chain id"));
+
+    // Neither of them was mined
+    assert_eq!(node.nonce(DEV_ACCOUNT), 0);
+    assert_eq!(result(&mut node, "eth_blockNumber", json!([])), "0x0");
+
+    let response = request(&mut node, "eth_foo", json!([]));
+    assert_eq!(response["error"]["code"], -32601);
+}
+
+#[test]
+fn test_oversized_inputs_are_rejected() {
+    let mut node = Node::new(&[DEV_ACCOUNT]);
+    let calldata = Bytes::from(vec![1; MAX_CALLDATA_SIZE + 1]);
+    let initcode = Bytes::from(vec![1; MAX_INITCODE_SIZE + 1]);
+
+    for (method, params) in [
+        (
+            "eth_call",
+            json!([{ "to": BOB, "input": calldata }, "latest"]),
+        ),
+        ("eth_estimateGas", json!([{ "to": BOB, "input": calldata }])),
+        ("eth_call", json!([{ "input": initcode }, "latest"])),
+        ("eth_estimateGas", json!([{ "input": initcode }])),
+        (
+            "eth_sendRawTransaction",
+            json!([sign(&UnsignedTx::new(
+                0,
+                TxKind::Call(BOB),
+                U256::ZERO,
+                calldata.clone()
+            ))]),
+        ),
+    ] {
+        let response = request(&mut node, method, params);
+        assert_eq!(response["error"]["code"], -32000, "{method}: {response}");
+        assert!(response["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("too large"));
+    }
+    assert_eq!(node.nonce(DEV_ACCOUNT), 0);
+
+    // The limits are inclusive
+    let calldata = Bytes::from(vec![1; MAX_CALLDATA_SIZE]);
+    result(
+        &mut node,
+        "eth_call",
+        json!([{ "to": BOB, "input": calldata }, "latest"]),
+    );
+}
+
+#[test]
+fn test_http_request_head_is_bounded() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || http::serve(listener, &mut Node::new(&[DEV_ACCOUNT])));
+
+    let send = |request: String| {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        // The node may close the connection before reading all of the request
+        let _ = stream.read_to_string(&mut response);
+        response
+    };
+    let post = |headers: &str| {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": "eth_chainId" }).to_string();
+        format!(
+            "POST / HTTP/1.1\r\n{headers}Content-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+    };
+
+    // Requests with a too long line or too many headers are dropped without a response
+    let long_header = format!("X-Foo: {}\r\n", "a".repeat(16 * 1024));
+    assert_eq!(send(post(&long_header)), "");
+    assert_eq!(send(post(&"X-Foo: a\r\n".repeat(100))), "");
+
+    // and the node keeps serving the next ones
+    let response = send(post(""));
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.ends_with(r#""result":"0x1"}"#), "{response}");
+}
+
+#[test]
+fn test_erc20() {
+    let mut node = Node::new(&[DEV_ACCOUNT]);
+
+    // Deploys the ERC20, like `cast send --create $(r55-node initcode erc20 --args ..)`
+    let initcode = r55_initcode(get_bytecode("erc20"), Some(DEV_ACCOUNT.abi_encode())).unwrap();
+    let gas = result(
+        &mut node,
+        "eth_estimateGas",
+        json!([{ "from": DEV_ACCOUNT, "input": initcode }]),
+    );
+    assert!(gas.as_str().unwrap().starts_with("0x"));
+    let tx = UnsignedTx::new(0, TxKind::Create, U256::ZERO, initcode);
+    let receipt = send(&mut node, &tx);
+    assert_eq!(receipt["status"], "0x1");
+    let erc20: Address = serde_json::from_value(receipt["contractAddress"].clone()).unwrap();
+    assert_eq!(erc20, DEV_ACCOUNT.create(0));
+    let code = result(&mut node, "eth_getCode", json!([erc20, "latest"]));
+    assert_ne!(code, "0x");
+
+    // Mints to BOB, which emits a `Transfer` in the receipt
+    let mint = get_calldata(
+        get_selector_from_sig("mint(address,uint256)"),
+        (BOB, U256::from(42)).abi_encode(),
+    );
+    let tx = UnsignedTx::new(1, TxKind::Call(erc20), U256::ZERO, mint.into());
+    let receipt = send(&mut node, &tx);
+    assert_eq!(receipt["status"], "0x1");
+    assert_eq!(receipt["logs"].as_array().unwrap().len(), 1);
+    assert_eq!(receipt["logs"][0]["address"], json!(erc20));
+
+    // Reads the balance back, like `cast call <erc20> "balance_of(address)" <bob>`
+    let balance_of = get_calldata(
+        get_selector_from_sig("balance_of(address)"),
+        BOB.abi_encode(),
+    );
+    let output = result(
+        &mut node,
+        "eth_call",
+        json!([{ "to": erc20, "data": Bytes::from(balance_of) }, "latest"]),
+    );
+    assert_eq!(output, json!(Bytes::from(U256::from(42).abi_encode())));
+
+    // Failed transfers are mined with a failed receipt, and reverted calls return their payload
+    let transfer = get_calldata(
+        get_selector_from_sig("transfer(address,uint256)"),
+        (BOB, U256::from(1)).abi_encode(),
+    );
+    let response = request(
+        &mut node,
+        "eth_call",
+        json!([{ "from": DEV_ACCOUNT, "to": erc20, "input": Bytes::from(transfer.clone()) }]),
+    );
+    assert_eq!(response["error"]["code"], 3);
+    assert!(response["error"]["data"].as_str().unwrap().len() > 2);
+    let tx = UnsignedTx::new(2, TxKind::Call(erc20), U256::ZERO, transfer.into());
+    assert_eq!(send(&mut node, &tx)["status"], "0x0");
+    assert_eq!(node.nonce(DEV_ACCOUNT), 3);
+}
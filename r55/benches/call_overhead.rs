@@ -0,0 +1,58 @@
+//! Overhead of setting up the emulator of a frame, on a loop of cheap `balance_of` calls: as txs,
+//! and as nested calls (through `forward`) to the same contract. The cold runs unload the programs
+//! before every tx, so that their frames parse the ELF of the contract again.
+//!   cargo bench --package r55 --bench call_overhead
+
+use std::time::{Duration, Instant};
+
+use alloy_primitives::{Address, Bytes};
+use alloy_sol_types::SolValue;
+use r55::{
+    exec::{deploy_contract, run_tx},
+    get_bytecode, programs,
+    test_utils::{add_balance_to_db, get_calldata, get_selector_from_sig, ALICE},
+};
+use revm::InMemoryDB;
+
+const ITERATIONS: u32 = 1_000;
+
+fn main() {
+    let mut db = InMemoryDB::default();
+    add_balance_to_db(&mut db, ALICE, 1e18 as u64);
+    let testbed = deploy_contract(&mut db, get_bytecode("testbed"), None).unwrap();
+
+    let balance_of = get_calldata(
+        get_selector_from_sig("balance_of(address)"),
+        ALICE.abi_encode(),
+    );
+    let forward = get_calldata(
+        get_selector_from_sig("forward(address,bytes)"),
+        (testbed, Bytes::from(balance_of.clone())).abi_encode(),
+    );
+
+    println!("{} iterations", ITERATIONS);
+    run(&mut db, testbed, "balance_of", balance_of.clone(), false);
+    run(&mut db, testbed, "cold balance_of", balance_of, true);
+    run(
+        &mut db,
+        testbed,
+        "nested balance_of",
+        forward.clone(),
+        false,
+    );
+    run(&mut db, testbed, "cold nested", forward, true);
+}
+
+fn run(db: &mut InMemoryDB, testbed: Address, name: &str, calldata: Vec<u8>, cold: bool) {
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        if cold {
+            programs::clear();
+        }
+        let start = Instant::now();
+        run_tx(db, &testbed, calldata.clone(), &ALICE).expect("Error executing tx");
+        elapsed += start.elapsed();
+    }
+
+    println!("{:<20} {:>10.2?}/tx", name, elapsed / ITERATIONS);
+}
@@ -0,0 +1,81 @@
+//! Time spent moving a 32KB payload between the host and the emulator: as calldata read in place,
+//! as calldata streamed through a `CalldataReader`, and as the return data of a nested call.
+//!   cargo bench --package r55 --bench data_path
+
+use std::time::{Duration, Instant};
+
+use alloy_primitives::{Address, Bytes};
+use alloy_sol_types::SolValue;
+use eth_riscv_syscalls::Syscall;
+use r55::{
+    exec::{deploy_contract, run_tx},
+    get_bytecode,
+    test_utils::{add_balance_to_db, get_calldata, get_selector_from_sig, ALICE},
+};
+use revm::InMemoryDB;
+
+const PAYLOAD_SIZE: usize = 32 * 1024;
+const ITERATIONS: u32 = 20;
+
+fn main() {
+    let mut db = InMemoryDB::default();
+    add_balance_to_db(&mut db, ALICE, 1e18 as u64);
+    let testbed = deploy_contract(&mut db, get_bytecode("testbed"), None).unwrap();
+
+    let payload = Bytes::from(
+        (0..PAYLOAD_SIZE)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>(),
+    );
+    let return_raw = get_calldata(
+        get_selector_from_sig("return_raw(uint64)"),
+        (PAYLOAD_SIZE as u64).abi_encode(),
+    );
+    let cases = [
+        (
+            "calldata (decoded)",
+            get_calldata(get_selector_from_sig("hash(bytes)"), payload.abi_encode()),
+        ),
+        (
+            "calldata (streamed)",
+            get_calldata(
+                get_selector_from_sig("hash_windows_streamed(bytes,uint64)"),
+                (payload.clone(), 1024_u64).abi_encode(),
+            ),
+        ),
+        (
+            "return data",
+            get_calldata(
+                get_selector_from_sig("forward(address,bytes)"),
+                (testbed, Bytes::from(return_raw)).abi_encode(),
+            ),
+        ),
+    ];
+
+    println!("{} bytes, {} iterations", PAYLOAD_SIZE, ITERATIONS);
+    for (name, calldata) in cases {
+        run(&mut db, testbed, name, calldata);
+    }
+}
+
+fn run(db: &mut InMemoryDB, testbed: Address, name: &str, calldata: Vec<u8>) {
+    let mut elapsed = Duration::ZERO;
+    let mut result = None;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let tx = run_tx(db, &testbed, calldata.clone(), &ALICE).expect("Error executing tx");
+        elapsed += start.elapsed();
+        result = Some(tx);
+    }
+
+    let result = result.expect("No iterations");
+    let copies = |syscall| result.syscalls.get(&syscall).copied().unwrap_or_default();
+    println!(
+        "{:<20} {:>10.2?}/tx  gas: {:>9}  CALLDATACOPY: {:>3}  RETURNDATACOPY: {:>3}",
+        name,
+        elapsed / ITERATIONS,
+        result.gas_used,
+        copies(Syscall::CallDataCopy),
+        copies(Syscall::ReturnDataCopy),
+    );
+}
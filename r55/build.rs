@@ -59,6 +59,9 @@ use core::include_bytes;
         r#"        _ => return Bytes::new(),
     };
 
+    // Fail loudly if the sources changed since the bytecode was compiled
+    crate::lock::check_fresh(contract_name, initcode);
+
     Bytes::from(initcode)
 }
 "#,
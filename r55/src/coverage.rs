@@ -0,0 +1,258 @@
+//! Coverage collection for R55 contracts.
+//!
+//! When the `coverage` feature is enabled, the emulator is stepped one instruction at a time and
+//! every executed PC is recorded against the hash of the running ELF. Hits are aggregated across
+//! all the transactions of the process and can be exported as an lcov tracefile.
+//!
+//! PCs are mapped back to the function symbols of the ELF and, with source maps (the
+//! `.sourcemap.json` that `--coverage` and `--min-size` builds export from the line tables of the
+//! runtime, see `symbols`), to source lines. Without them, the lcov tracefile only has function
+//! records (`FN`/`FNDA`), with one `SF` record per crate rather than per source file.
+
+use crate::{selectors, symbols};
+use alloy_core::primitives::{keccak256, B256};
+use rvemu::{emulator::Emulator, exception::Exception};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write as _,
+    fs,
+    path::Path,
+    sync::{LazyLock, Mutex},
+};
+use tracing::warn;
+
+/// Env var that, when set, points to the lcov file that is refreshed after every transaction.
+pub const COVERAGE_OUTPUT_ENV: &str = "R55_COVERAGE";
+
+static COVERAGE: LazyLock<Mutex<BTreeMap<B256, CodeCoverage>>> =
+    LazyLock::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Default)]
+struct CodeCoverage {
+    elf: Vec<u8>,
+    hits: HashMap<u64, u64>,
+}
+
+/// Hit counts of a single function symbol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCoverage {
+    /// Crate the function belongs to (first segment of the demangled path).
+    pub krate: String,
+    /// Demangled function name, without the trailing hash.
+    pub name: String,
+    /// Number of times the entry point of the function was executed.
+    pub hits: u64,
+    /// Source file and line of the function, with source maps.
+    pub location: Option<(String, u32)>,
+}
+
+/// Hit counts of a single source line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineCoverage {
+    pub file: String,
+    pub line: u32,
+    /// Number of times the most executed instruction of the line was executed.
+    pub hits: u64,
+}
+
+/// Registers a RISC-V ELF so that its executed PCs can be symbolized later on.
+pub(crate) fn register(elf: &[u8]) -> B256 {
+    let code_hash = keccak256(elf);
+    COVERAGE
+        .lock()
+        .expect("coverage lock poisoned")
+        .entry(code_hash)
+        .or_insert_with(|| CodeCoverage {
+            elf: elf.to_vec(),
+            hits: HashMap::new(),
+        });
+
+    code_hash
+}
+
+/// Drop-in replacement of `Emulator::start` that records every executed PC.
+pub(crate) fn start(emu: &mut Emulator, code_hash: B256) -> Result<(), Exception> {
+    let mut hits: HashMap<u64, u64> = HashMap::new();
+    let result = loop {
+        *hits.entry(emu.cpu.pc).or_default() += 1;
+        if let Err(exception) = emu.cpu.execute() {
+            break Err(exception);
+        }
+    };
+
+    let mut coverage = COVERAGE.lock().expect("coverage lock poisoned");
+    if let Some(code) = coverage.get_mut(&code_hash) {
+        for (pc, count) in hits {
+            *code.hits.entry(pc).or_default() += count;
+        }
+    }
+
+    result
+}
+
+/// Returns the hit counts of every function symbol of the executed contracts.
+pub fn functions() -> Vec<FunctionCoverage> {
+    let coverage = COVERAGE.lock().expect("coverage lock poisoned");
+    let mut functions = Vec::new();
+
+    for code in coverage.values() {
+        // Stripped runtimes are symbolized with their debug copy
+        let symbolizer = symbols::symbolizer(&code.elf);
+        if symbolizer.functions().is_empty() {
+            warn!("No symbols for coverage of {}", keccak256(&code.elf));
+        }
+
+        for (range, name) in symbolizer.functions() {
+            functions.push(FunctionCoverage {
+                krate: name.split("::").next().unwrap_or_default().to_string(),
+                name: name.clone(),
+                hits: code.hits.get(&range.start).copied().unwrap_or_default(),
+                location: symbolizer.line(range.start),
+            });
+        }
+    }
+
+    functions
+}
+
+/// Returns the hit counts of every source line of the executed contracts, which are only known
+/// with source maps.
+pub fn lines() -> Vec<LineCoverage> {
+    let coverage = COVERAGE.lock().expect("coverage lock poisoned");
+    let mut lines: BTreeMap<(String, u32), u64> = BTreeMap::new();
+
+    for code in coverage.values() {
+        let hits = code.hits.iter().map(|(pc, count)| (*pc, *count));
+        let hits = hits.collect::<BTreeMap<u64, u64>>();
+        let code_lines = match symbols::source_map(&code.elf) {
+            Some(map) => map
+                .lines
+                .iter()
+                .map(|line| (line.start..line.end, line.file.clone(), line.line))
+                .collect(),
+            // Line tables of the code itself, if it wasn't stripped
+            None => symbols::symbolizer(&code.elf).lines(),
+        };
+        for (range, file, line) in code_lines {
+            let max = hits.range(range).map(|(_, count)| *count);
+            let count = lines.entry((file, line)).or_default();
+            *count = max.fold(*count, u64::max);
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|((file, line), hits)| LineCoverage { file, line, hits })
+        .collect()
+}
+
+/// Lists the public methods of the contracts (the ones of their dispatch table, as listed in the
+/// selector table) that were never entered.
+pub fn uncovered() -> Vec<FunctionCoverage> {
+    let methods = public_methods();
+    functions()
+        .into_iter()
+        .filter(|f| f.hits == 0 && is_public_method(&methods, &f.name))
+        .collect()
+}
+
+// `Contract::method` of the functions of the selector table
+fn public_methods() -> BTreeSet<String> {
+    let Some(table) = selectors::table() else {
+        return BTreeSet::new();
+    };
+
+    let mut methods = BTreeSet::new();
+    for entry in table.functions.values().flatten() {
+        let method = entry.signature.split('(').next().unwrap_or_default();
+        for contract in &entry.contracts {
+            methods.insert(format!("{contract}::{method}"));
+        }
+    }
+    methods
+}
+
+// Whether the demangled `name` is a method of `methods`, e.g. `erc20::ERC20::transfer` (or
+// `<erc20::ERC20>::transfer`) of `ERC20::transfer`
+fn is_public_method(methods: &BTreeSet<String>, name: &str) -> bool {
+    let name = name.replace(['<', '>'], "");
+    let mut segments = name.rsplit("::");
+    let (Some(method), Some(contract)) = (segments.next(), segments.next()) else {
+        return false;
+    };
+    methods.contains(&format!("{contract}::{method}"))
+}
+
+/// Renders the aggregated coverage as an lcov tracefile, with one `SF` record per source file, or
+/// per crate for the functions whose source isn't known.
+pub fn lcov() -> String {
+    let mut by_file: BTreeMap<String, (Vec<FunctionCoverage>, Vec<LineCoverage>)> = BTreeMap::new();
+    for f in functions() {
+        let file = match &f.location {
+            Some((file, _)) => file.clone(),
+            None => f.krate.clone(),
+        };
+        by_file.entry(file).or_default().0.push(f);
+    }
+    for line in lines() {
+        by_file.entry(line.file.clone()).or_default().1.push(line);
+    }
+
+    let mut out = String::new();
+    for (file, (functions, lines)) in by_file {
+        let _ = writeln!(out, "TN:\nSF:{file}");
+        for f in &functions {
+            let line = f.location.as_ref().map_or(0, |(_, line)| *line);
+            let _ = writeln!(out, "FN:{},{}", line, f.name);
+        }
+        for f in &functions {
+            let _ = writeln!(out, "FNDA:{},{}", f.hits, f.name);
+        }
+        let _ = writeln!(out, "FNF:{}", functions.len());
+        let _ = writeln!(
+            out,
+            "FNH:{}",
+            functions.iter().filter(|f| f.hits > 0).count()
+        );
+        for line in &lines {
+            let _ = writeln!(out, "DA:{},{}", line.line, line.hits);
+        }
+        if !lines.is_empty() {
+            let _ = writeln!(out, "LF:{}", lines.len());
+            let _ = writeln!(out, "LH:{}", lines.iter().filter(|l| l.hits > 0).count());
+        }
+        let _ = writeln!(out, "end_of_record");
+    }
+
+    out
+}
+
+/// Writes the lcov tracefile to `path`.
+pub fn write_lcov<P: AsRef<Path>>(path: P) -> std::io::Result<()> {
+    fs::write(path, lcov())
+}
+
+/// Human-readable summary, which explicitly lists the uncovered public methods.
+pub fn summary() -> String {
+    let functions = functions();
+    let total = functions.len();
+    let covered = functions.iter().filter(|f| f.hits > 0).count();
+
+    let mut out = format!("R55 coverage: {covered}/{total} functions entered\n");
+    for f in uncovered() {
+        let _ = writeln!(out, "  - uncovered: {}", f.name);
+    }
+
+    out
+}
+
+/// Refreshes the lcov file pointed by `R55_COVERAGE`, if set.
+pub(crate) fn flush() {
+    let Ok(path) = std::env::var(COVERAGE_OUTPUT_ENV) else {
+        return;
+    };
+
+    if let Err(e) = write_lcov(&path) {
+        warn!("Unable to write coverage to {path}: {e}");
+    }
+}
@@ -1,14 +1,18 @@
 //! R55 crate errors
 
 use core::fmt;
+use std::collections::BTreeMap;
 
-use alloy_primitives::{keccak256, Bytes};
+use alloy_primitives::{keccak256, Address, Bytes};
+use eth_riscv_syscalls::Syscall;
 use revm::{
-    primitives::{EVMError, ExecutionResult, Log},
+    primitives::{EVMError, ExecutionResult, Log, U256},
     Database, InMemoryDB,
 };
 use rvemu::exception::Exception;
 
+use crate::{failure::FrameFailure, max_gas::BudgetedCall, selectors, state_diff::StateDiff};
+
 pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug)]
@@ -17,6 +21,18 @@ pub struct TxResult {
     pub logs: Vec<Log>,
     pub gas_used: u64,
     pub status: bool,
+    /// Number of syscalls issued by the RISC-V frames of the transaction, by kind.
+    pub syscalls: BTreeMap<Syscall, u64>,
+    /// Number of RISC-V instructions run by the frames of the transaction, startups included.
+    pub instructions: u64,
+    /// State changes of the transaction, if requested with `ExecEnv::with_state_diff`.
+    pub state_diff: Option<StateDiff>,
+    /// Calls of the transaction to methods with a `#[max_gas]` budget.
+    pub budgeted_calls: Vec<BudgetedCall>,
+    /// Address of the deployed contract, for deployment transactions.
+    pub created: Option<Address>,
+    /// Frames of the transaction that failed, even if their caller recovered.
+    pub failures: Vec<FrameFailure>,
 }
 
 /// Error encountered on RISC-V execution
@@ -36,6 +52,13 @@ where
     SyscallError(eth_riscv_syscalls::Error),
     /// Unexpected result of the transaction execution error
     UnexpectedExecResult(ExecutionResult),
+    /// The gas limit of the transaction doesn't cover its intrinsic cost
+    IntrinsicGas { required: u64, gas_limit: u64 },
+    /// The max fee per gas of the transaction doesn't cover the base fee of the block
+    MaxFeeBelowBasefee {
+        max_fee_per_gas: U256,
+        basefee: U256,
+    },
 }
 
 // Note: this `From` implementation here because `rvemu::exception::Exception`
@@ -89,7 +112,11 @@ where
                     output,
                     String::from_utf8(output.to_vec()).unwrap_or_default(),
                     gas_used
-                )
+                )?;
+                if let Some(error) = selectors::error_label(output) {
+                    write!(f, "\n > decoded: {}", error)?;
+                }
+                Ok(())
             }
             Self::RvEmuException(e) => write!(f, "Got RISC-V emulator exception: {:?}", e),
             Self::EvmError(e) => write!(f, "{}", e),
@@ -100,6 +127,22 @@ where
                 "Unexpected result of the transaction execution: {:?}",
                 other
             ),
+            Self::IntrinsicGas {
+                required,
+                gas_limit,
+            } => write!(
+                f,
+                "Intrinsic gas too low: required {}, gas limit {}",
+                required, gas_limit
+            ),
+            Self::MaxFeeBelowBasefee {
+                max_fee_per_gas,
+                basefee,
+            } => write!(
+                f,
+                "Max fee per gas {} is lower than the base fee {}",
+                max_fee_per_gas, basefee
+            ),
         }
     }
 }
@@ -1,85 +1,411 @@
-use alloy_core::primitives::{Keccak256, U32};
-use core::cell::RefCell;
-use eth_riscv_interpreter::setup_from_elf;
-use eth_riscv_syscalls::Syscall;
+use alloy_core::primitives::{keccak256, Keccak256, U32};
+use core::cell::{Cell, RefCell};
+use eth_riscv_interpreter::Program;
+use eth_riscv_syscalls::{
+    header::{Header, WIDE_CALL_VALUE, ZERO_GAS_CAP},
+    metadata::{GasBudget, Metadata},
+    Syscall,
+};
 use revm::{
+    db::CacheDB,
     handler::register::EvmHandler,
     interpreter::{
         CallInputs, CallScheme, CallValue, CreateInputs, CreateScheme, Host, InstructionResult,
         Interpreter, InterpreterAction, InterpreterResult, SharedMemory,
     },
-    primitives::{address, Address, Bytes, ExecutionResult, Log, Output, TransactTo, B256, U256},
-    Database, Evm, Frame, FrameOrResult, InMemoryDB,
+    primitives::{
+        address, AccessList, AccessListItem, Address, BlockEnv, Bytes, EVMError, ExecutionResult,
+        Log, ResultAndState, SpecId, TransactTo, B256, U256,
+    },
+    Database, DatabaseCommit, Evm, Frame, FrameOrResult, InMemoryDB,
 };
 use rvemu::{emulator::Emulator, exception::Exception};
-use std::{collections::BTreeMap, rc::Rc, sync::Arc};
-use tracing::{debug, info, trace, warn};
+use std::{collections::BTreeMap, ops::Range, rc::Rc, sync::Arc};
+use tracing::{debug, debug_span, field, info, trace, trace_span, warn, Span};
 
 use super::error::{Error, Result, TxResult};
+use super::failure::{self, FrameFailure, Trap};
 use super::gas;
+use super::max_gas::{self, BudgetedCall};
+use super::programs;
+use super::selectors;
+use super::state_diff::StateDiff;
+use super::symbols;
 use super::syscall_gas;
 
 const R5_REST_OF_RAM_INIT: u64 = 0x80300000; // Defined at `r5-rust-rt.x`
 
+// Bottom of the stack region, defined at `r5-rust-rt.x`. It is filled with a canary, so that frames
+// that ran past their stack are caught before their corrupted memory reaches the state.
+const STACK_GUARD: Range<u64> = 0x80100000..0x80101000;
+const STACK_CANARY: u8 = 0xa5;
+
+// Padding appended by revm to the analyzed bytecode, which isn't part of the constructor args
+const BYTECODE_PADDING: usize = 33;
+
+// Solidity's `Panic(uint256)` selector, and the code used for failed allocations
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+const PANIC_ALLOCATION_FAILURE: u64 = 0x41;
+
+// Max number of instructions run to measure the startup cost of a program
+const STARTUP_PROBE_LIMIT: u64 = 5_000_000;
+
+thread_local! {
+    /// Syscalls issued by the RISC-V frames of the transaction being executed, by kind.
+    static SYSCALL_COUNTS: RefCell<BTreeMap<Syscall, u64>> = RefCell::default();
+    /// RISC-V instructions run by the frames of the transaction being executed.
+    static INSTRUCTIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// `tracing` target of the spans of the executed frames, with their depth, address and selector.
+pub const FRAMES_TARGET: &str = "r55::frames";
+
+/// `tracing` target of the spans of the syscalls issued by RISC-V frames, with their storage key or
+/// account (if any), and of the gas they charge. E.g. `RUST_LOG=r55::syscalls=trace`.
+pub const SYSCALLS_TARGET: &str = "r55::syscalls";
+
+/// Longest call data (or constructor args) R55 frames can run with. Longer ones halt the frame.
+pub use eth_riscv_interpreter::MAX_CALLDATA_SIZE;
+
+/// Gas limit of the transactions sent by the executor, unless specified otherwise.
+pub const DEFAULT_GAS_LIMIT: u64 = 100_000_000;
+
+/// Sender of the deployment transactions.
+pub const DEPLOYER: Address = address!("000000000000000000000000000000000000000A");
+
+/// Gas price of the transactions sent by the executor, unless specified otherwise.
+pub const DEFAULT_GAS_PRICE: u64 = 42;
+
+/// Seconds between blocks, which `BlockEnvBuilder::mine` advances the timestamp by.
+pub const DEFAULT_BLOCK_TIME: u64 = 12;
+
+/// When the block of an `ExecEnv` advances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MinePolicy {
+    /// Only with explicit calls to `mine`: all the txs are included in the same block
+    #[default]
+    Manual,
+    /// After every tx sent with `send_tx`, so that each one is included in its own block
+    EveryTx,
+}
+
+/// Block the transactions of an `ExecEnv` are executed in. Defaults to revm's block env.
+///   ```ignore
+///   let block = BlockEnvBuilder::default().with_number(U256::from(100)).with_gas_limit(U256::from(30e6));
+///   let env = ExecEnv::default().with_block(block);
+///   send_tx(&mut db, &tx, &env)?;   // block 100
+///   env.mine();
+///   send_tx(&mut db, &tx, &env)?;   // block 101, 12s later
+///   ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockEnvBuilder {
+    pub number: U256,
+    pub timestamp: U256,
+    pub gas_limit: U256,
+    pub basefee: U256,
+    pub coinbase: Address,
+    pub prevrandao: B256,
+    /// Excess blob gas, which the blob base fee is derived from. `None` before Cancun.
+    pub excess_blob_gas: Option<u64>,
+    /// Seconds that `mine` advances the timestamp by
+    pub block_time: u64,
+    pub policy: MinePolicy,
+}
+
+impl Default for BlockEnvBuilder {
+    fn default() -> Self {
+        let block = BlockEnv::default();
+        Self {
+            number: block.number,
+            timestamp: block.timestamp,
+            gas_limit: block.gas_limit,
+            basefee: block.basefee,
+            coinbase: block.coinbase,
+            prevrandao: block.prevrandao.unwrap_or_default(),
+            excess_blob_gas: block.get_blob_excess_gas(),
+            block_time: DEFAULT_BLOCK_TIME,
+            policy: MinePolicy::Manual,
+        }
+    }
+}
+
+impl BlockEnvBuilder {
+    pub fn with_number(mut self, number: U256) -> Self {
+        self.number = number;
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: U256) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn with_gas_limit(mut self, gas_limit: U256) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn with_basefee(mut self, basefee: U256) -> Self {
+        self.basefee = basefee;
+        self
+    }
+
+    pub fn with_coinbase(mut self, coinbase: Address) -> Self {
+        self.coinbase = coinbase;
+        self
+    }
+
+    pub fn with_prevrandao(mut self, prevrandao: B256) -> Self {
+        self.prevrandao = prevrandao;
+        self
+    }
+
+    pub fn with_excess_blob_gas(mut self, excess_blob_gas: u64) -> Self {
+        self.excess_blob_gas = Some(excess_blob_gas);
+        self
+    }
+
+    pub fn with_block_time(mut self, block_time: u64) -> Self {
+        self.block_time = block_time;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: MinePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Advances to the next block
+    pub fn mine(&mut self) {
+        self.number += U256::from(1);
+        self.timestamp += U256::from(self.block_time);
+    }
+
+    /// revm's block env, under `spec_id`
+    pub fn build(&self, spec_id: SpecId) -> BlockEnv {
+        let mut block = BlockEnv {
+            number: self.number,
+            timestamp: self.timestamp,
+            gas_limit: self.gas_limit,
+            basefee: self.basefee,
+            coinbase: self.coinbase,
+            prevrandao: Some(self.prevrandao),
+            ..Default::default()
+        };
+        if let Some(excess_blob_gas) = self.excess_blob_gas {
+            block.set_blob_excess_gas_and_price(
+                excess_blob_gas,
+                spec_id.is_enabled_in(SpecId::PRAGUE),
+            );
+        }
+
+        block
+    }
+}
+
+/// Environment the R55 executor runs transactions with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecEnv {
+    /// Hardfork whose gas schedule and syscalls apply. Defaults to the latest one.
+    pub spec_id: SpecId,
+    /// Current block, which persists across the txs sent with this env and advances with `mine`
+    block: Cell<BlockEnvBuilder>,
+    /// Max fee per gas of the transactions, which is the gas price of legacy transactions.
+    pub max_fee_per_gas: U256,
+    /// Max priority fee per gas of the transactions. `None` for legacy transactions, which pay
+    /// their full gas price.
+    pub max_priority_fee_per_gas: Option<U256>,
+    /// Whether `send_tx` computes the state diff of the transactions. Disabled by default.
+    pub state_diff: bool,
+}
+
+impl Default for ExecEnv {
+    fn default() -> Self {
+        Self {
+            spec_id: SpecId::LATEST,
+            block: Cell::default(),
+            max_fee_per_gas: U256::from(DEFAULT_GAS_PRICE),
+            max_priority_fee_per_gas: None,
+            state_diff: false,
+        }
+    }
+}
+
+impl ExecEnv {
+    pub fn with_spec_id(mut self, spec_id: SpecId) -> Self {
+        self.spec_id = spec_id;
+        self
+    }
+
+    pub fn set_spec_id(&mut self, spec_id: SpecId) {
+        self.spec_id = spec_id;
+    }
+
+    pub fn with_block(self, block: BlockEnvBuilder) -> Self {
+        self.block.set(block);
+        self
+    }
+
+    /// Current block
+    pub fn block(&self) -> BlockEnvBuilder {
+        self.block.get()
+    }
+
+    pub fn set_block(&self, block: BlockEnvBuilder) {
+        self.block.set(block);
+    }
+
+    /// Advances to the next block, see `BlockEnvBuilder::mine`
+    pub fn mine(&self) {
+        let mut block = self.block.get();
+        block.mine();
+        self.block.set(block);
+    }
+
+    pub fn with_basefee(self, basefee: U256) -> Self {
+        let block = self.block().with_basefee(basefee);
+        self.with_block(block)
+    }
+
+    pub fn with_max_fee_per_gas(mut self, max_fee_per_gas: U256) -> Self {
+        self.max_fee_per_gas = max_fee_per_gas;
+        self
+    }
+
+    pub fn with_max_priority_fee_per_gas(mut self, max_priority_fee_per_gas: U256) -> Self {
+        self.max_priority_fee_per_gas = Some(max_priority_fee_per_gas);
+        self
+    }
+
+    pub fn with_state_diff(mut self) -> Self {
+        self.state_diff = true;
+        self
+    }
+
+    /// Priority fee actually paid: clamped so that the total fee doesn't exceed the max fee.
+    pub fn priority_fee_per_gas(&self) -> U256 {
+        let max_tip = self.max_fee_per_gas.saturating_sub(self.block().basefee);
+        match self.max_priority_fee_per_gas {
+            Some(priority_fee) => priority_fee.min(max_tip),
+            None => max_tip,
+        }
+    }
+
+    /// Price paid for each unit of gas used: the base fee plus the (clamped) priority fee. Before
+    /// London, the full max fee is paid.
+    pub fn effective_gas_price(&self) -> U256 {
+        if !self.spec_id.is_enabled_in(SpecId::LONDON) {
+            return self.max_fee_per_gas;
+        }
+
+        self.block().basefee + self.priority_fee_per_gas()
+    }
+
+    /// Checks the EIP-1559 invariants: the max fee must cover the base fee.
+    fn check_fees<DB: Database>(&self) -> core::result::Result<(), Error<DB>>
+    where
+        DB::Error: std::error::Error + 'static,
+    {
+        let basefee = self.block().basefee;
+        if self.max_fee_per_gas < basefee {
+            return Err(Error::MaxFeeBelowBasefee {
+                max_fee_per_gas: self.max_fee_per_gas,
+                basefee,
+            });
+        }
+
+        Ok(())
+    }
+}
+
 pub fn deploy_contract(
     db: &mut InMemoryDB,
     bytecode: Bytes,
     encoded_args: Option<Vec<u8>>,
 ) -> Result<Address> {
-    let init_code = if Some(&0xff) == bytecode.first() {
-        // Craft R55 initcode: [0xFF][codesize][bytecode][constructor_args]
-        let codesize = U32::from(bytecode.len());
-        debug!("[DEPLOY] BYTECODE SIZE: {}", codesize);
-
-        let mut init_code = Vec::new();
-        init_code.push(0xff);
-        init_code.extend_from_slice(&Bytes::from(codesize.to_be_bytes_vec()));
-        init_code.extend_from_slice(&bytecode);
-        if let Some(args) = encoded_args {
-            debug!("[DEPLOY] ENCODED_ARGS: {:#?}", Bytes::from(args.clone()));
-            init_code.extend_from_slice(&args);
-        }
-        debug!("[DEPLOY] INITCODE SIZE: {}", init_code.len());
-        Bytes::from(init_code)
-    } else {
-        // do not modify bytecode for EVM contracts
-        bytecode
+    deploy_contract_with_env(db, bytecode, encoded_args, &ExecEnv::default())
+}
+
+pub fn deploy_contract_with_env(
+    db: &mut InMemoryDB,
+    bytecode: Bytes,
+    encoded_args: Option<Vec<u8>>,
+    env: &ExecEnv,
+) -> Result<Address> {
+    deploy_contract_with_result(db, bytecode, encoded_args, env).map(|result| result.address)
+}
+
+/// Outcome of a successful deployment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployResult {
+    pub address: Address,
+    /// Gas charged to the deployment tx, including its intrinsic cost and the code deposit.
+    pub gas_used: u64,
+}
+
+/// Deploys a contract from `DEPLOYER`. Deployments are free of charge (so they ignore the fees of
+/// the env) and don't advance its block.
+pub fn deploy_contract_with_result(
+    db: &mut InMemoryDB,
+    bytecode: Bytes,
+    encoded_args: Option<Vec<u8>>,
+    env: &ExecEnv,
+) -> Result<DeployResult> {
+    let init_code = r55_initcode(bytecode, encoded_args)?;
+
+    // The created address is derived from the deployer's nonce, which is bumped by the tx
+    let expected = DEPLOYER.create(nonce_of(db, DEPLOYER));
+
+    let free = ExecEnv {
+        block: Cell::new(env.block().with_basefee(U256::ZERO)),
+        max_fee_per_gas: U256::ZERO,
+        max_priority_fee_per_gas: None,
+        state_diff: false,
+        ..env.clone()
     };
+    let gas_limit = env.block().gas_limit.saturating_to();
+    let result = R55Executor::new(&free).deploy_with_gas_limit(
+        db,
+        init_code,
+        U256::ZERO,
+        DEPLOYER,
+        gas_limit,
+    )?;
 
-    // Run CREATE tx
-    let mut evm = Evm::builder()
-        .with_db(db)
-        .modify_tx_env(|tx| {
-            tx.caller = address!("000000000000000000000000000000000000000A");
-            tx.transact_to = TransactTo::Create;
-            tx.data = init_code;
-            tx.value = U256::from(0);
-        })
-        .modify_cfg_env(|cfg| cfg.limit_contract_code_size = Some(usize::MAX))
-        .append_handler_register(handle_register)
-        .build();
+    let address = result
+        .created
+        .expect("Successful deployments create an address");
+    debug_assert_eq!(address, expected, "Unexpected CREATE address");
+    Ok(DeployResult {
+        address,
+        gas_used: result.gas_used,
+    })
+}
 
-    let result = evm.transact_commit()?;
+/// Initcode of a contract, with its (ABI-encoded) constructor args. R55 bytecode is wrapped as
+/// `[header][codesize][bytecode][constructor_args]`, while EVM bytecode is returned as is.
+pub fn r55_initcode(bytecode: Bytes, encoded_args: Option<Vec<u8>>) -> Result<Bytes> {
+    let is_r55 = Header::parse(&bytecode)
+        .map_err(Error::SyscallError)?
+        .is_some();
+    if !is_r55 {
+        // do not modify bytecode for EVM contracts
+        return Ok(bytecode);
+    }
 
-    match result {
-        ExecutionResult::Success {
-            output: Output::Create(_value, Some(addr)),
-            logs,
-            ..
-        } => {
-            info!(
-                "NEW DEPLOYMENT:\n> contract address: {:?}{}",
-                addr,
-                if logs.is_empty() {
-                    ""
-                } else {
-                    "\n> logs: {:#?}\n"
-                }
-            );
-            Ok(addr)
-        }
-        result => Err(Error::UnexpectedExecResult(result)),
+    let codesize = U32::from(bytecode.len());
+    debug!("[DEPLOY] BYTECODE SIZE: {}", codesize);
+
+    let mut init_code = Vec::new();
+    init_code.extend_from_slice(&Header::current().encode());
+    init_code.extend_from_slice(&Bytes::from(codesize.to_be_bytes_vec()));
+    init_code.extend_from_slice(&bytecode);
+    if let Some(args) = encoded_args {
+        debug!("[DEPLOY] ENCODED_ARGS: {:#?}", Bytes::from(args.clone()));
+        init_code.extend_from_slice(&args);
     }
+    debug!("[DEPLOY] INITCODE SIZE: {}", init_code.len());
+    Ok(Bytes::from(init_code))
 }
 
 pub fn run_tx(
@@ -88,89 +414,546 @@ pub fn run_tx(
     calldata: Vec<u8>,
     caller: &Address,
 ) -> Result<TxResult> {
-    let mut evm = Evm::builder()
-        .with_db(db)
-        .modify_tx_env(|tx| {
-            tx.caller = *caller;
-            tx.transact_to = TransactTo::Call(*addr);
-            tx.data = calldata.into();
-            tx.value = U256::from(0);
-            tx.gas_price = U256::from(42);
-            tx.gas_limit = 100_000_000;
-        })
-        .modify_cfg_env(|cfg| cfg.limit_contract_code_size = Some(usize::MAX))
-        .append_handler_register(handle_register)
-        .build();
+    run_tx_with_env(db, addr, calldata, caller, &ExecEnv::default())
+}
+
+pub fn run_tx_with_env(
+    db: &mut InMemoryDB,
+    addr: &Address,
+    calldata: Vec<u8>,
+    caller: &Address,
+    env: &ExecEnv,
+) -> Result<TxResult> {
+    send_tx(db, &TxSpec::call(*caller, *addr, calldata), env)
+}
 
-    let result = evm.transact_commit()?;
+/// Call transaction to be executed against a contract.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxSpec {
+    pub caller: Address,
+    pub to: Address,
+    pub calldata: Vec<u8>,
+    pub value: U256,
+    pub gas_limit: u64,
+    /// Accounts and storage slots warmed before execution (EIP-2930)
+    pub access_list: AccessList,
+}
+
+impl TxSpec {
+    pub fn call(caller: Address, to: Address, calldata: Vec<u8>) -> Self {
+        Self {
+            caller,
+            to,
+            calldata,
+            value: U256::ZERO,
+            gas_limit: DEFAULT_GAS_LIMIT,
+            access_list: AccessList::default(),
+        }
+    }
+
+    pub fn with_value(mut self, value: U256) -> Self {
+        self.value = value;
+        self
+    }
+
+    pub fn with_gas_limit(mut self, gas_limit: u64) -> Self {
+        self.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn with_access_list(mut self, access_list: AccessList) -> Self {
+        self.access_list = access_list;
+        self
+    }
+}
+
+/// Executor of transactions against a revm database, which runs the R55 frames on the RISC-V
+/// emulator. It is the entry point to embed R55 execution in other tools (the free functions of
+/// this module are built on it), and works with any db that changes can be committed to, e.g. an
+/// `InMemoryDB`, or a `CacheDB` over a forked state.
+///
+/// Transactions run with the spec, fees and current block of the borrowed `ExecEnv`, so that a
+/// block advanced by its `MinePolicy` is seen by every user of the env. The executor only logs
+/// through `tracing`: installing a subscriber is left to the embedder.
+///   ```ignore
+///   let env = ExecEnv::default();
+///   let executor = R55Executor::new(&env);
+///   let init_code = r55_initcode(bytecode, Some(constructor_args))?;
+///   let token = executor.deploy(&mut db, init_code, U256::ZERO, alice)?.created.unwrap();
+///   let result = executor.call(&mut db, token, calldata, U256::ZERO, alice, 1_000_000)?;
+///   ```
+#[derive(Debug, Clone, Copy)]
+pub struct R55Executor<'a> {
+    env: &'a ExecEnv,
+}
+
+impl<'a> R55Executor<'a> {
+    pub fn new(env: &'a ExecEnv) -> Self {
+        Self { env }
+    }
+
+    pub fn env(&self) -> &'a ExecEnv {
+        self.env
+    }
+
+    /// Deploys a contract from `initcode` (see `r55_initcode`) and commits it to the db, with a gas
+    /// limit of `DEFAULT_GAS_LIMIT`. The address of the contract is returned as `created`, and its
+    /// runtime code as the output.
+    pub fn deploy<DB>(
+        &self,
+        db: &mut DB,
+        initcode: Bytes,
+        value: U256,
+        caller: Address,
+    ) -> core::result::Result<TxResult, Error<DB>>
+    where
+        DB: Database + DatabaseCommit,
+        DB::Error: std::error::Error + 'static,
+    {
+        self.deploy_with_gas_limit(db, initcode, value, caller, DEFAULT_GAS_LIMIT)
+    }
+
+    pub fn deploy_with_gas_limit<DB>(
+        &self,
+        db: &mut DB,
+        initcode: Bytes,
+        value: U256,
+        caller: Address,
+        gas_limit: u64,
+    ) -> core::result::Result<TxResult, Error<DB>>
+    where
+        DB: Database + DatabaseCommit,
+        DB::Error: std::error::Error + 'static,
+    {
+        let access_list = AccessList::default();
+        self.transact(
+            db,
+            TransactTo::Create,
+            initcode,
+            value,
+            caller,
+            gas_limit,
+            access_list,
+        )
+    }
+
+    /// Executes a call transaction and commits its state changes to the db.
+    ///
+    /// The caller pays `gas_used * effective_gas_price`, on top of the value of the transaction.
+    pub fn call<DB>(
+        &self,
+        db: &mut DB,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        caller: Address,
+        gas_limit: u64,
+    ) -> core::result::Result<TxResult, Error<DB>>
+    where
+        DB: Database + DatabaseCommit,
+        DB::Error: std::error::Error + 'static,
+    {
+        let access_list = AccessList::default();
+        self.call_with_access_list(db, to, calldata, value, caller, gas_limit, access_list)
+    }
+
+    /// Executes a call transaction with an access list (EIP-2930), whose accounts and storage slots
+    /// are warm from the start of the execution. Each of its items is charged as intrinsic gas.
+    #[allow(clippy::too_many_arguments)]
+    pub fn call_with_access_list<DB>(
+        &self,
+        db: &mut DB,
+        to: Address,
+        calldata: Bytes,
+        value: U256,
+        caller: Address,
+        gas_limit: u64,
+        access_list: AccessList,
+    ) -> core::result::Result<TxResult, Error<DB>>
+    where
+        DB: Database + DatabaseCommit,
+        DB::Error: std::error::Error + 'static,
+    {
+        let transact_to = TransactTo::Call(to);
+        self.transact(
+            db,
+            transact_to,
+            calldata,
+            value,
+            caller,
+            gas_limit,
+            access_list,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn transact<DB>(
+        &self,
+        db: &mut DB,
+        transact_to: TransactTo,
+        data: Bytes,
+        value: U256,
+        caller: Address,
+        gas_limit: u64,
+        access_list: AccessList,
+    ) -> core::result::Result<TxResult, Error<DB>>
+    where
+        DB: Database + DatabaseCommit,
+        DB::Error: std::error::Error + 'static,
+    {
+        let env = self.env;
+        env.check_fees::<DB>()?;
+        let is_create = transact_to.is_create();
+        check_intrinsic_gas::<DB>(env.spec_id, &data, is_create, &access_list, gas_limit)?;
+        SYSCALL_COUNTS.take();
+        INSTRUCTIONS.take();
+        max_gas::take();
+        failure::reset();
+        let ResultAndState { result, state } = build_evm(
+            &mut *db,
+            env,
+            caller,
+            transact_to,
+            data,
+            value,
+            gas_limit,
+            access_list,
+        )
+        .transact()?;
+        let state_diff = if env.state_diff {
+            Some(StateDiff::new(db, &state).map_err(EVMError::Database)?)
+        } else {
+            None
+        };
+        db.commit(state);
+        if env.block().policy == MinePolicy::EveryTx {
+            env.mine();
+        }
+        let syscalls = SYSCALL_COUNTS.take();
+        let instructions = INSTRUCTIONS.take();
+        let budgeted_calls = max_gas::take();
+        #[cfg(feature = "coverage")]
+        crate::coverage::flush();
+
+        match result {
+            ExecutionResult::Success {
+                gas_used,
+                logs,
+                output,
+                ..
+            } => {
+                let created = output.address().copied();
+                match created {
+                    Some(addr) => info!("NEW DEPLOYMENT:\n> contract address: {:?}", addr),
+                    None => debug!("Tx result: {:?}", output.data()),
+                }
+                Ok(TxResult {
+                    output: output.into_data().into(),
+                    logs,
+                    gas_used,
+                    status: true,
+                    syscalls,
+                    instructions,
+                    state_diff,
+                    budgeted_calls,
+                    created,
+                    failures: failure::last_tx_failures(),
+                })
+            }
+            result => Err(Error::UnexpectedExecResult(result)),
+        }
+    }
+}
+
+/// Executes a call transaction and commits its state changes to the db, see `R55Executor::call`.
+///
+/// Unlike the executor, which only reports them, panics if a call of the transaction went over
+/// its `#[max_gas]` budget (see `max_gas::check`).
+pub fn send_tx(db: &mut InMemoryDB, tx: &TxSpec, env: &ExecEnv) -> Result<TxResult> {
+    let result = R55Executor::new(env).call_with_access_list(
+        db,
+        tx.to,
+        tx.calldata.clone().into(),
+        tx.value,
+        tx.caller,
+        tx.gas_limit,
+        tx.access_list.clone(),
+    );
+    if let Ok(result) = &result {
+        max_gas::check(&result.budgeted_calls);
+    }
+    result
+}
+
+/// Executes a sequence of transactions in order, possibly from different senders, committing each
+/// of them before the next one. A failed tx doesn't stop the sequence: like in a block, it still
+/// bumps the nonce of its sender and pays for its gas.
+pub fn send_txs(db: &mut InMemoryDB, txs: &[TxSpec], env: &ExecEnv) -> Vec<Result<TxResult>> {
+    txs.iter().map(|tx| send_tx(db, tx, env)).collect()
+}
 
-    match result {
+/// Nonce of an account, as tracked in the db. Every executed tx bumps the nonce of its sender
+/// (even if it reverts), while txs rejected before execution (e.g. for their intrinsic gas) don't.
+pub fn nonce_of(db: &InMemoryDB, addr: Address) -> u64 {
+    db.accounts
+        .get(&addr)
+        .map(|account| account.info.nonce)
+        .unwrap_or_default()
+}
+
+pub fn estimate_gas(db: &InMemoryDB, tx: &TxSpec) -> Result<u64> {
+    estimate_gas_with_env(db, tx, &ExecEnv::default())
+}
+
+/// Estimates the minimum gas limit that a call transaction succeeds with, up to `tx.gas_limit`.
+///
+/// Like `eth_estimateGas`, the call is run without committing (on a throwaway overlay of the db)
+/// and the gas limit is binary searched. As calls only forward 63/64 of the available gas, the gas
+/// used is not necessarily enough, so it is only used as the lower bound of the search. If the
+/// call fails even with the full gas limit, the failing result (with the revert payload, if any)
+/// is returned as an error.
+pub fn estimate_gas_with_env(db: &InMemoryDB, tx: &TxSpec, env: &ExecEnv) -> Result<u64> {
+    env.check_fees::<InMemoryDB>()?;
+    check_intrinsic_gas::<InMemoryDB>(
+        env.spec_id,
+        &tx.calldata,
+        false,
+        &tx.access_list,
+        tx.gas_limit,
+    )?;
+    let mut overlay = CacheDB::new(db);
+    let mut execute = |gas_limit: u64| -> Result<ExecutionResult> {
+        let mut evm = build_evm(
+            &mut overlay,
+            env,
+            tx.caller,
+            TransactTo::Call(tx.to),
+            tx.calldata.clone().into(),
+            tx.value,
+            gas_limit,
+            tx.access_list.clone(),
+        );
+        Ok(evm.transact()?.result)
+    };
+
+    let (gas_used, gas_refunded) = match execute(tx.gas_limit)? {
         ExecutionResult::Success {
-            reason: _,
             gas_used,
-            gas_refunded: _,
-            logs,
-            output: Output::Call(value),
+            gas_refunded,
             ..
-        } => {
-            debug!("Tx result: {:?}", value);
-            Ok(TxResult {
-                output: value.into(),
-                logs,
-                gas_used,
-                status: true,
-            })
+        } => (gas_used, gas_refunded),
+        result => return Err(Error::UnexpectedExecResult(result)),
+    };
+
+    // The gas spent before refunds is a lower bound
+    let mut lo = gas_used + gas_refunded - 1;
+    let mut hi = tx.gas_limit;
+
+    // Optimistically try with enough gas for a nested call to be forwarded what it needs
+    let optimistic = (gas_used + gas_refunded).saturating_mul(64) / 63;
+    if optimistic < hi {
+        if execute(optimistic)?.is_success() {
+            hi = optimistic;
+        } else {
+            lo = optimistic;
         }
-        result => Err(Error::UnexpectedExecResult(result)),
     }
+
+    while lo + 1 < hi {
+        let mid = lo + (hi - lo) / 2;
+        if execute(mid)?.is_success() {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+
+    debug!("[TX] ESTIMATED GAS: {}", hi);
+    Ok(hi)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_evm<'a, DB: Database>(
+    db: DB,
+    env: &ExecEnv,
+    caller: Address,
+    transact_to: TransactTo,
+    data: Bytes,
+    value: U256,
+    gas_limit: u64,
+    access_list: AccessList,
+) -> Evm<'a, (), DB> {
+    Evm::builder()
+        .with_db(db)
+        .modify_tx_env(|tx_env| {
+            tx_env.caller = caller;
+            tx_env.transact_to = transact_to;
+            tx_env.data = data;
+            tx_env.value = value;
+            tx_env.gas_price = env.max_fee_per_gas;
+            tx_env.gas_limit = gas_limit;
+            // Warmed by revm before the first frame, like the caller and the callee
+            tx_env.access_list = access_list.0;
+            // Legacy txs (and pre-London specs) pay their full gas price
+            if env.spec_id.is_enabled_in(SpecId::LONDON) {
+                tx_env.gas_priority_fee = env
+                    .max_priority_fee_per_gas
+                    .map(|priority_fee| priority_fee.min(env.max_fee_per_gas));
+            }
+        })
+        .modify_block_env(|block| *block = env.block().build(env.spec_id))
+        .modify_cfg_env(|cfg| cfg.limit_contract_code_size = Some(usize::MAX))
+        .with_spec_id(env.spec_id)
+        .append_handler_register(handle_register)
+        .build()
+}
+
+/// Fails early if the gas limit doesn't cover the intrinsic cost of the transaction.
+///
+/// The intrinsic gas itself is deducted by revm's validation handler before the first frame is
+/// executed, so it is always part of the reported `gas_used`.
+fn check_intrinsic_gas<DB: Database>(
+    spec_id: SpecId,
+    data: &[u8],
+    is_create: bool,
+    access_list: &[AccessListItem],
+    gas_limit: u64,
+) -> core::result::Result<(), Error<DB>>
+where
+    DB::Error: std::error::Error + 'static,
+{
+    let required =
+        gas::intrinsic_gas(spec_id, data, is_create) + gas::access_list_cost(spec_id, access_list);
+    debug!("[TX] INTRINSIC GAS: {}", required);
+
+    if required > gas_limit {
+        return Err(Error::IntrinsicGas {
+            required,
+            gas_limit,
+        });
+    }
+
+    Ok(())
 }
 
 #[derive(Debug)]
 struct RVEmu {
     emu: Emulator,
     created_address: Option<Address>,
+    // Calldata of the frame (the constructor args, for creates), served by `CallDataCopy`
+    calldata: Bytes,
+    // `#[max_gas]` budget of the called method, if any
+    budget: Option<GasBudget>,
+    // Header version of the running code, which selects the syscalls it can issue
+    abi_version: u8,
+    // Gas of the instructions the program runs before reaching its own code, which isn't charged
+    startup_cost: u64,
+    // ELF of the running code, to symbolize the backtraces of its failures
+    code: Bytes,
+    #[cfg(feature = "coverage")]
+    code_hash: B256,
+}
+
+// Frames count the instructions they ran once they're done
+impl Drop for RVEmu {
+    fn drop(&mut self) {
+        let instructions = self.emu.cpu.inst_counter.values().sum::<u64>();
+        INSTRUCTIONS.set(INSTRUCTIONS.get() + instructions);
+    }
 }
 
-fn riscv_context(frame: &Frame) -> Option<RVEmu> {
+/// Sets up the emulator for R55 frames. Bytecode with an unknown header version is rejected, so that
+/// it doesn't run under a syscall ABI or initcode layout it wasn't built for, while older versions
+/// run with the syscalls of their own ABI. So is truncated bytecode, and call data (or constructor
+/// args) that doesn't fit the calldata region of the emulator.
+fn riscv_context(frame: &Frame) -> core::result::Result<Option<RVEmu>, eth_riscv_syscalls::Error> {
     let interpreter = frame.interpreter();
 
-    let Some((0xFF, bytecode)) = interpreter.bytecode.split_first() else {
+    let Some((header, bytecode)) = Header::parse(&interpreter.bytecode)? else {
         warn!("NOT RISCV CONTRACT!");
-        return None;
+        return Ok(None);
     };
+    debug!("R55 bytecode version: {}", header.version);
+
+    let (code, calldata, budget, abi_version) = if frame.is_create() {
+        // R55 initcode: [header][codesize][deploy code][constructor_args]
+        use eth_riscv_syscalls::Error::Truncated;
+        let (code_size, init_code) = bytecode.split_at_checked(4).ok_or(Truncated)?;
+        let code_size = U32::from_be_slice(code_size).to::<usize>();
+        let end_of_args = init_code.len().checked_sub(BYTECODE_PADDING);
+        let (deploy_code, calldata) = end_of_args
+            .and_then(|end| Some((init_code.get(..code_size)?, init_code.get(code_size..end)?)))
+            .ok_or(Truncated)?;
 
-    let (code, calldata) = if frame.is_create() {
-        let (code_size, init_code) = bytecode.split_at(4);
-        let Some((0xFF, bytecode)) = init_code.split_first() else {
+        // The deploy code is what runs, so its own header sets the syscall ABI
+        let Some((deploy_header, code)) = Header::parse(deploy_code)? else {
             warn!("NOT RISCV CONTRACT!");
-            return None;
+            return Ok(None);
         };
-        let code_size = U32::from_be_slice(code_size).to::<usize>() - 1; // deduct control byte `0xFF`
-        let end_of_args = init_code.len() - 34; // deduct control byte + ignore empty (32 byte) word appended by revm
-
-        (&bytecode[..code_size], &bytecode[code_size..end_of_args])
+        (code, calldata, None, deploy_header.version)
     } else if frame.is_call() {
-        (bytecode, interpreter.contract.input.as_ref())
+        // Runtime code: [header][ELF][metadata], padded by revm. The trailer isn't loaded
+        let code_len = interpreter.contract.bytecode.original_byte_slice().len();
+        let code_len = code_len
+            .checked_sub(header.size())
+            .ok_or(eth_riscv_syscalls::Error::Truncated)?;
+        let code = Metadata::strip(&bytecode[..code_len]);
+        let calldata = interpreter.contract.input.as_ref();
+        let budget = Metadata::decode(&bytecode[..code_len])
+            .and_then(|metadata| metadata.max_gas_of(calldata.get(..4)?).cloned());
+        (code, calldata, budget, header.version)
     } else {
         todo!("Support EOF")
     };
+    if calldata.len() > MAX_CALLDATA_SIZE {
+        return Err(eth_riscv_syscalls::Error::CalldataTooLarge(calldata.len()));
+    }
 
-    match setup_from_elf(code, calldata) {
-        Ok(emu) => Some(RVEmu {
-            emu,
-            created_address: None,
-        }),
+    // Calls reuse the program of their code, and creates the one of their deploy code
+    let code_hash = match interpreter.contract.hash.filter(|_| frame.is_call()) {
+        Some(code_hash) => code_hash,
+        None => keccak256(code),
+    };
+    let emu = programs::get_or_load(code_hash, code)
+        .and_then(|loaded| Ok((loaded.program.instantiate(calldata)?, loaded.startup_cost)));
+    Ok(match emu {
+        Ok((mut emu, startup_cost)) => {
+            arm_stack_guard(&mut emu);
+            Some(RVEmu {
+                emu,
+                created_address: None,
+                // Shares the buffer of the frame's input (or initcode) rather than copying it
+                calldata: if calldata.is_empty() {
+                    Bytes::new()
+                } else if frame.is_create() {
+                    Bytes(interpreter.bytecode.0.slice_ref(calldata))
+                } else {
+                    interpreter.contract.input.clone()
+                },
+                budget,
+                abi_version,
+                startup_cost,
+                code: Bytes(interpreter.bytecode.0.slice_ref(code)),
+                #[cfg(feature = "coverage")]
+                code_hash: crate::coverage::register(code),
+            })
+        }
         Err(err) => {
             warn!("Failed to setup from ELF: {err}");
             None
         }
-    }
+    })
 }
 
 pub fn handle_register<EXT, DB: Database>(handler: &mut EvmHandler<'_, EXT, DB>) {
     trace!("HANDLE REGISTER");
+    let spec_id = handler.cfg.spec_id;
     let call_stack = Rc::<RefCell<Vec<_>>>::new(RefCell::new(Vec::new()));
 
+    // RISC-V frames are set up by revm's own call and create handlers, so that each of them runs
+    // within a checkpoint of the journal: its storage writes, logs, transfers and created accounts
+    // are discarded if it reverts or halts, even if its caller recovers from the failure.
+
     // create a riscv context on call frame.
     let call_stack_inner = call_stack.clone();
     let old_handle = handler.execution.call.clone();
@@ -199,27 +982,69 @@ pub fn handle_register<EXT, DB: Database>(handler: &mut EvmHandler<'_, EXT, DB>)
     let old_handle = handler.execution.execute_frame.clone();
     handler.execution.execute_frame = Arc::new(move |frame, memory, instraction_table, ctx| {
         let depth = call_stack.borrow().len() - 1;
+        let contract = &frame.interpreter().contract;
+        let span = debug_span!(
+            target: FRAMES_TARGET,
+            "frame",
+            depth,
+            address = %contract.target_address,
+            selector = %selectors::call_label(contract.hash.as_ref(), &contract.input),
+        );
+        let _entered = span.enter();
 
         // use last frame as stack is LIFO
-        let result = if let Some(Some(riscv_context)) = call_stack.borrow_mut().last_mut() {
-            debug!(
-                "=== [FRAME-{}] Contract: {} ============-",
-                depth,
-                frame.interpreter().contract.target_address,
-            );
-            execute_riscv(riscv_context, frame.interpreter_mut(), memory, ctx)?
-        } else {
-            debug!("=== [OLD Handler] ==================--");
-            old_handle(frame, memory, instraction_table, ctx)?
+        let result = match call_stack.borrow_mut().last_mut() {
+            Some(Ok(Some(riscv_context))) => {
+                debug!(
+                    "=== [FRAME-{}] Contract: {} ============-",
+                    depth,
+                    frame.interpreter().contract.target_address,
+                );
+                execute_riscv(riscv_context, frame.interpreter_mut(), memory, ctx, spec_id)?
+            }
+            // Code that can't run (e.g. of an unknown version, or EVM code that happens to start
+            // with the magic byte) only fails its own frame, which its caller can recover from
+            Some(Err(err)) => {
+                warn!("INVALID R55 BYTECODE > {}", err);
+                let result = match err {
+                    eth_riscv_syscalls::Error::Truncated => InstructionResult::OpcodeNotFound,
+                    eth_riscv_syscalls::Error::CalldataTooLarge(_) => {
+                        InstructionResult::MemoryLimitOOG
+                    }
+                    _ => InstructionResult::NotActivated,
+                };
+                InterpreterAction::Return {
+                    result: InterpreterResult {
+                        result,
+                        output: Bytes::new(),
+                        gas: frame.interpreter().gas,
+                    },
+                }
+            }
+            _ => {
+                debug!("=== [OLD Handler] ==================--");
+                old_handle(frame, memory, instraction_table, ctx)?
+            }
         };
 
-        // if action is return, pop the stack and potentially cache created address.
+        // if action is return, pop the stack. The created address isn't cached in the parent here,
+        // as a frame's address is known even if its creation fails: the parent reads it from the
+        // create outcome instead, which is zero on failure (see `execute_riscv`). This holds for
+        // creates issued by a runtime frame as well as by a constructor.
         if result.is_return() {
-            let mut stack = call_stack.borrow_mut();
-            stack.pop();
-
-            if let Some(Some(parent)) = stack.last_mut() {
-                parent.created_address = frame.created_address()
+            // Calls of budgeted methods are recorded whatever their outcome
+            let budget = call_stack
+                .borrow_mut()
+                .pop()
+                .and_then(|context| context.ok().flatten())
+                .and_then(|mut rvemu| rvemu.budget.take());
+            if let (Some(budget), InterpreterAction::Return { result }) = (budget, &result) {
+                max_gas::record(BudgetedCall {
+                    address: frame.interpreter().contract.target_address,
+                    method: budget.name,
+                    max_gas: budget.max_gas,
+                    gas_used: result.gas.spent(),
+                });
             }
         }
 
@@ -233,6 +1058,7 @@ fn execute_riscv(
     interpreter: &mut Interpreter,
     _shared_memory: &mut SharedMemory,
     host: &mut dyn Host,
+    spec_id: SpecId,
 ) -> Result<InterpreterAction> {
     trace!(
         "{} RISC-V execution:  PC: {:#x}",
@@ -244,9 +1070,26 @@ fn execute_riscv(
         rvemu.emu.cpu.pc,
     );
 
+    let abi_version = rvemu.abi_version;
     let emu = &mut rvemu.emu;
     emu.cpu.is_count = true;
 
+    // When resuming after a call or create, revm has pushed its outcome to the stack. Calls get
+    // their success flag back in a0, while the created address (zero if the create failed) is
+    // read with its own syscall.
+    if let Ok(outcome) = interpreter.stack.pop() {
+        let t0: u64 = emu.cpu.xregs.read(5);
+        match Syscall::decode(abi_version, t0) {
+            Some(Syscall::Call | Syscall::StaticCall | Syscall::DelegateCall) => {
+                emu.cpu.xregs.write(10, outcome.as_limbs()[0])
+            }
+            Some(Syscall::Create | Syscall::Create2) => {
+                rvemu.created_address = Some(Address::from_word(outcome.into()))
+            }
+            _ => {}
+        }
+    }
+
     let return_revert = |interpreter: &mut Interpreter, gas_used: u64| {
         let _ = interpreter.gas.record_cost(gas_used);
         Ok(InterpreterAction::Return {
@@ -259,37 +1102,87 @@ fn execute_riscv(
         })
     };
 
+    // Exceptional halt of the frame, which consumes its remaining gas
+    let halt = |interpreter: &mut Interpreter, result: InstructionResult| {
+        Ok(InterpreterAction::Return {
+            result: InterpreterResult {
+                result,
+                output: Bytes::new(),
+                gas: interpreter.gas,
+            },
+        })
+    };
+
     // Run emulator and capture ecalls
     loop {
+        #[cfg(feature = "coverage")]
+        let run_result = crate::coverage::start(emu, rvemu.code_hash);
+        #[cfg(not(feature = "coverage"))]
         let run_result = emu.start();
         match run_result {
             Err(Exception::EnvironmentCallFromMMode) => {
                 let t0: u64 = emu.cpu.xregs.read(5);
 
-                let Ok(syscall) = Syscall::try_from(t0 as u8) else {
-                    warn!("Unhandled syscall: {:?}", t0);
-                    return return_revert(interpreter, interpreter.gas.spent());
+                // Unknown ids (e.g. from a newer toolchain, or newer than the code's header
+                // version) halt the frame, like unknown opcodes
+                let Some(syscall) = Syscall::decode(abi_version, t0) else {
+                    warn!("UNKNOWN SYSCALL > {:#x} at PC {:#x}", t0, emu.cpu.pc);
+                    failure::record(FrameFailure::UnknownSyscall {
+                        address: interpreter.contract.target_address,
+                        id: t0,
+                        pc: emu.cpu.pc,
+                    });
+                    return halt(interpreter, InstructionResult::OpcodeNotFound);
                 };
                 debug!("[Syscall::{} - {:#04x}]", syscall, t0);
+                SYSCALL_COUNTS.with_borrow_mut(|counts| *counts.entry(syscall).or_default() += 1);
+                let span = trace_span!(
+                    target: SYSCALLS_TARGET,
+                    "syscall",
+                    name = %syscall,
+                    key = field::Empty,
+                    account = field::Empty,
+                );
+                let _entered = span.enter();
+
+                // Checked before the syscall runs, as the overflow may have corrupted its inputs
+                if stack_overflowed(emu) {
+                    warn!(
+                        "STACK OVERFLOW > at PC {:#x}, before {}",
+                        emu.cpu.pc, syscall
+                    );
+                    return halt(interpreter, InstructionResult::StackOverflow);
+                }
+
+                // Syscalls of a later hardfork halt the frame, like opcodes that aren't activated
+                let required = activation_spec(syscall);
+                if !spec_id.is_enabled_in(required) {
+                    warn!(
+                        "INACTIVE SYSCALL > {} requires {:?}, but the active spec is {:?}",
+                        syscall, required, spec_id
+                    );
+                    return halt(interpreter, InstructionResult::NotActivated);
+                }
 
                 match syscall {
                     Syscall::Return => {
                         let ret_offset: u64 = emu.cpu.xregs.read(10);
                         let ret_size: u64 = emu.cpu.xregs.read(11);
 
-                        let r55_gas = r55_gas_used(&emu.cpu.inst_counter);
+                        let r55_gas = r55_gas_used(&emu.cpu.inst_counter, rvemu.startup_cost);
                         debug!("> Total R55 gas: {}", r55_gas);
 
                         // RETURN logs the gas of the whole risc-v instruction set
                         syscall_gas!(interpreter, r55_gas);
 
-                        let data_bytes = dram_slice(emu, ret_offset, ret_size)?;
-                        trace!("> RETURN: {}", Bytes::from(data_bytes.to_vec()));
+                        // Read straight out of the emulator's memory, into the frame's output
+                        let output = Bytes::copy_from_slice(dram_slice(emu, ret_offset, ret_size)?);
+                        trace!("> RETURN: {}", output);
 
                         return Ok(InterpreterAction::Return {
                             result: InterpreterResult {
                                 result: InstructionResult::Return,
-                                output: data_bytes.to_vec().into(),
+                                output,
                                 gas: interpreter.gas, // FIXME: gas is not correct
                             },
                         });
@@ -300,6 +1193,7 @@ fn execute_riscv(
                         let key3: u64 = emu.cpu.xregs.read(12);
                         let key4: u64 = emu.cpu.xregs.read(13);
                         let key = U256::from_limbs([key1, key2, key3, key4]);
+                        span.record("key", field::display(key));
                         debug!(
                             "> SLOAD ({}) - Key: {:#02x}",
                             interpreter.contract.target_address, key
@@ -317,11 +1211,7 @@ fn execute_riscv(
                                 emu.cpu.xregs.write(13, limbs[3]);
                                 syscall_gas!(
                                     interpreter,
-                                    if state_load.is_cold {
-                                        gas::SLOAD_COLD
-                                    } else {
-                                        gas::SLOAD_WARM
-                                    }
+                                    gas::sload_cost(spec_id, state_load.is_cold)
                                 );
                             }
                             _ => {
@@ -330,11 +1220,20 @@ fn execute_riscv(
                         }
                     }
                     Syscall::SStore => {
+                        // Otherwise, the write would land in the journal and outlive the staticcall
+                        if interpreter.is_static {
+                            return halt(
+                                interpreter,
+                                InstructionResult::StateChangeDuringStaticCall,
+                            );
+                        }
+
                         let key1: u64 = emu.cpu.xregs.read(10);
                         let key2: u64 = emu.cpu.xregs.read(11);
                         let key3: u64 = emu.cpu.xregs.read(12);
                         let key4: u64 = emu.cpu.xregs.read(13);
                         let key = U256::from_limbs([key1, key2, key3, key4]);
+                        span.record("key", field::display(key));
                         debug!(
                             "> SSTORE ({}) - Key: {}",
                             interpreter.contract.target_address, key
@@ -350,17 +1249,224 @@ fn execute_riscv(
                             interpreter.contract.target_address, value
                         );
 
-                        let result = host.sstore(interpreter.contract.target_address, key, value);
-                        if let Some(result) = result {
-                            syscall_gas!(
+                        // Writes go through the journal, so that they are visible to the frames
+                        // below (e.g. reentrant calls) and are rolled back if this frame reverts
+                        match host.sstore(interpreter.contract.target_address, key, value) {
+                            Some(result) => {
+                                syscall_gas!(
+                                    interpreter,
+                                    gas::sstore_cost(spec_id, &result.data, result.is_cold)
+                                );
+                            }
+                            None => {
+                                return return_revert(interpreter, interpreter.gas.spent());
+                            }
+                        }
+                    }
+                    Syscall::SLoadMany => {
+                        let keys_offset: u64 = emu.cpu.xregs.read(10);
+                        let count: u64 = emu.cpu.xregs.read(11);
+                        let values_offset: u64 = emu.cpu.xregs.read(12);
+                        debug!(
+                            "> SLOADMANY ({}) - Keys: {}",
+                            interpreter.contract.target_address, count
+                        );
+
+                        let Some(keys) = read_words(emu, keys_offset, count) else {
+                            warn!(
+                                "> SLOADMANY keys out of memory [offset: {}, count: {}]",
+                                keys_offset, count
+                            );
+                            return halt(interpreter, InstructionResult::MemoryLimitOOG);
+                        };
+
+                        // Each key is charged as its own `SLOAD`
+                        let mut values = Vec::with_capacity(keys.len());
+                        for key in keys {
+                            let Some(state_load) =
+                                host.sload(interpreter.contract.target_address, key)
+                            else {
+                                return return_revert(interpreter, interpreter.gas.spent());
+                            };
+                            trace!(
+                                "> SLOADMANY - Key: {:#02x}, Value: {}",
+                                key,
+                                state_load.data
+                            );
+                            syscall_gas!(interpreter, gas::sload_cost(spec_id, state_load.is_cold));
+                            values.push(state_load.data);
+                        }
+
+                        if !write_words(emu, values_offset, &values) {
+                            warn!(
+                                "> SLOADMANY values out of memory [offset: {}, count: {}]",
+                                values_offset, count
+                            );
+                            return halt(interpreter, InstructionResult::MemoryLimitOOG);
+                        }
+                    }
+                    Syscall::SStoreMany => {
+                        if interpreter.is_static {
+                            return halt(
                                 interpreter,
-                                if result.is_cold {
-                                    gas::SSTORE_COLD
-                                } else {
-                                    gas::SSTORE_WARM
+                                InstructionResult::StateChangeDuringStaticCall,
+                            );
+                        }
+
+                        let entries_offset: u64 = emu.cpu.xregs.read(10);
+                        let count: u64 = emu.cpu.xregs.read(11);
+                        debug!(
+                            "> SSTOREMANY ({}) - Entries: {}",
+                            interpreter.contract.target_address, count
+                        );
+
+                        let Some(words) = count
+                            .checked_mul(2)
+                            .and_then(|words| read_words(emu, entries_offset, words))
+                        else {
+                            warn!(
+                                "> SSTOREMANY entries out of memory [offset: {}, count: {}]",
+                                entries_offset, count
+                            );
+                            return halt(interpreter, InstructionResult::MemoryLimitOOG);
+                        };
+
+                        // Each entry is charged as its own `SSTORE`, in order
+                        for entry in words.chunks_exact(2) {
+                            let (key, value) = (entry[0], entry[1]);
+                            trace!("> SSTOREMANY - Key: {}, Value: {}", key, value);
+                            match host.sstore(interpreter.contract.target_address, key, value) {
+                                Some(result) => {
+                                    syscall_gas!(
+                                        interpreter,
+                                        gas::sstore_cost(spec_id, &result.data, result.is_cold)
+                                    );
+                                }
+                                None => {
+                                    return return_revert(interpreter, interpreter.gas.spent());
                                 }
+                            }
+                        }
+                    }
+                    Syscall::ExtCodeSize => {
+                        let a0: u64 = emu.cpu.xregs.read(10);
+                        let a1: u64 = emu.cpu.xregs.read(11);
+                        let a2: u64 = emu.cpu.xregs.read(12);
+                        let addr = Address::from_word(U256::from_limbs([a0, a1, a2, 0]).into());
+                        span.record("account", field::display(addr));
+
+                        let (size, is_cold) = match host.code(addr) {
+                            Some(code) => (code.len() as u64, code.is_cold),
+                            None => (0, true),
+                        };
+                        syscall_gas!(interpreter, gas::extcodesize_cost(spec_id, is_cold));
+                        debug!("> EXTCODESIZE ({}): {}", addr, size);
+
+                        emu.cpu.xregs.write(10, size);
+                    }
+                    Syscall::SelfBalance => {
+                        syscall_gas!(interpreter, gas::SELFBALANCE);
+                        let this = interpreter.contract.target_address;
+                        let balance = host.balance(this).map(|b| b.data).unwrap_or_default();
+                        debug!("> SELFBALANCE ({}): {}", this, balance);
+
+                        let limbs = balance.as_limbs();
+                        emu.cpu.xregs.write(10, limbs[0]);
+                        emu.cpu.xregs.write(11, limbs[1]);
+                        emu.cpu.xregs.write(12, limbs[2]);
+                        emu.cpu.xregs.write(13, limbs[3]);
+                    }
+                    Syscall::TLoad => {
+                        let key1: u64 = emu.cpu.xregs.read(10);
+                        let key2: u64 = emu.cpu.xregs.read(11);
+                        let key3: u64 = emu.cpu.xregs.read(12);
+                        let key4: u64 = emu.cpu.xregs.read(13);
+                        let key = U256::from_limbs([key1, key2, key3, key4]);
+                        span.record("key", field::display(key));
+                        syscall_gas!(interpreter, gas::TLOAD);
+
+                        let value = host.tload(interpreter.contract.target_address, key);
+                        debug!(
+                            "> TLOAD ({}) - Key: {:#02x}, Value: {}",
+                            interpreter.contract.target_address, key, value
+                        );
+
+                        let limbs = value.as_limbs();
+                        emu.cpu.xregs.write(10, limbs[0]);
+                        emu.cpu.xregs.write(11, limbs[1]);
+                        emu.cpu.xregs.write(12, limbs[2]);
+                        emu.cpu.xregs.write(13, limbs[3]);
+                    }
+                    Syscall::TStore => {
+                        // Otherwise, the write would land in the journal and outlive the staticcall
+                        if interpreter.is_static {
+                            return halt(
+                                interpreter,
+                                InstructionResult::StateChangeDuringStaticCall,
                             );
                         }
+
+                        let key1: u64 = emu.cpu.xregs.read(10);
+                        let key2: u64 = emu.cpu.xregs.read(11);
+                        let key3: u64 = emu.cpu.xregs.read(12);
+                        let key4: u64 = emu.cpu.xregs.read(13);
+                        let key = U256::from_limbs([key1, key2, key3, key4]);
+                        span.record("key", field::display(key));
+
+                        let val1: u64 = emu.cpu.xregs.read(14);
+                        let val2: u64 = emu.cpu.xregs.read(15);
+                        let val3: u64 = emu.cpu.xregs.read(16);
+                        let val4: u64 = emu.cpu.xregs.read(17);
+                        let value = U256::from_limbs([val1, val2, val3, val4]);
+                        syscall_gas!(interpreter, gas::TSTORE);
+
+                        debug!(
+                            "> TSTORE ({}) - Key: {:#02x}, Value: {}",
+                            interpreter.contract.target_address, key, value
+                        );
+                        host.tstore(interpreter.contract.target_address, key, value);
+                    }
+                    Syscall::CallDataSize => {
+                        let size = rvemu.calldata.len();
+                        debug!("> CALLDATASIZE: {}", size);
+                        emu.cpu.xregs.write(10, size as u64);
+                    }
+                    Syscall::CallDataCopy => {
+                        let dest_offset = emu.cpu.xregs.read(10);
+                        let offset = emu.cpu.xregs.read(11);
+                        let size = emu.cpu.xregs.read(12);
+                        syscall_gas!(
+                            interpreter,
+                            gas::copy_cost(size).saturating_add(gas::memory_cost(size))
+                        );
+                        debug!(
+                            "> CALLDATACOPY [memory_offset: {}, offset: {}, size: {}]",
+                            dest_offset, offset, size
+                        );
+                        if size == 0 {
+                            continue;
+                        }
+
+                        let Some(memory) = dest_offset
+                            .checked_add(size)
+                            .and_then(|end| emu.cpu.bus.get_dram_slice(dest_offset..end).ok())
+                            .filter(|memory| memory.len() as u64 == size)
+                        else {
+                            warn!(
+                                "> CALLDATACOPY destination out of memory [offset: {}, size: {}]",
+                                dest_offset, size
+                            );
+                            return halt(interpreter, InstructionResult::MemoryLimitOOG);
+                        };
+
+                        // Unlike the return data, reading past the end of the calldata is allowed
+                        let calldata = rvemu.calldata.as_ref();
+                        let start = usize::try_from(offset)
+                            .map_or(calldata.len(), |offset| offset.min(calldata.len()));
+                        let data = &calldata[start..calldata.len().min(start + memory.len())];
+                        let (copied, padding) = memory.split_at_mut(data.len());
+                        copied.copy_from_slice(data);
+                        padding.fill(0);
                     }
                     Syscall::ReturnDataSize => {
                         let size = interpreter.return_data_buffer.len();
@@ -370,9 +1476,25 @@ fn execute_riscv(
                     Syscall::ReturnDataCopy => {
                         trace!("> RETURNDATA BUFFER: {}", &interpreter.return_data_buffer);
                         let dest_offset = emu.cpu.xregs.read(10);
-                        let offset = emu.cpu.xregs.read(11) as usize;
-                        let size = emu.cpu.xregs.read(12) as usize;
-                        let data = &interpreter.return_data_buffer.as_ref()[offset..offset + size];
+                        let offset = emu.cpu.xregs.read(11);
+                        let size = emu.cpu.xregs.read(12);
+                        syscall_gas!(
+                            interpreter,
+                            gas::copy_cost(size).saturating_add(gas::memory_cost(size))
+                        );
+
+                        // Reading past the end of the buffer is an exceptional halt, as in the EVM
+                        let buffer = interpreter.return_data_buffer.as_ref();
+                        let Some(range) = return_data_range(buffer.len(), offset, size) else {
+                            warn!(
+                                "> RETURNDATACOPY out of bounds [offset: {}, size: {}, buffer: {}]",
+                                offset,
+                                size,
+                                buffer.len()
+                            );
+                            return halt(interpreter, InstructionResult::OutOfOffset);
+                        };
+                        let data = &buffer[range];
                         debug!(
                             "> RETURNDATACOPY [memory_offset: {}, offset: {}, size: {}]\n{}",
                             dest_offset,
@@ -380,17 +1502,60 @@ fn execute_riscv(
                             size,
                             Bytes::from(data.to_vec())
                         );
+                        if data.is_empty() {
+                            continue;
+                        }
 
-                        // write return data to memory
-                        let return_memory = emu
-                            .cpu
-                            .bus
-                            .get_dram_slice(dest_offset..(dest_offset + size as u64))?;
-                        return_memory.copy_from_slice(data);
+                        // write return data to memory, which must fit in the emulator's DRAM
+                        let Some(memory) = dest_offset
+                            .checked_add(size)
+                            .and_then(|end| emu.cpu.bus.get_dram_slice(dest_offset..end).ok())
+                            .filter(|memory| memory.len() == data.len())
+                        else {
+                            warn!(
+                                "> RETURNDATACOPY destination out of memory [offset: {}, size: {}]",
+                                dest_offset, size
+                            );
+                            return halt(interpreter, InstructionResult::MemoryLimitOOG);
+                        };
+                        memory.copy_from_slice(data);
+                    }
+                    Syscall::Call => {
+                        return execute_call(
+                            emu,
+                            interpreter,
+                            host,
+                            spec_id,
+                            abi_version,
+                            CallScheme::Call,
+                        )
+                    }
+                    Syscall::StaticCall => {
+                        return execute_call(
+                            emu,
+                            interpreter,
+                            host,
+                            spec_id,
+                            abi_version,
+                            CallScheme::StaticCall,
+                        )
+                    }
+                    Syscall::DelegateCall => {
+                        return execute_call(
+                            emu,
+                            interpreter,
+                            host,
+                            spec_id,
+                            abi_version,
+                            CallScheme::DelegateCall,
+                        )
+                    }
+                    Syscall::Create => {
+                        return execute_create(emu, interpreter, host, spec_id, false)
+                    }
+                    Syscall::Create2 => {
+                        return execute_create(emu, interpreter, host, spec_id, true)
                     }
-                    Syscall::Call => return execute_call(emu, interpreter, host, false),
-                    Syscall::StaticCall => return execute_call(emu, interpreter, host, true),
-                    Syscall::Create => return execute_create(emu, interpreter, host),
                     Syscall::ReturnCreateAddress => {
                         debug!("> RETURNCREATEDADDRESS: {:?}", &rvemu.created_address);
                         let dest_offset = emu.cpu.xregs.read(10);
@@ -405,16 +1570,42 @@ fn execute_riscv(
                             .get_dram_slice(dest_offset..(dest_offset + 20_u64))?;
                         return_memory.copy_from_slice(addr.as_slice());
                     }
-                    Syscall::Revert => {
+                    Syscall::Revert | Syscall::Panic => {
                         let ret_offset: u64 = emu.cpu.xregs.read(10);
                         let ret_size: u64 = emu.cpu.xregs.read(11);
-                        let data_bytes: Vec<u8> = dram_slice(emu, ret_offset, ret_size)?.into();
+                        let data_bytes =
+                            Bytes::copy_from_slice(dram_slice(emu, ret_offset, ret_size)?);
                         debug!("REVERT > offset: {:#04x}, size: {}", ret_offset, ret_size);
+                        let (address, output) =
+                            (interpreter.contract.target_address, data_bytes.clone());
+                        failure::record(match syscall {
+                            Syscall::Panic => {
+                                let backtrace =
+                                    symbols::capture(emu, rvemu.code.clone(), R5_REST_OF_RAM_INIT);
+                                warn!(target: FRAMES_TARGET, "PANIC > backtrace:{}", backtrace);
+                                FrameFailure::Panic {
+                                    address,
+                                    output,
+                                    backtrace,
+                                }
+                            }
+                            _ => FrameFailure::Revert { address, output },
+                        });
+                        match panic_code(&data_bytes) {
+                            Some(PANIC_ALLOCATION_FAILURE) => {
+                                warn!(
+                                    "REVERT > allocation failure: Panic({:#04x})",
+                                    PANIC_ALLOCATION_FAILURE
+                                )
+                            }
+                            Some(code) => debug!("REVERT > Panic({:#04x})", code),
+                            None => {}
+                        }
 
                         return Ok(InterpreterAction::Return {
                             result: InterpreterResult {
                                 result: InstructionResult::Revert,
-                                output: Bytes::from(data_bytes),
+                                output: data_bytes,
                                 gas: interpreter.gas, // FIXME: gas is not correct
                             },
                         });
@@ -452,6 +1643,12 @@ fn execute_riscv(
                     Syscall::Keccak256 => {
                         let ret_offset: u64 = emu.cpu.xregs.read(10);
                         let ret_size: u64 = emu.cpu.xregs.read(11);
+                        syscall_gas!(
+                            interpreter,
+                            gas::keccak256_cost(ret_size)
+                                .saturating_add(gas::memory_cost(ret_size))
+                        );
+
                         let data_bytes = dram_slice(emu, ret_offset, ret_size)?;
 
                         let mut hasher = Keccak256::new();
@@ -481,6 +1678,53 @@ fn execute_riscv(
                         emu.cpu.xregs.write(12, limbs[2]);
                         emu.cpu.xregs.write(13, limbs[3]);
                     }
+                    Syscall::BlobBaseFee => {
+                        let value = U256::from(host.env().block.get_blob_gasprice().unwrap_or(0));
+                        let limbs = value.as_limbs();
+                        emu.cpu.xregs.write(10, limbs[0]);
+                        emu.cpu.xregs.write(11, limbs[1]);
+                        emu.cpu.xregs.write(12, limbs[2]);
+                        emu.cpu.xregs.write(13, limbs[3]);
+                    }
+                    Syscall::PrevRandao => {
+                        let block = &host.env().block;
+                        let value = if spec_id.is_enabled_in(SpecId::MERGE) {
+                            U256::from_be_bytes(block.prevrandao.unwrap_or_default().0)
+                        } else {
+                            block.difficulty
+                        };
+                        let limbs = value.as_limbs();
+                        emu.cpu.xregs.write(10, limbs[0]);
+                        emu.cpu.xregs.write(11, limbs[1]);
+                        emu.cpu.xregs.write(12, limbs[2]);
+                        emu.cpu.xregs.write(13, limbs[3]);
+                    }
+                    Syscall::Coinbase => {
+                        let coinbase = host.env().block.coinbase;
+
+                        // Break address into 3 u64s and write to registers
+                        let coinbase_bytes = coinbase.as_slice();
+                        let first_u64 = u64::from_be_bytes(coinbase_bytes[0..8].try_into()?);
+                        emu.cpu.xregs.write(10, first_u64);
+                        let second_u64 = u64::from_be_bytes(coinbase_bytes[8..16].try_into()?);
+                        emu.cpu.xregs.write(11, second_u64);
+                        let mut padded_bytes = [0u8; 8];
+                        padded_bytes[..4].copy_from_slice(&coinbase_bytes[16..20]);
+                        let third_u64 = u64::from_be_bytes(padded_bytes);
+                        emu.cpu.xregs.write(12, third_u64);
+                    }
+                    Syscall::Context => {
+                        let dest_offset = emu.cpu.xregs.read(10);
+                        syscall_gas!(interpreter, gas::memory_cost(CONTEXT_SIZE as u64));
+
+                        let context = encode_context(host);
+                        debug!("> CONTEXT: {}", Bytes::from(context.to_vec()));
+                        let context_memory = emu
+                            .cpu
+                            .bus
+                            .get_dram_slice(dest_offset..(dest_offset + CONTEXT_SIZE as u64))?;
+                        context_memory.copy_from_slice(&context);
+                    }
                     Syscall::ChainId => {
                         let value = host.env().cfg.chain_id;
                         emu.cpu.xregs.write(10, value);
@@ -509,8 +1753,18 @@ fn execute_riscv(
                         emu.cpu.xregs.write(12, limbs[2]);
                         emu.cpu.xregs.write(13, limbs[3]);
                     }
+                    Syscall::Gas => {
+                        syscall_gas!(interpreter, gas::GAS);
+                        // The instructions run so far are only charged at RETURN, so they are
+                        // subtracted here for the gas left to be what the frame can still spend
+                        let r55_gas = r55_gas_used(&emu.cpu.inst_counter, rvemu.startup_cost);
+                        let gas_left = interpreter.gas.remaining().saturating_sub(r55_gas);
+                        debug!("> GAS: {}", gas_left);
+
+                        emu.cpu.xregs.write(10, gas_left);
+                    }
                     Syscall::GasPrice => {
-                        let value = host.env().tx.gas_price;
+                        let value = host.env().effective_gas_price();
                         let limbs = value.as_limbs();
                         emu.cpu.xregs.write(10, limbs[0]);
                         emu.cpu.xregs.write(11, limbs[1]);
@@ -540,6 +1794,16 @@ fn execute_riscv(
                         let data_size: u64 = emu.cpu.xregs.read(11);
                         let topics_ptr: u64 = emu.cpu.xregs.read(12);
                         let topics_size: u64 = emu.cpu.xregs.read(13);
+                        // Like the EVM, which has no opcode past LOG4
+                        if topics_size > 4 {
+                            warn!("> LOG with {} topics", topics_size);
+                            return halt(interpreter, InstructionResult::OpcodeNotFound);
+                        }
+                        syscall_gas!(
+                            interpreter,
+                            gas::log_cost(topics_size, data_size)
+                                .saturating_add(gas::memory_cost(data_size))
+                        );
 
                         // Read data
                         let data = if data_size == 0 {
@@ -581,29 +1845,67 @@ fn execute_riscv(
                 continue;
             }
             Err(e) => {
-                debug!("Execution error: {:#?}", e);
-                syscall_gas!(interpreter, r55_gas_used(&emu.cpu.inst_counter));
-                return return_revert(interpreter, interpreter.gas.spent());
+                let registers = core::array::from_fn(|i| emu.cpu.xregs.read(i as u64));
+                let backtrace = symbols::capture(emu, rvemu.code.clone(), R5_REST_OF_RAM_INIT);
+                warn!(target: FRAMES_TARGET, "TRAP > backtrace:{}", backtrace);
+                let trap = Trap::new(
+                    interpreter.contract.target_address,
+                    e,
+                    emu.cpu.pc,
+                    registers,
+                    backtrace,
+                );
+                failure::record(FrameFailure::Trap(Box::new(trap)));
+                if stack_overflowed(emu) {
+                    warn!("STACK OVERFLOW > at PC {:#x}: {:?}", emu.cpu.pc, e);
+                    return halt(interpreter, InstructionResult::StackOverflow);
+                }
+
+                // Unlike reverts, faults are exceptional halts, which consume all the gas
+                warn!(
+                    "TRAP > RISC-V exception at PC {:#x}: {:?}\n> registers: {:x?}",
+                    emu.cpu.pc, e, registers
+                );
+                return halt(interpreter, InstructionResult::InvalidFEOpcode);
             }
         }
     }
 }
 
+/// Value of a `Call` or `StaticCall`: a 256-bit word whose limbs are in `a3`, `a7`, `t1` and `t2`,
+/// or a 64-bit one in `a3` for code built before `WIDE_CALL_VALUE`.
+fn call_value(emu: &Emulator, abi_version: u8) -> U256 {
+    let low = emu.cpu.xregs.read(13);
+    if abi_version < WIDE_CALL_VALUE {
+        return U256::from(low);
+    }
+    let high = [17, 6, 7].map(|reg| emu.cpu.xregs.read(reg));
+    U256::from_limbs([low, high[0], high[1], high[2]])
+}
+
 fn execute_call(
     emu: &mut Emulator,
     interpreter: &mut Interpreter,
     host: &mut dyn Host,
-    is_static: bool,
+    spec_id: SpecId,
+    abi_version: u8,
+    scheme: CallScheme,
 ) -> Result<InterpreterAction> {
     let a0: u64 = emu.cpu.xregs.read(10);
     let a1: u64 = emu.cpu.xregs.read(11);
     let a2: u64 = emu.cpu.xregs.read(12);
     let addr = Address::from_word(U256::from_limbs([a0, a1, a2, 0]).into());
-    let value: u64 = emu.cpu.xregs.read(13);
+    Span::current().record("account", field::display(addr));
+    // Delegatecalls keep the value of the current call, without transferring it again
+    let value = match scheme {
+        CallScheme::DelegateCall => CallValue::Apparent(interpreter.contract.call_value),
+        _ => CallValue::Transfer(call_value(emu, abi_version)),
+    };
 
     // Get calldata
     let args_offset: u64 = emu.cpu.xregs.read(14);
     let args_size: u64 = emu.cpu.xregs.read(15);
+    syscall_gas!(interpreter, gas::memory_cost(args_size));
     let calldata: Bytes = emu
         .cpu
         .bus
@@ -615,38 +1917,76 @@ fn execute_call(
     // Calculate gas cost of the call
     // TODO: check correctness (tried using evm.codes as ref but i'm no gas wizard)
     // TODO: unsure whether memory expansion cost is missing (should be captured in the risc-v costs)
-    let (empty_account_cost, addr_access_cost) = match host.load_account_delegated(addr) {
-        Some(account) => {
-            if account.is_cold {
-                (0, gas::CALL_NEW_ACCOUNT)
-            } else {
-                (0, gas::CALL_BASE)
-            }
+    let Some(account) = host.load_account_delegated(addr) else {
+        return Ok(InterpreterAction::Return {
+            result: InterpreterResult {
+                result: InstructionResult::FatalExternalError,
+                output: Bytes::new(),
+                gas: interpreter.gas,
+            },
+        });
+    };
+    let transfers_value = matches!(value, CallValue::Transfer(value) if !value.is_zero());
+    // Calls to codeless accounts (EOAs or empty ones) don't get a frame: revm settles them right
+    // away, transferring the value and leaving empty return data. Creating an account by sending it
+    // value costs extra, as does touching an empty one before Spurious Dragon
+    let empty_account_cost = match account.is_empty {
+        true if transfers_value || !spec_id.is_enabled_in(SpecId::SPURIOUS_DRAGON) => {
+            gas::CALL_EMPTY_ACCOUNT
         }
-        None => (gas::CALL_EMPTY_ACCOUNT, gas::CALL_NEW_ACCOUNT),
+        _ => 0,
     };
-    let value_cost = if value != 0 { gas::CALL_VALUE } else { 0 };
+    let addr_access_cost = gas::call_access_cost(spec_id, account.is_cold);
+    let value_cost = if transfers_value { gas::CALL_VALUE } else { 0 };
     let call_gas_cost = empty_account_cost + addr_access_cost + value_cost;
     syscall_gas!(interpreter, call_gas_cost);
 
     // proactively spend gas limit as the remaining will be refunded (otherwise it underflows)
-    let call_gas_limit = interpreter.gas.remaining();
+    // A cap of zero forwards all the gas, for which code built before `ZERO_GAS_CAP` passes the
+    // largest cap instead
+    let gas_cap = match emu.cpu.xregs.read(16) {
+        0 if abi_version >= ZERO_GAS_CAP => u64::MAX,
+        gas_cap => gas_cap,
+    };
+    let call_gas_limit = forwarded_gas(spec_id, interpreter.gas.remaining()).min(gas_cap);
     syscall_gas!(interpreter, call_gas_limit);
 
-    debug!("> {}Call context:", if is_static { "Static" } else { "" });
-    debug!("  - Caller: {}", interpreter.contract.target_address);
-    debug!("  - Target Address: {}", addr);
-    debug!("  - Value: {}", value);
+    // NOTE: the balance of the caller isn't checked here, as revm does it before creating the frame
+    // of the callee: if it can't cover the value, the call fails with `OutOfFunds` and no data, the
+    // callee doesn't run, the journal checkpoint of the transfer is reverted, and the forwarded gas
+    // is given back. Like any other failed call, the caller resumes with a0 = 0.
+
+    // Delegatecalls run the code of `addr` in the current context: same storage, caller and
+    // value, and static if the current call is
+    let (target_address, caller, is_static) = match scheme {
+        CallScheme::DelegateCall => (
+            interpreter.contract.target_address,
+            interpreter.contract.caller,
+            interpreter.is_static,
+        ),
+        _ => (
+            addr,
+            interpreter.contract.target_address,
+            scheme == CallScheme::StaticCall,
+        ),
+    };
+
+    debug!("> {:?} context:", scheme);
+    debug!("  - Caller: {}", caller);
+    debug!("  - Target Address: {}", target_address);
+    debug!("  - Code Address: {}", addr);
+    debug!("  - Value: {}", value.get());
+    debug!("  - Gas Limit: {}", call_gas_limit);
     debug!("  - Calldata: {:?}", calldata);
     Ok(InterpreterAction::Call {
         inputs: Box::new(CallInputs {
             input: calldata,
             gas_limit: call_gas_limit,
-            target_address: addr,
+            target_address,
             bytecode_address: addr,
-            caller: interpreter.contract.target_address,
-            value: CallValue::Transfer(U256::from(value)),
-            scheme: CallScheme::Call,
+            caller,
+            value,
+            scheme,
             is_static,
             is_eof: false,
             return_memory_offset: 0..0, // handled with RETURNDATACOPY
@@ -658,12 +1998,31 @@ fn execute_create(
     emu: &mut Emulator,
     interpreter: &mut Interpreter,
     _host: &mut dyn Host,
+    spec_id: SpecId,
+    is_create2: bool,
 ) -> Result<InterpreterAction> {
-    let value: u64 = emu.cpu.xregs.read(10);
+    // Creates are state changes, so they aren't allowed within a staticcall
+    if interpreter.is_static {
+        return Ok(InterpreterAction::Return {
+            result: InterpreterResult {
+                result: InstructionResult::StateChangeDuringStaticCall,
+                output: Bytes::new(),
+                gas: interpreter.gas,
+            },
+        });
+    }
+
+    let value = U256::from_limbs([
+        emu.cpu.xregs.read(10),
+        emu.cpu.xregs.read(11),
+        emu.cpu.xregs.read(12),
+        emu.cpu.xregs.read(13),
+    ]);
 
     // Get initcode
-    let args_offset: u64 = emu.cpu.xregs.read(11);
-    let args_size: u64 = emu.cpu.xregs.read(12);
+    let args_offset: u64 = emu.cpu.xregs.read(14);
+    let args_size: u64 = emu.cpu.xregs.read(15);
+    syscall_gas!(interpreter, gas::memory_cost(args_size));
     let init_code: Bytes = emu
         .cpu
         .bus
@@ -672,15 +2031,31 @@ fn execute_create(
         .to_vec()
         .into();
 
-    // TODO: calculate gas cost properly
-    let create_gas_cost = gas::CREATE_BASE;
-    syscall_gas!(interpreter, create_gas_cost);
+    // The salt of a CREATE2 doesn't fit in the argument registers, so it spills to t1 and t2
+    let scheme = if is_create2 {
+        let salt = U256::from_limbs([
+            emu.cpu.xregs.read(16),
+            emu.cpu.xregs.read(17),
+            emu.cpu.xregs.read(6),
+            emu.cpu.xregs.read(7),
+        ]);
+        CreateScheme::Create2 { salt }
+    } else {
+        CreateScheme::Create
+    };
 
-    // proactively spend gas limit as the remaining will be refunded (otherwise it underflows)
-    let create_gas_limit = interpreter.gas.remaining();
+    syscall_gas!(
+        interpreter,
+        gas::create_cost(spec_id, args_size, is_create2)
+    );
+
+    // The child frame runs the initcode with all but one 64th of the remaining gas, out of which
+    // revm charges the code deposit once it returns. If it can't be paid, only the create fails.
+    // The gas limit is spent proactively, as the remaining will be refunded (otherwise it underflows)
+    let create_gas_limit = forwarded_gas(spec_id, interpreter.gas.remaining());
     syscall_gas!(interpreter, create_gas_limit);
 
-    debug!("> CREATE CTX:");
+    debug!("> {} CTX:", if is_create2 { "CREATE2" } else { "CREATE" });
     debug!("  - Caller: {}", interpreter.contract.target_address);
     debug!("  - Value: {}", value);
     debug!("  - Initcode size: {:?}", init_code.len());
@@ -689,12 +2064,100 @@ fn execute_create(
             init_code,
             gas_limit: create_gas_limit,
             caller: interpreter.contract.target_address,
-            value: U256::from(value),
-            scheme: CreateScheme::Create,
+            value,
+            scheme,
         }),
     })
 }
 
+/// Gas forwarded to a nested call or create: all but one 64th of the remaining gas since
+/// Tangerine Whistle (EIP-150).
+fn forwarded_gas(spec_id: SpecId, remaining: u64) -> u64 {
+    if spec_id.is_enabled_in(SpecId::TANGERINE) {
+        remaining - remaining / 64
+    } else {
+        remaining
+    }
+}
+
+/// Returns the code of a Solidity `Panic(uint256)` revert payload.
+fn panic_code(data: &[u8]) -> Option<u64> {
+    let (selector, code) = data.split_at_checked(4)?;
+    if selector != PANIC_SELECTOR || code.len() != 32 {
+        return None;
+    }
+
+    U256::from_be_slice(code).try_into().ok()
+}
+
+/// Hardfork that introduced the EVM opcode backing a syscall.
+fn activation_spec(syscall: Syscall) -> SpecId {
+    match syscall {
+        Syscall::DelegateCall => SpecId::HOMESTEAD,
+        Syscall::ReturnDataSize | Syscall::ReturnDataCopy | Syscall::StaticCall => {
+            SpecId::BYZANTIUM
+        }
+        Syscall::Create2 => SpecId::PETERSBURG,
+        Syscall::ChainId | Syscall::SelfBalance => SpecId::ISTANBUL,
+        // The context includes the base fee
+        Syscall::BaseFee | Syscall::Context => SpecId::LONDON,
+        Syscall::TLoad | Syscall::TStore | Syscall::BlobBaseFee => SpecId::CANCUN,
+        _ => SpecId::FRONTIER,
+    }
+}
+
+/// Range of the return data buffer (of length `buffer_len`) read by `ReturnDataCopy`, if it fits
+/// in the buffer. Ranges that start past its end are out of bounds, even if empty.
+pub(crate) fn return_data_range(buffer_len: usize, offset: u64, size: u64) -> Option<Range<usize>> {
+    let start = usize::try_from(offset).ok()?;
+    let end = start.checked_add(usize::try_from(size).ok()?)?;
+    (end <= buffer_len).then_some(start..end)
+}
+
+/// Size of the buffer written by the `Context` syscall: 7 words.
+const CONTEXT_SIZE: usize = 7 * 32;
+
+/// Packs the block and tx context as big-endian words, in the order expected by the runtime's
+/// `Context`: timestamp, number, gas limit, base fee, gas price, chain id and origin.
+fn encode_context(host: &mut dyn Host) -> [u8; CONTEXT_SIZE] {
+    let env = host.env();
+    let words = [
+        env.block.timestamp,
+        env.block.number,
+        env.block.gas_limit,
+        env.block.basefee,
+        env.effective_gas_price(),
+        U256::from(env.cfg.chain_id),
+        env.tx.caller.into_word().into(),
+    ];
+
+    let mut context = [0u8; CONTEXT_SIZE];
+    for (chunk, word) in context.chunks_exact_mut(32).zip(words) {
+        chunk.copy_from_slice(&word.to_be_bytes::<32>());
+    }
+    context
+}
+
+// Fills the stack guard with the canary. The calldata region ends right below it, and is always
+// at most `MAX_CALLDATA_SIZE` long (see `Program::instantiate`).
+fn arm_stack_guard(emu: &mut Emulator) {
+    if let Ok(guard) = emu.cpu.bus.get_dram_slice(STACK_GUARD) {
+        guard.fill(STACK_CANARY);
+    }
+}
+
+// Whether the frame ran past the bottom of its stack: either its stack pointer is still in (or
+// below) the guard, or it has since unwound but left the canary overwritten.
+fn stack_overflowed(emu: &mut Emulator) -> bool {
+    let sp = emu.cpu.xregs.read(2);
+    sp < STACK_GUARD.end
+        || emu
+            .cpu
+            .bus
+            .get_dram_slice(STACK_GUARD)
+            .map_or(true, |guard| guard.iter().any(|b| *b != STACK_CANARY))
+}
+
 /// Returns RISC-V DRAM slice in a given size range, starts with a given offset
 fn dram_slice(emu: &mut Emulator, ret_offset: u64, ret_size: u64) -> Result<&mut [u8]> {
     if ret_size != 0 {
@@ -707,8 +2170,58 @@ fn dram_slice(emu: &mut Emulator, ret_offset: u64, ret_size: u64) -> Result<&mut
     }
 }
 
-fn r55_gas_used(inst_count: &BTreeMap<String, u64>) -> u64 {
-    let total_cost = inst_count
+/// Reads `count` words, laid out as the little-endian limbs of a `U256`, from the emulator's
+/// memory. `None` if they don't fit in it.
+fn read_words(emu: &mut Emulator, offset: u64, count: u64) -> Option<Vec<U256>> {
+    let size = count.checked_mul(32)?;
+    offset.checked_add(size)?;
+    let memory = dram_slice(emu, offset, size).ok()?;
+    (memory.len() as u64 == size)
+        .then(|| memory.chunks_exact(32).map(U256::from_le_slice).collect())
+}
+
+/// Writes `words` to the emulator's memory, as the little-endian limbs of a `U256`. Returns whether
+/// they fit in it.
+fn write_words(emu: &mut Emulator, offset: u64, words: &[U256]) -> bool {
+    let size = words.len() as u64 * 32;
+    let Some(memory) = offset
+        .checked_add(size)
+        .and_then(|_| dram_slice(emu, offset, size).ok())
+        .filter(|memory| memory.len() as u64 == size)
+    else {
+        return false;
+    };
+
+    for (chunk, word) in memory.chunks_exact_mut(32).zip(words) {
+        chunk.copy_from_slice(&word.to_le_bytes::<32>());
+    }
+    true
+}
+
+/// Gas of the instructions a program runs on empty call data until its first syscall (or
+/// `STARTUP_PROBE_LIMIT` instructions): zeroing its heap, and then failing to decode a selector (or
+/// constructor args). It's the minimum every frame of the program runs, which isn't charged to them
+/// so that gas metering focuses on the logic of their methods.
+pub(crate) fn startup_cost(program: &Program) -> u64 {
+    let Ok(mut emu) = program.instantiate(&[]) else {
+        return 0;
+    };
+    emu.cpu.is_count = true;
+    for _ in 0..STARTUP_PROBE_LIMIT {
+        if emu.cpu.execute().is_err() {
+            break;
+        }
+    }
+    instructions_cost(&emu.cpu.inst_counter)
+}
+
+/// Gas of the instructions a frame ran so far, past the startup cost of its program.
+fn r55_gas_used(inst_count: &BTreeMap<String, u64>, startup_cost: u64) -> u64 {
+    instructions_cost(inst_count).saturating_sub(startup_cost)
+}
+
+fn instructions_cost(inst_count: &BTreeMap<String, u64>) -> u64 {
+    inst_count
         .iter()
         .map(|(inst_name, count)|
             // Gas cost = number of instructions * cycles per instruction
@@ -729,11 +2242,5 @@ fn r55_gas_used(inst_count: &BTreeMap<String, u64>) -> u64 {
                 "beq" | "bne" | "blt" | "bge" | "bltu" | "bgeu" | "jal" | "jalr" => count * 3,
                 _ => *count, // All other instructions including `add` and `sub`
         })
-        .sum::<u64>();
-
-    // This is the minimum 'gas used' to ABI decode 'empty' calldata into Rust type arguments. Real calldata will take more gas.
-    // Internalising this would focus gas metering more on the function logic
-    let abi_decode_cost = 9_175_538;
-
-    total_cost - abi_decode_cost
+        .sum::<u64>()
 }
@@ -0,0 +1,131 @@
+//! Classification of the failures of RISC-V frames, so that a contract that faults (e.g. on an
+//! illegal instruction or a misaligned access) doesn't look like one that reverted on purpose.
+//!
+//! Failures are recorded for every frame of a transaction, including the ones its caller recovers
+//! from, and are returned in `TxResult::failures`. As failed transactions don't have a `TxResult`,
+//! the failures of the last transaction are also available with `last_tx_failures`.
+//!
+//! Traps and panics carry the backtrace of their frame, see `crate::symbols`.
+
+use std::{cell::RefCell, fmt};
+
+use alloy_primitives::{Address, Bytes};
+use rvemu::exception::Exception;
+
+use crate::symbols::Backtrace;
+
+thread_local! {
+    /// Failed frames of the transaction being executed (or of the last one).
+    static FAILURES: RefCell<Vec<FrameFailure>> = RefCell::default();
+}
+
+/// Failure of a RISC-V frame.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameFailure {
+    /// Explicit revert, with its payload
+    Revert { address: Address, output: Bytes },
+    /// Revert issued by the runtime when the contract panicked, with the panic message (or its
+    /// `Panic(uint256)` error) as payload
+    Panic {
+        address: Address,
+        output: Bytes,
+        backtrace: Backtrace,
+    },
+    /// Fault of the emulator, which halts the frame and consumes all its gas
+    Trap(Box<Trap>),
+    /// `ecall` with a `t0` that isn't a known syscall id, which also halts the frame
+    UnknownSyscall { address: Address, id: u64, pc: u64 },
+}
+
+impl FrameFailure {
+    pub fn address(&self) -> Address {
+        match self {
+            Self::Revert { address, .. }
+            | Self::Panic { address, .. }
+            | Self::UnknownSyscall { address, .. } => *address,
+            Self::Trap(trap) => trap.address,
+        }
+    }
+}
+
+/// Fault of the emulator while running a frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trap {
+    pub address: Address,
+    pub exception: Exception,
+    /// Program counter when the fault was raised
+    pub pc: u64,
+    /// Address whose access faulted, for page faults
+    pub fault_address: Option<u64>,
+    /// Integer registers `x0..x31` when the fault was raised
+    pub registers: [u64; 32],
+    pub backtrace: Backtrace,
+}
+
+impl Trap {
+    pub(crate) fn new(
+        address: Address,
+        exception: Exception,
+        pc: u64,
+        registers: [u64; 32],
+        backtrace: Backtrace,
+    ) -> Self {
+        let fault_address = match exception {
+            Exception::InstructionPageFault(addr)
+            | Exception::LoadPageFault(addr)
+            | Exception::StoreAMOPageFault(addr) => Some(addr),
+            _ => None,
+        };
+
+        Self {
+            address,
+            exception,
+            pc,
+            fault_address,
+            registers,
+            backtrace,
+        }
+    }
+}
+
+impl fmt::Display for FrameFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Revert { address, output } => write!(f, "{} reverted: {}", address, output),
+            Self::Panic {
+                address,
+                output,
+                backtrace,
+            } => write!(
+                f,
+                "{} panicked: {}{}",
+                address,
+                String::from_utf8(output.to_vec()).unwrap_or_else(|_| output.to_string()),
+                backtrace
+            ),
+            Self::Trap(trap) => write!(
+                f,
+                "{} trapped: {:?}{}",
+                trap.address, trap.exception, trap.backtrace
+            ),
+            Self::UnknownSyscall { address, id, pc } => write!(
+                f,
+                "{} issued unknown syscall {:#x} at PC {:#x}",
+                address, id, pc
+            ),
+        }
+    }
+}
+
+pub(crate) fn record(failure: FrameFailure) {
+    FAILURES.with_borrow_mut(|failures| failures.push(failure));
+}
+
+pub(crate) fn reset() {
+    FAILURES.take();
+}
+
+/// Failed frames of the last transaction executed on this thread, whether it succeeded or not.
+pub fn last_tx_failures() -> Vec<FrameFailure> {
+    FAILURES.with_borrow(|failures| failures.clone())
+}
@@ -1,17 +1,223 @@
+use revm::{
+    interpreter::SStoreResult,
+    primitives::{AccessListItem, SpecId},
+};
+
 // Standard EVM operation costs
 pub const SLOAD_COLD: u64 = 2100;
 pub const SLOAD_WARM: u64 = 100;
 pub const SSTORE_COLD: u64 = 2200;
 pub const SSTORE_WARM: u64 = 100;
 
+// Balance costs
+pub const SELFBALANCE: u64 = 5;
+
+// Remaining gas (GAS opcode)
+pub const GAS: u64 = 2;
+
+// Transient storage costs (EIP-1153)
+pub const TLOAD: u64 = 100;
+pub const TSTORE: u64 = 100;
+
+// Pre-Berlin (EIP-2929) storage costs
+pub const SLOAD_ISTANBUL: u64 = 800;
+pub const SLOAD_TANGERINE: u64 = 200;
+pub const SLOAD_FRONTIER: u64 = 50;
+pub const SSTORE_SET: u64 = 20000;
+pub const SSTORE_RESET: u64 = 5000;
+
 // Call-related costs
 pub const CALL_EMPTY_ACCOUNT: u64 = 25000;
 pub const CALL_NEW_ACCOUNT: u64 = 2600;
 pub const CALL_VALUE: u64 = 9000;
 pub const CALL_BASE: u64 = 100;
 
-// Create-related costs
+// Pre-Berlin (EIP-2929) call costs
+pub const CALL_TANGERINE: u64 = 700;
+pub const CALL_FRONTIER: u64 = 40;
+
+// Code-related costs
+pub const EXTCODESIZE_FRONTIER: u64 = 20;
+
+// Create-related costs. Creation txs pay the base cost of a create too (since Homestead)
 pub const CREATE_BASE: u64 = 32000;
+pub const CODE_DEPOSIT_BYTE: u64 = 200;
+
+// Memory-related costs
+pub const MEMORY_WORD: u64 = 3;
+pub const COPY_WORD: u64 = 3;
+
+// Hashing costs
+pub const KECCAK256_BASE: u64 = 30;
+pub const KECCAK256_WORD: u64 = 6;
+
+// Log costs
+pub const LOG_BASE: u64 = 375;
+pub const LOG_TOPIC: u64 = 375;
+pub const LOG_DATA_BYTE: u64 = 8;
+
+// Transaction-related (intrinsic) costs
+pub const TX_BASE: u64 = 21000;
+pub const TX_DATA_ZERO: u64 = 4;
+pub const TX_DATA_NON_ZERO: u64 = 16;
+pub const TX_DATA_NON_ZERO_FRONTIER: u64 = 68;
+pub const INITCODE_WORD: u64 = 2;
+pub const ACCESS_LIST_ADDRESS: u64 = 2400;
+pub const ACCESS_LIST_STORAGE_KEY: u64 = 1900;
+
+/// Gas charged to a transaction before any execution takes place: base cost, calldata bytes and,
+/// for deployments, the create surcharge plus the initcode words (EIP-3860).
+///
+/// Non-zero calldata bytes are cheaper since Istanbul (EIP-2028), the create surcharge applies
+/// since Homestead and initcode words are only charged since Shanghai.
+pub fn intrinsic_gas(spec_id: SpecId, data: &[u8], is_create: bool) -> u64 {
+    let zero_bytes = data.iter().filter(|b| **b == 0).count() as u64;
+    let non_zero_bytes = data.len() as u64 - zero_bytes;
+    let non_zero_cost = if spec_id.is_enabled_in(SpecId::ISTANBUL) {
+        TX_DATA_NON_ZERO
+    } else {
+        TX_DATA_NON_ZERO_FRONTIER
+    };
+
+    let mut gas = TX_BASE + zero_bytes * TX_DATA_ZERO + non_zero_bytes * non_zero_cost;
+    if is_create && spec_id.is_enabled_in(SpecId::HOMESTEAD) {
+        gas += CREATE_BASE;
+    }
+    if is_create && spec_id.is_enabled_in(SpecId::SHANGHAI) {
+        gas += INITCODE_WORD * (data.len() as u64).div_ceil(32);
+    }
+
+    gas
+}
+
+/// Intrinsic cost of the access list of a transaction (EIP-2930), charged per address and per
+/// storage key whether they are accessed or not. Access lists only exist since Berlin.
+pub fn access_list_cost(spec_id: SpecId, access_list: &[AccessListItem]) -> u64 {
+    if !spec_id.is_enabled_in(SpecId::BERLIN) {
+        return 0;
+    }
+
+    let keys: usize = access_list.iter().map(|item| item.storage_keys.len()).sum();
+    access_list.len() as u64 * ACCESS_LIST_ADDRESS + keys as u64 * ACCESS_LIST_STORAGE_KEY
+}
+
+/// Cost of a `SLOAD`. Access lists (and thus cold/warm pricing) only exist since Berlin.
+pub fn sload_cost(spec_id: SpecId, is_cold: bool) -> u64 {
+    if spec_id.is_enabled_in(SpecId::BERLIN) {
+        if is_cold {
+            SLOAD_COLD
+        } else {
+            SLOAD_WARM
+        }
+    } else if spec_id.is_enabled_in(SpecId::ISTANBUL) {
+        SLOAD_ISTANBUL
+    } else if spec_id.is_enabled_in(SpecId::TANGERINE) {
+        SLOAD_TANGERINE
+    } else {
+        SLOAD_FRONTIER
+    }
+}
+
+/// Cost of a `SSTORE` writing `vals.new_value`.
+///
+/// Since Berlin it only depends on whether the slot is cold. Before, there is no cold/warm
+/// distinction and writes are priced by the values of the slot: with the net gas metering of
+/// EIP-2200 since Istanbul, and as a set (zero to non-zero) or a reset (anything else) before.
+pub fn sstore_cost(spec_id: SpecId, vals: &SStoreResult, is_cold: bool) -> u64 {
+    if spec_id.is_enabled_in(SpecId::BERLIN) {
+        if is_cold {
+            SSTORE_COLD
+        } else {
+            SSTORE_WARM
+        }
+    } else if spec_id.is_enabled_in(SpecId::ISTANBUL) {
+        // Only the first write of a slot in the tx (a clean slot) pays for the write itself, a
+        // no-op or a write to a dirty slot costs as much as a `SLOAD`
+        if vals.is_new_eq_present() || !vals.is_original_eq_present() {
+            SLOAD_ISTANBUL
+        } else if vals.is_original_zero() {
+            SSTORE_SET
+        } else {
+            SSTORE_RESET
+        }
+    } else if vals.is_present_zero() && !vals.is_new_zero() {
+        SSTORE_SET
+    } else {
+        SSTORE_RESET
+    }
+}
+
+/// Cost of accessing the target account of a call.
+pub fn call_access_cost(spec_id: SpecId, is_cold: bool) -> u64 {
+    if spec_id.is_enabled_in(SpecId::BERLIN) {
+        if is_cold {
+            CALL_NEW_ACCOUNT
+        } else {
+            CALL_BASE
+        }
+    } else if spec_id.is_enabled_in(SpecId::TANGERINE) {
+        CALL_TANGERINE
+    } else {
+        CALL_FRONTIER
+    }
+}
+
+/// Cost of EXTCODESIZE, which is priced like a call's account access since Tangerine Whistle.
+pub fn extcodesize_cost(spec_id: SpecId, is_cold: bool) -> u64 {
+    if spec_id.is_enabled_in(SpecId::TANGERINE) {
+        call_access_cost(spec_id, is_cold)
+    } else {
+        EXTCODESIZE_FRONTIER
+    }
+}
+
+/// Word-proportional cost of the memory touched by a syscall buffer of `size` bytes (the linear
+/// part of the EVM memory expansion). Saturates, so that absurd sizes run out of gas instead of
+/// overflowing.
+pub fn memory_cost(size: u64) -> u64 {
+    MEMORY_WORD.saturating_mul(size.div_ceil(32))
+}
+
+/// Cost of copying `size` bytes between buffers (`CALLDATACOPY`, `RETURNDATACOPY`, ...), on top of
+/// the memory they touch.
+pub fn copy_cost(size: u64) -> u64 {
+    COPY_WORD.saturating_mul(size.div_ceil(32))
+}
+
+/// Cost of hashing `size` bytes with `KECCAK256`.
+pub fn keccak256_cost(size: u64) -> u64 {
+    KECCAK256_BASE.saturating_add(KECCAK256_WORD.saturating_mul(size.div_ceil(32)))
+}
+
+/// Cost of a `CREATE` (or `CREATE2`) with `size` bytes of initcode: the base cost, the initcode
+/// words since Shanghai (EIP-3860) and, for a `CREATE2`, hashing the initcode to derive the address.
+///
+/// The execution of the initcode and the deposit of the returned code are charged to the gas
+/// forwarded to the create.
+pub fn create_cost(spec_id: SpecId, size: u64, is_create2: bool) -> u64 {
+    let words = size.div_ceil(32);
+    let mut gas = CREATE_BASE;
+    if spec_id.is_enabled_in(SpecId::SHANGHAI) {
+        gas = gas.saturating_add(INITCODE_WORD.saturating_mul(words));
+    }
+    if is_create2 {
+        gas = gas.saturating_add(KECCAK256_WORD.saturating_mul(words));
+    }
+
+    gas
+}
+
+/// Cost of depositing `size` bytes of code at a created address.
+pub fn code_deposit_cost(size: u64) -> u64 {
+    CODE_DEPOSIT_BYTE.saturating_mul(size)
+}
+
+/// Cost of emitting a log with `topics` topics and `size` bytes of data.
+pub fn log_cost(topics: u64, size: u64) -> u64 {
+    LOG_BASE
+        .saturating_add(LOG_TOPIC.saturating_mul(topics))
+        .saturating_add(LOG_DATA_BYTE.saturating_mul(size))
+}
 
 // Macro to handle gas accounting for syscalls.
 // Returns OutOfGas InterpreterResult if gas limit is exceeded.
@@ -20,13 +226,13 @@ macro_rules! syscall_gas {
     ($interpreter:expr, $gas_cost:expr $(,)?) => {{
         let gas_cost = $gas_cost;
 
-        trace!("> About to record gas costs:");
-        trace!("  - Gas limit: {}", $interpreter.gas.limit());
-        trace!("  - Gas prev spent: {}", $interpreter.gas.spent());
-        trace!("  - Operation cost: {}", gas_cost);
-
         if !$interpreter.gas.record_cost(gas_cost) {
-            eprintln!("OUT OF GAS");
+            tracing::warn!(
+                target: $crate::exec::SYSCALLS_TARGET,
+                gas = gas_cost,
+                remaining = $interpreter.gas.remaining(),
+                "out of gas"
+            );
             return Ok(InterpreterAction::Return {
                 result: InterpreterResult {
                     result: InstructionResult::OutOfGas,
@@ -36,8 +242,11 @@ macro_rules! syscall_gas {
             });
         }
 
-        trace!("> Gas recorded successfully:");
-        trace!("  - Gas remaining: {}", $interpreter.gas.remaining());
-        trace!("  - Gas spent: {}", $interpreter.gas.spent());
+        tracing::trace!(
+            target: $crate::exec::SYSCALLS_TARGET,
+            gas = gas_cost,
+            remaining = $interpreter.gas.remaining(),
+            "charged"
+        );
     }};
 }
@@ -13,6 +13,31 @@ pub const UNISWAP_V2_PAIR_BYTECODE: &[u8] =
     include_bytes!("../../../r55-output-bytecode/uniswap-v2-pair.bin");
 pub const UNISWAP_V2_FACTORY_BYTECODE: &[u8] =
     include_bytes!("../../../r55-output-bytecode/uniswap-v2-factory.bin");
+pub const TESTBED_BYTECODE: &[u8] = include_bytes!("../../../r55-output-bytecode/testbed.bin");
+pub const VAULT_BYTECODE: &[u8] = include_bytes!("../../../r55-output-bytecode/vault.bin");
+pub const ERC721_RECEIVER_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/erc721-receiver.bin");
+pub const CLONE_FACTORY_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/clone-factory.bin");
+pub const UPGRADEABLE_PROXY_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/upgradeable-proxy.bin");
+pub const UPGRADEABLE_V1_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/upgradeable-v1.bin");
+pub const UPGRADEABLE_V2_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/upgradeable-v2.bin");
+pub const UPGRADEABLE_DELEGATOR_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/upgradeable-delegator.bin");
+pub const TOKEN_LAUNCHER_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/token-launcher.bin");
+pub const STD_TOKENS_FIXED_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/std-tokens-fixed.bin");
+pub const STD_TOKENS_MINTABLE_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/std-tokens-mintable.bin");
+pub const FEE_TOKEN_BYTECODE: &[u8] = include_bytes!("../../../r55-output-bytecode/fee-token.bin");
+pub const REVERT_REASONS_PLAIN_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/revert-reasons-plain.bin");
+pub const REVERT_REASONS_FORMATTED_BYTECODE: &[u8] =
+    include_bytes!("../../../r55-output-bytecode/revert-reasons-formatted.bin");
 
 pub fn get_bytecode(contract_name: &str) -> Bytes {
     let initcode = match contract_name {
@@ -22,8 +47,25 @@ pub fn get_bytecode(contract_name: &str) -> Bytes {
         "erc20x" => ERC20X_BYTECODE,
         "uniswap_v2_pair" => UNISWAP_V2_PAIR_BYTECODE,
         "uniswap_v2_factory" => UNISWAP_V2_FACTORY_BYTECODE,
+        "testbed" => TESTBED_BYTECODE,
+        "vault" => VAULT_BYTECODE,
+        "erc721_receiver" => ERC721_RECEIVER_BYTECODE,
+        "clone_factory" => CLONE_FACTORY_BYTECODE,
+        "upgradeable_proxy" => UPGRADEABLE_PROXY_BYTECODE,
+        "upgradeable_v1" => UPGRADEABLE_V1_BYTECODE,
+        "upgradeable_v2" => UPGRADEABLE_V2_BYTECODE,
+        "upgradeable_delegator" => UPGRADEABLE_DELEGATOR_BYTECODE,
+        "token_launcher" => TOKEN_LAUNCHER_BYTECODE,
+        "std_tokens_fixed" => STD_TOKENS_FIXED_BYTECODE,
+        "std_tokens_mintable" => STD_TOKENS_MINTABLE_BYTECODE,
+        "fee_token" => FEE_TOKEN_BYTECODE,
+        "revert_reasons_plain" => REVERT_REASONS_PLAIN_BYTECODE,
+        "revert_reasons_formatted" => REVERT_REASONS_FORMATTED_BYTECODE,
         _ => return Bytes::new(),
     };
 
+    // Fail loudly if the sources changed since the bytecode was compiled
+    crate::lock::check_fresh(contract_name, initcode);
+
     Bytes::from(initcode)
 }
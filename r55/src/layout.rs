@@ -0,0 +1,47 @@
+//! Storage layouts exported by `r55-compile`, used to find the slots of the fields of a contract.
+//!
+//! Whether a new implementation of an upgradeable contract is compatible with the storage of the
+//! previous one is checked by `r55-compile check-upgrade`.
+
+use std::{fs, path::Path};
+
+use alloy_primitives::U256;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StorageLayout {
+    pub contract: String,
+    pub fields: Vec<StorageField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct StorageField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub slot: U256,
+}
+
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum LayoutError {
+    #[error("Failed to load layout: {0}")]
+    Load(String),
+}
+
+impl StorageLayout {
+    /// Loads the layout exported for a contract, named as in `get_bytecode`
+    pub fn load(contract_name: &str) -> Result<Self, LayoutError> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../r55-output-bytecode")
+            .join(format!("{}.layout.json", contract_name.replace('_', "-")));
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| LayoutError::Load(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&content).map_err(|e| LayoutError::Load(e.to_string()))
+    }
+
+    /// Slot of a field, by name
+    pub fn field_slot(&self, name: &str) -> Option<U256> {
+        self.fields.iter().find(|f| f.name == name).map(|f| f.slot)
+    }
+}
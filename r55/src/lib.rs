@@ -1,6 +1,17 @@
-mod error;
+#[cfg(feature = "coverage")]
+pub mod coverage;
+pub mod error;
 pub mod exec;
-mod gas;
+pub mod failure;
+pub mod gas;
+pub mod layout;
+pub mod lock;
+pub mod max_gas;
+pub mod programs;
+pub mod selectors;
+pub mod state_diff;
+pub mod storage;
+pub mod symbols;
 
 mod generated;
 pub use generated::get_bytecode;
@@ -10,14 +21,23 @@ pub mod test_utils;
 #[cfg(test)]
 mod tests {
     use crate::{
-        exec::{deploy_contract, run_tx},
+        assert_field_eq,
+        exec::{
+            deploy_contract, r55_initcode, run_tx, send_tx, ExecEnv, R55Executor, TxSpec,
+            FRAMES_TARGET, SYSCALLS_TARGET,
+        },
         get_bytecode,
+        layout::{StorageField, StorageLayout},
+        storage::{StorageError, StorageReader},
         test_utils::*,
     };
 
     use alloy_core::hex::{self, ToHexExt};
-    use alloy_primitives::B256;
+    use alloy_primitives::{address, Bytes, B256};
     use alloy_sol_types::SolValue;
+    use eth_riscv_syscalls::Syscall;
+    use revm::{db::CacheDB, primitives::SpecId};
+    use std::collections::BTreeMap;
 
     fn setup_erc20(owner: Address) -> (InMemoryDB, Address) {
         initialize_logger();
@@ -267,6 +287,16 @@ mod tests {
         let id = get_mapping_slot(ALICE.abi_encode(), allowances_id);
         let expected_slot = get_mapping_slot(CAROL.abi_encode(), id);
         assert_eq!(allowance_carol, read_db_slot(&mut db, erc20, expected_slot));
+        assert_eq!(
+            get_nested_mapping_slot(&[ALICE.abi_encode(), CAROL.abi_encode()], allowances_id),
+            expected_slot
+        );
+
+        // The same slots are derived from the exported layout
+        let token = StorageReader::load("erc20", erc20).unwrap();
+        assert_field_eq!(db, token, "allowance_of[alice][carol]", allowance_carol);
+        assert_field_eq!(db, token, "balance_of[bob]", mint_bob);
+        assert_field_eq!(db, token, "owner", ALICE);
 
         // Assert `owner` is set to track the correct slot
         let expected_slot = U256::from(3);
@@ -276,6 +306,175 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_array_and_string_slots() {
+        // Solidity layout of `uint256[] a` and `string s` at slot 0: data starts at `keccak256(0)`
+        let data_start = U256::from_be_bytes(alloy_primitives::hex!(
+            "290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+        ));
+        assert_eq!(get_array_element_slot(U256::ZERO, 0, 1), data_start);
+        assert_eq!(
+            get_array_element_slot(U256::ZERO, 3, 2),
+            data_start + U256::from(6)
+        );
+        assert_eq!(get_string_slots(U256::ZERO), (U256::ZERO, data_start));
+
+        // Nested mappings derive from the outermost key
+        let keys = [ALICE.abi_encode(), BOB.abi_encode()];
+        let inner = get_mapping_slot(ALICE.abi_encode(), U256::from(2));
+        assert_eq!(
+            get_nested_mapping_slot(&keys, U256::from(2)),
+            get_mapping_slot(BOB.abi_encode(), inner)
+        );
+        assert_eq!(get_nested_mapping_slot(&[], U256::from(2)), U256::from(2));
+    }
+
+    #[test]
+    fn test_signature_encoding() {
+        let sig = "transfer(address,uint256)";
+        let calldata = encode_call(sig, &[BOB.into(), U256::from(5).into()]);
+        assert_eq!(
+            calldata.to_vec(),
+            get_calldata(
+                get_selector_from_sig(sig),
+                (BOB, U256::from(5)).abi_encode()
+            )
+        );
+
+        let output = (U256::from(7), true).abi_encode();
+        let decoded = decode_return("f() returns (uint256, bool)", &output);
+        assert_eq!(decoded, vec![U256::from(7).into(), true.into()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid arguments for `transfer(address,uint256)`")]
+    fn test_signature_encoding_type_mismatch() {
+        encode_call("transfer(address,uint256)", &[BOB.into(), true.into()]);
+    }
+
+    #[test]
+    fn test_storage_dump_helpers() {
+        let contract = address!("00000000000000000000000000000000000000ff");
+        let mut db = InMemoryDB::default();
+        let owner_slot = get_mapping_slot(ALICE.abi_encode(), U256::from(1));
+        db.insert_account_storage(contract, U256::ZERO, U256::from(10))
+            .unwrap();
+        db.insert_account_storage(contract, owner_slot, U256::from(7))
+            .unwrap();
+        let before = dump_storage(&db, contract);
+
+        db.insert_account_storage(contract, U256::ZERO, U256::ZERO)
+            .unwrap();
+        db.insert_account_storage(contract, U256::from(5), U256::from(1))
+            .unwrap();
+        let after = dump_storage(&db, contract);
+
+        // Cleared slots are dropped from the dump, and show up as zero in the diff
+        assert_eq!(
+            after,
+            BTreeMap::from([(owner_slot, U256::from(7)), (U256::from(5), U256::from(1))])
+        );
+        assert_eq!(
+            storage_diff(&before, &after),
+            BTreeMap::from([
+                (U256::ZERO, (U256::from(10), U256::ZERO)),
+                (U256::from(5), (U256::ZERO, U256::from(1))),
+            ])
+        );
+
+        let field = |name: &str, ty: &str, slot: u64| StorageField {
+            name: name.into(),
+            ty: ty.into(),
+            slot: U256::from(slot),
+        };
+        let layout = StorageLayout {
+            contract: "Token".into(),
+            fields: vec![
+                field("total_supply", "Slot<U256>", 0),
+                field("balance_of", "Mapping<Address,Slot<U256>>", 1),
+            ],
+        };
+        let printer = StoragePrinter::new()
+            .with_layout(layout)
+            .with_key("ALICE", ALICE.abi_encode());
+        assert_eq!(
+            printer.format(&before),
+            format!(
+                "{:#066x} (total_supply): 0xa\n{:#066x} (balance_of[ALICE]): 0x7\n",
+                U256::ZERO,
+                owner_slot
+            )
+        );
+        assert_eq!(StoragePrinter::new().format(&after).lines().count(), 2);
+    }
+
+    #[test]
+    fn test_storage_reader_paths() {
+        let field = |name: &str, ty: &str, slot: u64| StorageField {
+            name: name.into(),
+            ty: ty.into(),
+            slot: U256::from(slot),
+        };
+        let layout = StorageLayout {
+            contract: "Token".into(),
+            fields: vec![
+                field("total_supply", "Slot<U256>", 0),
+                field("balance_of", "Mapping<Address,Slot<U256>>", 1),
+                field(
+                    "allowance_of",
+                    "Mapping<Address,Mapping<Address,Slot<U256>>>",
+                    2,
+                ),
+                field("paused", "Mapping<u64,Slot<bool>>", 3),
+            ],
+        };
+        let spender = address!("00000000000000000000000000000000deadbeef");
+        let reader =
+            StorageReader::new(layout, Address::ZERO).with_key("spender", spender.abi_encode());
+        let slot = |path: &str| reader.resolve(path).map(|(slot, _)| slot);
+
+        assert_eq!(slot("total_supply"), Ok(U256::ZERO));
+        assert_eq!(
+            slot("balance_of[alice]"),
+            Ok(get_mapping_slot(ALICE.abi_encode(), U256::from(1)))
+        );
+
+        // Registered labels and literals of the key type are interchangeable
+        let allowance =
+            get_nested_mapping_slot(&[BOB.abi_encode(), spender.abi_encode()], U256::from(2));
+        assert_eq!(slot("allowance_of[bob][spender]"), Ok(allowance));
+        assert_eq!(
+            slot(&format!("allowance_of[bob][{}]", spender)),
+            Ok(allowance)
+        );
+        assert_eq!(
+            slot("paused[7]"),
+            Ok(get_mapping_slot(7u64.abi_encode(), U256::from(3)))
+        );
+
+        // Invalid paths
+        assert!(matches!(
+            slot("supply"),
+            Err(StorageError::UnknownField { .. })
+        ));
+        assert!(matches!(
+            slot("total_supply[alice]"),
+            Err(StorageError::NotAMapping { .. })
+        ));
+        assert!(matches!(
+            slot("allowance_of[bob]"),
+            Err(StorageError::NotAValue { .. })
+        ));
+        assert!(matches!(
+            slot("paused[alice"),
+            Err(StorageError::InvalidPath(_))
+        ));
+        assert!(matches!(
+            slot("paused[-1]"),
+            Err(StorageError::InvalidKey { .. })
+        ));
+    }
+
     #[test]
     fn test_custom_error() {
         let (mut db, erc20) = setup_erc20(ALICE);
@@ -418,6 +617,190 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_raw_call_result() {
+        let (mut db, erc20) = setup_erc20(ALICE);
+        let erc20x = setup_erc20x(&mut db);
+
+        // Mint 10 tokens to ERC20x
+        let value_mint = U256::from(10e18);
+        let selector_mint = get_selector_from_sig("mint(address,uint256)");
+        let calldata_mint = get_calldata(selector_mint, (erc20x, value_mint).abi_encode());
+        run_tx(&mut db, &erc20, calldata_mint, &ALICE).expect("Error executing tx");
+
+        let selector_x_try_transfer =
+            get_selector_from_sig("x_try_transfer(address,uint256,address)");
+        let mut x_try_transfer = |amount: U256| {
+            let calldata = get_calldata(selector_x_try_transfer, (BOB, amount, erc20).abi_encode());
+            let output = run_tx(&mut db, &erc20x, calldata, &ALICE)
+                .expect("Error executing tx")
+                .output;
+            <(bool, Bytes)>::abi_decode(&output, true).expect("Unable to decode output")
+        };
+
+        // Succeeding call: the raw (encoded) return value
+        let (success, data) = x_try_transfer(U256::from(1e18));
+        assert!(success, "Transfer failed");
+        assert_eq!(data, true.abi_encode());
+
+        // Reverting call: the raw error, which doesn't revert the caller
+        let (success, data) = x_try_transfer(value_mint);
+        assert!(!success, "Transfer succeeded");
+        let mut expected = keccak256("ERC20Error::InsufficientBalance(uint256)")[..4].to_vec();
+        expected.extend_from_slice(&U256::from(9e18).abi_encode());
+        assert_eq!(data, expected);
+    }
+
+    #[test]
+    fn test_multicall() {
+        let (mut db, erc20) = setup_erc20(ALICE);
+        let erc20x = setup_erc20x(&mut db);
+
+        let selector_mint = get_selector_from_sig("mint(address,uint256)");
+        let calldata_mint = get_calldata(selector_mint, (ALICE, U256::from(42e18)).abi_encode());
+        run_tx(&mut db, &erc20, calldata_mint, &ALICE).expect("Error executing tx");
+
+        let selector_approve = get_selector_from_sig("approve(address,uint256)");
+        let calldata_approve = get_calldata(selector_approve, (BOB, U256::from(5e18)).abi_encode());
+        run_tx(&mut db, &erc20, calldata_approve, &ALICE).expect("Error executing tx");
+
+        let mut summary = |method: &str| {
+            let selector = get_selector_from_sig(&format!("{method}(address,address,address)"));
+            let calldata = get_calldata(selector, (ALICE, BOB, erc20).abi_encode());
+            let result = run_tx(&mut db, &erc20x, calldata, &ALICE).expect("Error executing tx");
+            let values = <(U256, U256, U256)>::abi_decode(&result.output, true)
+                .expect("Unable to decode output");
+            (
+                values,
+                result.gas_used,
+                result.syscalls[&Syscall::StaticCall],
+            )
+        };
+
+        let (batched, batched_gas, batched_calls) = summary("x_summary");
+        let (separate, separate_gas, separate_calls) = summary("x_summary_separate");
+
+        let expected = (U256::from(42e18), U256::from(5e18), U256::from(42e18));
+        assert_eq!(batched, expected);
+        assert_eq!(separate, expected);
+
+        // Both make the same calls, which the host charges the same: the batch only saves the
+        // buffers that each interface call allocates for its output
+        assert_eq!(batched_calls, 3);
+        assert_eq!(separate_calls, 3);
+        assert!(
+            batched_gas < separate_gas,
+            "Multicall gas {batched_gas} isn't lower than separate calls gas {separate_gas}"
+        );
+    }
+
+    #[test]
+    fn test_interface_without_code() {
+        let (mut db, erc20) = setup_erc20(ALICE);
+        let erc20x = setup_erc20x(&mut db);
+
+        let selector_x_is_contract = get_selector_from_sig("x_is_contract(address)");
+        let mut x_is_contract = |target: Address| {
+            let calldata = get_calldata(selector_x_is_contract, target.abi_encode());
+            let output = run_tx(&mut db, &erc20x, calldata, &ALICE)
+                .expect("Error executing tx")
+                .output;
+            bool::abi_decode(&output, true).expect("Unable to decode output")
+        };
+        assert!(x_is_contract(erc20));
+        assert!(!x_is_contract(CAROL));
+
+        // The empty output of a call to an EOA isn't decoded as a zero balance
+        let selector_x_balance_of = get_selector_from_sig("x_balance_of(address,address)");
+        let calldata = get_calldata(selector_x_balance_of, (ALICE, CAROL).abi_encode());
+        run_tx(&mut db, &erc20x, calldata, &ALICE).expect_err("Call to an EOA succeeded");
+    }
+
+    #[test]
+    fn test_call_with_value() {
+        let (mut db, erc20) = setup_erc20(ALICE);
+        let erc20x = setup_erc20x(&mut db);
+
+        // Wrap 0.1 ETH through ERC20x, which forwards it to `deposit()`
+        let value = U256::from(1e17);
+        let selector_x_wrap = get_selector_from_sig("x_wrap(address)");
+        let calldata = get_calldata(selector_x_wrap, erc20.abi_encode());
+        let tx = TxSpec::call(ALICE, erc20x, calldata).with_value(value);
+        let output = send_tx(&mut db, &tx, &ExecEnv::default())
+            .expect("Error executing tx")
+            .output;
+        assert_eq!(output, true.abi_encode());
+
+        // The token minted what it received as `msg_value()` to its caller
+        let selector_balance = get_selector_from_sig("balance_of(address)");
+        let calldata = get_calldata(selector_balance, erc20x.abi_encode());
+        let balance = run_tx(&mut db, &erc20, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert_eq!(balance, value.abi_encode());
+
+        // And the ETH moved to the token, through ERC20x
+        let eth_balance = |db: &InMemoryDB, addr: Address| db.accounts[&addr].info.balance;
+        assert_eq!(eth_balance(&db, erc20x), U256::ZERO);
+        assert_eq!(eth_balance(&db, erc20), value);
+    }
+
+    #[test]
+    fn test_executor_on_forked_state() {
+        initialize_logger();
+        let mut base = InMemoryDB::default();
+        add_balance_to_db(&mut base, ALICE, 1e18 as u64);
+
+        // Run against an overlay of the base state, as an embedder would over a fork
+        let mut fork = CacheDB::new(&base);
+        let env = ExecEnv::default();
+        let executor = R55Executor::new(&env);
+
+        let init_code = r55_initcode(get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+        let deployment = executor
+            .deploy(&mut fork, init_code, U256::ZERO, ALICE)
+            .expect("Error deploying contract");
+        let erc20 = deployment.created.expect("No contract deployed");
+        assert_eq!(erc20, ALICE.create(0));
+
+        let value_mint = U256::from(42e18);
+        let calldata = get_calldata(
+            get_selector_from_sig("mint(address,uint256)"),
+            (BOB, value_mint).abi_encode(),
+        );
+        executor
+            .call(
+                &mut fork,
+                erc20,
+                calldata.into(),
+                U256::ZERO,
+                ALICE,
+                1_000_000,
+            )
+            .expect("Error executing tx");
+
+        let calldata = get_calldata(
+            get_selector_from_sig("balance_of(address)"),
+            BOB.abi_encode(),
+        );
+        let result = executor
+            .call(
+                &mut fork,
+                erc20,
+                calldata.into(),
+                U256::ZERO,
+                ALICE,
+                1_000_000,
+            )
+            .expect("Error executing tx");
+        assert_eq!(result.output, value_mint.abi_encode());
+        assert_eq!(result.created, None);
+
+        // Only the overlay was written to
+        assert!(!base.accounts.contains_key(&erc20));
+        assert_eq!(base.accounts[&ALICE].info.nonce, 0);
+    }
+
     #[test]
     fn test_string_error() {
         let (mut db, erc20) = setup_erc20(ALICE);
@@ -439,6 +822,14 @@ mod tests {
             panic_result.matches_string_error("This function always panics"),
             "Incorrect error"
         );
+        let failures = crate::failure::last_tx_failures();
+        assert!(
+            matches!(
+                failures.as_slice(),
+                [crate::failure::FrameFailure::Panic { address, .. }] if *address == erc20x
+            ),
+            "Expected a panic, got: {failures:?}"
+        );
 
         // Attempt a call that panics with a string msg
         let calldata_x_mint = get_calldata(
@@ -453,4 +844,222 @@ mod tests {
             "Incorrect error"
         );
     }
+
+    #[test]
+    fn test_panic_backtrace() {
+        let (mut db, _) = setup_erc20(ALICE);
+        let erc20x = setup_erc20x(&mut db);
+
+        let selector_panic = get_selector_from_sig("panics()");
+        run_tx(
+            &mut db,
+            &erc20x,
+            get_calldata(selector_panic, vec![]),
+            &ALICE,
+        )
+        .expect_err("Tx succeeded");
+
+        let failures = crate::failure::last_tx_failures();
+        let [failure @ crate::failure::FrameFailure::Panic { backtrace, .. }] = failures.as_slice()
+        else {
+            panic!("Expected a panic, got: {failures:?}");
+        };
+
+        // The panicking method is among the callers of the runtime's panic handler
+        let report = failure.to_string();
+        assert!(
+            backtrace.frames().iter().any(|frame| frame
+                .function
+                .as_deref()
+                .is_some_and(|name| name == "panics" || name.ends_with("::panics"))),
+            "`panics` is missing from the failure report: {report}"
+        );
+        assert!(report.contains(" in "), "Unsymbolized backtrace: {report}");
+    }
+
+    #[test]
+    fn test_intrinsic_gas() {
+        let (mut db, _) = setup_erc20(ALICE);
+        let vault = deploy_contract(&mut db, get_bytecode("vault"), Some(ALICE.abi_encode()))
+            .expect("Error deploying vault");
+
+        // Both calls are handled by the fallback of the vault, which never reads the calldata: it
+        // isn't copied (so no copy gas is charged), and only its intrinsic cost differs
+        let calldata_short = vec![];
+        let calldata_long = vec![0xff; 1024];
+
+        let gas_short = run_tx(&mut db, &vault, calldata_short.clone(), &ALICE)
+            .expect("Error executing tx")
+            .gas_used;
+        let gas_long = run_tx(&mut db, &vault, calldata_long.clone(), &ALICE)
+            .expect("Error executing tx")
+            .gas_used;
+
+        assert_eq!(
+            gas_long - gas_short,
+            1024 * crate::gas::TX_DATA_NON_ZERO,
+            "Unexpected calldata gas"
+        );
+        assert_eq!(
+            crate::gas::intrinsic_gas(SpecId::LATEST, &calldata_long, false)
+                - crate::gas::intrinsic_gas(SpecId::LATEST, &calldata_short, false),
+            1024 * crate::gas::TX_DATA_NON_ZERO,
+        );
+        assert!(gas_short > crate::gas::TX_BASE);
+    }
+
+    #[cfg(feature = "coverage")]
+    #[test]
+    fn test_coverage_report() {
+        let (mut db, _) = setup_erc20(ALICE);
+        let erc20x = setup_erc20x(&mut db);
+
+        let selector_panic = get_selector_from_sig("panics()");
+        run_tx(
+            &mut db,
+            &erc20x,
+            get_calldata(selector_panic, vec![]),
+            &ALICE,
+        )
+        .expect_err("Tx succeeded");
+
+        let functions = crate::coverage::functions();
+        let panics = functions
+            .iter()
+            .find(|f| f.name.ends_with("::panics"))
+            .expect("`panics` not found in the coverage report");
+        assert!(panics.hits > 0, "`panics` isn't covered");
+        assert!(
+            !functions.iter().any(|f| f.name.contains("x_mint_fails")),
+            "Commented-out method found in the coverage report"
+        );
+
+        // Only public methods are listed as uncovered, and `panics` isn't one of them
+        let uncovered = crate::coverage::uncovered();
+        assert!(
+            uncovered
+                .iter()
+                .all(|f| !["core", "alloc", "compiler_builtins"].contains(&f.krate.as_str())),
+            "Library functions listed as uncovered: {uncovered:?}"
+        );
+        assert!(!uncovered.iter().any(|f| f.name.ends_with("::panics")));
+
+        // The contracts are compiled with source maps (`--coverage`), with which the line of
+        // `panics` is covered, while the commented-out lines of `x_mint_fails` aren't code
+        let lines = crate::coverage::lines();
+        assert!(
+            !lines.is_empty(),
+            "No source maps, compile the contracts with `--coverage`"
+        );
+        let source = include_str!("../../examples/erc20x/src/lib.rs");
+        let line_of = |pattern: &str| {
+            source
+                .lines()
+                .position(|line| line.contains(pattern))
+                .unwrap() as u32
+                + 1
+        };
+        // The crate of erc20x is generated from its sources, which keep their lines
+        let erc20x = |line: &&crate::coverage::LineCoverage| {
+            line.file.contains("erc20x") && line.file.ends_with("lib.rs")
+        };
+        let panics_line = line_of("pub fn panics");
+        let x_mint_fails =
+            line_of("pub fn x_mint_fails")..line_of("If the call fails, it re-attemps");
+
+        assert!(
+            lines
+                .iter()
+                .filter(erc20x)
+                .any(|l| l.line == panics_line && l.hits > 0),
+            "The line of `panics` isn't covered"
+        );
+        assert!(
+            !lines
+                .iter()
+                .filter(erc20x)
+                .any(|l| x_mint_fails.contains(&l.line)),
+            "Commented-out lines found in the coverage report"
+        );
+
+        let lcov = crate::coverage::lcov();
+        assert!(lcov.contains("end_of_record"), "Malformed lcov report");
+        assert!(
+            lcov.contains(&format!("DA:{},", panics_line)),
+            "Lines missing from the lcov report"
+        );
+    }
+
+    #[test]
+    fn test_recent_trace_events() {
+        initialize_logger();
+        let frame = tracing::debug_span!(target: FRAMES_TARGET, "frame", depth = 1);
+        let _frame = frame.enter();
+        for i in 0..TRACE_CONTEXT_EVENTS {
+            let syscall = tracing::trace_span!(
+                target: SYSCALLS_TARGET,
+                "syscall",
+                name = "sload",
+                key = tracing::field::Empty
+            );
+            let _syscall = syscall.enter();
+            syscall.record("key", i);
+            tracing::trace!(target: SYSCALLS_TARGET, gas = 2100, "charged");
+        }
+        // Events of other targets aren't kept
+        tracing::info!("unrelated");
+
+        // Only the latest events are kept, with the fields of their spans
+        let events = recent_trace_events();
+        assert_eq!(events.len(), TRACE_CONTEXT_EVENTS);
+        let last = TRACE_CONTEXT_EVENTS - 1;
+        assert_eq!(
+            events[events.len() - 2],
+            format!(
+                "frame{{depth=1}}:syscall{{name=\"sload\" key={}}}: charged gas=2100",
+                last
+            )
+        );
+        assert!(events[events.len() - 1].starts_with(&format!(
+            "frame{{depth=1}}:syscall{{name=\"sload\" key={}}}: done in",
+            last
+        )));
+    }
+
+    #[test]
+    fn test_return_data_range_fuzz() {
+        // xorshift, seeded so that failures can be replayed
+        let mut seed = 0x2545_f491_4f6c_dd1du64;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for _ in 0..100_000 {
+            let len = (next() % 300) as usize;
+            let buffer = vec![0xab; len];
+            // Mix edge cases in, as purely random values are almost always out of bounds
+            let mut pick = || match next() % 5 {
+                0 => 0,
+                1 => len as u64,
+                2 => len as u64 + 1,
+                3 => u64::MAX - next() % 4,
+                _ => next() % 320,
+            };
+            let (offset, size) = (pick(), pick());
+
+            let fits = offset
+                .checked_add(size)
+                .is_some_and(|end| end <= len as u64);
+            match crate::exec::return_data_range(len, offset, size) {
+                Some(range) => {
+                    assert!(fits, "offset: {offset}, size: {size}, len: {len}");
+                    assert_eq!(buffer[range].len() as u64, size);
+                }
+                None => assert!(!fits, "offset: {offset}, size: {size}, len: {len}"),
+            }
+        }
+    }
 }
@@ -0,0 +1,145 @@
+//! Detection of stale bytecode: `r55-compile` records in `r55.lock` the hash of each contract's
+//! bytecode and of the sources it was compiled from. Since the tests embed the bytecode with
+//! `include_bytes!`, editing a contract without recompiling it would otherwise go unnoticed.
+//!
+//! The check runs the first time the bytecode of a contract is fetched with `get_bytecode`, and
+//! can be skipped with `R55_SKIP_STALE_CHECK=1` for intentionally frozen artifacts.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use alloy_primitives::{keccak256, B256};
+use serde::Deserialize;
+
+/// Env var to skip the check
+pub const SKIP_ENV: &str = "R55_SKIP_STALE_CHECK";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Lockfile {
+    pub contracts: BTreeMap<String, LockEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LockEntry {
+    pub bytecode: B256,
+    pub sources: Vec<String>,
+    pub source_hash: B256,
+}
+
+impl Lockfile {
+    /// Loads the lockfile written by `r55-compile`
+    pub fn load() -> Result<Self, String> {
+        let path = project_root().join("r55-output-bytecode").join("r55.lock");
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+
+        serde_json::from_str(&content).map_err(|e| format!("invalid {}: {}", path.display(), e))
+    }
+}
+
+impl LockEntry {
+    /// Reason why the bytecode doesn't match this entry, if any
+    pub fn stale_reason(&self, root: &Path, bytecode: &[u8]) -> Option<String> {
+        if keccak256(bytecode) != self.bytecode {
+            return Some("the embedded bytecode differs from the compiled one".into());
+        }
+
+        match source_hash(root, &self.sources) {
+            Ok(hash) if hash == self.source_hash => None,
+            Ok(_) => Some(format!("its sources changed ({})", self.sources.join(", "))),
+            Err(e) => Some(format!("unable to hash its sources: {}", e)),
+        }
+    }
+}
+
+/// Panics if the bytecode of `contract_name` (named as in `get_bytecode`) is out of date with its
+/// sources. Each contract is only checked once per test binary.
+pub fn check_fresh(contract_name: &str, bytecode: &[u8]) {
+    static CHECKED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+    if env::var(SKIP_ENV).is_ok_and(|v| !v.is_empty() && v != "0") {
+        return;
+    }
+
+    let mut checked = CHECKED.lock().unwrap_or_else(|e| e.into_inner());
+    if checked.contains(contract_name) {
+        return;
+    }
+
+    let name = contract_name.replace('_', "-");
+    let reason = match Lockfile::load() {
+        Ok(lockfile) => match lockfile.contracts.get(&name) {
+            Some(entry) => entry.stale_reason(&project_root(), bytecode),
+            None => Some("it is missing from r55.lock".into()),
+        },
+        Err(e) => Some(e),
+    };
+
+    if let Some(reason) = reason {
+        panic!(
+            "contract {} is stale, rerun r55-compile: {} (set {}=1 to skip this check)",
+            name, reason, SKIP_ENV
+        );
+    }
+
+    checked.insert(contract_name.to_string());
+}
+
+fn project_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+// Hashes the `.rs` and `.toml` files under `dirs`, sorted by their path relative to `root`. Each
+// file contributes its path and its contents, so that renames are also detected.
+//
+// NOTE: must be kept in sync with `source_hash` in `r55-compile/src/lock.rs`
+pub fn source_hash(root: &Path, dirs: &[String]) -> std::io::Result<B256> {
+    let mut files = Vec::new();
+    for dir in dirs {
+        collect_sources(&root.join(dir), &mut files)?;
+    }
+
+    let mut files = files
+        .into_iter()
+        .map(|path| {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            (relative.to_string_lossy().replace('\\', "/"), path)
+        })
+        .collect::<Vec<_>>();
+    files.sort();
+
+    let mut preimage = Vec::new();
+    for (relative, path) in files {
+        preimage.extend_from_slice(relative.as_bytes());
+        preimage.push(0);
+        preimage.extend_from_slice(&fs::read(path)?);
+        preimage.push(0);
+    }
+
+    Ok(keccak256(preimage))
+}
+
+// Skips build outputs and hidden dirs
+fn collect_sources(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+        if path.is_dir() {
+            if name != "target" && !name.starts_with('.') {
+                collect_sources(&path, files)?;
+            }
+        } else if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("rs" | "toml")
+        ) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Enforcement of the `#[max_gas]` budgets of the contract methods: `r55-compile` lists them in the
+//! metadata trailer of the runtime code, and every call to a budgeted method is recorded along with
+//! the gas used by its frame (including its subcalls, but not the intrinsic cost of the tx).
+//!
+//! `send_tx` panics if one of the calls of the tx exceeded its budget. The check can be skipped
+//! with `R55_IGNORE_MAX_GAS=1`, e.g. while iterating on an implementation.
+
+use std::{cell::RefCell, env, fmt};
+
+use alloy_primitives::Address;
+
+/// Env var to skip the check
+pub const IGNORE_ENV: &str = "R55_IGNORE_MAX_GAS";
+
+thread_local! {
+    /// Budgeted calls of the transaction being executed.
+    static CALLS: RefCell<Vec<BudgetedCall>> = RefCell::default();
+}
+
+/// Call to a method with a `#[max_gas]` budget.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetedCall {
+    pub address: Address,
+    /// Name of the method, as in the contract's source
+    pub method: String,
+    pub max_gas: u64,
+    /// Gas used by the frame of the call
+    pub gas_used: u64,
+}
+
+impl BudgetedCall {
+    pub fn exceeds_budget(&self) -> bool {
+        self.gas_used > self.max_gas
+    }
+}
+
+impl fmt::Display for BudgetedCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} used {} {} {}",
+            self.method,
+            thousands(self.gas_used),
+            if self.exceeds_budget() { ">" } else { "<=" },
+            thousands(self.max_gas)
+        )
+    }
+}
+
+pub(crate) fn record(call: BudgetedCall) {
+    CALLS.with_borrow_mut(|calls| calls.push(call));
+}
+
+pub(crate) fn take() -> Vec<BudgetedCall> {
+    CALLS.take()
+}
+
+/// Panics if any of `calls` exceeded its budget, unless the check is skipped.
+pub fn check(calls: &[BudgetedCall]) {
+    if env::var(IGNORE_ENV).is_ok_and(|v| !v.is_empty() && v != "0") {
+        return;
+    }
+
+    let exceeded = calls
+        .iter()
+        .filter(|call| call.exceeds_budget())
+        .map(|call| format!("{} (at {})", call, call.address))
+        .collect::<Vec<_>>();
+    if !exceeded.is_empty() {
+        panic!(
+            "#[max_gas] budget exceeded: {} (set {}=1 to ignore budgets)",
+            exceeded.join(", "),
+            IGNORE_ENV
+        );
+    }
+}
+
+// Formats `n` with a comma every three digits, e.g. "63,120"
+fn thousands(n: u64) -> String {
+    let digits = n.to_string();
+    digits
+        .as_bytes()
+        .rchunks(3)
+        .rev()
+        .map(|group| std::str::from_utf8(group).expect("ASCII digits"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
@@ -0,0 +1,150 @@
+//! Process-wide cache of the programs loaded from RISC-V code, so that frames running the same code
+//! only parse its ELF (and measure its startup cost) once, and get a fresh emulator instantiated
+//! from its memory image.
+//!
+//! Programs are keyed by the code hash of the called account, or by the hash of the deploy code for
+//! creates, and shared by all the threads (and databases) of the process. The code of an account only changes when it's created or
+//! selfdestructed, and new code has a new hash: a contract re-deployed at the same address misses
+//! the cache rather than running the program of its previous code, so entries never need to be
+//! invalidated. Past `CAPACITY` programs, the least recently used one is evicted.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex, MutexGuard},
+};
+
+use alloy_primitives::B256;
+use eth_riscv_interpreter::Program;
+
+/// Max number of programs kept loaded.
+pub const CAPACITY: usize = 64;
+
+static PROGRAMS: LazyLock<Mutex<ProgramCache>> =
+    LazyLock::new(|| Mutex::new(ProgramCache::default()));
+
+#[derive(Default)]
+struct ProgramCache {
+    entries: HashMap<B256, Entry>,
+    // Incremented on every access, so that the entry with the lowest `last_used` is the LRU one
+    clock: u64,
+}
+
+/// Program loaded from runtime (or deploy) code.
+pub(crate) struct Loaded {
+    pub program: Program,
+    /// Gas of the instructions the program runs before reaching its own code (see
+    /// `exec::startup_cost`), which isn't charged to its frames
+    pub startup_cost: u64,
+}
+
+struct Entry {
+    program: Arc<Loaded>,
+    last_used: u64,
+}
+
+impl ProgramCache {
+    fn get(&mut self, code_hash: &B256) -> Option<Arc<Loaded>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(code_hash)?;
+        entry.last_used = self.clock;
+        Some(entry.program.clone())
+    }
+
+    fn insert(&mut self, code_hash: B256, program: Arc<Loaded>) {
+        if self.entries.len() >= CAPACITY && !self.entries.contains_key(&code_hash) {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(code_hash, _)| *code_hash);
+            if let Some(lru) = lru {
+                self.entries.remove(&lru);
+            }
+        }
+
+        self.clock += 1;
+        let last_used = self.clock;
+        self.entries.insert(code_hash, Entry { program, last_used });
+    }
+}
+
+fn cache() -> MutexGuard<'static, ProgramCache> {
+    PROGRAMS.lock().expect("program cache lock poisoned")
+}
+
+/// Program of the code `code`, whose hash is `code_hash`, loading it on a miss.
+pub(crate) fn get_or_load(
+    code_hash: B256,
+    code: &[u8],
+) -> eth_riscv_interpreter::Result<Arc<Loaded>> {
+    if let Some(program) = cache().get(&code_hash) {
+        return Ok(program);
+    }
+
+    // Parsed without holding the lock, so that threads loading other programs don't wait on it.
+    // Threads missing on the same code at once parse it more than once, and keep the last one.
+    let program = Program::load(code)?;
+    let startup_cost = crate::exec::startup_cost(&program);
+    let program = Arc::new(Loaded {
+        program,
+        startup_cost,
+    });
+    cache().insert(code_hash, program.clone());
+    Ok(program)
+}
+
+/// Whether the program of the code with hash `code_hash` is loaded.
+pub fn contains(code_hash: &B256) -> bool {
+    cache().entries.contains_key(code_hash)
+}
+
+/// Number of programs loaded.
+pub fn len() -> usize {
+    cache().entries.len()
+}
+
+/// Unloads all the programs, so that the next frames parse their code again.
+pub fn clear() {
+    cache().entries.clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Smallest ELF `Program::load` accepts: a header without program headers
+    fn empty_program() -> Arc<Loaded> {
+        let mut elf = vec![0u8; 64];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2; // ELFCLASS64
+        elf[5] = 1; // little endian
+        elf[6] = 1; // EV_CURRENT
+        elf[18] = 0xf3; // EM_RISCV
+        elf[52] = 64; // e_ehsize
+        Arc::new(Loaded {
+            program: Program::load(&elf).unwrap(),
+            startup_cost: 0,
+        })
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = ProgramCache::default();
+        let program = empty_program();
+        for i in 0..CAPACITY {
+            cache.insert(B256::with_last_byte(i as u8), program.clone());
+        }
+
+        // The first entry is the oldest one, until it's accessed again
+        assert!(cache.get(&B256::with_last_byte(0)).is_some());
+        cache.insert(B256::repeat_byte(0xff), program.clone());
+        assert_eq!(cache.entries.len(), CAPACITY);
+        assert!(cache.entries.contains_key(&B256::with_last_byte(0)));
+        assert!(!cache.entries.contains_key(&B256::with_last_byte(1)));
+
+        // Re-inserting a loaded program doesn't evict anything
+        cache.insert(B256::repeat_byte(0xff), program);
+        assert_eq!(cache.entries.len(), CAPACITY);
+        assert!(cache.entries.contains_key(&B256::with_last_byte(2)));
+    }
+}
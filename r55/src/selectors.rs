@@ -0,0 +1,217 @@
+//! Labels of the selectors of the compiled contracts, so that frame traces and reverts show e.g.
+//! `ERC20::transfer_from(address,address,uint256)` rather than `0x23b872dd`. They're read from the
+//! `selectors.json` that `r55-compile` writes next to the bytecode, or from the file set with
+//! `R55_SELECTORS`. Without a table, selectors are left as they are.
+//!
+//! A selector shared by several contracts is attributed to the one whose code is running, with
+//! the code hashes of the runtimes recorded in the table. Distinct signatures with the same
+//! selector (collisions) are all listed.
+
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use alloy_primitives::{hex, B256};
+use serde::Deserialize;
+use tracing::warn;
+
+/// Env var with the path of the selector table
+pub const SELECTORS_ENV: &str = "R55_SELECTORS";
+
+static TABLE: LazyLock<Option<SelectorTable>> = LazyLock::new(|| {
+    let path = env::var_os(SELECTORS_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(default_path);
+    match SelectorTable::load(&path) {
+        Ok(table) => Some(table),
+        // Missing unless the contracts were compiled, which only costs the labels
+        Err(_) if !path.exists() => None,
+        Err(e) => {
+            warn!("Selectors aren't labeled: {}", e);
+            None
+        }
+    }
+});
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SelectorTable {
+    #[serde(default)]
+    pub functions: BTreeMap<String, Vec<SelectorEntry>>,
+    #[serde(default)]
+    pub errors: BTreeMap<String, Vec<SelectorEntry>>,
+    /// By topic, i.e. the full hash of the signature
+    #[serde(default)]
+    pub events: BTreeMap<String, Vec<SelectorEntry>>,
+    /// Contract of each runtime, by the hash of its deployed code
+    #[serde(default)]
+    pub runtimes: BTreeMap<B256, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SelectorEntry {
+    pub signature: String,
+    pub contracts: Vec<String>,
+}
+
+impl SelectorTable {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e))?;
+
+        serde_json::from_str(&content).map_err(|e| format!("invalid {}: {}", path.display(), e))
+    }
+
+    /// Label of the method called with `input`, on the contract with code hash `code_hash` (if
+    /// known), e.g. `ERC20::transfer(address,uint256)`
+    pub fn function_label(&self, code_hash: Option<&B256>, input: &[u8]) -> Option<String> {
+        let entries = self.functions.get(&hex::encode_prefixed(input.get(..4)?))?;
+        let contract = code_hash.and_then(|hash| self.runtimes.get(hash));
+
+        // Signatures of the running contract, if it declares any of them
+        let declared = entries
+            .iter()
+            .filter(|entry| contract.is_some_and(|c| entry.contracts.contains(c)))
+            .collect::<Vec<_>>();
+        let labels = match (contract, declared.as_slice()) {
+            (Some(contract), [_, ..]) => declared
+                .iter()
+                .map(|entry| format!("{}::{}", contract, entry.signature))
+                .collect::<Vec<_>>(),
+            _ => entries.iter().map(entry_label).collect(),
+        };
+
+        Some(labels.join(" | "))
+    }
+
+    /// Label of the error encoded in the revert data `output`, e.g. `ERC20Error::ZeroAmount`
+    pub fn error_label(&self, output: &[u8]) -> Option<String> {
+        let entries = self.errors.get(&hex::encode_prefixed(output.get(..4)?))?;
+        let signatures = entries.iter().map(|entry| entry.signature.as_str());
+        Some(signatures.collect::<Vec<_>>().join(" | "))
+    }
+
+    /// Label of the event with first topic `topic`, e.g. `Transfer(address,address,uint256)`
+    pub fn event_label(&self, topic: &B256) -> Option<String> {
+        let entries = self.events.get(&topic.to_string())?;
+        let signatures = entries.iter().map(|entry| entry.signature.as_str());
+        Some(signatures.collect::<Vec<_>>().join(" | "))
+    }
+}
+
+// `Contract::signature` if a single contract declares it, the bare signature otherwise
+fn entry_label(entry: &SelectorEntry) -> String {
+    match entry.contracts.as_slice() {
+        [contract] => format!("{}::{}", contract, entry.signature),
+        _ => entry.signature.clone(),
+    }
+}
+
+/// Selector table of the compiled contracts, if any.
+pub fn table() -> Option<&'static SelectorTable> {
+    TABLE.as_ref()
+}
+
+/// `selectors.json` in the output dir of `r55-compile`.
+pub fn default_path() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("r55-output-bytecode")
+        .join("selectors.json")
+}
+
+/// Label of a call frame: the signature of its method, or its raw selector if it's unknown.
+pub fn call_label(code_hash: Option<&B256>, input: &[u8]) -> String {
+    table()
+        .and_then(|table| table.function_label(code_hash, input))
+        .unwrap_or_else(|| hex::encode_prefixed(&input[..input.len().min(4)]))
+}
+
+/// Label of the error of the revert data `output`, if it's a known one.
+pub fn error_label(output: &[u8]) -> Option<String> {
+    table()?.error_label(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::keccak256;
+
+    fn selector(signature: &str) -> String {
+        hex::encode_prefixed(&keccak256(signature)[..4])
+    }
+
+    fn entry(signature: &str, contracts: &[&str]) -> SelectorEntry {
+        SelectorEntry {
+            signature: signature.into(),
+            contracts: contracts.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    // `transfer(address,uint256)` collides with `many_msg_babbage(bytes1)`
+    fn table() -> SelectorTable {
+        SelectorTable {
+            functions: BTreeMap::from([
+                (
+                    selector("transfer(address,uint256)"),
+                    vec![
+                        entry("transfer(address,uint256)", &["ERC20", "WETH"]),
+                        entry("many_msg_babbage(bytes1)", &["Babbage"]),
+                    ],
+                ),
+                (
+                    selector("transfer_from(address,address,uint256)"),
+                    vec![entry("transfer_from(address,address,uint256)", &["ERC20"])],
+                ),
+            ]),
+            errors: BTreeMap::from([(
+                selector("ERC20Error::ZeroAmount"),
+                vec![entry("ERC20Error::ZeroAmount", &["ERC20"])],
+            )]),
+            events: BTreeMap::new(),
+            runtimes: BTreeMap::from([(B256::repeat_byte(0x11), "WETH".into())]),
+        }
+    }
+
+    #[test]
+    fn test_function_label() {
+        let table = table();
+        let weth = B256::repeat_byte(0x11);
+        let transfer = keccak256("transfer(address,uint256)")[..4].to_vec();
+
+        assert_eq!(
+            table.function_label(
+                None,
+                &keccak256("transfer_from(address,address,uint256)")[..4]
+            ),
+            Some("ERC20::transfer_from(address,address,uint256)".into())
+        );
+        // The running contract picks its own signature among the colliding ones
+        assert_eq!(
+            table.function_label(Some(&weth), &transfer),
+            Some("WETH::transfer(address,uint256)".into())
+        );
+        // Otherwise every candidate is listed
+        assert_eq!(
+            table.function_label(None, &transfer),
+            Some("transfer(address,uint256) | Babbage::many_msg_babbage(bytes1)".into())
+        );
+        assert_eq!(table.function_label(None, &[0xde, 0xad, 0xbe, 0xef]), None);
+        assert_eq!(table.function_label(None, &[0xa9]), None);
+    }
+
+    #[test]
+    fn test_error_label() {
+        let table = table();
+        let mut output = keccak256("ERC20Error::ZeroAmount")[..4].to_vec();
+        assert_eq!(
+            table.error_label(&output),
+            Some("ERC20Error::ZeroAmount".into())
+        );
+
+        output[0] ^= 1;
+        assert_eq!(table.error_label(&output), None);
+    }
+}
@@ -0,0 +1,109 @@
+//! State changes of a transaction, computed from the journaled state before it is committed, to
+//! audit what a call actually touched.
+
+use core::fmt;
+use std::{collections::BTreeMap, fmt::Write};
+
+use revm::{
+    primitives::{Address, EvmState, U256},
+    Database,
+};
+
+use crate::test_utils::StoragePrinter;
+
+/// Changes of the accounts touched by a transaction. Accounts that were only read are omitted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    pub accounts: BTreeMap<Address, AccountDiff>,
+}
+
+/// Changes of an account, as (old, new) values
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountDiff {
+    pub balance: Option<(U256, U256)>,
+    pub nonce: Option<(u64, u64)>,
+    pub storage: BTreeMap<U256, (U256, U256)>,
+}
+
+impl AccountDiff {
+    pub fn is_empty(&self) -> bool {
+        self.balance.is_none() && self.nonce.is_none() && self.storage.is_empty()
+    }
+}
+
+impl StateDiff {
+    /// Diffs the state of a transaction against the db it is about to be committed to
+    pub(crate) fn new<DB: Database>(db: &mut DB, state: &EvmState) -> Result<Self, DB::Error> {
+        let mut accounts = BTreeMap::new();
+        for (address, account) in state {
+            let old = db.basic(*address)?.unwrap_or_default();
+
+            let diff = AccountDiff {
+                balance: (old.balance != account.info.balance)
+                    .then_some((old.balance, account.info.balance)),
+                nonce: (old.nonce != account.info.nonce).then_some((old.nonce, account.info.nonce)),
+                storage: account
+                    .storage
+                    .iter()
+                    .filter(|(_, slot)| slot.is_changed())
+                    .map(|(key, slot)| (*key, (slot.original_value(), slot.present_value())))
+                    .collect(),
+            };
+
+            if !diff.is_empty() {
+                accounts.insert(*address, diff);
+            }
+        }
+
+        Ok(Self { accounts })
+    }
+
+    /// Storage slots of `addr` written with a new value, with their (old, new) values
+    pub fn storage(&self, addr: Address) -> BTreeMap<U256, (U256, U256)> {
+        self.accounts
+            .get(&addr)
+            .map(|account| account.storage.clone())
+            .unwrap_or_default()
+    }
+
+    /// Formats the diff, annotating the slots of the accounts that have a printer (i.e. with their
+    /// storage layout) with the names of their fields
+    pub fn format(&self, printers: &[(Address, &StoragePrinter)]) -> String {
+        let mut output = String::new();
+        for (address, account) in &self.accounts {
+            let labels = printers
+                .iter()
+                .find(|(printer_addr, _)| printer_addr == address)
+                .map(|(_, printer)| printer.labels())
+                .unwrap_or_default();
+
+            let _ = writeln!(output, "{}:", address);
+            if let Some((old, new)) = account.balance {
+                let _ = writeln!(output, "  balance: {} -> {}", old, new);
+            }
+            if let Some((old, new)) = account.nonce {
+                let _ = writeln!(output, "  nonce: {} -> {}", old, new);
+            }
+            for (slot, (old, new)) in &account.storage {
+                let _ = match labels.get(slot) {
+                    Some(label) => {
+                        writeln!(
+                            output,
+                            "  {:#066x} ({}): {:#x} -> {:#x}",
+                            slot, label, old, new
+                        )
+                    }
+                    None => writeln!(output, "  {:#066x}: {:#x} -> {:#x}", slot, old, new),
+                };
+            }
+        }
+
+        output
+    }
+}
+
+impl fmt::Display for StateDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&[]))
+    }
+}
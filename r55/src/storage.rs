@@ -0,0 +1,264 @@
+//! Typed reads of contract storage, resolving paths like `"allowance_of[alice][bob]"` through the
+//! storage layouts exported by `r55-compile`, instead of computing the slots by hand.
+
+use std::collections::HashMap;
+
+use alloy_core::dyn_abi::{DynSolType, DynSolValue};
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::SolValue;
+use revm::Database;
+
+use crate::{
+    layout::{LayoutError, StorageLayout},
+    test_utils::{get_mapping_slot, ALICE, BOB, CAROL},
+};
+
+/// Failure to resolve or read a storage path
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum StorageError {
+    #[error(transparent)]
+    Layout(#[from] LayoutError),
+    #[error("Invalid path `{0}`")]
+    InvalidPath(String),
+    #[error("Field `{field}` not found in `{contract}`")]
+    UnknownField { contract: String, field: String },
+    #[error("`{path}` is a `{ty}`, which can't be indexed")]
+    NotAMapping { path: String, ty: String },
+    #[error("`{path}` is a `{ty}`, which isn't a single value")]
+    NotAValue { path: String, ty: String },
+    #[error("`{0}` has no Solidity equivalent")]
+    UnsupportedType(String),
+    #[error("Invalid `{ty}` key `{key}`: {err}")]
+    InvalidKey {
+        key: String,
+        ty: String,
+        err: String,
+    },
+    #[error("Unable to read slot {0:#x}")]
+    Read(U256),
+}
+
+/// Value read from storage, along with where it comes from
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageValue {
+    pub slot: U256,
+    pub raw: U256,
+    pub value: DynSolValue,
+}
+
+/// Reader of the storage of a deployed contract, through its exported layout.
+///   ```ignore
+///   let token = StorageReader::load("erc20", token_addr)?.with_key("spender", spender.abi_encode());
+///   assert_field_eq!(db, token, "balance_of[alice]", U256::from(100));
+///   assert_field_eq!(db, token, "allowance_of[alice][spender]", U256::ZERO);
+///   ```
+///
+/// Mapping keys are either labels, registered with `with_key` (`alice`, `bob` and `carol` are
+/// registered by default), or literals of the key type (e.g. `0xdead...beef` or `42`).
+#[derive(Debug, Clone)]
+pub struct StorageReader {
+    pub address: Address,
+    layout: StorageLayout,
+    keys: HashMap<String, Vec<u8>>,
+}
+
+impl StorageReader {
+    pub fn new(layout: StorageLayout, address: Address) -> Self {
+        let keys = [("alice", ALICE), ("bob", BOB), ("carol", CAROL)]
+            .into_iter()
+            .map(|(label, addr)| (label.to_string(), addr.abi_encode()))
+            .collect();
+
+        Self {
+            address,
+            layout,
+            keys,
+        }
+    }
+
+    /// Reader of a contract (named as in `get_bytecode`) deployed at `address`
+    pub fn load(contract_name: &str, address: Address) -> Result<Self, StorageError> {
+        Ok(Self::new(StorageLayout::load(contract_name)?, address))
+    }
+
+    /// Registers an (ABI-encoded) mapping key, referred to as `label` in the paths
+    pub fn with_key(mut self, label: impl Into<String>, key: Vec<u8>) -> Self {
+        self.keys.insert(label.into(), key);
+        self
+    }
+
+    /// Slot of the value at `path`, and its Solidity type
+    pub fn resolve(&self, path: &str) -> Result<(U256, DynSolType), StorageError> {
+        let (field, keys) = parse_path(path)?;
+        let field = self
+            .layout
+            .fields
+            .iter()
+            .find(|f| f.name == field)
+            .ok_or_else(|| StorageError::UnknownField {
+                contract: self.layout.contract.clone(),
+                field: field.to_string(),
+            })?;
+
+        // Mirrors the slot derivation of the runtime's `Mapping`
+        let (mut slot, mut ty) = (field.slot, field.ty.as_str());
+        for key in keys {
+            let Some((key_ty, value_ty)) = generic_args(ty, "Mapping") else {
+                return Err(StorageError::NotAMapping {
+                    path: path.to_string(),
+                    ty: ty.to_string(),
+                });
+            };
+            slot = get_mapping_slot(self.encode_key(key, key_ty)?, slot);
+            ty = value_ty;
+        }
+
+        match generic_args(ty, "Slot") {
+            Some((value_ty, "")) => Ok((slot, sol_type(value_ty)?)),
+            _ => Err(StorageError::NotAValue {
+                path: path.to_string(),
+                ty: ty.to_string(),
+            }),
+        }
+    }
+
+    /// Reads and decodes the value at `path`
+    pub fn read<DB: Database>(
+        &self,
+        db: &mut DB,
+        path: &str,
+    ) -> Result<StorageValue, StorageError> {
+        let (slot, ty) = self.resolve(path)?;
+        let raw = db
+            .storage(self.address, slot)
+            .map_err(|_| StorageError::Read(slot))?;
+
+        Ok(StorageValue {
+            slot,
+            raw,
+            value: decode_word(&ty, raw),
+        })
+    }
+
+    /// Asserts that the value at `path` is `expected`, see `assert_field_eq!`
+    #[track_caller]
+    pub fn assert_eq<DB: Database, V: SolValue>(&self, db: &mut DB, path: &str, expected: V) {
+        let fail = |e: StorageError| -> ! { panic!("Unable to read `{}`: {}", path, e) };
+        let (_, ty) = self.resolve(path).unwrap_or_else(|e| fail(e));
+        let read = self.read(db, path).unwrap_or_else(|e| fail(e));
+        let expected_word = U256::from_be_slice(&expected.abi_encode());
+        let expected = decode_word(&ty, expected_word);
+
+        assert!(
+            read.value == expected,
+            "Unexpected value of `{}` in {}:\n > slot: {:#066x}\n > raw word: {:#066x}\n > decoded: {:?}\n > expected: {:?}",
+            path,
+            self.address,
+            read.slot,
+            read.raw,
+            read.value,
+            expected
+        );
+    }
+
+    fn encode_key(&self, key: &str, ty: &str) -> Result<Vec<u8>, StorageError> {
+        if let Some(bytes) = self.keys.get(key) {
+            return Ok(bytes.clone());
+        }
+
+        let invalid = |err: String| StorageError::InvalidKey {
+            key: key.to_string(),
+            ty: ty.to_string(),
+            err,
+        };
+        let value = sol_type(ty)?
+            .coerce_str(key)
+            .map_err(|e| invalid(e.to_string()))?;
+        Ok(value.abi_encode())
+    }
+}
+
+/// Asserts that a field of a contract's storage has the expected value, reading it through a
+/// `StorageReader`:
+///   ```ignore
+///   assert_field_eq!(db, token, "balance_of[alice]", U256::from(100));
+///   ```
+#[macro_export]
+macro_rules! assert_field_eq {
+    ($db:expr, $reader:expr, $path:expr, $expected:expr $(,)?) => {
+        $reader.assert_eq(&mut $db, $path, $expected)
+    };
+}
+
+// Splits `field[key1][key2]` into the field name and its keys
+fn parse_path(path: &str) -> Result<(&str, Vec<&str>), StorageError> {
+    let invalid = || StorageError::InvalidPath(path.to_string());
+    let (field, mut rest) = match path.find('[') {
+        Some(i) => path.split_at(i),
+        None => (path, ""),
+    };
+    if field.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut keys = Vec::new();
+    while !rest.is_empty() {
+        let (key, tail) = rest
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .ok_or_else(invalid)?;
+        keys.push(key.trim());
+        rest = tail;
+    }
+
+    Ok((field.trim(), keys))
+}
+
+// Splits the arguments of a 2-parameter (or 1-parameter, the second being empty) generic type
+// with the given name, e.g. `Mapping<Address,Slot<U256>>` into `Address` and `Slot<U256>`
+fn generic_args<'a>(ty: &'a str, name: &str) -> Option<(&'a str, &'a str)> {
+    let args = ty
+        .strip_prefix(name)?
+        .strip_prefix('<')?
+        .strip_suffix('>')?;
+
+    let mut depth = 0;
+    for (i, c) in args.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            ',' if depth == 0 => return Some((&args[..i], &args[i + 1..])),
+            _ => {}
+        }
+    }
+
+    Some((args, ""))
+}
+
+// Solidity type of a Rust storage type
+fn sol_type(ty: &str) -> Result<DynSolType, StorageError> {
+    let sol = match ty {
+        "Address" => "address".to_string(),
+        "bool" => "bool".to_string(),
+        "B256" => "bytes32".to_string(),
+        "U256" => "uint256".to_string(),
+        "I256" => "int256".to_string(),
+        ty => {
+            let bits = ty.get(1..).and_then(|bits| bits.parse::<u16>().ok());
+            match (ty.chars().next(), bits) {
+                (Some('u'), Some(bits)) => format!("uint{}", bits),
+                (Some('i'), Some(bits)) => format!("int{}", bits),
+                _ => return Err(StorageError::UnsupportedType(ty.to_string())),
+            }
+        }
+    };
+
+    DynSolType::parse(&sol).map_err(|_| StorageError::UnsupportedType(ty.to_string()))
+}
+
+// Decodes a storage word as stored by the runtime's `Slot`: ABI-encoded in a single word. Words
+// that don't decode (e.g. dirty high bits) are kept as raw uints.
+fn decode_word(ty: &DynSolType, word: U256) -> DynSolValue {
+    ty.abi_decode(&word.to_be_bytes::<32>())
+        .unwrap_or(DynSolValue::Uint(word, 256))
+}
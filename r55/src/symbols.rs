@@ -0,0 +1,429 @@
+//! Symbolized backtraces of the RISC-V frames that trap or panic.
+//!
+//! Contracts aren't built with frame pointers, so the callers of the failing PC are recovered by
+//! scanning the stack for return addresses: words that point right after a call (`jal`/`jalr` to
+//! `ra`) in an executable segment of the code. The PCs are then mapped to the function symbols of
+//! the ELF and, with the line tables of the `--min-size` builds, to their source locations.
+//!
+//! `--min-size` runtimes are stripped of their symbols, which are read from the debug copy that
+//! `r55-compile` keeps next to the bytecode (`<contract>.runtime.debug`) instead. Along with it,
+//! `r55-compile` exports the source map of the runtime (`<contract>.sourcemap.json`).
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt, fs,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::LazyLock,
+};
+
+use alloy_primitives::{keccak256, Bytes, B256};
+use goblin::elf::{program_header::PT_LOAD, Elf};
+use rvemu::emulator::Emulator;
+use serde::Deserialize;
+
+/// Max number of frames of a backtrace, besides the failing PC.
+pub const MAX_FRAMES: usize = 16;
+
+// Bytes of the stack scanned for return addresses, from the stack pointer
+const SCAN_SIZE: u64 = 16 * 1024;
+
+// Opcodes of `jal` and `jalr`, and the `rd` of the calls, i.e. `ra`
+const JAL: u32 = 0x6f;
+const JALR: u32 = 0x67;
+const RA: u32 = 1;
+
+thread_local! {
+    /// Symbolizers of the codes that failed on this thread, by hash.
+    static SYMBOLIZERS: RefCell<HashMap<B256, Rc<Symbolizer>>> = RefCell::default();
+}
+
+/// Debug copies of the runtimes in the output dir, by the hash of their loadable segments.
+static DEBUG_COPIES: LazyLock<HashMap<B256, PathBuf>> = LazyLock::new(|| {
+    let mut copies = HashMap::new();
+    let Ok(entries) = fs::read_dir(output_dir()) else {
+        return copies;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if !path.to_string_lossy().ends_with(".runtime.debug") {
+            continue;
+        }
+        let Ok(elf) = fs::read(&path) else { continue };
+        if let Some(hash) = segments_hash(&elf) {
+            copies.insert(hash, path);
+        }
+    }
+    copies
+});
+
+/// Source maps in the output dir, by the hash of the loadable segments of their runtime.
+static SOURCE_MAPS: LazyLock<HashMap<B256, SourceMap>> = LazyLock::new(|| {
+    let mut maps = HashMap::new();
+    let Ok(entries) = fs::read_dir(output_dir()) else {
+        return maps;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        if !path.to_string_lossy().ends_with(".sourcemap.json") {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Ok(map) = serde_json::from_str::<SourceMap>(&content) {
+            maps.insert(map.code_hash, map);
+        }
+    }
+    maps
+});
+
+/// Source line of each range of PCs of a runtime, as exported by `r55-compile`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SourceMap {
+    pub contract: String,
+    /// Hash of the loadable segments of the runtime
+    pub code_hash: B256,
+    /// Sorted by address
+    pub lines: Vec<SourceLine>,
+}
+
+/// Source line of the instructions in `start..end`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct SourceLine {
+    pub start: u64,
+    pub end: u64,
+    pub file: String,
+    pub line: u32,
+}
+
+/// Source map of `code`, if `r55-compile` exported one for it.
+pub fn source_map(code: &[u8]) -> Option<&'static SourceMap> {
+    SOURCE_MAPS.get(&segments_hash(code)?)
+}
+
+/// Failing PC of a frame and the return addresses found on its stack, innermost first.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Backtrace {
+    /// ELF of the failing frame
+    pub code: Bytes,
+    pub pcs: Vec<u64>,
+}
+
+/// Symbolized PC of a backtrace. A PC within inlined calls has a frame per inlined function, all
+/// but the outermost one being flagged as `inlined`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub pc: u64,
+    /// Demangled name of the function, without its hash
+    pub function: Option<String>,
+    /// `file:line`, with source maps
+    pub location: Option<String>,
+    pub inlined: bool,
+}
+
+impl Backtrace {
+    /// Symbolized frames, innermost first.
+    pub fn frames(&self) -> Vec<Frame> {
+        let symbolizer = symbolizer(&self.code);
+        self.pcs
+            .iter()
+            .flat_map(|pc| symbolizer.frames(*pc))
+            .collect()
+    }
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, frame) in self.frames().iter().enumerate() {
+            write!(f, "\n  {:>2}: {}", i, frame)?;
+        }
+        Ok(())
+    }
+}
+
+// The code is left out, as it's as long as the binary
+impl fmt::Debug for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.frames()).finish()
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#x}", self.pc)?;
+        if let Some(function) = &self.function {
+            write!(f, " in {}", function)?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " at {}", location)?;
+        }
+        if self.inlined {
+            write!(f, " (inlined)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Symbols and line tables of an ELF binary.
+pub struct Symbolizer {
+    /// Function symbols, sorted by address
+    functions: Vec<(Range<u64>, String)>,
+    /// Executable segments, where return addresses point to
+    text: Vec<Range<u64>>,
+    lines: Option<addr2line::Context<gimli::EndianRcSlice<gimli::RunTimeEndian>>>,
+}
+
+impl Symbolizer {
+    /// Symbolizer of `code`, or of its debug copy if it has no symbols of its own.
+    pub fn new(code: &[u8]) -> Self {
+        let own = Self::from_elf(code);
+        if !own.functions.is_empty() {
+            return own;
+        }
+
+        let debug = segments_hash(code)
+            .and_then(|hash| DEBUG_COPIES.get(&hash).cloned())
+            .and_then(|path| fs::read(path).ok());
+        match debug {
+            Some(debug) => Self::from_elf(&debug),
+            None => own,
+        }
+    }
+
+    fn from_elf(elf_data: &[u8]) -> Self {
+        let Ok(elf) = Elf::parse(elf_data) else {
+            return Self {
+                functions: Vec::new(),
+                text: Vec::new(),
+                lines: None,
+            };
+        };
+
+        let mut functions = elf
+            .syms
+            .iter()
+            .filter(|sym| sym.is_function() && sym.st_value != 0)
+            .filter_map(|sym| {
+                let name = elf.strtab.get_at(sym.st_name)?;
+                let end = sym.st_value + sym.st_size.max(1);
+                Some((sym.st_value..end, demangle(name)))
+            })
+            .collect::<Vec<_>>();
+        functions.sort_by_key(|(range, _)| range.start);
+
+        let text = elf
+            .program_headers
+            .iter()
+            .filter(|ph| ph.p_type == PT_LOAD && ph.is_executable())
+            .map(|ph| ph.p_vaddr..ph.p_vaddr + ph.p_memsz)
+            .collect();
+
+        Self {
+            functions,
+            text,
+            lines: line_tables(&elf, elf_data),
+        }
+    }
+
+    /// Frames of `pc`: the functions inlined at it, if known, and the one it belongs to.
+    pub fn frames(&self, pc: u64) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        if let Some(lines) = &self.lines {
+            if let Ok(mut iter) = lines.find_frames(pc).skip_all_loads() {
+                while let Ok(Some(frame)) = iter.next() {
+                    let function = frame
+                        .function
+                        .and_then(|name| Some(demangle(name.raw_name().ok()?.as_ref())));
+                    let location = frame.location.and_then(|location| {
+                        Some(format!("{}:{}", location.file?, location.line?))
+                    });
+                    frames.push(Frame {
+                        pc,
+                        function,
+                        location,
+                        inlined: true,
+                    });
+                }
+            }
+        }
+
+        match frames.last_mut() {
+            Some(outermost) => {
+                outermost.inlined = false;
+                if outermost.function.is_none() {
+                    outermost.function = self.function(pc).map(Into::into);
+                }
+            }
+            None => frames.push(Frame {
+                pc,
+                function: self.function(pc).map(Into::into),
+                location: None,
+                inlined: false,
+            }),
+        }
+
+        frames
+    }
+
+    /// Function symbols, sorted by address.
+    pub fn functions(&self) -> &[(Range<u64>, String)] {
+        &self.functions
+    }
+
+    /// Source file and line of `pc`, innermost if it's within inlined calls, with source maps.
+    pub fn line(&self, pc: u64) -> Option<(String, u32)> {
+        let location = self.lines.as_ref()?.find_location(pc).ok()??;
+        Some((location.file?.to_string(), location.line?))
+    }
+
+    /// Source lines of the code, with the address range of each sequence of their instructions.
+    /// Empty without source maps.
+    pub fn lines(&self) -> Vec<(Range<u64>, String, u32)> {
+        let Some(lines) = &self.lines else {
+            return Vec::new();
+        };
+        let Ok(locations) = lines.find_location_range(0, u64::MAX) else {
+            return Vec::new();
+        };
+        locations
+            .filter_map(|(addr, len, location)| {
+                Some((addr..addr + len, location.file?.to_string(), location.line?))
+            })
+            .collect()
+    }
+
+    /// Name of the function symbol that contains `pc`.
+    pub fn function(&self, pc: u64) -> Option<&str> {
+        let i = self
+            .functions
+            .partition_point(|(range, _)| range.start <= pc);
+        let (range, name) = self.functions.get(i.checked_sub(1)?)?;
+        range.contains(&pc).then_some(name.as_str())
+    }
+
+    // Whether `addr` is the return address of a call, i.e. follows a `jal`/`jalr` that links `ra`
+    fn is_return_address(&self, emu: &mut Emulator, addr: u64) -> bool {
+        if !self.text.iter().any(|text| text.contains(&addr)) {
+            return false;
+        }
+        let Some(start) = addr.checked_sub(4) else {
+            return false;
+        };
+        let Ok(bytes) = emu.cpu.bus.get_dram_slice(start..addr) else {
+            return false;
+        };
+        let Ok(bytes) = <[u8; 4]>::try_from(&bytes[..]) else {
+            return false;
+        };
+        let inst = u32::from_le_bytes(bytes);
+
+        // `c.jalr rs1` is the only compressed call of rv64
+        let compressed = (inst >> 16) as u16;
+        let c_jalr = compressed & 0xf07f == 0x9002 && (compressed >> 7) & 0x1f != 0;
+        let linked = matches!(inst & 0x7f, JAL | JALR) && (inst >> 7) & 0x1f == RA;
+
+        c_jalr || linked
+    }
+}
+
+/// Backtrace of the frame running `code`, which failed at the current PC of `emu`. The stack is
+/// scanned from the stack pointer up to `stack_top`.
+pub(crate) fn capture(emu: &mut Emulator, code: Bytes, stack_top: u64) -> Backtrace {
+    let symbolizer = symbolizer(&code);
+    let mut pcs = vec![emu.cpu.pc];
+
+    // `ra` is stale once the function called something, unless it leads out of it
+    let ra = emu.cpu.xregs.read(1);
+    if symbolizer.function(ra) != symbolizer.function(emu.cpu.pc)
+        && symbolizer.is_return_address(emu, ra)
+    {
+        pcs.push(ra);
+    }
+
+    let sp = emu.cpu.xregs.read(2);
+    let end = stack_top.min(sp.saturating_add(SCAN_SIZE));
+    let mut addr = sp.next_multiple_of(8);
+    while addr + 8 <= end && pcs.len() <= MAX_FRAMES {
+        let word = emu
+            .cpu
+            .bus
+            .get_dram_slice(addr..addr + 8)
+            .ok()
+            .and_then(|bytes| <[u8; 8]>::try_from(&bytes[..]).ok())
+            .map(u64::from_le_bytes);
+        if let Some(word) = word {
+            // Return addresses are one past the call, while the PC of a frame is the call itself
+            if symbolizer.is_return_address(emu, word) && pcs.last() != Some(&word) {
+                pcs.push(word);
+            }
+        }
+        addr += 8;
+    }
+
+    // PCs of the callers are the calls, which report the caller's location rather than the next line
+    for pc in pcs.iter_mut().skip(1) {
+        *pc -= 1;
+    }
+
+    Backtrace { code, pcs }
+}
+
+/// Symbolizer of `code`, cached on this thread.
+pub(crate) fn symbolizer(code: &[u8]) -> Rc<Symbolizer> {
+    let hash = keccak256(code);
+    SYMBOLIZERS.with_borrow_mut(|symbolizers| {
+        symbolizers
+            .entry(hash)
+            .or_insert_with(|| Rc::new(Symbolizer::new(code)))
+            .clone()
+    })
+}
+
+// Line tables of the binary, if it was compiled with them
+fn line_tables(
+    elf: &Elf,
+    elf_data: &[u8],
+) -> Option<addr2line::Context<gimli::EndianRcSlice<gimli::RunTimeEndian>>> {
+    let endian = if elf.little_endian {
+        gimli::RunTimeEndian::Little
+    } else {
+        gimli::RunTimeEndian::Big
+    };
+    let section = |name: &str| {
+        elf.section_headers
+            .iter()
+            .find(|sh| elf.shdr_strtab.get_at(sh.sh_name) == Some(name))
+            .and_then(|sh| elf_data.get(sh.file_range()?))
+    };
+    section(".debug_line")?;
+
+    let dwarf = gimli::Dwarf::load(|id| {
+        let data = section(id.name()).unwrap_or_default();
+        Ok::<_, gimli::Error>(gimli::EndianRcSlice::new(Rc::from(data), endian))
+    })
+    .ok()?;
+    addr2line::Context::from_dwarf(dwarf).ok()
+}
+
+// Hash of the loadable segments of a binary, which its stripped copy shares
+fn segments_hash(elf_data: &[u8]) -> Option<B256> {
+    let elf = Elf::parse(elf_data).ok()?;
+    let mut preimage = Vec::new();
+    for ph in elf.program_headers.iter().filter(|ph| ph.p_type == PT_LOAD) {
+        preimage.extend_from_slice(&ph.p_vaddr.to_le_bytes());
+        preimage.extend_from_slice(elf_data.get(ph.file_range())?);
+    }
+    Some(keccak256(preimage))
+}
+
+// Demangled name, without the trailing hash
+fn demangle(name: &str) -> String {
+    format!("{:#}", rustc_demangle::demangle(name))
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("r55-output-bytecode")
+}
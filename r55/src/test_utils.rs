@@ -1,11 +1,43 @@
-use alloy_core::hex::FromHex;
-use alloy_primitives::address;
+pub use alloy_core::dyn_abi::DynSolValue;
+use alloy_core::{
+    dyn_abi::{FunctionExt, JsonAbiExt},
+    hex::FromHex,
+    json_abi::Function,
+};
+use alloy_primitives::{address, Log};
+use alloy_sol_types::SolEvent;
 use revm::Database;
 pub use revm::{
     primitives::{keccak256, ruint::Uint, AccountInfo, Address, Bytecode, Bytes, U256},
     InMemoryDB,
 };
-use std::{fs, path::Path, sync::Once};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::{self, Write},
+    fs,
+    path::Path,
+    sync::Once,
+    time::Instant,
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    filter::{EnvFilter, Targets},
+    fmt::format::FmtSpan,
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    Layer,
+};
+
+pub use crate::exec::{BlockEnvBuilder, MinePolicy};
+use crate::{
+    exec::{deploy_contract, FRAMES_TARGET, SYSCALLS_TARGET},
+    get_bytecode,
+    layout::StorageLayout,
+};
 
 static INIT: Once = Once::new();
 
@@ -13,23 +45,162 @@ pub const ALICE: Address = address!("000000000000000000000000000000000000000A");
 pub const BOB: Address = address!("000000000000000000000000000000000000000B");
 pub const CAROL: Address = address!("000000000000000000000000000000000000000C");
 
+/// Number of frame and syscall events of the failing test that are printed when it panics.
+pub const TRACE_CONTEXT_EVENTS: usize = 64;
+
+thread_local! {
+    /// Latest frame and syscall events of the test running on this thread.
+    static RECENT_EVENTS: RefCell<VecDeque<String>> = RefCell::default();
+}
+
+/// Sets up the subscriber of the tests: logs are filtered with `RUST_LOG` (e.g.
+/// `RUST_LOG=info,r55::syscalls=trace`), while the latest frame and syscall events are always kept,
+/// and printed as context if the test panics.
 pub fn initialize_logger() {
     INIT.call_once(|| {
         let log_level = std::env::var("RUST_LOG").unwrap_or("INFO".to_owned());
-        let tracing_sub = tracing_subscriber::fmt()
-            .with_max_level(tracing::Level::DEBUG)
-            .with_env_filter(tracing_subscriber::EnvFilter::new(log_level))
+        let fmt = tracing_subscriber::fmt::layer()
             .with_target(false)
-            .finish();
-        tracing::subscriber::set_global_default(tracing_sub)
-            .expect("Setting tracing subscriber failed");
+            .with_span_events(FmtSpan::CLOSE)
+            .with_filter(EnvFilter::new(log_level));
+        let recent = RecentEvents.with_filter(
+            Targets::new()
+                .with_target(FRAMES_TARGET, Level::TRACE)
+                .with_target(SYSCALLS_TARGET, Level::TRACE),
+        );
+        tracing::subscriber::set_global_default(
+            tracing_subscriber::registry().with(fmt).with(recent),
+        )
+        .expect("Setting tracing subscriber failed");
+
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let events = recent_trace_events();
+            if !events.is_empty() {
+                eprintln!("Last {} frame and syscall events:", events.len());
+                for event in events {
+                    eprintln!("  {}", event);
+                }
+            }
+            default_hook(info);
+        }));
+    });
+}
+
+/// Latest frame and syscall events of the current thread, oldest first.
+pub fn recent_trace_events() -> Vec<String> {
+    RECENT_EVENTS
+        .try_with(|events| {
+            events
+                .try_borrow()
+                .map(|events| events.iter().cloned().collect())
+                .unwrap_or_default()
+        })
+        .unwrap_or_default()
+}
+
+// Keeps the latest `TRACE_CONTEXT_EVENTS` events of the thread, prefixed by their spans
+struct RecentEvents;
+
+// Fields of a span, formatted as `name{field=value ...}`, and when it was created
+struct SpanLabel {
+    label: String,
+    created: Instant,
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for RecentEvents {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut fields = FieldWriter::default();
+        attrs.record(&mut fields);
+        span.extensions_mut().insert(SpanLabel {
+            label: format!("{}{{{}}}", span.name(), fields.0.trim_start()),
+            created: Instant::now(),
+        });
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let mut fields = FieldWriter::default();
+        values.record(&mut fields);
+        let mut extensions = span.extensions_mut();
+        if let Some(label) = extensions.get_mut::<SpanLabel>() {
+            label.label.pop();
+            label.label.push_str(&fields.0);
+            label.label.push('}');
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut line = scope_of(ctx.event_scope(event));
+        let mut fields = FieldWriter::default();
+        event.record(&mut fields);
+        line.push_str(&fields.0);
+        push_recent_event(line);
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else { return };
+        let elapsed = match span.extensions().get::<SpanLabel>() {
+            Some(label) => label.created.elapsed(),
+            None => return,
+        };
+        let line = scope_of(Some(span.scope()));
+        push_recent_event(format!("{} done in {:?}", line, elapsed));
+    }
+}
+
+// Labels of the spans of a scope, from its root, e.g. `frame{depth=1 ..}:syscall{name=SLOAD ..}:`
+fn scope_of<S: for<'a> LookupSpan<'a>>(
+    scope: Option<tracing_subscriber::registry::Scope<'_, S>>,
+) -> String {
+    let mut line = String::new();
+    for span in scope.into_iter().flat_map(|scope| scope.from_root()) {
+        if let Some(label) = span.extensions().get::<SpanLabel>() {
+            write!(line, "{}:", label.label).expect("Writing to a String");
+        }
+    }
+    line
+}
+
+fn push_recent_event(line: String) {
+    RECENT_EVENTS.with_borrow_mut(|events| {
+        if events.len() == TRACE_CONTEXT_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(line);
     });
 }
 
+// Formats fields as ` field=value`, with the message of events left bare
+#[derive(Default)]
+struct FieldWriter(String);
+
+impl Visit for FieldWriter {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            write!(self.0, " {:?}", value)
+        } else {
+            write!(self.0, " {}={:?}", field.name(), value)
+        }
+        .expect("Writing to a String");
+    }
+}
+
 pub fn add_balance_to_db(db: &mut InMemoryDB, addr: Address, value: u64) {
     db.insert_account_info(addr, AccountInfo::from_balance(U256::from(value)));
 }
 
+/// Deploys the `testbed` contract to a fresh db, in which `ALICE` is funded with 1 ETH.
+pub fn testbed_setup() -> (InMemoryDB, Address) {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    add_balance_to_db(&mut db, ALICE, 1e18 as u64);
+
+    let testbed = deploy_contract(&mut db, get_bytecode("testbed"), None).unwrap();
+    (db, testbed)
+}
+
 pub fn add_contract_to_db(db: &mut InMemoryDB, addr: Address, bytecode: Bytes) {
     let account = AccountInfo::new(
         Uint::from(0),
@@ -53,6 +224,37 @@ pub fn get_calldata(selector: [u8; 4], mut args: Vec<u8>) -> Vec<u8> {
     calldata
 }
 
+/// Calldata of a call to `sig` (e.g. `"transfer(address,uint256)"`). The arguments are
+/// type-checked against the signature.
+pub fn encode_call(sig: &str, args: &[DynSolValue]) -> Bytes {
+    parse_function(sig)
+        .abi_encode_input(args)
+        .unwrap_or_else(|e| panic!("Invalid arguments for `{}`: {}", sig, e))
+        .into()
+}
+
+/// Decodes the output of a call to `sig`, which must declare its return types
+/// (e.g. `"balance_of(address) returns (uint256)"`).
+pub fn decode_return(sig: &str, data: &[u8]) -> Vec<DynSolValue> {
+    parse_function(sig)
+        .abi_decode_output(data, true)
+        .unwrap_or_else(|e| panic!("Invalid output for `{}`: {}", sig, e))
+}
+
+fn parse_function(sig: &str) -> Function {
+    Function::parse(sig).unwrap_or_else(|e| panic!("Invalid signature `{}`: {}", sig, e))
+}
+
+/// Decodes the logs of an event, in order, skipping the ones of other events. Events are declared
+/// with `sol!`, mirroring the `Event` derive of the contract: its name, field types and indexed
+/// fields. Indexed dynamic fields (e.g. `string indexed tag`) are logged as hashes, so they decode
+/// into `B256`.
+pub fn decode_events<E: SolEvent>(logs: &[Log]) -> Vec<E> {
+    logs.iter()
+        .filter_map(|log| E::decode_raw_log(log.topics(), &log.data.data, true).ok())
+        .collect()
+}
+
 pub fn get_mapping_slot(key_bytes: Vec<u8>, id: U256) -> U256 {
     let mut data_bytes = Vec::with_capacity(64);
     data_bytes.extend_from_slice(&key_bytes);
@@ -61,11 +263,146 @@ pub fn get_mapping_slot(key_bytes: Vec<u8>, id: U256) -> U256 {
     keccak256(data_bytes).into()
 }
 
+/// Slot of a value in nested mappings, with the keys (ABI-encoded) from the outermost mapping to
+/// the innermost one, e.g. `[owner, spender]` for `allowances[owner][spender]`.
+pub fn get_nested_mapping_slot(keys: &[Vec<u8>], base: U256) -> U256 {
+    keys.iter()
+        .fold(base, |slot, key| get_mapping_slot(key.clone(), slot))
+}
+
+/// Slot of an element of a dynamic array stored at `base`, following Solidity's layout: elements
+/// start at `keccak256(base)`, each one spanning `element_size_slots` slots.
+pub fn get_array_element_slot(base: U256, index: u64, element_size_slots: u64) -> U256 {
+    let data_start: U256 = keccak256(base.to_be_bytes::<32>()).into();
+    data_start + U256::from(index) * U256::from(element_size_slots)
+}
+
+/// Slots of a string (or `bytes`) stored at `base`, following Solidity's layout: the first one
+/// holds the length, along with the data of strings shorter than 32 bytes. The data of longer ones
+/// starts at the second slot.
+pub fn get_string_slots(base: U256) -> (U256, U256) {
+    (base, keccak256(base.to_be_bytes::<32>()).into())
+}
+
+/// Slot of a field of an exported storage layout, derived through its mapping `keys` if any,
+/// e.g. `get_field_slot(&layout, "allowances", &[owner, spender])`.
+pub fn get_field_slot(layout: &StorageLayout, field: &str, keys: &[Vec<u8>]) -> U256 {
+    let base = layout
+        .field_slot(field)
+        .unwrap_or_else(|| panic!("Field `{}` not found in `{}`", field, layout.contract));
+    get_nested_mapping_slot(keys, base)
+}
+
 pub fn read_db_slot(db: &mut InMemoryDB, contract: Address, slot: U256) -> U256 {
     db.storage(contract, slot)
         .expect("Unable to read storge slot")
 }
 
+/// Every non-zero storage slot of `addr`. The in-memory db caches all the slots touched by the
+/// executed txs, so this is the full storage of contracts deployed in it.
+pub fn dump_storage(db: &InMemoryDB, addr: Address) -> BTreeMap<U256, U256> {
+    db.accounts
+        .get(&addr)
+        .map(|account| {
+            account
+                .storage
+                .iter()
+                .filter(|(_, value)| !value.is_zero())
+                .map(|(slot, value)| (*slot, *value))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Slots whose value differs between two dumps, with their values before and after. Slots missing
+/// from a dump are zero.
+pub fn storage_diff(
+    before: &BTreeMap<U256, U256>,
+    after: &BTreeMap<U256, U256>,
+) -> BTreeMap<U256, (U256, U256)> {
+    let value = |dump: &BTreeMap<U256, U256>, slot| dump.get(slot).copied().unwrap_or_default();
+
+    before
+        .keys()
+        .chain(after.keys())
+        .filter(|slot| value(before, slot) != value(after, slot))
+        .map(|slot| (*slot, (value(before, slot), value(after, slot))))
+        .collect()
+}
+
+/// Pretty-printer of storage dumps. With a storage layout, slots are annotated with the name of
+/// their field, and mapping entries with the keys registered by the test.
+///   ```ignore
+///   let printer = StoragePrinter::new()
+///       .with_layout(StorageLayout::load("erc20").unwrap())
+///       .with_key("ALICE", ALICE.abi_encode());
+///   println!("{}", printer.format(&dump_storage(&db, token)));   // balance_of[ALICE]: ...
+///   ```
+#[derive(Debug, Default)]
+pub struct StoragePrinter {
+    layout: Option<StorageLayout>,
+    keys: Vec<(String, Vec<u8>)>,
+}
+
+impl StoragePrinter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_layout(mut self, layout: StorageLayout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Registers an (ABI-encoded) mapping key, shown as `label` in the annotations
+    pub fn with_key(mut self, label: impl Into<String>, key: Vec<u8>) -> Self {
+        self.keys.push((label.into(), key));
+        self
+    }
+
+    /// Names of the slots that can be attributed to a field: the field itself, or the entries of
+    /// its mappings (at any depth) for the registered keys
+    pub fn labels(&self) -> HashMap<U256, String> {
+        let mut labels = HashMap::new();
+        let Some(layout) = &self.layout else {
+            return labels;
+        };
+
+        for field in &layout.fields {
+            let mut level = vec![(field.slot, field.name.clone())];
+            for _ in 0..field.ty.matches("Mapping<").count() {
+                level = level
+                    .iter()
+                    .flat_map(|(slot, name)| {
+                        self.keys.iter().map(move |(label, key)| {
+                            let entry = get_mapping_slot(key.clone(), *slot);
+                            (entry, format!("{}[{}]", name, label))
+                        })
+                    })
+                    .collect();
+                labels.extend(level.iter().cloned());
+            }
+            labels.insert(field.slot, field.name.clone());
+        }
+
+        labels
+    }
+
+    /// Formats a dump with a line per slot, annotated with its name when known
+    pub fn format(&self, storage: &BTreeMap<U256, U256>) -> String {
+        let labels = self.labels();
+        let mut output = String::new();
+        for (slot, value) in storage {
+            let _ = match labels.get(slot) {
+                Some(label) => writeln!(output, "{:#066x} ({}): {:#x}", slot, label, value),
+                None => writeln!(output, "{:#066x}: {:#x}", slot, value),
+            };
+        }
+
+        output
+    }
+}
+
 pub fn load_bytecode_from_file<P: AsRef<Path>>(path: P) -> Bytes {
     let content = fs::read_to_string(path).expect("Unable to load bytecode from path");
     let trimmed = content.trim().trim_start_matches("0x");
@@ -0,0 +1,322 @@
+use alloy_primitives::{address, Address, Bytes, U256};
+use alloy_sol_types::SolValue;
+use eth_riscv_syscalls::{
+    header::{Header, MAGIC, VERSION},
+    metadata::Metadata,
+    Syscall,
+};
+use r55::{
+    error::Error,
+    exec::{deploy_contract, handle_register, run_tx, ExecEnv, R55Executor, MAX_CALLDATA_SIZE},
+    failure::{last_tx_failures, FrameFailure},
+    get_bytecode,
+    layout::StorageLayout,
+    test_utils::{
+        add_balance_to_db, add_contract_to_db, get_calldata, get_selector_from_sig,
+        initialize_logger, read_db_slot, ALICE,
+    },
+};
+use revm::{
+    primitives::{ExecutionResult, HaltReason, Output, TransactTo},
+    Evm, InMemoryDB,
+};
+
+const ERC20_COPY: Address = address!("00000000000000000000000000000000000000e2");
+const TESTBED_COPY: Address = address!("00000000000000000000000000000000000000e3");
+const UNKNOWN_VERSION: u8 = VERSION + 1;
+
+fn setup() -> InMemoryDB {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    add_balance_to_db(&mut db, ALICE, 1e18 as u64);
+    db
+}
+
+// Replaces the header of R55 `code` with the one of the given version
+fn with_version(code: &[u8], version: u8) -> Bytes {
+    let (_, body) = Header::parse(code).unwrap().expect("Not R55 bytecode");
+    let mut bytes = match version {
+        0 => vec![MAGIC],
+        _ => Header { version, flags: 0 }.encode().to_vec(),
+    };
+    bytes.extend_from_slice(body);
+
+    bytes.into()
+}
+
+fn deployed_code(db: &InMemoryDB, addr: &Address) -> Bytes {
+    db.accounts[addr]
+        .info
+        .code
+        .clone()
+        .unwrap()
+        .original_bytes()
+}
+
+// Deploys `code` at `ERC20_COPY`, with the same owner as `erc20`
+fn copy_erc20(db: &mut InMemoryDB, erc20: Address, code: Bytes) {
+    add_contract_to_db(db, ERC20_COPY, code);
+
+    let owner_slot = StorageLayout::load("erc20")
+        .unwrap()
+        .field_slot("owner")
+        .unwrap();
+    let owner = read_db_slot(db, erc20, owner_slot);
+    db.insert_account_storage(ERC20_COPY, owner_slot, owner)
+        .unwrap();
+}
+
+// Mints tokens to ALICE and reads her balance back
+fn mint_and_check(db: &mut InMemoryDB, erc20: &Address) {
+    let amount = U256::from(42);
+    let mint = get_calldata(
+        get_selector_from_sig("mint(address,uint256)"),
+        (ALICE, amount).abi_encode(),
+    );
+    run_tx(db, erc20, mint, &ALICE).expect("Error minting");
+
+    let balance_of = get_calldata(
+        get_selector_from_sig("balance_of(address)"),
+        ALICE.abi_encode(),
+    );
+    let output = run_tx(db, erc20, balance_of, &ALICE)
+        .expect("Error reading the balance")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), amount);
+}
+
+#[test]
+fn test_current_header() {
+    let mut db = setup();
+    let bytecode = get_bytecode("erc20");
+    assert!(bytecode.starts_with(&Header::current().encode()));
+
+    let erc20 = deploy_contract(&mut db, bytecode, Some(ALICE.abi_encode())).unwrap();
+    assert!(deployed_code(&db, &erc20).starts_with(&Header::current().encode()));
+
+    mint_and_check(&mut db, &erc20);
+}
+
+#[test]
+fn test_version_0_deploy_code() {
+    let mut db = setup();
+    let bytecode = with_version(&get_bytecode("erc20"), 0);
+
+    let erc20 = deploy_contract(&mut db, bytecode, Some(ALICE.abi_encode())).unwrap();
+    mint_and_check(&mut db, &erc20);
+}
+
+#[test]
+fn test_version_0_initcode() {
+    let mut db = setup();
+    let bytecode = with_version(&get_bytecode("erc20"), 0);
+
+    // Version 0 initcode: [0xff][codesize][bytecode][constructor_args]
+    let mut init_code = vec![MAGIC];
+    init_code.extend_from_slice(&(bytecode.len() as u32).to_be_bytes());
+    init_code.extend_from_slice(&bytecode);
+    init_code.extend_from_slice(&ALICE.abi_encode());
+
+    let mut evm = Evm::builder()
+        .with_db(&mut db)
+        .modify_tx_env(|tx| {
+            tx.caller = ALICE;
+            tx.transact_to = TransactTo::Create;
+            tx.data = init_code.into();
+        })
+        .modify_cfg_env(|cfg| cfg.limit_contract_code_size = Some(usize::MAX))
+        .append_handler_register(handle_register)
+        .build();
+    let result = evm.transact_commit().unwrap();
+    drop(evm);
+
+    let ExecutionResult::Success {
+        output: Output::Create(_, Some(erc20)),
+        ..
+    } = result
+    else {
+        panic!("Deployment failed: {result:?}");
+    };
+    mint_and_check(&mut db, &erc20);
+}
+
+#[test]
+fn test_version_0_runtime() {
+    let mut db = setup();
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+
+    // Same runtime, deployed with the single byte prefix
+    let code = with_version(&deployed_code(&db, &erc20), 0);
+    copy_erc20(&mut db, erc20, code);
+
+    mint_and_check(&mut db, &ERC20_COPY);
+}
+
+#[test]
+fn test_version_1_runtime() {
+    let mut db = setup();
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+
+    // Same runtime, stamped with the version that predates the `Panic` syscall
+    let code = with_version(&deployed_code(&db, &erc20), 1);
+    copy_erc20(&mut db, erc20, code);
+
+    mint_and_check(&mut db, &ERC20_COPY);
+}
+
+#[test]
+fn test_version_1_syscall_abi() {
+    let mut db = setup();
+    let testbed = deploy_contract(&mut db, get_bytecode("testbed"), None).unwrap();
+    let code = with_version(&deployed_code(&db, &testbed), 1);
+    add_contract_to_db(&mut db, TESTBED_COPY, code);
+
+    // The syscalls of version 1 behave the same under both versions
+    let calldata = get_calldata(get_selector_from_sig("own_selector()"), vec![]);
+    let current = run_tx(&mut db, &testbed, calldata.clone(), &ALICE).unwrap();
+    let v1 = run_tx(&mut db, &TESTBED_COPY, calldata, &ALICE).unwrap();
+    assert_eq!(current.output, v1.output);
+    assert_eq!(current.gas_used, v1.gas_used);
+
+    // While the ones added later are unknown to version 1 code
+    let panic_id = u64::from(u8::from(Syscall::Panic));
+    let calldata = get_calldata(
+        get_selector_from_sig("raw_ecall(uint64)"),
+        panic_id.abi_encode(),
+    );
+    let err = run_tx(&mut db, &TESTBED_COPY, calldata, &ALICE).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::UnexpectedExecResult(ExecutionResult::Halt {
+                reason: HaltReason::OpcodeNotFound,
+                ..
+            })
+        ),
+        "Unexpected error: {err:?}"
+    );
+    assert!(matches!(
+        last_tx_failures().as_slice(),
+        [FrameFailure::UnknownSyscall { address: TESTBED_COPY, id, .. }] if *id == panic_id
+    ));
+}
+
+#[test]
+fn test_unknown_version_is_rejected() {
+    let mut db = setup();
+
+    // Deployments of unknown versions are refused
+    let bytecode = with_version(&get_bytecode("erc20"), UNKNOWN_VERSION);
+    let err = deploy_contract(&mut db, bytecode, Some(ALICE.abi_encode())).unwrap_err();
+    assert!(
+        matches!(
+            err,
+            Error::SyscallError(eth_riscv_syscalls::Error::UnsupportedVersion(
+                UNKNOWN_VERSION
+            ))
+        ),
+        "Unexpected error: {err:?}"
+    );
+
+    // And calls to deployed code of an unknown version halt
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+    let code = with_version(&deployed_code(&db, &erc20), UNKNOWN_VERSION);
+    add_contract_to_db(&mut db, ERC20_COPY, code);
+
+    let calldata = get_calldata(get_selector_from_sig("total_supply()"), vec![]);
+    let err = run_tx(&mut db, &ERC20_COPY, calldata, &ALICE).unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            Error::UnexpectedExecResult(ExecutionResult::Halt {
+                reason: HaltReason::NotActivated,
+                ..
+            })
+        ),
+        "Unexpected error: {err:?}"
+    );
+}
+
+#[test]
+fn test_invalid_code_only_fails_its_frame() {
+    let mut db = setup();
+    let testbed = deploy_contract(&mut db, get_bytecode("testbed"), None).unwrap();
+
+    // EVM code starting with SELFDESTRUCT reads as R55 bytecode of an unknown version, and a lone
+    // magic byte followed by a version as a truncated header
+    let selector = get_selector_from_sig("try_forward(address,bytes)");
+    for code in [vec![MAGIC, UNKNOWN_VERSION, 0x00], vec![MAGIC, VERSION]] {
+        add_contract_to_db(&mut db, ERC20_COPY, code.into());
+        let calldata = get_calldata(selector, (ERC20_COPY, Bytes::new()).abi_encode());
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("The caller shouldn't fail")
+            .output;
+        let (success, data) = <(bool, Bytes)>::abi_decode(&output, true).unwrap();
+        assert!(!success);
+        assert!(data.is_empty());
+    }
+
+    // Call data that doesn't fit the emulator halts the frame it's sent to
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+    let mut calldata = get_calldata(get_selector_from_sig("total_supply()"), vec![]);
+    calldata.resize(MAX_CALLDATA_SIZE + 1, 0);
+    let err = run_tx(&mut db, &erc20, calldata, &ALICE).unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            Error::UnexpectedExecResult(ExecutionResult::Halt {
+                reason: HaltReason::OutOfGas(_),
+                ..
+            })
+        ),
+        "Unexpected error: {err:?}"
+    );
+
+    // Initcode that ends within its codesize, or before the code it declares, halts the create
+    for initcode in [
+        vec![MAGIC, VERSION, 0, 0, 0],
+        vec![MAGIC, VERSION, 0, 0, 0, 0, 0x10, MAGIC],
+    ] {
+        let err = R55Executor::new(&ExecEnv::default())
+            .deploy_with_gas_limit(&mut db, initcode.into(), U256::ZERO, ALICE, 1_000_000)
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                Error::UnexpectedExecResult(ExecutionResult::Halt {
+                    reason: HaltReason::OpcodeNotFound,
+                    ..
+                })
+            ),
+            "Unexpected error: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn test_metadata_trailer() {
+    let mut db = setup();
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+
+    // The deployed code ends with the metadata of the compilation
+    let code = deployed_code(&db, &erc20);
+    let (header, body) = Header::parse(&code).unwrap().unwrap();
+    let metadata = Metadata::decode(body).expect("Missing metadata trailer");
+    assert_eq!(metadata.version, env!("CARGO_PKG_VERSION"));
+    assert!(metadata.toolchain.starts_with("nightly"));
+
+    // Without the trailer, the contract behaves the same
+    let mut stripped = header.encode().to_vec();
+    stripped.extend_from_slice(Metadata::strip(body));
+    assert!(stripped.len() < code.len());
+    copy_erc20(&mut db, erc20, stripped.into());
+
+    let total_supply = get_calldata(get_selector_from_sig("total_supply()"), vec![]);
+    let with_trailer = run_tx(&mut db, &erc20, total_supply.clone(), &ALICE).unwrap();
+    let without_trailer = run_tx(&mut db, &ERC20_COPY, total_supply, &ALICE).unwrap();
+    assert_eq!(with_trailer.output, without_trailer.output);
+    assert_eq!(with_trailer.gas_used, without_trailer.gas_used);
+
+    mint_and_check(&mut db, &erc20);
+    mint_and_check(&mut db, &ERC20_COPY);
+}
@@ -0,0 +1,168 @@
+use alloy_primitives::{hex, Address, B256, U256};
+use alloy_sol_types::SolValue;
+use r55::{
+    exec::{deploy_contract, run_tx, send_tx, ExecEnv, TxSpec},
+    get_bytecode,
+    test_utils::{
+        add_balance_to_db, get_calldata, get_selector_from_sig, initialize_logger, ALICE, BOB,
+    },
+};
+use revm::InMemoryDB;
+
+struct FactorySetup {
+    db: InMemoryDB,
+    implementation: Address,
+    factory: Address,
+}
+
+// Deploys an ERC20 to be used as the implementation of the clones, and a factory cloning it
+fn factory_setup() -> FactorySetup {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    for user in [ALICE, BOB] {
+        add_balance_to_db(&mut db, user, 1e18 as u64);
+    }
+
+    let implementation =
+        deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+    let factory = deploy_contract(
+        &mut db,
+        get_bytecode("clone_factory"),
+        Some(implementation.abi_encode()),
+    )
+    .unwrap();
+
+    FactorySetup {
+        db,
+        implementation,
+        factory,
+    }
+}
+
+// Canonical EIP-1167 initcode, as assembled by the runtime
+fn clone_init_code(implementation: Address) -> Vec<u8> {
+    [
+        &hex!("3d602d80600a3d3981f3363d3d373d3d3d363d73")[..],
+        implementation.as_slice(),
+        &hex!("5af43d82803e903d91602b57fd5bf3")[..],
+    ]
+    .concat()
+}
+
+fn call_address(db: &mut InMemoryDB, target: Address, calldata: Vec<u8>) -> Address {
+    let output = run_tx(db, &target, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    Address::abi_decode(&output, true).unwrap()
+}
+
+fn call_u256(db: &mut InMemoryDB, target: Address, calldata: Vec<u8>) -> U256 {
+    let output = run_tx(db, &target, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    U256::abi_decode(&output, true).unwrap()
+}
+
+#[test]
+fn test_clones_have_independent_storage() {
+    let FactorySetup {
+        mut db,
+        implementation,
+        factory,
+    } = factory_setup();
+
+    let selector_clone = get_selector_from_sig("create_clone()");
+    let clone_a = call_address(&mut db, factory, selector_clone.to_vec());
+    let clone_b = call_address(&mut db, factory, selector_clone.to_vec());
+    assert_ne!(clone_a, clone_b);
+
+    // Clones only hold the 45-byte proxy runtime
+    for clone in [clone_a, clone_b] {
+        let code = db.accounts[&clone].info.code.as_ref().unwrap();
+        assert_eq!(
+            code.original_bytes()[..],
+            clone_init_code(implementation)[10..]
+        );
+    }
+
+    // Deposits go through the proxy into the implementation's logic, but each clone keeps its own
+    // balances
+    let selector_deposit = get_selector_from_sig("deposit()");
+    let deposits = [
+        (ALICE, clone_a, U256::from(1e17)),
+        (BOB, clone_b, U256::from(2e17)),
+    ];
+    for (user, clone, value) in deposits {
+        let tx = TxSpec::call(user, clone, selector_deposit.to_vec()).with_value(value);
+        let result = send_tx(&mut db, &tx, &ExecEnv::default()).expect("Error executing tx");
+        assert!(result.status, "Deposit transaction failed");
+        assert_eq!(db.accounts[&clone].info.balance, value);
+    }
+
+    let selector_balance = get_selector_from_sig("balance_of(address)");
+    let selector_supply = get_selector_from_sig("total_supply()");
+    let expected = [
+        (clone_a, U256::from(1e17), U256::ZERO),
+        (clone_b, U256::ZERO, U256::from(2e17)),
+        (implementation, U256::ZERO, U256::ZERO),
+    ];
+    for (token, alice_balance, bob_balance) in expected {
+        let calldata = get_calldata(selector_balance, ALICE.abi_encode());
+        assert_eq!(call_u256(&mut db, token, calldata), alice_balance);
+
+        let calldata = get_calldata(selector_balance, BOB.abi_encode());
+        assert_eq!(call_u256(&mut db, token, calldata), bob_balance);
+
+        let supply = call_u256(&mut db, token, selector_supply.to_vec());
+        assert_eq!(supply, alice_balance + bob_balance);
+    }
+
+    // Reverts are forwarded by the proxy as well
+    let result = run_tx(&mut db, &clone_a, selector_deposit.to_vec(), &ALICE)
+        .expect_err("Deposit succeeded without value");
+    assert!(
+        result.matches_custom_error("ERC20Error::ZeroAmount"),
+        "Incorrect error signature"
+    );
+}
+
+#[test]
+fn test_clone_deterministic() {
+    let FactorySetup {
+        mut db,
+        implementation,
+        factory,
+    } = factory_setup();
+    let salt = B256::with_last_byte(42);
+
+    let selector_predict = get_selector_from_sig("predict_clone(bytes32)");
+    let predicted = call_address(
+        &mut db,
+        factory,
+        get_calldata(selector_predict, salt.abi_encode()),
+    );
+    assert_eq!(
+        predicted,
+        factory.create2_from_code(salt, clone_init_code(implementation))
+    );
+
+    let selector_clone = get_selector_from_sig("create_clone_deterministic(bytes32)");
+    let calldata = get_calldata(selector_clone, salt.abi_encode());
+    let clone = call_address(&mut db, factory, calldata.clone());
+    assert_eq!(clone, predicted);
+
+    // The clone works like the implementation
+    let tx = TxSpec::call(ALICE, clone, get_selector_from_sig("deposit()").to_vec())
+        .with_value(U256::from(1e17));
+    send_tx(&mut db, &tx, &ExecEnv::default()).expect("Error executing tx");
+
+    let selector_balance = get_selector_from_sig("balance_of(address)");
+    let calldata_balance = get_calldata(selector_balance, ALICE.abi_encode());
+    assert_eq!(
+        call_u256(&mut db, clone, calldata_balance),
+        U256::from(1e17)
+    );
+
+    // The address is taken, so the same salt can't be reused
+    run_tx(&mut db, &factory, calldata, &ALICE).expect_err("Clone succeeded with a used salt");
+}
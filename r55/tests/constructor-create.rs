@@ -0,0 +1,59 @@
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::SolValue;
+use r55::{
+    exec::{deploy_contract, nonce_of, run_tx, DEPLOYER},
+    get_bytecode,
+    test_utils::{get_calldata, get_selector_from_sig, initialize_logger, ALICE},
+};
+use revm::{Database, InMemoryDB};
+
+fn call_address(db: &mut InMemoryDB, target: Address, sig: &str) -> Address {
+    let calldata = get_calldata(get_selector_from_sig(sig), vec![]);
+    let output = run_tx(db, &target, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    Address::abi_decode(&output, true).unwrap()
+}
+
+#[test]
+fn test_constructor_deploys_token() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+
+    let launcher = deploy_contract(
+        &mut db,
+        get_bytecode("token_launcher"),
+        Some((ALICE, U256::ZERO).abi_encode()),
+    )
+    .unwrap();
+
+    // The token is created by the launcher while it is being deployed, whose nonce starts at 1
+    let token = call_address(&mut db, launcher, "token()");
+    assert_eq!(token, launcher.create(1));
+    assert_eq!(nonce_of(&db, launcher), 2);
+    assert!(!db.basic(token).unwrap().unwrap().is_empty_code_hash());
+
+    // The constructor could already call the token it deployed
+    assert_eq!(call_address(&mut db, launcher, "token_owner()"), ALICE);
+    assert_eq!(call_address(&mut db, token, "owner()"), ALICE);
+}
+
+#[test]
+fn test_constructor_failed_create_aborts_deployment() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    let launcher = DEPLOYER.create(nonce_of(&db, DEPLOYER));
+
+    // The launcher has no balance to send along with the token deployment, so the create fails
+    let result = deploy_contract(
+        &mut db,
+        get_bytecode("token_launcher"),
+        Some((ALICE, U256::from(1)).abi_encode()),
+    );
+
+    assert!(result.is_err());
+    let launcher_info = db.basic(launcher).unwrap().unwrap_or_default();
+    assert!(launcher_info.is_empty_code_hash());
+    let token_info = db.basic(launcher.create(1)).unwrap().unwrap_or_default();
+    assert!(token_info.is_empty_code_hash());
+}
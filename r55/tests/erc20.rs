@@ -1,14 +1,21 @@
-use alloy_primitives::{Address, B256, U256};
-use alloy_sol_types::SolValue;
+use alloy_primitives::{address, keccak256, Address, Bytes, B256, U256};
+use alloy_sol_types::{sol, Revert, SolError, SolValue};
+use eth_riscv_syscalls::header::Header;
 use r55::{
-    exec::{deploy_contract, run_tx},
+    error::Error,
+    exec::{deploy_contract, r55_initcode, run_tx, send_tx, ExecEnv, TxSpec},
     get_bytecode,
+    layout::StorageLayout,
+    selectors,
+    symbols::Symbolizer,
     test_utils::{
-        add_balance_to_db, get_calldata, get_selector_from_sig, initialize_logger, ALICE, BOB,
-        CAROL,
+        add_balance_to_db, decode_events, dump_storage, get_calldata, get_field_slot,
+        get_selector_from_sig, initialize_logger, recent_trace_events, storage_diff,
+        StoragePrinter, ALICE, BOB, CAROL,
     },
 };
-use revm::InMemoryDB;
+use revm::{primitives::ExecutionResult, InMemoryDB};
+use std::collections::BTreeMap;
 
 struct ERC20Setup {
     db: InMemoryDB,
@@ -53,6 +60,170 @@ fn test_erc20_deployment() {
     );
 }
 
+fn decimals_of(db: &mut InMemoryDB, token: Address) -> U256 {
+    let calldata = get_selector_from_sig("decimals()").to_vec();
+    let output = run_tx(db, &token, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    U256::abi_decode(&output, true).unwrap()
+}
+
+#[test]
+fn test_erc20_default_decimals() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    let bytecode = get_bytecode("erc20");
+
+    // The decimals can be left out of the constructor args
+    let token = deploy_contract(&mut db, bytecode.clone(), Some(ALICE.abi_encode())).unwrap();
+    assert_eq!(decimals_of(&mut db, token), U256::from(18));
+
+    let args = (ALICE, U256::from(6)).abi_encode();
+    let token = deploy_contract(&mut db, bytecode.clone(), Some(args)).unwrap();
+    assert_eq!(decimals_of(&mut db, token), U256::from(6));
+
+    // Args that aren't exactly the encoding of either form are rejected
+    let mut args = ALICE.abi_encode();
+    args.extend_from_slice(&[0u8; 16]);
+    assert!(deploy_contract(&mut db, bytecode, Some(args)).is_err());
+}
+
+#[test]
+fn test_erc20_default_decimals_from_contract() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    add_balance_to_db(&mut db, ALICE, 1e18 as u64);
+    let erc20x = deploy_contract(&mut db, get_bytecode("erc20x"), None).unwrap();
+
+    // `Deployable::deploy` takes either the owner alone, or along with the decimals
+    let calldata = get_calldata(
+        get_selector_from_sig("x_deploy(address)"),
+        ALICE.abi_encode(),
+    );
+    let output = run_tx(&mut db, &erc20x, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    let (token, _) = <(Address, Address)>::abi_decode(&output, true).unwrap();
+    assert_eq!(decimals_of(&mut db, token), U256::from(18));
+
+    let calldata = get_calldata(
+        get_selector_from_sig("x_deploy_with_decimals(address,uint256)"),
+        (ALICE, U256::from(6)).abi_encode(),
+    );
+    let output = run_tx(&mut db, &erc20x, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    let token = Address::abi_decode(&output, true).unwrap();
+    assert_eq!(decimals_of(&mut db, token), U256::from(6));
+}
+
+#[test]
+fn test_erc20_deploy2_from_contract() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    let erc20x = deploy_contract(&mut db, get_bytecode("erc20x"), None).unwrap();
+
+    // The initcode only depends on the constructor args, so the salt alone picks the address
+    let init_code = r55_initcode(get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+    let init_code_hash = keccak256(&init_code);
+
+    let mut deployed = Vec::new();
+    for salt in [B256::with_last_byte(1), B256::with_last_byte(2)] {
+        let args = (ALICE, salt).abi_encode();
+        let calldata = get_calldata(
+            get_selector_from_sig("x_predict(address,bytes32)"),
+            args.clone(),
+        );
+        let output = run_tx(&mut db, &erc20x, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        let predicted = Address::abi_decode(&output, true).unwrap();
+        assert_eq!(predicted, erc20x.create2(salt, init_code_hash));
+
+        let calldata = get_calldata(get_selector_from_sig("x_deploy2(address,bytes32)"), args);
+        let output = run_tx(&mut db, &erc20x, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        let token = Address::abi_decode(&output, true).unwrap();
+        assert_eq!(token, predicted);
+        assert_eq!(decimals_of(&mut db, token), U256::from(18));
+        deployed.push(token);
+    }
+    assert_ne!(deployed[0], deployed[1]);
+
+    // Reusing a salt collides with the existing token, so the deployment fails
+    let calldata = get_calldata(
+        get_selector_from_sig("x_deploy2(address,bytes32)"),
+        (ALICE, B256::with_last_byte(1)).abi_encode(),
+    );
+    let err = run_tx(&mut db, &erc20x, calldata, &ALICE).expect_err("Tx succeeded");
+    assert!(matches!(
+        err,
+        Error::UnexpectedExecResult(ExecutionResult::Revert { .. })
+    ));
+}
+
+#[test]
+fn test_tx_origin_in_nested_call() {
+    let ERC20Setup { mut db, token, .. } = erc20_setup(ALICE);
+    let erc20x = deploy_contract(&mut db, get_bytecode("erc20x"), None).unwrap();
+
+    // Called directly, the sender of the call is the sender of the tx
+    let calldata = get_calldata(get_selector_from_sig("callers()"), vec![]);
+    let output = run_tx(&mut db, &token, calldata, &BOB)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(
+        <(Address, Address)>::abi_decode(&output, true).unwrap(),
+        (BOB, BOB)
+    );
+
+    // Through erc20x, the token is called by erc20x, while the tx is still sent by BOB
+    let calldata = get_calldata(
+        get_selector_from_sig("x_callers(address)"),
+        token.abi_encode(),
+    );
+    let output = run_tx(&mut db, &erc20x, calldata, &BOB)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(
+        <(Address, Address)>::abi_decode(&output, true).unwrap(),
+        (erc20x, BOB)
+    );
+}
+
+#[test]
+fn test_interface_calldata() {
+    let ERC20Setup { mut db, .. } = erc20_setup(ALICE);
+    let erc20x = deploy_contract(&mut db, get_bytecode("erc20x"), None).unwrap();
+
+    // The alloy bindings of the ERC20 are checked against the same calldata
+    let expected: BTreeMap<&str, Bytes> =
+        include_str!("../../examples/erc20-bindings/interface-calldata.txt")
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once(' '))
+            .map(|(method, calldata)| (method, calldata.parse().unwrap()))
+            .collect();
+
+    let from = address!("000000000000000000000000000000000000a11c");
+    let to = address!("0000000000000000000000000000000000000b0b");
+    let calldata = get_calldata(
+        get_selector_from_sig("x_interface_calldata(address,address,uint256)"),
+        (from, to, U256::from(1000)).abi_encode(),
+    );
+    let output = run_tx(&mut db, &erc20x, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    let (transfer, transfer_from, balance_of, total_supply) =
+        <(Bytes, Bytes, Bytes, Bytes)>::abi_decode(&output, true).unwrap();
+
+    assert_eq!(transfer, expected["transfer"]);
+    assert_eq!(transfer_from, expected["transfer_from"]);
+    assert_eq!(balance_of, expected["balance_of"]);
+    assert_eq!(total_supply, expected["total_supply"]);
+}
+
 #[test]
 fn test_erc20_mint() {
     let ERC20Setup {
@@ -84,6 +255,46 @@ fn test_erc20_mint() {
     );
 }
 
+#[test]
+fn test_erc20_deposit() {
+    let ERC20Setup {
+        mut db,
+        token,
+        owner,
+    } = erc20_setup(ALICE);
+    let value = U256::from(1e17);
+
+    // The payable `deposit` mints the value sent with the call, which the token now holds
+    let deposit = get_calldata(get_selector_from_sig("deposit()"), vec![]);
+    let tx = TxSpec::call(BOB, token, deposit).with_value(value);
+    let result = send_tx(&mut db, &tx, &ExecEnv::default()).expect("Error executing tx");
+    assert!(result.status, "Deposit failed");
+    assert_eq!(db.accounts[&token].info.balance, value);
+
+    let calldata = get_calldata(
+        get_selector_from_sig("balance_of(address)"),
+        BOB.abi_encode(),
+    );
+    let output = run_tx(&mut db, &token, calldata, &BOB)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), value);
+
+    // Sending value to a method that isn't payable reverts, and the value stays with the sender
+    let approve = get_calldata(
+        get_selector_from_sig("approve(address,uint256)"),
+        (CAROL, value).abi_encode(),
+    );
+    let tx = TxSpec::call(owner, token, approve).with_value(value);
+    let err = send_tx(&mut db, &tx, &ExecEnv::default()).expect_err("Tx succeeded");
+    let Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) = err else {
+        panic!("Expected revert, got: {err}");
+    };
+    let revert = Revert::abi_decode(&output, true).expect("Unable to decode revert");
+    assert_eq!(revert.reason, "non-payable function");
+    assert_eq!(db.accounts[&token].info.balance, value);
+}
+
 #[test]
 fn test_erc20_transfer() {
     let ERC20Setup {
@@ -102,6 +313,7 @@ fn test_erc20_transfer() {
     assert!(mint_result.status, "Mint transaction failed");
 
     // Transfer
+    let storage_before = dump_storage(&db, token);
     let transfer_amount = U256::from(50e18);
     let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
     let calldata_transfer =
@@ -111,6 +323,27 @@ fn test_erc20_transfer() {
         run_tx(&mut db, &token, calldata_transfer, &owner).expect("Error executing tx");
     assert!(transfer_result.status, "Transfer transaction failed");
 
+    // Only the balances of the sender and the recipient changed
+    let layout = StorageLayout::load("erc20").unwrap();
+    let printer = StoragePrinter::new()
+        .with_layout(layout.clone())
+        .with_key("owner", owner.abi_encode())
+        .with_key("recipient", recipient.abi_encode());
+    let storage_after = dump_storage(&db, token);
+    let diff = storage_diff(&storage_before, &storage_after);
+    let owner_slot = get_field_slot(&layout, "balance_of", &[owner.abi_encode()]);
+    let recipient_slot = get_field_slot(&layout, "balance_of", &[recipient.abi_encode()]);
+    let expected = BTreeMap::from([
+        (owner_slot, (mint_amount, mint_amount - transfer_amount)),
+        (recipient_slot, (U256::ZERO, transfer_amount)),
+    ]);
+    assert_eq!(
+        diff,
+        expected,
+        "Unexpected storage changes:\n{}",
+        printer.format(&storage_after)
+    );
+
     // Verify balances
     let selector_balance = get_selector_from_sig("balance_of(address)");
 
@@ -141,6 +374,153 @@ fn test_erc20_transfer() {
     );
 }
 
+#[test]
+fn test_erc20_transfer_instructions() {
+    let ERC20Setup {
+        mut db,
+        token,
+        owner,
+    } = erc20_setup(ALICE);
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (owner, U256::MAX).abi_encode());
+    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing tx");
+
+    // The args and return value of `transfer` are fixed-size, so they're decoded and encoded in
+    // place: the instructions it runs don't depend on them. Run with `--nocapture` to see the count
+    let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
+    let instructions = [(BOB, U256::from(1)), (CAROL, U256::MAX - U256::from(1))].map(|args| {
+        let calldata = get_calldata(selector_transfer, args.abi_encode());
+        let result = run_tx(&mut db, &token, calldata, &owner).expect("Error executing tx");
+        assert_eq!(result.output, true.abi_encode());
+        result.instructions
+    });
+    println!("erc20 transfer: {} RISC-V instructions", instructions[0]);
+
+    assert_ne!(instructions[0], 0);
+    assert_eq!(instructions[0], instructions[1]);
+}
+
+#[test]
+fn test_erc20_has_no_overflow_checks() {
+    let ERC20Setup { db, token, .. } = erc20_setup(ALICE);
+    let code = db.accounts[&token]
+        .info
+        .code
+        .as_ref()
+        .unwrap()
+        .original_bytes();
+    let (_, elf) = Header::parse(&code).unwrap().expect("Not R55 code");
+
+    // Its math is done on `U256`, so no integer overflow check (and panic) makes it into the binary
+    let symbolizer = Symbolizer::new(elf);
+    assert!(!symbolizer.functions().is_empty(), "erc20 has no symbols");
+    let overflow_panics: Vec<_> = symbolizer
+        .functions()
+        .iter()
+        .map(|(_, name)| name)
+        .filter(|name| name.contains("panic_const_") && name.contains("overflow"))
+        .collect();
+    assert!(
+        overflow_panics.is_empty(),
+        "erc20 checks for overflows: {overflow_panics:?}"
+    );
+}
+
+sol! {
+    event Transfer(address indexed from, address indexed to, uint256 amount);
+    event DebugLog(bytes32 indexed tag, uint256 value);
+}
+
+#[test]
+fn test_erc20_transfer_events() {
+    let ERC20Setup {
+        mut db,
+        token,
+        owner,
+    } = erc20_setup(ALICE);
+
+    let mint_amount = U256::from(100e18);
+    let transfer_amount = U256::from(30e18);
+    let txs = [
+        ("mint(address,uint256)", (owner, mint_amount).abi_encode()),
+        // Emits an `Approval` event, which is skipped
+        (
+            "approve(address,uint256)",
+            (CAROL, transfer_amount).abi_encode(),
+        ),
+        (
+            "transfer(address,uint256)",
+            (BOB, transfer_amount).abi_encode(),
+        ),
+    ];
+
+    let mut logs = Vec::new();
+    for (sig, args) in txs {
+        let calldata = get_calldata(get_selector_from_sig(sig), args);
+        let result = run_tx(&mut db, &token, calldata, &owner).expect("Error executing tx");
+        assert!(result.status, "`{}` failed", sig);
+        logs.extend(result.logs);
+    }
+    assert_eq!(logs.len(), 3);
+
+    let transfers: Vec<_> = decode_events::<Transfer>(&logs)
+        .into_iter()
+        .map(|event| (event.from, event.to, event.amount))
+        .collect();
+    assert_eq!(
+        transfers,
+        vec![
+            (Address::ZERO, owner, mint_amount),
+            (owner, BOB, transfer_amount),
+        ]
+    );
+}
+
+#[test]
+fn test_shared_library_events() {
+    let ERC20Setup {
+        mut db,
+        token,
+        owner,
+    } = erc20_setup(ALICE);
+    let erc20x = deploy_contract(&mut db, get_bytecode("erc20x"), None).unwrap();
+
+    let mint_amount = U256::from(100e18);
+    let calldata = get_calldata(
+        get_selector_from_sig("mint(address,uint256)"),
+        (owner, mint_amount).abi_encode(),
+    );
+    run_tx(&mut db, &token, calldata, &owner).expect("Error executing tx");
+
+    // Both contracts emit the event of the `debug-log` library, on their own behalf
+    let calls = [
+        (
+            token,
+            get_calldata(get_selector_from_sig("debug_supply()"), vec![]),
+            "erc20.total_supply",
+        ),
+        (
+            erc20x,
+            get_calldata(
+                get_selector_from_sig("x_debug_balance(address,address)"),
+                (owner, token).abi_encode(),
+            ),
+            "erc20x.balance_of",
+        ),
+    ];
+    for (contract, calldata, tag) in calls {
+        let result = run_tx(&mut db, &contract, calldata, &owner).expect("Error executing tx");
+        assert_eq!(U256::abi_decode(&result.output, true).unwrap(), mint_amount);
+
+        assert_eq!(result.logs.len(), 1, "{}", tag);
+        assert_eq!(result.logs[0].address, contract, "{}", tag);
+        let events = decode_events::<DebugLog>(&result.logs);
+        assert_eq!(events[0].tag, keccak256(tag), "{}", tag);
+        assert_eq!(events[0].value, mint_amount, "{}", tag);
+    }
+}
+
 #[test]
 fn test_erc20_approve_and_transfer_from() {
     let ERC20Setup {
@@ -214,6 +594,157 @@ fn test_erc20_approve_and_transfer_from() {
     );
 }
 
+#[test]
+fn test_erc20_transfer_state_diff() {
+    let ERC20Setup {
+        mut db,
+        token,
+        owner,
+    } = erc20_setup(ALICE);
+    let recipient = BOB;
+
+    let mint_amount = U256::from(100e18);
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (owner, mint_amount).abi_encode());
+    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing tx");
+
+    let transfer_amount = U256::from(50e18);
+    let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
+    let calldata_transfer =
+        get_calldata(selector_transfer, (recipient, transfer_amount).abi_encode());
+    let tx = TxSpec::call(owner, token, calldata_transfer);
+    let result =
+        send_tx(&mut db, &tx, &ExecEnv::default().with_state_diff()).expect("Error executing tx");
+    let diff = result.state_diff.expect("No state diff");
+
+    // Exactly the two balance slots were written, and nothing else in the token
+    let layout = StorageLayout::load("erc20").unwrap();
+    let printer = StoragePrinter::new()
+        .with_layout(layout.clone())
+        .with_key("owner", owner.abi_encode())
+        .with_key("recipient", recipient.abi_encode());
+    let owner_slot = get_field_slot(&layout, "balance_of", &[owner.abi_encode()]);
+    let recipient_slot = get_field_slot(&layout, "balance_of", &[recipient.abi_encode()]);
+    let expected = BTreeMap::from([
+        (owner_slot, (mint_amount, mint_amount - transfer_amount)),
+        (recipient_slot, (U256::ZERO, transfer_amount)),
+    ]);
+    assert_eq!(
+        diff.storage(token),
+        expected,
+        "Unexpected state changes:\n{}",
+        diff.format(&[(token, &printer)])
+    );
+    assert_eq!(diff.accounts[&token].balance, None);
+    assert_eq!(diff.accounts[&token].nonce, None);
+
+    // The sender only paid for the gas, and no other storage was touched
+    let sender = &diff.accounts[&owner];
+    assert_eq!(sender.nonce.map(|(old, new)| new - old), Some(1));
+    assert!(sender.balance.is_some_and(|(old, new)| new < old));
+    assert!(diff
+        .accounts
+        .iter()
+        .all(|(addr, account)| *addr == token || account.storage.is_empty()));
+
+    // The annotated diff names the written fields
+    let formatted = diff.format(&[(token, &printer)]);
+    assert!(formatted.contains("(balance_of[owner])"));
+    assert!(formatted.contains("(balance_of[recipient])"));
+}
+
+#[test]
+fn test_reverted_subcall_is_rolled_back() {
+    let ERC20Setup {
+        mut db,
+        token,
+        owner,
+    } = erc20_setup(ALICE);
+    let erc20x = deploy_contract(&mut db, get_bytecode("erc20x"), None).unwrap();
+
+    let mint_amount = U256::from(100e18);
+    let allowance = U256::from(10e18);
+    let txs = [
+        ("mint(address,uint256)", (owner, mint_amount).abi_encode()),
+        ("approve(address,uint256)", (erc20x, allowance).abi_encode()),
+    ];
+    for (sig, args) in txs {
+        let calldata = get_calldata(get_selector_from_sig(sig), args);
+        run_tx(&mut db, &token, calldata, &owner).expect("Error executing tx");
+    }
+
+    // The first `transfer_from` exceeds the allowance and reverts, and is retried with the allowance
+    let selector = get_selector_from_sig("x_transfer_from(address,uint256,address)");
+    let calldata = get_calldata(selector, (owner, mint_amount, token).abi_encode());
+    let tx = TxSpec::call(BOB, erc20x, calldata);
+    let result =
+        send_tx(&mut db, &tx, &ExecEnv::default().with_state_diff()).expect("Error executing tx");
+
+    // Only the effects of the retried call persist
+    let transfers: Vec<_> = decode_events::<Transfer>(&result.logs)
+        .into_iter()
+        .map(|event| (event.from, event.to, event.amount))
+        .collect();
+    assert_eq!(result.logs.len(), 1);
+    assert_eq!(transfers, vec![(owner, BOB, allowance)]);
+
+    let layout = StorageLayout::load("erc20").unwrap();
+    let diff = result.state_diff.expect("No state diff");
+    let expected = BTreeMap::from([
+        (
+            get_field_slot(&layout, "balance_of", &[owner.abi_encode()]),
+            (mint_amount, mint_amount - allowance),
+        ),
+        (
+            get_field_slot(&layout, "balance_of", &[BOB.abi_encode()]),
+            (U256::ZERO, allowance),
+        ),
+        (
+            get_field_slot(
+                &layout,
+                "allowance_of",
+                &[owner.abi_encode(), erc20x.abi_encode()],
+            ),
+            (allowance, U256::ZERO),
+        ),
+    ]);
+    assert_eq!(diff.storage(token), expected);
+    assert!(diff.storage(erc20x).is_empty());
+}
+
+#[test]
+fn test_failing_subcall_is_labeled() {
+    let ERC20Setup { mut db, token, .. } = erc20_setup(ALICE);
+    let erc20x = deploy_contract(&mut db, get_bytecode("erc20x"), None).unwrap();
+    assert!(
+        selectors::table().is_some(),
+        "No selector table, compile the contracts first"
+    );
+
+    // Without an allowance, `transfer_from` is retried with a zero amount, whose error is bubbled up
+    let selector = get_selector_from_sig("x_transfer_from(address,uint256,address)");
+    let calldata = get_calldata(selector, (ALICE, U256::from(1e18), token).abi_encode());
+    let err = run_tx(&mut db, &erc20x, calldata, &BOB).expect_err("Transfer should fail");
+    assert!(
+        err.to_string()
+            .contains("> decoded: ERC20Error::ZeroAmount"),
+        "{err}"
+    );
+
+    // The nested frames are labeled with the method of the token
+    let label = "selector=ERC20::transfer_from(address,address,uint256)";
+    let events = recent_trace_events();
+    let nested = events
+        .iter()
+        .filter(|event| event.contains(label))
+        .collect::<Vec<_>>();
+    assert!(
+        !nested.is_empty(),
+        "No frame labeled {label} in {events:#?}"
+    );
+    assert!(nested.iter().all(|event| event.contains("frame{depth=1")));
+}
+
 #[test]
 fn test_erc20_transfer_insufficient_balance() {
     let ERC20Setup {
@@ -504,3 +1035,57 @@ fn test_erc20_self_transfer() {
         "Incorrect error signature"
     );
 }
+
+// Malformed calldata must be rejected with a revert that carries a payload, never trap
+fn malformed_calldata_revert(
+    db: &mut InMemoryDB,
+    token: Address,
+    calldata: Vec<u8>,
+) -> Option<Bytes> {
+    match run_tx(db, &token, calldata.clone(), &ALICE) {
+        Ok(_) => None,
+        Err(Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. })) => {
+            assert!(
+                !output.is_empty(),
+                "Empty revert for calldata: {}",
+                Bytes::from(calldata)
+            );
+            Some(output)
+        }
+        Err(err) => panic!(
+            "Unexpected error for calldata {}: {err}",
+            Bytes::from(calldata)
+        ),
+    }
+}
+
+#[test]
+fn test_erc20_fuzz_calldata() {
+    let ERC20Setup {
+        mut db,
+        token,
+        owner,
+    } = erc20_setup(ALICE);
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (owner, U256::from(42e18)).abi_encode());
+    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing tx");
+
+    let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
+    let calldata = get_calldata(selector_transfer, (BOB, U256::from(1e18)).abi_encode());
+
+    // Truncated calldata can't be decoded
+    for len in 0..calldata.len() {
+        let output = malformed_calldata_revert(&mut db, token, calldata[..len].to_vec())
+            .expect("Truncated calldata succeeded");
+        let revert = Revert::abi_decode(&output, true).expect("Unable to decode revert");
+        assert_eq!(revert.reason, "failed to decode calldata", "len: {len}");
+    }
+
+    // Bit-flipped calldata either succeeds or reverts cleanly
+    for i in 0..calldata.len() {
+        let mut flipped = calldata.clone();
+        flipped[i] ^= 1 << (i * 3 % 8);
+        malformed_calldata_revert(&mut db, token, flipped);
+    }
+}
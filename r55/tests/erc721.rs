@@ -1,11 +1,13 @@
-use alloy_primitives::{Address, B256, U256};
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256};
 use alloy_sol_types::SolValue;
 use r55::{
     exec::{deploy_contract, run_tx},
     get_bytecode,
+    layout::StorageLayout,
     test_utils::{
-        add_balance_to_db, get_calldata, get_selector_from_sig, initialize_logger, ALICE, BOB,
-        CAROL,
+        add_balance_to_db, decode_return, encode_call, get_calldata, get_field_slot,
+        get_selector_from_sig, get_string_slots, initialize_logger, read_db_slot, DynSolValue,
+        ALICE, BOB, CAROL,
     },
 };
 use revm::InMemoryDB;
@@ -33,6 +35,23 @@ fn erc721_setup(owner: Address) -> ERC721Setup {
     ERC721Setup { db, token, owner }
 }
 
+// Calls `sig` with `args`, and decodes its return value
+fn call(
+    db: &mut InMemoryDB,
+    token: Address,
+    caller: &Address,
+    sig: &str,
+    args: &[DynSolValue],
+) -> DynSolValue {
+    let calldata = encode_call(sig, args);
+    let result = run_tx(db, &token, calldata.to_vec(), caller).expect("Error executing tx");
+    assert!(result.status, "`{}` failed", sig);
+
+    decode_return(sig, &result.output)
+        .pop()
+        .expect("Missing return value")
+}
+
 #[test]
 fn test_erc721_deployment() {
     let ERC721Setup {
@@ -41,16 +60,8 @@ fn test_erc721_deployment() {
         owner,
     } = erc721_setup(ALICE);
 
-    let selector_owner = get_selector_from_sig("owner()");
-    let owner_result = run_tx(&mut db, &token, selector_owner.to_vec(), &ALICE)
-        .expect("Error executing tx")
-        .output;
-
-    assert_eq!(
-        Address::from_word(B256::from_slice(owner_result.as_slice())),
-        owner,
-        "Incorrect owner"
-    );
+    let token_owner = call(&mut db, token, &ALICE, "owner() returns (address)", &[]);
+    assert_eq!(token_owner, DynSolValue::Address(owner), "Incorrect owner");
 }
 
 #[test]
@@ -61,42 +72,23 @@ fn test_erc721_mint() {
         owner,
     } = erc721_setup(ALICE);
     let recipient = BOB;
-    let token_id = U256::from(1);
+    let token_id = DynSolValue::from(U256::from(1));
 
     // Mint token
-    let selector_mint = get_selector_from_sig("mint(address,uint256)");
-    let calldata_mint = get_calldata(selector_mint, (recipient, token_id).abi_encode());
-
-    let mint_result = run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing tx");
-    assert!(mint_result.status, "Mint transaction failed");
+    let args = [recipient.into(), token_id.clone()];
+    let sig_mint = "mint(address,uint256) returns (bool)";
+    let minted = call(&mut db, token, &owner, sig_mint, &args);
+    assert_eq!(minted, true.into(), "Mint transaction failed");
 
     // Verify ownership
-    let selector_owner_of = get_selector_from_sig("owner_of(uint256)");
-    let calldata_owner = get_calldata(selector_owner_of, token_id.abi_encode());
-
-    let owner_result = run_tx(&mut db, &token, calldata_owner, &owner)
-        .expect("Error executing tx")
-        .output;
-
-    assert_eq!(
-        Address::from_word(B256::from_slice(owner_result.as_slice())),
-        recipient,
-        "Incorrect token owner"
-    );
+    let sig_owner_of = "owner_of(uint256) returns (address)";
+    let token_owner = call(&mut db, token, &owner, sig_owner_of, &[token_id]);
+    assert_eq!(token_owner, recipient.into(), "Incorrect token owner");
 
     // Verify balance
-    let selector_balance = get_selector_from_sig("balance_of(address)");
-    let calldata_balance = get_calldata(selector_balance, recipient.abi_encode());
-
-    let balance_result = run_tx(&mut db, &token, calldata_balance, &owner)
-        .expect("Error executing tx")
-        .output;
-
-    assert_eq!(
-        U256::from_be_bytes::<32>(balance_result.as_slice().try_into().unwrap()),
-        U256::from(1),
-        "Incorrect balance"
-    );
+    let sig_balance = "balance_of(address) returns (uint256)";
+    let balance = call(&mut db, token, &owner, sig_balance, &[recipient.into()]);
+    assert_eq!(balance, U256::from(1).into(), "Incorrect balance");
 }
 
 #[test]
@@ -109,44 +101,32 @@ fn test_erc721_approve_and_transfer_from() {
     let token_owner = BOB;
     let spender = CAROL;
     let recipient = ALICE;
-    let token_id = U256::from(1);
+    let token_id = DynSolValue::from(U256::from(1));
 
     // Mint token to initial owner
-    let selector_mint = get_selector_from_sig("mint(address,uint256)");
-    let calldata_mint = get_calldata(selector_mint, (token_owner, token_id).abi_encode());
-
-    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing mint tx");
+    let args = [token_owner.into(), token_id.clone()];
+    let sig_mint = "mint(address,uint256) returns (bool)";
+    let minted = call(&mut db, token, &owner, sig_mint, &args);
+    assert_eq!(minted, true.into(), "Mint transaction failed");
 
     // Approve spender
-    let selector_approve = get_selector_from_sig("approve(address,uint256)");
-    let calldata_approve = get_calldata(selector_approve, (spender, token_id).abi_encode());
-
-    let approve_result =
-        run_tx(&mut db, &token, calldata_approve, &token_owner).expect("Error executing tx");
-    assert!(approve_result.status, "Approve transaction failed");
+    let args = [spender.into(), token_id.clone()];
+    let sig_approve = "approve(address,uint256) returns (bool)";
+    let approved = call(&mut db, token, &token_owner, sig_approve, &args);
+    assert_eq!(approved, true.into(), "Approve transaction failed");
 
     // Transfer from token_owner to recipient
-    let selector_transfer_from = get_selector_from_sig("transfer_from(address,address,uint256)");
-    let calldata_transfer_from = get_calldata(
-        selector_transfer_from,
-        (token_owner, recipient, token_id).abi_encode(),
-    );
-
-    let transfer_result =
-        run_tx(&mut db, &token, calldata_transfer_from, &spender).expect("Error executing tx");
-    assert!(transfer_result.status, "TransferFrom transaction failed");
+    let args = [token_owner.into(), recipient.into(), token_id.clone()];
+    let sig_transfer_from = "transfer_from(address,address,uint256) returns (bool)";
+    let transferred = call(&mut db, token, &spender, sig_transfer_from, &args);
+    assert_eq!(transferred, true.into(), "TransferFrom transaction failed");
 
     // Verify new owner
-    let selector_owner_of = get_selector_from_sig("owner_of(uint256)");
-    let calldata_owner = get_calldata(selector_owner_of, token_id.abi_encode());
-
-    let owner_result = run_tx(&mut db, &token, calldata_owner, &owner)
-        .expect("Error executing tx")
-        .output;
-
+    let sig_owner_of = "owner_of(uint256) returns (address)";
+    let new_owner = call(&mut db, token, &owner, sig_owner_of, &[token_id]);
     assert_eq!(
-        Address::from_word(B256::from_slice(owner_result.as_slice())),
-        recipient,
+        new_owner,
+        recipient.into(),
         "Incorrect token owner after transfer"
     );
 }
@@ -160,35 +140,29 @@ fn test_erc721_set_approval_for_all() {
     } = erc721_setup(ALICE);
     let token_owner = BOB;
     let operator = CAROL;
-    let token_id = U256::from(1);
+    let token_id = DynSolValue::from(U256::from(1));
 
     // Mint token
-    let selector_mint = get_selector_from_sig("mint(address,uint256)");
-    let calldata_mint = get_calldata(selector_mint, (token_owner, token_id).abi_encode());
-
-    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing mint tx");
+    let args = [token_owner.into(), token_id];
+    let sig_mint = "mint(address,uint256) returns (bool)";
+    let minted = call(&mut db, token, &owner, sig_mint, &args);
+    assert_eq!(minted, true.into(), "Mint transaction failed");
 
     // Set approval for all
-    let selector_set_approval = get_selector_from_sig("set_approval_for_all(address,bool)");
-    let calldata_set_approval = get_calldata(selector_set_approval, (operator, true).abi_encode());
-
-    let approval_result =
-        run_tx(&mut db, &token, calldata_set_approval, &token_owner).expect("Error executing tx");
-    assert!(
-        approval_result.status,
+    let args = [operator.into(), true.into()];
+    let sig_set_approval = "set_approval_for_all(address,bool) returns (bool)";
+    let approved = call(&mut db, token, &token_owner, sig_set_approval, &args);
+    assert_eq!(
+        approved,
+        true.into(),
         "SetApprovalForAll transaction failed"
     );
 
     // Verify approval status
-    let selector_is_approved = get_selector_from_sig("is_approved_for_all(address,address)");
-    let calldata_is_approved =
-        get_calldata(selector_is_approved, (token_owner, operator).abi_encode());
-
-    let is_approved_result = run_tx(&mut db, &token, calldata_is_approved, &owner)
-        .expect("Error executing tx")
-        .output;
-
-    assert_eq!(is_approved_result[31], 1, "Incorrect approval status");
+    let args = [token_owner.into(), operator.into()];
+    let sig_is_approved = "is_approved_for_all(address,address) returns (bool)";
+    let is_approved = call(&mut db, token, &owner, sig_is_approved, &args);
+    assert_eq!(is_approved, true.into(), "Incorrect approval status");
 }
 
 #[test]
@@ -371,3 +345,259 @@ fn test_erc721_query_non_existent_token() {
         "Incorrect error signature"
     );
 }
+
+// Deploys a receiver that acknowledges safe transfers if `accept`, and answers with a wrong value
+// otherwise
+fn deploy_receiver(db: &mut InMemoryDB, accept: bool) -> Address {
+    let bytecode = get_bytecode("erc721_receiver");
+    deploy_contract(db, bytecode, Some(accept.abi_encode())).unwrap()
+}
+
+fn safe_transfer_calldata(from: Address, to: Address, id: U256, data: &[u8]) -> Vec<u8> {
+    let selector = get_selector_from_sig("safe_transfer_from(address,address,uint256,bytes)");
+    get_calldata(
+        selector,
+        (from, to, id, Bytes::copy_from_slice(data)).abi_encode(),
+    )
+}
+
+fn owner_of(db: &mut InMemoryDB, token: Address, id: U256) -> Address {
+    let selector_owner_of = get_selector_from_sig("owner_of(uint256)");
+    let output = run_tx(
+        db,
+        &token,
+        get_calldata(selector_owner_of, id.abi_encode()),
+        &ALICE,
+    )
+    .expect("Error executing tx")
+    .output;
+    Address::abi_decode(&output, true).unwrap()
+}
+
+#[test]
+fn test_erc721_safe_transfer_to_eoa() {
+    let ERC721Setup {
+        mut db,
+        token,
+        owner,
+    } = erc721_setup(ALICE);
+    let token_id = U256::from(1);
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (BOB, token_id).abi_encode());
+    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing mint tx");
+
+    // Without code at the recipient, there is no hook to call
+    let calldata = safe_transfer_calldata(BOB, CAROL, token_id, b"");
+    let result = run_tx(&mut db, &token, calldata, &BOB).expect("Error executing tx");
+    assert!(result.status, "SafeTransferFrom transaction failed");
+
+    assert_eq!(owner_of(&mut db, token, token_id), CAROL);
+}
+
+#[test]
+fn test_erc721_safe_transfer_to_receiver() {
+    let ERC721Setup {
+        mut db,
+        token,
+        owner,
+    } = erc721_setup(ALICE);
+    let receiver = deploy_receiver(&mut db, true);
+    let token_id = U256::from(1);
+    let data = b"some data for the receiver";
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (BOB, token_id).abi_encode());
+    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing mint tx");
+
+    // CAROL transfers on behalf of BOB, so that the operator and the sender differ
+    let selector_approve = get_selector_from_sig("approve(address,uint256)");
+    let calldata_approve = get_calldata(selector_approve, (CAROL, token_id).abi_encode());
+    run_tx(&mut db, &token, calldata_approve, &BOB).expect("Error executing approve tx");
+
+    let calldata = safe_transfer_calldata(BOB, receiver, token_id, data);
+    let result = run_tx(&mut db, &token, calldata, &CAROL).expect("Error executing tx");
+    assert!(result.status, "SafeTransferFrom transaction failed");
+
+    assert_eq!(owner_of(&mut db, token, token_id), receiver);
+
+    // The hook was called with the transfer details
+    let selector_last = get_selector_from_sig("last_received()");
+    let output = run_tx(&mut db, &receiver, selector_last.to_vec(), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    let (operator, from, id, data_hash) =
+        <(Address, Address, U256, B256)>::abi_decode(&output, true).unwrap();
+    assert_eq!(operator, CAROL);
+    assert_eq!(from, BOB);
+    assert_eq!(id, token_id);
+    assert_eq!(data_hash, keccak256(data));
+}
+
+#[test]
+fn test_erc721_safe_transfer_rejected() {
+    let ERC721Setup {
+        mut db,
+        token,
+        owner,
+    } = erc721_setup(ALICE);
+    let receiver = deploy_receiver(&mut db, false);
+    let token_id = U256::from(1);
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (BOB, token_id).abi_encode());
+    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing mint tx");
+
+    // The receiver answers with a wrong value, and contracts without the hook revert
+    for recipient in [receiver, token] {
+        let calldata = safe_transfer_calldata(BOB, recipient, token_id, b"");
+        let result = run_tx(&mut db, &token, calldata, &BOB)
+            .expect_err("SafeTransferFrom transaction succeeded when it should fail");
+
+        assert!(
+            result.matches_custom_error("ERC721Error::UnsafeRecipient"),
+            "Incorrect error signature"
+        );
+    }
+
+    // The transfer was rolled back
+    assert_eq!(owner_of(&mut db, token, token_id), BOB);
+}
+
+fn set_token_uri(db: &mut InMemoryDB, token: Address, caller: &Address, id: U256, uri: &str) {
+    let selector = get_selector_from_sig("set_token_uri(uint256,string)");
+    let calldata = get_calldata(selector, (id, uri.to_string()).abi_encode());
+    let result = run_tx(db, &token, calldata, caller).expect("Error executing set_token_uri tx");
+    assert!(result.status, "SetTokenUri transaction failed");
+}
+
+fn token_uri(db: &mut InMemoryDB, token: Address, id: U256) -> DynSolValue {
+    let sig_token_uri = "token_uri(uint256) returns (string)";
+    call(db, token, &ALICE, sig_token_uri, &[id.into()])
+}
+
+// Length and data slots of the URI of the token `id`, as laid out by `StorageString`
+fn token_uri_slots(id: U256) -> (U256, U256) {
+    let layout = StorageLayout::load("erc721").unwrap();
+    get_string_slots(get_field_slot(&layout, "token_uri", &[id.abi_encode()]))
+}
+
+#[test]
+fn test_erc721_token_uri() {
+    let ERC721Setup {
+        mut db,
+        token,
+        owner,
+    } = erc721_setup(ALICE);
+    let (short_id, long_id) = (U256::from(1), U256::from(2));
+    let short_uri = "ipfs://short";
+    let long_uri = "https://metadata.example.com/collections/r55/tokens/2.json";
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    for id in [short_id, long_id] {
+        let calldata_mint = get_calldata(selector_mint, (BOB, id).abi_encode());
+        run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing mint tx");
+    }
+
+    // Only the owner of the contract can set the URIs, of minted tokens
+    let selector_set = get_selector_from_sig("set_token_uri(uint256,string)");
+    let calldata_set = get_calldata(selector_set, (short_id, short_uri.to_string()).abi_encode());
+    let result = run_tx(&mut db, &token, calldata_set, &BOB)
+        .expect_err("SetTokenUri transaction succeeded when it should fail");
+    assert!(result.matches_custom_error("ERC721Error::OnlyOwner"));
+
+    let unminted = (U256::from(3), short_uri.to_string());
+    let calldata_set = get_calldata(selector_set, unminted.abi_encode());
+    let result = run_tx(&mut db, &token, calldata_set, &owner)
+        .expect_err("SetTokenUri transaction succeeded when it should fail");
+    assert!(result.matches_custom_error("ERC721Error::NotMinted"));
+
+    set_token_uri(&mut db, token, &owner, short_id, short_uri);
+    set_token_uri(&mut db, token, &owner, long_id, long_uri);
+    assert_eq!(
+        token_uri(&mut db, token, short_id),
+        DynSolValue::String(short_uri.into())
+    );
+    assert_eq!(
+        token_uri(&mut db, token, long_id),
+        DynSolValue::String(long_uri.into())
+    );
+
+    // Short URIs are stored in the slot of the entry, along with twice their length
+    let (length_slot, data_slot) = token_uri_slots(short_id);
+    let mut word = [0u8; 32];
+    word[..short_uri.len()].copy_from_slice(short_uri.as_bytes());
+    word[31] = 2 * short_uri.len() as u8;
+    assert_eq!(
+        read_db_slot(&mut db, token, length_slot),
+        U256::from_be_bytes(word)
+    );
+    assert_eq!(read_db_slot(&mut db, token, data_slot), U256::ZERO);
+
+    // Long ones store `2 * len + 1` there, and their data from `keccak256(slot)`
+    let (length_slot, data_slot) = token_uri_slots(long_id);
+    assert_eq!(
+        read_db_slot(&mut db, token, length_slot),
+        U256::from(2 * long_uri.len() + 1)
+    );
+    for (i, chunk) in long_uri.as_bytes().chunks(32).enumerate() {
+        let mut word = [0u8; 32];
+        word[..chunk.len()].copy_from_slice(chunk);
+        let slot = data_slot + U256::from(i);
+        assert_eq!(
+            read_db_slot(&mut db, token, slot),
+            U256::from_be_bytes(word)
+        );
+    }
+
+    // Overwriting a long URI with a short one clears its data
+    set_token_uri(&mut db, token, &owner, long_id, short_uri);
+    assert_eq!(
+        token_uri(&mut db, token, long_id),
+        DynSolValue::String(short_uri.into())
+    );
+    for i in 0..long_uri.len().div_ceil(32) {
+        let slot = data_slot + U256::from(i);
+        assert_eq!(read_db_slot(&mut db, token, slot), U256::ZERO);
+    }
+}
+
+#[test]
+fn test_erc721_burn_clears_token_uri() {
+    let ERC721Setup {
+        mut db,
+        token,
+        owner,
+    } = erc721_setup(ALICE);
+    let token_id = U256::from(1);
+    let uri = "https://metadata.example.com/collections/r55/tokens/1.json";
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (BOB, token_id).abi_encode());
+    run_tx(&mut db, &token, calldata_mint, &owner).expect("Error executing mint tx");
+    set_token_uri(&mut db, token, &owner, token_id, uri);
+
+    // Only the holder of the token (or an operator) can burn it
+    let selector_burn = get_selector_from_sig("burn(uint256)");
+    let calldata_burn = get_calldata(selector_burn, token_id.abi_encode());
+    let result = run_tx(&mut db, &token, calldata_burn.clone(), &CAROL)
+        .expect_err("Burn transaction succeeded when it should fail");
+    assert!(result.matches_custom_error("ERC721Error::Unauthorized"));
+
+    let result = run_tx(&mut db, &token, calldata_burn, &BOB).expect("Error executing burn tx");
+    assert!(result.status, "Burn transaction failed");
+
+    // Both the length and the data slots of the URI are cleared
+    let (length_slot, data_slot) = token_uri_slots(token_id);
+    assert_eq!(read_db_slot(&mut db, token, length_slot), U256::ZERO);
+    for i in 0..uri.len().div_ceil(32) {
+        let slot = data_slot + U256::from(i);
+        assert_eq!(read_db_slot(&mut db, token, slot), U256::ZERO);
+    }
+
+    let selector_token_uri = get_selector_from_sig("token_uri(uint256)");
+    let calldata_token_uri = get_calldata(selector_token_uri, token_id.abi_encode());
+    let result = run_tx(&mut db, &token, calldata_token_uri, &ALICE)
+        .expect_err("TokenUri query succeeded when it should fail");
+    assert!(result.matches_custom_error("ERC721Error::NotMinted"));
+}
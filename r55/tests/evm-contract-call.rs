@@ -4,7 +4,8 @@ use r55::{
     exec::{deploy_contract, run_tx},
     get_bytecode,
     test_utils::{
-        add_balance_to_db, get_selector_from_sig, initialize_logger, load_bytecode_from_file,
+        add_balance_to_db, get_calldata, get_selector_from_sig, initialize_logger,
+        load_bytecode_from_file,
     },
 };
 use revm::{
@@ -191,3 +192,56 @@ fn evm_call() {
         }
     };
 }
+
+#[test]
+fn sol_bridge_call() {
+    initialize_logger();
+
+    let mut db = InMemoryDB::default();
+
+    let bytecode_evm = load_bytecode_from_file(EVM_PATH);
+    let evm = deploy_contract(&mut db, bytecode_evm, None).unwrap();
+    let erc20x = deploy_contract(&mut db, get_bytecode("erc20x"), None).unwrap();
+
+    let alice: Address = address!("000000000000000000000000000000000000000A");
+    add_balance_to_db(&mut db, alice, 1e18 as u64);
+
+    // `setCall` is a regular call, and `getCall` a staticcall from the mutable method
+    let value = U256::from(42);
+    let selector = get_selector_from_sig("x_sol_set(address,uint256)");
+    let output = run_tx(
+        &mut db,
+        &erc20x,
+        get_calldata(selector, (evm, value).abi_encode()),
+        &alice,
+    )
+    .expect("Error executing tx")
+    .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), value);
+
+    // The read-only method can only staticcall
+    let selector = get_selector_from_sig("x_sol_get(address)");
+    let output = run_tx(
+        &mut db,
+        &erc20x,
+        get_calldata(selector, evm.abi_encode()),
+        &alice,
+    )
+    .expect("Error executing tx")
+    .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), value);
+
+    // The fixture has no fallback, so unknown selectors revert without data
+    let selector = get_selector_from_sig("x_sol_reset(address)");
+    let output = run_tx(
+        &mut db,
+        &erc20x,
+        get_calldata(selector, evm.abi_encode()),
+        &alice,
+    )
+    .expect("Error executing tx")
+    .output;
+    let (success, revert_data) = <(bool, Bytes)>::abi_decode(&output, true).unwrap();
+    assert!(!success);
+    assert!(revert_data.is_empty());
+}
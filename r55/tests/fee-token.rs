@@ -0,0 +1,121 @@
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::{sol, SolCall, SolValue};
+use r55::{
+    error::Error,
+    exec::{deploy_contract, run_tx},
+    get_bytecode,
+    layout::StorageLayout,
+    test_utils::{get_field_slot, initialize_logger, read_db_slot, ALICE, BOB, CAROL},
+};
+use revm::{primitives::ExecutionResult, InMemoryDB};
+
+// `balance_of` and `total_supply` are only defined by the inherited `ERC20Base`
+sol! {
+    interface IFeeToken {
+        function total_supply() external view returns (uint256);
+        function balance_of(address owner) external view returns (uint256);
+        function transfer(address to, uint256 amount) external returns (bool);
+        function treasury() external view returns (address);
+        function fee_bps() external view returns (uint256);
+        function mint(address to, uint256 amount) external;
+    }
+}
+
+// Supply of 1000 owned by ALICE, with a fee of 1% paid to CAROL
+fn fee_token_setup() -> (InMemoryDB, Address) {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+
+    let args = (ALICE, U256::from(1000), CAROL, U256::from(100)).abi_encode();
+    let token = deploy_contract(&mut db, get_bytecode("fee_token"), Some(args)).unwrap();
+
+    (db, token)
+}
+
+// Returns the decoded output of the call, or `None` if it reverted
+fn call<C: SolCall>(
+    db: &mut InMemoryDB,
+    token: Address,
+    caller: Address,
+    call: C,
+) -> Option<C::Return> {
+    match run_tx(db, &token, call.abi_encode(), &caller) {
+        Ok(result) => {
+            Some(C::abi_decode_returns(&result.output, true).expect("Unable to decode output"))
+        }
+        Err(Error::UnexpectedExecResult(ExecutionResult::Revert { .. })) => None,
+        Err(err) => panic!("Error executing tx: {err}"),
+    }
+}
+
+fn balance_of(db: &mut InMemoryDB, token: Address, owner: Address) -> U256 {
+    call(db, token, owner, IFeeToken::balance_ofCall { owner })
+        .expect("balance_of is inherited from the base")
+        ._0
+}
+
+#[test]
+fn test_inherited_methods() {
+    let (mut db, token) = fee_token_setup();
+
+    // Dispatched by the base, reading the storage written by the constructor of the contract
+    assert_eq!(balance_of(&mut db, token, ALICE), U256::from(1000));
+    let supply = call(&mut db, token, ALICE, IFeeToken::total_supplyCall {})
+        .unwrap()
+        ._0;
+    assert_eq!(supply, U256::from(1000));
+    let treasury = call(&mut db, token, ALICE, IFeeToken::treasuryCall {})
+        .unwrap()
+        ._0;
+    assert_eq!(treasury, CAROL);
+
+    // Private methods of the base aren't dispatched, and the contract has no fallback
+    let mint = IFeeToken::mintCall {
+        to: BOB,
+        amount: U256::from(1),
+    };
+    assert!(call(&mut db, token, BOB, mint).is_none());
+}
+
+#[test]
+fn test_overridden_method() {
+    let (mut db, token) = fee_token_setup();
+
+    // The override of the contract takes the fee, which the transfer of the base wouldn't
+    let transfer = IFeeToken::transferCall {
+        to: BOB,
+        amount: U256::from(500),
+    };
+    assert!(call(&mut db, token, ALICE, transfer).unwrap()._0);
+    assert_eq!(balance_of(&mut db, token, ALICE), U256::from(500));
+    assert_eq!(balance_of(&mut db, token, BOB), U256::from(495));
+    assert_eq!(balance_of(&mut db, token, CAROL), U256::from(5));
+
+    // Errors of the base are reverted with as usual
+    let transfer = IFeeToken::transferCall {
+        to: ALICE,
+        amount: U256::from(1000),
+    };
+    assert!(call(&mut db, token, BOB, transfer).is_none());
+    assert_eq!(balance_of(&mut db, token, BOB), U256::from(495));
+}
+
+#[test]
+fn test_inherited_layout() {
+    let (mut db, token) = fee_token_setup();
+    let layout = StorageLayout::load("fee_token").unwrap();
+
+    // The fields of the base come first, and the ones of the contract after them
+    let slot = |field: &str| get_field_slot(&layout, field, &[]);
+    assert_eq!(slot("total_supply"), U256::ZERO);
+    assert_eq!(slot("balance_of"), U256::from(1));
+    assert_eq!(slot("treasury"), U256::from(2));
+    assert_eq!(slot("fee_bps"), U256::from(3));
+
+    assert_eq!(
+        read_db_slot(&mut db, token, slot("treasury")),
+        U256::from_be_slice(CAROL.as_slice())
+    );
+    let balance_slot = get_field_slot(&layout, "balance_of", &[ALICE.abi_encode()]);
+    assert_eq!(read_db_slot(&mut db, token, balance_slot), U256::from(1000));
+}
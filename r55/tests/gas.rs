@@ -0,0 +1,766 @@
+use alloy_primitives::{Address, Bytes};
+use alloy_sol_types::SolValue;
+use eth_riscv_syscalls::{header::Header, Syscall};
+use r55::{
+    error::Error,
+    exec::{
+        deploy_contract, deploy_contract_with_env, deploy_contract_with_result, estimate_gas,
+        run_tx, run_tx_with_env, send_tx, ExecEnv, R55Executor, TxSpec, DEFAULT_GAS_LIMIT,
+        DEFAULT_GAS_PRICE,
+    },
+    gas, get_bytecode,
+    layout::StorageLayout,
+    max_gas::BudgetedCall,
+    test_utils::{
+        add_balance_to_db, get_calldata, get_field_slot, get_selector_from_sig, initialize_logger,
+        read_db_slot, testbed_setup, ALICE, BOB,
+    },
+};
+use revm::{
+    interpreter::{self, SStoreResult},
+    primitives::{AccessList, AccessListItem, ExecutionResult, HaltReason, SpecId, B256, U256},
+    InMemoryDB,
+};
+
+// Gas used by a tx, excluding its intrinsic cost (which depends on the calldata bytes)
+fn execution_gas(db: &mut InMemoryDB, testbed: &Address, calldata: Vec<u8>) -> u64 {
+    let intrinsic_gas = gas::intrinsic_gas(SpecId::LATEST, &calldata, false);
+    let gas_used = run_tx(db, testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .gas_used;
+
+    gas_used - intrinsic_gas
+}
+
+fn hash_memory_gas(db: &mut InMemoryDB, testbed: &Address, size: u64) -> u64 {
+    let selector = get_selector_from_sig("hash_memory(uint64)");
+    execution_gas(db, testbed, get_calldata(selector, size.abi_encode()))
+}
+
+#[test]
+fn test_keccak_memory_gas() {
+    let (mut db, testbed) = testbed_setup();
+
+    // 32 bytes (1 word) vs 32KB (1024 words)
+    let gas_small = hash_memory_gas(&mut db, &testbed, 32);
+    let gas_large = hash_memory_gas(&mut db, &testbed, 32 * 1024);
+
+    assert_eq!(
+        gas_large - gas_small,
+        1023 * (gas::KECCAK256_WORD + gas::MEMORY_WORD)
+    );
+}
+
+#[test]
+fn test_absurd_size_runs_out_of_gas() {
+    let (mut db, testbed) = testbed_setup();
+
+    let selector = get_selector_from_sig("hash_memory(uint64)");
+    let calldata = get_calldata(selector, u64::MAX.abi_encode());
+    let err = run_tx(&mut db, &testbed, calldata, &ALICE).expect_err("Tx succeeded");
+
+    assert!(
+        matches!(
+            err,
+            Error::UnexpectedExecResult(ExecutionResult::Halt {
+                reason: HaltReason::OutOfGas(_),
+                ..
+            })
+        ),
+        "Expected OutOfGas, got: {err}"
+    );
+}
+
+#[test]
+fn test_keccak_gas_schedule() {
+    let (mut db, testbed) = testbed_setup();
+
+    // 1 word vs 10 words
+    let gas_one = hash_memory_gas(&mut db, &testbed, 32);
+    let gas_ten = hash_memory_gas(&mut db, &testbed, 320);
+
+    assert_eq!(
+        gas_ten - gas_one,
+        9 * (gas::KECCAK256_WORD + gas::MEMORY_WORD)
+    );
+}
+
+#[test]
+fn test_log_gas_schedule() {
+    let (mut db, testbed) = testbed_setup();
+
+    let selector = get_selector_from_sig("log_memory(uint64,uint64)");
+    let log_calldata = |topics: u64| get_calldata(selector, (topics, 64_u64).abi_encode());
+
+    // The requested topics are actually emitted
+    let result = run_tx(&mut db, &testbed, log_calldata(3), &ALICE).expect("Error executing tx");
+    assert_eq!(result.logs[0].topics().len(), 3);
+
+    // 0 topics vs 3 topics, with the same data
+    let gas_no_topics = execution_gas(&mut db, &testbed, log_calldata(0));
+    let gas_three_topics = execution_gas(&mut db, &testbed, log_calldata(3));
+
+    assert_eq!(gas_three_topics - gas_no_topics, 3 * gas::LOG_TOPIC);
+}
+
+#[test]
+fn test_return_data_copy_gas() {
+    let (mut db, testbed) = testbed_setup();
+
+    // The callee always returns 100 words, of which either 1 or 100 are copied
+    let return_raw = get_selector_from_sig("return_raw(uint64)");
+    let inner_calldata = get_calldata(return_raw, (100 * 32_u64).abi_encode());
+    let selector = get_selector_from_sig("copy_return_data(address,bytes,uint64)");
+    let copy_calldata = |words: u64| {
+        let args = (testbed, Bytes::from(inner_calldata.clone()), words);
+        get_calldata(selector, args.abi_encode())
+    };
+
+    let gas_one = execution_gas(&mut db, &testbed, copy_calldata(1));
+    let gas_hundred = execution_gas(&mut db, &testbed, copy_calldata(100));
+
+    assert_eq!(
+        gas_hundred - gas_one,
+        99 * (gas::COPY_WORD + gas::MEMORY_WORD)
+    );
+}
+
+#[test]
+fn test_batched_mapping_access() {
+    // 100 balances, the last entry overwriting the first one
+    let mut owners: Vec<Address> = (1..100_u64)
+        .map(|i| Address::from_word(B256::from(U256::from(i))))
+        .collect();
+    owners.push(owners[0]);
+    let amounts: Vec<U256> = (1..=100_u64).map(U256::from).collect();
+
+    let run = |db: &mut InMemoryDB, testbed: &Address, sig: &str, args: Vec<u8>| {
+        let calldata = get_calldata(get_selector_from_sig(sig), args);
+        let intrinsic_gas = gas::intrinsic_gas(SpecId::LATEST, &calldata, false);
+        let result = run_tx(db, testbed, calldata, &ALICE).expect("Error executing tx");
+        let count = |syscall| result.syscalls.get(&syscall).copied().unwrap_or_default();
+        let counts = [
+            count(Syscall::Keccak256),
+            count(Syscall::SLoad) + count(Syscall::SStore),
+            count(Syscall::SLoadMany) + count(Syscall::SStoreMany),
+        ];
+        (result.output, result.gas_used - intrinsic_gas, counts)
+    };
+    let (mut db, testbed) = testbed_setup();
+    let (mut db_batched, testbed_batched) = testbed_setup();
+
+    // Writes: the keys are hashed either way, but stored with a single syscall for the whole batch
+    let args = (owners.clone(), amounts.clone()).abi_encode();
+    let (_, gas, counts) = run(
+        &mut db,
+        &testbed,
+        "airdrop(address[],uint256[])",
+        args.clone(),
+    );
+    assert_eq!(counts[1..], [100, 0]);
+    let hashes = counts[0];
+    let (_, gas_batched, counts) = run(
+        &mut db_batched,
+        &testbed_batched,
+        "airdrop_batched(address[],uint256[])",
+        args,
+    );
+    assert_eq!(counts, [hashes, 0, 1]);
+    assert!(
+        gas_batched < gas,
+        "batched: {gas_batched}, individual: {gas}"
+    );
+
+    // Both paths leave the same storage
+    let layout = StorageLayout::load("testbed").unwrap();
+    for owner in &owners {
+        let slot = get_field_slot(&layout, "balances", &[owner.abi_encode()]);
+        assert_eq!(
+            read_db_slot(&mut db, testbed, slot),
+            read_db_slot(&mut db_batched, testbed_batched, slot)
+        );
+    }
+
+    // Reads return the same balances
+    let args = owners.abi_encode();
+    let (output, gas, counts) = run(&mut db, &testbed, "balances_of(address[])", args.clone());
+    assert_eq!(counts[1..], [100, 0]);
+    let hashes = counts[0];
+    let (output_batched, gas_batched, counts) =
+        run(&mut db, &testbed, "balances_of_batched(address[])", args);
+    assert_eq!(counts, [hashes, 0, 1]);
+    assert_eq!(output, output_batched);
+    assert_eq!(
+        Vec::<U256>::abi_decode(&output, true).unwrap()[..2],
+        [U256::from(100), U256::from(2)]
+    );
+    assert!(
+        gas_batched < gas,
+        "batched: {gas_batched}, individual: {gas}"
+    );
+}
+
+// Gas used by an erc20 transfer from ALICE to BOB, with the whole flow running under `spec_id`
+fn transfer_gas(spec_id: SpecId) -> u64 {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    add_balance_to_db(&mut db, ALICE, 1e18 as u64);
+
+    let mut env = ExecEnv::default();
+    env.set_spec_id(spec_id);
+
+    let erc20 = deploy_contract_with_env(
+        &mut db,
+        get_bytecode("erc20"),
+        Some(ALICE.abi_encode()),
+        &env,
+    )
+    .unwrap();
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (ALICE, U256::from(42e18)).abi_encode());
+    run_tx_with_env(&mut db, &erc20, calldata_mint, &ALICE, &env).expect("Error executing tx");
+
+    let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
+    let calldata_transfer = get_calldata(selector_transfer, (BOB, U256::from(1e18)).abi_encode());
+    run_tx_with_env(&mut db, &erc20, calldata_transfer, &ALICE, &env)
+        .expect("Error executing tx")
+        .gas_used
+}
+
+#[test]
+fn test_spec_dependent_gas() {
+    // A transfer reads both balances (cold) and then writes them back (warm). Before Berlin, the
+    // sender's balance is reset and the recipient's (zero until then) is set.
+    let gas_istanbul = transfer_gas(SpecId::ISTANBUL);
+    let gas_berlin = transfer_gas(SpecId::BERLIN);
+
+    let sloads = 2 * (gas::SLOAD_COLD - gas::SLOAD_ISTANBUL);
+    let sstores = gas::SSTORE_RESET + gas::SSTORE_SET - 2 * gas::SSTORE_WARM;
+    assert_eq!(gas_istanbul + sloads, gas_berlin + sstores);
+}
+
+#[test]
+fn test_pre_berlin_sstore_cost_matches_revm() {
+    let values = [U256::ZERO, U256::from(1), U256::from(2)];
+    for spec_id in [SpecId::FRONTIER, SpecId::PETERSBURG, SpecId::ISTANBUL] {
+        for original_value in values {
+            for present_value in values {
+                for new_value in values {
+                    let vals = SStoreResult {
+                        original_value,
+                        present_value,
+                        new_value,
+                    };
+                    let expected = interpreter::gas::sstore_cost(spec_id, &vals, u64::MAX, false);
+                    assert_eq!(
+                        Some(gas::sstore_cost(spec_id, &vals, false)),
+                        expected,
+                        "{spec_id:?}: {vals:?}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_syscall_not_active() {
+    let (mut db, testbed) = testbed_setup();
+    let calldata = get_calldata(get_selector_from_sig("base_fee()"), vec![]);
+
+    // Available under the latest spec
+    run_tx(&mut db, &testbed, calldata.clone(), &ALICE).expect("Error executing tx");
+
+    // BASEFEE was introduced in London, so the frame halts before it
+    let env = ExecEnv::default().with_spec_id(SpecId::BERLIN);
+    let err = run_tx_with_env(&mut db, &testbed, calldata.clone(), &ALICE, &env)
+        .expect_err("Tx succeeded");
+    assert!(
+        matches!(
+            &err,
+            Error::UnexpectedExecResult(ExecutionResult::Halt {
+                reason: HaltReason::NotActivated,
+                ..
+            })
+        ),
+        "Expected the frame to halt, got: {err}"
+    );
+
+    // Which only fails the call, so a caller can recover from it
+    let selector = get_selector_from_sig("try_forward(address,bytes)");
+    let calldata = get_calldata(selector, (testbed, Bytes::from(calldata)).abi_encode());
+    let output = run_tx_with_env(&mut db, &testbed, calldata, &ALICE, &env)
+        .expect("Caller shouldn't fail")
+        .output;
+    assert_eq!(
+        <(bool, Bytes)>::abi_decode(&output, true).unwrap(),
+        (false, Bytes::new())
+    );
+}
+
+#[test]
+fn test_estimate_gas() {
+    let (mut db, _) = testbed_setup();
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (ALICE, U256::from(42e18)).abi_encode());
+    run_tx(&mut db, &erc20, calldata_mint, &ALICE).expect("Error executing tx");
+
+    let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
+    let calldata_transfer = get_calldata(selector_transfer, (BOB, U256::from(1e18)).abi_encode());
+    let tx = TxSpec::call(ALICE, erc20, calldata_transfer);
+
+    let estimate = estimate_gas(&db, &tx).expect("Error estimating gas");
+    let env = ExecEnv::default();
+
+    // The estimate is tight: one less unit of gas is not enough
+    assert!(send_tx(&mut db, &tx.clone().with_gas_limit(estimate - 1), &env).is_err());
+    let result = send_tx(&mut db, &tx.with_gas_limit(estimate), &env).expect("Error executing tx");
+    assert!(result.status);
+    assert!(result.gas_used <= estimate);
+}
+
+#[test]
+fn test_estimate_gas_revert() {
+    let (mut db, _) = testbed_setup();
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+
+    // Transferring without balance always reverts
+    let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
+    let calldata_transfer = get_calldata(selector_transfer, (BOB, U256::from(1e18)).abi_encode());
+    let tx = TxSpec::call(ALICE, erc20, calldata_transfer);
+
+    let err = estimate_gas(&db, &tx).expect_err("Estimation succeeded");
+    assert!(
+        err.matches_custom_error_with_args(
+            "ERC20Error::InsufficientBalance(uint256)",
+            U256::ZERO.abi_encode()
+        ),
+        "Expected revert payload, got: {err}"
+    );
+}
+
+#[test]
+fn test_access_list() {
+    let (mut db, _) = testbed_setup();
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let calldata_mint = get_calldata(selector_mint, (ALICE, U256::from(42e18)).abi_encode());
+    run_tx(&mut db, &erc20, calldata_mint, &ALICE).expect("Error executing tx");
+
+    let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
+    let calldata_transfer = get_calldata(selector_transfer, (BOB, U256::from(1e18)).abi_encode());
+    let tx = TxSpec::call(ALICE, erc20, calldata_transfer);
+
+    // Pre-warms the balance slot of the sender, which the transfer reads and then writes
+    let layout = StorageLayout::load("erc20").unwrap();
+    let balance_slot = get_field_slot(&layout, "balance_of", &[ALICE.abi_encode()]);
+    let access_list = AccessList(vec![AccessListItem {
+        address: erc20,
+        storage_keys: vec![B256::from(balance_slot)],
+    }]);
+    let access_list_cost = gas::access_list_cost(SpecId::LATEST, &access_list);
+    assert_eq!(
+        access_list_cost,
+        gas::ACCESS_LIST_ADDRESS + gas::ACCESS_LIST_STORAGE_KEY
+    );
+
+    // Both txs run against the same state
+    let env = ExecEnv::default();
+    let cold = send_tx(&mut db.clone(), &tx, &env)
+        .expect("Error executing tx")
+        .gas_used;
+    let warm = send_tx(&mut db, &tx.with_access_list(access_list), &env)
+        .expect("Error executing tx")
+        .gas_used;
+
+    // Only the first read of the slot is cheaper, the intrinsic cost of the list is charged upfront
+    assert_eq!(
+        cold - (warm - access_list_cost),
+        gas::SLOAD_COLD - gas::SLOAD_WARM
+    );
+}
+
+fn capped_sum(db: &mut InMemoryDB, testbed: &Address, n: u64, gas: u64) -> (bool, u64) {
+    let selector = get_selector_from_sig("capped_sum(address,uint64,uint64)");
+    let calldata = get_calldata(selector, (*testbed, n, gas).abi_encode());
+    let output = run_tx(db, testbed, calldata, &ALICE)
+        .expect("Caller shouldn't revert")
+        .output;
+
+    <(bool, u64)>::abi_decode(&output, true).unwrap()
+}
+
+#[test]
+fn test_gas_limited_call() {
+    let (mut db, testbed) = testbed_setup();
+    let n = 1_000;
+
+    // The callee runs out of gas under the cap, which fails the call but not the caller
+    assert_eq!(capped_sum(&mut db, &testbed, n, 5_000), (false, 0));
+
+    // With enough gas, the call goes through
+    assert_eq!(
+        capped_sum(&mut db, &testbed, n, 5_000_000),
+        (true, n * (n - 1) / 2)
+    );
+}
+
+#[test]
+fn test_gas_limited_call_accounting() {
+    let (mut db, testbed) = testbed_setup();
+    let n = 1_000;
+    let call = |db: &mut InMemoryDB, gas: u64| {
+        let selector = get_selector_from_sig("capped_sum_cost(address,uint64,uint64)");
+        let calldata = get_calldata(selector, (testbed, n, gas).abi_encode());
+        let output = run_tx(db, &testbed, calldata, &ALICE)
+            .expect("Caller shouldn't revert")
+            .output;
+        <(bool, u64)>::abi_decode(&output, true).unwrap()
+    };
+
+    // The callee runs out of gas: the whole cap is spent, on top of the warm access to the callee
+    // and the instructions that encode the call
+    let (ok, cost) = call(&mut db, 5_000);
+    assert!(!ok);
+    assert!(cost >= 5_000 + gas::CALL_BASE, "cost: {cost}");
+    assert!(cost < 5_000 + gas::CALL_BASE + 1_000, "cost: {cost}");
+
+    // Which is the same for any cap the callee runs out of
+    let (ok, larger) = call(&mut db, 6_000);
+    assert!(!ok);
+    assert_eq!(larger - cost, 1_000);
+
+    // The callee returns: what it didn't use of the cap is given back to the caller
+    let (ok, cost) = call(&mut db, 5_000_000);
+    assert!(ok);
+    assert!(cost < 5_000_000, "cost: {cost}");
+
+    // Which only depends on what the callee used, not on the cap
+    let (_, capped) = call(&mut db, 4_000_000);
+    assert_eq!(capped, cost);
+
+    // A cap of zero forwards all the gas, as if there were no cap
+    assert_eq!(call(&mut db, 0), (true, cost));
+}
+
+// Deployment cost estimated by `r55-compile` for a contract without constructor args
+#[derive(serde::Deserialize)]
+struct DeployCost {
+    // Reports without a profile predate them, so were built with the default one
+    #[serde(default)]
+    profile: String,
+    initcode_size: usize,
+    runtime_size: usize,
+    intrinsic_gas: u64,
+    code_deposit_gas: u64,
+    create_gas: u64,
+}
+
+fn load_deploy_cost(name: &str) -> DeployCost {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("../r55-output-bytecode")
+        .join(format!("{name}.deploy.json"));
+    let json = std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()));
+
+    serde_json::from_str(&json).expect("Invalid deploy cost report")
+}
+
+#[test]
+fn test_deploy_cost_report() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    let bytecode = get_bytecode("testbed");
+    let cost = load_deploy_cost("testbed");
+
+    // R55 initcode: [header][codesize][bytecode]
+    let mut initcode = Header::current().encode().to_vec();
+    initcode.extend_from_slice(&(bytecode.len() as u32).to_be_bytes());
+    initcode.extend_from_slice(&bytecode);
+    assert_eq!(cost.initcode_size, initcode.len());
+    assert_eq!(
+        cost.intrinsic_gas,
+        gas::intrinsic_gas(SpecId::LATEST, &initcode, true)
+    );
+
+    let result = deploy_contract_with_result(&mut db, bytecode, None, &ExecEnv::default())
+        .expect("Error deploying the contract");
+
+    // The code deposit is charged on the runtime stored at the contract's address
+    let code = db.accounts[&result.address].info.code.clone().unwrap();
+    assert_eq!(cost.runtime_size, code.len());
+    assert_eq!(
+        cost.code_deposit_gas,
+        gas::CODE_DEPOSIT_BYTE * code.len() as u64
+    );
+
+    // Deployed from a contract instead, the tx costs are replaced by the ones of `CREATE`
+    assert_eq!(
+        cost.create_gas,
+        gas::create_cost(SpecId::LATEST, initcode.len() as u64, false) + cost.code_deposit_gas
+    );
+
+    // The constructor execution is on top of the estimate
+    assert!(result.gas_used >= cost.intrinsic_gas + cost.code_deposit_gas);
+}
+
+#[test]
+fn test_erc20_runtime_size() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    let cost = load_deploy_cost("erc20");
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+    let code = db.accounts[&erc20].info.code.clone().unwrap();
+    assert_eq!(cost.runtime_size, code.len());
+    println!("erc20 runtime ({}): {} bytes", cost.profile, code.len());
+
+    // `--min-size` builds leave out the symbols and the panic formatting machinery
+    let max_size = match cost.profile.as_str() {
+        "min-size" => 48 * 1024,
+        _ => 160 * 1024,
+    };
+    assert!(
+        code.len() <= max_size,
+        "erc20 runtime ({}) is {} bytes, over {max_size}",
+        cost.profile,
+        code.len()
+    );
+}
+
+// EVM initcode returning `size` bytes of (zeroed) memory as the runtime code:
+// `PUSH2 size PUSH1 0 RETURN`
+fn sized_init_code(size: u16) -> Bytes {
+    let [high, low] = size.to_be_bytes();
+    Bytes::from(vec![0x61, high, low, 0x60, 0x00, 0xf3])
+}
+
+fn create_raw_calldata(init_code: Bytes) -> Vec<u8> {
+    let selector = get_selector_from_sig("create_raw(bytes)");
+    get_calldata(selector, (init_code,).abi_encode())
+}
+
+// Cost of expanding the memory of an EVM frame to `size` bytes
+fn evm_memory_cost(size: u64) -> u64 {
+    let words = size.div_ceil(32);
+    gas::MEMORY_WORD * words + words * words / 512
+}
+
+#[test]
+fn test_create_gas_scales_with_code_size() {
+    let (mut db, testbed) = testbed_setup();
+
+    // Same initcode size (and non-zero bytes), only the size of the returned code differs
+    let mut create_gas = |size: u16| {
+        let calldata = create_raw_calldata(sized_init_code(size));
+        let intrinsic_gas = gas::intrinsic_gas(SpecId::LATEST, &calldata, false);
+        let result = run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+
+        let created = Address::abi_decode(&result.output, true).unwrap();
+        let code = db.accounts[&created].info.code.clone().unwrap();
+        assert_eq!(code.len(), size as usize);
+
+        result.gas_used - intrinsic_gas
+    };
+    let (small, large) = (0x0101, 0x1010);
+    let (small_gas, large_gas) = (create_gas(small), create_gas(large));
+
+    // On top of the base and initcode costs, shared by both, each byte of code is paid for
+    assert!(small_gas > gas::CREATE_BASE + gas::code_deposit_cost(small as u64));
+    assert_eq!(
+        large_gas - small_gas,
+        gas::code_deposit_cost((large - small) as u64) + evm_memory_cost(large as u64)
+            - evm_memory_cost(small as u64)
+    );
+}
+
+#[test]
+fn test_create_fails_without_gas_for_code_deposit() {
+    let (mut db, testbed) = testbed_setup();
+    let calldata = create_raw_calldata(sized_init_code(u16::MAX));
+
+    // Depositing the code costs ~13.1M gas, more than what is forwarded to the create. Only the
+    // create fails, while the tx goes on with the gas kept by the caller
+    let tx = TxSpec::call(ALICE, testbed, calldata.clone()).with_gas_limit(12_000_000);
+    let result = send_tx(&mut db, &tx, &ExecEnv::default()).expect("Error executing tx");
+    assert!(result.status);
+    assert_eq!(
+        Address::abi_decode(&result.output, true).unwrap(),
+        Address::ZERO
+    );
+
+    // With enough gas, the code is deposited
+    let result = run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+    let created = Address::abi_decode(&result.output, true).unwrap();
+    assert_ne!(created, Address::ZERO);
+    assert!(result.gas_used > gas::code_deposit_cost(u16::MAX as u64));
+}
+
+fn fee_env() -> ExecEnv {
+    let gwei = U256::from(1e9);
+    ExecEnv::default()
+        .with_basefee(gwei * U256::from(10))
+        .with_max_fee_per_gas(gwei * U256::from(30))
+        .with_max_priority_fee_per_gas(gwei * U256::from(2))
+}
+
+#[test]
+fn test_eip1559_fees() {
+    let (mut db, _) = testbed_setup();
+    let env = fee_env();
+    assert_eq!(env.effective_gas_price(), U256::from(12e9));
+
+    // The caller pays the value and the gas, at the base fee plus the priority fee
+    let value = U256::from(1e17);
+    let balance = db.accounts[&ALICE].info.balance;
+    let tx = TxSpec::call(ALICE, BOB, vec![])
+        .with_value(value)
+        .with_gas_limit(100_000);
+    let result = send_tx(&mut db, &tx, &env).expect("Error executing tx");
+
+    let gas_cost = U256::from(result.gas_used) * env.effective_gas_price();
+    assert_eq!(db.accounts[&ALICE].info.balance, balance - value - gas_cost);
+    assert_eq!(db.accounts[&BOB].info.balance, value);
+}
+
+#[test]
+fn test_eip1559_priority_fee_clamped() {
+    let gwei = U256::from(1e9);
+
+    // The priority fee can't push the price over the max fee
+    let env = fee_env().with_max_priority_fee_per_gas(gwei * U256::from(50));
+    assert_eq!(env.priority_fee_per_gas(), gwei * U256::from(20));
+    assert_eq!(env.effective_gas_price(), env.max_fee_per_gas);
+
+    // Legacy txs pay their full gas price, like pre-London ones
+    let legacy = ExecEnv::default().with_basefee(gwei);
+    assert_eq!(legacy.effective_gas_price(), U256::from(DEFAULT_GAS_PRICE));
+    let berlin = fee_env().with_spec_id(SpecId::BERLIN);
+    assert_eq!(berlin.effective_gas_price(), berlin.max_fee_per_gas);
+
+    // The contract sees the effective gas price
+    let (mut db, testbed) = testbed_setup();
+    let calldata = get_calldata(get_selector_from_sig("gas_price()"), vec![]);
+    let tx = TxSpec::call(ALICE, testbed, calldata).with_gas_limit(1_000_000);
+    let result = send_tx(&mut db, &tx, &env).expect("Error executing tx");
+    assert_eq!(
+        U256::abi_decode(&result.output, true),
+        Ok(env.max_fee_per_gas)
+    );
+}
+
+#[test]
+fn test_eip1559_max_fee_below_basefee() {
+    let (mut db, _) = testbed_setup();
+    let env = fee_env().with_basefee(U256::from(31e9));
+
+    let tx = TxSpec::call(ALICE, BOB, vec![]).with_gas_limit(100_000);
+    let err = send_tx(&mut db, &tx, &env).expect_err("Tx succeeded");
+    assert!(
+        matches!(err, Error::MaxFeeBelowBasefee { .. }),
+        "Expected max fee error, got: {err}"
+    );
+}
+
+#[test]
+fn test_max_gas_within_budget() {
+    let (mut db, testbed) = testbed_setup();
+
+    let selector = get_selector_from_sig("budgeted_balance_of(address)");
+    let calldata = get_calldata(selector, ALICE.abi_encode());
+    let result = run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+
+    let [call] = result.budgeted_calls.as_slice() else {
+        panic!(
+            "Expected a single budgeted call, got: {:?}",
+            result.budgeted_calls
+        );
+    };
+    assert_eq!(call.address, testbed);
+    assert_eq!(call.method, "budgeted_balance_of");
+    assert_eq!(call.max_gas, 100_000);
+    assert!(!call.exceeds_budget(), "{call}");
+
+    // Methods without a budget aren't recorded
+    let selector = get_selector_from_sig("balance_of(address)");
+    let calldata = get_calldata(selector, ALICE.abi_encode());
+    let result = run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+    assert!(result.budgeted_calls.is_empty());
+}
+
+#[test]
+#[should_panic(expected = "over_budget_write used")]
+fn test_max_gas_exceeded() {
+    let (mut db, testbed) = testbed_setup();
+
+    let selector = get_selector_from_sig("over_budget_write(address,uint256)");
+    let calldata = get_calldata(selector, (ALICE, U256::from(42)).abi_encode());
+    let _ = run_tx(&mut db, &testbed, calldata, &ALICE);
+}
+
+#[test]
+fn test_max_gas_reported_by_executor() {
+    let (mut db, testbed) = testbed_setup();
+
+    // Embedders (e.g. `r55-node`) get the budgets in the result, without the harness panic
+    let selector = get_selector_from_sig("over_budget_write(address,uint256)");
+    let calldata = get_calldata(selector, (ALICE, U256::from(42)).abi_encode());
+    let result = R55Executor::new(&ExecEnv::default())
+        .call(
+            &mut db,
+            testbed,
+            calldata.into(),
+            U256::ZERO,
+            ALICE,
+            DEFAULT_GAS_LIMIT,
+        )
+        .expect("Error executing tx");
+
+    let [call] = result.budgeted_calls.as_slice() else {
+        panic!(
+            "Expected a single budgeted call, got: {:?}",
+            result.budgeted_calls
+        );
+    };
+    assert_eq!(call.method, "over_budget_write");
+    assert!(call.exceeds_budget(), "{call}");
+}
+
+#[test]
+fn test_max_gas_message() {
+    let call = BudgetedCall {
+        address: Address::ZERO,
+        method: "transfer".into(),
+        max_gas: 60_000,
+        gas_used: 63_120,
+    };
+    assert_eq!(call.to_string(), "transfer used 63,120 > 60,000");
+
+    let call = BudgetedCall {
+        gas_used: 999,
+        ..call
+    };
+    assert_eq!(call.to_string(), "transfer used 999 <= 60,000");
+}
+
+#[test]
+fn test_startup_is_not_charged() {
+    initialize_logger();
+    let (mut db, testbed) = testbed_setup();
+
+    // Only the dispatch of the call (and the GAS syscall) is charged by the time it's entered, not
+    // the startup of the program (zeroing its heap), which runs millions of instructions
+    let calldata = get_calldata(get_selector_from_sig("gas_left_on_entry()"), vec![]);
+    let intrinsic_gas = gas::intrinsic_gas(SpecId::LATEST, &calldata, false);
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    let spent = DEFAULT_GAS_LIMIT - intrinsic_gas - u64::abi_decode(&output, true).unwrap();
+    assert!(
+        (gas::GAS..100_000).contains(&spent),
+        "spent {spent} gas on entry"
+    );
+}
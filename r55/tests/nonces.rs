@@ -0,0 +1,58 @@
+use alloy_sol_types::SolValue;
+use r55::{
+    exec::{deploy_contract, nonce_of, send_txs, ExecEnv, TxSpec, DEPLOYER},
+    get_bytecode,
+    test_utils::{
+        add_balance_to_db, get_calldata, get_selector_from_sig, initialize_logger, ALICE, BOB,
+        CAROL,
+    },
+};
+use revm::{primitives::U256, InMemoryDB};
+
+#[test]
+fn test_deploy_addresses_from_nonce() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    let nonce = nonce_of(&db, DEPLOYER);
+
+    // Each deployment bumps the deployer's nonce, so the same bytecode lands at a new address
+    let first = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+    let second = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+
+    assert_ne!(first, second);
+    assert_eq!(first, DEPLOYER.create(nonce));
+    assert_eq!(second, DEPLOYER.create(nonce + 1));
+    assert_eq!(nonce_of(&db, DEPLOYER), nonce + 2);
+}
+
+#[test]
+fn test_send_txs_nonces() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    for user in [ALICE, BOB] {
+        add_balance_to_db(&mut db, user, 1e18 as u64);
+    }
+    let erc20 = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+    let (alice, bob) = (nonce_of(&db, ALICE), nonce_of(&db, BOB));
+
+    let selector_mint = get_selector_from_sig("mint(address,uint256)");
+    let selector_transfer = get_selector_from_sig("transfer(address,uint256)");
+    let mint = get_calldata(selector_mint, (BOB, U256::from(1e18)).abi_encode());
+    let transfer = get_calldata(selector_transfer, (CAROL, U256::from(1e18)).abi_encode());
+
+    // The second transfer reverts, as BOB has no balance left
+    let txs = [
+        TxSpec::call(ALICE, erc20, mint),
+        TxSpec::call(BOB, erc20, transfer.clone()),
+        TxSpec::call(BOB, erc20, transfer),
+    ];
+    let results = send_txs(&mut db, &txs, &ExecEnv::default());
+
+    assert!(results[0].is_ok());
+    assert!(results[1].is_ok());
+    assert!(results[2].is_err());
+
+    // Failed txs also bump the nonce of their sender
+    assert_eq!(nonce_of(&db, ALICE), alice + 1);
+    assert_eq!(nonce_of(&db, BOB), bob + 2);
+}
@@ -0,0 +1,218 @@
+use alloy_primitives::{keccak256, Address, Bytes, U256};
+use alloy_sol_types::SolValue;
+use r55::{
+    error::{Error, TxResult},
+    exec::{deploy_contract, run_tx},
+    get_bytecode,
+    test_utils::{get_calldata, get_selector_from_sig, testbed_setup, ALICE, BOB, CAROL},
+};
+use revm::{primitives::ExecutionResult, InMemoryDB};
+
+const SIZES: [u64; 7] = [0, 1, 31, 32, 33, 64, 100];
+
+// Raw memory returned by the testbed: the calldata region, which is prefixed with its length
+fn raw_memory(calldata: &[u8], size: u64) -> Vec<u8> {
+    let mut memory = (calldata.len() as u64).to_le_bytes().to_vec();
+    memory.extend_from_slice(calldata);
+    memory.resize(size as usize, 0);
+    memory
+}
+
+fn forward(db: &mut InMemoryDB, testbed: Address, calldata: Vec<u8>) -> Bytes {
+    let selector = get_selector_from_sig("forward(address,bytes)");
+    let calldata = get_calldata(selector, (testbed, Bytes::from(calldata)).abi_encode());
+    let output = run_tx(db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+
+    Bytes::abi_decode(&output, true).expect("Unable to decode output")
+}
+
+#[test]
+fn test_raw_return_sizes() {
+    let (mut db, testbed) = testbed_setup();
+    let selector = get_selector_from_sig("return_raw(uint64)");
+
+    for size in SIZES {
+        let calldata = get_calldata(selector, size.abi_encode());
+        let output = run_tx(&mut db, &testbed, calldata.clone(), &ALICE)
+            .expect("Error executing tx")
+            .output;
+
+        assert_eq!(output, raw_memory(&calldata, size), "size: {size}");
+    }
+}
+
+#[test]
+fn test_call_return_data_sizes() {
+    let (mut db, testbed) = testbed_setup();
+    let selector = get_selector_from_sig("return_raw(uint64)");
+
+    for size in SIZES {
+        let inner_calldata = get_calldata(selector, size.abi_encode());
+        let output = forward(&mut db, testbed, inner_calldata.clone());
+
+        assert_eq!(output, raw_memory(&inner_calldata, size), "size: {size}");
+    }
+}
+
+#[test]
+fn test_call_expected_return_size() {
+    let (mut db, testbed) = testbed_setup();
+    let selector = get_selector_from_sig("return_raw(uint64)");
+    let selector_forward = get_selector_from_sig("forward_sized(address,bytes,uint64)");
+
+    // The output is truncated to the expected size, or zero-filled up to it
+    for size in SIZES {
+        let inner_calldata = get_calldata(selector, size.abi_encode());
+        let args = (testbed, Bytes::from(inner_calldata.clone()), 64u64).abi_encode();
+        let output = run_tx(
+            &mut db,
+            &testbed,
+            get_calldata(selector_forward, args),
+            &ALICE,
+        )
+        .expect("Error executing tx")
+        .output;
+        let output = Bytes::abi_decode(&output, true).expect("Unable to decode output");
+
+        let mut expected = raw_memory(&inner_calldata, size);
+        expected.resize(64, 0);
+        assert_eq!(output, expected, "size: {size}");
+    }
+}
+
+#[test]
+fn test_call_revert_data_sizes() {
+    let (mut db, testbed) = testbed_setup();
+    let selector = get_selector_from_sig("revert_raw(uint64)");
+
+    for size in SIZES {
+        // Direct revert
+        let calldata = get_calldata(selector, size.abi_encode());
+        let err = run_tx(&mut db, &testbed, calldata.clone(), &ALICE).expect_err("Tx succeeded");
+        match err {
+            Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) => {
+                assert_eq!(output, raw_memory(&calldata, size), "size: {size}")
+            }
+            err => panic!("Expected revert, got: {err}"),
+        }
+
+        // Revert of a nested call
+        let output = forward(&mut db, testbed, calldata.clone());
+        assert_eq!(output, raw_memory(&calldata, size), "size: {size}");
+    }
+}
+
+#[test]
+fn test_call_error_decoding() {
+    let (mut db, testbed) = testbed_setup();
+    let (a, b) = (U256::from(42), U256::MAX);
+
+    // The 68-byte error is forwarded untouched
+    let selector = get_selector_from_sig("fail_pair(uint256,uint256)");
+    let output = forward(
+        &mut db,
+        testbed,
+        get_calldata(selector, (a, b).abi_encode()),
+    );
+    assert_eq!(output.len(), 68);
+    assert_eq!(
+        output[..4],
+        keccak256("TestbedError::Pair(uint256,uint256)")[..4]
+    );
+
+    // And it is decoded by the interface of the caller
+    let selector = get_selector_from_sig("forward_pair(address,uint256,uint256)");
+    let calldata = get_calldata(selector, (testbed, a, b).abi_encode());
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+
+    assert_eq!(
+        <(U256, U256)>::abi_decode(&output, true).expect("Unable to decode output"),
+        (a, b)
+    );
+}
+
+fn sub_nonzero_outcome(
+    db: &mut InMemoryDB,
+    testbed: Address,
+    a: U256,
+    b: U256,
+) -> (u64, U256, String) {
+    let selector = get_selector_from_sig("sub_nonzero_outcome(address,uint256,uint256)");
+    let calldata = get_calldata(selector, (testbed, a, b).abi_encode());
+    let output = run_tx(db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+
+    <(u64, U256, String)>::abi_decode(&output, true).expect("Unable to decode output")
+}
+
+#[test]
+fn test_call_error_kinds() {
+    let (mut db, testbed) = testbed_setup();
+    let (three, five) = (U256::from(3), U256::from(5));
+
+    let outcome = sub_nonzero_outcome(&mut db, testbed, five, three);
+    assert_eq!(outcome, (0, U256::from(2), String::new()));
+
+    // The error declared by the callee
+    let outcome = sub_nonzero_outcome(&mut db, testbed, three, three);
+    assert_eq!(outcome, (1, U256::ZERO, String::new()));
+
+    // `Error(string)`
+    let outcome = sub_nonzero_outcome(&mut db, testbed, three, U256::MAX);
+    assert_eq!(outcome, (2, U256::ZERO, "b is too large".to_string()));
+
+    // A panic is decoded as such, rather than as the declared error
+    let outcome = sub_nonzero_outcome(&mut db, testbed, three, five);
+    assert_eq!(outcome, (3, U256::from(0x11), String::new()));
+}
+
+fn revert_data(result: Result<TxResult, Error>) -> Bytes {
+    match result {
+        Err(Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. })) => output,
+        Err(err) => panic!("Expected revert, got: {err}"),
+        Ok(_) => panic!("Tx succeeded"),
+    }
+}
+
+#[test]
+fn test_fallback_proxy_forwarding() {
+    let (mut db, proxy) = testbed_setup();
+    let token = deploy_contract(&mut db, get_bytecode("erc20"), Some(ALICE.abi_encode())).unwrap();
+
+    // Unknown selectors are forwarded to the token
+    let selector = get_selector_from_sig("set_implementation(address)");
+    let calldata = get_calldata(selector, token.abi_encode());
+    run_tx(&mut db, &proxy, calldata, &ALICE).expect("Error executing tx");
+
+    let selector = get_selector_from_sig("mint(address,uint256)");
+    let calldata = get_calldata(selector, (BOB, U256::from(100)).abi_encode());
+    run_tx(&mut db, &token, calldata, &ALICE).expect("Error executing tx");
+
+    // Return data passes through unmodified
+    let selector = get_selector_from_sig("balance_of(address)");
+    let calldata = get_calldata(selector, BOB.abi_encode());
+    let direct = run_tx(&mut db, &token, calldata.clone(), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    let proxied = run_tx(&mut db, &proxy, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(proxied, direct);
+    assert_eq!(U256::abi_decode(&proxied, true).unwrap(), U256::from(100));
+
+    // And so does revert data: neither CAROL nor the proxy hold any tokens
+    let selector = get_selector_from_sig("transfer(address,uint256)");
+    let calldata = get_calldata(selector, (BOB, U256::from(1)).abi_encode());
+    let direct = revert_data(run_tx(&mut db, &token, calldata.clone(), &CAROL));
+    let proxied = revert_data(run_tx(&mut db, &proxy, calldata, &ALICE));
+    assert_eq!(proxied, direct);
+    assert_eq!(
+        proxied[..4],
+        keccak256("ERC20Error::InsufficientBalance(uint256)")[..4]
+    );
+}
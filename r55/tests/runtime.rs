@@ -0,0 +1,1448 @@
+use alloy_primitives::{keccak256, Address, Bytes, B256, U256, U512};
+use alloy_sol_types::{
+    eip712_domain, sol, Panic, PanicKind, Revert, SolError, SolStruct, SolValue,
+};
+use eth_riscv_syscalls::{header::Header, Syscall};
+use r55::{
+    error::{Error, TxResult},
+    exec::{deploy_contract, run_tx, send_tx, ExecEnv, TxSpec, DEFAULT_GAS_LIMIT},
+    failure::{last_tx_failures, FrameFailure},
+    get_bytecode,
+    layout::StorageLayout,
+    programs,
+    test_utils::{
+        add_balance_to_db, add_contract_to_db, decode_events, get_calldata, get_field_slot,
+        get_selector_from_sig, read_db_slot, testbed_setup, BlockEnvBuilder, MinePolicy, ALICE,
+        BOB, CAROL,
+    },
+};
+use revm::{
+    primitives::{AccountInfo, ExecutionResult, HaltReason},
+    InMemoryDB,
+};
+use rvemu::exception::Exception;
+
+fn heap_usage_after(db: &mut InMemoryDB, testbed: &Address, rounds: u64) -> u64 {
+    let selector = get_selector_from_sig("alloc_stress(uint64)");
+    let output = run_tx(
+        db,
+        testbed,
+        get_calldata(selector, rounds.abi_encode()),
+        &ALICE,
+    )
+    .expect("Error executing tx")
+    .output;
+
+    u64::abi_decode(&output, true).expect("Unable to decode output")
+}
+
+#[test]
+fn test_heap_usage_is_bounded() {
+    let (mut db, testbed) = testbed_setup();
+
+    // Dropped Vecs are reused, so the heap doesn't grow with the number of rounds
+    let usage_one = heap_usage_after(&mut db, &testbed, 1);
+    let usage_many = heap_usage_after(&mut db, &testbed, 200);
+
+    assert_eq!(usage_one, usage_many);
+}
+
+#[test]
+fn test_calls_dont_share_heap() {
+    let (mut db, testbed) = testbed_setup();
+    let size = 4096_u64;
+    let dirty_heap = get_calldata(
+        get_selector_from_sig("dirty_heap(uint64)"),
+        size.abi_encode(),
+    );
+    let heap_garbage = get_calldata(
+        get_selector_from_sig("heap_garbage(uint64)"),
+        size.abi_encode(),
+    );
+
+    // Consecutive calls to the same code, which reuse its loaded program
+    run_tx(&mut db, &testbed, dirty_heap.clone(), &ALICE).expect("Error executing tx");
+    let output = run_tx(&mut db, &testbed, heap_garbage.clone(), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(u64::abi_decode(&output, true).unwrap(), 0);
+
+    // Nor does a nested call see the heap of its caller, which is dirtied by the decoded calldata
+    let calldata = get_calldata(
+        get_selector_from_sig("forward(address,bytes)"),
+        (testbed, Bytes::from(heap_garbage)).abi_encode(),
+    );
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    let output = Bytes::abi_decode(&output, true).unwrap();
+    assert_eq!(u64::abi_decode(&output, true).unwrap(), 0);
+}
+
+#[test]
+fn test_mapping_accesses_dont_grow_heap() {
+    let (mut db, testbed) = testbed_setup();
+    let amount = U256::from(3);
+    let selector = get_selector_from_sig("set_balance(address,uint256)");
+    let calldata = get_calldata(selector, (Address::with_last_byte(1), amount).abi_encode());
+    run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+
+    let mut sum_balances = |n: u64| {
+        let selector = get_selector_from_sig("sum_balances(uint64)");
+        let output = run_tx(
+            &mut db,
+            &testbed,
+            get_calldata(selector, n.abi_encode()),
+            &ALICE,
+        )
+        .expect("Error executing tx")
+        .output;
+        <(U256, u64)>::abi_decode(&output, true).unwrap()
+    };
+
+    // Read accesses get a guard per key, which is reused when the key is accessed again: the heap
+    // grows with the number of owners read, not with the number of reads
+    let (sum_few, usage_few) = sum_balances(10);
+    let (sum_all, usage_all) = sum_balances(256);
+    let (sum_many, usage_many) = sum_balances(10_000);
+    assert_eq!(sum_few, amount);
+    assert_eq!(sum_all, amount);
+    assert_eq!(sum_many, amount * U256::from(10_000 / 256 + 1));
+    assert!(usage_few < usage_all, "{usage_few} >= {usage_all}");
+    assert_eq!(usage_all, usage_many);
+}
+
+#[test]
+fn test_mapping_guard_held_across_accesses() {
+    let (mut db, testbed) = testbed_setup();
+    let owner = Address::with_last_byte(0xff);
+    let selector = get_selector_from_sig("set_balance(address,uint256)");
+    let calldata = get_calldata(selector, (owner, U256::from(7)).abi_encode());
+    run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+
+    // The guard still points to the balance of `owner`, however many guards were created since
+    let selector = get_selector_from_sig("held_balance(address,uint64)");
+    let calldata = get_calldata(selector, (owner, 200u64).abi_encode());
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), U256::from(7));
+}
+
+#[test]
+fn test_static_args_decoded_in_place() {
+    let (mut db, testbed) = testbed_setup();
+    let selector = get_selector_from_sig("heap_usage_on_entry(address,uint256)");
+    let calldata = get_calldata(selector, (BOB, U256::from(42)).abi_encode());
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+
+    assert_eq!(u64::abi_decode(&output, true).unwrap(), 0);
+}
+
+#[test]
+fn test_allocation_failure_panics() {
+    let (mut db, testbed) = testbed_setup();
+    let selector = get_selector_from_sig("alloc_bytes(uint64)");
+
+    // Fits in the heap
+    let calldata = get_calldata(selector, 1024_u64.abi_encode());
+    run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+
+    // Exceeds the heap
+    let calldata = get_calldata(selector, (64 * 1024 * 1024_u64).abi_encode());
+    let err = run_tx(&mut db, &testbed, calldata, &ALICE).expect_err("Tx succeeded");
+    let Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) = err else {
+        panic!("Expected revert, got: {err}");
+    };
+
+    let panic = Panic::abi_decode(&output, true).expect("Unable to decode panic");
+    assert_eq!(panic.kind(), Some(PanicKind::ResourceError));
+}
+
+#[test]
+fn test_msg_sig() {
+    let (mut db, testbed) = testbed_setup();
+
+    let selector = get_selector_from_sig("own_selector()");
+    let output = run_tx(&mut db, &testbed, selector.to_vec(), &ALICE)
+        .expect("Error executing tx")
+        .output;
+
+    let msg_sig = u32::abi_decode(&output, true).expect("Unable to decode output");
+    assert_eq!(msg_sig.to_be_bytes(), selector);
+}
+
+fn binary_op(
+    db: &mut InMemoryDB,
+    testbed: &Address,
+    op: &str,
+    a: U256,
+    b: U256,
+) -> Result<U256, Error> {
+    let selector = get_selector_from_sig(&format!("{op}(uint256,uint256)"));
+    let output = run_tx(
+        db,
+        testbed,
+        get_calldata(selector, (a, b).abi_encode()),
+        &ALICE,
+    )?
+    .output;
+
+    Ok(U256::abi_decode(&output, true).expect("Unable to decode output"))
+}
+
+fn expect_panic(result: Result<U256, Error>, code: u64) {
+    let err = result.expect_err("Tx succeeded");
+    let Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) = err else {
+        panic!("Expected revert, got: {err}");
+    };
+
+    assert_eq!(
+        output,
+        Panic {
+            code: U256::from(code)
+        }
+        .abi_encode()
+    );
+}
+
+#[test]
+fn test_checked_math() {
+    let (mut db, testbed) = testbed_setup();
+    let one = U256::from(1);
+
+    let sum = binary_op(&mut db, &testbed, "checked_add", U256::MAX - one, one).unwrap();
+    assert_eq!(sum, U256::MAX);
+    expect_panic(
+        binary_op(&mut db, &testbed, "checked_add", U256::MAX, one),
+        0x11,
+    );
+
+    let quotient = binary_op(&mut db, &testbed, "checked_div", U256::from(42), one).unwrap();
+    assert_eq!(quotient, U256::from(42));
+    expect_panic(
+        binary_op(&mut db, &testbed, "checked_div", U256::from(42), U256::ZERO),
+        0x12,
+    );
+
+    // Explicit wrapping operations still wrap
+    let wrapped = binary_op(&mut db, &testbed, "wrapping_add", U256::MAX, one).unwrap();
+    assert_eq!(wrapped, U256::ZERO);
+}
+
+fn unary_op(
+    db: &mut InMemoryDB,
+    testbed: &Address,
+    op: &str,
+    value: U256,
+) -> Result<Vec<u8>, Error> {
+    let selector = get_selector_from_sig(&format!("{op}(uint256)"));
+    let calldata = get_calldata(selector, value.abi_encode());
+    Ok(run_tx(db, testbed, calldata, &ALICE)?.output)
+}
+
+fn expect_revert(result: Result<Vec<u8>, Error>) -> Bytes {
+    let err = result.expect_err("Tx succeeded");
+    let Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) = err else {
+        panic!("Expected revert, got: {err}");
+    };
+
+    output
+}
+
+#[test]
+fn test_require_and_ensure() {
+    let (mut db, testbed) = testbed_setup();
+    let value = U256::from(42);
+
+    for op in ["require_positive", "ensure_positive"] {
+        let output = unary_op(&mut db, &testbed, op, value).expect("Error executing tx");
+        assert_eq!(U256::abi_decode(&output, true).unwrap(), value);
+    }
+
+    let output = expect_revert(unary_op(&mut db, &testbed, "require_positive", U256::ZERO));
+    assert_eq!(output, Revert::from("value must be positive").abi_encode());
+
+    let output = expect_revert(unary_op(&mut db, &testbed, "ensure_positive", U256::ZERO));
+    assert_eq!(output[..], keccak256("TestbedError::Zero")[..4]);
+}
+
+#[test]
+fn test_unit_return_types() {
+    let (mut db, testbed) = testbed_setup();
+    let balance_of = |db: &mut InMemoryDB| {
+        let calldata = get_calldata(
+            get_selector_from_sig("balance_of(address)"),
+            BOB.abi_encode(),
+        );
+        let output = run_tx(db, &testbed, calldata, &ALICE).unwrap().output;
+        U256::abi_decode(&output, true).unwrap()
+    };
+
+    // Both `()` and `Ok(())` return no data, like Solidity functions without return values
+    for sig in [
+        "set_balance(address,uint256)",
+        "set_nonzero_balance(address,uint256)",
+    ] {
+        let calldata = get_calldata(
+            get_selector_from_sig(sig),
+            (BOB, U256::from(1)).abi_encode(),
+        );
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert!(output.is_empty(), "{sig} returned {output:?}");
+    }
+    let calldata = get_calldata(
+        get_selector_from_sig("set_nonzero_balance(address,uint256)"),
+        (BOB, U256::ZERO).abi_encode(),
+    );
+    let output = expect_revert(run_tx(&mut db, &testbed, calldata, &ALICE).map(|r| r.output));
+    assert_eq!(output[..], keccak256("TestbedError::Zero")[..4]);
+
+    // Through the interface, the empty success data decodes as `Ok(())`, and the error as is
+    let selector = get_selector_from_sig("x_set_nonzero_balance(address,address,uint256)");
+    let x_set = |db: &mut InMemoryDB, amount: U256| {
+        let calldata = get_calldata(selector, (testbed, BOB, amount).abi_encode());
+        let output = run_tx(db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        u64::abi_decode(&output, true).unwrap()
+    };
+    assert_eq!(x_set(&mut db, U256::from(5)), 0);
+    assert_eq!(balance_of(&mut db), U256::from(5));
+    assert_eq!(x_set(&mut db, U256::ZERO), 1);
+    assert_eq!(balance_of(&mut db), U256::from(5));
+}
+
+#[test]
+fn test_dynamic_arrays() {
+    let (mut db, testbed) = testbed_setup();
+    let selector_airdrop = get_selector_from_sig("x_airdrop(address,address[],uint256[])");
+
+    // Round-trips through the interface of another contract, which is the testbed itself
+    for n in [0_u64, 1, 100] {
+        let recipients: Vec<Address> = (1..=n)
+            .map(|i| Address::left_padding_from(&i.to_be_bytes()))
+            .collect();
+        let amounts: Vec<U256> = (1..=n).map(|i| U256::from(i * 1000)).collect();
+
+        let calldata = get_calldata(
+            selector_airdrop,
+            (testbed, recipients.clone(), amounts.clone()).abi_encode(),
+        );
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert_eq!(Vec::<U256>::abi_decode(&output, true).unwrap(), amounts);
+    }
+
+    // Lengths are checked by the method itself
+    let calldata = get_calldata(
+        get_selector_from_sig("airdrop(address[],uint256[])"),
+        (vec![BOB], vec![U256::from(1), U256::from(2)]).abi_encode(),
+    );
+    let output = expect_revert(run_tx(&mut db, &testbed, calldata, &ALICE).map(|r| r.output));
+    assert_eq!(output[..], keccak256("TestbedError::LengthMismatch")[..4]);
+
+    // Arrays of dynamic elements
+    let selector_concat = get_selector_from_sig("concat(bytes[])");
+    for items in [
+        vec![],
+        vec![Bytes::from(vec![1])],
+        vec![Bytes::new(), Bytes::from(vec![2; 40])],
+    ] {
+        let calldata = get_calldata(selector_concat, items.abi_encode());
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        let expected: Vec<u8> = items.concat();
+        assert_eq!(
+            <(u64, Bytes)>::abi_decode(&output, true).unwrap(),
+            (items.len() as u64, Bytes::from(expected))
+        );
+    }
+}
+
+#[test]
+fn test_malformed_dynamic_array() {
+    let (mut db, testbed) = testbed_setup();
+    let owners = vec![ALICE, BOB, CAROL];
+    let calldata = get_calldata(
+        get_selector_from_sig("balances_of(address[])"),
+        owners.abi_encode(),
+    );
+    let run = |db: &mut InMemoryDB, calldata: Vec<u8>| run_tx(db, &testbed, calldata, &ALICE);
+    let expect_decode_error = |result: Result<TxResult, Error>, case: &str| {
+        let output = expect_revert(result.map(|r| r.output));
+        let reason = Revert::abi_decode(&output, true).unwrap().reason;
+        assert_eq!(reason, "failed to decode calldata", "{case}");
+    };
+
+    // Offsets and lengths that run past the end of the calldata
+    let with_word = |at: usize, word: U256| {
+        let mut malformed = calldata.clone();
+        malformed[4 + at..4 + at + 32].copy_from_slice(&word.to_be_bytes::<32>());
+        malformed
+    };
+    for (at, word) in [
+        (0, U256::from(calldata.len())),
+        (0, U256::from(u64::MAX)),
+        (0, U256::MAX),
+        (32, U256::from(owners.len() + 1)),
+        (32, U256::from(u64::MAX)),
+        (32, U256::MAX),
+    ] {
+        let case = format!("word {word} at {at}");
+        expect_decode_error(run(&mut db, with_word(at, word)), &case);
+    }
+    for len in 4..calldata.len() {
+        let case = format!("truncated to {len}");
+        expect_decode_error(run(&mut db, calldata[..len].to_vec()), &case);
+    }
+
+    // Bit-flipped offsets and lengths either decode or revert cleanly
+    for i in 4..4 + 64 {
+        let mut flipped = calldata.clone();
+        flipped[i] ^= 1 << (i * 3 % 8);
+        match run(&mut db, flipped) {
+            Ok(_) | Err(Error::UnexpectedExecResult(ExecutionResult::Revert { .. })) => {}
+            Err(err) => panic!("Unexpected failure flipping byte {i}: {err}"),
+        }
+    }
+}
+
+#[test]
+fn test_formatted_require() {
+    let (mut db, testbed) = testbed_setup();
+    let owner = Address::repeat_byte(0xab);
+
+    let selector = get_selector_from_sig("require_formatted(uint256,address)");
+    let calldata = get_calldata(selector, (U256::from(100), owner).abi_encode());
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), U256::from(100));
+
+    // Interpolates the amount, in decimal and hex, and the (lowercase) address
+    let amount = U256::from(10).pow(U256::from(30)) + U256::from(7);
+    let calldata = get_calldata(selector, (amount, owner).abi_encode());
+    let output = expect_revert(run_tx(&mut db, &testbed, calldata, &ALICE).map(|r| r.output));
+    assert_eq!(
+        Revert::abi_decode(&output, true).unwrap().reason,
+        "amount 1000000000000000000000000000007 (0xc9f2c9cd04674edea40000007) too large for \
+         0xabababababababababababababababababababab"
+    );
+
+    // Messages longer than 256 bytes are cut, ending with an ellipsis
+    let selector = get_selector_from_sig("require_truncated(address)");
+    let calldata = get_calldata(selector, owner.abi_encode());
+    let output = expect_revert(run_tx(&mut db, &testbed, calldata, &ALICE).map(|r| r.output));
+    let reason = Revert::abi_decode(&output, true).unwrap().reason;
+    let full = vec![format!("{:#x}", owner); 8].join(" ");
+    assert_eq!(reason, format!("{}…", &full[..253]));
+    assert_eq!(reason.len(), 256);
+}
+
+#[test]
+fn test_formatted_require_code_size() {
+    let mut db = InMemoryDB::default();
+    add_balance_to_db(&mut db, ALICE, 1e18 as u64);
+    let [plain, formatted] = ["revert_reasons_plain", "revert_reasons_formatted"]
+        .map(|name| deploy_contract(&mut db, get_bytecode(name), None).unwrap());
+    let [plain_size, formatted_size] =
+        [plain, formatted].map(|vault| db.accounts[&vault].info.code.as_ref().unwrap().len());
+    println!("runtime: {plain_size} bytes (constant reason) -> {formatted_size} bytes (formatted)");
+
+    // The formatter only adds the code of the args it interpolates, without `core::fmt`
+    assert!(
+        formatted_size <= plain_size + 4 * 1024,
+        "the formatter grew the runtime from {plain_size} to {formatted_size} bytes"
+    );
+
+    let deposit = get_calldata(
+        get_selector_from_sig("deposit(uint256)"),
+        U256::from(5).abi_encode(),
+    );
+    let withdraw = get_calldata(
+        get_selector_from_sig("withdraw(uint256)"),
+        U256::from(7).abi_encode(),
+    );
+    let reasons = [plain, formatted].map(|vault| {
+        run_tx(&mut db, &vault, deposit.clone(), &ALICE).expect("Error executing tx");
+        let output =
+            expect_revert(run_tx(&mut db, &vault, withdraw.clone(), &ALICE).map(|r| r.output));
+        Revert::abi_decode(&output, true).unwrap().reason
+    });
+    assert_eq!(reasons[0], "insufficient balance");
+    assert_eq!(reasons[1], format!("balance of {ALICE:#x} is 5, below 7"));
+}
+
+fn sum(db: &mut InMemoryDB, testbed: &Address, op: &str, n: u64) -> (u64, u64) {
+    let selector = get_selector_from_sig(&format!("{op}(uint64)"));
+    let result = run_tx(db, testbed, get_calldata(selector, n.abi_encode()), &ALICE)
+        .expect("Error executing tx");
+
+    let sum = u64::abi_decode(&result.output, true).expect("Unable to decode output");
+    (sum, result.gas_used)
+}
+
+#[test]
+fn test_wrapping_math() {
+    let (mut db, testbed) = testbed_setup();
+    let one = U256::from(1);
+
+    // Unchecked operations wrap at the boundary, instead of reverting
+    let diff = binary_op(&mut db, &testbed, "unchecked_sub", U256::ZERO, one).unwrap();
+    assert_eq!(diff, U256::MAX);
+    let diff = binary_op(&mut db, &testbed, "unchecked_sub", U256::MAX, one).unwrap();
+    assert_eq!(diff, U256::MAX - one);
+
+    // Same result, but the unchecked loop is cheaper as it has no overflow branches
+    let n = 500;
+    let (checked, checked_gas) = sum(&mut db, &testbed, "checked_sum", n);
+    let (unchecked, unchecked_gas) = sum(&mut db, &testbed, "unchecked_sum", n);
+    assert_eq!(checked, n * (n - 1) / 2);
+    assert_eq!(unchecked, checked);
+    assert!(
+        unchecked_gas < checked_gas,
+        "unchecked: {unchecked_gas}, checked: {checked_gas}"
+    );
+}
+
+fn modular_op(db: &mut InMemoryDB, testbed: &Address, op: &str, args: (U256, U256, U256)) -> U256 {
+    let selector = get_selector_from_sig(&format!("{op}(uint256,uint256,uint256)"));
+    let output = run_tx(
+        db,
+        testbed,
+        get_calldata(selector, args.abi_encode()),
+        &ALICE,
+    )
+    .expect("Error executing tx")
+    .output;
+
+    U256::abi_decode(&output, true).expect("Unable to decode output")
+}
+
+// Reference implementation, widening to 512 bits
+fn reference_mod(a: U256, b: U256, n: U256, mul: bool) -> U256 {
+    if n.is_zero() {
+        return U256::ZERO;
+    }
+
+    let (a, b, n) = (U512::from(a), U512::from(b), U512::from(n));
+    let result = if mul { a * b % n } else { (a + b) % n };
+    U256::from(result)
+}
+
+#[test]
+fn test_addmod_mulmod() {
+    let (mut db, testbed) = testbed_setup();
+
+    // Edge cases
+    let mut cases = vec![
+        (U256::MAX, U256::MAX, U256::MAX),
+        (U256::MAX, U256::MAX, U256::from(1)),
+        (U256::MAX, U256::MAX, U256::ZERO),
+        (U256::MAX, U256::from(2), U256::from(7)),
+        (U256::ZERO, U256::MAX, U256::MAX - U256::from(1)),
+    ];
+
+    // Pseudo-random inputs (xorshift)
+    let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+    let mut next_u256 = || {
+        let limbs = [0; 4].map(|_: u64| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        });
+        U256::from_limbs(limbs)
+    };
+    for _ in 0..16 {
+        // Moduli of all sizes
+        let n = next_u256();
+        let n = n >> (n.as_limbs()[0] % 256) as usize;
+        cases.push((next_u256(), next_u256(), n));
+    }
+
+    for (a, b, n) in cases {
+        assert_eq!(
+            modular_op(&mut db, &testbed, "add_mod", (a, b, n)),
+            reference_mod(a, b, n, false),
+            "addmod({a}, {b}, {n})"
+        );
+        assert_eq!(
+            modular_op(&mut db, &testbed, "mul_mod", (a, b, n)),
+            reference_mod(a, b, n, true),
+            "mulmod({a}, {b}, {n})"
+        );
+    }
+}
+
+fn guarded_call_calldata(testbed: Address, inner_calldata: Vec<u8>) -> Vec<u8> {
+    let selector = get_selector_from_sig("guarded_call(address,bytes)");
+    get_calldata(
+        selector,
+        (testbed, Bytes::from(inner_calldata)).abi_encode(),
+    )
+}
+
+fn guarded_call(db: &mut InMemoryDB, testbed: Address, inner_calldata: Vec<u8>) -> Bytes {
+    let calldata = guarded_call_calldata(testbed, inner_calldata);
+    let output = run_tx(db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+
+    Bytes::abi_decode(&output, true).expect("Unable to decode output")
+}
+
+#[test]
+fn test_reentrancy_lock() {
+    let (mut db, testbed) = testbed_setup();
+    let is_locked = get_selector_from_sig("is_locked()").to_vec();
+
+    // The lock is held during the call, and released afterwards
+    for _ in 0..2 {
+        let output = guarded_call(&mut db, testbed, is_locked.clone());
+        assert!(bool::abi_decode(&output, true).unwrap());
+
+        let output = run_tx(&mut db, &testbed, is_locked.clone(), &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert!(!bool::abi_decode(&output, true).unwrap());
+    }
+
+    // Reentering through a callback reverts
+    let reentrant_calldata = guarded_call_calldata(testbed, is_locked);
+    let output = guarded_call(&mut db, testbed, reentrant_calldata);
+    assert_eq!(output[..], keccak256("ReentrancyGuardReentrantCall()")[..4]);
+}
+
+fn read_slot(db: &mut InMemoryDB, testbed: &Address, slot: U256) -> U256 {
+    let selector = get_selector_from_sig("read_slot(uint256)");
+    let output = run_tx(
+        db,
+        testbed,
+        get_calldata(selector, slot.abi_encode()),
+        &ALICE,
+    )
+    .expect("Error executing tx")
+    .output;
+
+    U256::abi_decode(&output, true).expect("Unable to decode output")
+}
+
+#[test]
+fn test_raw_storage_access() {
+    let (mut db, testbed) = testbed_setup();
+
+    // Raw writes land in the given slot
+    let (slot, value) = (U256::from(0xdead), U256::from(42));
+    let selector = get_selector_from_sig("write_slot(uint256,uint256)");
+    let calldata = get_calldata(selector, (slot, value).abi_encode());
+    run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+
+    assert_eq!(read_db_slot(&mut db, testbed, slot), value);
+    assert_eq!(read_slot(&mut db, &testbed, slot), value);
+
+    // Raw reads see the slots of typed fields, `balances` being the third one
+    let amount = U256::from(7);
+    let selector = get_selector_from_sig("set_balance(address,uint256)");
+    let calldata = get_calldata(selector, (BOB, amount).abi_encode());
+    run_tx(&mut db, &testbed, calldata, &ALICE).expect("Error executing tx");
+
+    let balance_slot = keccak256([BOB.abi_encode(), U256::from(2).abi_encode()].concat()).into();
+    assert_eq!(read_db_slot(&mut db, testbed, balance_slot), amount);
+    assert_eq!(read_slot(&mut db, &testbed, balance_slot), amount);
+}
+
+#[test]
+fn test_storage_init_defaults() {
+    let (mut db, testbed) = testbed_setup();
+
+    // Deployed without args, and yet `total_supply` holds its `#[init]` default
+    let layout = StorageLayout::load("testbed").unwrap();
+    let slot = get_field_slot(&layout, "total_supply", &[]);
+    assert_eq!(read_db_slot(&mut db, testbed, slot), U256::from(1_000_000));
+
+    // The fields without a default are left zeroed
+    let slot = get_field_slot(&layout, "implementation", &[]);
+    assert_eq!(read_db_slot(&mut db, testbed, slot), U256::ZERO);
+}
+
+#[test]
+fn test_unchanged_mapping_write() {
+    let (mut db, testbed) = testbed_setup();
+    let sstores = |db: &mut InMemoryDB, sig: &str, args: Vec<u8>| {
+        let calldata = get_calldata(get_selector_from_sig(sig), args);
+        let result = run_tx(db, &testbed, calldata, &ALICE).expect("Error executing tx");
+        result
+            .syscalls
+            .get(&Syscall::SStore)
+            .copied()
+            .unwrap_or_default()
+    };
+
+    // A real update stores the balance
+    let args = (BOB, U256::from(5)).abi_encode();
+    assert_eq!(sstores(&mut db, "add_balance(address,uint256)", args), 1);
+
+    // Adding zero leaves it unchanged, so it isn't stored again
+    let args = (BOB, U256::ZERO).abi_encode();
+    assert_eq!(sstores(&mut db, "add_balance(address,uint256)", args), 0);
+    let bob_slot = keccak256([BOB.abi_encode(), U256::from(2).abi_encode()].concat()).into();
+    assert_eq!(read_db_slot(&mut db, testbed, bob_slot), U256::from(5));
+
+    // Unless explicitly forced
+    let args = BOB.abi_encode();
+    assert_eq!(sstores(&mut db, "touch_balance(address)", args), 1);
+    assert_eq!(read_db_slot(&mut db, testbed, bob_slot), U256::from(5));
+}
+
+#[test]
+fn test_keccak256() {
+    let (mut db, testbed) = testbed_setup();
+
+    // Both below and above the stack buffer of `keccak256_concat`
+    for len in [0, 1, 31, 32, 33, 64, 128, 129, 300] {
+        let data: Vec<u8> = (0..len).map(|i| i as u8).collect();
+        let expected = keccak256(&data);
+
+        let selector = get_selector_from_sig("hash(bytes)");
+        let calldata = get_calldata(selector, (Bytes::from(data.clone()),).abi_encode());
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert_eq!(
+            B256::abi_decode(&output, true).unwrap(),
+            expected,
+            "len: {len}"
+        );
+
+        let (a, b) = data.split_at(len / 3);
+        let selector = get_selector_from_sig("hash_concat(bytes,bytes)");
+        let calldata = get_calldata(
+            selector,
+            (Bytes::copy_from_slice(a), Bytes::copy_from_slice(b)).abi_encode(),
+        );
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert_eq!(
+            B256::abi_decode(&output, true).unwrap(),
+            expected,
+            "len: {len}"
+        );
+    }
+}
+
+#[test]
+fn test_batched_context() {
+    let (mut db, testbed) = testbed_setup();
+
+    let selector = get_selector_from_sig("context_fields()");
+    let fields = run_tx(&mut db, &testbed, selector.to_vec(), &ALICE).expect("Error executing tx");
+    let selector = get_selector_from_sig("context_batched()");
+    let batched = run_tx(&mut db, &testbed, selector.to_vec(), &ALICE).expect("Error executing tx");
+
+    assert_eq!(batched.output, fields.output);
+    let (_, _, chain_id, origin, _) =
+        <(U256, U256, u64, Address, U256)>::abi_decode(&batched.output, true).unwrap();
+    assert_eq!((chain_id, origin), (1, ALICE));
+
+    // A single syscall replaces the five individual ones, even when loaded several times
+    let env_syscalls = [
+        Syscall::Timestamp,
+        Syscall::Number,
+        Syscall::ChainId,
+        Syscall::Origin,
+        Syscall::BaseFee,
+    ];
+    for syscall in env_syscalls {
+        assert_eq!(fields.syscalls.get(&syscall), Some(&1), "{syscall}");
+        assert_eq!(batched.syscalls.get(&syscall), None, "{syscall}");
+    }
+    assert_eq!(fields.syscalls.get(&Syscall::Context), None);
+    assert_eq!(batched.syscalls.get(&Syscall::Context), Some(&1));
+}
+
+type BlockFields = (U256, U256, U256, U256, Address, U256, U256);
+
+fn block_fields(db: &mut InMemoryDB, testbed: Address, env: &ExecEnv) -> BlockFields {
+    let selector = get_selector_from_sig("block_fields()");
+    let tx = TxSpec::call(ALICE, testbed, selector.to_vec()).with_gas_limit(1_000_000);
+    let output = send_tx(db, &tx, env).expect("Error executing tx").output;
+
+    BlockFields::abi_decode(&output, true).expect("Unable to decode output")
+}
+
+#[test]
+fn test_block_env() {
+    let (mut db, testbed) = testbed_setup();
+    let block = BlockEnvBuilder::default()
+        .with_number(U256::from(19_000_000))
+        .with_timestamp(U256::from(1_700_000_000))
+        .with_gas_limit(U256::from(30_000_000))
+        .with_basefee(U256::from(7))
+        .with_coinbase(CAROL)
+        .with_prevrandao(B256::repeat_byte(0x42))
+        .with_excess_blob_gas(10_000_000);
+    let env = ExecEnv::default().with_block(block);
+    let blob_base_fee = block.build(env.spec_id).get_blob_gasprice().unwrap();
+    assert!(blob_base_fee > 1);
+
+    // Every field is visible from the contract
+    let expected = (
+        block.number,
+        block.timestamp,
+        block.gas_limit,
+        block.basefee,
+        block.coinbase,
+        U256::from_be_bytes(block.prevrandao.0),
+        U256::from(blob_base_fee),
+    );
+    assert_eq!(block_fields(&mut db, testbed, &env), expected);
+
+    // The block persists across txs, until it is mined
+    assert_eq!(block_fields(&mut db, testbed, &env), expected);
+    env.mine();
+    let (number, timestamp, ..) = block_fields(&mut db, testbed, &env);
+    assert_eq!(number, block.number + U256::from(1));
+    assert_eq!(timestamp, block.timestamp + U256::from(12));
+}
+
+#[test]
+fn test_block_deadline() {
+    let (mut db, testbed) = testbed_setup();
+    let block = BlockEnvBuilder::default().with_timestamp(U256::from(1_700_000_000));
+    let env = ExecEnv::default().with_block(block);
+
+    let deadline = 1_700_000_000u64;
+    let calldata = get_calldata(
+        get_selector_from_sig("before_deadline(uint64)"),
+        deadline.abi_encode(),
+    );
+    let tx = TxSpec::call(ALICE, testbed, calldata);
+
+    // The deadline itself is still in time
+    let result = send_tx(&mut db, &tx, &env).expect("Error executing tx");
+    assert!(result.status);
+    assert_eq!(u64::abi_decode(&result.output, true).unwrap(), deadline);
+
+    // The next block is past it
+    env.mine();
+    let output = expect_revert(send_tx(&mut db, &tx, &env).map(|result| result.output));
+    let reason = Revert::abi_decode(&output, true).unwrap().reason;
+    assert_eq!(reason, "deadline expired");
+}
+
+#[test]
+fn test_block_env_mine_every_tx() {
+    let (mut db, testbed) = testbed_setup();
+    let block = BlockEnvBuilder::default()
+        .with_number(U256::from(100))
+        .with_block_time(2)
+        .with_policy(MinePolicy::EveryTx);
+    let env = ExecEnv::default().with_block(block);
+
+    for i in 0..3u64 {
+        let (number, timestamp, ..) = block_fields(&mut db, testbed, &env);
+        assert_eq!(number, block.number + U256::from(i));
+        assert_eq!(timestamp, block.timestamp + U256::from(2 * i));
+    }
+    assert_eq!(env.block().number, block.number + U256::from(3));
+}
+
+#[test]
+fn test_deploy_with_value() {
+    let (mut db, testbed) = testbed_setup();
+    add_balance_to_db(&mut db, CAROL, 1e19 as u64);
+
+    // Deploy a vault seeded with 1 ETH
+    let value = U256::from(1e18);
+    let selector = get_selector_from_sig("deploy_vault()");
+    let tx = TxSpec::call(CAROL, testbed, get_calldata(selector, vec![])).with_value(value);
+    let output = send_tx(&mut db, &tx, &ExecEnv::default())
+        .expect("Error executing tx")
+        .output;
+    let vault = Address::abi_decode(&output, true).unwrap();
+
+    // The constructor already saw the value as its balance
+    let selector = get_selector_from_sig("initial_balance()");
+    let output = run_tx(&mut db, &vault, get_calldata(selector, vec![]), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), value);
+
+    let selector = get_selector_from_sig("owner()");
+    let output = run_tx(&mut db, &vault, get_calldata(selector, vec![]), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(Address::abi_decode(&output, true).unwrap(), CAROL);
+
+    // The value moved from the deploying contract to the vault
+    assert_eq!(db.accounts[&vault].info.balance, value);
+    assert_eq!(db.accounts[&testbed].info.balance, U256::ZERO);
+}
+
+sol! {
+    event Note(string indexed tag, address indexed author, string message);
+
+    struct Permit {
+        address owner;
+        address spender;
+        uint256 value;
+        uint256 nonce;
+        uint256 deadline;
+    }
+}
+
+#[test]
+fn test_eip712_permit_digest() {
+    let (mut db, testbed) = testbed_setup();
+
+    let domain = eip712_domain! {
+        name: "Testbed",
+        version: "1",
+        chain_id: 1,
+        verifying_contract: testbed,
+    };
+    let permit = Permit {
+        owner: ALICE,
+        spender: BOB,
+        value: U256::from(1e18),
+        nonce: U256::from(3),
+        deadline: U256::MAX,
+    };
+
+    let selector = get_selector_from_sig("domain_separator()");
+    let output = run_tx(&mut db, &testbed, selector.to_vec(), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(B256::abi_decode(&output, true).unwrap(), domain.separator());
+
+    let selector = get_selector_from_sig("permit_digest(address,address,uint256,uint256,uint256)");
+    let args = (
+        permit.owner,
+        permit.spender,
+        permit.value,
+        permit.nonce,
+        permit.deadline,
+    );
+    let output = run_tx(
+        &mut db,
+        &testbed,
+        get_calldata(selector, args.abi_encode()),
+        &ALICE,
+    )
+    .expect("Error executing tx")
+    .output;
+    assert_eq!(
+        B256::abi_decode(&output, true).unwrap(),
+        permit.eip712_signing_hash(&domain)
+    );
+}
+
+#[test]
+fn test_send_value_through_interface() {
+    let (mut db, testbed) = testbed_setup();
+    let vault = deploy_contract(&mut db, get_bytecode("vault"), Some(ALICE.abi_encode())).unwrap();
+    let rejecting = deploy_contract(&mut db, get_bytecode("testbed"), None).unwrap();
+
+    let selector = get_selector_from_sig("pay(address,uint256,uint64)");
+    let amount = U256::from(1e15);
+    let pay = |db: &mut InMemoryDB, target: Address, gas: u64| {
+        let calldata = get_calldata(selector, (target, amount, gas).abi_encode());
+        let tx = TxSpec::call(ALICE, testbed, calldata).with_value(amount);
+        let output = send_tx(db, &tx, &ExecEnv::default())
+            .expect("Error executing tx")
+            .output;
+        <(bool, Bytes)>::abi_decode(&output, true).unwrap()
+    };
+
+    // The fallback of the vault receives the value
+    assert_eq!(pay(&mut db, vault, 0), (true, Bytes::new()));
+    let selector_received = get_selector_from_sig("received()");
+    let output = run_tx(&mut db, &vault, selector_received.to_vec(), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), amount);
+    assert_eq!(db.accounts[&vault].info.balance, amount);
+
+    // The gas cap applies, so the vault runs out of gas without reverting the sender
+    assert_eq!(pay(&mut db, vault, 1), (false, Bytes::new()));
+    assert_eq!(db.accounts[&vault].info.balance, amount);
+
+    // The fallback of the testbed isn't payable, and its revert data is returned
+    let non_payable = Revert::from("non-payable function").abi_encode();
+    assert_eq!(
+        pay(&mut db, rejecting, 0),
+        (false, Bytes::from(non_payable))
+    );
+    assert_eq!(db.accounts[&rejecting].info.balance, U256::ZERO);
+
+    // EOAs simply receive the value
+    assert_eq!(pay(&mut db, CAROL, 0), (true, Bytes::new()));
+    assert_eq!(db.accounts[&CAROL].info.balance, amount);
+
+    // The value of failed transfers stays with the sender
+    assert_eq!(db.accounts[&testbed].info.balance, amount * U256::from(2));
+}
+
+#[test]
+fn test_send_value_above_64_bits() {
+    let (mut db, testbed) = testbed_setup();
+    let vault = deploy_contract(&mut db, get_bytecode("vault"), Some(ALICE.abi_encode())).unwrap();
+
+    // 100 ETH, which doesn't fit in a u64 of wei
+    let amount = U256::from(10).pow(U256::from(20));
+    assert!(amount > U256::from(u64::MAX));
+    db.insert_account_info(ALICE, AccountInfo::from_balance(amount * U256::from(2)));
+
+    let selector = get_selector_from_sig("pay(address,uint256,uint64)");
+    for target in [vault, CAROL] {
+        let calldata = get_calldata(selector, (target, amount, 0u64).abi_encode());
+        let tx = TxSpec::call(ALICE, testbed, calldata).with_value(amount);
+        let output = send_tx(&mut db, &tx, &ExecEnv::default())
+            .expect("Error executing tx")
+            .output;
+        assert_eq!(
+            <(bool, Bytes)>::abi_decode(&output, true).unwrap(),
+            (true, Bytes::new())
+        );
+
+        // The whole value is forwarded, rather than its low 64 bits
+        assert_eq!(db.accounts[&target].info.balance, amount);
+    }
+    assert_eq!(db.accounts[&testbed].info.balance, U256::ZERO);
+}
+
+#[test]
+fn test_indexed_dynamic_event() {
+    let (mut db, testbed) = testbed_setup();
+
+    let selector = get_selector_from_sig("note(string,string)");
+    let args = ("audit".to_string(), "all good".to_string()).abi_encode();
+    let result = run_tx(&mut db, &testbed, get_calldata(selector, args), &ALICE)
+        .expect("Error executing tx");
+
+    // Indexed strings are logged as the hash of their contents
+    let notes = decode_events::<Note>(&result.logs);
+    assert_eq!(notes.len(), 1);
+    assert_eq!(notes[0].tag, keccak256("audit"));
+    assert_eq!(notes[0].author, ALICE);
+    assert_eq!(notes[0].message, "all good");
+}
+
+#[test]
+fn test_log4_event() {
+    let (mut db, testbed) = testbed_setup();
+
+    let selector = get_selector_from_sig("route(address,uint256,uint256)");
+    let args = (BOB, U256::from(7), U256::from(42)).abi_encode();
+    let result = run_tx(&mut db, &testbed, get_calldata(selector, args), &ALICE)
+        .expect("Error executing tx");
+
+    // The signature and the 3 indexed fields are all kept as topics
+    assert_eq!(result.logs.len(), 1);
+    let log = &result.logs[0];
+    assert_eq!(
+        log.topics(),
+        [
+            keccak256("Routed(address,address,uint256,uint256)"),
+            ALICE.into_word(),
+            BOB.into_word(),
+            B256::from(U256::from(7)),
+        ]
+    );
+    assert_eq!(log.data.data, U256::from(42).abi_encode());
+}
+
+#[test]
+fn test_self_call() {
+    let (mut db, testbed) = testbed_setup();
+    let balance_of = |db: &mut InMemoryDB| {
+        let calldata = get_calldata(
+            get_selector_from_sig("balance_of(address)"),
+            BOB.abi_encode(),
+        );
+        let output = run_tx(db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        U256::abi_decode(&output, true).unwrap()
+    };
+
+    // The outer frame sees the write of the inner one, which runs in a fresh frame of the same contract
+    let amount = U256::from(7);
+    let selector = get_selector_from_sig("set_balance_via_self(address,uint256)");
+    let calldata = get_calldata(selector, (BOB, amount).abi_encode());
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), amount);
+    assert_eq!(balance_of(&mut db), amount);
+
+    // A reverting inner call only undoes its own writes, not the pending ones of the outer frame
+    let amount = U256::from(42);
+    let selector = get_selector_from_sig("set_balance_then_revert_via_self(address,uint256)");
+    let calldata = get_calldata(selector, (BOB, amount).abi_encode());
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(
+        <(bool, U256)>::abi_decode(&output, true).unwrap(),
+        (false, amount)
+    );
+    assert_eq!(balance_of(&mut db), amount);
+}
+
+#[test]
+fn test_reentrant_storage_visibility() {
+    let (mut db, outer) = testbed_setup();
+    let via = deploy_contract(&mut db, get_bytecode("testbed"), None).unwrap();
+
+    // outer -> via -> outer: the reentrant frame reads the pending write of the first one, and the
+    // frame that reverts only rolls back its own write
+    let amount = U256::from(42);
+    let selector = get_selector_from_sig("reentrant_balance(address,address,uint256)");
+    let calldata = get_calldata(selector, (via, BOB, amount).abi_encode());
+    let output = run_tx(&mut db, &outer, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(
+        <(U256, bool, U256)>::abi_decode(&output, true).unwrap(),
+        (amount, false, amount)
+    );
+
+    // Both the write of the outer frame and the one of the intermediate frame are committed
+    let balance_slot = keccak256([BOB.abi_encode(), U256::from(2).abi_encode()].concat()).into();
+    assert_eq!(read_db_slot(&mut db, outer, balance_slot), amount);
+    assert_eq!(
+        read_db_slot(&mut db, via, balance_slot),
+        amount + U256::from(1)
+    );
+}
+
+#[test]
+fn test_calls_to_codeless_addresses() {
+    let (mut db, testbed) = testbed_setup();
+    let empty = Address::repeat_byte(0xee);
+
+    for target in [CAROL, empty] {
+        // Typed calls succeed with no data, which the interface reports as a failed decode
+        let selector = get_selector_from_sig("sub_nonzero_outcome(address,uint256,uint256)");
+        let calldata = get_calldata(
+            selector,
+            (target, U256::from(2), U256::from(1)).abi_encode(),
+        );
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert_eq!(
+            <(u64, U256, String)>::abi_decode(&output, true).unwrap(),
+            (4, U256::ZERO, String::new())
+        );
+
+        let selector = get_selector_from_sig("capped_sum(address,uint64,uint64)");
+        let calldata = get_calldata(selector, (target, 10u64, 100_000u64).abi_encode());
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert_eq!(
+            <(bool, u64)>::abi_decode(&output, true).unwrap(),
+            (false, 0)
+        );
+    }
+
+    // Value sent to an empty account creates it
+    let amount = U256::from(1e15);
+    let selector = get_selector_from_sig("pay(address,uint256,uint64)");
+    let calldata = get_calldata(selector, (empty, amount, 0u64).abi_encode());
+    let tx = TxSpec::call(ALICE, testbed, calldata).with_value(amount);
+    let output = send_tx(&mut db, &tx, &ExecEnv::default())
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(
+        <(bool, Bytes)>::abi_decode(&output, true).unwrap(),
+        (true, Bytes::new())
+    );
+    assert_eq!(db.accounts[&empty].info.balance, amount);
+}
+
+#[test]
+fn test_send_value_above_balance() {
+    let (mut db, testbed) = testbed_setup();
+    let vault = deploy_contract(&mut db, get_bytecode("vault"), Some(ALICE.abi_encode())).unwrap();
+    db.accounts.get_mut(&testbed).unwrap().info.balance = U256::from(1);
+
+    // With 1 wei, sending 2 wei fails without running the callee, and the sender carries on
+    for target in [vault, CAROL] {
+        let selector = get_selector_from_sig("send_and_record(address,uint256)");
+        let calldata = get_calldata(selector, (target, U256::from(2)).abi_encode());
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+        assert!(!bool::abi_decode(&output, true).unwrap());
+
+        // The write that follows the failed transfer is committed
+        let balance_slot: U256 =
+            keccak256([target.abi_encode(), U256::from(2).abi_encode()].concat()).into();
+        assert_eq!(read_db_slot(&mut db, testbed, balance_slot), U256::from(2));
+    }
+
+    assert_eq!(db.accounts[&testbed].info.balance, U256::from(1));
+    assert_eq!(db.accounts[&vault].info.balance, U256::ZERO);
+    assert!(db
+        .accounts
+        .get(&CAROL)
+        .is_none_or(|account| account.info.balance.is_zero()));
+
+    let selector_received = get_selector_from_sig("received()");
+    let output = run_tx(&mut db, &vault, selector_received.to_vec(), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), U256::ZERO);
+}
+
+#[test]
+fn test_stack_overflow_halts() {
+    let (mut db, testbed) = testbed_setup();
+    let selector = get_selector_from_sig("store_recursive_sum(address,uint64)");
+    let balance_slot: U256 =
+        keccak256([BOB.abi_encode(), U256::from(2).abi_encode()].concat()).into();
+
+    // Fits in the stack
+    let calldata = get_calldata(selector, (BOB, 16_u64).abi_encode());
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(U256::abi_decode(&output, true).unwrap(), U256::from(136));
+    assert_eq!(
+        read_db_slot(&mut db, testbed, balance_slot),
+        U256::from(136)
+    );
+
+    // Runs past the 2MB stack, which halts the frame instead of storing a corrupted sum
+    let calldata = get_calldata(selector, (BOB, 100_000_u64).abi_encode());
+    let err = run_tx(&mut db, &testbed, calldata, &ALICE).expect_err("Tx succeeded");
+    assert!(
+        matches!(
+            err,
+            Error::UnexpectedExecResult(ExecutionResult::Halt {
+                reason: HaltReason::StackOverflow,
+                ..
+            })
+        ),
+        "Expected StackOverflow, got: {err}"
+    );
+    assert_eq!(
+        read_db_slot(&mut db, testbed, balance_slot),
+        U256::from(136)
+    );
+}
+
+#[test]
+fn test_streamed_calldata() {
+    let (mut db, testbed) = testbed_setup();
+    let payload: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+    let expected = payload.chunks(1024).fold(B256::ZERO, |hash, chunk| {
+        keccak256([hash.as_slice(), chunk].concat())
+    });
+
+    let mut heap_usage = Vec::new();
+    for sig in [
+        "hash_windows_owned(bytes,uint64)",
+        "hash_windows_streamed(bytes,uint64)",
+    ] {
+        let args = (Bytes::from(payload.clone()), 1024_u64).abi_encode();
+        let calldata = get_calldata(get_selector_from_sig(sig), args);
+        let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+            .expect("Error executing tx")
+            .output;
+
+        let (hash, usage) = <(B256, u64)>::abi_decode(&output, true).unwrap();
+        assert_eq!(hash, expected, "{}", sig);
+        heap_usage.push(usage);
+    }
+
+    // The decoded payload takes up the heap, while the streamed one only needs the window buffer
+    assert!(heap_usage[0] >= 64 * 1024, "{:?}", heap_usage);
+    assert!(heap_usage[1] < 2 * 1024, "{:?}", heap_usage);
+
+    // A length running past the end of the calldata fails to decode
+    let mut args = (Bytes::from(payload[..64].to_vec()), 1024_u64).abi_encode();
+    args[96..128].copy_from_slice(&U256::from(65).to_be_bytes::<32>());
+    let calldata = get_calldata(
+        get_selector_from_sig("hash_windows_streamed(bytes,uint64)"),
+        args,
+    );
+    let err = run_tx(&mut db, &testbed, calldata, &ALICE).expect_err("Tx succeeded");
+    let Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) = err else {
+        panic!("Expected revert, got: {err}");
+    };
+    assert_eq!(
+        Revert::abi_decode(&output, true).unwrap().reason,
+        "failed to decode calldata"
+    );
+}
+
+/// Zeroes the instruction at the entry point of the ELF in `runtime_code`, which is illegal.
+/// Returns the corrupted code and the entry point.
+fn corrupt_entry_point(runtime_code: &[u8]) -> (Bytes, u64) {
+    let (header, _) = Header::parse(runtime_code).unwrap().unwrap();
+    let elf = &runtime_code[header.size()..];
+    let read_u64 = |at: usize| u64::from_le_bytes(elf[at..at + 8].try_into().unwrap());
+    let read_u16 = |at: usize| u16::from_le_bytes(elf[at..at + 2].try_into().unwrap()) as usize;
+
+    // Find the file offset of the entry point in the loadable segment that maps it
+    let (entry, phoff) = (read_u64(24), read_u64(32) as usize);
+    let (phentsize, phnum) = (read_u16(54), read_u16(56));
+    let offset = (0..phnum)
+        .map(|i| phoff + i * phentsize)
+        .find_map(|ph| {
+            let p_type = u32::from_le_bytes(elf[ph..ph + 4].try_into().unwrap());
+            let (p_offset, p_vaddr, p_filesz) =
+                (read_u64(ph + 8), read_u64(ph + 16), read_u64(ph + 32));
+            (p_type == 1 && (p_vaddr..p_vaddr + p_filesz).contains(&entry))
+                .then(|| p_offset + entry - p_vaddr)
+        })
+        .expect("Entry point isn't loaded");
+
+    let mut corrupted = runtime_code.to_vec();
+    corrupted[header.size() + offset as usize..][..4].fill(0);
+    (corrupted.into(), entry)
+}
+
+#[test]
+fn test_trap_is_not_a_revert() {
+    let (mut db, testbed) = testbed_setup();
+    let runtime_code = db.accounts[&testbed]
+        .info
+        .code
+        .as_ref()
+        .unwrap()
+        .original_bytes();
+    let (corrupted, entry) = corrupt_entry_point(&runtime_code);
+    let broken = Address::repeat_byte(0x55);
+    add_contract_to_db(&mut db, broken, corrupted);
+
+    // The illegal instruction halts the frame and consumes all its gas
+    let calldata = get_calldata(get_selector_from_sig("own_selector()"), vec![]);
+    let err = run_tx(&mut db, &broken, calldata, &ALICE).expect_err("Tx succeeded");
+    let Error::UnexpectedExecResult(ExecutionResult::Halt { reason, gas_used }) = err else {
+        panic!("Expected halt, got: {err}");
+    };
+    assert_eq!(reason, HaltReason::InvalidFEOpcode);
+    assert_eq!(gas_used, DEFAULT_GAS_LIMIT);
+
+    // And is reported as a trap at the corrupted instruction
+    let failures = last_tx_failures();
+    let [FrameFailure::Trap(trap)] = failures.as_slice() else {
+        panic!("Expected a single trap, got: {failures:?}");
+    };
+    assert_eq!(trap.address, broken);
+    assert!(
+        (entry..=entry + 4).contains(&trap.pc),
+        "Trap at {:#x}, entry at {:#x}",
+        trap.pc,
+        entry
+    );
+    assert!(matches!(trap.exception, Exception::IllegalInstruction(_)));
+}
+
+#[test]
+fn test_unknown_syscall_halts_frame() {
+    let (mut db, testbed) = testbed_setup();
+    let selector = get_selector_from_sig("raw_ecall(uint64)");
+
+    // Neither an unused id, nor one that would alias a syscall once truncated, is run
+    for id in [0xee_u64, 0x100 + u8::from(Syscall::Keccak256) as u64] {
+        let calldata = get_calldata(selector, id.abi_encode());
+        let err = run_tx(&mut db, &testbed, calldata, &ALICE).expect_err("Tx succeeded");
+        let Error::UnexpectedExecResult(ExecutionResult::Halt { reason, gas_used }) = err else {
+            panic!("Expected halt, got: {err}");
+        };
+        assert_eq!(reason, HaltReason::OpcodeNotFound);
+        assert_eq!(gas_used, DEFAULT_GAS_LIMIT);
+
+        let failures = last_tx_failures();
+        let [FrameFailure::UnknownSyscall {
+            address,
+            id: failed_id,
+            pc,
+        }] = failures.as_slice()
+        else {
+            panic!("Expected an unknown syscall, got: {failures:?}");
+        };
+        assert_eq!((*address, *failed_id), (testbed, id));
+        assert_ne!(*pc, 0);
+    }
+
+    // When nested, the caller sees an ordinary failed call with no data
+    let inner_calldata = get_calldata(selector, 0xee_u64.abi_encode());
+    let calldata = get_calldata(
+        get_selector_from_sig("try_forward(address,bytes)"),
+        (testbed, Bytes::from(inner_calldata)).abi_encode(),
+    );
+    let output = run_tx(&mut db, &testbed, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(
+        <(bool, Bytes)>::abi_decode(&output, true).unwrap(),
+        (false, Bytes::new())
+    );
+    assert!(matches!(
+        last_tx_failures().as_slice(),
+        [FrameFailure::UnknownSyscall { id: 0xee, .. }]
+    ));
+}
+
+#[test]
+fn test_program_cache_follows_code() {
+    let (mut db, testbed) = testbed_setup();
+    let testbed_code = db.accounts[&testbed]
+        .info
+        .code
+        .as_ref()
+        .unwrap()
+        .original_bytes();
+    let own_selector = get_calldata(get_selector_from_sig("own_selector()"), vec![]);
+    let output = run_tx(&mut db, &testbed, own_selector.clone(), &ALICE)
+        .expect("Error executing tx")
+        .output;
+    assert!(programs::contains(&keccak256(&testbed_code)));
+
+    // Re-deploys ERC20 at the same address, in a fresh db: its frames run the ERC20 program rather
+    // than the one loaded for the testbed
+    let mut erc20_db = InMemoryDB::default();
+    add_balance_to_db(&mut erc20_db, ALICE, 1e18 as u64);
+    let erc20 = deploy_contract(
+        &mut erc20_db,
+        get_bytecode("erc20"),
+        Some(ALICE.abi_encode()),
+    )
+    .unwrap();
+    let erc20_code = erc20_db.accounts[&erc20]
+        .info
+        .code
+        .as_ref()
+        .unwrap()
+        .original_bytes();
+    let mut fresh_db = InMemoryDB::default();
+    add_balance_to_db(&mut fresh_db, ALICE, 1e18 as u64);
+    add_contract_to_db(&mut fresh_db, testbed, erc20_code.clone());
+
+    let decimals = get_calldata(get_selector_from_sig("decimals()"), vec![]);
+    let result = run_tx(&mut fresh_db, &testbed, decimals, &ALICE).expect("Error executing tx");
+    assert_eq!(U256::abi_decode(&result.output, true).unwrap(), U256::ZERO);
+    assert!(programs::contains(&keccak256(&erc20_code)));
+    run_tx(&mut fresh_db, &testbed, own_selector.clone(), &ALICE)
+        .expect_err("ERC20 doesn't have `own_selector`");
+
+    // The original code keeps running its own program
+    let result = run_tx(&mut db, &testbed, own_selector, &ALICE).expect("Error executing tx");
+    assert_eq!(result.output, output);
+}
@@ -0,0 +1,72 @@
+use alloy_primitives::keccak256;
+use r55::{
+    get_bytecode,
+    lock::{check_fresh, source_hash, LockEntry, Lockfile, SKIP_ENV},
+};
+use std::{env, fs, path::PathBuf};
+
+// Creates a fake contract crate under a temp dir
+fn setup(name: &str) -> (PathBuf, LockEntry) {
+    let root = env::temp_dir().join(format!("r55-lock-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("contract/src")).unwrap();
+    fs::create_dir_all(root.join("contract/target")).unwrap();
+    fs::write(root.join("contract/Cargo.toml"), "[package]").unwrap();
+    fs::write(root.join("contract/src/lib.rs"), "fn main() {}").unwrap();
+
+    let sources = vec!["contract".to_string()];
+    let entry = LockEntry {
+        bytecode: keccak256([0xff, 0x01]),
+        source_hash: source_hash(&root, &sources).unwrap(),
+        sources,
+    };
+
+    (root, entry)
+}
+
+#[test]
+fn test_compiled_contracts_are_fresh() {
+    if env::var(SKIP_ENV).is_ok() {
+        return;
+    }
+
+    let lockfile = Lockfile::load().expect("No r55.lock, rerun r55-compile");
+    for name in lockfile.contracts.keys() {
+        let name = name.replace('-', "_");
+        check_fresh(&name, &get_bytecode(&name));
+    }
+}
+
+#[test]
+fn test_stale_sources() {
+    let (root, entry) = setup("sources");
+    assert_eq!(entry.stale_reason(&root, &[0xff, 0x01]), None);
+
+    // Build outputs and other files are ignored
+    fs::write(root.join("contract/target/out.rs"), "").unwrap();
+    fs::write(root.join("contract/README.md"), "").unwrap();
+    assert_eq!(entry.stale_reason(&root, &[0xff, 0x01]), None);
+
+    // Edits and renames of the sources are detected
+    fs::write(root.join("contract/src/lib.rs"), "fn main() { }").unwrap();
+    assert!(entry.stale_reason(&root, &[0xff, 0x01]).is_some());
+
+    fs::write(root.join("contract/src/lib.rs"), "fn main() {}").unwrap();
+    fs::rename(
+        root.join("contract/src/lib.rs"),
+        root.join("contract/src/main.rs"),
+    )
+    .unwrap();
+    assert!(entry.stale_reason(&root, &[0xff, 0x01]).is_some());
+
+    fs::remove_dir_all(root).unwrap();
+}
+
+#[test]
+fn test_stale_bytecode() {
+    let (root, entry) = setup("bytecode");
+    let reason = entry.stale_reason(&root, &[0xff, 0x02]).unwrap();
+    assert!(reason.contains("bytecode differs"));
+
+    fs::remove_dir_all(root).unwrap();
+}
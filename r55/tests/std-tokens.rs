@@ -0,0 +1,176 @@
+use alloy_primitives::{Address, U256};
+use alloy_sol_types::{sol, SolCall, SolValue};
+use r55::{
+    error::{Error, TxResult},
+    exec::{deploy_contract, run_tx},
+    get_bytecode,
+    test_utils::{get_calldata, get_selector_from_sig, initialize_logger, ALICE, BOB, CAROL},
+};
+use revm::{primitives::ExecutionResult, InMemoryDB};
+
+// Implemented by both tokens, through a `#[contract_impl]` block
+sol! {
+    interface IERC20Std {
+        function totalSupply() external view returns (uint256);
+        function balanceOf(address owner) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function transfer(address to, uint256 amount) external returns (bool);
+        function approve(address spender, uint256 amount) external returns (bool);
+        function transferFrom(address from, address to, uint256 amount) external returns (bool);
+    }
+}
+
+struct StdTokensSetup {
+    db: InMemoryDB,
+    fixed: Address,
+    mintable: Address,
+}
+
+// Both tokens with a supply of 1000, owned by ALICE
+fn std_tokens_setup() -> StdTokensSetup {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+
+    let supply = U256::from(1000);
+    let fixed = deploy_contract(
+        &mut db,
+        get_bytecode("std_tokens_fixed"),
+        Some((ALICE, supply).abi_encode()),
+    )
+    .unwrap();
+    let mintable = deploy_contract(
+        &mut db,
+        get_bytecode("std_tokens_mintable"),
+        Some(ALICE.abi_encode()),
+    )
+    .unwrap();
+
+    let calldata = get_calldata(
+        get_selector_from_sig("mint(address,uint256)"),
+        (ALICE, supply).abi_encode(),
+    );
+    let mint_result = run_tx(&mut db, &mintable, calldata, &ALICE).expect("Error executing tx");
+    assert!(mint_result.status, "Mint transaction failed");
+
+    StdTokensSetup {
+        db,
+        fixed,
+        mintable,
+    }
+}
+
+// Whether the tx reverted, rather than failing in any other way
+fn reverted(result: Result<TxResult, Error>) -> bool {
+    match result {
+        Ok(_) => false,
+        Err(Error::UnexpectedExecResult(ExecutionResult::Revert { .. })) => true,
+        Err(err) => panic!("Error executing tx: {err}"),
+    }
+}
+
+// Returns the decoded output of the call, or `None` if it reverted
+fn call<C: SolCall>(
+    db: &mut InMemoryDB,
+    token: Address,
+    caller: Address,
+    call: C,
+) -> Option<C::Return> {
+    match run_tx(db, &token, call.abi_encode(), &caller) {
+        Ok(result) => {
+            Some(C::abi_decode_returns(&result.output, true).expect("Unable to decode output"))
+        }
+        Err(Error::UnexpectedExecResult(ExecutionResult::Revert { .. })) => None,
+        Err(err) => panic!("Error executing tx: {err}"),
+    }
+}
+
+fn balance_of(db: &mut InMemoryDB, token: Address, owner: Address) -> U256 {
+    call(db, token, owner, IERC20Std::balanceOfCall { owner })
+        .unwrap()
+        ._0
+}
+
+#[test]
+fn test_std_tokens_interface() {
+    let StdTokensSetup {
+        mut db,
+        fixed,
+        mintable,
+    } = std_tokens_setup();
+
+    for token in [fixed, mintable] {
+        let supply = call(&mut db, token, ALICE, IERC20Std::totalSupplyCall {})
+            .unwrap()
+            ._0;
+        assert_eq!(supply, U256::from(1000));
+
+        let transfer = IERC20Std::transferCall {
+            to: BOB,
+            amount: U256::from(100),
+        };
+        assert!(call(&mut db, token, ALICE, transfer).unwrap()._0);
+        assert_eq!(balance_of(&mut db, token, ALICE), U256::from(900));
+        assert_eq!(balance_of(&mut db, token, BOB), U256::from(100));
+
+        // Spend an allowance of BOB on behalf of ALICE
+        let approve = IERC20Std::approveCall {
+            spender: BOB,
+            amount: U256::from(50),
+        };
+        assert!(call(&mut db, token, ALICE, approve).unwrap()._0);
+        let transfer_from = IERC20Std::transferFromCall {
+            from: ALICE,
+            to: CAROL,
+            amount: U256::from(50),
+        };
+        assert!(call(&mut db, token, BOB, transfer_from.clone()).unwrap()._0);
+        assert_eq!(balance_of(&mut db, token, CAROL), U256::from(50));
+
+        let allowance = IERC20Std::allowanceCall {
+            owner: ALICE,
+            spender: BOB,
+        };
+        assert_eq!(call(&mut db, token, BOB, allowance).unwrap()._0, U256::ZERO);
+
+        // Reverts of the trait methods are forwarded as usual
+        assert!(call(&mut db, token, BOB, transfer_from).is_none());
+        let transfer = IERC20Std::transferCall {
+            to: ALICE,
+            amount: U256::from(101),
+        };
+        assert!(call(&mut db, token, BOB, transfer).is_none());
+    }
+}
+
+#[test]
+fn test_std_tokens_inherent_methods() {
+    let StdTokensSetup {
+        mut db,
+        fixed,
+        mintable,
+    } = std_tokens_setup();
+
+    // Inherent methods are still dispatched along with the trait ones
+    let calldata = get_calldata(
+        get_selector_from_sig("mint(address,uint256)"),
+        (BOB, U256::from(1)).abi_encode(),
+    );
+    let mint_result = run_tx(&mut db, &mintable, calldata.clone(), &BOB);
+    assert!(reverted(mint_result), "Only the owner can mint");
+
+    // The fixed token doesn't have a `mint` method, nor a fallback
+    let mint_result = run_tx(&mut db, &fixed, calldata, &ALICE);
+    assert!(reverted(mint_result), "Unknown selectors must revert");
+
+    // Balance on the fixed token, read by the mintable one through its `IERC20Std` interface
+    let calldata = get_calldata(
+        get_selector_from_sig("balance_on(address,address)"),
+        (fixed, ALICE).abi_encode(),
+    );
+    let result = run_tx(&mut db, &mintable, calldata, &BOB).expect("Error executing tx");
+    assert!(result.status, "Cross-contract call failed");
+    assert_eq!(
+        U256::abi_decode(&result.output, true).unwrap(),
+        U256::from(1000)
+    );
+}
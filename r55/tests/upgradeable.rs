@@ -0,0 +1,225 @@
+use alloy_primitives::{keccak256, Address, B256, U256};
+use alloy_sol_types::{Revert, SolError, SolValue};
+use r55::{
+    error::Error,
+    exec::{deploy_contract, run_tx},
+    get_bytecode,
+    layout::StorageLayout,
+    test_utils::{
+        add_balance_to_db, get_calldata, get_selector_from_sig, initialize_logger, read_db_slot,
+        ALICE, BOB, CAROL,
+    },
+};
+use revm::{primitives::ExecutionResult, InMemoryDB};
+
+struct ProxySetup {
+    db: InMemoryDB,
+    proxy: Address,
+}
+
+// Deploys `CounterV1` behind a proxy administered by CAROL
+fn proxy_setup() -> ProxySetup {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+    for user in [ALICE, BOB, CAROL] {
+        add_balance_to_db(&mut db, user, 1e18 as u64);
+    }
+
+    let v1 = deploy_contract(&mut db, get_bytecode("upgradeable_v1"), None).unwrap();
+    let proxy = deploy_contract(
+        &mut db,
+        get_bytecode("upgradeable_proxy"),
+        Some((v1, CAROL).abi_encode()),
+    )
+    .unwrap();
+
+    ProxySetup { db, proxy }
+}
+
+fn call_u256(db: &mut InMemoryDB, target: Address, calldata: Vec<u8>) -> U256 {
+    let output = run_tx(db, &target, calldata, &ALICE)
+        .expect("Error executing tx")
+        .output;
+    U256::abi_decode(&output, true).unwrap()
+}
+
+// Reason of an `Error(string)` revert
+fn revert_reason(err: Error) -> String {
+    let Error::UnexpectedExecResult(ExecutionResult::Revert { output, .. }) = err else {
+        panic!("Expected revert, got: {err}");
+    };
+    Revert::abi_decode(&output, true).unwrap().reason
+}
+
+fn eip1967_slot(label: &str) -> U256 {
+    U256::from_be_bytes(keccak256(label).0) - U256::from(1)
+}
+
+#[test]
+fn test_proxy_upgrade() {
+    let ProxySetup { mut db, proxy } = proxy_setup();
+
+    let selector_increment = get_selector_from_sig("increment()");
+    let selector_count = get_selector_from_sig("count()");
+    let selector_version = get_selector_from_sig("version()");
+    let selector_upgrade = get_selector_from_sig("upgrade_to(address)");
+
+    // Calls are delegated to V1, which writes to the storage of the proxy
+    for _ in 0..2 {
+        call_u256(&mut db, proxy, selector_increment.to_vec());
+    }
+    assert_eq!(
+        call_u256(&mut db, proxy, selector_count.to_vec()),
+        U256::from(2)
+    );
+    assert_eq!(
+        call_u256(&mut db, proxy, selector_version.to_vec()),
+        U256::from(1)
+    );
+    assert_eq!(read_db_slot(&mut db, proxy, U256::ZERO), U256::from(2));
+
+    // Only the admin can upgrade, and only to a contract: the upgrades of anyone else are
+    // delegated to V1, which doesn't have such a method
+    let v2 = deploy_contract(&mut db, get_bytecode("upgradeable_v2"), None).unwrap();
+    run_tx(
+        &mut db,
+        &proxy,
+        get_calldata(selector_upgrade, v2.abi_encode()),
+        &BOB,
+    )
+    .expect_err("Non-admin upgraded the proxy");
+    assert_eq!(
+        call_u256(&mut db, proxy, selector_version.to_vec()),
+        U256::from(1)
+    );
+
+    let err = run_tx(
+        &mut db,
+        &proxy,
+        get_calldata(selector_upgrade, BOB.abi_encode()),
+        &CAROL,
+    )
+    .expect_err("Upgraded to an account without code");
+    assert_eq!(revert_reason(err), "Implementation without code");
+
+    let result = run_tx(
+        &mut db,
+        &proxy,
+        get_calldata(selector_upgrade, v2.abi_encode()),
+        &CAROL,
+    )
+    .expect("Error executing tx");
+    assert!(result.status, "Upgrade transaction failed");
+    assert_eq!(result.logs.len(), 1);
+    assert_eq!(
+        result.logs[0].topics(),
+        [keccak256("Upgraded(address)"), v2.into_word()]
+    );
+
+    // The implementation is kept in its EIP-1967 slot
+    let implementation = read_db_slot(&mut db, proxy, eip1967_slot("eip1967.proxy.implementation"));
+    assert_eq!(B256::from(implementation), v2.into_word());
+    let selector_implementation = get_selector_from_sig("implementation()");
+    let output = run_tx(&mut db, &proxy, selector_implementation.to_vec(), &CAROL)
+        .expect("Error executing tx")
+        .output;
+    assert_eq!(Address::abi_decode(&output, true).unwrap(), v2);
+    let output = run_tx(
+        &mut db,
+        &proxy,
+        get_selector_from_sig("admin()").to_vec(),
+        &CAROL,
+    )
+    .expect("Error executing tx")
+    .output;
+    assert_eq!(Address::abi_decode(&output, true).unwrap(), CAROL);
+
+    // The methods of the proxy don't shadow the ones of the implementation: only the admin calls
+    // them, and it can't call the implementation
+    run_tx(&mut db, &proxy, selector_implementation.to_vec(), &BOB)
+        .expect_err("V2 doesn't have `implementation`");
+    let err = run_tx(&mut db, &proxy, selector_count.to_vec(), &CAROL)
+        .expect_err("The admin called the implementation");
+    assert_eq!(revert_reason(err), "Admin can't call the implementation");
+
+    // The count survives the upgrade, and the new methods work
+    assert_eq!(
+        call_u256(&mut db, proxy, selector_version.to_vec()),
+        U256::from(2)
+    );
+    assert_eq!(
+        call_u256(&mut db, proxy, selector_count.to_vec()),
+        U256::from(2)
+    );
+    assert_eq!(
+        call_u256(&mut db, proxy, selector_increment.to_vec()),
+        U256::from(3)
+    );
+
+    let selector_set_step = get_selector_from_sig("set_step(uint256)");
+    let result = run_tx(
+        &mut db,
+        &proxy,
+        get_calldata(selector_set_step, U256::from(5).abi_encode()),
+        &BOB,
+    )
+    .expect("Error executing tx");
+    assert!(result.status, "SetStep transaction failed");
+    assert_eq!(
+        call_u256(&mut db, proxy, get_selector_from_sig("step()").to_vec()),
+        U256::from(5)
+    );
+    assert_eq!(
+        call_u256(&mut db, proxy, selector_increment.to_vec()),
+        U256::from(8)
+    );
+
+    // The implementation itself was never written to
+    assert_eq!(call_u256(&mut db, v2, selector_count.to_vec()), U256::ZERO);
+}
+
+#[test]
+fn test_delegating_interface() {
+    initialize_logger();
+    let mut db = InMemoryDB::default();
+
+    let v1 = deploy_contract(&mut db, get_bytecode("upgradeable_v1"), None).unwrap();
+    let delegator = deploy_contract(
+        &mut db,
+        get_bytecode("upgradeable_delegator"),
+        Some(v1.abi_encode()),
+    )
+    .unwrap();
+
+    // `ICounter::increment` is delegated to V1, whose code writes to the storage of the delegator
+    let selector_increment = get_selector_from_sig("increment()");
+    for expected in 1..=2 {
+        assert_eq!(
+            call_u256(&mut db, delegator, selector_increment.to_vec()),
+            U256::from(expected)
+        );
+    }
+    assert_eq!(read_db_slot(&mut db, delegator, U256::ZERO), U256::from(2));
+    assert_eq!(
+        call_u256(
+            &mut db,
+            delegator,
+            get_selector_from_sig("count()").to_vec()
+        ),
+        U256::from(2)
+    );
+
+    // The implementation itself was never written to
+    assert_eq!(read_db_slot(&mut db, v1, U256::ZERO), U256::ZERO);
+}
+
+#[test]
+fn test_upgrade_layout_compatibility() {
+    let v2 = StorageLayout::load("upgradeable_v2").unwrap();
+    let proxy = StorageLayout::load("upgradeable_proxy").unwrap();
+
+    // Upgrading V1 to V2 is checked by `r55-compile check-upgrade`. The proxy slots don't collide with the ones of the implementations
+    for field in proxy.fields {
+        assert!(v2.fields.iter().all(|f| f.slot != field.slot));
+    }
+}